@@ -0,0 +1,170 @@
+//! Bots that fill seats at a poker server.
+//!
+//! Each bot connects as its own user, joins the waitlist, and lets one of
+//! the library's baseline agents play until the server closes the
+//! connection. Bots are handy for testing servers and for giving a couple
+//! of humans enough players for a game.
+
+use anyhow::{bail, Error};
+use clap::{builder::PossibleValue, value_parser, Arg, ArgAction, Command};
+use private_poker::{
+    agent::{self, BASELINE_AGENTS},
+    entities::GameView,
+    messages::{TableId, UserState},
+    net::client::ConnectOptions,
+    Client,
+};
+use std::thread;
+
+/// Short names for the baseline agents so they're quicker to type.
+const STRATEGY_ALIASES: [(&str, &str); 2] = [
+    ("calling_station", "calling"),
+    ("tight_aggressive", "tight"),
+];
+
+/// Connect a bot to the server and waitlist it at the table.
+fn connect_bot(
+    username: &str,
+    addr: &str,
+    options: &ConnectOptions,
+    table_id: Option<TableId>,
+) -> Result<(Client, GameView), Error> {
+    let (mut client, view) = Client::connect_with_options(username, addr, options)?;
+    if let Some(table_id) = table_id {
+        client.join_table(table_id)?;
+    }
+    client.change_state(UserState::Play)?;
+    Ok((client, view))
+}
+
+fn main() -> Result<(), Error> {
+    let addr = Arg::new("connect")
+        .help("server socket connection address, or unix:PATH for a Unix socket")
+        .default_value("127.0.0.1:6969")
+        .long("connect")
+        .alias("addr")
+        .value_name("IP:PORT");
+
+    let count = Arg::new("count")
+        .help("number of bots to connect")
+        .default_value("1")
+        .long("count")
+        .value_name("COUNT")
+        .value_parser(value_parser!(u64).range(1..));
+
+    let name = Arg::new("name")
+        .help("bots are named this followed by a number, like bot1 and bot2")
+        .default_value("bot")
+        .long("name")
+        .value_name("NAME");
+
+    let password = Arg::new("password")
+        .help("password for servers that require one, shared by every bot")
+        .long("password")
+        .value_name("PASSWORD");
+
+    let strategies = BASELINE_AGENTS.map(|name| {
+        let value = PossibleValue::new(name);
+        match STRATEGY_ALIASES.iter().find(|(agent, _)| *agent == name) {
+            Some((_, alias)) => value.alias(alias),
+            None => value,
+        }
+    });
+    let strategy = Arg::new("strategy")
+        .help("baseline agent the bots play with")
+        .default_value("tight_aggressive")
+        .long("strategy")
+        .value_name("STRATEGY")
+        .value_parser(strategies);
+
+    let start = Arg::new("start")
+        .help("start the game once every bot is waitlisted")
+        .long("start")
+        .action(ArgAction::SetTrue);
+
+    let table = Arg::new("table")
+        .help("table to join instead of the one users start at")
+        .long("table")
+        .value_name("TABLE")
+        .value_parser(value_parser!(TableId));
+
+    let matches = Command::new("pp_bot")
+        .about("fill seats at a poker server with bots")
+        .version("0.0.1")
+        .arg(addr)
+        .arg(count)
+        .arg(name)
+        .arg(password)
+        .arg(start)
+        .arg(strategy)
+        .arg(table)
+        .get_matches();
+
+    let addr = matches
+        .get_one::<String>("connect")
+        .expect("server address has a default");
+    let count = *matches
+        .get_one::<u64>("count")
+        .expect("count has a default");
+    let name = matches
+        .get_one::<String>("name")
+        .expect("name has a default");
+    // Aliases are passed along as they were typed.
+    let strategy = matches
+        .get_one::<String>("strategy")
+        .expect("strategy has a default");
+    let strategy = STRATEGY_ALIASES
+        .iter()
+        .find_map(|(agent, alias)| (alias == strategy).then(|| agent.to_string()))
+        .unwrap_or_else(|| strategy.clone());
+    let table_id = matches.get_one::<TableId>("table").copied();
+    let options = ConnectOptions {
+        password: matches.get_one::<String>("password").cloned(),
+        ..Default::default()
+    };
+
+    // Bots are connected one at a time so the game isn't started until
+    // they're all waitlisted, and so bad addresses or passwords fail
+    // before any bot starts playing.
+    let mut clients = Vec::with_capacity(count as usize);
+    for i in 1..=count {
+        clients.push(connect_bot(
+            &format!("{name}{i}"),
+            addr,
+            &options,
+            table_id,
+        )?);
+    }
+    if matches.get_flag("start") {
+        if let Some((client, _)) = clients.last_mut() {
+            client.start_game()?;
+        }
+    }
+    let bots: Vec<_> = clients
+        .into_iter()
+        .map(|(mut client, view)| {
+            let username = client.username.to_string();
+            let mut agent = agent::from_name(&strategy).expect("strategies are validated");
+            let bot = thread::spawn(move || client.play(view, &mut *agent));
+            (username, bot)
+        })
+        .collect();
+    let mut num_failed = 0;
+    for (username, bot) in bots {
+        match bot.join() {
+            Ok(Ok(())) => {}
+            Ok(Err(error)) => {
+                eprintln!("{username}: {error}");
+                num_failed += 1;
+            }
+            Err(_) => {
+                eprintln!("{username}: panicked");
+                num_failed += 1;
+            }
+        }
+    }
+    if num_failed > 0 {
+        bail!("{num_failed} of {count} bot(s) stopped with an error");
+    }
+    Ok(())
+}
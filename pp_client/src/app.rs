@@ -1,90 +1,218 @@
 use anyhow::{bail, Error};
-use chrono::{DateTime, Utc};
-use clap::{Arg, Command};
-use mio::{Events, Interest, Poll, Waker};
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::{value_parser, Arg, Command};
+use mio::Waker;
 use private_poker::{
-    entities::{Action, Card, GameView, Suit, Usd, User, Username},
-    functional,
+    constants::DEFAULT_TURN_TIMEOUT,
+    entities::{Action, ChipDisplay, Dollars, GameView, Usd, Usdf, User, Username},
     messages::UserState,
     net::{
-        messages::{ClientMessage, ServerMessage, UserCommand},
-        server::{DEFAULT_POLL_TIMEOUT, SERVER, WAKER},
-        utils::{read_prefixed, write_prefixed},
+        client::ConnectOptions,
+        messages::{AdminOp, ClientMessage, ServerMessage, TableId, UserCommand},
+        socket::Socket,
+        tls::Stream,
     },
 };
 use ratatui::{
     self,
-    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
-    layout::{Alignment, Constraint, Flex, Layout, Margin, Position},
+    crossterm::{
+        event::{
+            self, Event, KeyCode, KeyEvent, KeyEventKind, MouseButton, MouseEvent, MouseEventKind,
+        },
+        execute,
+        terminal::SetTitle,
+    },
+    layout::{Alignment, Constraint, Flex, Layout, Margin, Position, Rect},
     style::{Style, Stylize},
     symbols::scrollbar,
     text::{Line, Span, Text},
     widgets::{
-        block, Block, Cell, Clear, List, ListDirection, ListItem, Padding, Paragraph, Row,
-        Scrollbar, ScrollbarOrientation, Table,
+        Cell, Clear, LineGauge, List, ListDirection, ListItem, Padding, Paragraph, Row, Scrollbar,
+        ScrollbarOrientation, Table,
     },
     DefaultTerminal, Frame,
 };
+use serde_json::json;
 use std::{
-    collections::{HashSet, VecDeque},
-    io,
-    net::TcpStream,
-    sync::mpsc::{channel, Receiver, Sender},
-    thread,
+    collections::HashSet,
+    fs::File,
+    io::{self, LineWriter, Write},
+    iter,
+    sync::mpsc::{channel, Sender},
     time::{Duration, Instant},
 };
 
+mod aliases;
+mod completion;
+mod keymap;
+mod stats;
+mod table;
+mod theme;
 mod widgets;
 
+use crate::connection::{Connection, Reconnect, Reconnecting};
+pub use aliases::Aliases;
+use completion::complete;
+use keymap::KeyAction;
+pub use keymap::Keymap;
+use stats::{PlayerStats, StatsTracker};
+pub use table::{players_table, render_cards, table_block, CARDS_HEIGHT};
+pub use theme::Theme;
 use widgets::{ScrollableList, UserInput};
 
 pub const MAX_LOG_RECORDS: usize = 1024;
 pub const POLL_TIMEOUT: Duration = Duration::from_millis(100);
+const TERMINAL_TITLE: &str = "pp_client";
+/// The turn timer changes color when there's this little time left.
+const TURN_TIMER_WARNING: Duration = Duration::from_secs(5);
+/// How long the table border stays lit or unlit while flashing.
+const TURN_ALERT_FLASH_PERIOD: Duration = Duration::from_millis(500);
+/// Three four-character stats columns and the spaces between them.
+const STATS_WIDTH: u16 = 14;
+/// Default percentage of the height the table and lobby get, with the
+/// log and chat getting the rest.
+const DEFAULT_TABLE_HEIGHT: u16 = 55;
+/// Default percentage of the width the lobby gets, with the table
+/// getting the rest.
+const DEFAULT_LOBBY_WIDTH: u16 = 40;
+/// Panes can't be resized past these percentages so none disappear.
+pub const MIN_PANE_PERCENTAGE: u16 = 10;
+pub const MAX_PANE_PERCENTAGE: u16 = 90;
+/// How many percentage points panes grow or shrink by with each key.
+const PANE_RESIZE_STEP: u16 = 5;
 
-fn blinds_to_string(view: &GameView) -> String {
-    format!(" blinds: ${}/${}  ", view.big_blind, view.small_blind)
+/// Labels and commands for the actions a user can take, in the order
+/// they're displayed as buttons. Calls are labeled with the equity they
+/// need to break even.
+fn action_options_to_buttons(
+    view: &GameView,
+    action_options: &HashSet<Action>,
+) -> Vec<(String, &'static str)> {
+    [
+        (Action::Fold, "fold"),
+        (Action::Check, "check"),
+        (Action::Call(0), "call"),
+        (Action::Raise(0), "raise"),
+        (Action::AllIn, "all-in"),
+    ]
+    .into_iter()
+    .filter_map(|(action, cmd)| {
+        action_options.get(&action).map(|action| match action {
+            Action::Call(amount) => {
+                let equity = 100.0 * view.minimum_defense_equity(*amount);
+                let action = action.to_chips_string(view.chip_display);
+                (format!(" {action} (needs {equity:.0}%) "), cmd)
+            }
+            action => (
+                format!(" {} ", action.to_chips_string(view.chip_display)),
+                cmd,
+            ),
+        })
+    })
+    .collect()
 }
 
-fn board_to_vec_of_spans(view: &GameView) -> Vec<Span<'_>> {
-    let mut span = vec![];
-    if !view.board.is_empty() {
-        span.push(" board: ".into());
-        // Player cards styled according to suit.
-        for card in view.board.iter() {
-            let card_repr = card_to_span(card);
-            span.push(card_repr);
-            span.push("  ".into());
+/// Resolve raise amounts like `50`, `0.25`, `pot`, `half`, `2/3`, or
+/// `2.5x` against the view. Plain amounts are in the units the table
+/// shows, whether that's chips, play money, or dollars. Fractions are of
+/// the pot after calling, and multiples are of the largest investment in
+/// the pot.
+fn raise_amount(amount: &str, view: &GameView, player_idx: usize) -> Option<Usd> {
+    let fraction = |numerator: &str, denominator: &str| -> Option<Usdf> {
+        let denominator: Usdf = denominator.parse().ok()?;
+        (denominator > 0.0).then_some(numerator.parse::<Usdf>().ok()? / denominator)
+    };
+    match amount {
+        "pot" => Some(view.pot_raise_amount(player_idx, 1.0)),
+        "half" => Some(view.pot_raise_amount(player_idx, 0.5)),
+        amount => {
+            if let Some((numerator, denominator)) = amount.split_once('/') {
+                let fraction = fraction(numerator, denominator)?;
+                Some(view.pot_raise_amount(player_idx, fraction))
+            } else if let Some(multiple) = amount.strip_suffix('x') {
+                let multiple = multiple.parse().ok()?;
+                Some(view.multiple_raise_amount(player_idx, multiple))
+            } else {
+                amount.parse().ok().map(|Dollars(amount)| amount)
+            }
         }
     }
-    span
 }
 
-fn card_to_span(card: &Card) -> Span<'_> {
-    let Card(value, suit) = card;
-    let value = match value {
-        1 | 14 => "A",
-        11 => "J",
-        12 => "Q",
-        13 => "K",
-        v => &v.to_string(),
+fn help_menu_text(commands: &Command, aliases: &Aliases) -> String {
+    format!("{}\n{aliases}", commands.clone().render_help())
+}
+
+/// The call amount, the pot it's going into, and the pot odds, if the
+/// user can call.
+fn price_to_call(view: &GameView, action_options: &HashSet<Action>) -> Option<String> {
+    let Some(Action::Call(amount)) = action_options.get(&Action::Call(0)) else {
+        return None;
     };
-    match suit {
-        Suit::Club => format!("{value:>2}/c").light_green(),
-        Suit::Diamond => format!("{value:>2}/d").light_blue(),
-        Suit::Heart => format!("{value:>2}/h").light_red(),
-        Suit::Spade => format!("{value:>2}/s").into(),
-        Suit::Wild => format!("{value:>2}/w").light_magenta(),
-    }
+    let odds = view.pot_odds(*amount).unwrap_or_default();
+    let equity = 100.0 * view.minimum_defense_equity(*amount);
+    Some(format!(
+        " to call: {} | pot: {} | pot odds: {odds:.1}:1 ({equity:.0}%)",
+        view.chip_display.chips(*amount),
+        view.chip_display.chips(view.pot.size)
+    ))
 }
 
-fn pot_to_string(view: &GameView) -> String {
-    format!(" pot: {}  ", view.pot)
+/// A pane's percentage after growing or shrinking it a step.
+fn resize_pane(percentage: u16, grow: bool) -> u16 {
+    let percentage = if grow {
+        percentage + PANE_RESIZE_STEP
+    } else {
+        percentage.saturating_sub(PANE_RESIZE_STEP)
+    };
+    percentage.clamp(MIN_PANE_PERCENTAGE, MAX_PANE_PERCENTAGE)
+}
+
+fn stats_to_row(stats: Option<&PlayerStats>) -> Row<'static> {
+    let percentage_repr = |percentage: Option<f32>| match percentage {
+        Some(percentage) => format!("{percentage:.0}"),
+        None => "-".to_string(),
+    };
+    let (vpip, pfr, aggression) = match stats {
+        Some(stats) => (
+            percentage_repr(stats.vpip()),
+            percentage_repr(stats.pfr()),
+            match stats.aggression() {
+                Some(aggression) => format!("{aggression:.1}"),
+                None => "-".to_string(),
+            },
+        ),
+        None => ("-".to_string(), "-".to_string(), "-".to_string()),
+    };
+    Row::new(
+        [vpip, pfr, aggression].map(|stat| Cell::new(Text::from(stat).alignment(Alignment::Right))),
+    )
+}
+
+fn user_to_row(user: &User, chip_display: ChipDisplay) -> Row<'_> {
+    let money = chip_display.chips(user.money).to_string();
+    Row::new(vec![
+        Cell::new(Text::from(user.name.to_string()).alignment(Alignment::Left)),
+        Cell::new(Text::from(money).alignment(Alignment::Right)),
+    ])
 }
 
-fn user_to_row(user: &User) -> Row {
+/// Waitlisters are listed in the order they'll be seated, along with the
+/// seats they're waiting for.
+fn waitlister_to_row(
+    position: usize,
+    user: &User,
+    seat_idx: Option<&usize>,
+    chip_display: ChipDisplay,
+) -> Row<'static> {
+    let name = match seat_idx {
+        Some(seat_idx) => format!("{position}. {} (seat {seat_idx})", user.name),
+        None => format!("{position}. {}", user.name),
+    };
+    let money = chip_display.chips(user.money).to_string();
     Row::new(vec![
-        Cell::new(Text::from(user.name.clone()).alignment(Alignment::Left)),
-        Cell::new(Text::from(format!("${}", user.money)).alignment(Alignment::Right)),
+        Cell::new(Text::from(name).alignment(Alignment::Left)),
+        Cell::new(Text::from(money).alignment(Alignment::Right)),
     ])
 }
 
@@ -94,9 +222,23 @@ enum RecordKind {
     Alert,
     Error,
     Game,
+    Showdown,
     You,
 }
 
+impl RecordKind {
+    fn label(&self) -> &'static str {
+        match self {
+            RecordKind::Ack => "ACK",
+            RecordKind::Alert => "ALERT",
+            RecordKind::Error => "ERROR",
+            RecordKind::Game => "GAME",
+            RecordKind::Showdown => "SHOW",
+            RecordKind::You => "YOU",
+        }
+    }
+}
+
 /// A timestamped terminal message with an importance label to help
 /// direct user attention.
 #[derive(Clone)]
@@ -116,23 +258,46 @@ impl Record {
     }
 }
 
-impl From<Record> for ListItem<'_> {
-    fn from(val: Record) -> Self {
-        let repr = match val.kind {
-            RecordKind::Ack => "ACK".light_blue(),
-            RecordKind::Alert => "ALERT".light_magenta(),
-            RecordKind::Error => "ERROR".light_red(),
-            RecordKind::Game => "GAME".light_yellow(),
-            RecordKind::You => "YOU".light_green(),
+impl Record {
+    /// The record as a line of JSON for session logs, like
+    /// `{"datetime":"2024-09-01T12:00:00Z","kind":"GAME","content":"..."}`.
+    fn to_json(&self) -> String {
+        json!({
+            "datetime": self.datetime.to_rfc3339_opts(SecondsFormat::Millis, true),
+            "kind": self.kind.label(),
+            "content": self.content,
+        })
+        .to_string()
+    }
+
+    fn into_list_item(self, theme: &Theme) -> ListItem<'static> {
+        let color = match self.kind {
+            RecordKind::Ack => theme.ack,
+            RecordKind::Alert => theme.alert,
+            RecordKind::Error => theme.error,
+            RecordKind::Game => theme.game,
+            RecordKind::Showdown => theme.highlight,
+            RecordKind::You => theme.you,
+        };
+        let repr = self.kind.label().fg(color);
+        // Showdowns stand out from the rest of the log as a block.
+        let style = match self.kind {
+            RecordKind::Showdown => Style::new().fg(theme.highlight).bold(),
+            _ => Style::new(),
         };
 
-        let msg = vec![
-            format!("[{} ", val.datetime.format("%H:%M:%S")).into(),
+        let mut lines = self.content.lines();
+        let first = vec![
+            format!("[{} ", self.datetime.format("%H:%M:%S")).into(),
             Span::styled(format!("{repr:5}"), repr.style),
-            format!("]: {}", val.content).into(),
+            "]: ".into(),
+            Span::styled(lines.next().unwrap_or_default().to_string(), style),
         ];
+        // Lines after the first are indented to line up under it.
+        let indent = " ".repeat(18);
+        let rest = lines.map(|line| Line::styled(format!("{indent}{line}"), style));
 
-        let content = Line::from(msg);
+        let content: Vec<_> = iter::once(Line::from(first)).chain(rest).collect();
         ListItem::new(content)
     }
 }
@@ -140,20 +305,17 @@ impl From<Record> for ListItem<'_> {
 /// Provides turn time remaining warnings at specific intervals when it's
 /// the player's turn.
 struct TurnWarnings {
-    t: Instant,
+    deadline: Instant,
     idx: usize,
-    warnings: [u8; 8],
+    warnings: [u64; 8],
 }
 
 impl TurnWarnings {
     /// Check for a new warning.
-    fn check(&mut self) -> Option<u8> {
+    fn check(&mut self) -> Option<u64> {
         if self.idx > 0 {
-            let ceiling = self.warnings.last().expect("warnings immutable");
             let warning = self.warnings[self.idx - 1];
-            let dt = Instant::now() - self.t;
-            let remaining = ceiling.saturating_sub(dt.as_secs() as u8);
-            if remaining <= warning {
+            if self.seconds_left() <= warning {
                 self.idx -= 1;
                 return Some(warning);
             }
@@ -167,41 +329,104 @@ impl TurnWarnings {
 
     fn new() -> Self {
         Self {
-            t: Instant::now(),
+            deadline: Instant::now(),
             idx: 0,
             warnings: [1, 2, 3, 4, 5, 10, 20, 30],
         }
     }
 
-    fn reset(&mut self) {
-        self.t = Instant::now();
-        self.idx = self.warnings.len();
+    /// Start warning about a turn that ends at the deadline. Warnings for
+    /// more time than the turn has are skipped.
+    fn reset(&mut self, deadline: Instant) {
+        self.deadline = deadline;
+        let seconds_left = self.seconds_left();
+        self.idx = self
+            .warnings
+            .iter()
+            .take_while(|warning| **warning <= seconds_left)
+            .count();
+    }
+
+    /// Whole seconds left in the turn, rounded up.
+    fn seconds_left(&self) -> u64 {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0)
     }
 }
 
+/// Which part of the UI keyboard navigation applies to.
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Focus {
+    Input,
+    Log,
+}
+
 /// App holds the application state.
 pub struct App {
     username: Username,
     addr: String,
+    /// How to connect again when the connection drops
+    connect_options: ConnectOptions,
     commands: Command,
+    /// Short names for commands
+    aliases: Aliases,
     /// Help menu
     help_menu_text: String,
     /// Whether to display the help menu window
     show_help_menu: bool,
+    /// Recent hands fetched from the server
+    hand_history_text: String,
+    /// Whether to display the hand history window
+    show_hand_history: bool,
+    /// How many lines the hand history window is scrolled down
+    hand_history_scroll: u16,
     /// History of recorded messages
     log_handle: ScrollableList,
+    /// Where recorded messages are also written, one JSON object per line
+    session_log: Option<LineWriter<File>>,
+    /// Chat messages from users at the table
+    chat_handle: ScrollableList,
+    /// Statistics for players seen at the table
+    stats: StatsTracker,
+    /// Colors for everything drawn
+    theme: Theme,
+    /// Whether to ring the terminal bell, retitle the terminal, and flash
+    /// the table border when it's the user's turn
+    turn_alerts: bool,
+    /// When the user's turn started, if it's their turn
+    turn_started: Option<Instant>,
+    /// How long the acting player's turn lasts and when it ends, if
+    /// anyone's turn is being timed
+    turn_deadline: Option<(Duration, Instant)>,
     /// Current value of the input box
     user_input: UserInput,
+    /// Values the input box could be completed to
+    completion_suggestions: Vec<String>,
+    focus: Focus,
+    /// What keys do
+    keymap: Keymap,
+    /// Areas drawn in the last frame, kept so mouse clicks can be
+    /// matched to what's under them.
+    log_area: Rect,
+    user_input_area: Rect,
+    /// Clickable action buttons and the commands they run.
+    action_buttons: Vec<(Rect, &'static str)>,
+    /// Percentage of the height the table and lobby get
+    table_height: u16,
+    /// Percentage of the width the lobby gets
+    lobby_width: u16,
 }
 
 impl App {
     fn handle_command(
         &mut self,
         user_input: &str,
+        view: &GameView,
         action_options: &HashSet<Action>,
         tx_client: &Sender<ClientMessage>,
         waker: &Waker,
     ) -> Result<(), Error> {
+        let user_input = self.aliases.expand(user_input);
         let cmd = user_input.split(' ');
         match self.commands.clone().try_get_matches_from(cmd) {
             Ok(matches) => {
@@ -210,7 +435,7 @@ impl App {
                         "all-in" => {
                             if let Some(action) = action_options.get(&Action::AllIn) {
                                 let msg = ClientMessage {
-                                    username: self.username.to_string(),
+                                    username: self.username.clone(),
                                     command: UserCommand::TakeAction(action.clone()),
                                 };
                                 tx_client.send(msg)?;
@@ -218,9 +443,67 @@ impl App {
                             } else {
                                 let record =
                                     Record::new(RecordKind::Error, "can't all-in now".to_string());
-                                self.log_handle.push(record.into());
+                                self.log(record);
                             }
                         }
+                        "back" => {
+                            let msg = ClientMessage {
+                                username: self.username.clone(),
+                                command: UserCommand::ChangeState(UserState::Play),
+                            };
+                            tx_client.send(msg)?;
+                            waker.wake()?;
+                        }
+                        "ban" | "force-fold" | "kick" => {
+                            match matches
+                                .subcommand_matches(cmd)
+                                .and_then(|matches| matches.get_one::<Username>("username"))
+                            {
+                                Some(username) => {
+                                    let username = username.clone();
+                                    let op = match cmd {
+                                        "ban" => AdminOp::Ban(username),
+                                        "force-fold" => AdminOp::ForceFold(username),
+                                        _ => AdminOp::Kick(username),
+                                    };
+                                    let msg = ClientMessage {
+                                        username: self.username.clone(),
+                                        command: UserCommand::Admin(op),
+                                    };
+                                    tx_client.send(msg)?;
+                                    waker.wake()?;
+                                }
+                                None => unreachable!("always matches"),
+                            }
+                        }
+                        "bomb" => match matches.subcommand_matches("bomb") {
+                            Some(matches) => match matches.get_one::<String>("vote") {
+                                Some(vote) => {
+                                    let msg = ClientMessage {
+                                        username: self.username.clone(),
+                                        command: UserCommand::BombPot(vote == "yes"),
+                                    };
+                                    tx_client.send(msg)?;
+                                    waker.wake()?;
+                                }
+                                None => unreachable!("always matches"),
+                            },
+                            None => unreachable!("always matches"),
+                        },
+                        "buyin" => match matches.subcommand_matches("buyin") {
+                            Some(matches) => match matches.get_one::<Dollars>("amount") {
+                                Some(Dollars(amount)) => {
+                                    let msg = ClientMessage {
+                                        username: self.username.clone(),
+                                        command: UserCommand::BuyIn(*amount),
+                                    };
+                                    tx_client.send(msg)?;
+                                    waker.wake()?;
+                                }
+                                None => unreachable!("always matches"),
+                            },
+                            None => unreachable!("always matches"),
+                        },
                         "call" => {
                             // Actions use their variant for comparisons,
                             // so we don't need to provide the correct call
@@ -228,7 +511,7 @@ impl App {
                             // options.
                             if let Some(action) = action_options.get(&Action::Call(0)) {
                                 let msg = ClientMessage {
-                                    username: self.username.to_string(),
+                                    username: self.username.clone(),
                                     command: UserCommand::TakeAction(action.clone()),
                                 };
                                 tx_client.send(msg)?;
@@ -236,13 +519,13 @@ impl App {
                             } else {
                                 let record =
                                     Record::new(RecordKind::Error, "can't call now".to_string());
-                                self.log_handle.push(record.into());
+                                self.log(record);
                             }
                         }
                         "check" => {
                             if let Some(action) = action_options.get(&Action::Check) {
                                 let msg = ClientMessage {
-                                    username: self.username.to_string(),
+                                    username: self.username.clone(),
                                     command: UserCommand::TakeAction(action.clone()),
                                 };
                                 tx_client.send(msg)?;
@@ -250,9 +533,24 @@ impl App {
                             } else {
                                 let record =
                                     Record::new(RecordKind::Error, "can't check now".to_string());
-                                self.log_handle.push(record.into());
+                                self.log(record);
                             }
                         }
+                        "chat" => match matches.subcommand_matches("chat") {
+                            Some(matches) => match matches.get_many::<String>("message") {
+                                Some(words) => {
+                                    let message = words.cloned().collect::<Vec<_>>().join(" ");
+                                    let msg = ClientMessage {
+                                        username: self.username.clone(),
+                                        command: UserCommand::Chat(message),
+                                    };
+                                    tx_client.send(msg)?;
+                                    waker.wake()?;
+                                }
+                                None => unreachable!("always matches"),
+                            },
+                            None => unreachable!("always matches"),
+                        },
                         "fold" => {
                             if let Some(action) = action_options.get(&Action::Fold) {
                                 let msg = ClientMessage {
@@ -264,13 +562,72 @@ impl App {
                             } else {
                                 let record =
                                     Record::new(RecordKind::Error, "can't fold now".to_string());
-                                self.log_handle.push(record.into());
+                                self.log(record);
                             }
                         }
+                        "history" => {
+                            let msg = ClientMessage {
+                                username: self.username.clone(),
+                                command: UserCommand::ListHands,
+                            };
+                            tx_client.send(msg)?;
+                            waker.wake()?;
+                        }
+                        "join" => match matches.subcommand_matches("join") {
+                            Some(matches) => match matches.get_one::<TableId>("table") {
+                                Some(table_id) => {
+                                    let msg = ClientMessage {
+                                        username: self.username.clone(),
+                                        command: UserCommand::JoinTable(*table_id),
+                                    };
+                                    tx_client.send(msg)?;
+                                    waker.wake()?;
+                                }
+                                None => unreachable!("always matches"),
+                            },
+                            None => unreachable!("always matches"),
+                        },
+                        "leaderboard" => match matches.subcommand_matches("leaderboard") {
+                            Some(matches) => match matches.get_one::<usize>("top") {
+                                Some(top) => {
+                                    let msg = ClientMessage {
+                                        username: self.username.clone(),
+                                        command: UserCommand::Leaderboard(*top),
+                                    };
+                                    tx_client.send(msg)?;
+                                    waker.wake()?;
+                                }
+                                None => unreachable!("always matches"),
+                            },
+                            None => unreachable!("always matches"),
+                        },
+                        "muck" => {
+                            let msg = ClientMessage {
+                                username: self.username.clone(),
+                                command: UserCommand::MuckHand,
+                            };
+                            tx_client.send(msg)?;
+                            waker.wake()?;
+                        }
                         "play" => {
+                            let command = match matches
+                                .subcommand_matches("play")
+                                .and_then(|matches| matches.get_one::<usize>("seat"))
+                            {
+                                Some(seat_idx) => UserCommand::TakeSeat(*seat_idx),
+                                None => UserCommand::ChangeState(UserState::Play),
+                            };
                             let msg = ClientMessage {
                                 username: self.username.clone(),
-                                command: UserCommand::ChangeState(UserState::Play),
+                                command,
+                            };
+                            tx_client.send(msg)?;
+                            waker.wake()?;
+                        }
+                        "rake" => {
+                            let msg = ClientMessage {
+                                username: self.username.clone(),
+                                command: UserCommand::Admin(AdminOp::Rake),
                             };
                             tx_client.send(msg)?;
                             waker.wake()?;
@@ -284,17 +641,38 @@ impl App {
                                 match matches.subcommand_matches("raise") {
                                     Some(matches) => match matches.get_one::<String>("amount") {
                                         Some(amount) => {
-                                            let action = if let Ok(amount) = amount.parse::<Usd>() {
-                                                Action::Raise(amount)
-                                            } else {
-                                                action.clone()
-                                            };
-                                            let msg = ClientMessage {
-                                                username: self.username.to_string(),
-                                                command: UserCommand::TakeAction(action),
+                                            let player_idx = view
+                                                .players
+                                                .iter()
+                                                .position(|p| p.user.name == self.username);
+                                            let action = match (amount.as_str(), player_idx) {
+                                                ("", _) => Some(action.clone()),
+                                                (amount, Some(player_idx)) => {
+                                                    raise_amount(amount, view, player_idx)
+                                                        .map(Action::Raise)
+                                                }
+                                                (amount, None) => amount
+                                                    .parse()
+                                                    .ok()
+                                                    .map(|Dollars(amount)| Action::Raise(amount)),
                                             };
-                                            tx_client.send(msg)?;
-                                            waker.wake()?;
+                                            match action {
+                                                Some(action) => {
+                                                    let msg = ClientMessage {
+                                                        username: self.username.clone(),
+                                                        command: UserCommand::TakeAction(action),
+                                                    };
+                                                    tx_client.send(msg)?;
+                                                    waker.wake()?;
+                                                }
+                                                None => {
+                                                    let record = Record::new(
+                                                        RecordKind::Error,
+                                                        format!("invalid raise amount: {amount}"),
+                                                    );
+                                                    self.log(record);
+                                                }
+                                            }
                                         }
                                         None => unreachable!("always matches"),
                                     },
@@ -305,7 +683,7 @@ impl App {
                             } else {
                                 let record =
                                     Record::new(RecordKind::Error, "can't raise now".to_string());
-                                self.log_handle.push(record.into());
+                                self.log(record);
                             }
                         }
                         "show" => {
@@ -316,6 +694,14 @@ impl App {
                             tx_client.send(msg)?;
                             waker.wake()?;
                         }
+                        "sitout" => {
+                            let msg = ClientMessage {
+                                username: self.username.clone(),
+                                command: UserCommand::ChangeState(UserState::SitOut),
+                            };
+                            tx_client.send(msg)?;
+                            waker.wake()?;
+                        }
                         "spectate" => {
                             let msg = ClientMessage {
                                 username: self.username.clone(),
@@ -332,6 +718,28 @@ impl App {
                             tx_client.send(msg)?;
                             waker.wake()?;
                         }
+                        "tables" => {
+                            let msg = ClientMessage {
+                                username: self.username.clone(),
+                                command: UserCommand::ListTables,
+                            };
+                            tx_client.send(msg)?;
+                            waker.wake()?;
+                        }
+                        "twice" => match matches.subcommand_matches("twice") {
+                            Some(matches) => match matches.get_one::<String>("consent") {
+                                Some(consent) => {
+                                    let msg = ClientMessage {
+                                        username: self.username.clone(),
+                                        command: UserCommand::RunItTwice(consent == "yes"),
+                                    };
+                                    tx_client.send(msg)?;
+                                    waker.wake()?;
+                                }
+                                None => unreachable!("always matches"),
+                            },
+                            None => unreachable!("always matches"),
+                        },
                         _ => unreachable!("always a subcommand"),
                     }
                 }
@@ -341,23 +749,103 @@ impl App {
                     RecordKind::Error,
                     format!("unrecognized command: {user_input}"),
                 );
-                self.log_handle.push(record.into());
+                self.log(record);
             }
         }
         Ok(())
     }
 
-    pub fn new(username: Username, addr: String) -> Self {
+    pub fn new(username: Username, addr: String, keymap: Keymap) -> Self {
         let all_in = Command::new("all-in").about("Go all-in, betting all your money on the hand.");
+        let username_arg = Arg::new("username")
+            .help("User to moderate.")
+            .required(true)
+            .value_name("USERNAME")
+            .value_parser(|name: &str| Username::new(name));
+        let back = Command::new("back").about("Get dealt back in after sitting out.");
+        let ban = Command::new("ban")
+            .about("Ban a user and their IP address. Only admins can ban.")
+            .arg(username_arg.clone());
+        let bomb_about = [
+            "Vote for the next hand to be a bomb pot, where everyone antes and the hand",
+            "starts on the flop. It's a bomb pot once every player dealt in votes for one.",
+        ]
+        .join("\n");
+        let bomb = Command::new("bomb").about(bomb_about).arg(
+            Arg::new("vote")
+                .help("Whether to vote for a bomb pot.")
+                .default_value("yes")
+                .value_name("yes|no")
+                .value_parser(["yes", "no"]),
+        );
+        let buyin = Command::new("buyin")
+            .about("Buy more money, up to the table's max buy-in. Players get it after their hand.")
+            .arg(
+                Arg::new("amount")
+                    .help("Dollars to buy, like `100` or `2.50`.")
+                    .required(true)
+                    .value_name("AMOUNT")
+                    .value_parser(value_parser!(Dollars)),
+            );
         let call = Command::new("call").about("Match the investment required to stay in the hand.");
+        let chat = Command::new("chat")
+            .about("Send a message to everyone at your table.")
+            .arg(
+                Arg::new("message")
+                    .help("Message to send.")
+                    .num_args(1..)
+                    .required(true)
+                    .trailing_var_arg(true)
+                    .value_name("MESSAGE"),
+            );
         let check =
             Command::new("check").about("Check, voting to move to the next card reveal(s).");
         let fold = Command::new("fold").about("Fold, forfeiting your hand.");
-        let play = Command::new("play").about("Join the playing waitlist.");
+        let force_fold = Command::new("force-fold")
+            .about("Fold for a user that's taking too long. Only admins can force folds.")
+            .arg(username_arg.clone());
+        let history = Command::new("history")
+            .about("Review the last few hands played at your table. Press Esc to close.");
+        let join = Command::new("join")
+            .about("Join another table. If you're a player, you won't move until the game is over.")
+            .arg(
+                Arg::new("table")
+                    .help("ID of the table to join.")
+                    .required(true)
+                    .value_name("TABLE")
+                    .value_parser(value_parser!(TableId)),
+            );
+        let leaderboard = Command::new("leaderboard")
+            .about("Show the players that have won the most at the server's cash games.")
+            .arg(
+                Arg::new("top")
+                    .help("Number of players to show.")
+                    .default_value("10")
+                    .value_name("N")
+                    .value_parser(value_parser!(usize)),
+            );
+        let kick = Command::new("kick")
+            .about("Disconnect a user. Only admins can kick.")
+            .arg(username_arg);
+        let muck = Command::new("muck")
+            .about("Muck your hand after losing at showdown. Losing hands are mucked by default.");
+        let play = Command::new("play")
+            .about("Join the playing waitlist. Waitlisters are seated in the order they joined.")
+            .arg(
+                Arg::new("seat")
+                    .help("Wait for this seat instead of taking the first one open.")
+                    .long("seat")
+                    .value_name("SEAT")
+                    .value_parser(value_parser!(usize)),
+            );
+        let rake = Command::new("rake")
+            .about("Show how much rake the server has collected. Only admins can check the rake.");
         let raise_about = [
             "Raise the investment required to stay in the hand. Entering without a value",
-            "defaults to the min raise amount. Entering AMOUNT will raise by AMOUNT, but",
-            "AMOUNT must be >= the min raise.",
+            "defaults to the min raise amount. Entering AMOUNT will raise by AMOUNT dollars",
+            "(like `50` or `0.25`), but AMOUNT must be >= the min raise. AMOUNT can also be",
+            "`pot`, `half`, or a fraction like `2/3` to raise by that much of the pot after",
+            "calling, or a multiple like `3x` to raise to that many times the current bet.",
         ]
         .join("\n");
         let raise = Command::new("raise").about(raise_about).arg(
@@ -366,12 +854,29 @@ impl App {
                 .default_value("")
                 .value_name("AMOUNT"),
         );
-        let show = Command::new("show").about("Show your hand. Only possible during the showdown.");
+        let show = Command::new("show")
+            .about("Show your hand. Only possible during the showdown or right after losing it.");
+        let sitout = Command::new("sitout").about(
+            "Keep your seat and money without being dealt in. Takes effect after your hand.",
+        );
         let spectate = Command::new("spectate").about(
             "Join spectators. If you're a player, you won't spectate until the game is over.",
         );
         let start =
             Command::new("start").about("Start the game. Requires 2+ players or waitlisters.");
+        let tables = Command::new("tables").about("List all the tables hosted by the server.");
+        let twice_about = [
+            "Agree to run the rest of the board twice once all remaining players are all-in.",
+            "The pot is split between the two boards if every remaining player agrees.",
+        ]
+        .join("\n");
+        let twice = Command::new("twice").about(twice_about).arg(
+            Arg::new("consent")
+                .help("Whether to run it twice.")
+                .default_value("yes")
+                .value_name("yes|no")
+                .value_parser(["yes", "no"]),
+        );
         let usage = "Enter commands to interact with the poker server.";
         let commands = Command::new("poker")
             .disable_help_flag(true)
@@ -381,184 +886,338 @@ impl App {
             .no_binary_name(true)
             .override_usage(usage)
             .subcommand(all_in)
+            .subcommand(back)
+            .subcommand(ban)
+            .subcommand(bomb)
+            .subcommand(buyin)
             .subcommand(call)
+            .subcommand(chat)
             .subcommand(check)
             .subcommand(fold)
+            .subcommand(force_fold)
+            .subcommand(history)
+            .subcommand(join)
+            .subcommand(kick)
+            .subcommand(leaderboard)
+            .subcommand(muck)
             .subcommand(play)
+            .subcommand(rake)
             .subcommand(raise)
             .subcommand(show)
+            .subcommand(sitout)
             .subcommand(spectate)
-            .subcommand(start);
-        let help_menu_text = commands.clone().render_help().to_string();
+            .subcommand(start)
+            .subcommand(tables)
+            .subcommand(twice);
+        let aliases = Aliases::default();
+        let help_menu_text = help_menu_text(&commands, &aliases);
         Self {
             username,
             addr,
+            connect_options: ConnectOptions::default(),
             commands,
+            aliases,
             help_menu_text,
             show_help_menu: false,
+            hand_history_text: String::new(),
+            show_hand_history: false,
+            hand_history_scroll: 0,
             log_handle: ScrollableList::new(MAX_LOG_RECORDS),
+            session_log: None,
+            chat_handle: ScrollableList::new(MAX_LOG_RECORDS),
+            stats: StatsTracker::default(),
+            theme: Theme::default(),
+            turn_alerts: true,
+            turn_started: None,
+            turn_deadline: None,
             user_input: UserInput::new(),
+            completion_suggestions: Vec::new(),
+            focus: Focus::Input,
+            keymap,
+            log_area: Rect::default(),
+            user_input_area: Rect::default(),
+            action_buttons: Vec::new(),
+            table_height: DEFAULT_TABLE_HEIGHT,
+            lobby_width: DEFAULT_LOBBY_WIDTH,
         }
     }
 
-    pub fn run(
-        mut self,
-        stream: TcpStream,
-        mut view: GameView,
-        mut terminal: DefaultTerminal,
-    ) -> Result<(), Error> {
-        let (tx_client, rx_client): (Sender<ClientMessage>, Receiver<ClientMessage>) = channel();
-        let (tx_server, rx_server): (Sender<ServerMessage>, Receiver<ServerMessage>) = channel();
-
-        let mut poll = Poll::new()?;
-        let waker = Waker::new(poll.registry(), WAKER)?;
-
-        // This thread is where the actual client-server networking happens for
-        // non-blocking IO. Some non-blocking IO between client threads is also
-        // managed by this thread. The UI thread sends client command messages
-        // to this thread; those messages are eventually written to the server.
-        thread::spawn(move || -> Result<(), Error> {
-            let mut events = Events::with_capacity(64);
-            let mut messages_to_write: VecDeque<ClientMessage> = VecDeque::new();
-            stream.set_nonblocking(true)?;
-            let mut stream = mio::net::TcpStream::from_std(stream);
-            poll.registry()
-                .register(&mut stream, SERVER, Interest::READABLE)?;
-
-            loop {
-                if let Err(error) = poll.poll(&mut events, Some(DEFAULT_POLL_TIMEOUT)) {
-                    match error.kind() {
-                        io::ErrorKind::Interrupted => continue,
-                        _ => bail!(error),
-                    }
-                }
+    /// Start counting down the acting player's turn from the view.
+    fn update_turn_deadline(&mut self, view: &GameView) {
+        self.turn_deadline = view
+            .turn_timer
+            .map(|timer| (timer.timeout, Instant::now() + timer.remaining));
+    }
 
-                for event in events.iter() {
-                    match event.token() {
-                        SERVER => {
-                            if event.is_writable() && !messages_to_write.is_empty() {
-                                while let Some(msg) = messages_to_write.pop_front() {
-                                    if let Err(error) =
-                                        write_prefixed::<ClientMessage, mio::net::TcpStream>(
-                                            &mut stream,
-                                            &msg,
-                                        )
-                                    {
-                                        match error.kind() {
-                                            // `write_prefixed` uses `write_all` under the hood, so we know
-                                            // that if any of these occur, then the connection was probably
-                                            // dropped at some point.
-                                            io::ErrorKind::BrokenPipe
-                                            | io::ErrorKind::ConnectionAborted
-                                            | io::ErrorKind::ConnectionReset
-                                            | io::ErrorKind::TimedOut
-                                            | io::ErrorKind::UnexpectedEof => {
-                                                bail!("connection dropped");
-                                            }
-                                            // Would block "errors" are the OS's way of saying that the
-                                            // connection is not actually ready to perform this I/O operation.
-                                            io::ErrorKind::WouldBlock => {
-                                                // The message couldn't be sent, so we need to push it back
-                                                // onto the queue so we don't accidentally forget about it.
-                                                messages_to_write.push_front(msg);
-                                            }
-                                            // Retry writing in the case that the full message couldn't
-                                            // be written. This should be infrequent.
-                                            io::ErrorKind::WriteZero => {
-                                                messages_to_write.push_front(msg);
-                                                continue;
-                                            }
-                                            // Other errors we'll consider fatal.
-                                            _ => bail!(error),
-                                        }
-                                        poll.registry().reregister(
-                                            &mut stream,
-                                            SERVER,
-                                            Interest::READABLE,
-                                        )?;
-                                        break;
-                                    }
-                                }
-                            }
+    /// Show the record in the log, writing it to the session log too.
+    /// The session log is given up on if it can't be written to.
+    fn log(&mut self, record: Record) {
+        let written = match self.session_log {
+            Some(ref mut session_log) => writeln!(session_log, "{}", record.to_json()),
+            None => Ok(()),
+        };
+        self.log_handle.push(record.into_list_item(&self.theme));
+        if let Err(error) = written {
+            self.session_log = None;
+            let record = Record::new(
+                RecordKind::Error,
+                format!("stopped writing the session log: {error}"),
+            );
+            self.log_handle.push(record.into_list_item(&self.theme));
+        }
+    }
 
-                            if event.is_readable() {
-                                // We can (maybe) read from the connection.
-                                loop {
-                                    match read_prefixed::<ServerMessage, mio::net::TcpStream>(
-                                        &mut stream,
-                                    ) {
-                                        Ok(msg) => {
-                                            tx_server.send(msg)?;
-                                        }
-                                        Err(error) => {
-                                            match error.kind() {
-                                                // `read_prefixed` uses `read_exact` under the hood, so we know
-                                                // that an Eof error means the connection was dropped.
-                                                io::ErrorKind::BrokenPipe
-                                                | io::ErrorKind::ConnectionAborted
-                                                | io::ErrorKind::ConnectionReset
-                                                | io::ErrorKind::InvalidData
-                                                | io::ErrorKind::TimedOut
-                                                | io::ErrorKind::UnexpectedEof => {
-                                                    bail!("connection dropped");
-                                                }
-                                                // Would block "errors" are the OS's way of saying that the
-                                                // connection is not actually ready to perform this I/O operation.
-                                                io::ErrorKind::WouldBlock => {}
-                                                // Other errors we'll consider fatal.
-                                                _ => {
-                                                    bail!(error)
-                                                }
-                                            }
-                                            break;
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        WAKER => {
-                            while let Ok(msg) = rx_client.try_recv() {
-                                messages_to_write.push_back(msg);
-                                poll.registry().reregister(
-                                    &mut stream,
-                                    SERVER,
-                                    Interest::READABLE | Interest::WRITABLE,
-                                )?;
-                            }
+    fn end_turn_alert(&mut self) -> Result<(), Error> {
+        if self.turn_alerts && self.turn_started.take().is_some() {
+            execute!(io::stdout(), SetTitle(TERMINAL_TITLE))?;
+        }
+        Ok(())
+    }
+
+    fn start_turn_alert(&mut self) -> Result<(), Error> {
+        self.turn_started = Some(Instant::now());
+        if self.turn_alerts {
+            let mut stdout = io::stdout();
+            // ASCII bell.
+            stdout.write_all(b"\x07")?;
+            execute!(stdout, SetTitle(format!("your turn - {TERMINAL_TITLE}")))?;
+        }
+        Ok(())
+    }
+
+    /// Short names for commands, like `f` for `fold`.
+    pub fn aliases(mut self, aliases: Aliases) -> Self {
+        self.help_menu_text = help_menu_text(&self.commands, &aliases);
+        self.aliases = aliases;
+        self
+    }
+
+    /// Options to connect with again when the connection drops. They
+    /// should match the ones the user first connected with.
+    pub fn connect_options(mut self, options: ConnectOptions) -> Self {
+        self.connect_options = options;
+        self
+    }
+
+    /// Also write every recorded message to the file so the session can
+    /// be reviewed after the client exits.
+    pub fn session_log(mut self, file: File) -> Self {
+        self.session_log = Some(LineWriter::new(file));
+        self
+    }
+
+    /// Percentages of the height the table and lobby get and of the
+    /// width the lobby gets. They can be changed with keys while running.
+    pub fn pane_sizes(mut self, table_height: u16, lobby_width: u16) -> Self {
+        self.table_height = table_height.clamp(MIN_PANE_PERCENTAGE, MAX_PANE_PERCENTAGE);
+        self.lobby_width = lobby_width.clamp(MIN_PANE_PERCENTAGE, MAX_PANE_PERCENTAGE);
+        self
+    }
+
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Whether to ring the bell, retitle the terminal, and flash the table
+    /// border when it's the user's turn. Enabled by default.
+    pub fn turn_alerts(mut self, enabled: bool) -> Self {
+        self.turn_alerts = enabled;
+        self
+    }
+
+    fn complete_user_input(&mut self, view: &GameView, action_options: &HashSet<Action>) {
+        if let Some(completion) =
+            complete(&self.commands, &self.user_input.value, view, action_options)
+        {
+            self.user_input.value = completion.value;
+            self.user_input.jump_to_last();
+            self.completion_suggestions = completion.suggestions;
+        }
+    }
+
+    fn handle_mouse(
+        &mut self,
+        mouse: MouseEvent,
+        view: &GameView,
+        action_options: &HashSet<Action>,
+        tx_client: &Sender<ClientMessage>,
+        waker: &Waker,
+    ) -> Result<(), Error> {
+        let position = Position::new(mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::ScrollUp if self.show_hand_history => {
+                self.hand_history_scroll = self.hand_history_scroll.saturating_sub(1);
+            }
+            MouseEventKind::ScrollDown if self.show_hand_history => {
+                self.hand_history_scroll = self.hand_history_scroll.saturating_add(1);
+            }
+            MouseEventKind::ScrollUp if self.log_area.contains(position) => {
+                self.log_handle.move_up();
+            }
+            MouseEventKind::ScrollDown if self.log_area.contains(position) => {
+                self.log_handle.move_down();
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                let button = self
+                    .action_buttons
+                    .iter()
+                    .find(|(area, _)| area.contains(position))
+                    .map(|(_, cmd)| *cmd);
+                if let Some(cmd) = button {
+                    let record = Record::new(RecordKind::You, cmd.to_string());
+                    self.log(record);
+                    self.handle_command(cmd, view, action_options, tx_client, waker)?;
+                } else if self.user_input_area.contains(position) {
+                    // Put the cursor where the user clicked, ignoring the
+                    // box's border.
+                    self.focus = Focus::Input;
+                    let char_idx = position.x.saturating_sub(self.user_input_area.x + 1);
+                    self.user_input.move_to(char_idx as usize);
+                } else if self.log_area.contains(position) {
+                    self.focus = Focus::Log;
+                    // Clicking the scrollbar's upper or lower half scrolls
+                    // the log in that direction.
+                    if position.x == self.log_area.right().saturating_sub(2) {
+                        let middle = self.log_area.y + self.log_area.height / 2;
+                        if position.y < middle {
+                            self.log_handle.move_up();
+                        } else {
+                            self.log_handle.move_down();
                         }
-                        _ => {}
                     }
                 }
             }
-        });
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub fn run(
+        mut self,
+        stream: Stream<Socket>,
+        mut view: GameView,
+        mut terminal: DefaultTerminal,
+    ) -> Result<(), Error> {
+        let (tx_progress, rx_progress) = channel();
+        let reconnect = Reconnect {
+            addr: self.addr.clone(),
+            options: self.connect_options.clone(),
+            tx_progress,
+        };
+        let Connection {
+            tx_client,
+            rx_server,
+            waker,
+        } = Connection::spawn(self.username.clone(), stream, Some(reconnect))?;
 
         let mut action_options = HashSet::new();
         let mut turn_warnings = TurnWarnings::new();
+        self.update_turn_deadline(&view);
         loop {
-            terminal.draw(|frame| self.draw(&view, frame))?;
+            terminal.draw(|frame| self.draw(&view, &action_options, frame))?;
 
             if event::poll(POLL_TIMEOUT)? {
-                if let Event::Key(KeyEvent {
-                    code,
-                    modifiers,
-                    kind,
-                    ..
-                }) = event::read()?
-                {
-                    if kind == KeyEventKind::Press {
-                        match modifiers {
-                            KeyModifiers::CONTROL => match code {
-                                KeyCode::Home => self.log_handle.jump_to_first(),
-                                KeyCode::End => self.log_handle.jump_to_last(),
-                                _ => {}
-                            },
-                            KeyModifiers::NONE => match code {
+                match event::read()? {
+                    // The hand history window takes over the keyboard until
+                    // it's closed.
+                    Event::Key(KeyEvent {
+                        code,
+                        modifiers,
+                        kind: KeyEventKind::Press,
+                        ..
+                    }) if self.show_hand_history => match self.keymap.get((code, modifiers)) {
+                        Some(KeyAction::ScrollUp) => {
+                            self.hand_history_scroll = self.hand_history_scroll.saturating_sub(1)
+                        }
+                        Some(KeyAction::ScrollDown) => {
+                            self.hand_history_scroll = self.hand_history_scroll.saturating_add(1)
+                        }
+                        Some(KeyAction::Exit) => self.show_hand_history = false,
+                        _ => {}
+                    },
+                    Event::Key(KeyEvent {
+                        code,
+                        modifiers,
+                        kind: KeyEventKind::Press,
+                        ..
+                    }) => {
+                        // Keys that edit the input box go to the input box
+                        // unless the log is focused and they're bound to
+                        // something.
+                        let key = (code, modifiers);
+                        let is_input_key = keymap::is_input_key(key);
+                        let action = match self.keymap.get(key) {
+                            Some(_) if is_input_key && self.focus == Focus::Input => None,
+                            Some(action) => Some(action),
+                            None => {
+                                if is_input_key {
+                                    self.focus = Focus::Input;
+                                }
+                                None
+                            }
+                        };
+                        self.completion_suggestions.clear();
+                        match action {
+                            Some(KeyAction::Complete) => {
+                                self.complete_user_input(&view, &action_options)
+                            }
+                            // Help is only toggled when there's nothing to
+                            // complete.
+                            Some(KeyAction::ToggleHelp)
+                                if self.focus == Focus::Input
+                                    && !self.user_input.value.is_empty() =>
+                            {
+                                self.complete_user_input(&view, &action_options)
+                            }
+                            // Plain arrow keys recall commands like a shell
+                            // once there's something in the input box.
+                            Some(KeyAction::ScrollDown)
+                                if modifiers.is_empty()
+                                    && self.focus == Focus::Input
+                                    && self.user_input.is_recalling() =>
+                            {
+                                self.user_input.recall_next()
+                            }
+                            Some(KeyAction::ScrollUp)
+                                if modifiers.is_empty()
+                                    && self.focus == Focus::Input
+                                    && (self.user_input.is_recalling()
+                                        || !self.user_input.value.is_empty()) =>
+                            {
+                                self.user_input.recall_previous()
+                            }
+                            Some(KeyAction::Exit) => return Ok(()),
+                            Some(KeyAction::FocusLog) => self.focus = Focus::Log,
+                            Some(KeyAction::GrowLobby) => {
+                                self.lobby_width = resize_pane(self.lobby_width, true)
+                            }
+                            Some(KeyAction::GrowTable) => {
+                                self.table_height = resize_pane(self.table_height, true)
+                            }
+                            Some(KeyAction::NextCommand) => self.user_input.recall_next(),
+                            Some(KeyAction::PreviousCommand) => self.user_input.recall_previous(),
+                            Some(KeyAction::ScrollDown) => self.log_handle.move_down(),
+                            Some(KeyAction::ScrollToBottom) => self.log_handle.jump_to_last(),
+                            Some(KeyAction::ScrollToTop) => self.log_handle.jump_to_first(),
+                            Some(KeyAction::ScrollUp) => self.log_handle.move_up(),
+                            Some(KeyAction::ShrinkLobby) => {
+                                self.lobby_width = resize_pane(self.lobby_width, false)
+                            }
+                            Some(KeyAction::ShrinkTable) => {
+                                self.table_height = resize_pane(self.table_height, false)
+                            }
+                            Some(KeyAction::ToggleHelp) => {
+                                self.show_help_menu = !self.show_help_menu
+                            }
+                            None if is_input_key => match code {
                                 KeyCode::Enter => {
                                     let user_input = self.user_input.submit();
                                     let record = Record::new(RecordKind::You, user_input.clone());
-                                    self.log_handle.push(record.into());
+                                    self.log(record);
                                     self.handle_command(
                                         &user_input,
+                                        &view,
                                         &action_options,
                                         &tx_client,
                                         &waker,
@@ -569,55 +1228,169 @@ impl App {
                                 KeyCode::Delete => self.user_input.delete(),
                                 KeyCode::Left => self.user_input.move_left(),
                                 KeyCode::Right => self.user_input.move_right(),
-                                KeyCode::Up => self.log_handle.move_up(),
-                                KeyCode::Down => self.log_handle.move_down(),
                                 KeyCode::Home => self.user_input.jump_to_first(),
                                 KeyCode::End => self.user_input.jump_to_last(),
-                                KeyCode::Tab => self.show_help_menu = !self.show_help_menu,
-                                KeyCode::Esc => return Ok(()),
                                 _ => {}
                             },
-                            _ => {}
+                            None => {}
                         }
                     }
+                    Event::Mouse(mouse) => {
+                        self.handle_mouse(mouse, &view, &action_options, &tx_client, &waker)?
+                    }
+                    _ => {}
                 }
             }
 
+            // The connection thread reconnects on its own, so this only
+            // keeps the user posted. Turns can't be taken while
+            // disconnected, and the server signals them again if it's
+            // still the user's turn after reconnecting.
+            if let Ok(progress) = rx_progress.try_recv() {
+                let record = match progress {
+                    Reconnecting::Retry {
+                        attempt,
+                        delay,
+                        error,
+                    } => {
+                        action_options.clear();
+                        turn_warnings.clear();
+                        self.end_turn_alert()?;
+                        let msg = format!(
+                            "{error}, reconnecting in {:.1}s (attempt {attempt})",
+                            delay.as_secs_f32()
+                        );
+                        Record::new(RecordKind::Error, msg)
+                    }
+                    Reconnecting::Done { dropped: 0 } => {
+                        Record::new(RecordKind::Alert, "reconnected".to_string())
+                    }
+                    Reconnecting::Done { dropped } => {
+                        let msg = format!(
+                            "reconnected, dropping {dropped} command(s) sent while disconnected"
+                        );
+                        Record::new(RecordKind::Alert, msg)
+                    }
+                    Reconnecting::Failed(error) => bail!("couldn't reconnect: {error}"),
+                };
+                self.log(record);
+            }
+
             if let Ok(msg) = rx_server.try_recv() {
                 match msg {
                     ServerMessage::Ack(msg) => {
+                        if let UserCommand::TakeAction(ref action) = msg.command {
+                            self.stats.record_action(&msg.username, action);
+                        }
                         if msg.username == self.username {
                             match msg.command {
-                                // Our action was acknowledged, so we don't need warnings anymore.
+                                // Our action was acknowledged, so we don't need warnings
+                                // or action buttons anymore.
                                 UserCommand::TakeAction(_) => {
+                                    action_options.clear();
                                     turn_warnings.clear();
+                                    self.end_turn_alert()?;
                                 }
                                 // Our action timed-out and so the server booted us; let's exit.
                                 UserCommand::Leave => return Ok(()),
                                 _ => {}
                             }
                         }
-                        let record = Record::new(RecordKind::Ack, msg.to_string());
-                        self.log_handle.push(record.into());
+                        let record =
+                            Record::new(RecordKind::Ack, msg.to_chips_string(view.chip_display));
+                        self.log(record);
+                    }
+                    ServerMessage::Chat { username, message } => {
+                        let line = Line::from(vec![
+                            format!("[{}] ", Utc::now().format("%H:%M:%S")).into(),
+                            format!("{username}: ").bold(),
+                            message.into(),
+                        ]);
+                        self.chat_handle.push(ListItem::new(line));
                     }
                     ServerMessage::ClientError(error) => {
                         let record = Record::new(RecordKind::Error, error.to_string());
-                        self.log_handle.push(record.into());
+                        self.log(record);
+                    }
+                    ServerMessage::GameView(new_view) => {
+                        self.stats.update_view(&new_view);
+                        self.update_turn_deadline(&new_view);
+                        view = *new_view;
+                    }
+                    ServerMessage::Hands(hands) => {
+                        self.hand_history_text = if hands.is_empty() {
+                            "no hands have been played at this table yet".to_string()
+                        } else {
+                            // Most recent hands first.
+                            hands
+                                .iter()
+                                .rev()
+                                .enumerate()
+                                .map(|(i, hand)| match i {
+                                    0 => format!("last hand, {hand}"),
+                                    i => format!("{} hands ago, {hand}", i + 1),
+                                })
+                                .collect::<Vec<_>>()
+                                .join("\n")
+                        };
+                        self.hand_history_scroll = 0;
+                        self.show_hand_history = true;
+                    }
+                    ServerMessage::Leaderboard(standings) => {
+                        if standings.is_empty() {
+                            let record = Record::new(
+                                RecordKind::Game,
+                                "no cash game hands have been played yet".to_string(),
+                            );
+                            self.log(record);
+                        }
+                        for (i, standing) in standings.iter().enumerate() {
+                            let record =
+                                Record::new(RecordKind::Game, format!("{}. {standing}", i + 1));
+                            self.log(record);
+                        }
+                    }
+                    // Pings are answered and sessions are kept by the
+                    // connection thread.
+                    ServerMessage::Ping | ServerMessage::Session(_) => {}
+                    ServerMessage::Rake(ledger) => {
+                        let record = Record::new(RecordKind::Game, ledger.to_string());
+                        self.log(record);
+                    }
+                    ServerMessage::Showdown(showdown) => {
+                        let record = Record::new(RecordKind::Showdown, showdown.to_string());
+                        self.log(record);
+                    }
+                    ServerMessage::ShowOrMuck => {
+                        let record = Record::new(RecordKind::Alert, msg.to_string());
+                        self.log(record);
                     }
-                    ServerMessage::GameView(new_view) => view = new_view,
                     ServerMessage::Status(msg) => {
                         let record = Record::new(RecordKind::Game, msg);
-                        self.log_handle.push(record.into());
+                        self.log(record);
+                    }
+                    ServerMessage::Tables(tables) => {
+                        for table in tables {
+                            let record = Record::new(RecordKind::Game, table.to_string());
+                            self.log(record);
+                        }
                     }
                     ServerMessage::TurnSignal(new_action_options) => {
                         action_options = new_action_options;
-                        turn_warnings.reset();
+                        // Servers that don't time turns in views use the
+                        // default timeout.
+                        let deadline = match self.turn_deadline {
+                            Some((_, deadline)) => deadline,
+                            None => Instant::now() + DEFAULT_TURN_TIMEOUT,
+                        };
+                        turn_warnings.reset(deadline);
                         let record = Record::new(RecordKind::Alert, "it's your turn!".to_string());
-                        self.log_handle.push(record.into());
+                        self.log(record);
+                        self.start_turn_alert()?;
                     }
                     ServerMessage::UserError(error) => {
                         let record = Record::new(RecordKind::Error, error.to_string());
-                        self.log_handle.push(record.into());
+                        self.log(record);
                     }
                 };
             }
@@ -625,24 +1398,36 @@ impl App {
             // Signal how much time is left to the user at specific intervals.
             if let Some(warning) = turn_warnings.check() {
                 let record = Record::new(RecordKind::Alert, format!("{warning:>2} second(s) left"));
-                self.log_handle.push(record.into());
+                self.log(record);
             }
         }
     }
 
-    fn draw(&mut self, view: &GameView, frame: &mut Frame) {
+    fn draw(&mut self, view: &GameView, action_options: &HashSet<Action>, frame: &mut Frame) {
+        // The price to call gets a line of its own above the input box
+        // while there's a call to make.
+        let price = price_to_call(view, action_options);
+        let price_height = price.is_some() as u16;
         let window = Layout::vertical([
             Constraint::Min(6),
+            Constraint::Length(price_height),
             Constraint::Length(3),
             Constraint::Length(1),
         ]);
-        let [top_area, user_input_area, help_area] = window.areas(frame.area());
-        let [view_area, log_area] =
-            Layout::vertical([Constraint::Percentage(55), Constraint::Percentage(45)])
-                .areas(top_area);
-        let [lobby_area, table_area] =
-            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
-                .areas(view_area);
+        let [top_area, price_area, user_input_area, help_area] = window.areas(frame.area());
+        let [view_area, bottom_area] = Layout::vertical([
+            Constraint::Percentage(self.table_height),
+            Constraint::Percentage(100 - self.table_height),
+        ])
+        .areas(top_area);
+        let [log_area, chat_area] =
+            Layout::horizontal([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .areas(bottom_area);
+        let [lobby_area, table_area] = Layout::horizontal([
+            Constraint::Percentage(self.lobby_width),
+            Constraint::Percentage(100 - self.lobby_width),
+        ])
+        .areas(view_area);
         let [spectator_area, waitlister_area] =
             Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
                 .areas(lobby_area);
@@ -651,11 +1436,14 @@ impl App {
         let mut spectators = Vec::from_iter(view.spectators.values());
         spectators.sort_unstable();
         let spectators = Table::new(
-            spectators.iter().map(|user| user_to_row(user)),
+            spectators
+                .iter()
+                .map(|user| user_to_row(user, view.chip_display)),
             [Constraint::Percentage(50), Constraint::Percentage(50)],
         )
         .block(
-            Block::bordered()
+            self.theme
+                .block()
                 .padding(Padding::uniform(1))
                 .title(" spectators  "),
         );
@@ -663,125 +1451,119 @@ impl App {
 
         // Render waitlisters area.
         let waitlisters = Table::new(
-            view.waitlist.iter().map(|user| user_to_row(user)),
+            view.waitlist.iter().enumerate().map(|(idx, user)| {
+                waitlister_to_row(
+                    idx + 1,
+                    user,
+                    view.seat_requests.get(&user.name),
+                    view.chip_display,
+                )
+            }),
             [Constraint::Percentage(50), Constraint::Percentage(50)],
         )
         .block(
-            Block::bordered()
+            self.theme
+                .block()
                 .padding(Padding::uniform(1))
                 .title(" waitlisters  "),
         );
         frame.render_widget(waitlisters, waitlister_area);
 
-        // Render table area.
-        let table = Table::new(
-            view.players.iter().enumerate().map(|(player_idx, player)| {
-                // Indicator if it's the player's move.
-                let move_repr = match view.next_action_idx {
-                    Some(next_action_idx) if player_idx == next_action_idx => "→",
-                    _ => " ",
-                };
-                let move_repr = Text::from(move_repr);
-
-                // Indicator for what blind each player pays.
-                let button_repr = if player_idx == view.big_blind_idx {
-                    "BB"
-                } else if player_idx == view.small_blind_idx {
-                    "SB"
-                } else {
-                    "  "
-                };
-                let button_repr = Text::from(button_repr);
-
-                // Username column.
-                let username_repr = player.user.name.clone();
-                let username_repr = Text::from(username_repr);
-
-                // Money column.
-                let money_repr = format!("${}", player.user.money);
-                let money_repr = Text::from(money_repr);
-
-                // State column.
-                let state_repr = player.state.to_string();
-                let state_repr = Text::from(state_repr);
-
-                // This is the final row representation for the table entry.
-                let mut row = vec![
-                    Cell::new(move_repr.alignment(Alignment::Center)),
-                    Cell::new(button_repr.alignment(Alignment::Left)),
-                    Cell::new(username_repr.alignment(Alignment::Left)),
-                    Cell::new(money_repr.alignment(Alignment::Right)),
-                    Cell::new(state_repr.alignment(Alignment::Center)),
-                ];
-
-                // Player cards styled according to suit.
-                for card_idx in 0..2 {
-                    let card_repr = match player.cards.get(card_idx) {
-                        Some(card) => Text::from(card_to_span(card)),
-                        None => Text::from("    "),
-                    };
-                    let card_cell = Cell::new(card_repr.alignment(Alignment::Right));
-                    row.push(card_cell);
-                }
+        // Render table area. The board and the user's own cards are drawn
+        // as card art above the players. The border flashes while it's the
+        // user's turn.
+        let flash = match self.turn_started {
+            Some(turn_started) if self.turn_alerts => {
+                let periods =
+                    turn_started.elapsed().as_millis() / TURN_ALERT_FLASH_PERIOD.as_millis();
+                periods.is_multiple_of(2)
+            }
+            _ => false,
+        };
+        let table_border_style = if flash {
+            Style::new().fg(self.theme.highlight).bold()
+        } else {
+            Style::new().fg(self.theme.border)
+        };
+        let table_block = table_block(view, table_border_style);
+        let [cards_area, players_area] =
+            Layout::vertical([Constraint::Length(CARDS_HEIGHT + 1), Constraint::Min(1)])
+                .areas(table_block.inner(table_area));
+        // Stats are drawn in their own column next to the players, with a
+        // header row the players skip so rows line up.
+        let [players_area, stats_area] =
+            Layout::horizontal([Constraint::Min(1), Constraint::Length(STATS_WIDTH)])
+                .areas(players_area);
+        let [timer_area, players_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(1)]).areas(players_area);
+        frame.render_widget(table_block, table_area);
 
-                // Player's highest subhand displayed.
-                let hand_repr = if player.cards.is_empty() {
-                    "  ".to_string()
-                } else {
-                    let mut cards = view.board.clone();
-                    cards.extend(player.cards.clone());
-                    functional::prepare_hand(&mut cards);
-                    let hand = functional::eval(&cards);
-                    if let Some(subhand) = hand.first() {
-                        format!("({})", subhand.rank)
-                    } else {
-                        "  ".to_string()
-                    }
-                };
-                let hand_repr = Text::from(hand_repr).alignment(Alignment::Right);
-                let hand_cell = Cell::new(hand_repr);
-                row.push(hand_cell);
+        let hand = view
+            .players
+            .iter()
+            .find(|p| p.user.name == self.username)
+            .map(|player| player.cards.as_slice());
+        render_cards(frame, cards_area, view, hand, &self.theme);
 
-                Row::new(row)
-            }),
-            [
-                Constraint::Max(3),
-                Constraint::Fill(1),
-                Constraint::Fill(2),
-                Constraint::Fill(2),
-                Constraint::Fill(2),
-                Constraint::Fill(1),
-                Constraint::Fill(1),
-                Constraint::Fill(1),
-            ],
+        let table = players_table(view, &self.theme);
+        frame.render_widget(table, players_area);
+
+        // Count down the acting player's turn above the players.
+        if let (Some((timeout, deadline)), Some(player)) = (
+            self.turn_deadline,
+            view.next_action_idx.and_then(|idx| view.players.get(idx)),
+        ) {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let ratio = if timeout.is_zero() {
+                0.0
+            } else {
+                (remaining.as_secs_f64() / timeout.as_secs_f64()).clamp(0.0, 1.0)
+            };
+            let color = if remaining <= TURN_TIMER_WARNING {
+                self.theme.alert
+            } else {
+                self.theme.highlight
+            };
+            let timer = LineGauge::default()
+                .filled_style(Style::new().fg(color))
+                .unfilled_style(Style::new().dim())
+                .label(format!("{} {:>2}s ", player.user.name, remaining.as_secs()))
+                .ratio(ratio);
+            frame.render_widget(timer, timer_area);
+        }
+
+        let stats = Table::new(
+            view.players
+                .iter()
+                .map(|player| stats_to_row(self.stats.get(&player.user.name))),
+            [Constraint::Length(4); 3],
         )
-        .block(
-            block::Block::bordered()
-                .padding(Padding::uniform(1))
-                .title(
-                    block::Title::from(board_to_vec_of_spans(view))
-                        .position(block::Position::Top)
-                        .alignment(Alignment::Left),
-                )
-                .title(
-                    block::Title::from(blinds_to_string(view))
-                        .position(block::Position::Bottom)
-                        .alignment(Alignment::Right),
-                )
-                .title(
-                    block::Title::from(pot_to_string(view))
-                        .position(block::Position::Bottom)
-                        .alignment(Alignment::Left),
-                ),
-        );
-        frame.render_widget(table, table_area);
+        .header(Row::new(["VPIP", " PFR", "  AF"]).dim());
+        frame.render_widget(stats, stats_area);
+
+        // Render clickable action buttons along the bottom of the table
+        // when it's the user's turn.
+        self.action_buttons.clear();
+        let mut button_x = table_area.x + 2;
+        let button_y = table_area.bottom().saturating_sub(2);
+        for (label, cmd) in action_options_to_buttons(view, action_options) {
+            let width = label.len() as u16;
+            if button_x + width > table_area.right().saturating_sub(2) {
+                break;
+            }
+            let button_area = Rect::new(button_x, button_y, width, 1);
+            frame.render_widget(Paragraph::new(label.bold().reversed()), button_area);
+            self.action_buttons.push((button_area, cmd));
+            button_x += width + 1;
+        }
 
         // Render log window.
         let log_records = self.log_handle.list_items.clone();
         let log_records = List::new(log_records)
             .direction(ListDirection::BottomToTop)
-            .block(block::Block::bordered().title(" history  "));
+            .block(self.theme.block().title(" history  "));
         frame.render_stateful_widget(log_records, log_area, &mut self.log_handle.list_state);
+        self.log_area = log_area;
 
         // Render log window scrollbar.
         frame.render_stateful_widget(
@@ -796,26 +1578,50 @@ impl App {
             &mut self.log_handle.scroll_state,
         );
 
+        // Render chat window.
+        let chat_messages = self.chat_handle.list_items.clone();
+        let chat_messages = List::new(chat_messages)
+            .direction(ListDirection::BottomToTop)
+            .block(self.theme.block().title(" chat  "));
+        frame.render_stateful_widget(chat_messages, chat_area, &mut self.chat_handle.list_state);
+
+        // Render the price to call.
+        if let Some(price) = price {
+            let price = Paragraph::new(price.fg(self.theme.highlight).bold());
+            frame.render_widget(price, price_area);
+        }
+
         // Render user input area.
         let username = self.username.clone();
         let addr = self.addr.clone();
+        let mut user_input_block = self
+            .theme
+            .block()
+            .title(format!(" {username}@{addr}  ").fg(self.theme.you));
+        if !self.completion_suggestions.is_empty() {
+            let suggestions = self.completion_suggestions.join(" | ");
+            user_input_block = user_input_block.title_bottom(format!(" {suggestions}  "));
+        }
         let user_input = Paragraph::new(self.user_input.value.as_str())
             .style(Style::default())
-            .block(block::Block::bordered().title(format!(" {username}@{addr}  ").light_green()));
+            .block(user_input_block);
         frame.render_widget(user_input, user_input_area);
-        frame.set_cursor_position(Position::new(
-            // Draw the cursor at the current position in the input field.
-            // This position is can be controlled via the left and right arrow key
-            user_input_area.x + self.user_input.char_idx as u16 + 1,
-            // Move one line down, from the border to the input line
-            user_input_area.y + 1,
-        ));
+        self.user_input_area = user_input_area;
+        if self.focus == Focus::Input {
+            frame.set_cursor_position(Position::new(
+                // Draw the cursor at the current position in the input field.
+                // This position is can be controlled via the left and right arrow key
+                user_input_area.x + self.user_input.char_idx as u16 + 1,
+                // Move one line down, from the border to the input line
+                user_input_area.y + 1,
+            ));
+        }
 
         // Render user input help message.
         let help_message = vec![
             "press ".into(),
             "Tab".bold(),
-            " to view help, press ".into(),
+            " to complete a command or view help, press ".into(),
             "Enter".bold(),
             " to record a command, or press ".into(),
             "Esc".bold(),
@@ -837,8 +1643,29 @@ impl App {
             // Render help text.
             let help_text = Paragraph::new(self.help_menu_text.clone())
                 .style(Style::default())
-                .block(block::Block::bordered().padding(Padding::uniform(1)));
+                .block(self.theme.block().padding(Padding::uniform(1)));
             frame.render_widget(help_text, help_menu_area);
         }
+
+        // Render the hand history window.
+        if self.show_hand_history {
+            let vertical = Layout::vertical([Constraint::Percentage(80)]).flex(Flex::Center);
+            let horizontal = Layout::horizontal([Constraint::Max(95)]).flex(Flex::Center);
+            let [hand_history_area] = vertical.areas(frame.area());
+            let [hand_history_area] = horizontal.areas(hand_history_area);
+            frame.render_widget(Clear, hand_history_area);
+
+            let hand_history = Paragraph::new(self.hand_history_text.clone())
+                .style(Style::default())
+                .scroll((self.hand_history_scroll, 0))
+                .block(
+                    self.theme
+                        .block()
+                        .padding(Padding::uniform(1))
+                        .title(" hand history  ")
+                        .title_bottom(" press Esc to close  "),
+                );
+            frame.render_widget(hand_history, hand_history_area);
+        }
     }
 }
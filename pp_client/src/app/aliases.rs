@@ -0,0 +1,86 @@
+use anyhow::{bail, Error};
+use std::{collections::BTreeMap, fmt, fs, path::Path, str::FromStr};
+
+/// Short names for commands so actions can be typed quickly under a turn
+/// timer.
+pub struct Aliases {
+    commands: BTreeMap<String, String>,
+}
+
+impl Default for Aliases {
+    fn default() -> Self {
+        let commands = [
+            ("a", "all-in"),
+            ("c", "call"),
+            ("f", "fold"),
+            ("k", "check"),
+            ("r", "raise"),
+        ]
+        .into_iter()
+        .map(|(alias, command)| (alias.to_string(), command.to_string()))
+        .collect();
+        Self { commands }
+    }
+}
+
+impl fmt::Display for Aliases {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.commands.keys().map(String::len).max().unwrap_or(0);
+        writeln!(f, "Aliases:")?;
+        for (alias, command) in &self.commands {
+            writeln!(f, "  {alias:width$}  {command}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Aliases {
+    type Err = Error;
+
+    /// Parse aliases with one `alias = command` line each, like
+    /// `pot = raise 100`. Blank lines and lines starting with `#` are
+    /// ignored. Aliases are added to the default ones, replacing the
+    /// defaults with the same name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut aliases = Aliases::default();
+        for (line_idx, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((alias, command)) = line.split_once('=') else {
+                bail!("line {}: expected `alias = command`", line_idx + 1);
+            };
+            let (alias, command) = (alias.trim(), command.trim());
+            if alias.is_empty() || alias.contains(char::is_whitespace) {
+                bail!("line {}: aliases must be one word", line_idx + 1);
+            } else if command.is_empty() {
+                bail!("line {}: {alias} is an alias for nothing", line_idx + 1);
+            }
+            aliases
+                .commands
+                .insert(alias.to_string(), command.to_string());
+        }
+        Ok(aliases)
+    }
+}
+
+impl Aliases {
+    /// Replace the alias the input starts with, if any, keeping the rest
+    /// of the input as arguments, so `r 50` becomes `raise 50`.
+    pub fn expand(&self, user_input: &str) -> String {
+        let (word, args) = match user_input.split_once(' ') {
+            Some((word, args)) => (word, Some(args)),
+            None => (user_input, None),
+        };
+        match (self.commands.get(word), args) {
+            (Some(command), Some(args)) => format!("{command} {args}"),
+            (Some(command), None) => command.clone(),
+            (None, _) => user_input.to_string(),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        fs::read_to_string(path)?.parse()
+    }
+}
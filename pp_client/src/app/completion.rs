@@ -0,0 +1,108 @@
+use clap::Command;
+use private_poker::entities::{Action, GameView};
+use std::collections::HashSet;
+
+/// What to replace the input box value with, and other values the user
+/// could've meant when the input is ambiguous.
+pub struct Completion {
+    pub value: String,
+    pub suggestions: Vec<String>,
+}
+
+impl Completion {
+    /// Complete as much of `prefix` as the candidates agree on, keeping
+    /// `head` (the words before the one being completed) as is.
+    fn from_candidates(head: &str, prefix: &str, mut candidates: Vec<String>) -> Option<Self> {
+        candidates.retain(|candidate| candidate.starts_with(prefix));
+        candidates.sort();
+        candidates.dedup();
+        match candidates.as_slice() {
+            [] => None,
+            [candidate] => Some(Self {
+                value: format!("{head}{candidate} "),
+                suggestions: Vec::new(),
+            }),
+            [first, rest @ ..] => {
+                let common_len = rest.iter().fold(first.len(), |len, candidate| {
+                    first
+                        .chars()
+                        .zip(candidate.chars())
+                        .take_while(|(a, b)| a == b)
+                        .map(|(a, _)| a.len_utf8())
+                        .sum::<usize>()
+                        .min(len)
+                });
+                Some(Self {
+                    value: format!("{head}{}", &first[..common_len]),
+                    suggestions: candidates,
+                })
+            }
+        }
+    }
+}
+
+/// Values that make sense for a command's argument given the state of
+/// the game.
+fn argument_candidates(
+    command: &Command,
+    view: &GameView,
+    action_options: &HashSet<Action>,
+) -> Vec<String> {
+    let Some(arg) = command.get_arguments().next() else {
+        return Vec::new();
+    };
+    let possible_values = arg.get_possible_values();
+    if !possible_values.is_empty() {
+        return possible_values
+            .iter()
+            .map(|value| value.get_name().to_string())
+            .collect();
+    }
+    match arg.get_id().as_str() {
+        "amount" => match action_options.get(&Action::Raise(0)) {
+            Some(Action::Raise(min_raise)) => {
+                let mut amounts = vec![min_raise.to_string()];
+                if view.pot.size > *min_raise {
+                    amounts.push(view.pot.size.to_string());
+                }
+                amounts.extend(["half", "pot"].map(String::from));
+                amounts
+            }
+            _ => Vec::new(),
+        },
+        "username" => view
+            .spectators
+            .keys()
+            .chain(view.waitlist.iter().map(|user| &user.name))
+            .chain(view.players.iter().map(|player| &player.user.name))
+            .map(ToString::to_string)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Complete the command or first argument being typed. Commands come from
+/// the subcommands in `commands`, and arguments come from their possible
+/// values or the game (raise amounts and usernames).
+pub fn complete(
+    commands: &Command,
+    input: &str,
+    view: &GameView,
+    action_options: &HashSet<Action>,
+) -> Option<Completion> {
+    match input.split_once(' ') {
+        None => {
+            let names = commands
+                .get_subcommands()
+                .map(|command| command.get_name().to_string())
+                .collect();
+            Completion::from_candidates("", input, names)
+        }
+        Some((name, arg)) if !arg.contains(' ') => {
+            let command = commands.find_subcommand(name)?;
+            let candidates = argument_candidates(command, view, action_options);
+            Completion::from_candidates(&format!("{name} "), arg, candidates)
+        }
+        Some(_) => None,
+    }
+}
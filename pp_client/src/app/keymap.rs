@@ -0,0 +1,219 @@
+use anyhow::{bail, Error};
+use ratatui::crossterm::event::{KeyCode, KeyModifiers};
+use std::{collections::HashMap, fs, path::Path, str::FromStr};
+
+/// Things a user can do with a single key press.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum KeyAction {
+    /// Complete the command being typed. The key that toggles help also
+    /// completes commands, so this is unbound by default.
+    Complete,
+    Exit,
+    /// Focus the log so keys that usually edit the input box can be
+    /// bound to scrolling. Unbound by default.
+    FocusLog,
+    /// Give the lobby more of the width and the table less.
+    GrowLobby,
+    /// Give the table and lobby more of the height and the log and chat
+    /// less.
+    GrowTable,
+    /// Recall the submitted command after the one being recalled.
+    NextCommand,
+    /// Recall the submitted command before the one being recalled.
+    PreviousCommand,
+    ScrollDown,
+    ScrollToBottom,
+    ScrollToTop,
+    ScrollUp,
+    ShrinkLobby,
+    ShrinkTable,
+    ToggleHelp,
+}
+
+impl FromStr for KeyAction {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let action = match s {
+            "complete" => KeyAction::Complete,
+            "exit" => KeyAction::Exit,
+            "focus_log" => KeyAction::FocusLog,
+            "grow_lobby" => KeyAction::GrowLobby,
+            "grow_table" => KeyAction::GrowTable,
+            "next_command" => KeyAction::NextCommand,
+            "previous_command" => KeyAction::PreviousCommand,
+            "scroll_down" => KeyAction::ScrollDown,
+            "scroll_to_bottom" => KeyAction::ScrollToBottom,
+            "scroll_to_top" => KeyAction::ScrollToTop,
+            "scroll_up" => KeyAction::ScrollUp,
+            "shrink_lobby" => KeyAction::ShrinkLobby,
+            "shrink_table" => KeyAction::ShrinkTable,
+            "toggle_help" => KeyAction::ToggleHelp,
+            _ => bail!("unknown key action: {s}"),
+        };
+        Ok(action)
+    }
+}
+
+/// A key and the modifiers held down with it.
+pub type Key = (KeyCode, KeyModifiers);
+
+/// Parse keys like `j`, `Up`, `ctrl+home`, or `PageDown`.
+fn parse_key(s: &str) -> Result<Key, Error> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut parts: Vec<&str> = s.split('+').collect();
+    let code = match parts.pop() {
+        // A trailing "+" means the plus key itself.
+        Some("") if s.ends_with('+') => {
+            parts.pop();
+            "+"
+        }
+        Some(code) => code,
+        None => bail!("empty key"),
+    };
+    for modifier in parts {
+        modifiers |= match modifier.to_lowercase().as_str() {
+            "alt" => KeyModifiers::ALT,
+            "ctrl" => KeyModifiers::CONTROL,
+            "shift" => KeyModifiers::SHIFT,
+            _ => bail!("unknown key modifier: {modifier}"),
+        };
+    }
+    let mut chars = code.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match code.to_lowercase().as_str() {
+            "backspace" => KeyCode::Backspace,
+            "delete" => KeyCode::Delete,
+            "down" => KeyCode::Down,
+            "end" => KeyCode::End,
+            "enter" => KeyCode::Enter,
+            "esc" => KeyCode::Esc,
+            "home" => KeyCode::Home,
+            "left" => KeyCode::Left,
+            "pagedown" => KeyCode::PageDown,
+            "pageup" => KeyCode::PageUp,
+            "right" => KeyCode::Right,
+            "space" => KeyCode::Char(' '),
+            "tab" => KeyCode::Tab,
+            "up" => KeyCode::Up,
+            f if f.starts_with('f') => match f[1..].parse() {
+                Ok(n) => KeyCode::F(n),
+                Err(_) => bail!("unknown key: {code}"),
+            },
+            _ => bail!("unknown key: {code}"),
+        },
+    };
+    Ok(normalize((code, modifiers)))
+}
+
+/// Shift is already part of a character (`J` rather than `shift+j`), and
+/// terminals disagree on whether they report it, so it's ignored for
+/// characters.
+fn normalize((code, modifiers): Key) -> Key {
+    match code {
+        KeyCode::Char(_) => (code, modifiers - KeyModifiers::SHIFT),
+        _ => (code, modifiers),
+    }
+}
+
+/// Keys that edit the input box when it's focused. Bindings to these keys
+/// only apply while the log is focused so users can still type.
+pub fn is_input_key((code, modifiers): Key) -> bool {
+    (modifiers - KeyModifiers::SHIFT).is_empty()
+        && matches!(
+            code,
+            KeyCode::Backspace
+                | KeyCode::Char(_)
+                | KeyCode::Delete
+                | KeyCode::End
+                | KeyCode::Enter
+                | KeyCode::Home
+                | KeyCode::Left
+                | KeyCode::Right
+        )
+}
+
+/// Maps keys to what they do.
+pub struct Keymap {
+    bindings: HashMap<Key, KeyAction>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = HashMap::from([
+            ((KeyCode::Esc, KeyModifiers::NONE), KeyAction::Exit),
+            (
+                (KeyCode::Down, KeyModifiers::CONTROL),
+                KeyAction::ScrollDown,
+            ),
+            ((KeyCode::Down, KeyModifiers::NONE), KeyAction::ScrollDown),
+            ((KeyCode::Down, KeyModifiers::ALT), KeyAction::NextCommand),
+            (
+                (KeyCode::End, KeyModifiers::CONTROL),
+                KeyAction::ScrollToBottom,
+            ),
+            (
+                (KeyCode::End, KeyModifiers::NONE),
+                KeyAction::ScrollToBottom,
+            ),
+            (
+                (KeyCode::Home, KeyModifiers::CONTROL),
+                KeyAction::ScrollToTop,
+            ),
+            ((KeyCode::Home, KeyModifiers::NONE), KeyAction::ScrollToTop),
+            ((KeyCode::Left, KeyModifiers::ALT), KeyAction::ShrinkLobby),
+            (
+                (KeyCode::PageDown, KeyModifiers::ALT),
+                KeyAction::ShrinkTable,
+            ),
+            ((KeyCode::PageUp, KeyModifiers::ALT), KeyAction::GrowTable),
+            ((KeyCode::Right, KeyModifiers::ALT), KeyAction::GrowLobby),
+            ((KeyCode::Up, KeyModifiers::CONTROL), KeyAction::ScrollUp),
+            ((KeyCode::Up, KeyModifiers::NONE), KeyAction::ScrollUp),
+            ((KeyCode::Up, KeyModifiers::ALT), KeyAction::PreviousCommand),
+            ((KeyCode::Tab, KeyModifiers::NONE), KeyAction::ToggleHelp),
+        ]);
+        Self { bindings }
+    }
+}
+
+impl FromStr for Keymap {
+    type Err = Error;
+
+    /// Parse a keymap with one `action = key` binding per line, like
+    /// `scroll_down = j`. Blank lines and lines starting with `#` are
+    /// ignored. Actions that are bound in the keymap lose their default
+    /// keys, and an action can be bound to several keys.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut keymap = Keymap::default();
+        let mut rebound = Vec::new();
+        for (line_idx, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((action, key)) = line.split_once('=') else {
+                bail!("line {}: expected `action = key`", line_idx + 1);
+            };
+            let action: KeyAction = action.trim().parse()?;
+            let key = parse_key(key.trim())?;
+            if !rebound.contains(&action) {
+                keymap.bindings.retain(|_, bound| *bound != action);
+                rebound.push(action);
+            }
+            keymap.bindings.insert(key, action);
+        }
+        Ok(keymap)
+    }
+}
+
+impl Keymap {
+    pub fn get(&self, key: Key) -> Option<KeyAction> {
+        self.bindings.get(&normalize(key)).copied()
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        fs::read_to_string(path)?.parse()
+    }
+}
@@ -0,0 +1,106 @@
+use private_poker::entities::{Action, GameView, Username};
+use std::collections::{HashMap, HashSet};
+
+/// Counts behind a player's HUD numbers.
+#[derive(Default)]
+pub struct PlayerStats {
+    /// Hands the player was dealt into.
+    hands: usize,
+    /// Hands where the player voluntarily put money in preflop.
+    vpip_hands: usize,
+    /// Hands where the player raised preflop.
+    pfr_hands: usize,
+    /// Raises and all-ins on any street.
+    aggressive_actions: usize,
+    calls: usize,
+}
+
+impl PlayerStats {
+    /// Voluntarily put money in pot, as a percentage of hands.
+    pub fn vpip(&self) -> Option<f32> {
+        percentage(self.vpip_hands, self.hands)
+    }
+
+    /// Preflop raise, as a percentage of hands.
+    pub fn pfr(&self) -> Option<f32> {
+        percentage(self.pfr_hands, self.hands)
+    }
+
+    /// Aggression factor, the ratio of raises to calls.
+    pub fn aggression(&self) -> Option<f32> {
+        if self.calls == 0 {
+            None
+        } else {
+            Some(self.aggressive_actions as f32 / self.calls as f32)
+        }
+    }
+}
+
+fn percentage(count: usize, total: usize) -> Option<f32> {
+    if total == 0 {
+        None
+    } else {
+        Some(100.0 * count as f32 / total as f32)
+    }
+}
+
+/// Tracks player statistics from the game views and action acks the
+/// client sees, so they only cover hands played while connected.
+#[derive(Default)]
+pub struct StatsTracker {
+    stats: HashMap<Username, PlayerStats>,
+    /// Whether a hand is being played, which is when there's money in
+    /// the pot.
+    in_hand: bool,
+    /// Whether the current hand is still preflop.
+    preflop: bool,
+    /// Players already counted towards VPIP and PFR this hand.
+    vpip_players: HashSet<Username>,
+    pfr_players: HashSet<Username>,
+}
+
+impl StatsTracker {
+    pub fn get(&self, username: &str) -> Option<&PlayerStats> {
+        self.stats.get(username)
+    }
+
+    /// Record an action acknowledged by the server.
+    pub fn record_action(&mut self, username: &str, action: &Action) {
+        if !self.in_hand {
+            return;
+        }
+        let stats = self.stats.entry(username.into()).or_default();
+        match action {
+            Action::Call(_) => stats.calls += 1,
+            Action::AllIn | Action::Raise(_) => stats.aggressive_actions += 1,
+            Action::Check | Action::Fold => return,
+        }
+        if self.preflop {
+            if self.vpip_players.insert(username.into()) {
+                stats.vpip_hands += 1;
+            }
+            if matches!(action, Action::AllIn | Action::Raise(_))
+                && self.pfr_players.insert(username.into())
+            {
+                stats.pfr_hands += 1;
+            }
+        }
+    }
+
+    /// Look for the start of a hand, counting the players that were dealt in.
+    pub fn update_view(&mut self, view: &GameView) {
+        let in_hand = view.pot.size > 0;
+        if in_hand && !self.in_hand {
+            for player in view.players.iter() {
+                self.stats
+                    .entry(player.user.name.clone())
+                    .or_default()
+                    .hands += 1;
+            }
+            self.vpip_players.clear();
+            self.pfr_players.clear();
+        }
+        self.in_hand = in_hand;
+        self.preflop = view.board.is_empty();
+    }
+}
@@ -0,0 +1,216 @@
+use private_poker::{
+    entities::{Card, GameView, PlayerState},
+    functional,
+};
+use ratatui::{
+    layout::{Alignment, Constraint, Flex, Layout, Rect},
+    style::{Style, Stylize},
+    text::Text,
+    widgets::{block, Block, Cell, Padding, Row, Table},
+    Frame,
+};
+
+use super::{
+    theme::Theme,
+    widgets::{card_to_span, CardArt},
+};
+
+/// Height of the cards drawn by [`render_cards`].
+pub const CARDS_HEIGHT: u16 = CardArt::HEIGHT;
+
+fn blinds_to_string(view: &GameView) -> String {
+    let chips = |amount| view.chip_display.chips(amount);
+    if let Some(ante) = view.bomb_pot {
+        format!(" bomb pot, ante: {}  ", chips(ante))
+    } else if view.ante > 0 && view.is_big_blind_ante {
+        format!(
+            " blinds: {}/{}, big blind ante: {}  ",
+            chips(view.big_blind),
+            chips(view.small_blind),
+            chips(view.ante)
+        )
+    } else if view.ante > 0 {
+        format!(
+            " blinds: {}/{}, ante: {}  ",
+            chips(view.big_blind),
+            chips(view.small_blind),
+            chips(view.ante)
+        )
+    } else {
+        format!(
+            " blinds: {}/{}  ",
+            chips(view.big_blind),
+            chips(view.small_blind)
+        )
+    }
+}
+
+fn pot_to_string(view: &GameView) -> String {
+    match view.jackpot {
+        Some(jackpot) => format!(
+            " pot: {}, jackpot: {}  ",
+            view.pot,
+            view.chip_display.chips(jackpot)
+        ),
+        None => format!(" pot: {}  ", view.pot),
+    }
+}
+
+fn tournament_to_string(view: &GameView) -> String {
+    match view.tournament {
+        Some(ref tournament) => format!(" {tournament}  "),
+        None => String::new(),
+    }
+}
+
+/// The table's border, titled with the pot, blinds, and tournament.
+pub fn table_block(view: &GameView, border_style: Style) -> Block<'static> {
+    Block::bordered()
+        .border_style(border_style)
+        .padding(Padding::uniform(1))
+        .title(
+            block::Title::from(tournament_to_string(view))
+                .position(block::Position::Top)
+                .alignment(Alignment::Right),
+        )
+        .title(
+            block::Title::from(blinds_to_string(view))
+                .position(block::Position::Bottom)
+                .alignment(Alignment::Right),
+        )
+        .title(
+            block::Title::from(pot_to_string(view))
+                .position(block::Position::Bottom)
+                .alignment(Alignment::Left),
+        )
+}
+
+/// Draw the board, and the second board if it was run twice, as card art
+/// along the left of the area, with the hand along the right.
+pub fn render_cards(
+    frame: &mut Frame,
+    area: Rect,
+    view: &GameView,
+    hand: Option<&[Card]>,
+    theme: &Theme,
+) {
+    let board = CardArt::new(&view.board, theme);
+    let board_width = board.width();
+    frame.render_widget(board, area);
+    if !view.second_board.is_empty() {
+        let second_board_area = Rect {
+            x: area.x + board_width + 2,
+            width: area.width.saturating_sub(board_width + 2),
+            ..area
+        };
+        frame.render_widget(CardArt::new(&view.second_board, theme), second_board_area);
+    }
+    if let Some(hand) = hand {
+        let hand = CardArt::new(hand, theme).border_style(Style::new().fg(theme.highlight).bold());
+        let [hand_area] = Layout::horizontal([Constraint::Length(hand.width())])
+            .flex(Flex::End)
+            .areas(area);
+        frame.render_widget(hand, hand_area);
+    }
+}
+
+/// A row for each player with their seat, position, money, state, cards,
+/// and best hand, with the acting player's row highlighted.
+pub fn players_table(view: &GameView, theme: &Theme) -> Table<'static> {
+    let rows: Vec<_> = view
+        .players
+        .iter()
+        .map(|player| {
+            // Indicator if it's the player's move.
+            let is_acting = view.action_on.as_ref() == Some(&player.user.name);
+            let move_repr = Text::from(if is_acting { "→" } else { " " });
+
+            // Seat and position columns.
+            let seat_repr = Text::from(player.seat_idx.to_string());
+            let position_repr = match player.position {
+                Some(position) => position.to_string(),
+                None => "  ".to_string(),
+            };
+            let position_repr = Text::from(position_repr);
+
+            // Username column.
+            let username_repr = player.user.name.to_string();
+            let username_repr = Text::from(username_repr);
+
+            // Money column.
+            let money_repr = view.chip_display.chips(player.user.money).to_string();
+            let money_repr = Text::from(money_repr);
+
+            // State column, showing what waiting players did this
+            // street so the hand so far can be followed at a glance.
+            let state_repr = match (
+                &player.state,
+                view.last_action_this_street(&player.user.name),
+            ) {
+                (PlayerState::Wait, Some(action)) => action.to_chips_string(view.chip_display),
+                (state, _) => state.to_string(),
+            };
+            let state_repr = Text::from(state_repr);
+
+            // This is the final row representation for the table entry.
+            let mut row = vec![
+                Cell::new(move_repr.alignment(Alignment::Center)),
+                Cell::new(seat_repr.alignment(Alignment::Right)),
+                Cell::new(position_repr.alignment(Alignment::Left)),
+                Cell::new(username_repr.alignment(Alignment::Left)),
+                Cell::new(money_repr.alignment(Alignment::Right)),
+                Cell::new(state_repr.alignment(Alignment::Center)),
+            ];
+
+            // Player cards styled according to suit.
+            for card_idx in 0..2 {
+                let card_repr = match player.cards.get(card_idx) {
+                    Some(card) => Text::from(card_to_span(card, theme)),
+                    None => Text::from("    "),
+                };
+                let card_cell = Cell::new(card_repr.alignment(Alignment::Right));
+                row.push(card_cell);
+            }
+
+            // Player's highest subhand displayed.
+            let hand_repr = if player.cards.is_empty() {
+                "  ".to_string()
+            } else {
+                let mut cards = view.board.clone();
+                cards.extend(player.cards.clone());
+                functional::prepare_hand(&mut cards);
+                let hand = functional::eval(&cards);
+                if let Some(subhand) = hand.first() {
+                    format!("({})", subhand.rank)
+                } else {
+                    "  ".to_string()
+                }
+            };
+            let hand_repr = Text::from(hand_repr).alignment(Alignment::Right);
+            let hand_cell = Cell::new(hand_repr);
+            row.push(hand_cell);
+
+            // The acting player's row stands out so everyone can see
+            // who the table is waiting on.
+            if is_acting {
+                Row::new(row).style(Style::new().fg(theme.highlight).bold())
+            } else {
+                Row::new(row)
+            }
+        })
+        .collect();
+    Table::new(
+        rows,
+        [
+            Constraint::Max(3),
+            Constraint::Max(2),
+            Constraint::Fill(1),
+            Constraint::Fill(2),
+            Constraint::Fill(2),
+            Constraint::Fill(2),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+        ],
+    )
+}
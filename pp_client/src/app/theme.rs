@@ -0,0 +1,151 @@
+use anyhow::{bail, Error};
+use ratatui::{
+    style::{Color, Style},
+    widgets::Block,
+};
+use std::{fs, path::Path, str::FromStr};
+
+/// Colors used throughout the TUI.
+#[derive(Clone, Debug)]
+pub struct Theme {
+    /// Record labels in the log.
+    pub ack: Color,
+    pub alert: Color,
+    pub error: Color,
+    pub game: Color,
+    pub you: Color,
+    /// Borders around every window and card.
+    pub border: Color,
+    /// The user's own cards, the acting player's row, and the table
+    /// border flashing on the user's turn.
+    pub highlight: Color,
+    /// Card values, colored by suit.
+    pub black_suit: Color,
+    pub red_suit: Color,
+    pub wild_suit: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    pub fn block(&self) -> Block<'static> {
+        Block::bordered().border_style(Style::new().fg(self.border))
+    }
+
+    pub fn dark() -> Self {
+        Self {
+            ack: Color::LightBlue,
+            alert: Color::LightMagenta,
+            error: Color::LightRed,
+            game: Color::LightYellow,
+            you: Color::LightGreen,
+            border: Color::Reset,
+            highlight: Color::LightYellow,
+            black_suit: Color::Reset,
+            red_suit: Color::LightRed,
+            wild_suit: Color::LightMagenta,
+        }
+    }
+
+    /// Get a built-in theme by name.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "solarized" => Some(Self::solarized()),
+            _ => None,
+        }
+    }
+
+    /// Darker colors that're readable on light backgrounds.
+    pub fn light() -> Self {
+        Self {
+            ack: Color::Blue,
+            alert: Color::Magenta,
+            error: Color::Red,
+            game: Color::Rgb(175, 135, 0),
+            you: Color::Green,
+            border: Color::Reset,
+            highlight: Color::Rgb(175, 135, 0),
+            black_suit: Color::Reset,
+            red_suit: Color::Red,
+            wild_suit: Color::Magenta,
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Ethan Schoonover's Solarized accent colors.
+    pub fn solarized() -> Self {
+        Self {
+            ack: Color::Rgb(0x26, 0x8b, 0xd2),
+            alert: Color::Rgb(0xd3, 0x36, 0x82),
+            error: Color::Rgb(0xdc, 0x32, 0x2f),
+            game: Color::Rgb(0xb5, 0x89, 0x00),
+            you: Color::Rgb(0x85, 0x99, 0x00),
+            border: Color::Rgb(0x58, 0x6e, 0x75),
+            highlight: Color::Rgb(0xcb, 0x4b, 0x16),
+            black_suit: Color::Reset,
+            red_suit: Color::Rgb(0xdc, 0x32, 0x2f),
+            wild_suit: Color::Rgb(0x6c, 0x71, 0xc4),
+        }
+    }
+}
+
+impl FromStr for Theme {
+    type Err = Error;
+
+    /// Parse a theme with one `name = color` line per color, like
+    /// `error = #dc322f` or `border = dark-gray`. Colors can also be
+    /// 256-color indices. A `base = THEME` line picks the built-in theme
+    /// that colors not in the file come from, defaulting to the dark theme.
+    /// Blank lines and lines starting with `#` are ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut colors = Vec::new();
+        let mut theme = Theme::default();
+        for (line_idx, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((name, value)) = line.split_once('=') else {
+                bail!("line {}: expected `name = color`", line_idx + 1);
+            };
+            let (name, value) = (name.trim(), value.trim());
+            if name == "base" {
+                theme = match Theme::from_name(value) {
+                    Some(theme) => theme,
+                    None => bail!("unknown base theme: {value}"),
+                };
+            } else {
+                let Ok(color) = value.parse::<Color>() else {
+                    bail!("line {}: invalid color: {value}", line_idx + 1);
+                };
+                colors.push((name, color));
+            }
+        }
+        for (name, color) in colors {
+            let field = match name {
+                "ack" => &mut theme.ack,
+                "alert" => &mut theme.alert,
+                "error" => &mut theme.error,
+                "game" => &mut theme.game,
+                "you" => &mut theme.you,
+                "border" => &mut theme.border,
+                "highlight" => &mut theme.highlight,
+                "black_suit" => &mut theme.black_suit,
+                "red_suit" => &mut theme.red_suit,
+                "wild_suit" => &mut theme.wild_suit,
+                _ => bail!("unknown theme color: {name}"),
+            };
+            *field = color;
+        }
+        Ok(theme)
+    }
+}
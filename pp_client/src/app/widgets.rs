@@ -1,11 +1,91 @@
 use ratatui::{
     self,
-    widgets::{ListItem, ListState, ScrollDirection, ScrollbarState},
+    buffer::Buffer,
+    layout::{Alignment, Rect},
+    style::{Style, Stylize},
+    text::Span,
+    widgets::{Block, ListItem, ListState, Paragraph, ScrollDirection, ScrollbarState, Widget},
 };
 
 use std::collections::VecDeque;
 
-use private_poker::constants::MAX_USER_INPUT_LENGTH;
+use private_poker::{
+    constants::MAX_CHAT_MESSAGE_LENGTH,
+    entities::{Card, Suit},
+};
+
+use super::theme::Theme;
+
+/// Long enough to fit a chat command and the longest chat message.
+pub const MAX_INPUT_LENGTH: usize = MAX_CHAT_MESSAGE_LENGTH + 5;
+/// Number of submitted inputs that can be recalled.
+pub const MAX_INPUT_HISTORY: usize = 100;
+
+/// A card's value and suit glyph, colored red or black by suit.
+pub fn card_to_span(card: &Card, theme: &Theme) -> Span<'static> {
+    let Card(value, suit) = card;
+    let value = match value {
+        1 | 14 => "A",
+        11 => "J",
+        12 => "Q",
+        13 => "K",
+        v => &v.to_string(),
+    };
+    match suit {
+        Suit::Club => format!("{value}♣").fg(theme.black_suit),
+        Suit::Diamond => format!("{value}♦").fg(theme.red_suit),
+        Suit::Heart => format!("{value}♥").fg(theme.red_suit),
+        Suit::Spade => format!("{value}♠").fg(theme.black_suit),
+        Suit::Wild => format!("{value}★").fg(theme.wild_suit),
+    }
+}
+
+/// Cards drawn side by side as boxes. Cards that don't fit in the area
+/// aren't drawn.
+pub struct CardArt<'a> {
+    cards: &'a [Card],
+    border_style: Style,
+    theme: &'a Theme,
+}
+
+impl<'a> CardArt<'a> {
+    pub const CARD_WIDTH: u16 = 5;
+    pub const HEIGHT: u16 = 3;
+
+    pub fn border_style(mut self, style: Style) -> Self {
+        self.border_style = style;
+        self
+    }
+
+    pub fn new(cards: &'a [Card], theme: &'a Theme) -> Self {
+        Self {
+            cards,
+            border_style: Style::new().fg(theme.border),
+            theme,
+        }
+    }
+
+    /// Width needed to draw all the cards.
+    pub fn width(&self) -> u16 {
+        (self.cards.len() as u16 * (Self::CARD_WIDTH + 1)).saturating_sub(1)
+    }
+}
+
+impl Widget for CardArt<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        for (card_idx, card) in self.cards.iter().enumerate() {
+            let x = area.x + card_idx as u16 * (Self::CARD_WIDTH + 1);
+            if x + Self::CARD_WIDTH > area.right() {
+                break;
+            }
+            let card_area = Rect::new(x, area.y, Self::CARD_WIDTH, Self::HEIGHT.min(area.height));
+            Paragraph::new(card_to_span(card, self.theme))
+                .alignment(Alignment::Center)
+                .block(Block::bordered().border_style(self.border_style))
+                .render(card_area, buf);
+        }
+    }
+}
 
 /// Manages terminal messages and the terminal view position.
 pub struct ScrollableList {
@@ -65,6 +145,11 @@ pub struct UserInput {
     pub char_idx: usize,
     /// Current value of the input box.
     pub value: String,
+    /// Submitted inputs, oldest first.
+    history: VecDeque<String>,
+    /// Which submitted input is being recalled, and what was in the input
+    /// box before recalling started.
+    recalling: Option<(usize, String)>,
 }
 
 impl UserInput {
@@ -117,9 +202,14 @@ impl UserInput {
         }
     }
 
+    /// Whether submitted inputs are being cycled through.
+    pub fn is_recalling(&self) -> bool {
+        self.recalling.is_some()
+    }
+
     pub fn input(&mut self, new_char: char) {
-        // Username length is about the same size as the largest allowed
-        if self.value.len() < MAX_USER_INPUT_LENGTH {
+        // Chat messages are the longest inputs
+        if self.value.len() < MAX_INPUT_LENGTH {
             let idx = self.byte_idx();
             self.value.insert(idx, new_char);
             self.move_right();
@@ -139,6 +229,12 @@ impl UserInput {
         self.char_idx = self.clamp_cursor(cursor_moved_left);
     }
 
+    /// Move the cursor to a character, or to the end of the input if
+    /// the character is past the end.
+    pub fn move_to(&mut self, char_idx: usize) {
+        self.char_idx = self.clamp_cursor(char_idx);
+    }
+
     pub fn move_right(&mut self) {
         let cursor_moved_right = self.char_idx.saturating_add(1);
         self.char_idx = self.clamp_cursor(cursor_moved_right);
@@ -148,13 +244,57 @@ impl UserInput {
         Self {
             char_idx: 0,
             value: String::new(),
+            history: VecDeque::with_capacity(MAX_INPUT_HISTORY),
+            recalling: None,
+        }
+    }
+
+    /// Replace the input with the submitted input after the one being
+    /// recalled, or with whatever was there before recalling started
+    /// once there aren't any newer inputs.
+    pub fn recall_next(&mut self) {
+        if let Some((idx, draft)) = self.recalling.take() {
+            if idx + 1 < self.history.len() {
+                self.value.clone_from(&self.history[idx + 1]);
+                self.recalling = Some((idx + 1, draft));
+            } else {
+                self.value = draft;
+            }
+            self.jump_to_last();
         }
     }
 
+    /// Replace the input with the submitted input before the one being
+    /// recalled, starting with the latest.
+    pub fn recall_previous(&mut self) {
+        let recalling = match self.recalling.take() {
+            Some((idx, draft)) => Some((idx.saturating_sub(1), draft)),
+            None => self
+                .history
+                .len()
+                .checked_sub(1)
+                .map(|idx| (idx, self.value.clone())),
+        };
+        if let Some((idx, _)) = recalling {
+            self.value.clone_from(&self.history[idx]);
+            self.jump_to_last();
+        }
+        self.recalling = recalling;
+    }
+
     pub fn submit(&mut self) -> String {
         let input = self.value.clone();
         self.char_idx = 0;
         self.value.clear();
+        self.recalling = None;
+        // Submitting the same thing over and over only needs to be
+        // recalled once.
+        if !input.is_empty() && self.history.back() != Some(&input) {
+            if self.history.len() == MAX_INPUT_HISTORY {
+                self.history.pop_front();
+            }
+            self.history.push_back(input.clone());
+        }
         input
     }
 }
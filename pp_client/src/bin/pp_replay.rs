@@ -0,0 +1,239 @@
+//! A read-only TUI for stepping through recorded hands.
+//!
+//! Servers started with `--hand_history PATH` record every hand they
+//! deal. The replayer loads those files and draws each hand the same way
+//! the client draws a table, one action at a time, so hands can be gone
+//! over after the game. Only cards that were shown at the end of a hand
+//! are known, so they're the only ones drawn.
+
+use anyhow::{bail, Error};
+use clap::{value_parser, Arg, Command};
+use pp_client::app::{players_table, render_cards, table_block, Theme, CARDS_HEIGHT};
+use private_poker::{
+    entities::{GameView, HandHistory},
+    persistence,
+    replay::hand_views,
+};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Style, Stylize},
+    text::{Line, Text},
+    widgets::Paragraph,
+    DefaultTerminal, Frame,
+};
+
+/// The hands being replayed and where the replay is in them.
+struct Replayer {
+    hands: Vec<HandHistory>,
+    hand_idx: usize,
+    views: Vec<GameView>,
+    view_idx: usize,
+    theme: Theme,
+}
+
+impl Replayer {
+    fn new(hands: Vec<HandHistory>, hand_idx: usize, theme: Theme) -> Self {
+        let views = hand_views(&hands[hand_idx]);
+        Self {
+            hands,
+            hand_idx,
+            views,
+            view_idx: 0,
+            theme,
+        }
+    }
+
+    /// Go to another hand, starting from its first action.
+    fn change_hand(&mut self, hand_idx: usize) {
+        if hand_idx < self.hands.len() && hand_idx != self.hand_idx {
+            self.hand_idx = hand_idx;
+            self.views = hand_views(&self.hands[hand_idx]);
+            self.view_idx = 0;
+        }
+    }
+
+    /// Lines describing what's happened in the hand up to the current
+    /// step, along with who won once the hand is over.
+    fn history(&self) -> Vec<Line<'static>> {
+        let hand = &self.hands[self.hand_idx];
+        let view = &self.views[self.view_idx];
+        let chips = |amount| hand.chip_display.chips(amount);
+        let mut lines = vec![Line::from(if hand.is_bomb_pot {
+            format!("bomb pot, everyone antes {}", chips(hand.ante))
+        } else if hand.is_small_blind_dead {
+            format!(
+                "the small blind is dead, {} posts the big blind",
+                hand.big_blind_username
+            )
+        } else {
+            format!(
+                "{} posts the small blind, {} posts the big blind",
+                hand.small_blind_username, hand.big_blind_username
+            )
+        })];
+        let mut street = None;
+        for hand_action in view.actions.iter() {
+            if street != Some(hand_action.street) {
+                street = Some(hand_action.street);
+                lines.push(Line::from(format!("{}:", hand_action.street)).bold());
+            }
+            lines.push(Line::from(format!(
+                "  {} {}",
+                hand_action.username,
+                hand_action.action.to_action_string(hand.chip_display)
+            )));
+        }
+        if view.action_on.is_none() {
+            match hand.showdown() {
+                Some(showdown) => {
+                    lines.extend(
+                        showdown
+                            .to_string()
+                            .lines()
+                            .map(|line| Line::from(line.to_string()).fg(self.theme.highlight)),
+                    );
+                }
+                None => {
+                    for pot in hand.pots.iter() {
+                        let pot = format!(
+                            "{} wins {}",
+                            pot.winners.join(", "),
+                            hand.chip_display.chips(pot.size)
+                        );
+                        lines.push(Line::from(pot).fg(self.theme.highlight));
+                    }
+                }
+            }
+        }
+        lines
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let view = &self.views[self.view_idx];
+        let window = Layout::vertical([
+            Constraint::Min(6),
+            Constraint::Percentage(35),
+            Constraint::Length(1),
+        ]);
+        let [table_area, history_area, help_area] = window.areas(frame.area());
+
+        let step = format!(
+            " hand {} of {}, step {} of {}  ",
+            self.hand_idx + 1,
+            self.hands.len(),
+            self.view_idx + 1,
+            self.views.len()
+        );
+        let table_block = table_block(view, Style::new().fg(self.theme.border)).title(step);
+        let [cards_area, players_area] =
+            Layout::vertical([Constraint::Length(CARDS_HEIGHT + 1), Constraint::Min(1)])
+                .areas(table_block.inner(table_area));
+        frame.render_widget(table_block, table_area);
+        render_cards(frame, cards_area, view, None, &self.theme);
+        frame.render_widget(players_table(view, &self.theme), players_area);
+
+        // Keep the latest of the history in view.
+        let history = self.history();
+        let height = history_area.height.saturating_sub(2) as usize;
+        let scroll = history.len().saturating_sub(height) as u16;
+        let history = Paragraph::new(Text::from(history))
+            .scroll((scroll, 0))
+            .block(self.theme.block().title(" history  "));
+        frame.render_widget(history, history_area);
+
+        let help_message = Line::from(vec![
+            "press ".into(),
+            "←/→".bold(),
+            " to step through actions, ".into(),
+            "↑/↓".bold(),
+            " to change hands, or ".into(),
+            "Esc".bold(),
+            " to exit".into(),
+        ]);
+        frame.render_widget(Paragraph::new(help_message), help_area);
+    }
+
+    fn run(mut self, mut terminal: DefaultTerminal) -> Result<(), Error> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let last_view_idx = self.views.len() - 1;
+            match key.code {
+                KeyCode::Esc | KeyCode::Char('q') => return Ok(()),
+                KeyCode::Right | KeyCode::Char('l') | KeyCode::Char(' ') => {
+                    self.view_idx = (self.view_idx + 1).min(last_view_idx);
+                }
+                KeyCode::Left | KeyCode::Char('h') => {
+                    self.view_idx = self.view_idx.saturating_sub(1);
+                }
+                KeyCode::Home => self.view_idx = 0,
+                KeyCode::End => self.view_idx = last_view_idx,
+                KeyCode::Down | KeyCode::Char('j') | KeyCode::PageDown => {
+                    self.change_hand(self.hand_idx + 1);
+                }
+                KeyCode::Up | KeyCode::Char('k') | KeyCode::PageUp => {
+                    self.change_hand(self.hand_idx.saturating_sub(1));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn main() -> Result<(), Error> {
+    let path = Arg::new("path")
+        .help("hand history file recorded by a server")
+        .required(true)
+        .value_name("PATH");
+
+    let hand = Arg::new("hand")
+        .help("hand to start at, counting from 1 for the oldest")
+        .default_value("1")
+        .long("hand")
+        .value_name("HAND")
+        .value_parser(value_parser!(usize));
+
+    let theme = Arg::new("theme")
+        .help("color theme; one of dark, light, or solarized, or a file of `name = color` lines")
+        .default_value("dark")
+        .long("theme")
+        .value_name("THEME|PATH");
+
+    let matches = Command::new("pp_replay")
+        .about("step through recorded poker hands")
+        .version("0.0.1")
+        .arg(path)
+        .arg(hand)
+        .arg(theme)
+        .get_matches();
+
+    let path = matches.get_one::<String>("path").expect("path is required");
+    let hands = persistence::read_hand_history(path)?;
+    if hands.is_empty() {
+        bail!("{path} doesn't have any hands");
+    }
+    let hand = *matches
+        .get_one::<usize>("hand")
+        .expect("hand has a default");
+    if hand == 0 || hand > hands.len() {
+        bail!("{path} only has hands 1 through {}", hands.len());
+    }
+    let theme = matches
+        .get_one::<String>("theme")
+        .expect("theme has a default");
+    let theme = match Theme::from_name(theme) {
+        Some(theme) => theme,
+        None => Theme::load(theme)?,
+    };
+
+    let terminal = ratatui::init();
+    let result = Replayer::new(hands, hand - 1, theme).run(terminal);
+    ratatui::restore();
+    result
+}
@@ -0,0 +1,305 @@
+//! Non-blocking networking shared by the TUI and headless modes.
+
+use anyhow::{bail, Error};
+use mio::{Events, Interest, Poll, Token, Waker};
+use private_poker::{
+    entities::Username,
+    net::{
+        client::ConnectOptions,
+        messages::{ClientError, ClientMessage, ServerMessage, UserCommand},
+        socket::{NonblockingSocket, Socket},
+        tls::Stream,
+        utils::{read_prefixed, write_prefixed},
+    },
+    Client,
+};
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+    time::Duration,
+};
+
+const SERVER: Token = Token(0);
+const WAKER: Token = Token(1);
+const POLL_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long to wait before the first attempt to reconnect.
+const MIN_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+/// Waits between attempts double up to this long.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// Channels to and from the server, and a waker to signal that a message
+/// was sent to the server.
+pub struct Connection {
+    pub tx_client: Sender<ClientMessage>,
+    pub rx_server: Receiver<ServerMessage>,
+    pub waker: Waker,
+}
+
+/// Progress connecting again after the connection drops.
+pub enum Reconnecting {
+    /// The connection dropped or the last attempt failed, so another
+    /// attempt is made after the delay.
+    Retry {
+        attempt: u32,
+        delay: Duration,
+        error: String,
+    },
+    /// Connected again. The view the server sent comes next on the
+    /// server channel. Commands sent while disconnected were meant for a
+    /// table that has moved on since, so they're dropped instead of sent.
+    Done { dropped: usize },
+    /// The server turned the user away for good, so there are no more
+    /// attempts.
+    Failed(String),
+}
+
+/// Where and how to connect again when the connection drops. The
+/// options should have the session from the last time the user
+/// connected, so the server gives the user their spot back.
+pub struct Reconnect {
+    pub addr: String,
+    pub options: ConnectOptions,
+    pub tx_progress: Sender<Reconnecting>,
+}
+
+impl Reconnect {
+    /// Connect as the user until it works, waiting twice as long after
+    /// each failed attempt. Returns nothing if the main thread hung up or
+    /// the server won't ever let the user back.
+    fn retry(
+        &mut self,
+        username: &Username,
+        mut error: Error,
+        rx_client: &Receiver<ClientMessage>,
+        tx_server: &Sender<ServerMessage>,
+    ) -> Option<Stream<Socket>> {
+        let mut attempt = 1;
+        let mut delay = MIN_RECONNECT_DELAY;
+        loop {
+            let retry = Reconnecting::Retry {
+                attempt,
+                delay,
+                error: error.to_string(),
+            };
+            self.tx_progress.send(retry).ok()?;
+            thread::sleep(delay);
+            match Client::connect_with_options(username, &self.addr, &self.options) {
+                Ok((client, view)) => {
+                    self.options.session = Some(client.session);
+                    let dropped = rx_client.try_iter().count();
+                    self.tx_progress.send(Reconnecting::Done { dropped }).ok()?;
+                    tx_server
+                        .send(ServerMessage::GameView(Box::new(view)))
+                        .ok()?;
+                    return Some(client.stream);
+                }
+                Err(new_error) => {
+                    if matches!(
+                        new_error.downcast_ref(),
+                        Some(
+                            ClientError::AuthenticationFailed
+                                | ClientError::Banned
+                                | ClientError::InvalidUsername(_)
+                        )
+                    ) {
+                        let _ = self
+                            .tx_progress
+                            .send(Reconnecting::Failed(new_error.to_string()));
+                        return None;
+                    }
+                    error = new_error;
+                }
+            }
+            attempt += 1;
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    }
+}
+
+impl Connection {
+    /// Spawn the thread that exchanges messages with the server. Pings
+    /// from the server are answered as the user without being passed on.
+    /// When the connection drops, the thread reconnects if it's told how
+    /// to. Otherwise, it exits and drops its end of the server channel.
+    pub fn spawn(
+        username: Username,
+        stream: Stream<Socket>,
+        mut reconnect: Option<Reconnect>,
+    ) -> Result<Self, Error> {
+        let (tx_client, rx_client): (Sender<ClientMessage>, Receiver<ClientMessage>) = channel();
+        let (tx_server, rx_server): (Sender<ServerMessage>, Receiver<ServerMessage>) = channel();
+
+        let mut poll = Poll::new()?;
+        let waker = Waker::new(poll.registry(), WAKER)?;
+
+        // This thread is where the actual client-server networking happens for
+        // non-blocking IO. Some non-blocking IO between client threads is also
+        // managed by this thread. The main thread sends client command messages
+        // to this thread; those messages are eventually written to the server.
+        thread::spawn(move || -> Result<(), Error> {
+            let mut stream = stream;
+            loop {
+                let Err(error) = exchange(&mut poll, stream, &username, &rx_client, &tx_server)
+                else {
+                    return Ok(());
+                };
+                let Some(ref mut reconnect) = reconnect else {
+                    return Err(error);
+                };
+                match reconnect.retry(&username, error, &rx_client, &tx_server) {
+                    Some(new_stream) => stream = new_stream,
+                    None => return Ok(()),
+                }
+            }
+        });
+
+        Ok(Self {
+            tx_client,
+            rx_server,
+            waker,
+        })
+    }
+}
+
+/// Exchange messages with the server until the connection drops, which
+/// is an error, or the main thread hangs up.
+fn exchange(
+    poll: &mut Poll,
+    stream: Stream<Socket>,
+    username: &Username,
+    rx_client: &Receiver<ClientMessage>,
+    tx_server: &Sender<ServerMessage>,
+) -> Result<(), Error> {
+    let mut events = Events::with_capacity(64);
+    let mut messages_to_write: VecDeque<ClientMessage> = VecDeque::new();
+    let mut stream = stream.into_nonblocking()?;
+    poll.registry()
+        .register(&mut stream, SERVER, Interest::READABLE)?;
+
+    loop {
+        if let Err(error) = poll.poll(&mut events, Some(POLL_TIMEOUT)) {
+            match error.kind() {
+                io::ErrorKind::Interrupted => continue,
+                _ => bail!(error),
+            }
+        }
+
+        for event in events.iter() {
+            match event.token() {
+                SERVER => {
+                    if event.is_writable() && !messages_to_write.is_empty() {
+                        while let Some(msg) = messages_to_write.pop_front() {
+                            if let Err(error) = write_prefixed::<
+                                ClientMessage,
+                                Stream<NonblockingSocket>,
+                            >(&mut stream, &msg)
+                            {
+                                match error.kind() {
+                                    // `write_prefixed` uses `write_all` under the hood, so we know
+                                    // that if any of these occur, then the connection was probably
+                                    // dropped at some point.
+                                    io::ErrorKind::BrokenPipe
+                                    | io::ErrorKind::ConnectionAborted
+                                    | io::ErrorKind::ConnectionReset
+                                    | io::ErrorKind::TimedOut
+                                    | io::ErrorKind::UnexpectedEof => {
+                                        bail!("connection dropped");
+                                    }
+                                    // Would block "errors" are the OS's way of saying that the
+                                    // connection is not actually ready to perform this I/O operation.
+                                    io::ErrorKind::WouldBlock => {
+                                        // The message couldn't be sent, so we need to push it back
+                                        // onto the queue so we don't accidentally forget about it.
+                                        messages_to_write.push_front(msg);
+                                    }
+                                    // Retry writing in the case that the full message couldn't
+                                    // be written. This should be infrequent.
+                                    io::ErrorKind::WriteZero => {
+                                        messages_to_write.push_front(msg);
+                                        continue;
+                                    }
+                                    // Other errors we'll consider fatal.
+                                    _ => bail!(error),
+                                }
+                                poll.registry().reregister(
+                                    &mut stream,
+                                    SERVER,
+                                    Interest::READABLE,
+                                )?;
+                                break;
+                            }
+                        }
+                    }
+
+                    // Encrypted data can be left over when the socket can't
+                    // take all of it at once, so keep sending it.
+                    if event.is_writable() && stream.wants_write() {
+                        let _ = stream.flush();
+                    }
+
+                    if event.is_readable() {
+                        // We can (maybe) read from the connection.
+                        loop {
+                            match read_prefixed::<ServerMessage, Stream<NonblockingSocket>>(
+                                &mut stream,
+                            ) {
+                                Ok(ServerMessage::Ping) => {
+                                    messages_to_write.push_back(ClientMessage {
+                                        username: username.clone(),
+                                        command: UserCommand::Pong,
+                                    });
+                                    poll.registry().reregister(
+                                        &mut stream,
+                                        SERVER,
+                                        Interest::READABLE | Interest::WRITABLE,
+                                    )?;
+                                }
+                                Ok(msg) => {
+                                    if tx_server.send(msg).is_err() {
+                                        return Ok(());
+                                    }
+                                }
+                                Err(error) => {
+                                    match error.kind() {
+                                        // `read_prefixed` uses `read_exact` under the hood, so we know
+                                        // that an Eof error means the connection was dropped.
+                                        io::ErrorKind::BrokenPipe
+                                        | io::ErrorKind::ConnectionAborted
+                                        | io::ErrorKind::ConnectionReset
+                                        | io::ErrorKind::InvalidData
+                                        | io::ErrorKind::TimedOut
+                                        | io::ErrorKind::UnexpectedEof => {
+                                            bail!("connection dropped");
+                                        }
+                                        // Would block "errors" are the OS's way of saying that the
+                                        // connection is not actually ready to perform this I/O operation.
+                                        io::ErrorKind::WouldBlock => {}
+                                        // Other errors we'll consider fatal.
+                                        _ => {
+                                            bail!(error)
+                                        }
+                                    }
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                }
+                WAKER => {
+                    while let Ok(msg) = rx_client.try_recv() {
+                        messages_to_write.push_back(msg);
+                        poll.registry().reregister(
+                            &mut stream,
+                            SERVER,
+                            Interest::READABLE | Interest::WRITABLE,
+                        )?;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
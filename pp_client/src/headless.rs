@@ -0,0 +1,92 @@
+//! A client mode without the TUI, for driving the client from scripts.
+//!
+//! Each line read from stdin is a JSON [`UserCommand`], like
+//! `"StartGame"` or `{"TakeAction": {"Raise": 2000}}`, with amounts in
+//! cents. Each message from the server is printed to stdout as a line of
+//! JSON, starting with the view the server sent when the client
+//! connected. Lines that can't be parsed are reported on stderr and
+//! skipped. Once stdin is closed, the client exits after the server goes
+//! quiet for a second so replies to the last commands aren't lost. It
+//! also exits when the connection drops.
+
+use anyhow::{bail, Error};
+use private_poker::{
+    entities::{GameView, Username},
+    net::{
+        messages::{ClientMessage, ServerMessage, UserCommand},
+        socket::Socket,
+        tls::Stream,
+    },
+};
+use std::{
+    io::{self, BufRead, Write},
+    sync::{mpsc::RecvTimeoutError, Arc},
+    thread,
+    time::Duration,
+};
+
+use crate::{app::POLL_TIMEOUT, connection::Connection};
+
+/// How long the server has to be quiet after stdin closes before exiting.
+const QUIET_TIMEOUT: Duration = Duration::from_secs(1);
+
+fn print_message(stdout: &mut impl Write, msg: &ServerMessage) -> Result<(), Error> {
+    serde_json::to_writer(&mut *stdout, msg)?;
+    writeln!(stdout)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+pub fn run(username: Username, stream: Stream<Socket>, view: GameView) -> Result<(), Error> {
+    let Connection {
+        tx_client,
+        rx_server,
+        waker,
+    } = Connection::spawn(username.clone(), stream, None)?;
+
+    let mut stdout = io::stdout().lock();
+    print_message(&mut stdout, &ServerMessage::GameView(Box::new(view)))?;
+
+    // Reading stdin blocks, so it gets its own thread that forwards
+    // commands to the connection thread. The waker outlives the stdin
+    // thread since wakes are lost if the waker is dropped before the
+    // connection thread sees them.
+    let waker = Arc::new(waker);
+    let stdin_waker = waker.clone();
+    let stdin_thread = thread::spawn(move || -> Result<(), Error> {
+        for line in io::stdin().lock().lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<UserCommand>(&line) {
+                Ok(command) => {
+                    let msg = ClientMessage {
+                        username: username.clone(),
+                        command,
+                    };
+                    tx_client.send(msg)?;
+                    stdin_waker.wake()?;
+                }
+                Err(error) => eprintln!("invalid command {line:?}: {error}"),
+            }
+        }
+        Ok(())
+    });
+
+    while !stdin_thread.is_finished() {
+        match rx_server.recv_timeout(POLL_TIMEOUT) {
+            Ok(msg) => print_message(&mut stdout, &msg)?,
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+    match stdin_thread.join() {
+        Ok(result) => result?,
+        Err(_) => bail!("stdin thread panicked"),
+    }
+    while let Ok(msg) = rx_server.recv_timeout(QUIET_TIMEOUT) {
+        print_message(&mut stdout, &msg)?;
+    }
+    Ok(())
+}
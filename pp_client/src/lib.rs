@@ -0,0 +1,6 @@
+//! The client's TUI and connection handling, shared by the client and
+//! the hand history replayer.
+
+pub mod app;
+pub mod connection;
+pub mod headless;
@@ -2,17 +2,33 @@
 //!
 //! The client runs with two threads; one for managing the TCP connection
 //! and exchanging data, and another for updating the TUI at fixed
-//! intervals and in response to user commands.
+//! intervals and in response to user commands. With `--headless`, the
+//! TUI is replaced by JSON commands read from stdin and JSON server
+//! messages written to stdout so the client can be scripted. With
+//! `--bot`, one of the library's baseline agents plays instead.
 //!
 //! [`ratatui`]: https://github.com/ratatui/ratatui
 
 use anyhow::Error;
 
-use clap::{Arg, Command};
-use private_poker::{constants::MAX_USER_INPUT_LENGTH, entities::Username, Client};
-
-mod app;
-use app::App;
+use clap::{builder::PossibleValuesParser, value_parser, Arg, ArgAction, Command};
+use pp_client::{
+    app::{self, Aliases, App, Keymap, Theme},
+    headless,
+};
+use private_poker::{
+    agent::{self, BASELINE_AGENTS},
+    constants::MAX_USER_INPUT_LENGTH,
+    messages::UserState,
+    net::client::{ConnectOptions, Transport},
+    tls::ClientTls,
+    Client,
+};
+use ratatui::crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+};
+use std::{fs::OpenOptions, io};
 
 fn main() -> Result<(), Error> {
     let username = Arg::new("username")
@@ -20,21 +36,131 @@ fn main() -> Result<(), Error> {
         .value_name("USERNAME");
 
     let addr = Arg::new("connect")
-        .help("server socket connection address")
+        .help("server socket connection address, or unix:PATH for a Unix socket")
         .default_value("127.0.0.1:6969")
         .long("connect")
         .value_name("IP:PORT");
 
+    let password = Arg::new("password")
+        .help("password for servers that require one")
+        .long("password")
+        .value_name("PASSWORD");
+
+    let ca_cert = Arg::new("ca_cert")
+        .help("encrypt the connection with TLS, trusting servers signed by these PEM certificates")
+        .long("ca_cert")
+        .value_name("PATH")
+        .conflicts_with("known_certs");
+
+    let known_certs = Arg::new("known_certs")
+        .help("encrypt the connection with TLS, trusting a server's first certificate and saving it here")
+        .long("known_certs")
+        .value_name("PATH");
+
+    let compress = Arg::new("compress")
+        .help("ask the server to compress big messages")
+        .long("compress")
+        .action(ArgAction::SetTrue);
+
+    let transport = Arg::new("transport")
+        .help("protocol to connect over; QUIC also needs --ca_cert or --known_certs")
+        .default_value("tcp")
+        .long("transport")
+        .value_name("TRANSPORT")
+        .value_parser(PossibleValuesParser::new(["tcp", "quic"]));
+
+    let aliases = Arg::new("aliases")
+        .help("add command aliases with a file of `alias = command` lines, like `pot = raise 100`")
+        .long("aliases")
+        .value_name("PATH");
+
+    let keymap = Arg::new("keymap")
+        .help("rebind keys with a file of `action = key` lines, like `scroll_down = j`")
+        .long("keymap")
+        .value_name("PATH");
+
+    let pane_percentage = value_parser!(u16)
+        .range(i64::from(app::MIN_PANE_PERCENTAGE)..=i64::from(app::MAX_PANE_PERCENTAGE));
+    let table_height = Arg::new("table_height")
+        .help("percentage of the height for the table and lobby, with the log and chat getting the rest")
+        .default_value("55")
+        .long("table_height")
+        .alias("table-height")
+        .value_name("PERCENT")
+        .value_parser(pane_percentage);
+
+    let lobby_width = Arg::new("lobby_width")
+        .help("percentage of the width for the lobby, with the table getting the rest")
+        .default_value("40")
+        .long("lobby_width")
+        .alias("lobby-width")
+        .value_name("PERCENT")
+        .value_parser(pane_percentage);
+
+    let theme = Arg::new("theme")
+        .help("color theme; one of dark, light, or solarized, or a file of `name = color` lines")
+        .default_value("dark")
+        .long("theme")
+        .value_name("THEME|PATH");
+
+    let headless = Arg::new("headless")
+        .help("run without the TUI, reading JSON commands from stdin and writing JSON server messages to stdout")
+        .long("headless")
+        .action(ArgAction::SetTrue);
+
+    let bot = Arg::new("bot")
+        .help("let a baseline agent play from the waitlist instead of running the TUI")
+        .long("bot")
+        .value_name("AGENT")
+        .value_parser(PossibleValuesParser::new(BASELINE_AGENTS))
+        .conflicts_with("headless");
+
+    let log_file = Arg::new("log_file")
+        .help("append every message shown in the log to this file as lines of JSON")
+        .long("log_file")
+        .alias("log-file")
+        .value_name("PATH")
+        .conflicts_with_all(["bot", "headless"]);
+
+    let no_turn_alerts = Arg::new("no_turn_alerts")
+        .help("don't ring the bell or flash the table when it's your turn")
+        .long("no_turn_alerts")
+        .action(ArgAction::SetTrue);
+
     let matches = Command::new("pp_client")
         .about("connect to a centralized poker server over TCP")
         .version("0.0.1")
         .arg(addr)
         .arg(username)
+        .arg(password)
+        .arg(aliases)
+        .arg(ca_cert)
+        .arg(known_certs)
+        .arg(bot)
+        .arg(compress)
+        .arg(headless)
+        .arg(keymap)
+        .arg(lobby_width)
+        .arg(log_file)
+        .arg(no_turn_alerts)
+        .arg(table_height)
+        .arg(theme)
+        .arg(transport)
         .get_matches();
 
-    let mut username = match matches.get_one::<Username>("username") {
+    let mut username = match matches.get_one::<String>("username") {
         Some(username) => username.to_string(),
-        None => whoami::username(),
+        // Account names can have spaces, which usernames can't.
+        None => whoami::username()
+            .chars()
+            .map(|c| {
+                if c.is_whitespace() || c.is_control() {
+                    '_'
+                } else {
+                    c
+                }
+            })
+            .collect(),
     };
     username.truncate(MAX_USER_INPUT_LENGTH);
 
@@ -42,18 +168,94 @@ fn main() -> Result<(), Error> {
         .get_one::<String>("connect")
         .expect("server address is an invalid string");
 
+    // Load the aliases, keymap, and theme and open the log file before
+    // connecting so a bad file doesn't leave a connection hanging.
+    let aliases = match matches.get_one::<String>("aliases") {
+        Some(path) => Aliases::load(path)?,
+        None => Aliases::default(),
+    };
+    let keymap = match matches.get_one::<String>("keymap") {
+        Some(path) => Keymap::load(path)?,
+        None => Keymap::default(),
+    };
+    let theme = matches
+        .get_one::<String>("theme")
+        .expect("theme has a default");
+    let theme = match Theme::from_name(theme) {
+        Some(theme) => theme,
+        None => Theme::load(theme)?,
+    };
+    let log_file = match matches.get_one::<String>("log_file") {
+        Some(path) => Some(OpenOptions::new().create(true).append(true).open(path)?),
+        None => None,
+    };
+
     // Doesn't make sense to use the complexity of non-blocking IO
     // for connecting to the poker server, so we try to connect with
     // a blocking client instead. The client is then eventually
     // converted to a non-blocking stream and polled for events.
-    let (client, view) = Client::connect(&username, addr)?;
+    let tls = match (
+        matches.get_one::<String>("ca_cert"),
+        matches.get_one::<String>("known_certs"),
+    ) {
+        (Some(path), _) => Some(ClientTls::Verify(path.into())),
+        (_, Some(path)) => Some(ClientTls::TrustOnFirstUse(path.into())),
+        _ => None,
+    };
+    let transport = match matches.get_one::<String>("transport").map(String::as_str) {
+        #[cfg(feature = "quic")]
+        Some("quic") => Transport::Quic,
+        #[cfg(not(feature = "quic"))]
+        Some("quic") => {
+            anyhow::bail!("QUIC connections need pp_client built with `--features quic`")
+        }
+        _ => Transport::Tcp,
+    };
+    let options = ConnectOptions {
+        password: matches.get_one::<String>("password").cloned(),
+        session: None,
+        tls,
+        compress: matches.get_flag("compress"),
+        transport,
+    };
+    let (mut client, view) = Client::connect_with_options(&username, addr, &options)?;
+    if let Some(name) = matches.get_one::<String>("bot") {
+        let mut agent = agent::from_name(name).expect("agent names are validated");
+        client.change_state(UserState::Play)?;
+        return client.play(view, &mut *agent);
+    }
     let Client {
         username,
         addr,
+        session,
         stream,
     } = client;
+    if matches.get_flag("headless") {
+        return headless::run(username, stream, view);
+    }
     let terminal = ratatui::init();
-    let app_result = App::new(username, addr).run(stream, view, terminal);
+    execute!(io::stdout(), EnableMouseCapture)?;
+    let mut app = App::new(username, addr, keymap)
+        .aliases(aliases)
+        .connect_options(ConnectOptions {
+            session: Some(session),
+            ..options
+        })
+        .pane_sizes(
+            *matches
+                .get_one::<u16>("table_height")
+                .expect("table height has a default"),
+            *matches
+                .get_one::<u16>("lobby_width")
+                .expect("lobby width has a default"),
+        )
+        .theme(theme)
+        .turn_alerts(!matches.get_flag("no_turn_alerts"));
+    if let Some(file) = log_file {
+        app = app.session_log(file);
+    }
+    let app_result = app.run(stream, view, terminal);
+    execute!(io::stdout(), DisableMouseCapture)?;
     ratatui::restore();
     app_result
 }
@@ -0,0 +1,124 @@
+//! Running a server from its console with `pp_server ctl`.
+//!
+//! Servers started with `--console ADDR` answer requests at that
+//! address, so hosts can list users, kick and ban them, pause tables,
+//! change blinds, and dump the server's state without joining it.
+
+use anyhow::{bail, Error};
+use clap::{value_parser, Arg, ArgMatches, Command};
+use private_poker::{
+    control::{self, ControlRequest, ControlResponse},
+    entities::{Dollars, Username},
+    messages::{AdminOp, TableId},
+};
+
+fn username() -> Arg {
+    Arg::new("username").required(true).value_name("USERNAME")
+}
+
+fn table_id() -> Arg {
+    Arg::new("table_id")
+        .required(true)
+        .value_name("TABLE")
+        .value_parser(value_parser!(TableId))
+}
+
+fn usd(id: &'static str) -> Arg {
+    Arg::new(id)
+        .required(true)
+        .value_name("USD")
+        .value_parser(value_parser!(Dollars))
+}
+
+pub fn command() -> Command {
+    let console_addr = Arg::new("console")
+        .help("server console address, or unix:PATH for a Unix socket")
+        .long("console")
+        .value_name("ADDR")
+        .required(true);
+
+    Command::new("ctl")
+        .about("run a server from its console")
+        .arg(console_addr)
+        .subcommand_required(true)
+        .subcommand(Command::new("users").about("list the users at each table"))
+        .subcommand(
+            Command::new("kick")
+                .about("disconnect a user")
+                .arg(username()),
+        )
+        .subcommand(
+            Command::new("ban")
+                .about("disconnect a user and keep them from coming back")
+                .arg(username()),
+        )
+        .subcommand(
+            Command::new("fold")
+                .about("force a user to fold")
+                .arg(username()),
+        )
+        .subcommand(
+            Command::new("pause")
+                .about("stop a table from starting new hands")
+                .arg(table_id()),
+        )
+        .subcommand(
+            Command::new("resume")
+                .about("let a paused table start new hands again")
+                .arg(table_id()),
+        )
+        .subcommand(
+            Command::new("blinds")
+                .about("change a table's minimum blinds starting next hand")
+                .arg(table_id())
+                .arg(usd("small_blind"))
+                .arg(usd("big_blind")),
+        )
+        .subcommand(Command::new("rake").about("show the rake collected so far"))
+        .subcommand(Command::new("dump").about("dump every table's settings and users as JSON"))
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Error> {
+    let addr = matches
+        .get_one::<String>("console")
+        .expect("console address is required")
+        .parse()?;
+    let username = |matches: &ArgMatches| {
+        let username = matches
+            .get_one::<String>("username")
+            .expect("username is required");
+        Username::from(username.as_str())
+    };
+    let table_id = |matches: &ArgMatches| {
+        *matches
+            .get_one::<TableId>("table_id")
+            .expect("table is required")
+    };
+    let request = match matches.subcommand() {
+        Some(("users", _)) => ControlRequest::Users,
+        Some(("kick", matches)) => ControlRequest::Admin(AdminOp::Kick(username(matches))),
+        Some(("ban", matches)) => ControlRequest::Admin(AdminOp::Ban(username(matches))),
+        Some(("fold", matches)) => ControlRequest::Admin(AdminOp::ForceFold(username(matches))),
+        Some(("pause", matches)) => ControlRequest::Admin(AdminOp::Pause(table_id(matches))),
+        Some(("resume", matches)) => ControlRequest::Admin(AdminOp::Resume(table_id(matches))),
+        Some(("blinds", matches)) => ControlRequest::Admin(AdminOp::SetBlinds {
+            table_id: table_id(matches),
+            small_blind: matches
+                .get_one::<Dollars>("small_blind")
+                .expect("small blind is required")
+                .0,
+            big_blind: matches
+                .get_one::<Dollars>("big_blind")
+                .expect("big blind is required")
+                .0,
+        }),
+        Some(("rake", _)) => ControlRequest::Admin(AdminOp::Rake),
+        Some(("dump", _)) => ControlRequest::Dump,
+        _ => unreachable!("a subcommand is required"),
+    };
+    match control::request(&addr, &request)? {
+        ControlResponse::Error(error) => bail!(error),
+        response => println!("{response}"),
+    }
+    Ok(())
+}
@@ -0,0 +1,90 @@
+//! Checking a running server with `pp_server healthcheck`.
+//!
+//! The check connects as a throwaway user, waits for the server to
+//! acknowledge the user and send them a game view, and then leaves. It
+//! exits with 0 if the server made the whole round trip in time and 1
+//! otherwise, which is what container liveness probes expect.
+
+use anyhow::{bail, Error};
+use clap::{value_parser, Arg, ArgMatches, Command};
+use private_poker::{net::client::ConnectOptions, tls::ClientTls, Client};
+use std::{process, sync::mpsc, thread, time::Duration};
+
+pub fn command() -> Command {
+    let addr = Arg::new("addr")
+        .help("server address, or unix:PATH for a Unix socket")
+        .default_value("127.0.0.1:6969")
+        .long("addr")
+        .value_name("IP:PORT");
+
+    let username = Arg::new("username")
+        .help("user to connect as; defaults to one named after the process")
+        .long("username")
+        .value_name("USERNAME");
+
+    let password = Arg::new("password")
+        .help("password for servers that require one")
+        .long("password")
+        .value_name("PASSWORD");
+
+    let ca_cert = Arg::new("ca_cert")
+        .help("connect with TLS, trusting servers signed by these PEM certificates")
+        .long("ca_cert")
+        .value_name("PATH");
+
+    let timeout = Arg::new("timeout")
+        .help("seconds to wait for the server before failing")
+        .default_value("5")
+        .long("timeout")
+        .value_name("SECONDS")
+        .value_parser(value_parser!(u64));
+
+    Command::new("healthcheck")
+        .about("check that a server accepts users, exiting with 1 if it doesn't")
+        .arg(addr)
+        .arg(username)
+        .arg(password)
+        .arg(ca_cert)
+        .arg(timeout)
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), Error> {
+    let addr = matches
+        .get_one::<String>("addr")
+        .expect("server address has a default")
+        .clone();
+    let username = match matches.get_one::<String>("username") {
+        Some(username) => username.clone(),
+        None => format!("health{}", process::id()),
+    };
+    let options = ConnectOptions {
+        password: matches.get_one::<String>("password").cloned(),
+        tls: matches
+            .get_one::<String>("ca_cert")
+            .map(|path| ClientTls::Verify(path.into())),
+        ..Default::default()
+    };
+    let timeout = Duration::from_secs(
+        *matches
+            .get_one::<u64>("timeout")
+            .expect("timeout has a default"),
+    );
+
+    // The client's own timeouts are longer than probes usually wait, so
+    // the check runs on its own thread and is abandoned if it's late.
+    let (tx, rx) = mpsc::channel();
+    let server_addr = addr.clone();
+    thread::spawn(move || {
+        let result = Client::connect_with_options(&username, &server_addr, &options)
+            .and_then(|(mut client, _)| client.leave());
+        let _ = tx.send(result);
+    });
+    match rx.recv_timeout(timeout) {
+        Ok(Ok(())) => {
+            println!("{addr} is healthy");
+            Ok(())
+        }
+        Ok(Err(error)) => bail!("{addr} is unhealthy: {error}"),
+        Err(_) => bail!("{addr} didn't respond within {timeout:?}"),
+    }
+}
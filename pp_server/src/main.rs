@@ -3,69 +3,571 @@
 //! The server runs with two threads; one for managing TCP connections
 //! and exchanging data, and another for updating the poker game state
 //! at fixed intervals and in response to user commands.
+//!
+//! Accepting QUIC connections with `--quic` needs the `quic` feature,
+//! and runs the networking on tokio instead of its own thread.
+//!
+//! Sending SIGINT, SIGTERM, or SIGQUIT, or pressing Ctrl-C on Windows,
+//! shuts the server down once the hands being played finish. Doing it
+//! again exits immediately. On Unix, sending SIGHUP reloads the
+//! `--settings` file without dropping anyone.
+//!
+//! Starting the server with `--console ADDR` lets `pp_server ctl` list
+//! users, kick and ban them, pause tables, and change blinds without
+//! joining the server. `pp_server healthcheck` connects as a throwaway
+//! user and exits with 1 if the server doesn't let them in, for liveness
+//! probes.
+//!
+//! On Linux, the server can be supervised by systemd. It listens on
+//! sockets passed with socket activation instead of binding its own, and
+//! reports readiness and watchdog pings to `Type=notify` services.
 
-use anyhow::Error;
-use clap::{value_parser, Arg, Command};
-use log::info;
+use anyhow::{bail, Error};
+use clap::{value_parser, Arg, ArgAction, Command};
 use private_poker::{
-    entities::Usd,
-    server::{self, PokerConfig},
-    GameSettings, DEFAULT_MAX_USERS, MAX_PLAYERS,
+    auth::Credentials,
+    entities::{ChipDisplay, Dollars, Rank, Username},
+    persistence::{FileStore, SqliteStore},
+    server::{self, LiveSettings, PokerConfig, DEFAULT_IO_WORKERS, DEFAULT_MAX_TABLES},
+    tls, BombPotSettings, GameSettings, JackpotSettings, RakeSettings, TournamentSettings,
+    DEFAULT_MAX_USERS, MAX_PLAYERS,
 };
-#[cfg(target_os = "linux")]
+use std::time::Duration;
+use tracing::info;
+use tracing_subscriber::EnvFilter;
+#[cfg(unix)]
 use {
-    signal_hook::{
-        consts::{SIGINT, SIGQUIT, SIGTERM},
-        iterator::Signals,
-    },
-    std::{process, thread},
+    signal_hook::{consts::SIGHUP, iterator::Signals},
+    std::thread,
+    tracing::error,
 };
 
+mod ctl;
+mod healthcheck;
+mod signals;
+#[cfg(target_os = "linux")]
+mod systemd;
+
 fn main() -> Result<(), Error> {
     let addr = Arg::new("bind")
-        .help("server socket bind address")
+        .help("server socket bind address, or unix:PATH for a Unix socket (repeatable)")
         .default_value("127.0.0.1:6969")
         .long("bind")
+        .value_name("IP:PORT")
+        .action(ArgAction::Append);
+
+    let websocket_addr = Arg::new("websocket")
+        .help("also accept WebSocket connections at this address")
+        .long("websocket")
+        .value_name("IP:PORT");
+
+    let status_addr = Arg::new("status")
+        .help("serve the server's status as JSON at http://IP:PORT/status")
+        .long("status")
         .value_name("IP:PORT");
 
+    let quic_addr = Arg::new("quic")
+        .help("also accept QUIC connections at this address (needs a certificate)")
+        .long("quic")
+        .value_name("IP:PORT")
+        .requires("cert");
+
+    let proxy_protocol = Arg::new("proxy_protocol")
+        .help("expect connections to start with a PROXY protocol header, as sent by load balancers")
+        .long("proxy_protocol")
+        .action(ArgAction::SetTrue);
+
+    let log_format = Arg::new("log_format")
+        .help("log as human-readable text or as JSON lines for log aggregators")
+        .default_value("text")
+        .long("log_format")
+        .alias("log-format")
+        .value_name("FORMAT")
+        .value_parser(["text", "json"]);
+
     let buy_in = Arg::new("buy_in")
         .help("new user starting money")
         .default_value("200")
         .long("buy_in")
         .value_name("USD")
-        .value_parser(value_parser!(Usd));
+        .value_parser(value_parser!(Dollars));
+
+    let small_blind = Arg::new("small_blind")
+        .help("minimum small blind (defaults to half the big blind)")
+        .long("small_blind")
+        .alias("small-blind")
+        .value_name("USD")
+        .value_parser(value_parser!(Dollars));
+
+    let big_blind = Arg::new("big_blind")
+        .help("minimum big blind (defaults to 5% of the buy-in)")
+        .long("big_blind")
+        .alias("big-blind")
+        .value_name("USD")
+        .value_parser(value_parser!(Dollars));
+
+    let ante = Arg::new("ante")
+        .help("ante collected from every player each hand")
+        .default_value("0")
+        .long("ante")
+        .value_name("USD")
+        .value_parser(value_parser!(Dollars));
+
+    let big_blind_ante = Arg::new("big_blind_ante")
+        .help("collect the ante from the big blind for the whole table")
+        .long("big_blind_ante")
+        .alias("big-blind-ante")
+        .action(ArgAction::SetTrue);
+
+    let max_rebuys = Arg::new("max_rebuys")
+        .help("times each user can buy more money at a table")
+        .default_value("3")
+        .long("max_rebuys")
+        .alias("max-rebuys")
+        .value_name("REBUYS")
+        .value_parser(value_parser!(usize));
+
+    let min_buy_in = Arg::new("min_buy_in")
+        .help("least money users need to take a seat")
+        .default_value("0")
+        .long("min_buy_in")
+        .alias("min-buy-in")
+        .value_name("USD")
+        .value_parser(value_parser!(Dollars));
+
+    let max_buy_in = Arg::new("max_buy_in")
+        .help("most money users can buy up to (defaults to the buy-in)")
+        .long("max_buy_in")
+        .alias("max-buy-in")
+        .value_name("USD")
+        .value_parser(value_parser!(Dollars));
+
+    let rake = Arg::new("rake")
+        .help("percentage of each pot taken as rake")
+        .long("rake")
+        .value_name("PERCENT")
+        .value_parser(value_parser!(u8).range(0..=100));
+
+    let rake_cap = Arg::new("rake_cap")
+        .help("most rake taken from a single hand")
+        .long("rake_cap")
+        .alias("rake-cap")
+        .value_name("USD")
+        .value_parser(value_parser!(Dollars))
+        .requires("rake");
+
+    let no_flop_no_drop = Arg::new("no_flop_no_drop")
+        .help("don't rake hands that end before the flop")
+        .long("no_flop_no_drop")
+        .alias("no-flop-no-drop")
+        .action(ArgAction::SetTrue)
+        .requires("rake");
+
+    let jackpot_drop = Arg::new("jackpot_drop")
+        .help("money dropped from each hand into a jackpot paid out when quads or better lose")
+        .long("jackpot_drop")
+        .alias("jackpot-drop")
+        .value_name("USD")
+        .value_parser(value_parser!(Dollars));
+
+    let bomb_pot_ante = Arg::new("bomb_pot_ante")
+        .help("ante every player posts for a bomb pot, which players can vote for")
+        .long("bomb_pot_ante")
+        .alias("bomb-pot-ante")
+        .value_name("USD")
+        .value_parser(value_parser!(Dollars));
+
+    let bomb_pot_every = Arg::new("bomb_pot_every")
+        .help("deal a bomb pot every this many hands")
+        .long("bomb_pot_every")
+        .alias("bomb-pot-every")
+        .value_name("HANDS")
+        .value_parser(value_parser!(usize))
+        .requires("bomb_pot_ante");
+
+    let tournament = Arg::new("tournament")
+        .help("play a tournament with escalating blinds instead of a cash game")
+        .long("tournament")
+        .action(ArgAction::SetTrue);
+
+    let bounty = Arg::new("bounty")
+        .help("make the tournament a knockout, paying this bounty for each player knocked out")
+        .long("bounty")
+        .value_name("USD")
+        .value_parser(value_parser!(Dollars))
+        .requires("tournament");
+
+    let late_registration = Arg::new("late_registration")
+        .help("blind levels players can register late or re-enter the tournament for")
+        .long("late_registration")
+        .alias("late-registration")
+        .value_name("LEVELS")
+        .value_parser(value_parser!(usize))
+        .requires("tournament");
+
+    let chip_display = Arg::new("chip_display")
+        .help("how amounts are shown (chips by default for tournaments, usd otherwise)")
+        .long("chip_display")
+        .alias("chip-display")
+        .value_name("DISPLAY")
+        .value_parser(["chips", "play-money", "usd"]);
+
+    let level_duration = Arg::new("level_duration")
+        .help("tournament blind level duration")
+        .default_value("600")
+        .long("level_duration")
+        .value_name("SECONDS")
+        .value_parser(value_parser!(u64));
+
+    let turn_timeout = Arg::new("turn_timeout")
+        .help("how long players have to act before they're forced to fold")
+        .default_value("30")
+        .long("turn_timeout")
+        .alias("turn-timeout")
+        .value_name("SECONDS")
+        .value_parser(value_parser!(u64));
+
+    let reconnect_timeout = Arg::new("reconnect_timeout")
+        .help("how long disconnected users keep their seat (0 removes them right away)")
+        .default_value("60")
+        .long("reconnect_timeout")
+        .value_name("SECONDS")
+        .value_parser(value_parser!(u64));
+
+    let runout_delay = Arg::new("runout_delay")
+        .help("how long each street is shown when the board is run out with no more betting")
+        .default_value("2")
+        .long("runout_delay")
+        .alias("runout-delay")
+        .value_name("SECONDS")
+        .value_parser(value_parser!(u64));
+
+    let heartbeat = Arg::new("heartbeat")
+        .help("how often clients are pinged to detect dead connections (0 disables pings)")
+        .default_value("5")
+        .long("heartbeat")
+        .value_name("SECONDS")
+        .value_parser(value_parser!(u64));
+
+    let idle_timeout = Arg::new("idle_timeout")
+        .help("how long users can go without a command before they spectate, then get disconnected after as long again (0 disables)")
+        .default_value("0")
+        .long("idle_timeout")
+        .value_name("SECONDS")
+        .value_parser(value_parser!(u64));
+
+    let max_tables = Arg::new("max_tables")
+        .help("maximum number of tables the server can host (defaults to 8)")
+        .long("max_tables")
+        .value_name("TABLES")
+        .value_parser(value_parser!(usize));
+
+    let io_workers = Arg::new("io_workers")
+        .help("number of threads reading from and writing to connections (defaults to 4)")
+        .long("io_workers")
+        .value_name("THREADS")
+        .value_parser(value_parser!(usize));
+
+    let state = Arg::new("state")
+        .help("file to save users' money and the leaderboard to so they survive restarts")
+        .long("state")
+        .value_name("PATH");
+
+    let state_db = Arg::new("state_db")
+        .help("SQLite database to save users' money and the leaderboard to instead of a file")
+        .long("state_db")
+        .value_name("PATH")
+        .conflicts_with("state");
+
+    let hand_history = Arg::new("hand_history")
+        .help("file to append a record of every finished hand to")
+        .long("hand_history")
+        .value_name("PATH");
+
+    let settings = Arg::new("settings")
+        .help("JSON file of settings to reload on SIGHUP, like {\"turn_timeout\": 30, \"max_spectators\": 10}")
+        .long("settings")
+        .value_name("PATH");
+
+    let console_addr = Arg::new("console")
+        .help("answer `pp_server ctl` at this loopback address, or unix:PATH for a Unix socket")
+        .long("console")
+        .value_name("ADDR");
+
+    let passwords = Arg::new("passwords")
+        .help("file to save users' passwords to; users must log in with a password if set")
+        .long("passwords")
+        .value_name("PATH");
+
+    let admin = Arg::new("admin")
+        .help("user that can kick, ban, and force other users to fold (repeatable)")
+        .long("admin")
+        .value_name("USERNAME")
+        .action(ArgAction::Append);
+
+    let cert = Arg::new("cert")
+        .help("PEM certificate chain for encrypting connections with TLS")
+        .long("cert")
+        .value_name("PATH")
+        .requires("key");
+
+    let key = Arg::new("key")
+        .help("PEM private key for the TLS certificate")
+        .long("key")
+        .value_name("PATH")
+        .requires("cert");
 
     let matches = Command::new("pp_server")
         .about("host a centralized poker server over TCP")
         .version("0.0.1")
         .arg(addr)
+        .arg(websocket_addr)
+        .arg(status_addr)
+        .arg(quic_addr)
+        .arg(proxy_protocol)
+        .arg(log_format)
         .arg(buy_in)
+        .arg(small_blind)
+        .arg(big_blind)
+        .arg(ante)
+        .arg(big_blind_ante)
+        .arg(min_buy_in)
+        .arg(max_buy_in)
+        .arg(max_rebuys)
+        .arg(rake)
+        .arg(rake_cap)
+        .arg(no_flop_no_drop)
+        .arg(jackpot_drop)
+        .arg(bomb_pot_ante)
+        .arg(bomb_pot_every)
+        .arg(tournament)
+        .arg(bounty)
+        .arg(late_registration)
+        .arg(chip_display)
+        .arg(level_duration)
+        .arg(turn_timeout)
+        .arg(reconnect_timeout)
+        .arg(runout_delay)
+        .arg(heartbeat)
+        .arg(idle_timeout)
+        .arg(max_tables)
+        .arg(io_workers)
+        .arg(state)
+        .arg(state_db)
+        .arg(hand_history)
+        .arg(settings)
+        .arg(console_addr)
+        .arg(passwords)
+        .arg(admin)
+        .arg(cert)
+        .arg(key)
+        .subcommand(ctl::command())
+        .subcommand(healthcheck::command())
         .get_matches();
+    match matches.subcommand() {
+        Some(("ctl", matches)) => return ctl::run(matches),
+        Some(("healthcheck", matches)) => return healthcheck::run(matches),
+        _ => {}
+    }
 
-    let addr = matches
-        .get_one::<String>("bind")
-        .expect("server address is an invalid string");
-    let buy_in = matches
-        .get_one::<Usd>("buy_in")
-        .expect("buy-in is an invalid integer");
-
-    let game_settings = GameSettings::new(MAX_PLAYERS, DEFAULT_MAX_USERS, *buy_in);
-    let config: PokerConfig = game_settings.into();
+    let addrs: Vec<String> = matches
+        .get_many::<String>("bind")
+        .expect("server address has a default")
+        .cloned()
+        .collect();
+    let Dollars(buy_in) = *matches
+        .get_one::<Dollars>("buy_in")
+        .expect("buy-in is an invalid amount");
+    let big_blind = matches
+        .get_one::<Dollars>("big_blind")
+        .map_or(buy_in / 20, |Dollars(big_blind)| *big_blind);
+    let small_blind = matches
+        .get_one::<Dollars>("small_blind")
+        .map_or(big_blind / 2, |Dollars(small_blind)| *small_blind);
+    let Dollars(ante) = *matches
+        .get_one::<Dollars>("ante")
+        .expect("ante is an invalid amount");
+    let max_rebuys = matches
+        .get_one::<usize>("max_rebuys")
+        .expect("max rebuys is an invalid integer");
+    let Dollars(min_buy_in) = *matches
+        .get_one::<Dollars>("min_buy_in")
+        .expect("min buy-in is an invalid amount");
+    let max_buy_in = matches
+        .get_one::<Dollars>("max_buy_in")
+        .map_or(buy_in, |Dollars(max_buy_in)| *max_buy_in);
+    let level_duration = matches
+        .get_one::<u64>("level_duration")
+        .expect("level duration is an invalid integer");
+    let turn_timeout = matches
+        .get_one::<u64>("turn_timeout")
+        .expect("turn timeout is an invalid integer");
+    let reconnect_timeout = matches
+        .get_one::<u64>("reconnect_timeout")
+        .expect("reconnect timeout is an invalid integer");
+    let runout_delay = matches
+        .get_one::<u64>("runout_delay")
+        .expect("run-out delay is an invalid integer");
+    let heartbeat = matches
+        .get_one::<u64>("heartbeat")
+        .expect("heartbeat is an invalid integer");
+    let idle_timeout = matches
+        .get_one::<u64>("idle_timeout")
+        .expect("idle timeout is an invalid integer");
+    let max_tables = matches
+        .get_one::<usize>("max_tables")
+        .copied()
+        .unwrap_or(DEFAULT_MAX_TABLES);
+    let io_workers = matches
+        .get_one::<usize>("io_workers")
+        .copied()
+        .unwrap_or(DEFAULT_IO_WORKERS);
 
-    // Catching signals for exit.
-    #[cfg(target_os = "linux")]
+    let mut game_settings = GameSettings::new(MAX_PLAYERS, DEFAULT_MAX_USERS, buy_in)
+        .with_blinds(small_blind, big_blind)
+        .with_buy_in_bounds(min_buy_in, max_buy_in)
+        .with_rebuys(*max_rebuys)
+        .with_turn_timeout(Duration::from_secs(*turn_timeout));
+    game_settings = if matches.get_flag("big_blind_ante") {
+        game_settings.with_big_blind_ante(ante)
+    } else {
+        game_settings.with_ante(ante)
+    };
+    if let Some(Dollars(drop)) = matches.get_one::<Dollars>("jackpot_drop") {
+        game_settings = game_settings.with_jackpot(JackpotSettings::new(*drop, Rank::FourOfAKind));
+    }
+    if let Some(Dollars(ante)) = matches.get_one::<Dollars>("bomb_pot_ante") {
+        let every = matches.get_one::<usize>("bomb_pot_every").copied();
+        game_settings = game_settings.with_bomb_pot(BombPotSettings::new(*ante, every));
+    }
+    if matches.get_flag("tournament") {
+        let level_duration = Duration::from_secs(*level_duration);
+        let mut tournament = TournamentSettings::new(level_duration);
+        if let Some(Dollars(bounty)) = matches.get_one::<Dollars>("bounty") {
+            tournament = tournament.with_bounty(*bounty);
+        }
+        if let Some(levels) = matches.get_one::<usize>("late_registration") {
+            tournament = tournament.with_late_registration(*levels);
+        }
+        game_settings = game_settings.with_tournament(tournament);
+    }
+    let chip_display = match matches
+        .get_one::<String>("chip_display")
+        .map(String::as_str)
     {
-        let mut signals = Signals::new([SIGINT, SIGTERM, SIGQUIT])?;
+        Some("chips") => ChipDisplay::Chips,
+        Some("play-money") => ChipDisplay::PlayMoney,
+        Some(_) => ChipDisplay::Usd,
+        None if game_settings.tournament.is_some() => ChipDisplay::Chips,
+        None => ChipDisplay::Usd,
+    };
+    game_settings = game_settings.with_chip_display(chip_display);
+    game_settings.validate()?;
+    let mut config: PokerConfig = game_settings.into();
+    config.max_tables = max_tables;
+    config.io_workers = io_workers;
+    config.server_timeouts.reconnect = Duration::from_secs(*reconnect_timeout);
+    config.server_timeouts.runout = Duration::from_secs(*runout_delay);
+    config.server_timeouts.heartbeat = Duration::from_secs(*heartbeat);
+    config.server_timeouts.idle = Duration::from_secs(*idle_timeout);
+    if let Some(path) = matches.get_one::<String>("state") {
+        config.store = Some(Box::new(FileStore::new(path)));
+    } else if let Some(path) = matches.get_one::<String>("state_db") {
+        config.store = Some(Box::new(SqliteStore::open(path)?));
+    }
+    config.rake = matches.get_one::<u8>("rake").map(|percent| {
+        let cap = matches
+            .get_one::<Dollars>("rake_cap")
+            .map(|Dollars(cap)| *cap);
+        RakeSettings::new(*percent, cap, matches.get_flag("no_flop_no_drop"))
+    });
+    config.hand_history = matches.get_one::<String>("hand_history").map(Into::into);
+    config.websocket_addr = matches.get_one::<String>("websocket").cloned();
+    config.status_addr = matches.get_one::<String>("status").cloned();
+    config.console_addr = matches.get_one::<String>("console").cloned();
+    config.quic_addr = matches.get_one::<String>("quic").cloned();
+    config.proxy_protocol = matches.get_flag("proxy_protocol");
+    if let Some(admins) = matches.get_many::<String>("admin") {
+        config.admins = admins
+            .map(|admin| Username::new(admin.as_str()))
+            .collect::<Result<_, _>>()?;
+    }
+    if let Some(path) = matches.get_one::<String>("passwords") {
+        config.credentials = Some(Credentials::load(path)?);
+    }
+    // Sockets passed by systemd replace the addresses to bind to.
+    #[cfg(target_os = "linux")]
+    let addrs = match systemd::listen_addrs()? {
+        inherited_addrs if inherited_addrs.is_empty() => addrs,
+        inherited_addrs => inherited_addrs,
+    };
+    #[cfg(target_os = "linux")]
+    if let Some(supervisor) = systemd::Systemd::new() {
+        config.supervisor = Some(Box::new(supervisor));
+    }
+    let settings = matches.get_one::<String>("settings").cloned();
+    if let Some(ref path) = settings {
+        config.live_settings.set(LiveSettings::load(path)?);
+    }
+    if let (Some(cert), Some(key)) = (
+        matches.get_one::<String>("cert"),
+        matches.get_one::<String>("key"),
+    ) {
+        config.tls = Some(tls::server_config(cert, key)?);
+    }
+
+    signals::handle_shutdown(config.shutdown.clone())?;
+    #[cfg(unix)]
+    if let Some(path) = settings {
+        let live_settings = config.live_settings.clone();
+        let mut signals = Signals::new([SIGHUP])?;
         thread::spawn(move || {
-            if let Some(sig) = signals.forever().next() {
-                process::exit(sig);
+            for _ in signals.forever() {
+                // Bad edits leave the current settings in place.
+                match LiveSettings::load(&path) {
+                    Ok(settings) => {
+                        info!("reloaded settings: {settings:?}");
+                        live_settings.set(settings);
+                    }
+                    Err(error) => error!("couldn't reload settings: {error}"),
+                }
             }
         });
     }
 
-    env_logger::builder().format_target(false).init();
-    info!("starting at {addr}");
-    server::run(addr, config)?;
+    // Only errors are logged unless RUST_LOG says otherwise.
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_target(false);
+    match matches.get_one::<String>("log_format").map(String::as_str) {
+        Some("json") => subscriber.json().init(),
+        _ => subscriber.init(),
+    }
+    for addr in &addrs {
+        info!("starting at {addr}");
+    }
+    if config.quic_addr.is_some() {
+        let [ref addr] = addrs[..] else {
+            bail!("QUIC connections can only be served alongside one bind address");
+        };
+        run_with_quic(addr, config)?;
+    } else {
+        server::run_on(&addrs, config)?;
+    }
 
     Ok(())
 }
+
+/// Only the tokio server accepts QUIC connections.
+#[cfg(feature = "quic")]
+fn run_with_quic(addr: &str, config: PokerConfig) -> Result<(), Error> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    runtime.block_on(server::run_async(addr, config))
+}
+
+#[cfg(not(feature = "quic"))]
+fn run_with_quic(_addr: &str, _config: PokerConfig) -> Result<(), Error> {
+    bail!("QUIC connections need pp_server built with `--features quic`")
+}
@@ -0,0 +1,70 @@
+//! Shutting down when the OS asks.
+//!
+//! The first request shuts the server down gracefully, telling users the
+//! server is going away and letting tables finish the hands being
+//! played. Another request exits right away. Unix servers listen for
+//! SIGINT, SIGTERM, and SIGQUIT, while Windows servers listen for
+//! Ctrl-C, Ctrl-Break, and their console closing.
+
+use anyhow::Error;
+use private_poker::server::ShutdownSignal;
+use std::process;
+
+/// Exit code for exiting without waiting for hands to finish.
+const FORCED_EXIT_CODE: i32 = 130;
+
+fn request_shutdown(shutdown: &ShutdownSignal) {
+    if shutdown.is_triggered() {
+        process::exit(FORCED_EXIT_CODE);
+    }
+    #[cfg(target_os = "linux")]
+    super::systemd::notify_stopping();
+    shutdown.trigger();
+}
+
+#[cfg(unix)]
+pub fn handle_shutdown(shutdown: ShutdownSignal) -> Result<(), Error> {
+    use signal_hook::{
+        consts::{SIGINT, SIGQUIT, SIGTERM},
+        iterator::Signals,
+    };
+    use std::thread;
+
+    let mut signals = Signals::new([SIGINT, SIGTERM, SIGQUIT])?;
+    thread::spawn(move || {
+        for _ in signals.forever() {
+            request_shutdown(&shutdown);
+        }
+    });
+    Ok(())
+}
+
+#[cfg(windows)]
+pub fn handle_shutdown(shutdown: ShutdownSignal) -> Result<(), Error> {
+    use std::{io, sync::OnceLock};
+    use windows_sys::{core::BOOL, Win32::System::Console::SetConsoleCtrlHandler};
+
+    // Console handlers can't capture anything, so the signal lives here.
+    static SHUTDOWN: OnceLock<ShutdownSignal> = OnceLock::new();
+
+    unsafe extern "system" fn handler(_ctrl_type: u32) -> BOOL {
+        if let Some(shutdown) = SHUTDOWN.get() {
+            request_shutdown(shutdown);
+        }
+        // Handled, so the process isn't killed.
+        1
+    }
+
+    let _ = SHUTDOWN.set(shutdown);
+    // SAFETY: the handler is a plain function that lives as long as the
+    // process does.
+    if unsafe { SetConsoleCtrlHandler(Some(handler), 1) } == 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn handle_shutdown(_shutdown: ShutdownSignal) -> Result<(), Error> {
+    Ok(())
+}
@@ -0,0 +1,67 @@
+//! Running under systemd.
+//!
+//! Sockets passed with socket activation are listened on in place of
+//! `--bind` addresses, and services with `Type=notify` are told when
+//! the server is ready, when it's stopping, and that it's still alive
+//! for `WatchdogSec=`.
+
+use private_poker::{server::Supervisor, socket::FD_PREFIX};
+use sd_notify::NotifyState;
+use std::{
+    env, io,
+    time::{Duration, Instant},
+};
+use tracing::warn;
+
+/// Addresses of the listening sockets systemd passed to the server, if
+/// any.
+pub fn listen_addrs() -> io::Result<Vec<String>> {
+    Ok(sd_notify::listen_fds()?
+        .map(|fd| format!("{FD_PREFIX}{fd}"))
+        .collect())
+}
+
+/// Let systemd know the server is stopping.
+pub fn notify_stopping() {
+    notify(NotifyState::Stopping);
+}
+
+fn notify(state: NotifyState) {
+    if let Err(error) = sd_notify::notify(false, &[state]) {
+        warn!("couldn't notify systemd: {error}");
+    }
+}
+
+/// Tells systemd how the server is doing.
+pub struct Systemd {
+    /// How often the watchdog has to hear from the server, and when it
+    /// last did.
+    watchdog: Option<(Duration, Instant)>,
+}
+
+impl Systemd {
+    /// Returns nothing unless systemd is waiting to hear from the server.
+    pub fn new() -> Option<Self> {
+        env::var_os("NOTIFY_SOCKET")?;
+        let mut usec = 0;
+        let watchdog = sd_notify::watchdog_enabled(false, &mut usec)
+            .then(|| (Duration::from_micros(usec), Instant::now()));
+        Some(Self { watchdog })
+    }
+}
+
+impl Supervisor for Systemd {
+    fn ready(&mut self) {
+        notify(NotifyState::Ready);
+    }
+
+    /// Pets the watchdog twice per interval, like systemd suggests.
+    fn alive(&mut self) {
+        if let Some((interval, ref mut last_pet)) = self.watchdog {
+            if last_pet.elapsed() >= interval / 2 {
+                notify(NotifyState::Watchdog);
+                *last_pet = Instant::now();
+            }
+        }
+    }
+}
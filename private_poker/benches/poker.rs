@@ -0,0 +1,122 @@
+//! Throughput of the hand evaluator and the game engine.
+//!
+//! Evaluator benchmarks report hands evaluated per second, and
+//! simulation benchmarks report complete hands played per second,
+//! blinds through payouts, so regressions in evaluation or pot logic
+//! show up as lower throughput. Everything is seeded so runs compare
+//! the same work.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use private_poker::{
+    agent::{CallingStation, TightAggressive},
+    entities::Card,
+    equity,
+    functional::{self, new_deck},
+    lookup::CardSet,
+    sim::Simulation,
+    GameSettings,
+};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+/// Number of hands dealt for each evaluator benchmark iteration.
+const NUM_EVALUATIONS: usize = 1000;
+/// Number of hands played for each simulation benchmark iteration.
+const NUM_SIM_HANDS: usize = 100;
+
+/// Deal random hands of the given size from fresh decks.
+fn deal_hands(num_hands: usize, hand_size: usize) -> Vec<Vec<Card>> {
+    let mut rng = StdRng::seed_from_u64(0);
+    (0..num_hands)
+        .map(|_| {
+            let mut deck = new_deck();
+            deck.shuffle(&mut rng);
+            deck[..hand_size].to_vec()
+        })
+        .collect()
+}
+
+fn bench_evaluator(c: &mut Criterion) {
+    let mut group = c.benchmark_group("evaluator");
+    group.throughput(Throughput::Elements(NUM_EVALUATIONS as u64));
+    for hand_size in [5, 7] {
+        let hands = deal_hands(NUM_EVALUATIONS, hand_size);
+        group.bench_function(format!("evaluate {hand_size} cards"), |b| {
+            b.iter(|| {
+                for hand in hands.iter() {
+                    black_box(functional::evaluate(black_box(hand)));
+                }
+            })
+        });
+    }
+    // Hands that are already sorted with their high aces added, like
+    // the engine evaluates at showdown.
+    let hands: Vec<_> = deal_hands(NUM_EVALUATIONS, 7)
+        .into_iter()
+        .map(|mut hand| {
+            functional::prepare_hand(&mut hand);
+            hand
+        })
+        .collect();
+    group.bench_function("eval prepared 7 cards", |b| {
+        b.iter(|| {
+            for hand in hands.iter() {
+                black_box(functional::eval(black_box(hand)));
+            }
+        })
+    });
+    // Just the lookup, the way equity calculations rank runouts.
+    let hands: Vec<_> = deal_hands(NUM_EVALUATIONS, 7)
+        .iter()
+        .map(|hand| CardSet::new(hand))
+        .collect();
+    group.bench_function("lookup 7 cards", |b| {
+        b.iter(|| {
+            for hand in hands.iter() {
+                black_box(black_box(hand).strength());
+            }
+        })
+    });
+    group.finish();
+
+    // Equity is evaluating every possible river for each hand.
+    let mut group = c.benchmark_group("equity");
+    let hands = deal_hands(1, 8).remove(0);
+    let (hands, board) = (vec![hands[..2].to_vec(), hands[2..4].to_vec()], &hands[4..]);
+    group.bench_function("exhaustive heads-up turn", |b| {
+        b.iter(|| equity::exhaustive(black_box(&hands), black_box(board)))
+    });
+    group.finish();
+}
+
+fn bench_simulation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("simulation");
+    group.throughput(Throughput::Elements(NUM_SIM_HANDS as u64));
+    for num_players in [2, 6, 10] {
+        group.bench_function(format!("{num_players} players"), |b| {
+            b.iter_batched(
+                || {
+                    let mut sim = Simulation::new(GameSettings::default().with_seed(0));
+                    // A mix of passive and aggressive players so hands
+                    // reach showdown with side pots as well as ending
+                    // early.
+                    for i in 0..num_players {
+                        let username = i.to_string();
+                        if i % 2 == 0 {
+                            sim.add_agent(&username, CallingStation).unwrap();
+                        } else {
+                            sim.add_agent(&username, TightAggressive::default())
+                                .unwrap();
+                        }
+                    }
+                    sim
+                },
+                |mut sim| sim.run(NUM_SIM_HANDS),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_evaluator, bench_simulation);
+criterion_main!(benches);
@@ -0,0 +1,24 @@
+//! Messages from clients as the server reads them, handshakes and all.
+//! Anyone can connect and send these bytes.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use private_poker::{
+    messages::{ClientMessage, UserCommand},
+    utils::FrameBuffer,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let mut reader = data;
+    let mut buf = FrameBuffer::default();
+    // Connections send messages back to back, with the framing they
+    // negotiated carrying over.
+    while let Ok(msg) = buf.read_message::<ClientMessage, _>(&mut reader) {
+        // The server logs messages and validates settings for new tables.
+        let _ = msg.to_string();
+        if let UserCommand::CreateTable(settings) = msg.command {
+            let _ = settings.validate();
+        }
+    }
+});
@@ -0,0 +1,14 @@
+//! Messages from servers as clients read them. Clients connect to
+//! servers they don't control.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use private_poker::{messages::ServerMessage, utils::read_prefixed};
+
+fuzz_target!(|data: &[u8]| {
+    let mut reader = data;
+    while let Ok(msg) = read_prefixed::<ServerMessage, _>(&mut reader) {
+        let _ = msg.to_string();
+    }
+});
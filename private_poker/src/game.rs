@@ -1,28 +1,56 @@
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::SeedableRng;
 use serde::{Deserialize, Serialize};
 use std::{
-    cmp::{max, min, Ordering},
-    collections::{BTreeSet, HashMap, HashSet, VecDeque},
-    fmt,
+    cmp::{max, min},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    fmt, mem,
+    time::Duration,
 };
 use thiserror::Error;
+// Browsers don't give WebAssembly the standard library's clock.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+use std::time::Instant;
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+use web_time::Instant;
 
+pub mod agent;
 pub mod constants;
 pub mod entities;
+pub mod equity;
 pub mod functional;
-
-use constants::{DEFAULT_MAX_USERS, MAX_PLAYERS};
+pub mod invariants;
+pub mod lookup;
+pub mod range;
+pub mod replay;
+pub mod sim;
+
+use constants::{
+    DEFAULT_MAX_REBUYS, DEFAULT_MAX_SPECTATORS, DEFAULT_MAX_USERS, DEFAULT_TURN_TIMEOUT,
+    MAX_PLAYERS, MAX_USERS, TOURNAMENT_BLIND_MULTIPLES,
+};
 use entities::{
-    Action, Bet, BetAction, Card, GameView, GameViews, Player, PlayerState, PlayerView, Pot,
-    PotView, SubHand, Usd, Usdf, User, DEFAULT_BUY_IN, DEFAULT_MIN_BIG_BLIND,
-    DEFAULT_MIN_SMALL_BLIND,
+    Action, Bet, BetAction, Card, ChipDisplay, Chips, GameSnapshot, GameView, GameViews,
+    HandAction, HandHistory, JackpotPayout, Knockout, Player, PlayerState, PlayerView, Position,
+    Pot, PotResult, PotView, Rank, SidePotView, Street, SubHand, TournamentView, Usd, Usdf, User,
+    Username, CENTS_PER_USD, DEFAULT_BUY_IN, DEFAULT_MIN_BIG_BLIND, DEFAULT_MIN_SMALL_BLIND,
 };
 
 #[derive(Debug, Deserialize, Eq, Error, PartialEq, Serialize)]
 pub enum UserError {
+    #[error("bomb pots aren't allowed at this table")]
+    BombPotsDisabled,
+    #[error("can't run it twice now")]
+    CannotRunItTwice,
     #[error("can't show hand now")]
     CannotShowHand,
+    #[error("can't muck hand now")]
+    CannotMuckHand,
+    #[error("can't buy in to a tournament")]
+    CannotBuyIn,
+    #[error("can't sit out of a tournament")]
+    CannotSitOut,
     #[error("can't start game unless you're waitlisted or playing")]
     CannotStartGame,
     #[error("game is full")]
@@ -31,18 +59,44 @@ pub enum UserError {
     GameAlreadyInProgress,
     #[error("game already starting")]
     GameAlreadyStarting,
-    #[error("insufficient funds to satisfy the ${big_blind} big blind")]
-    InsufficientFunds { big_blind: Usd },
+    #[error("insufficient funds to satisfy the {big_blind} big blind")]
+    InsufficientFunds { big_blind: Chips },
     #[error("{action} is invalid")]
     InvalidAction { action: Action },
     #[error("tried an illegal {bet}")]
     InvalidBet { bet: Bet },
+    #[error("invalid table settings: {0}")]
+    InvalidGameSettings(GameSettingsError),
+    #[error("only admins can do that")]
+    NotAnAdmin,
     #[error("need at least 2 players to start the game")]
     NotEnoughPlayers,
+    #[error("not at a table")]
+    NotAtTable,
     #[error("tried acting out of turn")]
     OutOfTurnAction,
+    #[error("no rebuys left")]
+    RebuyLimitReached,
+    #[error("seat {seat_idx} doesn't exist")]
+    SeatDoesNotExist { seat_idx: usize },
+    #[error("no room left to spectate")]
+    SpectatorsFull,
+    #[error("can't buy in past a {max_buy_in} stack")]
+    StackAboveMaxBuyIn { max_buy_in: Chips },
+    #[error("need a stack of at least {min_buy_in}")]
+    StackBelowMinBuyIn { min_buy_in: Chips },
+    #[error("can't host any more tables")]
+    TableCapacityReached,
+    #[error("table does not exist")]
+    TableDoesNotExist,
+    #[error("can't join the tournament while it's in progress")]
+    TournamentInProgress,
+    #[error("no tournament is running")]
+    TournamentNotRunning,
     #[error("user already exists")]
     UserAlreadyExists,
+    #[error("already seated")]
+    UserAlreadySeated,
     #[error("user does not exist")]
     UserDoesNotExist,
     #[error("not playing")]
@@ -51,13 +105,203 @@ pub enum UserError {
     UserAlreadyShowingHand,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Deserialize, Eq, Error, PartialEq, Serialize)]
+pub enum GameSettingsError {
+    #[error("big blind ({big_blind}) can't exceed the buy-in ({buy_in})")]
+    BigBlindExceedsBuyIn { big_blind: Chips, buy_in: Chips },
+    #[error("buy-in ({buy_in}) must be between {min_buy_in} and {max_buy_in}")]
+    BuyInOutOfBounds {
+        buy_in: Chips,
+        min_buy_in: Chips,
+        max_buy_in: Chips,
+    },
+    #[error("max players ({max_players}) must be between 2 and {MAX_PLAYERS}")]
+    PlayersOutOfBounds { max_players: usize },
+    #[error("rake ({percent}%) can't exceed 100%")]
+    RakeTooHigh { percent: u8 },
+    #[error("big blind must be greater than zero")]
+    ZeroBigBlind,
+    #[error("bomb pot ante must be greater than zero")]
+    ZeroBombPotAnte,
+    #[error("bomb pots can't be every 0 hands")]
+    ZeroBombPotFrequency,
+    #[error("small blind ({small_blind}) can't exceed the big blind ({big_blind})")]
+    SmallBlindExceedsBigBlind {
+        small_blind: Chips,
+        big_blind: Chips,
+    },
+    #[error("max spectators ({max_spectators}) can't exceed {MAX_USERS}")]
+    TooManySpectators { max_spectators: usize },
+    #[error("max users ({max_users}) can't exceed {MAX_USERS}")]
+    TooManyUsers { max_users: usize },
+}
+
+/// Tournament settings. Blinds increase at fixed intervals rather than
+/// with the size of player stacks, and players are eliminated once they
+/// run out of money.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TournamentSettings {
+    /// How long each blind level lasts.
+    pub level_duration: Duration,
+    /// Bounty on each player's head, paid to whoever knocks them out of
+    /// the tournament. It's a knockout tournament if this isn't zero.
+    #[serde(default)]
+    pub bounty: Usd,
+    /// Number of blind levels players can register late and busted
+    /// players can re-enter for. Registration closes when the tournament
+    /// starts if this is zero.
+    #[serde(default)]
+    pub late_registration_levels: usize,
+    /// Name shared by the tables of a multi-table tournament. The server
+    /// moves players between tables with the same name to keep them
+    /// balanced, and decides when the tournament's over rather than
+    /// letting each table crown its last player.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Number of players that finish in the money. Multi-table
+    /// tournaments play hand-for-hand on the bubble, when the next
+    /// player out is the last one to miss out.
+    #[serde(default)]
+    pub paid_places: usize,
+}
+
+impl TournamentSettings {
+    pub fn new(level_duration: Duration) -> Self {
+        Self {
+            level_duration,
+            bounty: 0,
+            late_registration_levels: 0,
+            name: None,
+            paid_places: 0,
+        }
+    }
+
+    /// Make the table one of the tables of a multi-table tournament,
+    /// paying the given number of places.
+    pub fn with_multi_table(mut self, name: impl Into<String>, paid_places: usize) -> Self {
+        self.name = Some(name.into());
+        self.paid_places = paid_places;
+        self
+    }
+
+    /// Keep registration open for the first few blind levels, letting
+    /// new players join and busted players re-enter with a fresh stack.
+    pub fn with_late_registration(mut self, levels: usize) -> Self {
+        self.late_registration_levels = levels;
+        self
+    }
+
+    /// Put a bounty on every player's head, making it a knockout
+    /// tournament.
+    pub fn with_bounty(mut self, bounty: Usd) -> Self {
+        self.bounty = bounty;
+        self
+    }
+}
+
+/// Rake settings. A percentage of each pot is taken before it's awarded,
+/// which some communities use to fund prizes.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RakeSettings {
+    pub percent: u8,
+    /// Most rake taken from a single hand. The rake is uncapped if this
+    /// isn't set.
+    pub cap: Option<Usd>,
+    /// Skip the rake for hands that end before the flop.
+    pub no_flop_no_drop: bool,
+}
+
+impl RakeSettings {
+    pub fn new(percent: u8, cap: Option<Usd>, no_flop_no_drop: bool) -> Self {
+        Self {
+            percent,
+            cap,
+            no_flop_no_drop,
+        }
+    }
+}
+
+/// Bad beat jackpot settings. Every hand that sees a flop drops some of
+/// its pot into the jackpot, which is paid out when a hand of at least
+/// `min_rank` loses at showdown.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct JackpotSettings {
+    /// Most money dropped into the jackpot from a single hand.
+    pub drop: Usd,
+    pub min_rank: Rank,
+}
+
+impl JackpotSettings {
+    pub fn new(drop: Usd, min_rank: Rank) -> Self {
+        Self { drop, min_rank }
+    }
+}
+
+/// Bomb pot settings. Every player antes the same amount in a bomb pot,
+/// no blinds are posted, and the hand starts on the flop without any
+/// betting before it.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct BombPotSettings {
+    /// Money every player antes.
+    pub ante: Usd,
+    /// Every this many hands is a bomb pot. Bomb pots are also dealt
+    /// whenever every player votes for one.
+    pub every: Option<usize>,
+}
+
+impl BombPotSettings {
+    pub fn new(ante: Usd, every: Option<usize>) -> Self {
+        Self { ante, every }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct GameSettings {
     pub buy_in: Usd,
+    /// Users need at least this much money to take a seat in a cash
+    /// game, and buying in can't bring their stack past `max_buy_in`.
+    pub min_buy_in: Usd,
+    pub max_buy_in: Usd,
     pub min_big_blind: Usd,
     pub min_small_blind: Usd,
+    /// Ante collected from every player at the start of each hand, or
+    /// just from the big blind with `is_big_blind_ante`. No ante is
+    /// collected if this is zero.
+    pub min_ante: Usd,
+    /// Whether the big blind posts the ante for the whole table, like in
+    /// most tournaments, so there's one ante to collect each hand instead
+    /// of one per player.
+    #[serde(default)]
+    pub is_big_blind_ante: bool,
     pub max_players: usize,
     pub max_users: usize,
+    /// Most users that can watch without playing. New users start out
+    /// watching, so this also limits how many can join at once.
+    pub max_spectators: usize,
+    /// Times each user can buy more money, either to rebuy after going
+    /// broke or to top up a short stack. Tournaments don't allow buying
+    /// in at all.
+    pub max_rebuys: usize,
+    /// Pots are raked if this is set.
+    pub rake: Option<RakeSettings>,
+    /// Tables have a bad beat jackpot if this is set.
+    pub jackpot: Option<JackpotSettings>,
+    /// Tables have bomb pots if this is set.
+    #[serde(default)]
+    pub bomb_pot: Option<BombPotSettings>,
+    /// Tournament mode is enabled if this is set.
+    pub tournament: Option<TournamentSettings>,
+    /// How long players have to act on their turn before they're forced
+    /// to fold.
+    pub turn_timeout: Duration,
+    /// Seed for shuffling the deck so deals are reproducible, like in
+    /// tests and simulations. The deck is shuffled randomly if this
+    /// isn't set.
+    pub seed: Option<u64>,
+    /// How amounts are shown to users, so home games and tournaments can
+    /// count chips instead of dollars.
+    #[serde(default)]
+    pub chip_display: ChipDisplay,
 }
 
 impl GameSettings {
@@ -66,44 +310,416 @@ impl GameSettings {
         let min_small_blind = min_big_blind / 2;
         Self {
             buy_in,
+            min_buy_in: 0,
+            max_buy_in: buy_in,
             min_big_blind,
             min_small_blind,
+            min_ante: 0,
+            is_big_blind_ante: false,
             max_players,
             max_users,
+            max_spectators: max_users,
+            max_rebuys: DEFAULT_MAX_REBUYS,
+            rake: None,
+            jackpot: None,
+            bomb_pot: None,
+            tournament: None,
+            turn_timeout: DEFAULT_TURN_TIMEOUT,
+            seed: None,
+            chip_display: ChipDisplay::default(),
+        }
+    }
+
+    pub fn with_ante(mut self, ante: Usd) -> Self {
+        self.min_ante = ante;
+        self
+    }
+
+    pub(crate) fn dollars_to_cents(&mut self) {
+        use entities::dollars_to_cents;
+
+        self.buy_in = dollars_to_cents(self.buy_in);
+        self.min_buy_in = dollars_to_cents(self.min_buy_in);
+        self.max_buy_in = dollars_to_cents(self.max_buy_in);
+        self.min_big_blind = dollars_to_cents(self.min_big_blind);
+        self.min_small_blind = dollars_to_cents(self.min_small_blind);
+        self.min_ante = dollars_to_cents(self.min_ante);
+        if let Some(ref mut rake) = self.rake {
+            rake.cap = rake.cap.map(dollars_to_cents);
+        }
+        if let Some(ref mut jackpot) = self.jackpot {
+            jackpot.drop = dollars_to_cents(jackpot.drop);
+        }
+        if let Some(ref mut bomb_pot) = self.bomb_pot {
+            bomb_pot.ante = dollars_to_cents(bomb_pot.ante);
+        }
+        if let Some(ref mut tournament) = self.tournament {
+            tournament.bounty = dollars_to_cents(tournament.bounty);
+        }
+    }
+
+    /// Have the big blind post the ante for the whole table.
+    pub fn with_big_blind_ante(mut self, ante: Usd) -> Self {
+        self.min_ante = ante;
+        self.is_big_blind_ante = true;
+        self
+    }
+
+    /// Pair an amount with how these settings show amounts.
+    pub fn chips(&self, amount: Usd) -> Chips {
+        self.chip_display.chips(amount)
+    }
+
+    pub fn with_chip_display(mut self, chip_display: ChipDisplay) -> Self {
+        self.chip_display = chip_display;
+        self
+    }
+
+    /// Use a blind structure independent of the buy-in.
+    pub fn with_blinds(mut self, small_blind: Usd, big_blind: Usd) -> Self {
+        self.min_small_blind = small_blind;
+        self.min_big_blind = big_blind;
+        self
+    }
+
+    /// Check that the settings describe a playable game.
+    pub fn validate(&self) -> Result<(), GameSettingsError> {
+        if self.min_big_blind == 0 {
+            Err(GameSettingsError::ZeroBigBlind)
+        } else if self.min_big_blind > self.buy_in {
+            Err(GameSettingsError::BigBlindExceedsBuyIn {
+                big_blind: self.chips(self.min_big_blind),
+                buy_in: self.chips(self.buy_in),
+            })
+        } else if !(self.min_buy_in..=self.max_buy_in).contains(&self.buy_in) {
+            Err(GameSettingsError::BuyInOutOfBounds {
+                buy_in: self.chips(self.buy_in),
+                min_buy_in: self.chips(self.min_buy_in),
+                max_buy_in: self.chips(self.max_buy_in),
+            })
+        } else if let Some(rake) = self.rake.as_ref().filter(|rake| rake.percent > 100) {
+            Err(GameSettingsError::RakeTooHigh {
+                percent: rake.percent,
+            })
+        } else if self
+            .bomb_pot
+            .as_ref()
+            .is_some_and(|bomb_pot| bomb_pot.ante == 0)
+        {
+            Err(GameSettingsError::ZeroBombPotAnte)
+        } else if self
+            .bomb_pot
+            .as_ref()
+            .is_some_and(|bomb_pot| bomb_pot.every == Some(0))
+        {
+            Err(GameSettingsError::ZeroBombPotFrequency)
+        } else if self.min_small_blind > self.min_big_blind {
+            Err(GameSettingsError::SmallBlindExceedsBigBlind {
+                small_blind: self.chips(self.min_small_blind),
+                big_blind: self.chips(self.min_big_blind),
+            })
+        } else if !(2..=MAX_PLAYERS).contains(&self.max_players) {
+            Err(GameSettingsError::PlayersOutOfBounds {
+                max_players: self.max_players,
+            })
+        } else if self.max_users > MAX_USERS {
+            Err(GameSettingsError::TooManyUsers {
+                max_users: self.max_users,
+            })
+        } else if self.max_spectators > MAX_USERS {
+            Err(GameSettingsError::TooManySpectators {
+                max_spectators: self.max_spectators,
+            })
+        } else {
+            Ok(())
         }
     }
+
+    pub fn with_bomb_pot(mut self, bomb_pot: BombPotSettings) -> Self {
+        self.bomb_pot = Some(bomb_pot);
+        self
+    }
+
+    /// Bound how much money users can sit down with and buy up to.
+    pub fn with_buy_in_bounds(mut self, min_buy_in: Usd, max_buy_in: Usd) -> Self {
+        self.min_buy_in = min_buy_in;
+        self.max_buy_in = max_buy_in;
+        self
+    }
+
+    pub fn with_max_spectators(mut self, max_spectators: usize) -> Self {
+        self.max_spectators = max_spectators;
+        self
+    }
+
+    pub fn with_rebuys(mut self, max_rebuys: usize) -> Self {
+        self.max_rebuys = max_rebuys;
+        self
+    }
+
+    pub fn with_jackpot(mut self, jackpot: JackpotSettings) -> Self {
+        self.jackpot = Some(jackpot);
+        self
+    }
+
+    pub fn with_rake(mut self, rake: RakeSettings) -> Self {
+        self.rake = Some(rake);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn with_tournament(mut self, tournament: TournamentSettings) -> Self {
+        self.tournament = Some(tournament);
+        self
+    }
+
+    pub fn with_turn_timeout(mut self, turn_timeout: Duration) -> Self {
+        self.turn_timeout = turn_timeout;
+        self
+    }
 }
 
 impl Default for GameSettings {
     fn default() -> Self {
         Self {
             buy_in: DEFAULT_BUY_IN,
+            min_buy_in: 0,
+            max_buy_in: DEFAULT_BUY_IN,
             min_big_blind: DEFAULT_MIN_BIG_BLIND,
             min_small_blind: DEFAULT_MIN_SMALL_BLIND,
+            min_ante: 0,
+            is_big_blind_ante: false,
             max_players: MAX_PLAYERS,
             max_users: DEFAULT_MAX_USERS,
+            max_spectators: DEFAULT_MAX_SPECTATORS,
+            max_rebuys: DEFAULT_MAX_REBUYS,
+            rake: None,
+            jackpot: None,
+            bomb_pot: None,
+            tournament: None,
+            turn_timeout: DEFAULT_TURN_TIMEOUT,
+            seed: None,
+            chip_display: ChipDisplay::default(),
+        }
+    }
+}
+
+/// Tournament progress.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Tournament {
+    /// Current blind level, starting from 0.
+    pub level: usize,
+    level_duration: Duration,
+    /// When the tournament started. Nothing if the tournament isn't
+    /// running.
+    #[serde(with = "elapsed")]
+    start: Option<Instant>,
+    /// Winner of the last tournament.
+    pub winner: Option<Username>,
+    #[serde(default)]
+    bounty: Usd,
+    /// Bounties collected during the current or last tournament.
+    #[serde(default)]
+    pub bounties_won: BTreeMap<Username, Usd>,
+    #[serde(default)]
+    late_registration_levels: usize,
+    /// Times each player has bought into the current or last tournament,
+    /// counting re-entries.
+    #[serde(default)]
+    pub entries: BTreeMap<Username, usize>,
+    /// Whether the table is one of a multi-table tournament's tables,
+    /// which wait for players to be moved in rather than crowning their
+    /// last player.
+    #[serde(default)]
+    is_multi_table: bool,
+    /// Whether tables are playing hand-for-hand, with every table
+    /// finishing its hand before any table deals the next.
+    #[serde(default)]
+    is_hand_for_hand: bool,
+}
+
+impl Tournament {
+    /// Return the blind multiple for the given level. Levels past the
+    /// predefined multiples keep doubling so tournaments always end.
+    pub fn get_blind_multiple(level: usize) -> Usd {
+        let num_multiples = TOURNAMENT_BLIND_MULTIPLES.len();
+        match TOURNAMENT_BLIND_MULTIPLES.get(level) {
+            Some(multiple) => *multiple,
+            None => {
+                let doublings = min(level + 1 - num_multiples, 16) as u32;
+                TOURNAMENT_BLIND_MULTIPLES[num_multiples - 1].saturating_mul(2u32.pow(doublings))
+            }
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.start.is_some()
+    }
+
+    /// Return whether players can still register or re-enter.
+    pub fn is_registration_open(&self) -> bool {
+        self.is_running() && self.level < self.late_registration_levels
+    }
+
+    fn get_num_entries(&self) -> usize {
+        self.entries.values().sum()
+    }
+
+    fn new(settings: &TournamentSettings) -> Self {
+        Self {
+            level: 0,
+            level_duration: settings.level_duration,
+            start: None,
+            winner: None,
+            bounty: settings.bounty,
+            bounties_won: BTreeMap::new(),
+            late_registration_levels: settings.late_registration_levels,
+            entries: BTreeMap::new(),
+            is_multi_table: settings.name.is_some(),
+            is_hand_for_hand: false,
+        }
+    }
+
+    fn start(&mut self) {
+        self.level = 0;
+        self.start = Some(Instant::now());
+        self.winner = None;
+        self.bounties_won.clear();
+        self.entries.clear();
+        self.is_hand_for_hand = false;
+    }
+
+    /// End the tournament. No one's left to knock the winner out, so
+    /// they collect their own bounty.
+    fn stop(&mut self, winner: Option<Username>) {
+        self.start = None;
+        self.is_hand_for_hand = false;
+        if let Some(ref winner) = winner {
+            if self.bounty > 0 {
+                *self.bounties_won.entry(winner.clone()).or_default() += self.bounty;
+            }
+        }
+        self.winner = winner;
+    }
+
+    fn dollars_to_cents(&mut self) {
+        self.bounty = entities::dollars_to_cents(self.bounty);
+        for bounty in self.bounties_won.values_mut() {
+            *bounty = entities::dollars_to_cents(*bounty);
+        }
+    }
+
+    /// Return the time remaining in the current blind level.
+    fn time_to_next_level(&self) -> Option<Duration> {
+        self.start.map(|start| {
+            let elapsed = Instant::now() - start;
+            let level_end = self.level_duration * (self.level as u32 + 1);
+            level_end.saturating_sub(elapsed)
+        })
+    }
+
+    /// Move to the blind level according to how long the tournament has
+    /// been running.
+    fn update_level(&mut self) {
+        if let Some(start) = self.start {
+            if !self.level_duration.is_zero() {
+                let elapsed = Instant::now() - start;
+                self.level = (elapsed.as_secs_f64() / self.level_duration.as_secs_f64()) as usize;
+            }
         }
     }
 }
 
-#[derive(Debug)]
+/// Instants can't be serialized, so a tournament's start is saved as how
+/// long it's been running instead.
+mod elapsed {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::time::Duration;
+
+    use super::Instant;
+
+    pub fn serialize<S: Serializer>(
+        start: &Option<Instant>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        start.map(|start| start.elapsed()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Instant>, D::Error> {
+        let elapsed = Option::<Duration>::deserialize(deserializer)?;
+        Ok(elapsed.map(|elapsed| {
+            Instant::now()
+                .checked_sub(elapsed)
+                .unwrap_or_else(Instant::now)
+        }))
+    }
+}
+
+/// Who a view of the game is for, which decides whose hole cards are in
+/// it.
+#[derive(Clone, Copy, Debug)]
+enum Viewer<'a> {
+    /// A user at the table, who sees their own hand and any hands that
+    /// are face up.
+    User(&'a str),
+    /// Someone who isn't at the table, who only sees hands that are face
+    /// up.
+    Public,
+    /// Sees every hand. Views like this are for replaying hands that are
+    /// over and must never be sent to users.
+    Omniscient,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct GameData {
     /// Deck of cards. This is instantiated once and reshuffled
-    /// each deal.
+    /// each deal. The deck is left out of checkpoints so they can't be
+    /// used to see the cards that haven't been dealt yet.
+    #[serde(skip, default = "functional::new_deck")]
     deck: [Card; 52],
+    /// Shuffles the deck, seeded from the settings if they have a seed.
+    #[serde(skip, default = "StdRng::from_entropy")]
+    rng: StdRng,
     /// Money from users that've left the game. This money is
     /// split equally amongst all users at a particular game state.
     /// This helps keep the amount of money in the game constant,
     /// encouraging additional gameplay.
     pub donations: Usdf,
+    /// Money dropped into the bad beat jackpot that hasn't been paid out
+    /// yet.
+    #[serde(default)]
+    pub jackpot: Usd,
     pub small_blind: Usd,
     pub big_blind: Usd,
-    pub spectators: HashMap<String, User>,
+    pub ante: Usd,
+    pub spectators: HashMap<Username, User>,
     pub waitlist: VecDeque<User>,
     pub open_seats: VecDeque<usize>,
     pub players: Vec<Player>,
     /// Community cards shared amongst all players.
     pub board: Vec<Card>,
+    /// Alternate community cards dealt when all players remaining in an
+    /// all-in pot agree to run it twice. The pot is split between the
+    /// two boards.
+    pub second_board: Vec<Card>,
+    /// Number of community cards on the board when betting ended with
+    /// multiple players still in the hand. Players can agree to run it
+    /// twice until the rest of the board is dealt.
+    runout_idx: Option<usize>,
+    /// Players that've agreed to run it twice.
+    players_running_it_twice: BTreeSet<Username>,
+    /// Players that've voted for the next hand to be a bomb pot.
+    #[serde(default)]
+    players_voting_bomb_pot: BTreeSet<Username>,
+    /// Players that lost at showdown and chose to muck their hand.
+    #[serde(default)]
+    players_mucking: BTreeSet<Username>,
     /// Count of the number of players active in a hand.
     /// All-in and folding are considered INACTIVE since they
     /// have no more moves to make. Once `num_players_called`
@@ -119,42 +735,514 @@ pub struct GameData {
     /// Queue of users that're playing the game but have opted
     /// to spectate. We can't safely remove them from the game mid gameplay,
     /// so we instead queue them for removal.
-    players_to_spectate: BTreeSet<String>,
+    players_to_spectate: BTreeSet<Username>,
     /// Queue of users that're playing the game but have opted
     /// to leave. We can't safely remove them from the game mid gameplay,
     /// so we instead queue them for removal.
-    players_to_remove: BTreeSet<String>,
+    players_to_remove: BTreeSet<Username>,
+    /// Players that've opted to sit out. They keep their seats and
+    /// stacks, but they aren't dealt in from the next hand on.
+    #[serde(default)]
+    players_sitting_out: BTreeSet<Username>,
+    /// Money players have bought in the middle of a hand. They can't
+    /// use it until the hand is over.
+    #[serde(default)]
+    players_buying_in: BTreeMap<Username, Usd>,
+    /// Number of times each user has bought in. Users keep their count
+    /// if they leave so they can't get around the limit by rejoining.
+    #[serde(default)]
+    rebuys: HashMap<Username, usize>,
+    /// Seats that waitlisters asked to play in.
+    #[serde(default)]
+    seat_requests: HashMap<Username, usize>,
+    #[serde(skip)]
     deck_idx: usize,
     pub small_blind_idx: usize,
     pub big_blind_idx: usize,
+    /// Seat of the last hand's big blind. Players move around in
+    /// `players` as others join and leave, so the big blind is moved by
+    /// seat to keep it from skipping players or landing on the same
+    /// player twice.
+    #[serde(default)]
+    big_blind_seat_idx: Option<usize>,
+    /// Whether no one posts the small blind this hand because the last
+    /// big blind left. The small blind index is the button then.
+    #[serde(default)]
+    pub is_small_blind_dead: bool,
+    /// Whether the hand is a bomb pot.
+    #[serde(default)]
+    pub is_bomb_pot: bool,
+    /// Hands dealt since the last bomb pot.
+    #[serde(default)]
+    hands_since_bomb_pot: usize,
     starting_action_idx: usize,
     pub next_action_idx: Option<usize>,
+    pub tournament: Option<Tournament>,
+    /// Players that lost a pot in a bounty tournament and who won it,
+    /// from the highest pot each player lost. Players that end the hand
+    /// without any money were knocked out by the pot's winners.
+    #[serde(default)]
+    eliminators: BTreeMap<Username, Vec<Username>>,
+    /// Record of the hand in progress, or of the last hand once it's
+    /// over.
+    hand_history: HandHistory,
     settings: GameSettings,
 }
 
 impl GameData {
+    /// Return whether a user with the given money can take a seat. Users
+    /// must be able to afford the big blind, except in tournaments where
+    /// players can play until they're out of money.
+    fn can_play(&self, money: Usd) -> bool {
+        match self.tournament {
+            Some(ref tournament) if tournament.is_running() => money > 0,
+            _ => money >= self.big_blind,
+        }
+    }
+
+    /// Return whether the hand about to be dealt is a bomb pot, either
+    /// because it's been enough hands since the last one or because every
+    /// player being dealt in voted for one.
+    fn is_bomb_pot_due(&self) -> bool {
+        let Some(ref bomb_pot) = self.settings.bomb_pot else {
+            return false;
+        };
+        bomb_pot
+            .every
+            .is_some_and(|every| self.hands_since_bomb_pot + 1 >= every)
+            || self
+                .players
+                .iter()
+                .filter(|p| p.state != PlayerState::SitOut)
+                .all(|p| self.players_voting_bomb_pot.contains(&p.user.name))
+    }
+
+    /// Vote for (or against) the next hand being a bomb pot.
+    fn vote_bomb_pot(&mut self, username: &str, vote: bool) -> Result<(), UserError> {
+        if self.settings.bomb_pot.is_none() {
+            return Err(UserError::BombPotsDisabled);
+        }
+        if !self.players.iter().any(|p| p.user.name == username) {
+            return Err(UserError::UserNotPlaying);
+        }
+        if vote {
+            self.players_voting_bomb_pot.insert(username.into());
+        } else {
+            self.players_voting_bomb_pot.remove(username);
+        }
+        Ok(())
+    }
+
+    /// Force a player to put money into the pot (i.e., for antes and blinds),
+    /// putting them all-in if they can't afford the full amount.
+    fn post(&mut self, player_idx: usize, amount: Usd) {
+        let player = &mut self.players[player_idx];
+        if player.state == PlayerState::AllIn {
+            return;
+        }
+        let bet = if player.user.money <= amount {
+            player.state = PlayerState::AllIn;
+            self.num_players_active -= 1;
+            Bet {
+                action: BetAction::AllIn,
+                amount: player.user.money,
+            }
+        } else {
+            Bet {
+                action: BetAction::Raise,
+                amount,
+            }
+        };
+        self.pot.bet(player_idx, &bet);
+        player.user.money -= bet.amount;
+    }
+
+    /// Force a player to put dead money into the pot (i.e., for an
+    /// ante), which doesn't count toward their bet. They post what
+    /// they have left if they can't afford the full amount.
+    fn post_dead(&mut self, player_idx: usize, amount: Usd) {
+        let player = &mut self.players[player_idx];
+        let amount = amount.min(player.user.money);
+        if player.state == PlayerState::AllIn || amount == 0 {
+            return;
+        }
+        self.pot.dead += amount;
+        player.user.money -= amount;
+        if player.user.money == 0 {
+            player.state = PlayerState::AllIn;
+            self.num_players_active -= 1;
+        }
+    }
+
+    /// Scale the blinds and ante by a multiple of their minimums.
+    fn set_blind_multiple(&mut self, multiple: Usd) {
+        self.small_blind = multiple * self.settings.min_small_blind;
+        self.big_blind = multiple * self.settings.min_big_blind;
+        self.ante = multiple * self.settings.min_ante;
+    }
+
+    /// Put the cards that've been dealt back on top of the deck and
+    /// shuffle the rest under them so dealing can pick up where it left
+    /// off.
+    fn restack_deck(&mut self) {
+        let mut dealt = Vec::with_capacity(self.deck.len());
+        for card in self
+            .players
+            .iter()
+            .flat_map(|p| p.cards.iter())
+            .chain(self.board.iter())
+            .chain(self.second_board.iter())
+        {
+            if !dealt.contains(card) {
+                dealt.push(*card);
+            }
+        }
+        let mut undealt: Vec<_> = functional::new_deck()
+            .into_iter()
+            .filter(|card| !dealt.contains(card))
+            .collect();
+        undealt.shuffle(&mut self.rng);
+        self.deck_idx = dealt.len();
+        for (deck_card, card) in self.deck.iter_mut().zip(dealt.into_iter().chain(undealt)) {
+            *deck_card = card;
+        }
+    }
+
+    /// Convert a game saved back when money was counted in whole
+    /// dollars.
+    fn dollars_to_cents(&mut self) {
+        use entities::dollars_to_cents;
+
+        self.donations *= CENTS_PER_USD as Usdf;
+        self.jackpot = dollars_to_cents(self.jackpot);
+        self.small_blind = dollars_to_cents(self.small_blind);
+        self.big_blind = dollars_to_cents(self.big_blind);
+        self.ante = dollars_to_cents(self.ante);
+        for user in self.spectators.values_mut().chain(self.waitlist.iter_mut()) {
+            user.dollars_to_cents();
+        }
+        for player in self.players.iter_mut() {
+            player.dollars_to_cents();
+        }
+        self.pot.dollars_to_cents();
+        for amount in self.players_buying_in.values_mut() {
+            *amount = dollars_to_cents(*amount);
+        }
+        if let Some(ref mut tournament) = self.tournament {
+            tournament.dollars_to_cents();
+        }
+        self.hand_history.dollars_to_cents();
+        self.settings.dollars_to_cents();
+    }
+
+    fn is_tournament_running(&self) -> bool {
+        self.tournament.as_ref().is_some_and(|t| t.is_running())
+    }
+
+    /// End a running tournament if there's only one player left with
+    /// money, returning whether there's no one left to play against.
+    /// Multi-table tournaments only end when the server says the table
+    /// is the last one left, and wait for players to be moved in
+    /// otherwise.
+    fn crown_tournament_winner(&mut self, is_final_table: bool) -> bool {
+        let mut remaining = self
+            .waitlist
+            .iter()
+            .chain(self.players.iter().map(|p| &p.user));
+        let winner = match (remaining.next(), remaining.next()) {
+            (Some(_), Some(_)) => return false,
+            (winner, _) => winner.map(|u| u.name.clone()),
+        };
+        if let Some(ref mut tournament) = self.tournament {
+            if !tournament.is_multi_table || is_final_table {
+                tournament.stop(winner);
+            }
+        }
+        true
+    }
+
+    /// Return the bounty on each player's head if a knockout tournament
+    /// is running.
+    fn get_bounty(&self) -> Option<Usd> {
+        self.tournament
+            .as_ref()
+            .filter(|t| t.is_running() && t.bounty > 0)
+            .map(|t| t.bounty)
+    }
+
+    /// Pay the bounties of players that were knocked out this hand to
+    /// the players that won the last of their money. The bounty is split
+    /// evenly, with any odd amount going to the first eliminator.
+    fn pay_bounties(&mut self) {
+        let eliminators = mem::take(&mut self.eliminators);
+        let Some(bounty) = self.get_bounty() else {
+            return;
+        };
+        for (username, eliminators) in eliminators {
+            let is_knocked_out = self
+                .players
+                .iter()
+                .any(|p| p.user.name == username && p.user.money == 0);
+            if !is_knocked_out || eliminators.is_empty() {
+                continue;
+            }
+            let share = bounty / eliminators.len() as Usd;
+            let mut remainder = bounty % eliminators.len() as Usd;
+            let tournament = self.tournament.as_mut().expect("tournament is running");
+            for eliminator in eliminators.iter() {
+                *tournament
+                    .bounties_won
+                    .entry(eliminator.clone())
+                    .or_default() += share + remainder;
+                remainder = 0;
+            }
+            self.hand_history.knockouts.push(Knockout {
+                username,
+                eliminators,
+                bounty,
+            });
+        }
+    }
+
+    /// Buy a user more money. Players' money is held until
+    /// [`GameData::update_buy_ins`] is called since they could be in the
+    /// middle of a hand.
+    fn buy_in_user(&mut self, username: &str, amount: Usd) -> Result<bool, UserError> {
+        if self.tournament.is_some() {
+            return Err(UserError::CannotBuyIn);
+        }
+        let rebuys = self.rebuys.get(username).copied().unwrap_or(0);
+        if rebuys >= self.settings.max_rebuys {
+            return Err(UserError::RebuyLimitReached);
+        }
+        let pending = self.players_buying_in.get(username).copied().unwrap_or(0);
+        let (money, is_player) = if let Some(user) = self.spectators.get_mut(username) {
+            (&mut user.money, false)
+        } else if let Some(user) = self.waitlist.iter_mut().find(|u| u.name == username) {
+            (&mut user.money, false)
+        } else if let Some(player) = self.players.iter_mut().find(|p| p.user.name == username) {
+            (&mut player.user.money, true)
+        } else {
+            return Err(UserError::UserDoesNotExist);
+        };
+        let stack = money.saturating_add(pending).saturating_add(amount);
+        if amount == 0 || stack < self.settings.min_buy_in {
+            return Err(UserError::StackBelowMinBuyIn {
+                min_buy_in: self.settings.chips(self.settings.min_buy_in),
+            });
+        } else if stack > self.settings.max_buy_in {
+            return Err(UserError::StackAboveMaxBuyIn {
+                max_buy_in: self.settings.chips(self.settings.max_buy_in),
+            });
+        }
+        // Bought money is borrowed from the donations so the amount of
+        // money in the game stays the same once the user leaves.
+        self.donations -= amount as Usdf;
+        self.rebuys.insert(username.into(), rebuys + 1);
+        if is_player {
+            *self.players_buying_in.entry(username.into()).or_default() += amount;
+            Ok(false)
+        } else {
+            *money += amount;
+            Ok(true)
+        }
+    }
+
+    /// Return how much rake to take from a pot, keeping track of the
+    /// hand's rake so it doesn't exceed the cap.
+    fn take_rake(&mut self, pot_size: Usd) -> Usd {
+        let Some(ref rake) = self.settings.rake else {
+            return 0;
+        };
+        if rake.no_flop_no_drop && self.board.is_empty() {
+            return 0;
+        }
+        let mut amount = (pot_size as u64 * rake.percent as u64 / 100) as Usd;
+        if let Some(cap) = rake.cap {
+            amount = min(amount, cap.saturating_sub(self.hand_history.rake));
+        }
+        self.hand_history.rake += amount;
+        amount
+    }
+
+    /// Return how much of a pot to drop into the bad beat jackpot. Only
+    /// hands that see a flop drop into the jackpot, and they drop at most
+    /// the configured amount across all their pots.
+    fn take_jackpot_drop(&mut self, pot_size: Usd) -> Usd {
+        let Some(ref jackpot) = self.settings.jackpot else {
+            return 0;
+        };
+        if self.board.is_empty() {
+            return 0;
+        }
+        let amount = min(
+            pot_size,
+            jackpot.drop.saturating_sub(self.hand_history.jackpot_drop),
+        );
+        self.hand_history.jackpot_drop += amount;
+        self.jackpot += amount;
+        amount
+    }
+
+    /// Pay out the bad beat jackpot if a qualifying hand lost a pot at
+    /// showdown. The hand's hole cards have to play, so hands that are
+    /// only as good as the board don't count. If several hands qualify,
+    /// the best of them hits the jackpot. The jackpot is paid out at
+    /// most once per hand.
+    fn pay_jackpot(
+        &mut self,
+        board_idx: usize,
+        seats_in_pot: &[usize],
+        hands_in_pot: &[Vec<SubHand>],
+        winner_indices: &[usize],
+    ) {
+        let Some(ref jackpot) = self.settings.jackpot else {
+            return;
+        };
+        if self.jackpot == 0 || self.hand_history.jackpot_payout.is_some() {
+            return;
+        }
+        let mut board = if board_idx == 0 {
+            self.board.clone()
+        } else {
+            self.second_board.clone()
+        };
+        functional::prepare_hand(&mut board);
+        let board_hand = functional::eval(&board);
+        let loser_idx = hands_in_pot
+            .iter()
+            .enumerate()
+            .filter(|(idx, hand)| {
+                !winner_indices.contains(idx)
+                    && hand[0].rank >= jackpot.min_rank
+                    && **hand > board_hand
+            })
+            .max_by(|(_, hand1), (_, hand2)| hand1.cmp(hand2))
+            .map(|(idx, _)| idx);
+        let Some(loser_idx) = loser_idx else {
+            return;
+        };
+
+        // Any remainder from splitting the jackpot goes to the
+        // donations.
+        let size = self.jackpot;
+        self.jackpot = 0;
+        let loser_share = size / 2;
+        let winner_share = (size - loser_share) / winner_indices.len() as Usd;
+        let mut remainder = size as Usdf;
+        let loser = &mut self.players[seats_in_pot[loser_idx]].user;
+        loser.money += loser_share;
+        remainder -= loser_share as Usdf;
+        self.hand_history.audit.jackpot_won += loser_share;
+        let loser = loser.name.clone();
+        let mut winners = Vec::with_capacity(winner_indices.len());
+        for winner_idx in winner_indices {
+            let winner = &mut self.players[seats_in_pot[*winner_idx]].user;
+            winner.money += winner_share;
+            remainder -= winner_share as Usdf;
+            winners.push(winner.name.clone());
+        }
+        self.hand_history.audit.jackpot_won += winner_share * winner_indices.len() as Usd;
+        self.donations += remainder;
+        self.hand_history.jackpot_payout = Some(JackpotPayout {
+            size,
+            loser,
+            winners,
+            chip_display: self.settings.chip_display,
+        });
+    }
+
+    /// Give players the money they bought in the middle of a hand. This
+    /// should only be called between hands.
+    fn update_buy_ins(&mut self) {
+        for player in self.players.iter_mut() {
+            if let Some(amount) = self.players_buying_in.remove(&player.user.name) {
+                player.user.money += amount;
+            }
+        }
+    }
+
+    /// Record the hands players showed and, if the hand went to
+    /// showdown, the hands that were mucked instead.
+    fn record_shown_hands(&mut self) {
+        let num_players_in_hand = self
+            .players
+            .iter()
+            .filter(|p| !matches!(p.state, PlayerState::Fold | PlayerState::SitOut))
+            .count();
+        let history = &mut self.hand_history;
+        history.shown_hands = self
+            .players
+            .iter()
+            .filter(|p| p.state == PlayerState::Show)
+            .map(|p| (p.user.name.clone(), p.cards.clone()))
+            .collect();
+        history.mucked_hands = if num_players_in_hand > 1 {
+            self.players
+                .iter()
+                .filter(|p| matches!(p.state, PlayerState::AllIn | PlayerState::Wait))
+                .map(|p| p.user.name.clone())
+                .collect()
+        } else {
+            Vec::new()
+        };
+    }
+
+    /// Sit out players that've opted to and bring back the ones that
+    /// changed their minds. This should only be called between hands.
+    /// Tournament players are always dealt in.
+    fn update_sitting_out(&mut self) {
+        let is_tournament_running = self.is_tournament_running();
+        for player in self.players.iter_mut() {
+            if !is_tournament_running && self.players_sitting_out.contains(&player.user.name) {
+                player.state = PlayerState::SitOut;
+            } else if player.state == PlayerState::SitOut {
+                player.state = PlayerState::Wait;
+            }
+        }
+    }
+
     fn new() -> Self {
         let settings = GameSettings::default();
         Self {
             deck: functional::new_deck(),
+            rng: StdRng::from_entropy(),
             donations: 0.0,
+            jackpot: 0,
             small_blind: settings.min_small_blind,
             big_blind: settings.min_big_blind,
+            ante: settings.min_ante,
             spectators: HashMap::with_capacity(settings.max_users),
             waitlist: VecDeque::with_capacity(settings.max_users),
             open_seats: VecDeque::from_iter(0..settings.max_players),
             players: Vec::with_capacity(settings.max_players),
             board: Vec::with_capacity(5),
+            second_board: Vec::with_capacity(5),
+            runout_idx: None,
+            players_running_it_twice: BTreeSet::new(),
+            players_voting_bomb_pot: BTreeSet::new(),
+            players_mucking: BTreeSet::new(),
             num_players_active: 0,
             num_players_called: 0,
             pot: Pot::new(settings.max_players),
             players_to_remove: BTreeSet::new(),
             players_to_spectate: BTreeSet::new(),
+            players_sitting_out: BTreeSet::new(),
+            players_buying_in: BTreeMap::new(),
+            rebuys: HashMap::new(),
+            seat_requests: HashMap::new(),
             deck_idx: 0,
             small_blind_idx: 0,
             big_blind_idx: 1,
+            big_blind_seat_idx: None,
+            is_small_blind_dead: false,
+            is_bomb_pot: false,
+            hands_since_bomb_pot: 0,
             starting_action_idx: 2,
             next_action_idx: None,
+            tournament: settings.tournament.as_ref().map(Tournament::new),
+            eliminators: BTreeMap::new(),
+            hand_history: HandHistory::default(),
             settings,
         }
     }
@@ -164,30 +1252,52 @@ impl From<GameSettings> for GameData {
     fn from(value: GameSettings) -> Self {
         Self {
             deck: functional::new_deck(),
+            rng: match value.seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            },
             donations: 0.0,
+            jackpot: 0,
             small_blind: value.min_small_blind,
             big_blind: value.min_big_blind,
+            ante: value.min_ante,
             spectators: HashMap::with_capacity(value.max_users),
             waitlist: VecDeque::with_capacity(value.max_users),
             open_seats: VecDeque::from_iter(0..value.max_players),
             players: Vec::with_capacity(value.max_players),
             board: Vec::with_capacity(5),
+            second_board: Vec::with_capacity(5),
+            runout_idx: None,
+            players_running_it_twice: BTreeSet::new(),
+            players_voting_bomb_pot: BTreeSet::new(),
+            players_mucking: BTreeSet::new(),
             num_players_active: 0,
             num_players_called: 0,
             pot: Pot::new(value.max_players),
             players_to_remove: BTreeSet::new(),
             players_to_spectate: BTreeSet::new(),
+            players_sitting_out: BTreeSet::new(),
+            players_buying_in: BTreeMap::new(),
+            rebuys: HashMap::new(),
+            seat_requests: HashMap::new(),
             deck_idx: 0,
             small_blind_idx: 0,
             big_blind_idx: 1,
+            big_blind_seat_idx: None,
+            is_small_blind_dead: false,
+            is_bomb_pot: false,
+            hands_since_bomb_pot: 0,
             starting_action_idx: 2,
             next_action_idx: None,
+            tournament: value.tournament.as_ref().map(Tournament::new),
+            eliminators: BTreeMap::new(),
+            hand_history: HandHistory::default(),
             settings: value,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Lobby {
     start_game: bool,
 }
@@ -204,37 +1314,38 @@ impl Lobby {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct SeatPlayers {}
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct MoveButton {}
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct CollectBlinds {}
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Deal {}
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TakeAction {
     pub action_options: Option<HashSet<Action>>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Flop {}
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Turn {}
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct River {}
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ShowHands {
-    /// Temporarily maps player seats to poker hand evaluations so a player's
-    /// hand doesn't have to be evaluated multiple times per game.
-    hand_eval_cache: HashMap<usize, Vec<SubHand>>,
+    /// Temporarily maps boards and player seats to poker hand evaluations so
+    /// a player's hand doesn't have to be evaluated multiple times per game.
+    #[serde(skip)]
+    hand_eval_cache: HashMap<(usize, usize), Vec<SubHand>>,
 }
 
 impl Default for ShowHands {
@@ -251,27 +1362,63 @@ impl ShowHands {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DistributePot {
-    /// Temporarily maps player seats to poker hand evaluations so a player's
-    /// hand doesn't have to be evaluated multiple times per game.
-    hand_eval_cache: HashMap<usize, Vec<SubHand>>,
+    /// Temporarily maps boards and player seats to poker hand evaluations so
+    /// a player's hand doesn't have to be evaluated multiple times per game.
+    #[serde(skip)]
+    hand_eval_cache: HashMap<(usize, usize), Vec<SubHand>>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct RemovePlayers {}
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct DivideDonations {}
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct UpdateBlinds {}
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct BootPlayers {}
 
+/// Label each player's position around the table, or nothing for
+/// players that aren't dealt in. Players between the big blind and the
+/// button are under the gun, with the last three of them labeled cutoff,
+/// hijack, and lojack working back from the button.
+pub(crate) fn label_positions(
+    is_dealt_in: &[bool],
+    button_idx: usize,
+    small_blind_idx: usize,
+    big_blind_idx: usize,
+    is_small_blind_dead: bool,
+) -> Vec<Option<Position>> {
+    let num_players = is_dealt_in.len();
+    let mut positions = vec![None; num_players];
+    let between: Vec<_> = (1..num_players)
+        .map(|offset| (big_blind_idx + offset) % num_players)
+        .take_while(|player_idx| *player_idx != button_idx)
+        .filter(|player_idx| is_dealt_in[*player_idx])
+        .collect();
+    for (i, player_idx) in between.iter().enumerate() {
+        positions[*player_idx] = Some(match between.len() - i {
+            _ if i == 0 => Position::UnderTheGun(0),
+            1 => Position::Cutoff,
+            2 => Position::Hijack,
+            3 => Position::Lojack,
+            _ => Position::UnderTheGun(i),
+        });
+    }
+    positions[button_idx] = Some(Position::Button);
+    if !is_small_blind_dead && small_blind_idx != button_idx {
+        positions[small_blind_idx] = Some(Position::SmallBlind);
+    }
+    positions[big_blind_idx] = Some(Position::BigBlind);
+    positions
+}
+
 /// A poker game.
-#[derive(Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Game<T> {
     pub data: GameData,
     pub state: T,
@@ -279,13 +1426,16 @@ pub struct Game<T> {
 
 /// General game methods.
 impl<T> Game<T> {
-    pub fn action_options_to_string(action_options: &HashSet<Action>) -> String {
+    pub fn action_options_to_string(
+        action_options: &HashSet<Action>,
+        chip_display: ChipDisplay,
+    ) -> String {
         let num_options = action_options.len();
         action_options
             .iter()
             .enumerate()
             .map(|(i, action)| {
-                let repr = action.to_option_string();
+                let repr = action.to_option_string(chip_display);
                 match i {
                     0 if num_options == 1 => repr,
                     0 if num_options == 2 => format!("{repr} "),
@@ -298,18 +1448,44 @@ impl<T> Game<T> {
             .join("")
     }
 
-    fn as_view(&self, username: &str) -> GameView {
+    /// Whether the player's hand is face up for everyone to see. Players
+    /// turn their hands face up by showing them, and hands still in the
+    /// pot are turned face up once betting is over so everyone can sweat
+    /// the run-out.
+    fn is_face_up(&self, player: &Player) -> bool {
+        player.state == PlayerState::Show
+            || (self.data.runout_idx.is_some()
+                && !matches!(player.state, PlayerState::Fold | PlayerState::SitOut))
+    }
+
+    /// The player's hole cards that the viewer is allowed to see. This is
+    /// the only place hole cards make it into views, so it's all that
+    /// keeps opponents' hands from leaking to users.
+    fn get_visible_cards(&self, player: &Player, viewer: Viewer) -> Vec<Card> {
+        let is_visible = match viewer {
+            Viewer::User(username) => player.user.name == username || self.is_face_up(player),
+            Viewer::Public => self.is_face_up(player),
+            Viewer::Omniscient => true,
+        };
+        if is_visible {
+            player.cards.clone()
+        } else {
+            vec![]
+        }
+    }
+
+    fn as_view(&self, viewer: Viewer) -> GameView {
         let mut players = Vec::with_capacity(self.data.settings.max_players);
-        for player in self.data.players.iter() {
-            let cards = if player.user.name == username || player.state == PlayerState::Show {
-                player.cards.clone()
-            } else {
-                vec![]
-            };
+        let positions = self.get_positions();
+        for (player_idx, (player, position)) in self.data.players.iter().zip(positions).enumerate()
+        {
             let player_view = PlayerView {
                 user: player.user.clone(),
                 state: player.state.clone(),
-                cards,
+                cards: self.get_visible_cards(player, viewer),
+                investment: self.data.pot.get_investment_by_player_idx(player_idx),
+                seat_idx: player.seat_idx,
+                position,
             };
             players.push(player_view);
         }
@@ -323,17 +1499,55 @@ impl<T> Game<T> {
             donations: self.data.donations,
             small_blind: self.data.small_blind,
             big_blind: self.data.big_blind,
+            ante: self.data.ante,
+            is_big_blind_ante: self.data.settings.is_big_blind_ante,
+            bomb_pot: self
+                .data
+                .settings
+                .bomb_pot
+                .as_ref()
+                .filter(|_| self.data.is_bomb_pot)
+                .map(|bomb_pot| bomb_pot.ante),
             spectators: self.data.spectators.clone(),
             waitlist: self.data.waitlist.clone(),
             open_seats: self.data.open_seats.clone(),
             players,
             board: self.data.board.clone(),
+            second_board: self.data.second_board.clone(),
             pot: PotView {
                 size: self.data.pot.get_size(),
+                call: self.data.pot.get_call(),
+                side_pots: self.get_side_pot_views(),
+                chip_display: self.data.settings.chip_display,
             },
             small_blind_idx: self.data.small_blind_idx,
             big_blind_idx: self.data.big_blind_idx,
+            is_small_blind_dead: self.data.is_small_blind_dead,
             next_action_idx,
+            action_on: next_action_idx
+                .and_then(|idx| self.data.players.get(idx))
+                .map(|player| player.user.name.clone()),
+            button_idx: (self.get_num_players_dealt_in() >= 2).then(|| self.get_button_idx()),
+            actions: self.data.hand_history.actions.clone(),
+            tournament: self.get_tournament_view(),
+            seat_requests: self.data.seat_requests.clone(),
+            waitlist_position: match viewer {
+                Viewer::User(username) => self
+                    .data
+                    .waitlist
+                    .iter()
+                    .position(|u| u.name == username)
+                    .map(|idx| idx + 1),
+                Viewer::Public | Viewer::Omniscient => None,
+            },
+            jackpot: self
+                .data
+                .settings
+                .jackpot
+                .as_ref()
+                .map(|_| self.data.jackpot),
+            turn_timer: None,
+            chip_display: self.data.settings.chip_display,
         }
     }
 
@@ -412,7 +1626,7 @@ impl<T> Game<T> {
     /// Return the username of the user that has the next turn (or nothing
     /// if there is no turn next). Helps determine whether to notify the
     /// player that their turn has come.
-    pub fn get_next_action_username(&self) -> Option<String> {
+    pub fn get_next_action_username(&self) -> Option<Username> {
         self.data
             .next_action_idx
             .map(|action_idx| self.data.players[action_idx].user.name.clone())
@@ -429,15 +1643,34 @@ impl<T> Game<T> {
         self.data.players.len()
     }
 
-    /// Return the number of players plus the number of players in
-    /// the waitlist. This is equal to the number of players that
-    /// could play the game if the game started. This helps determine
-    /// whether the game can actually start.
-    pub fn get_num_potential_players(&self) -> usize {
+    /// Return the number of players that aren't sitting out.
+    fn get_num_players_dealt_in(&self) -> usize {
+        self.data
+            .players
+            .iter()
+            .filter(|p| p.state != PlayerState::SitOut)
+            .count()
+    }
+
+    /// Return the number of players that haven't folded.
+    fn get_num_players_in_hand(&self) -> usize {
+        self.data
+            .players
+            .iter()
+            .filter(|p| !matches!(p.state, PlayerState::Fold | PlayerState::SitOut))
+            .count()
+    }
+
+    /// Return the number of players plus the number of players in
+    /// the waitlist, less the players sitting out. This is equal to the
+    /// number of players that could play the game if the game started.
+    /// This helps determine whether the game can actually start.
+    pub fn get_num_potential_players(&self) -> usize {
+        let num_sitting_out = self.get_num_players() - self.get_num_players_dealt_in();
         min(
             self.data.players.len() + self.data.waitlist.len(),
             self.data.settings.max_players,
-        )
+        ) - num_sitting_out
     }
 
     /// Return the number of pots, indicating whether to continue
@@ -458,10 +1691,71 @@ impl<T> Game<T> {
         unique_investments.len()
     }
 
+    pub fn get_tournament_view(&self) -> Option<TournamentView> {
+        self.data
+            .tournament
+            .as_ref()
+            .map(|tournament| TournamentView {
+                level: tournament.level + 1,
+                time_to_next_level: tournament.time_to_next_level(),
+                winner: tournament.winner.clone(),
+                bounty: tournament.bounty,
+                bounties_won: tournament.bounties_won.clone(),
+                is_registration_open: tournament.is_registration_open(),
+                entries: tournament.get_num_entries(),
+                prize_pool: (tournament.get_num_entries() as Usd)
+                    .saturating_mul(self.data.settings.buy_in),
+                is_hand_for_hand: tournament.is_hand_for_hand,
+            })
+    }
+
     fn get_num_users(&self) -> usize {
         self.data.spectators.len() + self.data.waitlist.len() + self.data.players.len()
     }
 
+    /// Return the main pot and side pots, and the players that can win
+    /// each of them.
+    fn get_side_pot_views(&self) -> Vec<SidePotView> {
+        let mut contesting = BTreeSet::new();
+        let mut all_in = BTreeSet::new();
+        for (player_idx, player) in self.data.players.iter().enumerate() {
+            match player.state {
+                PlayerState::Wait => {}
+                PlayerState::AllIn | PlayerState::Show => {
+                    all_in.insert(player_idx);
+                }
+                PlayerState::Fold | PlayerState::SitOut => continue,
+            }
+            contesting.insert(player_idx);
+        }
+        self.data
+            .pot
+            .side_pots(&contesting, &all_in)
+            .into_iter()
+            .map(|side_pot| SidePotView {
+                size: side_pot.size,
+                eligible: side_pot
+                    .seats
+                    .iter()
+                    .map(|player_idx| self.data.players[*player_idx].user.name.clone())
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Return a view of the game with only the hands that are face up,
+    /// like for someone who isn't at the table.
+    pub fn get_public_view(&self) -> GameView {
+        self.as_view(Viewer::Public)
+    }
+
+    /// Return a view of the game with every player's cards shown, like
+    /// for replaying a hand after it's over. It has every player's hole
+    /// cards, so it's never for users.
+    pub fn get_revealed_view(&self) -> GameView {
+        self.as_view(Viewer::Omniscient)
+    }
+
     /// Return independent views of the game for each user. For non-players,
     /// only the board is shown until the showdown. For players, only their
     /// hand and the board is shown until the showdown.
@@ -474,7 +1768,7 @@ impl<T> Game<T> {
             .chain(self.data.waitlist.iter().map(|u| &u.name))
             .chain(self.data.players.iter().map(|p| &p.user.name))
         {
-            views.insert(username.to_string(), self.as_view(username));
+            views.insert(username.clone(), self.as_view(Viewer::User(username)));
         }
         views
     }
@@ -485,10 +1779,100 @@ impl<T> Game<T> {
         self.data.num_players_active == self.data.num_players_called
     }
 
+    /// Return the index of the player with the button. Heads-up, the
+    /// small blind has the button, and the button stays put on a dead
+    /// small blind. Otherwise, it's on the player dealt in before the
+    /// small blind.
+    fn get_button_idx(&self) -> usize {
+        let small_blind_idx = self.data.small_blind_idx;
+        if self.is_heads_up() || self.data.is_small_blind_dead {
+            return small_blind_idx;
+        }
+        let num_players = self.get_num_players();
+        (1..num_players)
+            .map(|offset| (small_blind_idx + num_players - offset) % num_players)
+            .find(|player_idx| self.data.players[*player_idx].state != PlayerState::SitOut)
+            .unwrap_or(small_blind_idx)
+    }
+
+    /// Return how many seats the player is to the left of the button,
+    /// starting from 0 for the player right after it, so the button is
+    /// the furthest.
+    fn get_seats_left_of_button(&self, player_idx: usize) -> usize {
+        let num_players = self.get_num_players();
+        (player_idx + num_players - self.get_button_idx() - 1) % num_players
+    }
+
+    /// Return each player's position, or nothing for players that aren't
+    /// dealt in.
+    fn get_positions(&self) -> Vec<Option<Position>> {
+        // Blinds can point past the players that are left until the
+        // button moves for the next hand.
+        let num_players = self.get_num_players();
+        if self.get_num_players_dealt_in() < 2
+            || self.data.small_blind_idx >= num_players
+            || self.data.big_blind_idx >= num_players
+        {
+            return vec![None; num_players];
+        }
+        let is_dealt_in: Vec<_> = self
+            .data
+            .players
+            .iter()
+            .map(|player| player.state != PlayerState::SitOut)
+            .collect();
+        label_positions(
+            &is_dealt_in,
+            self.get_button_idx(),
+            self.data.small_blind_idx,
+            self.data.big_blind_idx,
+            self.data.is_small_blind_dead,
+        )
+    }
+
+    /// Return whether there are only two players at the table. Heads-up
+    /// play has some special rules around the button and blinds.
+    pub fn is_heads_up(&self) -> bool {
+        self.get_num_players_dealt_in() == 2
+    }
+
+    /// Return whether players can still agree to run it twice. Betting
+    /// must be over with multiple players remaining in the hand, and the
+    /// rest of the board can't have been dealt yet.
+    pub fn can_run_it_twice(&self) -> bool {
+        let board_len = self.data.board.len();
+        let is_before_runout = match self.data.runout_idx {
+            Some(runout_idx) => runout_idx == board_len,
+            None => self.is_ready_for_showdown(),
+        };
+        is_before_runout && board_len < 5 && self.get_num_players_in_hand() > 1
+    }
+
+    /// Return whether every player remaining in the hand has agreed to
+    /// run it twice.
+    pub fn is_running_it_twice(&self) -> bool {
+        self.data.runout_idx.is_some()
+            && self
+                .data
+                .players
+                .iter()
+                .filter(|p| !matches!(p.state, PlayerState::Fold | PlayerState::SitOut))
+                .all(|p| self.data.players_running_it_twice.contains(&p.user.name))
+    }
+
     pub fn is_pot_empty(&self) -> bool {
         self.data.pot.is_empty()
     }
 
+    /// Return whether the rest of the board is being dealt without any
+    /// more betting, and some of it has been dealt already. Players can
+    /// no longer agree to run it twice at that point.
+    pub fn is_running_out(&self) -> bool {
+        self.data
+            .runout_idx
+            .is_some_and(|runout_idx| self.data.board.len() > runout_idx)
+    }
+
     /// Return whether the betting round is over and the game can continue
     /// to the next phase. Used to help signal state transitions.
     pub fn is_ready_for_next_phase(&self) -> bool {
@@ -537,11 +1921,13 @@ impl<T> Game<T> {
             } else {
                 return Ok(false);
             }
+        } else if self.data.spectators.len() >= self.data.settings.max_spectators {
+            return Err(UserError::SpectatorsFull);
         }
         self.data.spectators.insert(
-            username.to_string(),
+            username.into(),
             User {
-                name: username.to_string(),
+                name: username.into(),
                 money: self.data.settings.buy_in,
             },
         );
@@ -553,30 +1939,87 @@ impl<T> Game<T> {
     /// in preparation for a new round of betting.
     fn prepare_for_next_phase(&mut self) -> Option<HashSet<Action>> {
         self.data.num_players_called = 0;
-        self.data.next_action_idx = Some(self.data.starting_action_idx);
+        // Heads-up, the small blind has the button and acts first before the
+        // flop, but the big blind acts first on every street after the flop.
+        let starting_action_idx = if self.is_heads_up() && !self.data.board.is_empty() {
+            self.data.big_blind_idx
+        } else {
+            self.data.starting_action_idx
+        };
+        self.data.next_action_idx = Some(starting_action_idx);
         self.data.next_action_idx = self.get_next_action_idx(true);
         self.get_next_action_options()
     }
 
     fn redistribute_user_money(&mut self, money: &mut Usd) {
-        self.data.donations += (*money as Usdf) - (self.data.settings.buy_in as Usdf);
+        // Tournament money is forfeited when a player leaves.
+        if self.data.tournament.is_none() {
+            self.data.donations += (*money as Usdf) - (self.data.settings.buy_in as Usdf);
+        }
         *money = 0;
     }
 
+    /// Buy a user more money, either to rebuy after going broke or to top
+    /// up a short stack. Players in the middle of a hand get the money once
+    /// the hand is over. Returns whether the user got the money right away.
+    pub fn buy_in_user(&mut self, username: &str, amount: Usd) -> Result<bool, UserError> {
+        self.data.buy_in_user(username, amount)
+    }
+
+    /// Sit a player out, keeping their seat and stack without dealing them
+    /// in. Players that opt to sit out in the middle of a hand play it out
+    /// first. Returns whether the player is already sitting out.
+    pub fn sit_out_user(&mut self, username: &str) -> Result<bool, UserError> {
+        if self.data.is_tournament_running() {
+            return Err(UserError::CannotSitOut);
+        }
+        match self.data.players.iter().find(|p| p.user.name == username) {
+            Some(player) => {
+                let is_sitting_out = player.state == PlayerState::SitOut;
+                self.data.players_sitting_out.insert(username.into());
+                Ok(is_sitting_out)
+            }
+            None => Err(UserError::UserNotPlaying),
+        }
+    }
+
     /// Add a user to the waitlist, putting them in queue to play. The queue
     /// is eventually drained until the table is full and there are no more
-    /// seats available for play.
+    /// seats available for play. Players sitting out are dealt back in.
+    /// Waitlisters that asked for a seat are happy to take any seat again.
     pub fn waitlist_user(&mut self, username: &str) -> Result<bool, UserError> {
-        // Need to remove the player from the removal and spectate sets just in
-        // case they wanted to do one of those, but then changed their mind and
-        // want to play again.
+        // Need to remove the player from the removal, spectate, and sit out
+        // sets just in case they wanted to do one of those, but then changed
+        // their mind and want to play again.
+        self.data.seat_requests.remove(username);
         self.data.players_to_spectate.remove(username);
         self.data.players_to_remove.remove(username);
+        self.data.players_sitting_out.remove(username);
+        // Spectators can only join a running tournament while registration
+        // is open. New players and busted players buy in for a fresh
+        // stack, but players that left with money keep it.
+        if let (Some(user), Some(tournament)) = (
+            self.data.spectators.get_mut(username),
+            self.data.tournament.as_mut().filter(|t| t.is_running()),
+        ) {
+            if !tournament.is_registration_open() {
+                return Err(UserError::TournamentInProgress);
+            }
+            if user.money == 0 || !tournament.entries.contains_key(username) {
+                user.money = self.data.settings.buy_in;
+                *tournament.entries.entry(username.into()).or_default() += 1;
+            }
+        }
         if let Some(user) = self.data.spectators.remove(username) {
-            if user.money < self.data.big_blind {
-                self.data.spectators.insert(username.to_string(), user);
+            if !self.data.can_play(user.money) && self.data.tournament.is_none() {
+                self.data.spectators.insert(username.into(), user);
                 return Err(UserError::InsufficientFunds {
-                    big_blind: self.data.big_blind,
+                    big_blind: self.data.settings.chips(self.data.big_blind),
+                });
+            } else if user.money < self.data.settings.min_buy_in && self.data.tournament.is_none() {
+                self.data.spectators.insert(username.into(), user);
+                return Err(UserError::StackBelowMinBuyIn {
+                    min_buy_in: self.data.settings.chips(self.data.settings.min_buy_in),
                 });
             }
             self.data.waitlist.push_back(user);
@@ -602,9 +2045,11 @@ macro_rules! impl_user_managers {
                 let mut user = if let Some(user) = self.data.spectators.remove(username) {
                     user
                 } else if let Some(waitlist_idx) = self.data.waitlist.iter().position(|u| u.name == username) {
+                    self.data.seat_requests.remove(username);
                     self.data.waitlist.remove(waitlist_idx).expect("waitlister exists")
                 } else if let Some(player_idx) = self.data.players.iter().position(|p| p.user.name == username) {
                     self.data.players_to_spectate.remove(username);
+                    self.data.players_sitting_out.remove(username);
                     let player = self.data.players.remove(player_idx);
                     self.data.open_seats.push_back(player.seat_idx);
                     player.user
@@ -624,16 +2069,18 @@ macro_rules! impl_user_managers {
                 let user = if self.data.spectators.contains_key(username) {
                     return Ok(true);
                 } else if let Some(waitlist_idx) = self.data.waitlist.iter().position(|u| u.name == username) {
+                    self.data.seat_requests.remove(username);
                     self.data.waitlist.remove(waitlist_idx).expect("waitlister exists")
                 } else if let Some(player_idx) = self.data.players.iter().position(|p| p.user.name == username) {
                     self.data.players_to_remove.remove(username);
+                    self.data.players_sitting_out.remove(username);
                     let player = self.data.players.remove(player_idx);
                     self.data.open_seats.push_back(player.seat_idx);
                     player.user
                 } else {
                     return Err(UserError::UserDoesNotExist);
                 };
-                self.data.spectators.insert(username.to_string(), user);
+                self.data.spectators.insert(username.into(), user);
                 Ok(true)
             }
         })*
@@ -652,6 +2099,7 @@ macro_rules! impl_user_managers_with_queue {
                 let mut user = if let Some(user) = self.data.spectators.remove(username) {
                     user
                 } else if let Some(waitlist_idx) = self.data.waitlist.iter().position(|u| u.name == username) {
+                    self.data.seat_requests.remove(username);
                     self.data.waitlist.remove(waitlist_idx).expect("waitlister exists")
                 } else if let Some(_) = self.data.players.iter().position(|p| p.user.name == username) {
                     // Need to remove the player from other queues just in
@@ -660,7 +2108,7 @@ macro_rules! impl_user_managers_with_queue {
                     // The player is still at the table while the game is ongoing.
                     // We don't want to disrupt gameplay, so we just queue the
                     // player for removal and remove them later.
-                    self.data.players_to_remove.insert(username.to_string());
+                    self.data.players_to_remove.insert(username.into());
                     return Ok(false);
                 } else {
                     return Err(UserError::UserDoesNotExist);
@@ -678,17 +2126,18 @@ macro_rules! impl_user_managers_with_queue {
                 let user = if self.data.spectators.contains_key(username) {
                     return Ok(true)
                 } else if let Some(waitlist_idx) = self.data.waitlist.iter().position(|u| u.name == username) {
+                    self.data.seat_requests.remove(username);
                     self.data.waitlist.remove(waitlist_idx).expect("waitlister exists")
                 } else if let Some(_) = self.data.players.iter().position(|p| p.user.name == username) {
                     // Need to remove the player from other queues just in
                     // case they changed their mind.
                     self.data.players_to_remove.remove(username);
-                    self.data.players_to_spectate.insert(username.to_string());
+                    self.data.players_to_spectate.insert(username.into());
                     return Ok(false);
                 } else {
                     return Err(UserError::UserDoesNotExist);
                 };
-                self.data.spectators.insert(username.to_string(), user);
+                self.data.spectators.insert(username.into(), user);
                 Ok(true)
             }
         })*
@@ -717,6 +2166,25 @@ impl_user_managers_with_queue!(
 );
 
 impl Game<Lobby> {
+    /// Return the users at the game and their money so the game can be
+    /// restored later.
+    pub fn get_snapshot(&self) -> GameSnapshot {
+        let mut spectators: Vec<_> = self.data.spectators.values().cloned().collect();
+        spectators.sort();
+        GameSnapshot {
+            donations: self.data.donations,
+            spectators,
+            waitlist: self.data.waitlist.iter().cloned().collect(),
+            players: self
+                .data
+                .players
+                .iter()
+                .map(|p| (p.seat_idx, p.user.clone()))
+                .collect(),
+            jackpot: self.data.jackpot,
+        }
+    }
+
     pub fn init_start(&mut self) -> Result<(), UserError> {
         match (self.state.start_game, self.get_num_potential_players() >= 2) {
             (false, false) => Err(UserError::NotEnoughPlayers),
@@ -731,6 +2199,30 @@ impl Game<Lobby> {
     pub fn is_ready_to_start(&self) -> bool {
         self.state.start_game && self.get_num_potential_players() >= 2
     }
+
+    /// Restore a game from a snapshot. Players that can't be seated
+    /// (e.g., because their seat no longer exists) are put back in the
+    /// waitlist.
+    pub fn restore(settings: GameSettings, snapshot: GameSnapshot) -> Self {
+        let mut game: Game<Lobby> = settings.into();
+        game.data.donations = snapshot.donations;
+        game.data.jackpot = snapshot.jackpot;
+        for user in snapshot.spectators {
+            game.data.spectators.insert(user.name.clone(), user);
+        }
+        game.data.waitlist.extend(snapshot.waitlist);
+        for (seat_idx, user) in snapshot.players {
+            match game.data.open_seats.iter().position(|idx| *idx == seat_idx) {
+                Some(open_seat_idx) => {
+                    game.data.open_seats.remove(open_seat_idx);
+                    game.data.players.push(Player::new(user, seat_idx));
+                }
+                None => game.data.waitlist.push_back(user),
+            }
+        }
+        game.data.players.sort_by_key(|p| p.seat_idx);
+        game
+    }
 }
 
 impl From<GameSettings> for Game<Lobby> {
@@ -744,7 +2236,32 @@ impl From<GameSettings> for Game<Lobby> {
 }
 
 impl From<Game<Lobby>> for Game<SeatPlayers> {
-    fn from(value: Game<Lobby>) -> Self {
+    fn from(mut value: Game<Lobby>) -> Self {
+        // Starting a game also starts a tournament if one isn't already
+        // running. Everyone that's registered for the tournament starts
+        // with the same stack.
+        let is_tournament_starting = value.data.tournament.as_mut().is_some_and(|tournament| {
+            if !tournament.is_running() {
+                tournament.start();
+                return true;
+            }
+            false
+        });
+        if is_tournament_starting {
+            value
+                .data
+                .set_blind_multiple(Tournament::get_blind_multiple(0));
+            let tournament = value.data.tournament.as_mut().expect("tournament exists");
+            for user in value
+                .data
+                .waitlist
+                .iter_mut()
+                .chain(value.data.players.iter_mut().map(|p| &mut p.user))
+            {
+                user.money = value.data.settings.buy_in;
+                tournament.entries.insert(user.name.clone(), 1);
+            }
+        }
         Self {
             data: value.data,
             state: SeatPlayers {},
@@ -763,44 +2280,43 @@ impl From<Game<SeatPlayers>> for Game<Lobby> {
 
 impl From<Game<SeatPlayers>> for Game<MoveButton> {
     fn from(mut value: Game<SeatPlayers>) -> Self {
-        loop {
-            match (
-                value.data.open_seats.pop_front(),
-                value.data.waitlist.pop_front(),
-            ) {
-                (Some(open_seat_idx), Some(user)) => {
-                    if user.money < value.data.big_blind {
-                        value.data.spectators.insert(user.name.clone(), user);
-                    } else {
-                        let num_players = value.get_num_players();
-                        let player = Player::new(user, open_seat_idx);
-                        if num_players > 0 {
-                            match (0..num_players - 1).position(|player_idx| {
-                                value.data.players[player_idx].seat_idx < open_seat_idx
-                                    && value.data.players[player_idx + 1].seat_idx > open_seat_idx
-                            }) {
-                                Some(player_idx) => {
-                                    value.data.players.insert(player_idx + 1, player)
-                                }
-                                None => value.data.players.push(player),
-                            }
-                        } else {
-                            value.data.players.push(player);
-                        }
-                    }
-                    continue;
-                }
-                (Some(open_seat_idx), None) => {
-                    value.data.open_seats.push_front(open_seat_idx);
+        value.data.update_sitting_out();
+        // Waitlisters are seated in the order they joined the waitlist.
+        // Waitlisters waiting on a seat that's taken keep their place in
+        // line while the ones behind them take other seats.
+        let mut waitlist = VecDeque::with_capacity(value.data.waitlist.len());
+        while let Some(user) = value.data.waitlist.pop_front() {
+            let open_seat_idx = match value.data.seat_requests.get(&user.name) {
+                Some(seat_idx) => value.data.open_seats.iter().position(|idx| idx == seat_idx),
+                None if value.data.open_seats.is_empty() => None,
+                None => Some(0),
+            };
+            match open_seat_idx {
+                Some(_) if !value.data.can_play(user.money) => {
+                    value.data.seat_requests.remove(&user.name);
+                    value.data.spectators.insert(user.name.clone(), user);
                 }
-                (None, Some(user)) => {
-                    value.data.waitlist.push_front(user);
+                Some(open_seat_idx) => {
+                    value.data.seat_requests.remove(&user.name);
+                    let seat_idx = value
+                        .data
+                        .open_seats
+                        .remove(open_seat_idx)
+                        .expect("open seat exists");
+                    let player_idx = value
+                        .data
+                        .players
+                        .partition_point(|p| p.seat_idx < seat_idx);
+                    value
+                        .data
+                        .players
+                        .insert(player_idx, Player::new(user, seat_idx));
                 }
-                (None, None) => {}
+                None => waitlist.push_back(user),
             }
-            break;
         }
-        value.data.num_players_active = value.get_num_players();
+        value.data.waitlist = waitlist;
+        value.data.num_players_active = value.get_num_players_dealt_in();
         Self {
             data: value.data,
             state: MoveButton {},
@@ -810,32 +2326,53 @@ impl From<Game<SeatPlayers>> for Game<MoveButton> {
 
 /// Move the blinds and next action indices, preparing the next game
 /// by determining who will be paying blinds and who will be making
-/// the first action.
+/// the first action. Heads-up, this naturally puts the small blind
+/// on the button and has them act first before the flop. Players
+/// sitting out are skipped.
+///
+/// The big blind always moves to the next player after the last big
+/// blind's seat, so no one skips it or pays it twice when players bust
+/// or leave. The last big blind pays the small blind, and if they're
+/// gone, the small blind is dead and no one pays it that hand rather
+/// than making someone pay it twice.
 impl From<Game<MoveButton>> for Game<CollectBlinds> {
     fn from(mut value: Game<MoveButton>) -> Self {
-        let num_players = value.get_num_players();
-        let player_indices = value
+        let player_indices: Vec<_> = value
             .data
             .players
             .iter()
             .enumerate()
-            .map(|(player_idx, _)| player_idx);
+            .filter(|(_, p)| p.state != PlayerState::SitOut)
+            .map(|(player_idx, _)| player_idx)
+            .collect();
+        let num_players = player_indices.len();
+        let players = &value.data.players;
         // Search for the big blind and starting positions.
-        let mut seats = player_indices
-            .clone()
-            .cycle()
-            .skip(value.data.big_blind_idx + 1);
-        value.data.big_blind_idx = seats.next().expect("big blind position exists");
-        value.data.starting_action_idx = seats.next().expect("starting action position exists");
+        let big_blind_pos = match value.data.big_blind_seat_idx {
+            Some(seat_idx) => player_indices
+                .iter()
+                .position(|player_idx| players[*player_idx].seat_idx > seat_idx),
+            None => player_indices
+                .iter()
+                .position(|player_idx| *player_idx > value.data.big_blind_idx),
+        }
+        .unwrap_or(0);
+        let mut seats = player_indices.iter().cycle().skip(big_blind_pos);
+        value.data.big_blind_idx = *seats.next().expect("big blind position exists");
+        value.data.starting_action_idx = *seats.next().expect("starting action position exists");
         value.data.next_action_idx = Some(value.data.starting_action_idx);
-        // Reverse the table search to find the small blind position relative
-        // to the big blind position since the small blind must always trail the big
-        // blind.
-        let mut seats = player_indices
-            .rev()
-            .cycle()
-            .skip(num_players - value.data.big_blind_idx);
-        value.data.small_blind_idx = seats.next().expect("small blind position exists");
+        // The small blind must always trail the big blind.
+        value.data.small_blind_idx =
+            player_indices[(big_blind_pos + num_players - 1) % num_players];
+        // Heads-up, both blinds are always paid.
+        let small_blind_seat_idx = players[value.data.small_blind_idx].seat_idx;
+        value.data.is_small_blind_dead = num_players > 2
+            && value
+                .data
+                .big_blind_seat_idx
+                .is_some_and(|seat_idx| seat_idx != small_blind_seat_idx);
+        value.data.big_blind_seat_idx = Some(players[value.data.big_blind_idx].seat_idx);
+        value.data.is_bomb_pot = value.data.is_bomb_pot_due();
         Self {
             data: value.data,
             state: CollectBlinds {},
@@ -843,39 +2380,91 @@ impl From<Game<MoveButton>> for Game<CollectBlinds> {
     }
 }
 
-/// Collect blinds, initializing the main pot.
+/// Collect antes and blinds, or just antes for a bomb pot, initializing
+/// the main pot.
 impl From<Game<CollectBlinds>> for Game<Deal> {
     fn from(mut value: Game<CollectBlinds>) -> Self {
         value.data.pot = Pot::new(value.data.settings.max_players);
-        for (player_idx, blind) in [
-            (value.data.small_blind_idx, value.data.small_blind),
-            (value.data.big_blind_idx, value.data.big_blind),
-        ] {
-            let player = &mut value.data.players[player_idx];
-            let bet = match player.user.money.cmp(&blind) {
-                Ordering::Equal => {
-                    player.state = PlayerState::AllIn;
-                    value.data.num_players_active -= 1;
-                    Bet {
-                        action: BetAction::AllIn,
-                        amount: player.user.money,
-                    }
-                }
-                Ordering::Greater => {
-                    player.state = PlayerState::Wait;
-                    Bet {
-                        action: BetAction::Raise,
-                        amount: blind,
-                    }
+        let bomb_pot_ante = value
+            .data
+            .settings
+            .bomb_pot
+            .as_ref()
+            .filter(|_| value.data.is_bomb_pot)
+            .map(|bomb_pot| bomb_pot.ante);
+        value.data.hand_history = HandHistory {
+            players: value
+                .data
+                .players
+                .iter()
+                .filter(|p| p.state != PlayerState::SitOut)
+                .map(|p| (p.seat_idx, p.user.clone()))
+                .collect(),
+            small_blind: value.data.small_blind,
+            big_blind: value.data.big_blind,
+            ante: bomb_pot_ante.unwrap_or(value.data.ante),
+            is_big_blind_ante: value.data.settings.is_big_blind_ante && bomb_pot_ante.is_none(),
+            is_bomb_pot: bomb_pot_ante.is_some(),
+            small_blind_username: value.data.players[value.data.small_blind_idx]
+                .user
+                .name
+                .clone(),
+            big_blind_username: value.data.players[value.data.big_blind_idx]
+                .user
+                .name
+                .clone(),
+            is_small_blind_dead: value.data.is_small_blind_dead,
+            chip_display: value.data.settings.chip_display,
+            ..Default::default()
+        };
+        // Everyone antes the same in a bomb pot, so no one posts blinds.
+        if let Some(ante) = bomb_pot_ante {
+            for player_idx in 0..value.get_num_players() {
+                if value.data.players[player_idx].state != PlayerState::SitOut {
+                    value.data.post(player_idx, ante);
                 }
-                _ => unreachable!(
-                    "a player can't be in a game if they don't have enough for the big blind"
-                ),
+            }
+            value.data.hands_since_bomb_pot = 0;
+            value.data.players_voting_bomb_pot.clear();
+            value.data.num_players_called = 0;
+            return Self {
+                data: value.data,
+                state: Deal {},
             };
-            // Impossible for a side pot to be created from the blinds, so
-            // we don't even need to check.
-            value.data.pot.bet(player_idx, &bet);
-            player.user.money -= blind;
+        }
+        value.data.hands_since_bomb_pot += 1;
+        let is_big_blind_ante = value.data.settings.is_big_blind_ante;
+        // Antes are dead money, so they don't count toward the call or
+        // the minimum raise. Players that can't cover the ante are
+        // all-in for what they have instead, which caps a side pot they
+        // can still win.
+        let ante = value.data.ante;
+        if ante > 0 && !is_big_blind_ante {
+            for player_idx in 0..value.get_num_players() {
+                let player = &value.data.players[player_idx];
+                if player.state == PlayerState::SitOut {
+                    continue;
+                } else if player.user.money > ante {
+                    value.data.post_dead(player_idx, ante);
+                } else {
+                    value.data.post(player_idx, ante);
+                }
+            }
+        }
+        if !value.data.is_small_blind_dead {
+            value
+                .data
+                .post(value.data.small_blind_idx, value.data.small_blind);
+        }
+        value
+            .data
+            .post(value.data.big_blind_idx, value.data.big_blind);
+        // The big blind covers their blind before the table's ante, so
+        // a short stack always posts a full big blind if they can.
+        if is_big_blind_ante {
+            value
+                .data
+                .post_dead(value.data.big_blind_idx, value.data.ante);
         }
         value.data.num_players_called = 0;
         Self {
@@ -888,21 +2477,39 @@ impl From<Game<CollectBlinds>> for Game<Deal> {
 /// Shuffle the game's deck and deal 2 cards to each player.
 impl From<Game<Deal>> for Game<TakeAction> {
     fn from(mut value: Game<Deal>) -> Self {
-        value.data.deck.shuffle(&mut thread_rng());
+        value.data.deck.shuffle(&mut value.data.rng);
         value.data.deck_idx = 0;
 
         let num_players = value.get_num_players();
-        let mut seats = (0..num_players).cycle().skip(value.data.small_blind_idx);
+        let num_players_dealt_in = value.get_num_players_dealt_in();
+        let sitting_out: Vec<_> = value
+            .data
+            .players
+            .iter()
+            .map(|p| p.state == PlayerState::SitOut)
+            .collect();
+        let mut seats = (0..num_players)
+            .cycle()
+            .skip(value.data.small_blind_idx)
+            .filter(|player_idx| !sitting_out[*player_idx]);
         // Deal 2 cards per player, looping over players and dealing them 1 card
         // at a time.
-        while value.data.deck_idx < (2 * num_players) {
+        while value.data.deck_idx < (2 * num_players_dealt_in) {
             let deal_idx = seats.next().expect("dealing position exists");
             let player = &mut value.data.players[deal_idx];
             let card = value.data.deck[value.data.deck_idx];
             player.cards.push(card);
             value.data.deck_idx += 1;
         }
-        let action_options = value.prepare_for_next_phase();
+        // Bomb pots skip betting before the flop, so the round is over
+        // as soon as the cards are dealt.
+        let action_options = if value.data.is_bomb_pot {
+            value.data.num_players_called = value.data.num_players_active;
+            value.data.next_action_idx = None;
+            None
+        } else {
+            value.prepare_for_next_phase()
+        };
         Self {
             data: value.data,
             state: TakeAction { action_options },
@@ -911,8 +2518,27 @@ impl From<Game<Deal>> for Game<TakeAction> {
 }
 
 impl Game<TakeAction> {
+    /// Remember where the board was when betting ended if multiple players
+    /// are still in the hand so the board can be run twice from there.
+    fn start_runout(&mut self) {
+        if self.is_ready_for_showdown()
+            && self.get_num_players_in_hand() > 1
+            && self.data.board.len() < 5
+        {
+            self.data.runout_idx = Some(self.data.board.len());
+        }
+    }
+
     pub fn act(&mut self, action: Action) -> Result<Action, UserError> {
+        let player_idx = self.data.next_action_idx;
         let sanitized_action = self.affect(action)?;
+        if let Some(player_idx) = player_idx {
+            self.data.hand_history.actions.push(HandAction {
+                street: Street::from_num_community_cards(self.data.board.len()),
+                username: self.data.players[player_idx].user.name.clone(),
+                action: sanitized_action.clone(),
+            });
+        }
         self.data.next_action_idx = self.get_next_action_idx(false);
         self.state.action_options = self.get_next_action_options();
         Ok(sanitized_action)
@@ -997,7 +2623,8 @@ impl Game<TakeAction> {
 }
 
 impl From<Game<TakeAction>> for Game<Flop> {
-    fn from(value: Game<TakeAction>) -> Self {
+    fn from(mut value: Game<TakeAction>) -> Self {
+        value.start_runout();
         Self {
             data: value.data,
             state: Flop {},
@@ -1006,7 +2633,8 @@ impl From<Game<TakeAction>> for Game<Flop> {
 }
 
 impl From<Game<TakeAction>> for Game<Turn> {
-    fn from(value: Game<TakeAction>) -> Self {
+    fn from(mut value: Game<TakeAction>) -> Self {
+        value.start_runout();
         Self {
             data: value.data,
             state: Turn {},
@@ -1015,7 +2643,8 @@ impl From<Game<TakeAction>> for Game<Turn> {
 }
 
 impl From<Game<TakeAction>> for Game<River> {
-    fn from(value: Game<TakeAction>) -> Self {
+    fn from(mut value: Game<TakeAction>) -> Self {
+        value.start_runout();
         Self {
             data: value.data,
             state: River {},
@@ -1123,6 +2752,9 @@ impl From<Game<River>> for Game<TakeAction> {
 impl From<Game<River>> for Game<ShowHands> {
     fn from(mut value: Game<River>) -> Self {
         value.step();
+        if value.is_running_it_twice() {
+            value.deal_second_board();
+        }
         Self {
             data: value.data,
             state: ShowHands::new(),
@@ -1130,6 +2762,56 @@ impl From<Game<River>> for Game<ShowHands> {
     }
 }
 
+impl Game<River> {
+    /// Deal a second board for running it twice. The second board shares
+    /// the community cards that were dealt before the run-out.
+    fn deal_second_board(&mut self) {
+        let runout_idx = self.data.runout_idx.unwrap_or(self.data.board.len());
+        self.data.second_board = self.data.board[..runout_idx].to_vec();
+        while self.data.second_board.len() < 5 {
+            let card = self.data.deck[self.data.deck_idx];
+            self.data.second_board.push(card);
+            self.data.deck_idx += 1;
+        }
+    }
+}
+
+macro_rules! impl_run_it_twice {
+    ($($t:ty),+) => {
+        $(impl $t {
+            /// Agree or decline to run the rest of the board twice. Every
+            /// player remaining in the hand has to agree for the board to
+            /// be run twice.
+            pub fn run_it_twice(&mut self, username: &str, consent: bool) -> Result<(), UserError> {
+                if !self.can_run_it_twice() {
+                    return Err(UserError::CannotRunItTwice);
+                }
+                match self
+                    .data
+                    .players
+                    .iter()
+                    .find(|p| {
+                        p.user.name == username
+                            && !matches!(p.state, PlayerState::Fold | PlayerState::SitOut)
+                    })
+                {
+                    Some(_) => {
+                        if consent {
+                            self.data.players_running_it_twice.insert(username.into());
+                        } else {
+                            self.data.players_running_it_twice.remove(username);
+                        }
+                        Ok(())
+                    }
+                    None => Err(UserError::UserNotPlaying),
+                }
+            }
+        })*
+    }
+}
+
+impl_run_it_twice!(Game<TakeAction>, Game<Flop>, Game<Turn>, Game<River>);
+
 macro_rules! impl_show_hands {
     ($($t:ty),+) => {
         $(impl $t {
@@ -1138,7 +2820,7 @@ macro_rules! impl_show_hands {
                     .data
                     .players
                     .iter_mut()
-                    .find(|p| p.user.name == username)
+                    .find(|p| p.user.name == username && p.state != PlayerState::SitOut)
                 {
                     Some(player) => {
                         if player.state != PlayerState::Show {
@@ -1165,13 +2847,10 @@ impl_show_hands!(
 
 impl From<Game<ShowHands>> for Game<DistributePot> {
     fn from(mut value: Game<ShowHands>) -> Self {
-        let num_players_remaining: usize = value
-            .data
-            .players
-            .iter()
-            .map(|p| if p.state == PlayerState::Fold { 0 } else { 1 })
-            .sum();
-        if num_players_remaining > 1 {
+        // Hands are already face up after a run-out. Otherwise, winners
+        // show their hands as pots are awarded and losers get to choose
+        // whether to show or muck theirs.
+        if value.data.runout_idx.is_some() && value.get_num_players_in_hand() > 1 {
             for player_idx in value.data.pot.investments.keys() {
                 let player = &mut value.data.players[*player_idx];
                 match player.state {
@@ -1194,66 +2873,137 @@ impl Game<DistributePot> {
     /// hands to one another. Get the winning indices and distribute
     /// the pot accordingly.
     fn distribute(&mut self) {
-        let mut investments = Vec::from_iter(self.data.pot.investments.iter_mut());
-        investments
-            .sort_unstable_by(|(_, investment1), (_, investment2)| investment1.cmp(investment2));
-        if let Some((_, largest_call)) = investments.last() {
-            // Get the pot size and the player indices in the pot.
-            let mut pot_idx = investments.len() - 1;
-            let mut pot_call = **largest_call;
-            for (idx, (player_idx, investment)) in investments.iter().enumerate().rev() {
-                let player = &self.data.players[**player_idx];
-                if player.state != PlayerState::Fold && investment < largest_call {
-                    pot_call = **largest_call - **investment;
-                    break;
+        // Everyone left in the hand is done betting, so pots are
+        // distributed from the last side pot down to the main pot.
+        let contesting: BTreeSet<usize> = self
+            .data
+            .pot
+            .investments
+            .keys()
+            .filter(|player_idx| self.data.players[**player_idx].state != PlayerState::Fold)
+            .copied()
+            .collect();
+        let mut side_pots = self.data.pot.side_pots(&contesting, &contesting);
+        if let Some(side_pot) = side_pots.pop() {
+            // Take the pot's money out of the investments, leaving what's
+            // left for the pots below it.
+            let lower_call = side_pots.last().map_or(0, |side_pot| side_pot.call);
+            for investment in self.data.pot.investments.values_mut() {
+                *investment = min(*investment, lower_call);
+            }
+            // Dead money is in the main pot, which is the last to go.
+            if side_pots.is_empty() {
+                self.data.pot.dead = 0;
+            }
+            let mut pot_size = side_pot.size;
+            let seats_in_pot = side_pot.seats;
+
+            self.data.hand_history.audit.chips_in += pot_size;
+            let rake = self.data.take_rake(pot_size);
+            pot_size -= rake;
+            let jackpot_drop = self.data.take_jackpot_drop(pot_size);
+            pot_size -= jackpot_drop;
+
+            // When running it twice, the pot is split evenly between the
+            // two boards, with the first board getting any odd chip.
+            let num_boards: Usd = if self.data.second_board.is_empty() {
+                1
+            } else {
+                2
+            };
+            let mut pot_winners = Vec::new();
+            for board_idx in 0..num_boards {
+                let board = if board_idx == 0 {
+                    &self.data.board
+                } else {
+                    &self.data.second_board
+                };
+                let mut board_pot_size = pot_size / num_boards;
+                if board_idx == 0 {
+                    board_pot_size += pot_size % num_boards;
                 }
-                pot_idx = idx;
-            }
-
-            // Evaluate the hands in the pot and get the winners.
-            let mut pot_size: Usd = 0;
-            let mut seats_in_pot = Vec::with_capacity(self.data.settings.max_players);
-            let mut hands_in_pot = Vec::with_capacity(self.data.settings.max_players);
-            for (player_idx, investment) in investments[pot_idx..].as_mut() {
-                let pot_investment = min(pot_call, **investment);
-                pot_size += pot_investment;
-                **investment -= pot_investment;
-                let player = &mut self.data.players[**player_idx];
-                if player.state != PlayerState::Fold {
-                    seats_in_pot.push(*player_idx);
+
+                // Evaluate the hands in the pot and get the winners.
+                let mut hands_in_pot = Vec::with_capacity(seats_in_pot.len());
+                for player_idx in seats_in_pot.iter() {
+                    let player = &self.data.players[*player_idx];
                     let hand_eval = || {
                         let mut cards = player.cards.clone();
-                        cards.extend(self.data.board.clone());
+                        cards.extend(board.clone());
                         functional::prepare_hand(&mut cards);
                         functional::eval(&cards)
                     };
                     let hand = self
                         .state
                         .hand_eval_cache
-                        .entry(**player_idx)
+                        .entry((board_idx as usize, *player_idx))
                         .or_insert_with(hand_eval);
                     hands_in_pot.push(hand.clone());
                 }
+                let winner_indices = functional::argmax(&hands_in_pot);
+                self.data.pay_jackpot(
+                    board_idx as usize,
+                    &seats_in_pot,
+                    &hands_in_pot,
+                    &winner_indices,
+                );
+
+                // Finally, split the pot amongst all the winners. When the
+                // pot doesn't split evenly, the odd chips go one at a time
+                // to the winners closest to the button's left, like
+                // dealers award them at a live table.
+                let num_winners = winner_indices.len();
+                let pot_split = board_pot_size / num_winners as Usd;
+                let mut odd_chips = board_pot_size % num_winners as Usd;
+                let mut winner_player_indices: Vec<_> = winner_indices
+                    .into_iter()
+                    .map(|winner_idx| seats_in_pot[winner_idx])
+                    .collect();
+                winner_player_indices
+                    .sort_by_key(|player_idx| self.get_seats_left_of_button(*player_idx));
+                let mut winners = Vec::with_capacity(num_winners);
+                for winner_player_idx in winner_player_indices {
+                    let player = &mut self.data.players[winner_player_idx];
+                    player.user.money += pot_split;
+                    if odd_chips > 0 {
+                        player.user.money += 1;
+                        odd_chips -= 1;
+                    }
+                    // Winning a contested pot means showing the hand
+                    // that won it.
+                    if seats_in_pot.len() > 1
+                        && matches!(player.state, PlayerState::AllIn | PlayerState::Wait)
+                    {
+                        player.state = PlayerState::Show;
+                    }
+                    winners.push(player.user.name.clone());
+                }
+                self.data.hand_history.audit.awarded += board_pot_size;
+                for winner in winners.iter() {
+                    if !pot_winners.contains(winner) {
+                        pot_winners.push(winner.clone());
+                    }
+                }
+                self.data.hand_history.pots.push(PotResult {
+                    board_idx: board_idx as usize,
+                    size: board_pot_size,
+                    winners,
+                });
             }
-            let winner_indices = functional::argmax(&hands_in_pot);
 
-            // Finally, split the pot amongst all the winners. There's
-            // a possibility for the pot to not split perfectly
-            // amongst all players; in this case, the remainder is
-            // put in the donations and will eventually be redistributed
-            // amongst remaining users. This also encourages users to
-            // stay in the game so they can be donated these breadcrumbs
-            // and continue playing with them.
-            let num_winners = winner_indices.len();
-            let pot_split = pot_size / num_winners as Usd;
-            let mut pot_remainder = pot_size as Usdf;
-            for winner_idx in winner_indices {
-                let winner_player_idx = seats_in_pot[winner_idx];
-                let player = &mut self.data.players[*winner_player_idx];
-                player.user.money += pot_split;
-                pot_remainder -= pot_split as Usdf;
+            // Pots are awarded from the top down, so the first pot a
+            // player loses is the one that took the last of their money.
+            if self.data.get_bounty().is_some() {
+                for player_idx in seats_in_pot.iter() {
+                    let username = &self.data.players[*player_idx].user.name;
+                    if !pot_winners.contains(username) {
+                        self.data
+                            .eliminators
+                            .entry(username.clone())
+                            .or_insert_with(|| pot_winners.clone());
+                    }
+                }
             }
-            self.data.donations += pot_remainder;
         }
 
         // Remove null investments.
@@ -1279,7 +3029,29 @@ impl From<Game<DistributePot>> for Game<ShowHands> {
 impl From<Game<DistributePot>> for Game<RemovePlayers> {
     fn from(mut value: Game<DistributePot>) -> Self {
         value.distribute();
+        // Stacks are compared before buy-ins made during the hand are
+        // added to them.
+        let history = &mut value.data.hand_history;
+        history.audit.stack_deltas = history
+            .players
+            .iter()
+            .map(|(_, user)| {
+                let money = value
+                    .data
+                    .players
+                    .iter()
+                    .find(|p| p.user.name == user.name)
+                    .map_or(0, |p| p.user.money);
+                (user.name.clone(), money as i64 - user.money as i64)
+            })
+            .collect();
+        value.data.pay_bounties();
+        value.data.update_buy_ins();
         value.data.num_players_active = 0;
+        let history = &mut value.data.hand_history;
+        history.board.clone_from(&value.data.board);
+        history.second_board.clone_from(&value.data.second_board);
+        value.data.record_shown_hands();
         Self {
             data: value.data,
             state: RemovePlayers {},
@@ -1287,8 +3059,47 @@ impl From<Game<DistributePot>> for Game<RemovePlayers> {
     }
 }
 
+impl Game<RemovePlayers> {
+    /// Return a record of the hand that just finished. Players that lost
+    /// at showdown haven't chosen whether to show their hands yet, so
+    /// their hands are recorded as mucked.
+    pub fn get_hand_history(&self) -> HandHistory {
+        self.data.hand_history.clone()
+    }
+
+    /// Return the players that lost at showdown and haven't chosen
+    /// whether to show or muck their hand yet.
+    pub fn get_showdown_losers(&self) -> Vec<Username> {
+        if self.get_num_players_in_hand() < 2 {
+            return Vec::new();
+        }
+        self.data
+            .players
+            .iter()
+            .filter(|p| {
+                matches!(p.state, PlayerState::AllIn | PlayerState::Wait)
+                    && !self.data.players_mucking.contains(&p.user.name)
+            })
+            .map(|p| p.user.name.clone())
+            .collect()
+    }
+
+    /// Muck a losing hand instead of showing it. Hands are mucked by
+    /// default, so this just lets the player skip the choice.
+    pub fn muck_hand(&mut self, username: &str) -> Result<(), UserError> {
+        if self.get_showdown_losers().iter().any(|u| u == username) {
+            self.data.players_mucking.insert(username.into());
+            Ok(())
+        } else {
+            Err(UserError::CannotMuckHand)
+        }
+    }
+}
+
 impl From<Game<RemovePlayers>> for Game<DivideDonations> {
     fn from(mut value: Game<RemovePlayers>) -> Self {
+        // Losers had their chance to show their hands.
+        value.data.record_shown_hands();
         while let Some(username) = value.data.players_to_remove.pop_first() {
             // It is possible for a user to leave in this state but right before
             // this state transition occurs. That'd cause this method to return
@@ -1303,6 +3114,14 @@ impl From<Game<RemovePlayers>> for Game<DivideDonations> {
     }
 }
 
+impl Game<DivideDonations> {
+    /// Return a record of the hand that just finished, including the
+    /// hands losers chose to show.
+    pub fn get_hand_history(&self) -> HandHistory {
+        self.data.hand_history.clone()
+    }
+}
+
 /// Empty the community donations pot and split it equally amongst
 /// all users. The community donations pot is filled with money from
 /// users that left the game. Redistributing the money back to remaining
@@ -1310,7 +3129,14 @@ impl From<Game<RemovePlayers>> for Game<DivideDonations> {
 /// gameplay if a user aggregates most of the money and then leaves.
 impl From<Game<DivideDonations>> for Game<UpdateBlinds> {
     fn from(mut value: Game<DivideDonations>) -> Self {
-        let num_users = value.get_num_users();
+        // Spectators aren't in a tournament, so they can't receive any
+        // tournament money.
+        let is_tournament = value.data.tournament.is_some();
+        let num_users = if is_tournament {
+            value.get_num_players()
+        } else {
+            value.get_num_users()
+        };
         if num_users > 0 && value.data.donations > 0 as Usdf {
             let donation_per_user = value.data.donations as Usd / num_users as Usd;
             for user in value
@@ -1319,6 +3145,7 @@ impl From<Game<DivideDonations>> for Game<UpdateBlinds> {
                 .iter_mut()
                 .map(|(_, u)| u)
                 .chain(value.data.waitlist.iter_mut())
+                .filter(|_| !is_tournament)
                 .chain(value.data.players.iter_mut().map(|p| &mut p.user))
             {
                 user.money += donation_per_user;
@@ -1340,6 +3167,20 @@ impl From<Game<DivideDonations>> for Game<UpdateBlinds> {
 /// any other action.
 impl From<Game<UpdateBlinds>> for Game<BootPlayers> {
     fn from(mut value: Game<UpdateBlinds>) -> Self {
+        // Tournament blinds only depend on how long the tournament has been
+        // running.
+        if let Some(level) = value.data.tournament.as_mut().map(|tournament| {
+            tournament.update_level();
+            tournament.level
+        }) {
+            value
+                .data
+                .set_blind_multiple(Tournament::get_blind_multiple(level));
+            return Self {
+                data: value.data,
+                state: BootPlayers {},
+            };
+        }
         let min_money = value
             .data
             .spectators
@@ -1352,8 +3193,7 @@ impl From<Game<UpdateBlinds>> for Game<BootPlayers> {
             .unwrap_or(Usd::MAX);
         if min_money < Usd::MAX {
             let multiple = max(1, min_money / value.data.settings.buy_in);
-            value.data.small_blind = multiple * value.data.settings.min_small_blind;
-            value.data.big_blind = multiple * value.data.settings.min_big_blind;
+            value.data.set_blind_multiple(multiple);
         }
         Self {
             data: value.data,
@@ -1368,8 +3208,21 @@ impl From<Game<UpdateBlinds>> for Game<BootPlayers> {
 impl From<Game<BootPlayers>> for Game<Lobby> {
     fn from(mut value: Game<BootPlayers>) -> Self {
         value.data.board.clear();
+        value.data.second_board.clear();
+        // The hand's history was already handed off, so views in the
+        // lobby don't show the last hand's actions.
+        value.data.hand_history = HandHistory::default();
+        value.data.runout_idx = None;
+        value.data.players_running_it_twice.clear();
+        value.data.players_mucking.clear();
+        let is_tournament_running = value.data.is_tournament_running();
         for player in value.data.players.iter_mut() {
-            if player.user.money < value.data.big_blind {
+            let can_play = if is_tournament_running {
+                player.user.money > 0
+            } else {
+                player.user.money >= value.data.big_blind
+            };
+            if !can_play {
                 value.data.open_seats.push_back(player.seat_idx);
                 value
                     .data
@@ -1386,14 +3239,35 @@ impl From<Game<BootPlayers>> for Game<Lobby> {
             // is a softer action.
             value.spectate_user(&username).ok();
         }
+        value.data.update_sitting_out();
+        // A tournament ends once there's only one player left with money.
+        // Otherwise, the next hand of the tournament starts automatically.
+        let start_game = is_tournament_running && !value.data.crown_tournament_winner(false);
         Self {
             data: value.data,
-            state: Lobby::new(),
+            state: Lobby { start_game },
         }
     }
 }
 
-#[derive(Debug)]
+/// A game saved at any point, even in the middle of a hand, so it can be
+/// picked up again later or attached to a bug report. The deck isn't
+/// saved, so the cards that haven't been dealt yet are reshuffled when
+/// the game is restored.
+///
+/// Each variant is a version of the game's data, converted on restore.
+/// The variants wrap the current types, so old checkpoints only load
+/// from self-describing formats like JSON, where fields added since are
+/// filled in with their defaults. Old binary checkpoints don't load.
+#[derive(Debug, Deserialize, Serialize)]
+pub enum GameCheckpoint {
+    /// Money counted in whole dollars.
+    V1(PokerState),
+    /// Money counted in cents.
+    V2(PokerState),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum PokerState {
     Lobby(Game<Lobby>),
     SeatPlayers(Game<SeatPlayers>),
@@ -1424,12 +3298,37 @@ impl fmt::Display for PokerState {
             PokerState::Lobby(_) => "in lobby",
             PokerState::SeatPlayers(_) => "seating players",
             PokerState::MoveButton(_) => "moving button",
+            PokerState::CollectBlinds(ref game) if game.data.is_bomb_pot => {
+                let ante = game.data.settings.bomb_pot.as_ref().map_or(0, |b| b.ante);
+                &format!(
+                    "bomb pot! collecting {} from all players and dealing straight to the flop",
+                    game.data.settings.chips(ante)
+                )
+            }
             PokerState::CollectBlinds(ref game) => {
-                let big_blind = game.data.big_blind;
+                let big_blind = game.data.settings.chips(game.data.big_blind);
                 let big_blind_username = &game.data.players[game.data.big_blind_idx].user.name;
-                let small_blind = game.data.small_blind;
+                let small_blind = game.data.settings.chips(game.data.small_blind);
                 let small_blind_username = &game.data.players[game.data.small_blind_idx].user.name;
-                &format!("collecting ${big_blind} from {big_blind_username} and ${small_blind} from {small_blind_username}")
+                let ante = game.data.ante;
+                let blinds = if game.data.is_small_blind_dead {
+                    format!("{big_blind} from {big_blind_username} (the small blind is dead)")
+                } else {
+                    format!("{big_blind} from {big_blind_username} and {small_blind} from {small_blind_username}")
+                };
+                if ante > 0 && game.data.settings.is_big_blind_ante {
+                    &format!(
+                        "collecting {blinds}, and {} big blind ante from {big_blind_username}",
+                        game.data.settings.chips(ante)
+                    )
+                } else if ante > 0 {
+                    &format!(
+                        "collecting {} ante from all players, {blinds}",
+                        game.data.settings.chips(ante)
+                    )
+                } else {
+                    &format!("collecting {blinds}")
+                }
             }
             PokerState::Deal(_) => "dealing cards",
             PokerState::TakeAction(ref game) => {
@@ -1466,21 +3365,180 @@ impl fmt::Display for PokerState {
 }
 
 impl PokerState {
-    pub fn get_action_options(&self) -> Option<HashSet<Action>> {
+    /// Whether the game is waiting in the lobby rather than playing a
+    /// hand.
+    pub fn is_in_lobby(&self) -> bool {
+        matches!(self, PokerState::Lobby(_))
+    }
+
+    /// Whether the board is being run out with no more betting. See
+    /// [`Game::is_running_out`].
+    pub fn is_running_out(&self) -> bool {
         match self {
-            PokerState::TakeAction(ref game) => game.get_action_options(),
-            _ => None,
+            PokerState::Flop(game) => game.is_running_out(),
+            PokerState::Turn(game) => game.is_running_out(),
+            PokerState::River(game) => game.is_running_out(),
+            _ => false,
         }
     }
 
-    pub fn get_next_action_username(&self) -> Option<String> {
-        match self {
-            PokerState::TakeAction(ref game) => game.get_next_action_username(),
-            _ => None,
+    /// Save the whole game so it can be restored exactly where it is.
+    pub fn checkpoint(&self) -> GameCheckpoint {
+        GameCheckpoint::V2(self.clone())
+    }
+
+    /// Restore a game from a checkpoint. The cards left in the deck are
+    /// reshuffled, so seeded games deal different cards than they would
+    /// have had they not been saved.
+    pub fn from_checkpoint(checkpoint: GameCheckpoint) -> Self {
+        let mut state = match checkpoint {
+            GameCheckpoint::V1(mut state) => {
+                state.dollars_to_cents();
+                state
+            }
+            GameCheckpoint::V2(state) => state,
+        };
+        state.get_data_mut().restack_deck();
+        state
+    }
+
+    fn dollars_to_cents(&mut self) {
+        if let PokerState::TakeAction(ref mut game) = self {
+            if let Some(options) = game.state.action_options.take() {
+                let options = options
+                    .into_iter()
+                    .map(|mut action| {
+                        action.dollars_to_cents();
+                        action
+                    })
+                    .collect();
+                game.state.action_options = Some(options);
+            }
         }
+        self.get_data_mut().dollars_to_cents();
     }
 
-    pub fn get_views(&self) -> GameViews {
+    fn get_data(&self) -> &GameData {
+        match self {
+            PokerState::Lobby(ref game) => &game.data,
+            PokerState::SeatPlayers(ref game) => &game.data,
+            PokerState::MoveButton(ref game) => &game.data,
+            PokerState::CollectBlinds(ref game) => &game.data,
+            PokerState::Deal(ref game) => &game.data,
+            PokerState::TakeAction(ref game) => &game.data,
+            PokerState::Flop(ref game) => &game.data,
+            PokerState::Turn(ref game) => &game.data,
+            PokerState::River(ref game) => &game.data,
+            PokerState::ShowHands(ref game) => &game.data,
+            PokerState::DistributePot(ref game) => &game.data,
+            PokerState::RemovePlayers(ref game) => &game.data,
+            PokerState::DivideDonations(ref game) => &game.data,
+            PokerState::UpdateBlinds(ref game) => &game.data,
+            PokerState::BootPlayers(ref game) => &game.data,
+        }
+    }
+
+    fn get_data_mut(&mut self) -> &mut GameData {
+        match self {
+            PokerState::Lobby(ref mut game) => &mut game.data,
+            PokerState::SeatPlayers(ref mut game) => &mut game.data,
+            PokerState::MoveButton(ref mut game) => &mut game.data,
+            PokerState::CollectBlinds(ref mut game) => &mut game.data,
+            PokerState::Deal(ref mut game) => &mut game.data,
+            PokerState::TakeAction(ref mut game) => &mut game.data,
+            PokerState::Flop(ref mut game) => &mut game.data,
+            PokerState::Turn(ref mut game) => &mut game.data,
+            PokerState::River(ref mut game) => &mut game.data,
+            PokerState::ShowHands(ref mut game) => &mut game.data,
+            PokerState::DistributePot(ref mut game) => &mut game.data,
+            PokerState::RemovePlayers(ref mut game) => &mut game.data,
+            PokerState::DivideDonations(ref mut game) => &mut game.data,
+            PokerState::UpdateBlinds(ref mut game) => &mut game.data,
+            PokerState::BootPlayers(ref mut game) => &mut game.data,
+        }
+    }
+
+    /// Return a snapshot of the game's users if the game is in the lobby.
+    pub fn get_snapshot(&self) -> Option<GameSnapshot> {
+        match self {
+            PokerState::Lobby(ref game) => Some(game.get_snapshot()),
+            _ => None,
+        }
+    }
+
+    /// Return a record of the last hand once losers have chosen whether
+    /// to show their hands.
+    pub fn get_hand_history(&self) -> Option<HandHistory> {
+        match self {
+            PokerState::DivideDonations(ref game) => Some(game.get_hand_history()),
+            _ => None,
+        }
+    }
+
+    /// Return the players that lost at showdown and can still choose to
+    /// show or muck their hand.
+    pub fn get_showdown_losers(&self) -> Vec<Username> {
+        match self {
+            PokerState::RemovePlayers(ref game) => game.get_showdown_losers(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn get_action_options(&self) -> Option<HashSet<Action>> {
+        match self {
+            PokerState::TakeAction(ref game) => game.get_action_options(),
+            _ => None,
+        }
+    }
+
+    pub fn get_next_action_username(&self) -> Option<Username> {
+        match self {
+            PokerState::TakeAction(ref game) => game.get_next_action_username(),
+            _ => None,
+        }
+    }
+
+    pub fn get_public_view(&self) -> GameView {
+        match self {
+            PokerState::Lobby(ref game) => game.get_public_view(),
+            PokerState::SeatPlayers(ref game) => game.get_public_view(),
+            PokerState::MoveButton(ref game) => game.get_public_view(),
+            PokerState::CollectBlinds(ref game) => game.get_public_view(),
+            PokerState::Deal(ref game) => game.get_public_view(),
+            PokerState::TakeAction(ref game) => game.get_public_view(),
+            PokerState::Flop(ref game) => game.get_public_view(),
+            PokerState::Turn(ref game) => game.get_public_view(),
+            PokerState::River(ref game) => game.get_public_view(),
+            PokerState::ShowHands(ref game) => game.get_public_view(),
+            PokerState::DistributePot(ref game) => game.get_public_view(),
+            PokerState::RemovePlayers(ref game) => game.get_public_view(),
+            PokerState::DivideDonations(ref game) => game.get_public_view(),
+            PokerState::UpdateBlinds(ref game) => game.get_public_view(),
+            PokerState::BootPlayers(ref game) => game.get_public_view(),
+        }
+    }
+
+    pub fn get_revealed_view(&self) -> GameView {
+        match self {
+            PokerState::Lobby(ref game) => game.get_revealed_view(),
+            PokerState::SeatPlayers(ref game) => game.get_revealed_view(),
+            PokerState::MoveButton(ref game) => game.get_revealed_view(),
+            PokerState::CollectBlinds(ref game) => game.get_revealed_view(),
+            PokerState::Deal(ref game) => game.get_revealed_view(),
+            PokerState::TakeAction(ref game) => game.get_revealed_view(),
+            PokerState::Flop(ref game) => game.get_revealed_view(),
+            PokerState::Turn(ref game) => game.get_revealed_view(),
+            PokerState::River(ref game) => game.get_revealed_view(),
+            PokerState::ShowHands(ref game) => game.get_revealed_view(),
+            PokerState::DistributePot(ref game) => game.get_revealed_view(),
+            PokerState::RemovePlayers(ref game) => game.get_revealed_view(),
+            PokerState::DivideDonations(ref game) => game.get_revealed_view(),
+            PokerState::UpdateBlinds(ref game) => game.get_revealed_view(),
+            PokerState::BootPlayers(ref game) => game.get_revealed_view(),
+        }
+    }
+
+    pub fn get_views(&self) -> GameViews {
         match self {
             PokerState::Lobby(ref game) => game.get_views(),
             PokerState::SeatPlayers(ref game) => game.get_views(),
@@ -1500,6 +3558,26 @@ impl PokerState {
         }
     }
 
+    pub fn get_tournament_view(&self) -> Option<TournamentView> {
+        match self {
+            PokerState::Lobby(ref game) => game.get_tournament_view(),
+            PokerState::SeatPlayers(ref game) => game.get_tournament_view(),
+            PokerState::MoveButton(ref game) => game.get_tournament_view(),
+            PokerState::CollectBlinds(ref game) => game.get_tournament_view(),
+            PokerState::Deal(ref game) => game.get_tournament_view(),
+            PokerState::TakeAction(ref game) => game.get_tournament_view(),
+            PokerState::Flop(ref game) => game.get_tournament_view(),
+            PokerState::Turn(ref game) => game.get_tournament_view(),
+            PokerState::River(ref game) => game.get_tournament_view(),
+            PokerState::ShowHands(ref game) => game.get_tournament_view(),
+            PokerState::DistributePot(ref game) => game.get_tournament_view(),
+            PokerState::RemovePlayers(ref game) => game.get_tournament_view(),
+            PokerState::DivideDonations(ref game) => game.get_tournament_view(),
+            PokerState::UpdateBlinds(ref game) => game.get_tournament_view(),
+            PokerState::BootPlayers(ref game) => game.get_tournament_view(),
+        }
+    }
+
     pub fn init_start(&mut self, username: &str) -> Result<(), UserError> {
         match self {
             PokerState::Lobby(ref mut game) => {
@@ -1520,6 +3598,11 @@ impl PokerState {
         PokerState::Lobby(game)
     }
 
+    pub fn restore(settings: GameSettings, snapshot: GameSnapshot) -> Self {
+        let game = Game::<Lobby>::restore(settings, snapshot);
+        PokerState::Lobby(game)
+    }
+
     fn phase_transition(game: Game<TakeAction>) -> PokerState {
         match game.get_num_community_cards() {
             0 => PokerState::Flop(game.into()),
@@ -1532,6 +3615,23 @@ impl PokerState {
         }
     }
 
+    /// Vote for (or against) the next hand being a bomb pot. The next
+    /// hand is a bomb pot once every player dealt into it has voted for
+    /// one.
+    pub fn vote_bomb_pot(&mut self, username: &str, vote: bool) -> Result<(), UserError> {
+        self.get_data_mut().vote_bomb_pot(username, vote)
+    }
+
+    pub fn run_it_twice(&mut self, username: &str, consent: bool) -> Result<(), UserError> {
+        match self {
+            PokerState::TakeAction(ref mut game) => game.run_it_twice(username, consent),
+            PokerState::Flop(ref mut game) => game.run_it_twice(username, consent),
+            PokerState::Turn(ref mut game) => game.run_it_twice(username, consent),
+            PokerState::River(ref mut game) => game.run_it_twice(username, consent),
+            _ => Err(UserError::CannotRunItTwice),
+        }
+    }
+
     pub fn show_hand(&mut self, username: &str) -> Result<(), UserError> {
         match self {
             PokerState::ShowHands(ref mut game) => {
@@ -1554,6 +3654,13 @@ impl PokerState {
         }
     }
 
+    pub fn muck_hand(&mut self, username: &str) -> Result<(), UserError> {
+        match self {
+            PokerState::RemovePlayers(ref mut game) => game.muck_hand(username),
+            _ => Err(UserError::CannotMuckHand),
+        }
+    }
+
     pub fn step(self) -> Self {
         match self {
             PokerState::Lobby(game) => {
@@ -1621,6 +3728,163 @@ impl PokerState {
         }
     }
 
+    /// Buy a user more money. Players get it right away unless they're
+    /// in the middle of a hand.
+    pub fn buy_in_user(&mut self, username: &str, amount: Usd) -> Result<(), UserError> {
+        let is_hand_in_progress = matches!(
+            self,
+            PokerState::MoveButton(_)
+                | PokerState::CollectBlinds(_)
+                | PokerState::Deal(_)
+                | PokerState::TakeAction(_)
+                | PokerState::Flop(_)
+                | PokerState::Turn(_)
+                | PokerState::River(_)
+                | PokerState::ShowHands(_)
+                | PokerState::DistributePot(_)
+        );
+        let data = self.get_data_mut();
+        data.buy_in_user(username, amount)?;
+        if !is_hand_in_progress {
+            data.update_buy_ins();
+        }
+        Ok(())
+    }
+
+    /// Change the minimum blinds. Hands being played keep their blinds,
+    /// and later hands' blinds are scaled from the new minimums.
+    pub fn set_blinds(&mut self, small_blind: Usd, big_blind: Usd) -> Result<(), UserError> {
+        let is_in_lobby = self.is_in_lobby();
+        let data = self.get_data_mut();
+        let settings = data.settings.clone().with_blinds(small_blind, big_blind);
+        settings
+            .validate()
+            .map_err(UserError::InvalidGameSettings)?;
+        // Blinds are only updated between hands, which the lobby skips.
+        let multiple = max(1, data.big_blind / data.settings.min_big_blind);
+        data.settings = settings;
+        if is_in_lobby {
+            data.set_blind_multiple(multiple);
+        }
+        Ok(())
+    }
+
+    /// Change how many spectators can watch. Spectators already watching
+    /// aren't kicked out if there are more of them than that.
+    pub fn set_max_spectators(&mut self, max_spectators: usize) {
+        self.get_data_mut().settings.max_spectators = max_spectators;
+    }
+
+    /// Return the players in a tournament, starting from
+    /// whoever's due to post the big blind next, followed by anyone
+    /// waiting for a seat. The player due the big blind is the fairest to
+    /// move to another table, since they'd pay the most to stay.
+    pub fn get_tournament_players(&self) -> Vec<Username> {
+        let data = self.get_data();
+        let num_players = data.players.len();
+        let next_big_blind_idx = data
+            .big_blind_seat_idx
+            .and_then(|seat_idx| data.players.iter().position(|p| p.seat_idx > seat_idx))
+            .unwrap_or(0);
+        (0..num_players)
+            .map(|offset| &data.players[(next_big_blind_idx + offset) % num_players].user)
+            .chain(data.waitlist.iter())
+            .map(|user| user.name.clone())
+            .collect()
+    }
+
+    /// Take a player out of a running tournament to move them to another
+    /// of its tables, keeping their stack. Players can only be moved
+    /// between hands.
+    pub fn move_out_player(&mut self, username: &str) -> Result<User, UserError> {
+        if !self.is_in_lobby() {
+            return Err(UserError::GameAlreadyInProgress);
+        }
+        let data = self.get_data_mut();
+        if !data.is_tournament_running() {
+            return Err(UserError::TournamentNotRunning);
+        }
+        data.seat_requests.remove(username);
+        data.players_to_spectate.remove(username);
+        data.players_to_remove.remove(username);
+        if let Some(waitlist_idx) = data.waitlist.iter().position(|u| u.name == username) {
+            Ok(data
+                .waitlist
+                .remove(waitlist_idx)
+                .expect("waitlister exists"))
+        } else if let Some(player_idx) = data.players.iter().position(|p| p.user.name == username) {
+            let player = data.players.remove(player_idx);
+            data.open_seats.push_back(player.seat_idx);
+            Ok(player.user)
+        } else {
+            Err(UserError::UserNotPlaying)
+        }
+    }
+
+    /// Seat a player moved from another table of a tournament with the
+    /// stack they had there. They're dealt in as soon as a seat opens up,
+    /// which starts the next hand if the table was waiting on players.
+    pub fn move_in_player(&mut self, user: User) {
+        let data = self.get_data_mut();
+        data.spectators.remove(&user.name);
+        data.waitlist.push_back(user);
+        let is_tournament_running = data.is_tournament_running();
+        if let PokerState::Lobby(ref mut game) = self {
+            if is_tournament_running && game.get_num_potential_players() >= 2 {
+                game.state.start_game = true;
+            }
+        }
+    }
+
+    /// End a multi-table tournament at the table once the server decides
+    /// it's over, crowning the table's last player if it has one. Does
+    /// nothing mid-hand or while the table has players left to play.
+    pub fn finish_tournament(&mut self) {
+        if self.is_in_lobby() {
+            let data = self.get_data_mut();
+            if data.is_tournament_running() {
+                data.crown_tournament_winner(true);
+            }
+        }
+    }
+
+    /// Start or stop playing hand-for-hand.
+    pub fn set_hand_for_hand(&mut self, is_hand_for_hand: bool) {
+        if let Some(ref mut tournament) = self.get_data_mut().tournament {
+            tournament.is_hand_for_hand = is_hand_for_hand && tournament.is_running();
+        }
+    }
+
+    /// Add a user to the waitlist like [`PokerState::waitlist_user`], but
+    /// have them wait for a particular seat to open up.
+    pub fn waitlist_user_at_seat(
+        &mut self,
+        username: &str,
+        seat_idx: usize,
+    ) -> Result<(), UserError> {
+        let data = self.get_data_mut();
+        if seat_idx >= data.settings.max_players {
+            return Err(UserError::SeatDoesNotExist { seat_idx });
+        } else if data.players.iter().any(|p| p.user.name == username) {
+            return Err(UserError::UserAlreadySeated);
+        }
+        self.waitlist_user(username)?;
+        self.get_data_mut()
+            .seat_requests
+            .insert(username.into(), seat_idx);
+        Ok(())
+    }
+
+    /// Players can start or stop sitting out right away if a hand isn't
+    /// being played.
+    fn update_sitting_out(&mut self) {
+        match self {
+            PokerState::Lobby(ref mut game) => game.data.update_sitting_out(),
+            PokerState::SeatPlayers(ref mut game) => game.data.update_sitting_out(),
+            _ => {}
+        }
+    }
+
     pub fn take_action(&mut self, username: &str, action: Action) -> Result<Action, UserError> {
         match self {
             PokerState::TakeAction(ref mut game)
@@ -1685,13 +3949,20 @@ macro_rules! impl_user_managers {
                         game.$name(username)?;
                     },
                 }
+                self.update_sitting_out();
                 Ok(())
             })*
         }
     }
 }
 
-impl_user_managers!(new_user, remove_user, spectate_user, waitlist_user);
+impl_user_managers!(
+    new_user,
+    remove_user,
+    sit_out_user,
+    spectate_user,
+    waitlist_user
+);
 
 impl From<GameSettings> for PokerState {
     fn from(value: GameSettings) -> Self {
@@ -1702,14 +3973,23 @@ impl From<GameSettings> for PokerState {
 
 #[cfg(test)]
 mod game_tests {
-    use std::collections::HashSet;
+    use std::collections::{BTreeMap, HashSet};
+
+    use std::time::{Duration, Instant};
 
     use super::{
-        entities::{Action, Card, Suit},
-        BootPlayers, CollectBlinds, Deal, DistributePot, DivideDonations, Flop, Game, Lobby,
-        MoveButton, RemovePlayers, River, SeatPlayers, ShowHands, TakeAction, Turn, UpdateBlinds,
-        UserError,
+        constants::{MAX_PLAYERS, MAX_USERS, MAX_USER_INPUT_LENGTH},
+        entities::{
+            parse_cards, Action, Card, ChipDisplay, Dollars, GameSnapshot, JackpotPayout, Knockout,
+            ParseCardError, ParseUsdError, PlayerState, Position, Rank, Street, Suit, User,
+            Username, UsernameError,
+        },
+        BombPotSettings, BootPlayers, CollectBlinds, Deal, DistributePot, DivideDonations, Flop,
+        Game, GameSettings, GameSettingsError, JackpotSettings, Lobby, MoveButton, PokerState,
+        RakeSettings, RemovePlayers, River, SeatPlayers, ShowHands, TakeAction, TournamentSettings,
+        Turn, UpdateBlinds, UserError, Viewer,
     };
+    use crate::functional::evaluate;
 
     fn init_2_player_game() -> Game<SeatPlayers> {
         let game = Game::<Lobby>::new();
@@ -1733,6 +4013,18 @@ mod game_tests {
         game
     }
 
+    fn init_2_player_tournament() -> Game<SeatPlayers> {
+        let settings = GameSettings::default()
+            .with_tournament(TournamentSettings::new(Duration::from_secs(60)));
+        let mut game: Game<Lobby> = settings.into();
+        for i in 0..2 {
+            let username = i.to_string();
+            game.new_user(&username).unwrap();
+            game.waitlist_user(&username).unwrap();
+        }
+        game.into()
+    }
+
     fn init_game_at_collect_blinds() -> Game<Deal> {
         let game = init_3_player_game();
         let game: Game<MoveButton> = game.into();
@@ -1817,6 +4109,254 @@ mod game_tests {
         }
     }
 
+    #[test]
+    fn collect_blinds_with_ante() {
+        let settings = GameSettings::default().with_ante(2);
+        let mut game: Game<Lobby> = settings.into();
+        for i in 0..3 {
+            let username = i.to_string();
+            game.new_user(&username).unwrap();
+            game.waitlist_user(&username).unwrap();
+        }
+        let game: Game<SeatPlayers> = game.into();
+        let mut game: Game<MoveButton> = game.into();
+        // The small blind can only afford part of their blind after the ante.
+        game.data.players[1].user.money = 5;
+        let game: Game<CollectBlinds> = game.into();
+        let game: Game<Deal> = game.into();
+        let buy_in = game.data.settings.buy_in;
+        let ante = game.data.ante;
+        for (i, money) in [buy_in - ante, 0, buy_in - ante - game.data.big_blind]
+            .iter()
+            .enumerate()
+        {
+            assert_eq!(game.data.players[i].user.money, *money);
+        }
+        assert_eq!(game.data.players[1].state, PlayerState::AllIn);
+        assert_eq!(game.data.num_players_active, 2);
+        assert_eq!(game.data.pot.get_size(), 2 * ante + 5 + game.data.big_blind);
+    }
+
+    #[test]
+    fn antes_dont_count_toward_raises() {
+        let settings = GameSettings::default().with_ante(100);
+        let mut game: Game<Lobby> = settings.into();
+        for i in 0..3 {
+            let username = i.to_string();
+            game.new_user(&username).unwrap();
+            game.waitlist_user(&username).unwrap();
+        }
+        let game: Game<SeatPlayers> = game.into();
+        let game: Game<MoveButton> = game.into();
+        let game: Game<CollectBlinds> = game.into();
+        let game: Game<Deal> = game.into();
+        let game: Game<TakeAction> = game.into();
+        let big_blind = game.data.big_blind;
+        assert_eq!(game.data.pot.dead, 300);
+        assert_eq!(game.data.pot.get_call(), big_blind);
+
+        // The first player to act calls the big blind or raises to twice
+        // it, as if there were no ante.
+        let player_idx = game.data.next_action_idx.unwrap();
+        assert_eq!(game.data.pot.get_investment_by_player_idx(player_idx), 0);
+        let action_options = game.get_action_options().unwrap();
+        assert_eq!(
+            action_options.get(&Action::Call(0)),
+            Some(&Action::Call(big_blind))
+        );
+        assert_eq!(
+            action_options.get(&Action::Raise(0)),
+            Some(&Action::Raise(2 * big_blind))
+        );
+    }
+
+    #[test]
+    fn collect_big_blind_ante() {
+        let settings = GameSettings::default();
+        let ante = settings.min_big_blind;
+        let mut game: Game<Lobby> = settings.with_big_blind_ante(ante).into();
+        for i in 0..3 {
+            let username = i.to_string();
+            game.new_user(&username).unwrap();
+            game.waitlist_user(&username).unwrap();
+        }
+        let game: Game<SeatPlayers> = game.into();
+        let mut game: Game<MoveButton> = game.into();
+        // The big blind can only afford part of the ante after their blind.
+        let big_blind = game.data.big_blind;
+        game.data.players[2].user.money = big_blind + 3;
+        let game: Game<CollectBlinds> = game.into();
+        let game: Game<Deal> = game.into();
+        let buy_in = game.data.settings.buy_in;
+        let small_blind = game.data.small_blind;
+        for (i, money) in [buy_in, buy_in - small_blind, 0].iter().enumerate() {
+            assert_eq!(game.data.players[i].user.money, *money);
+        }
+        assert_eq!(game.data.players[2].state, PlayerState::AllIn);
+        assert_eq!(game.data.num_players_active, 2);
+        // The ante doesn't count toward the big blind's bet.
+        assert_eq!(game.data.pot.dead, 3);
+        assert_eq!(game.data.pot.get_call(), big_blind);
+        assert_eq!(game.data.pot.get_size(), small_blind + big_blind + 3);
+        assert!(game.data.hand_history.is_big_blind_ante);
+    }
+
+    #[test]
+    fn validate_settings() {
+        let chips = |amount| ChipDisplay::Usd.chips(amount);
+        assert_eq!(GameSettings::default().validate(), Ok(()));
+        let settings = GameSettings::new(10, 16, 100).with_blinds(1, 2);
+        assert_eq!(settings.validate(), Ok(()));
+        let game: Game<Lobby> = settings.into();
+        assert_eq!(game.data.small_blind, 1);
+        assert_eq!(game.data.big_blind, 2);
+        assert_eq!(
+            GameSettings::new(10, 16, 100)
+                .with_blinds(50, 200)
+                .validate(),
+            Err(GameSettingsError::BigBlindExceedsBuyIn {
+                big_blind: chips(200),
+                buy_in: chips(100)
+            })
+        );
+        assert_eq!(
+            GameSettings::new(10, 16, 100)
+                .with_blinds(20, 10)
+                .validate(),
+            Err(GameSettingsError::SmallBlindExceedsBigBlind {
+                small_blind: chips(20),
+                big_blind: chips(10)
+            })
+        );
+        assert_eq!(
+            GameSettings::new(10, 16, 100)
+                .with_buy_in_bounds(150, 300)
+                .validate(),
+            Err(GameSettingsError::BuyInOutOfBounds {
+                buy_in: chips(100),
+                min_buy_in: chips(150),
+                max_buy_in: chips(300)
+            })
+        );
+        assert_eq!(
+            GameSettings::new(MAX_PLAYERS + 1, 16, 100).validate(),
+            Err(GameSettingsError::PlayersOutOfBounds {
+                max_players: MAX_PLAYERS + 1
+            })
+        );
+        assert_eq!(
+            GameSettings::new(1, 16, 100).validate(),
+            Err(GameSettingsError::PlayersOutOfBounds { max_players: 1 })
+        );
+        assert_eq!(
+            GameSettings::new(10, usize::MAX, 100)
+                .with_max_spectators(16)
+                .validate(),
+            Err(GameSettingsError::TooManyUsers {
+                max_users: usize::MAX
+            })
+        );
+        assert_eq!(
+            GameSettings::new(10, 16, 100)
+                .with_max_spectators(MAX_USERS + 1)
+                .validate(),
+            Err(GameSettingsError::TooManySpectators {
+                max_spectators: MAX_USERS + 1
+            })
+        );
+        assert_eq!(
+            GameSettings::new(10, 16, 100)
+                .with_rake(RakeSettings::new(101, None, false))
+                .validate(),
+            Err(GameSettingsError::RakeTooHigh { percent: 101 })
+        );
+        assert_eq!(
+            GameSettings::new(10, 16, 10).validate(),
+            Err(GameSettingsError::ZeroBigBlind)
+        );
+        assert_eq!(
+            GameSettings::default()
+                .with_bomb_pot(BombPotSettings::new(0, None))
+                .validate(),
+            Err(GameSettingsError::ZeroBombPotAnte)
+        );
+        assert_eq!(
+            GameSettings::default()
+                .with_bomb_pot(BombPotSettings::new(100, Some(0)))
+                .validate(),
+            Err(GameSettingsError::ZeroBombPotFrequency)
+        );
+    }
+
+    #[test]
+    fn seeded_games_deal_the_same_cards() {
+        let deal = |seed| {
+            let settings = GameSettings::default().with_seed(seed);
+            let mut game: Game<Lobby> = settings.into();
+            for i in 0..3 {
+                let username = i.to_string();
+                game.new_user(&username).unwrap();
+                game.waitlist_user(&username).unwrap();
+            }
+            let game: Game<SeatPlayers> = game.into();
+            let game: Game<MoveButton> = game.into();
+            let game: Game<CollectBlinds> = game.into();
+            let game: Game<Deal> = game.into();
+            let game: Game<TakeAction> = game.into();
+            game.data
+                .players
+                .iter()
+                .map(|player| player.cards.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(deal(7), deal(7));
+        assert_ne!(deal(7), deal(8));
+    }
+
+    #[test]
+    fn pot_odds() {
+        let game = init_game_at_deal();
+        let view = game.as_view(Viewer::User("0"));
+        assert_eq!(view.action_on, Some(game.data.players[0].user.name.clone()));
+        assert_eq!(view.button_idx, Some(game.get_button_idx()));
+        // The first to act faces the $10 big blind with $15 in the pot.
+        assert_eq!(view.pot.size, 1500);
+        assert_eq!(view.pot_odds(1000), Some(1.5));
+        assert_eq!(view.minimum_defense_equity(1000), 0.4);
+        assert_eq!(view.pot_odds(0), None);
+        assert_eq!(view.minimum_defense_equity(0), 0.0);
+    }
+
+    #[test]
+    fn view_actions_this_hand() {
+        let mut game = init_game_at_deal();
+        game.act(Action::Call(1000)).unwrap();
+        game.act(Action::Fold).unwrap();
+        let view = game.as_view(Viewer::Public);
+        let actions: Vec<_> = view
+            .actions
+            .iter()
+            .map(|action| (action.street, action.username.as_str(), &action.action))
+            .collect();
+        assert_eq!(
+            actions,
+            [
+                (Street::Preflop, "0", &Action::Call(1000)),
+                (Street::Preflop, "1", &Action::Fold)
+            ]
+        );
+        assert_eq!(view.last_action_this_street("1"), Some(&Action::Fold));
+        assert_eq!(view.last_action_this_street("2"), None);
+
+        // Actions from earlier streets aren't this street's.
+        game.act(Action::Check).unwrap();
+        let game: Game<Flop> = game.into();
+        let game: Game<TakeAction> = game.into();
+        let view = game.as_view(Viewer::Public);
+        assert_eq!(view.actions.len(), 3);
+        assert_eq!(view.last_action_this_street("0"), None);
+    }
+
     #[test]
     fn deal() {
         let game = init_game_at_deal();
@@ -1842,6 +4382,89 @@ mod game_tests {
         assert_eq!(game.get_num_community_cards(), 5);
     }
 
+    #[test]
+    fn run_it_twice() {
+        let mut game = init_game_at_deal();
+        assert_eq!(
+            game.run_it_twice("1", true),
+            Err(UserError::CannotRunItTwice)
+        );
+        game.act(Action::Fold).unwrap();
+        game.act(Action::AllIn).unwrap();
+        game.act(Action::AllIn).unwrap();
+        assert_eq!(game.run_it_twice("0", true), Err(UserError::UserNotPlaying));
+        assert_eq!(game.run_it_twice("1", true), Ok(()));
+        let mut game: Game<Flop> = game.into();
+        assert!(!game.is_running_it_twice());
+        assert_eq!(game.run_it_twice("2", true), Ok(()));
+        assert!(game.is_running_it_twice());
+        let mut game: Game<Turn> = game.into();
+        // Can't change your mind once the run-out has started.
+        assert_eq!(
+            game.run_it_twice("2", false),
+            Err(UserError::CannotRunItTwice)
+        );
+        let game: Game<River> = game.into();
+        let mut game: Game<ShowHands> = game.into();
+        assert_eq!(game.data.second_board.len(), 5);
+        assert!(game
+            .data
+            .second_board
+            .iter()
+            .all(|card| !game.data.board.contains(card)));
+
+        // Each player wins one of the boards, so they split the pot.
+        game.data.board = vec![
+            Card(2, Suit::Spade),
+            Card(4, Suit::Diamond),
+            Card(6, Suit::Club),
+            Card(8, Suit::Diamond),
+            Card(10, Suit::Heart),
+        ];
+        game.data.second_board = vec![
+            Card(13, Suit::Club),
+            Card(3, Suit::Spade),
+            Card(5, Suit::Heart),
+            Card(9, Suit::Spade),
+            Card(11, Suit::Diamond),
+        ];
+        game.data.players[1].cards = vec![Card(1, Suit::Heart), Card(1, Suit::Diamond)];
+        game.data.players[2].cards = vec![Card(13, Suit::Heart), Card(13, Suit::Diamond)];
+        let game: Game<DistributePot> = game.into();
+        let game: Game<ShowHands> = game.into();
+        assert!(game.is_pot_empty());
+        for player in game.data.players.iter() {
+            assert_eq!(player.user.money, game.data.settings.buy_in);
+        }
+    }
+
+    #[test]
+    fn reveal_hands_during_runout() {
+        let mut game = init_game_at_deal();
+        game.act(Action::Fold).unwrap();
+        game.act(Action::AllIn).unwrap();
+        let view = game.as_view(Viewer::Public);
+        assert!(view.players[1].cards.is_empty());
+        game.act(Action::AllIn).unwrap();
+        assert!(!game.is_running_out());
+
+        // Hands in the pot are face up as soon as betting is over, but
+        // players can still agree to run it twice until the flop.
+        let game: Game<Flop> = game.into();
+        let view = game.as_view(Viewer::Public);
+        assert!(view.players[0].cards.is_empty());
+        assert_eq!(view.players[1].cards, game.data.players[1].cards);
+        assert_eq!(view.players[2].cards, game.data.players[2].cards);
+        assert!(!game.is_running_out());
+        assert!(game.can_run_it_twice());
+
+        let game: Game<Turn> = game.into();
+        assert!(game.is_running_out());
+        assert!(!game.can_run_it_twice());
+        let game: Game<River> = game.into();
+        assert!(game.is_running_out());
+    }
+
     #[test]
     fn early_showdown_1_all_in_2_folds() {
         let game = init_game_at_showdown_with_1_all_in();
@@ -1873,7 +4496,7 @@ mod game_tests {
         let game: Game<CollectBlinds> = game.into();
         let game: Game<Deal> = game.into();
         let mut game: Game<TakeAction> = game.into();
-        assert_eq!(game.act(Action::Call(5)), Ok(Action::Call(5)));
+        assert_eq!(game.act(Action::Call(500)), Ok(Action::Call(500)));
         assert_eq!(game.get_next_action_options(), None);
         let game: Game<Flop> = game.into();
         let game: Game<Turn> = game.into();
@@ -1933,22 +4556,105 @@ mod game_tests {
     }
 
     #[test]
-    fn early_showdown_2_winners() {
+    fn early_showdown_with_rake() {
         let mut game = init_game_at_showdown_with_2_all_ins();
+        game.data.settings =
+            game.data
+                .settings
+                .clone()
+                .with_rake(RakeSettings::new(5, Some(15), true));
         game.data.board = vec![
-            Card(2, Suit::Diamond),
             Card(4, Suit::Diamond),
             Card(5, Suit::Diamond),
             Card(6, Suit::Diamond),
             Card(7, Suit::Diamond),
         ];
-        game.data.players[1].cards = vec![Card(1, Suit::Heart), Card(7, Suit::Heart)];
-        game.data.players[2].cards = vec![Card(2, Suit::Heart), Card(5, Suit::Heart)];
+        game.data.players[1].cards = vec![Card(1, Suit::Diamond), Card(7, Suit::Heart)];
+        game.data.players[2].cards = vec![Card(2, Suit::Diamond), Card(5, Suit::Heart)];
         let game: Game<DistributePot> = game.into();
-        let game: Game<ShowHands> = game.into();
+        let mut game: Game<ShowHands> = game.into();
         assert!(game.is_pot_empty());
-        for i in 0..3 {
-            assert_eq!(game.data.players[i].user.money, game.data.settings.buy_in);
+        // 5% of the pot is more than the cap, so the cap is taken.
+        let buy_in = game.data.settings.buy_in;
+        for (i, money) in [buy_in, 2 * buy_in - 15, 0].iter().enumerate() {
+            assert_eq!(game.data.players[i].user.money, *money);
+        }
+        assert_eq!(game.data.hand_history.rake, 15);
+        assert_eq!(game.data.take_rake(100), 0);
+
+        // Hands that end before the flop aren't raked.
+        game.data.hand_history.rake = 0;
+        game.data.board.clear();
+        assert_eq!(game.data.take_rake(100), 0);
+    }
+
+    #[test]
+    fn early_showdown_with_bad_beat_jackpot() {
+        let mut game = init_game_at_showdown_with_2_all_ins();
+        game.data.settings = game
+            .data
+            .settings
+            .clone()
+            .with_jackpot(JackpotSettings::new(10, Rank::FourOfAKind));
+        game.data.jackpot = 100;
+        game.data.board = vec![
+            Card(5, Suit::Diamond),
+            Card(6, Suit::Diamond),
+            Card(7, Suit::Diamond),
+            Card(7, Suit::Spade),
+            Card(7, Suit::Heart),
+        ];
+        // Quads lose to a straight flush.
+        game.data.players[1].cards = vec![Card(7, Suit::Club), Card(2, Suit::Heart)];
+        game.data.players[2].cards = vec![Card(8, Suit::Diamond), Card(9, Suit::Diamond)];
+        let game: Game<DistributePot> = game.into();
+        let game: Game<ShowHands> = game.into();
+        assert!(game.is_pot_empty());
+        let buy_in = game.data.settings.buy_in;
+        for (i, money) in [buy_in, 55, 2 * buy_in - 10 + 55].iter().enumerate() {
+            assert_eq!(game.data.players[i].user.money, *money);
+        }
+        assert_eq!(game.data.jackpot, 0);
+        assert_eq!(game.data.hand_history.jackpot_drop, 10);
+        assert_eq!(
+            game.data.hand_history.jackpot_payout,
+            Some(JackpotPayout {
+                size: 110,
+                loser: "1".into(),
+                winners: vec!["2".into()],
+                chip_display: ChipDisplay::Usd,
+            })
+        );
+
+        // Everything that went into the pots is accounted for, and the
+        // folded player's stack didn't change.
+        let game: Game<DistributePot> = game.into();
+        let game: Game<RemovePlayers> = game.into();
+        let mut hand = game.get_hand_history();
+        assert!(hand.is_balanced());
+        assert_eq!(hand.audit.jackpot_won, 110);
+        assert_eq!(hand.audit.stack_deltas.get("0"), Some(&0));
+        hand.audit.awarded -= 1;
+        assert!(!hand.is_balanced());
+    }
+
+    #[test]
+    fn early_showdown_2_winners() {
+        let mut game = init_game_at_showdown_with_2_all_ins();
+        game.data.board = vec![
+            Card(2, Suit::Diamond),
+            Card(4, Suit::Diamond),
+            Card(5, Suit::Diamond),
+            Card(6, Suit::Diamond),
+            Card(7, Suit::Diamond),
+        ];
+        game.data.players[1].cards = vec![Card(1, Suit::Heart), Card(7, Suit::Heart)];
+        game.data.players[2].cards = vec![Card(2, Suit::Heart), Card(5, Suit::Heart)];
+        let game: Game<DistributePot> = game.into();
+        let game: Game<ShowHands> = game.into();
+        assert!(game.is_pot_empty());
+        for i in 0..3 {
+            assert_eq!(game.data.players[i].user.money, game.data.settings.buy_in);
         }
     }
 
@@ -2014,16 +4720,16 @@ mod game_tests {
             game.get_next_action_options(),
             Some(HashSet::from([
                 Action::AllIn,
-                Action::Call(195),
+                Action::Call(19500),
                 Action::Fold,
             ]))
         );
         assert_eq!(game.act(Action::AllIn), Ok(Action::AllIn));
         assert_eq!(
             game.get_next_action_options(),
-            Some(HashSet::from([Action::Call(390), Action::Fold,]))
+            Some(HashSet::from([Action::Call(39000), Action::Fold,]))
         );
-        assert_eq!(game.act(Action::Call(390)), Ok(Action::Call(390)));
+        assert_eq!(game.act(Action::Call(39000)), Ok(Action::Call(39000)));
         let game: Game<Flop> = game.into();
         let game: Game<Turn> = game.into();
         let game: Game<River> = game.into();
@@ -2057,6 +4763,133 @@ mod game_tests {
         }
     }
 
+    #[test]
+    fn side_pots_with_different_stacks() {
+        let game = Game::<Lobby>::new();
+        let mut game: Game<SeatPlayers> = game.into();
+        for i in 0..4 {
+            let username = i.to_string();
+            game.new_user(&username).unwrap();
+            game.waitlist_user(&username).unwrap();
+        }
+        let mut game: Game<MoveButton> = game.into();
+        for (i, money) in [10000, 60000, 100000, 100000].iter().enumerate() {
+            game.data.players[i].user.money = *money;
+        }
+        let game: Game<CollectBlinds> = game.into();
+        let game: Game<Deal> = game.into();
+        let mut game: Game<TakeAction> = game.into();
+        assert_eq!(game.data.next_action_idx, Some(3));
+        assert_eq!(game.act(Action::Raise(30000)), Ok(Action::Raise(30000)));
+        assert_eq!(game.act(Action::AllIn), Ok(Action::AllIn));
+        assert_eq!(game.act(Action::AllIn), Ok(Action::AllIn));
+        assert_eq!(game.act(Action::Call(59000)), Ok(Action::Call(59000)));
+
+        // Players that can still bet are eligible for every pot, even if
+        // they haven't matched the call yet.
+        let side_pots = |game: &Game<TakeAction>| {
+            game.as_view(Viewer::User("0"))
+                .pot
+                .side_pots
+                .into_iter()
+                .map(|side_pot| {
+                    let eligible: Vec<_> = side_pot
+                        .eligible
+                        .iter()
+                        .map(|username| username.to_string())
+                        .collect();
+                    (side_pot.size, eligible)
+                })
+                .collect::<Vec<_>>()
+        };
+        let usernames = |usernames: &[&str]| {
+            usernames
+                .iter()
+                .map(|username| username.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            side_pots(&game),
+            vec![
+                (40000, usernames(&["0", "1", "2", "3"])),
+                (120000, usernames(&["1", "2", "3"])),
+            ]
+        );
+
+        // Money from a player that folds stays in the pots they put it
+        // in, even if it's between all-ins.
+        assert_eq!(game.act(Action::Fold), Ok(Action::Fold));
+        assert_eq!(
+            side_pots(&game),
+            vec![
+                (40000, usernames(&["0", "1", "2"])),
+                (120000, usernames(&["1", "2"])),
+            ]
+        );
+
+        let game: Game<Flop> = game.into();
+        let game: Game<Turn> = game.into();
+        let game: Game<River> = game.into();
+        let mut game: Game<ShowHands> = game.into();
+        game.data.board = vec![
+            Card(2, Suit::Club),
+            Card(3, Suit::Diamond),
+            Card(8, Suit::Heart),
+            Card(9, Suit::Spade),
+            Card(11, Suit::Club),
+        ];
+        game.data.players[0].cards = vec![Card(1, Suit::Spade), Card(1, Suit::Heart)];
+        game.data.players[1].cards = vec![Card(13, Suit::Spade), Card(13, Suit::Heart)];
+        game.data.players[2].cards = vec![Card(4, Suit::Club), Card(6, Suit::Diamond)];
+        game.data.players[3].cards = vec![Card(5, Suit::Club), Card(7, Suit::Diamond)];
+        assert_eq!(game.get_num_pots(), 2);
+        let game: Game<DistributePot> = game.into();
+        let game: Game<ShowHands> = game.into();
+        let game: Game<DistributePot> = game.into();
+        let game: Game<RemovePlayers> = game.into();
+        let history = game.get_hand_history();
+        let pots: Vec<_> = history
+            .pots
+            .iter()
+            .map(|pot| (pot.size, pot.winners.clone()))
+            .collect();
+        assert_eq!(
+            pots,
+            vec![(120000, vec!["1".into()]), (40000, vec!["0".into()])]
+        );
+        for (i, money) in [40000, 120000, 40000, 70000].iter().enumerate() {
+            assert_eq!(game.data.players[i].user.money, *money);
+        }
+    }
+
+    #[test]
+    fn heads_up_action_order() {
+        let game = init_2_player_game();
+        let game: Game<MoveButton> = game.into();
+        let game: Game<CollectBlinds> = game.into();
+        assert!(game.is_heads_up());
+        let (small_blind_idx, big_blind_idx) = (game.data.small_blind_idx, game.data.big_blind_idx);
+        assert_ne!(small_blind_idx, big_blind_idx);
+        let game: Game<Deal> = game.into();
+        // The small blind (button) acts first before the flop.
+        let mut game: Game<TakeAction> = game.into();
+        assert_eq!(game.data.next_action_idx, Some(small_blind_idx));
+        assert_eq!(game.act(Action::Call(500)), Ok(Action::Call(500)));
+        assert_eq!(game.data.next_action_idx, Some(big_blind_idx));
+        assert_eq!(game.act(Action::Check), Ok(Action::Check));
+        assert!(game.is_ready_for_next_phase());
+        // The big blind acts first after the flop.
+        let game: Game<Flop> = game.into();
+        let mut game: Game<TakeAction> = game.into();
+        assert_eq!(game.data.next_action_idx, Some(big_blind_idx));
+        assert_eq!(game.act(Action::Check), Ok(Action::Check));
+        assert_eq!(game.data.next_action_idx, Some(small_blind_idx));
+        assert_eq!(game.act(Action::Check), Ok(Action::Check));
+        let game: Game<Turn> = game.into();
+        let game: Game<TakeAction> = game.into();
+        assert_eq!(game.data.next_action_idx, Some(big_blind_idx));
+    }
+
     #[test]
     fn manipulating_user_in_lobby() {
         let mut game = Game::<SeatPlayers>::new();
@@ -2101,6 +4934,62 @@ mod game_tests {
         assert_eq!(game.new_user(username), Err(UserError::CapacityReached));
     }
 
+    #[test]
+    fn spectators_full() {
+        let mut game = Game::<Lobby>::new();
+        game.data.settings = game.data.settings.clone().with_max_spectators(1);
+        assert_eq!(game.new_user("ognf"), Ok(true));
+        assert_eq!(game.new_user("onjk"), Err(UserError::SpectatorsFull));
+
+        // Making room in the watch list lets someone else in.
+        assert_eq!(game.waitlist_user("ognf"), Ok(true));
+        assert_eq!(game.new_user("onjk"), Ok(true));
+    }
+
+    #[test]
+    fn buy_in() {
+        let settings = GameSettings::default()
+            .with_buy_in_bounds(10000, 30000)
+            .with_rebuys(2);
+        let mut game: Game<Lobby> = settings.into();
+        assert_eq!(game.new_user("ognf"), Ok(true));
+        assert_eq!(
+            game.buy_in_user("ognf", 10100),
+            Err(UserError::StackAboveMaxBuyIn {
+                max_buy_in: ChipDisplay::Usd.chips(30000)
+            })
+        );
+        assert_eq!(game.buy_in_user("ognf", 10000), Ok(true));
+        assert_eq!(game.data.spectators["ognf"].money, 30000);
+        assert_eq!(game.data.donations, -10000.0);
+
+        // Losing money and topping up counts toward the same limit, even
+        // after leaving and coming back. Short stacks can't take a seat
+        // until they meet the minimum.
+        game.data.spectators.get_mut("ognf").unwrap().money = 5000;
+        let error = Err(UserError::StackBelowMinBuyIn {
+            min_buy_in: ChipDisplay::Usd.chips(10000),
+        });
+        assert_eq!(game.waitlist_user("ognf"), error);
+        assert_eq!(game.buy_in_user("ognf", 2000), error);
+        assert_eq!(game.buy_in_user("ognf", 5000), Ok(true));
+        assert_eq!(game.waitlist_user("ognf"), Ok(true));
+        assert_eq!(game.remove_user("ognf"), Ok(true));
+        assert_eq!(game.new_user("ognf"), Ok(true));
+        assert_eq!(
+            game.buy_in_user("ognf", 5000),
+            Err(UserError::RebuyLimitReached)
+        );
+        assert_eq!(
+            game.buy_in_user("onjk", 5000),
+            Err(UserError::UserDoesNotExist)
+        );
+
+        let game = init_2_player_tournament();
+        let mut game: Game<Lobby> = game.into();
+        assert_eq!(game.buy_in_user("0", 5000), Err(UserError::CannotBuyIn));
+    }
+
     #[test]
     fn move_button() {
         let game = init_game_at_move_button();
@@ -2115,6 +5004,56 @@ mod game_tests {
 
     // Fill a game to capacity and then move the action index around.
     // Every player should get their turn.
+    #[test]
+    fn move_button_past_eliminated_players() {
+        // Blinds for the hand after the first hand's button, small blind,
+        // big blind, or first player to act is eliminated.
+        for (eliminated, small_blind, big_blind, is_small_blind_dead) in [
+            (None, "2", "3", false),
+            (Some("0"), "2", "3", false),
+            (Some("1"), "2", "3", false),
+            (Some("2"), "1", "3", true),
+            (Some("3"), "2", "0", false),
+        ] {
+            let game = Game::<Lobby>::new();
+            let mut game: Game<SeatPlayers> = game.into();
+            for i in 0..4 {
+                let username = i.to_string();
+                game.new_user(&username).unwrap();
+                game.waitlist_user(&username).unwrap();
+            }
+            let game: Game<MoveButton> = game.into();
+            let game: Game<CollectBlinds> = game.into();
+            let username = |game: &Game<CollectBlinds>, player_idx: usize| {
+                game.data.players[player_idx].user.name.to_string()
+            };
+            assert_eq!(username(&game, game.data.small_blind_idx), "1");
+            assert_eq!(username(&game, game.data.big_blind_idx), "2");
+
+            let mut game = Game {
+                data: game.data,
+                state: MoveButton {},
+            };
+            if let Some(eliminated) = eliminated {
+                game.data.players.retain(|p| p.user.name != eliminated);
+            }
+            let game: Game<CollectBlinds> = game.into();
+            assert_eq!(username(&game, game.data.small_blind_idx), small_blind);
+            assert_eq!(username(&game, game.data.big_blind_idx), big_blind);
+            assert_eq!(game.data.is_small_blind_dead, is_small_blind_dead);
+
+            // No one pays the small blind when it's dead.
+            let small_blind_idx = game.data.small_blind_idx;
+            let game: Game<Deal> = game.into();
+            let investment = game.data.pot.get_investment_by_player_idx(small_blind_idx);
+            if is_small_blind_dead {
+                assert_eq!(investment, 0);
+            } else {
+                assert_eq!(investment, game.data.small_blind);
+            }
+        }
+    }
+
     #[test]
     fn move_next_action_idx() {
         let game = Game::<Lobby>::new();
@@ -2159,6 +5098,212 @@ mod game_tests {
         assert_eq!(game.get_num_players(), 1);
     }
 
+    #[test]
+    fn record_hand_history() {
+        let mut game = init_game_at_showdown_with_2_all_ins();
+        game.data.board = vec![
+            Card(2, Suit::Diamond),
+            Card(4, Suit::Diamond),
+            Card(5, Suit::Diamond),
+            Card(6, Suit::Diamond),
+            Card(7, Suit::Diamond),
+        ];
+        game.data.players[1].cards = vec![Card(1, Suit::Heart), Card(7, Suit::Heart)];
+        game.data.players[2].cards = vec![Card(2, Suit::Heart), Card(5, Suit::Heart)];
+        let game: Game<DistributePot> = game.into();
+        let game: Game<RemovePlayers> = game.into();
+        let history = game.get_hand_history();
+        assert_eq!(history.players.len(), 3);
+        assert_eq!(history.small_blind_username, "1");
+        assert_eq!(history.big_blind_username, "2");
+        assert_eq!(
+            history
+                .actions
+                .iter()
+                .map(|a| (a.street, a.username.as_str(), a.action.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (Street::Preflop, "0", Action::Fold),
+                (Street::Preflop, "1", Action::AllIn),
+                (Street::Preflop, "2", Action::AllIn),
+            ]
+        );
+        assert_eq!(history.board, game.data.board);
+        assert!(history.to_string().contains("  preflop:\n    0 folds\n"));
+        assert_eq!(history.shown_hands.len(), 2);
+        assert_eq!(history.pots.len(), 1);
+        // The board plays, so the pot is split.
+        let mut winners = history.pots[0].winners.clone();
+        winners.sort();
+        assert_eq!(winners, vec!["1".to_string(), "2".to_string()]);
+    }
+
+    #[test]
+    fn split_pot_odd_chips() {
+        let mut game = init_game_at_showdown_with_2_all_ins();
+        game.data.board = vec![
+            Card(2, Suit::Diamond),
+            Card(4, Suit::Diamond),
+            Card(5, Suit::Diamond),
+            Card(6, Suit::Diamond),
+            Card(7, Suit::Diamond),
+        ];
+        game.data.players[1].cards = vec![Card(1, Suit::Heart), Card(7, Suit::Heart)];
+        game.data.players[2].cards = vec![Card(2, Suit::Heart), Card(5, Suit::Heart)];
+        // The button folded after putting in a chip, so the pot doesn't
+        // split evenly.
+        assert_eq!(game.get_button_idx(), 0);
+        game.data.players[0].user.money -= 1;
+        game.data.pot.investments.insert(0, 1);
+        let size = game.data.pot.get_size();
+        assert_eq!(size % 2, 1);
+        let game: Game<DistributePot> = game.into();
+        let game: Game<RemovePlayers> = game.into();
+
+        // The odd chip goes to the winner closest to the button's left.
+        assert_eq!(game.data.players[1].user.money, size / 2 + 1);
+        assert_eq!(game.data.players[2].user.money, size / 2);
+        assert_eq!(game.data.donations, 0.0);
+        let history = game.get_hand_history();
+        assert_eq!(
+            history.pots[0].winners,
+            vec!["1".to_string(), "2".to_string()]
+        );
+        assert_eq!(history.audit.awarded, size);
+        assert_eq!(history.audit.donated, 0);
+        assert!(history.is_balanced());
+
+        // Heads-up, the small blind has the button, so the big blind is
+        // first to its left.
+        let game = init_2_player_game();
+        let game: Game<MoveButton> = game.into();
+        let game: Game<CollectBlinds> = game.into();
+        assert_eq!(game.get_button_idx(), game.data.small_blind_idx);
+        assert_eq!(game.get_seats_left_of_button(game.data.big_blind_idx), 0);
+    }
+
+    #[test]
+    fn label_positions() {
+        let mut game: Game<Lobby> = GameSettings::default().into();
+        for i in 0..6 {
+            let username = i.to_string();
+            game.new_user(&username).unwrap();
+            game.waitlist_user(&username).unwrap();
+        }
+        let game: Game<SeatPlayers> = game.into();
+        let game: Game<MoveButton> = game.into();
+        let mut game: Game<CollectBlinds> = game.into();
+        let labels = |game: &Game<CollectBlinds>| {
+            let button_idx = game.get_button_idx();
+            let positions = game.get_positions();
+            (0..positions.len())
+                .map(|offset| positions[(button_idx + offset) % positions.len()])
+                .map(|position| position.map_or("-".to_string(), |p| p.to_string()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(labels(&game), ["BTN", "SB", "BB", "UTG", "HJ", "CO"]);
+
+        // Players sitting out don't get a position, and the ones left
+        // shift up.
+        let hijack_idx = (game.get_button_idx() + 4) % 6;
+        game.data.players[hijack_idx].state = PlayerState::SitOut;
+        assert_eq!(labels(&game), ["BTN", "SB", "BB", "UTG", "-", "CO"]);
+
+        // Views carry each player's seat and position.
+        let view = game.get_revealed_view();
+        let player = &view.players[game.data.big_blind_idx];
+        assert_eq!(
+            player.seat_idx,
+            game.data.players[game.data.big_blind_idx].seat_idx
+        );
+        assert_eq!(player.position, Some(Position::BigBlind));
+    }
+
+    #[test]
+    fn summarize_showdown() {
+        let mut game = init_game_at_showdown_with_2_all_ins();
+        game.data.board = vec![
+            Card(2, Suit::Diamond),
+            Card(4, Suit::Diamond),
+            Card(9, Suit::Club),
+            Card(13, Suit::Diamond),
+            Card(13, Suit::Spade),
+        ];
+        game.data.players[1].cards = vec![Card(1, Suit::Heart), Card(13, Suit::Heart)];
+        game.data.players[2].cards = vec![Card(2, Suit::Heart), Card(2, Suit::Spade)];
+        let game: Game<DistributePot> = game.into();
+        let game: Game<RemovePlayers> = game.into();
+        let showdown = game.get_hand_history().showdown().unwrap();
+        let hands: Vec<_> = showdown
+            .hands
+            .iter()
+            .map(|hand| (hand.username.as_str(), hand.descriptions.clone()))
+            .collect();
+        assert_eq!(
+            hands,
+            vec![
+                ("1", vec!["three kings".to_string()]),
+                ("2", vec!["a full house, twos full of kings".to_string()]),
+            ]
+        );
+        assert_eq!(showdown.pots.len(), 1);
+        assert_eq!(showdown.pots[0].winners, vec!["2".to_string()]);
+        let repr = showdown.to_string();
+        assert!(repr.contains("\n2 shows 2/h 2/s for a full house, twos full of kings"));
+        assert!(repr.contains(&format!("\n2 wins {}", Dollars(showdown.pots[0].size))));
+
+        // Hands that don't go to showdown don't have one.
+        let mut game = init_game_at_showdown_with_2_all_ins();
+        for player in game.data.players.iter_mut() {
+            player.state = PlayerState::Fold;
+        }
+        game.data.players[1].state = PlayerState::Wait;
+        let game: Game<DistributePot> = game.into();
+        let game: Game<RemovePlayers> = game.into();
+        assert!(game.get_hand_history().showdown().is_none());
+    }
+
+    #[test]
+    fn describe_hands() {
+        let describe = |cards: &[Card]| evaluate(cards).description();
+        assert_eq!(
+            describe(&[
+                Card(1, Suit::Heart),
+                Card(9, Suit::Club),
+                Card(6, Suit::Spade)
+            ]),
+            "ace high"
+        );
+        assert_eq!(
+            describe(&[
+                Card(6, Suit::Heart),
+                Card(6, Suit::Club),
+                Card(9, Suit::Spade)
+            ]),
+            "a pair of sixes"
+        );
+        assert_eq!(
+            describe(&[
+                Card(5, Suit::Heart),
+                Card(4, Suit::Club),
+                Card(3, Suit::Spade),
+                Card(2, Suit::Spade),
+                Card(1, Suit::Diamond),
+            ]),
+            "a straight, five high"
+        );
+        assert_eq!(
+            describe(&[
+                Card(10, Suit::Heart),
+                Card(11, Suit::Heart),
+                Card(12, Suit::Heart),
+                Card(13, Suit::Heart),
+                Card(1, Suit::Heart),
+            ]),
+            "a royal flush"
+        );
+    }
+
     #[test]
     fn remove_player() {
         let mut game = init_game_at_showdown_with_2_all_ins();
@@ -2188,40 +5333,362 @@ mod game_tests {
     }
 
     #[test]
-    fn remove_player_with_queue() {
-        let mut game = init_game_at_showdown_with_2_all_ins();
-        game.data.board = vec![
-            Card(2, Suit::Diamond),
-            Card(4, Suit::Diamond),
-            Card(5, Suit::Diamond),
-            Card(6, Suit::Diamond),
-            Card(7, Suit::Diamond),
-        ];
-        game.data.players[1].cards = vec![Card(1, Suit::Heart), Card(7, Suit::Heart)];
-        game.data.players[2].cards = vec![Card(2, Suit::Heart), Card(5, Suit::Heart)];
-        assert_eq!(game.remove_user("0"), Ok(false));
+    fn remove_player_with_queue() {
+        let mut game = init_game_at_showdown_with_2_all_ins();
+        game.data.board = vec![
+            Card(2, Suit::Diamond),
+            Card(4, Suit::Diamond),
+            Card(5, Suit::Diamond),
+            Card(6, Suit::Diamond),
+            Card(7, Suit::Diamond),
+        ];
+        game.data.players[1].cards = vec![Card(1, Suit::Heart), Card(7, Suit::Heart)];
+        game.data.players[2].cards = vec![Card(2, Suit::Heart), Card(5, Suit::Heart)];
+        assert_eq!(game.remove_user("0"), Ok(false));
+        let game: Game<DistributePot> = game.into();
+        let game: Game<RemovePlayers> = game.into();
+        let game: Game<DivideDonations> = game.into();
+        assert!(!game.contains_user("0"));
+        assert!(game.contains_player("1"));
+        assert!(game.contains_player("2"));
+        let game: Game<UpdateBlinds> = game.into();
+        for i in 0..2 {
+            assert_eq!(game.data.players[i].user.money, game.data.settings.buy_in);
+        }
+        let mut expected_open_seats = Vec::from_iter(3..game.data.settings.max_players);
+        expected_open_seats.push(0);
+        assert_eq!(game.data.open_seats, expected_open_seats)
+    }
+
+    #[test]
+    fn restore_from_snapshot() {
+        let user = |name: &str, money| User {
+            name: name.into(),
+            money,
+        };
+        let snapshot = GameSnapshot {
+            donations: 2.0,
+            spectators: vec![user("0", 100)],
+            waitlist: vec![user("1", 200)],
+            players: vec![(4, user("2", 300)), (MAX_PLAYERS, user("3", 400))],
+            jackpot: 50,
+        };
+        let game = Game::<Lobby>::restore(GameSettings::default(), snapshot);
+        assert!(game.contains_spectator("0"));
+        assert!(game.contains_waitlister("1"));
+        assert!(game.contains_player("2"));
+        assert!(!game.data.open_seats.contains(&4));
+        // The last player's seat doesn't exist, so they're put back
+        // in the waitlist.
+        assert!(game.contains_waitlister("3"));
+        assert_eq!(
+            game.get_snapshot(),
+            GameSnapshot {
+                donations: 2.0,
+                spectators: vec![user("0", 100)],
+                waitlist: vec![user("1", 200), user("3", 400)],
+                players: vec![(4, user("2", 300))],
+                jackpot: 50,
+            }
+        );
+    }
+
+    #[test]
+    fn seat_players() {
+        let game = init_game_at_seat_players();
+        assert_eq!(game.data.num_players_active, game.get_num_players());
+        assert!(game.contains_player("0"));
+        assert!(game.contains_player("1"));
+        assert!(game.contains_player("2"));
+    }
+
+    #[test]
+    fn tournament_blinds_increase() {
+        let game = init_2_player_tournament();
+        assert!(game.data.is_tournament_running());
+        let mut game: Game<MoveButton> = game.into();
+        // Pretend the tournament has been running for a couple of levels.
+        if let Some(ref mut tournament) = game.data.tournament {
+            tournament.start = Some(Instant::now() - Duration::from_secs(150));
+        }
+        let game: Game<CollectBlinds> = game.into();
+        let game: Game<Deal> = game.into();
+        let mut game: Game<TakeAction> = game.into();
+        game.act(Action::Fold).unwrap();
+        let game: Game<ShowHands> = game.into();
+        let game: Game<DistributePot> = game.into();
+        let game: Game<RemovePlayers> = game.into();
+        let game: Game<DivideDonations> = game.into();
+        let game: Game<UpdateBlinds> = game.into();
+        let game: Game<BootPlayers> = game.into();
+        let tournament = game.get_tournament_view().unwrap();
+        assert_eq!(tournament.level, 3);
+        assert!(tournament.time_to_next_level.unwrap() <= Duration::from_secs(30));
+        assert_eq!(game.data.big_blind, 3 * game.data.settings.min_big_blind);
+        assert_eq!(
+            game.data.small_blind,
+            3 * game.data.settings.min_small_blind
+        );
+        // The next hand starts automatically.
+        let game: Game<Lobby> = game.into();
+        assert!(game.is_ready_to_start());
+    }
+
+    #[test]
+    fn tournament_short_stack_and_winner() {
+        let game = init_2_player_tournament();
+        let mut game: Game<MoveButton> = game.into();
+        // The big blind can't afford the full blind, but still gets to play.
+        game.data.players[0].user.money = 3;
+        game.data.players[1].user.money = 397;
+        let game: Game<CollectBlinds> = game.into();
+        assert_eq!(game.data.big_blind_idx, 0);
+        let game: Game<Deal> = game.into();
+        assert_eq!(game.data.players[0].state, PlayerState::AllIn);
+        assert_eq!(game.data.players[0].user.money, 0);
+        // The small blind already covers the short stack, so there's
+        // nothing left to do but show hands.
+        let game: Game<TakeAction> = game.into();
+        assert_eq!(game.get_action_options(), None);
+        assert!(game.is_ready_for_showdown());
+        let game: Game<Flop> = game.into();
+        let game: Game<Turn> = game.into();
+        let game: Game<River> = game.into();
+        let mut game: Game<ShowHands> = game.into();
+        game.data.board = vec![
+            Card(2, Suit::Spade),
+            Card(4, Suit::Diamond),
+            Card(6, Suit::Club),
+            Card(8, Suit::Diamond),
+            Card(10, Suit::Heart),
+        ];
+        game.data.players[0].cards = vec![Card(3, Suit::Heart), Card(9, Suit::Club)];
+        game.data.players[1].cards = vec![Card(1, Suit::Heart), Card(1, Suit::Diamond)];
+        let game: Game<DistributePot> = game.into();
+        let game: Game<ShowHands> = game.into();
+        let game: Game<DistributePot> = game.into();
+        let game: Game<RemovePlayers> = game.into();
+        let game: Game<DivideDonations> = game.into();
+        let game: Game<UpdateBlinds> = game.into();
+        let game: Game<BootPlayers> = game.into();
+        let mut game: Game<Lobby> = game.into();
+        assert_eq!(game.get_num_players(), 1);
+        assert!(!game.data.is_tournament_running());
+        assert!(!game.is_ready_to_start());
+        let tournament = game.get_tournament_view().unwrap();
+        assert_eq!(tournament.winner, Some("1".into()));
+        assert_eq!(tournament.time_to_next_level, None);
+        // The eliminated player can register for the next tournament.
+        assert_eq!(game.waitlist_user("0"), Ok(true));
+    }
+
+    #[test]
+    fn knockout_tournament_bounties() {
+        let settings = GameSettings::default()
+            .with_tournament(TournamentSettings::new(Duration::from_secs(60)).with_bounty(500));
+        let mut game: Game<Lobby> = settings.into();
+        for i in 0..3 {
+            let username = i.to_string();
+            game.new_user(&username).unwrap();
+            game.waitlist_user(&username).unwrap();
+        }
+        let game: Game<SeatPlayers> = game.into();
+        let mut game: Game<MoveButton> = game.into();
+        for (player, money) in game.data.players.iter_mut().zip([50, 1000, 500]) {
+            player.user.money = money;
+        }
+        let game: Game<CollectBlinds> = game.into();
+        let game: Game<Deal> = game.into();
+        let mut game: Game<TakeAction> = game.into();
+        while !game.is_ready_for_next_phase() {
+            game.act(Action::AllIn).unwrap();
+        }
+        let game: Game<Flop> = game.into();
+        let game: Game<Turn> = game.into();
+        let game: Game<River> = game.into();
+        let mut game: Game<ShowHands> = game.into();
+        game.data.board = vec![
+            Card(2, Suit::Spade),
+            Card(4, Suit::Diamond),
+            Card(6, Suit::Club),
+            Card(8, Suit::Diamond),
+            Card(10, Suit::Heart),
+        ];
+        game.data.players[0].cards = vec![Card(3, Suit::Heart), Card(9, Suit::Club)];
+        game.data.players[1].cards = vec![Card(13, Suit::Heart), Card(13, Suit::Diamond)];
+        game.data.players[2].cards = vec![Card(1, Suit::Heart), Card(1, Suit::Diamond)];
+
+        // The short stack loses the main pot, and the middle stack only
+        // loses a side pot, getting back what no one else could cover.
+        let mut game: Game<DistributePot> = game.into();
+        while game.get_num_pots() >= 2 {
+            let next: Game<ShowHands> = game.into();
+            game = next.into();
+        }
+        let game: Game<RemovePlayers> = game.into();
+        assert_eq!(game.data.players[0].user.money, 0);
+        assert_eq!(game.data.players[1].user.money, 500);
+        let history = game.get_hand_history();
+        assert_eq!(
+            history.knockouts,
+            vec![Knockout {
+                username: "0".into(),
+                eliminators: vec!["2".into()],
+                bounty: 500,
+            }]
+        );
+        assert_eq!(
+            history.knockouts[0].to_string(),
+            "2 knocked out 0, collecting a $5 bounty"
+        );
+        let tournament = game.get_tournament_view().unwrap();
+        assert_eq!(tournament.bounty, 500);
+        assert_eq!(tournament.bounties_won, BTreeMap::from([("2".into(), 500)]));
+
+        // The winner collects their own bounty once the tournament ends.
+        let mut game: Game<DivideDonations> = game.into();
+        game.data.players[1].user.money = 0;
+        let game: Game<UpdateBlinds> = game.into();
+        let game: Game<BootPlayers> = game.into();
+        let game: Game<Lobby> = game.into();
+        let tournament = game.get_tournament_view().unwrap();
+        assert_eq!(tournament.winner, Some("2".into()));
+        assert_eq!(
+            tournament.bounties_won,
+            BTreeMap::from([("2".into(), 1000)])
+        );
+        assert_eq!(tournament.bounties_to_string(), "2 collected $10");
+    }
+
+    #[test]
+    fn tournament_late_registration_and_reentry() {
+        let settings = GameSettings::default().with_tournament(
+            TournamentSettings::new(Duration::from_secs(60)).with_late_registration(1),
+        );
+        let buy_in = settings.buy_in;
+        let mut game: Game<Lobby> = settings.into();
+        for i in 0..3 {
+            let username = i.to_string();
+            game.new_user(&username).unwrap();
+            game.waitlist_user(&username).unwrap();
+        }
+        let mut game: Game<SeatPlayers> = game.into();
+        assert!(game.data.is_tournament_running());
+
+        // New users can register after the tournament starts.
+        assert_eq!(game.new_user("3"), Ok(true));
+        assert_eq!(game.waitlist_user("3"), Ok(true));
+        let tournament = game.get_tournament_view().unwrap();
+        assert!(tournament.is_registration_open);
+        assert_eq!(tournament.entries, 4);
+        assert_eq!(tournament.prize_pool, 4 * buy_in);
+        assert!(tournament
+            .to_string()
+            .ends_with("left), late registration open"));
+
+        // Play a hand and pretend the first player busted.
+        let game: Game<MoveButton> = game.into();
+        let game: Game<CollectBlinds> = game.into();
+        let game: Game<Deal> = game.into();
+        let mut game: Game<TakeAction> = game.into();
+        while !game.is_ready_for_next_phase() {
+            game.act(Action::Fold).unwrap();
+        }
+        let game: Game<Flop> = game.into();
+        let game: Game<Turn> = game.into();
+        let game: Game<River> = game.into();
+        let game: Game<ShowHands> = game.into();
+        let game: Game<DistributePot> = game.into();
+        let game: Game<RemovePlayers> = game.into();
+        let game: Game<DivideDonations> = game.into();
+        let game: Game<UpdateBlinds> = game.into();
+        let mut game: Game<BootPlayers> = game.into();
+        game.data.players[0].user.money = 0;
+        let mut game: Game<Lobby> = game.into();
+        assert!(game.data.spectators.contains_key("0"));
+
+        // Busted players re-enter with a fresh stack.
+        assert_eq!(game.waitlist_user("0"), Ok(true));
+        assert_eq!(game.data.waitlist.back().unwrap().money, buy_in);
+        let tournament = game.get_tournament_view().unwrap();
+        assert_eq!(tournament.entries, 5);
+        assert_eq!(game.data.tournament.as_ref().unwrap().entries["0"], 2);
+
+        // Registration closes once the late registration levels are over.
+        game.data.tournament.as_mut().unwrap().level = 1;
+        assert_eq!(game.new_user("4"), Ok(true));
+        assert_eq!(
+            game.waitlist_user("4"),
+            Err(UserError::TournamentInProgress)
+        );
+        assert!(!game.get_tournament_view().unwrap().is_registration_open);
+    }
+
+    #[test]
+    fn tournament_in_progress() {
+        let mut game = init_2_player_tournament();
+        assert_eq!(game.new_user("2"), Ok(true));
+        assert_eq!(
+            game.waitlist_user("2"),
+            Err(UserError::TournamentInProgress)
+        );
+    }
+
+    #[test]
+    fn multi_table_tournament_moves_players() {
+        let settings = GameSettings::default().with_tournament(
+            TournamentSettings::new(Duration::from_secs(60)).with_multi_table("sunday", 2),
+        );
+        let mut game: Game<Lobby> = settings.into();
+        for i in 0..3 {
+            let username = i.to_string();
+            game.new_user(&username).unwrap();
+            game.waitlist_user(&username).unwrap();
+        }
+        let game: Game<SeatPlayers> = game.into();
+        let game: Game<MoveButton> = game.into();
+        let game: Game<CollectBlinds> = game.into();
+        let game: Game<Deal> = game.into();
+        let mut game: Game<TakeAction> = game.into();
+        while !game.is_ready_for_next_phase() {
+            game.act(Action::Fold).unwrap();
+        }
+        let game: Game<Flop> = game.into();
+        let game: Game<Turn> = game.into();
+        let game: Game<River> = game.into();
+        let game: Game<ShowHands> = game.into();
         let game: Game<DistributePot> = game.into();
         let game: Game<RemovePlayers> = game.into();
         let game: Game<DivideDonations> = game.into();
-        assert!(!game.contains_user("0"));
-        assert!(game.contains_player("1"));
-        assert!(game.contains_player("2"));
         let game: Game<UpdateBlinds> = game.into();
-        for i in 0..2 {
-            assert_eq!(game.data.players[i].user.money, game.data.settings.buy_in);
-        }
-        let mut expected_open_seats = Vec::from_iter(3..game.data.settings.max_players);
-        expected_open_seats.push(0);
-        assert_eq!(game.data.open_seats, expected_open_seats)
-    }
+        let mut game: Game<BootPlayers> = game.into();
+        game.data.players[0].user.money = 0;
+        game.data.players[1].user.money = 0;
 
-    #[test]
-    fn seat_players() {
-        let game = init_game_at_seat_players();
-        assert_eq!(game.data.num_players_active, game.get_num_players());
-        assert!(game.contains_player("0"));
-        assert!(game.contains_player("1"));
-        assert!(game.contains_player("2"));
+        // The last player at the table waits for players to be moved in
+        // rather than winning.
+        let game: Game<Lobby> = game.into();
+        assert!(game.data.is_tournament_running());
+        assert!(!game.is_ready_to_start());
+        let mut state = PokerState::Lobby(game);
+        assert_eq!(state.get_tournament_players(), vec![Username::from("2")]);
+        let user = User {
+            name: "3".into(),
+            money: 123,
+        };
+        state.move_in_player(user.clone());
+        let PokerState::Lobby(ref game) = state else {
+            unreachable!("players are moved between hands");
+        };
+        assert!(game.is_ready_to_start());
+        assert_eq!(state.move_out_player("3"), Ok(user));
+        assert_eq!(state.move_out_player("3"), Err(UserError::UserNotPlaying));
+
+        // The table manager decides when the tournament's over.
+        state.finish_tournament();
+        let tournament = state.get_tournament_view().unwrap();
+        assert_eq!(tournament.winner, Some("2".into()));
+        assert_eq!(tournament.time_to_next_level, None);
     }
 
     #[test]
@@ -2250,6 +5717,32 @@ mod game_tests {
         assert_eq!(game.get_next_action_options(), None);
     }
 
+    #[test]
+    fn size_raises() {
+        let mut game = init_game_at_deal();
+        let player_idx = game.data.next_action_idx.unwrap();
+        let view = game.as_view(Viewer::User("0"));
+        assert_eq!(view.pot.size, 1500);
+        assert_eq!(view.call_amount(player_idx), 1000);
+        assert_eq!(view.min_raise_amount(player_idx), 2000);
+        // Calling $10 makes the pot $25, so a pot-sized raise is $35.
+        assert_eq!(view.pot_raise_amount(player_idx, 1.0), 3500);
+        assert_eq!(view.pot_raise_amount(player_idx, 0.5), 2250);
+        assert_eq!(view.multiple_raise_amount(player_idx, 3.0), 3000);
+        // Raises are never smaller than the minimum.
+        assert_eq!(view.pot_raise_amount(player_idx, 0.1), 2000);
+        assert_eq!(view.multiple_raise_amount(player_idx, 1.5), 2000);
+        assert_eq!(game.act(Action::Raise(3500)), Ok(Action::Raise(3500)));
+
+        // The small blind already has $5 in the pot.
+        let player_idx = game.data.next_action_idx.unwrap();
+        let view = game.as_view(Viewer::User("0"));
+        assert_eq!(view.call_amount(player_idx), 3000);
+        assert_eq!(view.min_raise_amount(player_idx), 6500);
+        assert_eq!(view.pot_raise_amount(player_idx, 1.0), 11000);
+        assert_eq!(view.multiple_raise_amount(player_idx, 3.0), 10000);
+    }
+
     #[test]
     fn take_action_2_calls_1_check() {
         let mut game = init_game_at_deal();
@@ -2257,29 +5750,29 @@ mod game_tests {
             game.get_next_action_options(),
             Some(HashSet::from([
                 Action::AllIn,
-                Action::Call(10),
+                Action::Call(1000),
                 Action::Fold,
-                Action::Raise(20)
+                Action::Raise(2000)
             ]))
         );
-        assert_eq!(game.act(Action::Call(10)), Ok(Action::Call(10)));
+        assert_eq!(game.act(Action::Call(1000)), Ok(Action::Call(1000)));
         assert_eq!(
             game.get_next_action_options(),
             Some(HashSet::from([
                 Action::AllIn,
-                Action::Call(5),
+                Action::Call(500),
                 Action::Fold,
-                Action::Raise(15)
+                Action::Raise(1500)
             ]))
         );
-        assert_eq!(game.act(Action::Call(5)), Ok(Action::Call(5)));
+        assert_eq!(game.act(Action::Call(500)), Ok(Action::Call(500)));
         assert_eq!(
             game.get_next_action_options(),
             Some(HashSet::from([
                 Action::AllIn,
                 Action::Check,
                 Action::Fold,
-                Action::Raise(20)
+                Action::Raise(2000)
             ]))
         );
         assert_eq!(game.act(Action::Check), Ok(Action::Check));
@@ -2293,9 +5786,9 @@ mod game_tests {
             game.get_next_action_options(),
             Some(HashSet::from([
                 Action::AllIn,
-                Action::Call(10),
+                Action::Call(1000),
                 Action::Fold,
-                Action::Raise(20)
+                Action::Raise(2000)
             ]))
         );
         assert_eq!(game.act(Action::Fold), Ok(Action::Fold));
@@ -2303,9 +5796,9 @@ mod game_tests {
             game.get_next_action_options(),
             Some(HashSet::from([
                 Action::AllIn,
-                Action::Call(5),
+                Action::Call(500),
                 Action::Fold,
-                Action::Raise(15)
+                Action::Raise(1500)
             ]))
         );
         assert_eq!(game.act(Action::Fold), Ok(Action::Fold));
@@ -2319,9 +5812,9 @@ mod game_tests {
             game.get_next_action_options(),
             Some(HashSet::from([
                 Action::AllIn,
-                Action::Call(10),
+                Action::Call(1000),
                 Action::Fold,
-                Action::Raise(20)
+                Action::Raise(2000)
             ]))
         );
         assert_eq!(game.act(Action::Fold), Ok(Action::Fold));
@@ -2329,52 +5822,167 @@ mod game_tests {
             game.get_next_action_options(),
             Some(HashSet::from([
                 Action::AllIn,
-                Action::Call(5),
+                Action::Call(500),
                 Action::Fold,
-                Action::Raise(15)
+                Action::Raise(1500)
             ]))
         );
-        // Total call is 20
-        assert_eq!(game.act(Action::Raise(15)), Ok(Action::Raise(15)));
+        // Total call is $20
+        assert_eq!(game.act(Action::Raise(1500)), Ok(Action::Raise(1500)));
         assert_eq!(
             game.get_next_action_options(),
             Some(HashSet::from([
                 Action::AllIn,
-                Action::Call(10),
+                Action::Call(1000),
                 Action::Fold,
-                Action::Raise(30)
+                Action::Raise(3000)
             ]))
         );
-        // Total call is 40
-        assert_eq!(game.act(Action::Raise(30)), Ok(Action::Raise(30)));
+        // Total call is $40
+        assert_eq!(game.act(Action::Raise(3000)), Ok(Action::Raise(3000)));
         assert_eq!(
             game.get_next_action_options(),
             Some(HashSet::from([
                 Action::AllIn,
-                Action::Call(20),
+                Action::Call(2000),
                 Action::Fold,
-                Action::Raise(60)
+                Action::Raise(6000)
             ]))
         );
-        // Total call is 80
-        assert_eq!(game.act(Action::Raise(60)), Ok(Action::Raise(60)));
+        // Total call is $80
+        assert_eq!(game.act(Action::Raise(6000)), Ok(Action::Raise(6000)));
         assert_eq!(
             game.get_next_action_options(),
             Some(HashSet::from([
                 Action::AllIn,
-                Action::Call(40),
+                Action::Call(4000),
                 Action::Fold,
-                Action::Raise(120)
+                Action::Raise(12000)
             ]))
         );
         assert_eq!(game.act(Action::Fold), Ok(Action::Fold));
         assert_eq!(game.get_next_action_options(), None);
     }
+
+    #[test]
+    fn format_and_parse_dollars() {
+        assert_eq!(Dollars(5).to_string(), "$0.05");
+        assert_eq!(Dollars(150).to_string(), "$1.50");
+        assert_eq!(Dollars(20000).to_string(), "$200");
+        assert_eq!(format!("{:>6}", Dollars(10)), " $0.10");
+
+        let parse = |s: &str| s.parse::<Dollars>().map(|Dollars(amount)| amount);
+        assert_eq!(parse("0.05"), Ok(5));
+        assert_eq!(parse(".1"), Ok(10));
+        assert_eq!(parse("$1.5"), Ok(150));
+        assert_eq!(parse("200"), Ok(20000));
+        assert_eq!(parse("2."), Ok(200));
+        assert_eq!(parse(""), Err(ParseUsdError::Invalid("".to_string())));
+        assert_eq!(parse("."), Err(ParseUsdError::Invalid(".".to_string())));
+        assert_eq!(parse("-1"), Err(ParseUsdError::Invalid("-1".to_string())));
+        assert_eq!(
+            parse("1.2.3"),
+            Err(ParseUsdError::Invalid("1.2.3".to_string()))
+        );
+        assert_eq!(
+            parse("0.001"),
+            Err(ParseUsdError::TooPrecise("0.001".to_string()))
+        );
+        assert_eq!(
+            parse("50000000"),
+            Err(ParseUsdError::TooLarge("50000000".to_string()))
+        );
+    }
+
+    #[test]
+    fn parse_cards_as_written() {
+        assert_eq!("As".parse(), Ok(Card(1, Suit::Spade)));
+        assert_eq!("td".parse(), Ok(Card(10, Suit::Diamond)));
+        // Cards parse the way they're displayed.
+        let card = Card(10, Suit::Heart);
+        assert_eq!(card.to_string().parse(), Ok(card));
+        assert_eq!(
+            "Ax".parse::<Card>(),
+            Err(ParseCardError::Invalid("Ax".to_string()))
+        );
+        assert_eq!(
+            "AsK".parse::<Card>(),
+            Err(ParseCardError::Invalid("AsK".to_string()))
+        );
+
+        assert_eq!(
+            parse_cards("AsKh"),
+            Ok(vec![Card(1, Suit::Spade), Card(13, Suit::Heart)])
+        );
+        assert_eq!(
+            parse_cards(" 2c, 10d 7/s "),
+            Ok(vec![
+                Card(2, Suit::Club),
+                Card(10, Suit::Diamond),
+                Card(7, Suit::Spade)
+            ])
+        );
+        assert_eq!(parse_cards(""), Ok(vec![]));
+        assert_eq!(
+            parse_cards("AsK"),
+            Err(ParseCardError::Invalid("AsK".to_string()))
+        );
+    }
+
+    #[test]
+    fn format_chips() {
+        assert_eq!(ChipDisplay::Chips.chips(150000).to_string(), "1500");
+        assert_eq!(ChipDisplay::Chips.chips(5).to_string(), "0.05");
+        assert_eq!(ChipDisplay::PlayMoney.chips(20000).to_string(), "P$200");
+        assert_eq!(ChipDisplay::Usd.chips(150).to_string(), "$1.50");
+        assert_eq!(format!("{:>6}", ChipDisplay::Chips.chips(1000)), "    10");
+
+        // Tables show amounts the way their settings say to.
+        let settings = GameSettings::default().with_chip_display(ChipDisplay::Chips);
+        let game: Game<Lobby> = settings.into();
+        let view = game.get_revealed_view();
+        assert_eq!(view.chip_display, ChipDisplay::Chips);
+        assert_eq!(view.pot.to_string(), "0");
+        assert_eq!(
+            Action::Raise(2000).to_action_string(ChipDisplay::Chips),
+            "raises 20"
+        );
+    }
+
+    #[test]
+    fn validate_usernames() {
+        assert!(Username::new("ognf").is_ok());
+        assert!(Username::new("o_n-f.9").is_ok());
+        assert_eq!(Username::new(""), Err(UsernameError::Empty));
+        assert_eq!(Username::new("o g"), Err(UsernameError::InvalidChar(' ')));
+        assert_eq!(Username::new(" ognf"), Err(UsernameError::InvalidChar(' ')));
+        assert_eq!(
+            Username::new("ognf\t"),
+            Err(UsernameError::InvalidChar('\t'))
+        );
+        assert_eq!(
+            Username::new("\u{1b}[2J"),
+            Err(UsernameError::InvalidChar('\u{1b}'))
+        );
+        assert_eq!(
+            Username::new("a".repeat(MAX_USER_INPUT_LENGTH + 1)),
+            Err(UsernameError::TooLong {
+                max: MAX_USER_INPUT_LENGTH
+            })
+        );
+        // Length is counted in characters, not bytes.
+        assert!(Username::new("é".repeat(MAX_USER_INPUT_LENGTH)).is_ok());
+    }
 }
 
 #[cfg(test)]
 mod state_tests {
-    use super::{entities::Action, PokerState, UserError};
+    use std::collections::BTreeMap;
+
+    use super::{
+        entities::{Action, Card, PlayerState, Street, Suit, Usd, Usdf},
+        functional, BombPotSettings, GameCheckpoint, GameSettings, PokerState, UserError,
+    };
 
     fn init_state() -> PokerState {
         let mut state = PokerState::new();
@@ -2386,6 +5994,114 @@ mod state_tests {
         state
     }
 
+    /// Start a game with bomb pots, stopped where the first hand's blinds
+    /// are about to be collected.
+    fn init_state_with_bomb_pots(every: Option<usize>) -> PokerState {
+        let settings = GameSettings::default()
+            .with_seed(0)
+            .with_bomb_pot(BombPotSettings::new(100, every));
+        let mut state: PokerState = settings.into();
+        for i in 0..3 {
+            let username = i.to_string();
+            state.new_user(&username).unwrap();
+            state.waitlist_user(&username).unwrap();
+        }
+        step_to_next_hand(state)
+    }
+
+    /// Step the game, folding for whoever's turn it is, until the next
+    /// hand's blinds are about to be collected.
+    fn step_to_next_hand(mut state: PokerState) -> PokerState {
+        state = state.step();
+        while !matches!(state, PokerState::CollectBlinds(_)) {
+            if let PokerState::Lobby(ref mut game) = state {
+                game.init_start().unwrap();
+            }
+            state = state.step();
+        }
+        state
+    }
+
+    #[test]
+    fn bomb_pots_every_few_hands() {
+        let mut state = init_state_with_bomb_pots(Some(2));
+        assert!(state.to_string().starts_with("collecting"));
+        state = step_to_next_hand(state);
+        assert_eq!(
+            state.to_string(),
+            "bomb pot! collecting $1 from all players and dealing straight to the flop"
+        );
+
+        // Everyone antes without posting blinds, and no one acts until
+        // the flop.
+        state = state.step().step();
+        assert!(state.get_next_action_username().is_none());
+        let view = state.get_revealed_view();
+        assert_eq!(view.bomb_pot, Some(100));
+        assert_eq!(view.pot.size, 300);
+        assert!(view.players.iter().all(|player| player.investment == 100));
+        state = state.step().step();
+        assert!(matches!(state, PokerState::TakeAction(_)));
+        assert_eq!(state.get_revealed_view().board.len(), 3);
+        let username = state.get_next_action_username().unwrap();
+        state.take_action(&username, Action::Check).unwrap();
+
+        while !matches!(state, PokerState::DivideDonations(_)) {
+            state = state.step();
+        }
+        let hand = state.get_hand_history().unwrap();
+        assert!(hand.is_bomb_pot);
+        assert_eq!(hand.ante, 100);
+        assert!(hand.is_balanced());
+        assert!(hand
+            .actions
+            .iter()
+            .all(|hand_action| hand_action.street != Street::Preflop));
+
+        // The hands in between are dealt normally.
+        state = step_to_next_hand(state);
+        assert!(state.to_string().starts_with("collecting"));
+        state = state.step().step();
+        assert!(state.get_next_action_username().is_some());
+        assert_eq!(state.get_revealed_view().bomb_pot, None);
+    }
+
+    #[test]
+    fn vote_for_bomb_pot() {
+        let mut state = init_state();
+        assert_eq!(
+            state.vote_bomb_pot("0", true),
+            Err(UserError::BombPotsDisabled)
+        );
+
+        let mut state = init_state_with_bomb_pots(None);
+        assert_eq!(
+            state.vote_bomb_pot("3", true),
+            Err(UserError::UserNotPlaying)
+        );
+        for username in ["0", "1"] {
+            assert_eq!(state.vote_bomb_pot(username, true), Ok(()));
+        }
+        state = step_to_next_hand(state);
+        assert!(state.to_string().starts_with("collecting"));
+
+        // Every player has to vote for it, and players can take their
+        // votes back.
+        for username in ["0", "1", "2"] {
+            assert_eq!(state.vote_bomb_pot(username, true), Ok(()));
+        }
+        assert_eq!(state.vote_bomb_pot("1", false), Ok(()));
+        state = step_to_next_hand(state);
+        assert!(state.to_string().starts_with("collecting"));
+        assert_eq!(state.vote_bomb_pot("1", true), Ok(()));
+        state = step_to_next_hand(state);
+        assert!(state.to_string().starts_with("bomb pot!"));
+
+        // Votes are used up by the bomb pot.
+        state = step_to_next_hand(state.step());
+        assert!(state.to_string().starts_with("collecting"));
+    }
+
     #[test]
     fn cant_start_game() {
         let mut state = init_state();
@@ -2400,6 +6116,148 @@ mod state_tests {
         assert_eq!(state.init_start("0"), Err(UserError::NotEnoughPlayers));
     }
 
+    #[test]
+    fn change_blinds() {
+        let mut state = init_state();
+        assert!(matches!(
+            state.set_blinds(10, 5),
+            Err(UserError::InvalidGameSettings(_))
+        ));
+        // The lobby doesn't wait for the next hand.
+        assert_eq!(state.set_blinds(4, 8), Ok(()));
+        let view = state.get_revealed_view();
+        assert_eq!((view.small_blind, view.big_blind), (4, 8));
+    }
+
+    #[test]
+    fn player_sits_out() {
+        let mut state = init_state();
+        // Waitlisters don't have seats to keep.
+        assert_eq!(state.sit_out_user("1"), Err(UserError::UserNotPlaying));
+        assert_eq!(state.init_start("0"), Ok(()));
+        // SeatPlayers
+        state = state.step();
+        // MoveButton
+        state = state.step();
+        // Players sitting out in the middle of a hand play it out.
+        assert_eq!(state.sit_out_user("1"), Ok(()));
+        // CollectBlinds, Deal, and TakeAction.
+        for _ in 0..3 {
+            state = state.step();
+        }
+        let PokerState::TakeAction(ref game) = state else {
+            panic!("should be taking actions");
+        };
+        assert_eq!(game.data.players[1].cards.len(), 2);
+        while !state.is_in_lobby() {
+            state = state.step();
+        }
+        let PokerState::Lobby(ref game) = state else {
+            unreachable!("in lobby");
+        };
+        assert_eq!(game.data.players[1].state, PlayerState::SitOut);
+
+        // The next hand is heads-up without the player sitting out, but
+        // they keep their seat and money.
+        let money = game.data.players[1].user.money;
+        assert_eq!(state.init_start("0"), Ok(()));
+        for _ in 0..5 {
+            state = state.step();
+        }
+        let PokerState::TakeAction(ref game) = state else {
+            panic!("should be taking actions");
+        };
+        assert!(game.is_heads_up());
+        assert!(game.data.players[1].cards.is_empty());
+        assert_eq!(game.data.players[1].user.money, money);
+        assert_ne!(game.data.small_blind_idx, 1);
+        assert_ne!(game.data.big_blind_idx, 1);
+
+        // Coming back takes effect after the hand.
+        assert_eq!(state.waitlist_user("1"), Ok(()));
+        while !state.is_in_lobby() {
+            state = state.step();
+        }
+        let PokerState::Lobby(ref game) = state else {
+            unreachable!("in lobby");
+        };
+        assert_eq!(game.data.players[1].state, PlayerState::Wait);
+    }
+
+    #[test]
+    fn player_buys_in_after_hand() {
+        let mut state = init_state();
+        assert_eq!(state.init_start("0"), Ok(()));
+        // SeatPlayers, MoveButton, CollectBlinds, Deal, and TakeAction.
+        for _ in 0..5 {
+            state = state.step();
+        }
+        let PokerState::TakeAction(ref game) = state else {
+            panic!("should be taking actions");
+        };
+        // Top up the big blind, which players can't use until the hand is
+        // over.
+        let big_blind_idx = game.data.big_blind_idx;
+        let username = game.data.players[big_blind_idx].user.name.clone();
+        let money = game.data.players[big_blind_idx].user.money;
+        let amount = game.data.big_blind;
+        let total_money = 3 * game.data.settings.buy_in;
+        assert_eq!(state.buy_in_user(&username, amount), Ok(()));
+        let PokerState::TakeAction(ref game) = state else {
+            unreachable!("still taking actions");
+        };
+        assert_eq!(game.data.players[big_blind_idx].user.money, money);
+        while !state.is_in_lobby() {
+            state = state.step();
+        }
+        let PokerState::Lobby(ref game) = state else {
+            unreachable!("in lobby");
+        };
+        // The bought money is borrowed from the donations.
+        assert!(game.data.players_buying_in.is_empty());
+        assert_eq!(game.data.donations, -(amount as Usdf));
+        let money: Usd = game.data.players.iter().map(|p| p.user.money).sum();
+        assert_eq!(money, total_money + amount);
+    }
+
+    #[test]
+    fn waitlisters_take_requested_seats_in_order() {
+        let mut state = init_state();
+        for username in ["3", "4"] {
+            state.new_user(username).unwrap();
+        }
+        assert_eq!(
+            state.waitlist_user_at_seat("3", 100),
+            Err(UserError::SeatDoesNotExist { seat_idx: 100 })
+        );
+        assert_eq!(state.waitlist_user_at_seat("3", 5), Ok(()));
+        assert_eq!(state.waitlist_user_at_seat("4", 5), Ok(()));
+        assert_eq!(state.init_start("0"), Ok(()));
+        // SeatPlayers and MoveButton.
+        for _ in 0..2 {
+            state = state.step();
+        }
+        let PokerState::MoveButton(ref game) = state else {
+            panic!("should be moving the button");
+        };
+        let seats: Vec<_> = game
+            .data
+            .players
+            .iter()
+            .map(|p| (p.user.name.as_str(), p.seat_idx))
+            .collect();
+        assert_eq!(seats, vec![("0", 0), ("1", 1), ("2", 2), ("3", 5)]);
+        // The second user to ask for the seat waits for it even though
+        // other seats are open.
+        let views = state.get_views();
+        assert_eq!(views["4"].waitlist_position, Some(1));
+        assert_eq!(views["4"].seat_requests["4"], 5);
+        assert_eq!(
+            state.waitlist_user_at_seat("3", 6),
+            Err(UserError::UserAlreadySeated)
+        );
+    }
+
     #[test]
     fn early_showdown_1_winner_2_early_folds() {
         let mut state = init_state();
@@ -2500,11 +6358,14 @@ mod state_tests {
         state = state.step();
         // Call
         assert_eq!(
-            state.take_action("0", Action::Call(10)),
-            Ok(Action::Call(10))
+            state.take_action("0", Action::Call(1000)),
+            Ok(Action::Call(1000))
         );
         // Check
-        assert_eq!(state.take_action("1", Action::Call(5)), Ok(Action::Call(5)));
+        assert_eq!(
+            state.take_action("1", Action::Call(500)),
+            Ok(Action::Call(500))
+        );
         // Check
         assert_eq!(state.take_action("2", Action::Check), Ok(Action::Check));
         // Flop
@@ -2553,4 +6414,260 @@ mod state_tests {
         state = state.step();
         assert_eq!(state.init_start("0"), Ok(()));
     }
+
+    fn call_or_check(state: &mut PokerState) {
+        let username = state.get_next_action_username().unwrap();
+        let action_options = state.get_action_options().unwrap();
+        let action = action_options
+            .get(&Action::Call(0))
+            .or(action_options.get(&Action::Check))
+            .cloned()
+            .unwrap();
+        assert_eq!(state.take_action(&username, action.clone()), Ok(action));
+    }
+
+    #[test]
+    fn show_or_muck_losing_hands() {
+        let mut state = init_state();
+        assert_eq!(state.init_start("0"), Ok(()));
+        while !matches!(state, PokerState::ShowHands(_)) {
+            if state.get_action_options().is_some() {
+                call_or_check(&mut state);
+            } else {
+                state = state.step();
+            }
+        }
+        let data = state.get_data_mut();
+        data.board = vec![
+            Card(2, Suit::Diamond),
+            Card(5, Suit::Club),
+            Card(9, Suit::Heart),
+            Card(11, Suit::Diamond),
+            Card(13, Suit::Spade),
+        ];
+        data.players[0].cards = vec![Card(1, Suit::Heart), Card(1, Suit::Club)];
+        data.players[1].cards = vec![Card(3, Suit::Heart), Card(3, Suit::Spade)];
+        data.players[2].cards = vec![Card(6, Suit::Heart), Card(7, Suit::Spade)];
+        // DistributePot, then RemovePlayers.
+        state = state.step().step();
+        assert!(matches!(state, PokerState::RemovePlayers(_)));
+
+        // Only the winner's hand is face up until the losers choose.
+        let view = state.get_views().remove("1").unwrap();
+        let cards: Vec<_> = view.players.iter().map(|p| p.cards.len()).collect();
+        assert_eq!(cards, vec![2, 2, 0]);
+        assert_eq!(state.get_showdown_losers(), vec!["1", "2"]);
+        assert_eq!(state.muck_hand("0"), Err(UserError::CannotMuckHand));
+        assert_eq!(state.muck_hand("1"), Ok(()));
+        assert_eq!(state.muck_hand("1"), Err(UserError::CannotMuckHand));
+        assert_eq!(state.show_hand("2"), Ok(()));
+        assert!(state.get_showdown_losers().is_empty());
+        assert!(state.get_hand_history().is_none());
+
+        // The choices are recorded once the window closes.
+        state = state.step();
+        let history = state.get_hand_history().unwrap();
+        let shown: Vec<_> = history
+            .shown_hands
+            .iter()
+            .map(|(u, _)| u.as_str())
+            .collect();
+        assert_eq!(shown, vec!["0", "2"]);
+        assert_eq!(history.mucked_hands, vec!["1"]);
+        assert!(history.to_string().contains("  1 mucked\n"));
+        let showdown = history.showdown().unwrap();
+        assert_eq!(showdown.pots[0].winners, vec!["0"]);
+        assert!(showdown.to_string().contains("\n1 mucks"));
+    }
+
+    /// The players whose hole cards are in each user's view, along with
+    /// a public view's under an empty username.
+    fn get_visible_hands(state: &PokerState) -> BTreeMap<String, Vec<String>> {
+        let views = state
+            .get_views()
+            .into_iter()
+            .map(|(username, view)| (username.to_string(), view))
+            .chain([(String::new(), state.get_public_view())]);
+        views
+            .map(|(username, view)| {
+                let players = view
+                    .players
+                    .into_iter()
+                    .filter(|p| !p.cards.is_empty())
+                    .map(|p| p.user.name.to_string())
+                    .collect();
+                (username, players)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn views_only_show_hands_users_can_see() {
+        let mut state = init_state();
+        state.new_user("3").unwrap();
+        assert_eq!(state.init_start("0"), Ok(()));
+
+        // Players only see their own hands while betting, and spectators
+        // don't see any.
+        let hidden = BTreeMap::from([
+            (String::new(), vec![]),
+            ("0".to_string(), vec!["0".to_string()]),
+            ("1".to_string(), vec!["1".to_string()]),
+            ("2".to_string(), vec!["2".to_string()]),
+            ("3".to_string(), vec![]),
+        ]);
+        while !matches!(state, PokerState::ShowHands(_)) {
+            if matches!(state, PokerState::TakeAction(_)) {
+                assert_eq!(get_visible_hands(&state), hidden);
+            }
+            if state.get_action_options().is_some() {
+                call_or_check(&mut state);
+            } else {
+                state = state.step();
+            }
+        }
+        assert_eq!(get_visible_hands(&state), hidden);
+
+        // Only the winner's hand is turned face up at showdown, and
+        // losers' hands stay hidden unless they show them.
+        let data = state.get_data_mut();
+        data.board = vec![
+            Card(2, Suit::Diamond),
+            Card(5, Suit::Club),
+            Card(9, Suit::Heart),
+            Card(11, Suit::Diamond),
+            Card(13, Suit::Spade),
+        ];
+        data.players[0].cards = vec![Card(1, Suit::Heart), Card(1, Suit::Club)];
+        data.players[1].cards = vec![Card(3, Suit::Heart), Card(3, Suit::Spade)];
+        data.players[2].cards = vec![Card(6, Suit::Heart), Card(7, Suit::Spade)];
+        state = state.step().step();
+        let visible = get_visible_hands(&state);
+        assert_eq!(visible["3"], vec!["0"]);
+        assert_eq!(visible["1"], vec!["0", "1"]);
+        assert_eq!(visible[""], vec!["0"]);
+        assert_eq!(state.show_hand("2"), Ok(()));
+        assert_eq!(get_visible_hands(&state)["3"], vec!["0", "2"]);
+
+        // Hands dealt for the next hand are hidden again.
+        state = step_to_next_hand(state).step().step();
+        assert!(matches!(state, PokerState::TakeAction(_)));
+        assert_eq!(get_visible_hands(&state), hidden);
+    }
+
+    #[test]
+    fn views_show_hands_for_run_out() {
+        let mut state = init_state();
+        state.new_user("3").unwrap();
+        assert_eq!(state.init_start("0"), Ok(()));
+        while state.get_action_options().is_none() {
+            state = state.step();
+        }
+
+        // Two players get all their money in, and the one that folded
+        // keeps their hand hidden while the others are turned face up.
+        let mut all_in = Vec::new();
+        for action in [Action::AllIn, Action::Fold, Action::AllIn] {
+            let username = state.get_next_action_username().unwrap();
+            if action == Action::AllIn {
+                all_in.push(username.to_string());
+            }
+            state.take_action(&username, action).unwrap();
+        }
+        all_in.sort();
+        state = state.step();
+        assert!(matches!(state, PokerState::Flop(_)));
+        for (username, players) in get_visible_hands(&state) {
+            let mut expected = all_in.clone();
+            if !username.is_empty() && !expected.contains(&username) && username != "3" {
+                expected.push(username);
+                expected.sort();
+            }
+            assert_eq!(players, expected);
+        }
+    }
+
+    #[test]
+    fn checkpoint_mid_hand() {
+        let mut state = init_state();
+        assert_eq!(state.init_start("0"), Ok(()));
+        // Up to TakeAction.
+        for _ in 0..5 {
+            state = state.step();
+        }
+        call_or_check(&mut state);
+        let checkpoint = bincode::serialize(&state.checkpoint()).unwrap();
+        let checkpoint: GameCheckpoint = bincode::deserialize(&checkpoint).unwrap();
+        let mut restored = PokerState::from_checkpoint(checkpoint);
+        assert_eq!(
+            serde_json::to_value(restored.get_revealed_view()).unwrap(),
+            serde_json::to_value(state.get_revealed_view()).unwrap()
+        );
+        assert_eq!(state.get_action_options(), restored.get_action_options());
+
+        // The hand goes on from where it was saved, and the rest of the
+        // deck doesn't repeat the cards that've been dealt.
+        call_or_check(&mut restored);
+        call_or_check(&mut restored);
+        // Flop, then back to TakeAction.
+        restored = restored.step().step();
+        let PokerState::TakeAction(ref game) = restored else {
+            panic!("should be back to taking actions after the flop");
+        };
+        assert_eq!(game.data.board.len(), 3);
+        let mut cards: Vec<_> = game
+            .data
+            .players
+            .iter()
+            .flat_map(|p| p.cards.iter())
+            .chain(game.data.board.iter())
+            .collect();
+        cards.sort();
+        cards.dedup();
+        assert_eq!(cards.len(), 9);
+        let mut deck = game.data.deck;
+        deck.sort();
+        let mut new_deck = functional::new_deck();
+        new_deck.sort();
+        assert_eq!(deck, new_deck);
+    }
+
+    #[test]
+    fn checkpoints_are_versioned() {
+        let state = init_state();
+        let checkpoint = serde_json::to_value(state.checkpoint()).unwrap();
+        assert!(checkpoint.get("V2").is_some());
+        let checkpoint: GameCheckpoint = serde_json::from_value(checkpoint).unwrap();
+        let restored = PokerState::from_checkpoint(checkpoint);
+        assert!(restored.is_in_lobby());
+        assert_eq!(restored.get_views().len(), 3);
+    }
+
+    #[test]
+    fn v1_checkpoints_count_money_in_dollars() {
+        // Saved mid-hand at a $5/$10 table with $200 buy-ins by the last
+        // version that counted money in whole dollars.
+        let checkpoint = include_str!("../tests/fixtures/checkpoint_v1.json");
+        let checkpoint: GameCheckpoint = serde_json::from_str(checkpoint).unwrap();
+        let mut state = PokerState::from_checkpoint(checkpoint);
+        let PokerState::TakeAction(ref game) = state else {
+            panic!("checkpoint should've been taking actions");
+        };
+        assert_eq!(game.data.settings.buy_in, 20000);
+        assert_eq!((game.data.small_blind, game.data.big_blind), (500, 1000));
+        let stacks: Vec<_> = game.data.players.iter().map(|p| p.user.money).collect();
+        assert_eq!(stacks, vec![19000, 19500, 19000]);
+        let pot: Usd = game.data.pot.investments.values().sum();
+        assert_eq!(pot, 1500 + 1000);
+
+        // The hand plays on in cents.
+        assert_eq!(
+            state.get_action_options().unwrap().get(&Action::Call(0)),
+            Some(&Action::Call(1000))
+        );
+        call_or_check(&mut state);
+        let money: Usd = state.get_data().players.iter().map(|p| p.user.money).sum();
+        let pot: Usd = state.get_data().pot.investments.values().sum();
+        assert_eq!(money + pot, 60000);
+    }
 }
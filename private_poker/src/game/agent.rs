@@ -0,0 +1,232 @@
+//! Programmatic players.
+//!
+//! An [`Agent`] decides what a player does on their turn. The same agent
+//! can play in-process in a [`Simulation`](super::sim::Simulation) or
+//! over the network with [`Client::play`](crate::Client::play). A few
+//! baseline agents are included for filling seats and for measuring new
+//! agents against.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::collections::HashSet;
+
+use super::{
+    entities::{Action, GameView, Rank},
+    functional::evaluate,
+    range::Range,
+};
+
+/// Names of the baseline agents accepted by [`from_name`].
+pub const BASELINE_AGENTS: [&str; 3] = ["calling_station", "random", "tight_aggressive"];
+
+pub trait Agent {
+    /// Pick an action given the game from the player's point of view and
+    /// the actions they can take. Actions that aren't valid are replaced
+    /// with folds.
+    fn act(&mut self, view: &GameView, action_options: &HashSet<Action>) -> Action;
+}
+
+/// Closures make quick agents, like `|_: &GameView, _: &HashSet<Action>| Action::Fold`.
+impl<F> Agent for F
+where
+    F: FnMut(&GameView, &HashSet<Action>) -> Action,
+{
+    fn act(&mut self, view: &GameView, action_options: &HashSet<Action>) -> Action {
+        self(view, action_options)
+    }
+}
+
+/// Get a baseline agent by name.
+pub fn from_name(name: &str) -> Option<Box<dyn Agent + Send>> {
+    match name {
+        "calling_station" => Some(Box::new(CallingStation)),
+        "random" => Some(Box::new(RandomAgent::new())),
+        "tight_aggressive" => Some(Box::new(TightAggressive::default())),
+        _ => None,
+    }
+}
+
+/// Check if possible, otherwise fold.
+fn check_or_fold(action_options: &HashSet<Action>) -> Action {
+    if action_options.contains(&Action::Check) {
+        Action::Check
+    } else {
+        Action::Fold
+    }
+}
+
+/// Check or call, going all-in if calling takes the whole stack.
+fn check_or_call(action_options: &HashSet<Action>) -> Action {
+    [Action::Check, Action::Call(0), Action::AllIn]
+        .iter()
+        .find_map(|action| action_options.get(action))
+        .cloned()
+        .unwrap_or(Action::Fold)
+}
+
+/// Never folds and never raises. Calling stations are easy to beat, so
+/// they make a good first opponent for a new agent.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CallingStation;
+
+impl Agent for CallingStation {
+    fn act(&mut self, _: &GameView, action_options: &HashSet<Action>) -> Action {
+        check_or_call(action_options)
+    }
+}
+
+/// Takes a random action each turn, raising a random amount between the
+/// minimum raise and the pot.
+#[derive(Clone, Debug)]
+pub struct RandomAgent {
+    rng: StdRng,
+}
+
+impl Default for RandomAgent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RandomAgent {
+    pub fn new() -> Self {
+        Self {
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Make the agent's choices reproducible, like for seeded simulations.
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Agent for RandomAgent {
+    fn act(&mut self, view: &GameView, action_options: &HashSet<Action>) -> Action {
+        // Options are put in a fixed order so seeded agents make the
+        // same choices every time.
+        let action_options: Vec<_> = [
+            Action::AllIn,
+            Action::Call(0),
+            Action::Check,
+            Action::Fold,
+            Action::Raise(0),
+        ]
+        .iter()
+        .filter_map(|action| action_options.get(action))
+        .collect();
+        if action_options.is_empty() {
+            return Action::Fold;
+        }
+        match action_options[self.rng.gen_range(0..action_options.len())] {
+            Action::Raise(min_raise) => {
+                let max_raise = view.pot.size.max(*min_raise);
+                Action::Raise(self.rng.gen_range(*min_raise..=max_raise))
+            }
+            action => action.clone(),
+        }
+    }
+}
+
+/// Only plays hands from a strong starting range, raising them preflop
+/// and betting the pot after the flop with two pair or better. One pair
+/// calls small bets, and everything else checks or folds.
+#[derive(Clone, Debug)]
+pub struct TightAggressive {
+    range: Range,
+}
+
+impl Default for TightAggressive {
+    fn default() -> Self {
+        Self::new(
+            "77+, A9s+, KTs+, QTs+, JTs, AJo+, KQo"
+                .parse()
+                .expect("default range is valid"),
+        )
+    }
+}
+
+impl TightAggressive {
+    /// Play the starting hands in the range.
+    pub fn new(range: Range) -> Self {
+        Self { range }
+    }
+
+    fn bet_pot(view: &GameView, action_options: &HashSet<Action>) -> Action {
+        match action_options.get(&Action::Raise(0)) {
+            Some(Action::Raise(min_raise)) => Action::Raise(view.pot.size.max(*min_raise)),
+            _ => check_or_call(action_options),
+        }
+    }
+}
+
+impl Agent for TightAggressive {
+    fn act(&mut self, view: &GameView, action_options: &HashSet<Action>) -> Action {
+        // It's the agent's turn, so the player with the next action is
+        // the agent.
+        let Some(player) = view
+            .next_action_idx
+            .and_then(|player_idx| view.players.get(player_idx))
+        else {
+            return check_or_fold(action_options);
+        };
+        let [card1, card2] = player.cards[..] else {
+            return check_or_fold(action_options);
+        };
+
+        if view.board.is_empty() {
+            return if self.range.contains(&[card1, card2]) {
+                TightAggressive::bet_pot(view, action_options)
+            } else {
+                check_or_fold(action_options)
+            };
+        }
+
+        let mut cards = vec![card1, card2];
+        cards.extend_from_slice(&view.board);
+        match evaluate(&cards).rank() {
+            Rank::HighCard => check_or_fold(action_options),
+            Rank::OnePair => match action_options.get(&Action::Call(0)) {
+                Some(Action::Call(call)) if 2 * call <= view.pot.size => Action::Call(*call),
+                _ => check_or_fold(action_options),
+            },
+            _ => TightAggressive::bet_pot(view, action_options),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CallingStation, RandomAgent, TightAggressive};
+    use crate::game::{sim::Simulation, GameSettings};
+
+    #[test]
+    fn baseline_agents_play() {
+        let mut sim = Simulation::new(GameSettings::default().with_seed(0));
+        sim.add_agent("calling_station", CallingStation).unwrap();
+        sim.add_agent("random", RandomAgent::with_seed(0)).unwrap();
+        sim.add_agent("tight_aggressive", TightAggressive::default())
+            .unwrap();
+        let results = sim.run(500);
+        assert_eq!(results.hands, 500);
+        for player_results in results.players.values() {
+            assert_eq!(player_results.invalid_actions, 0);
+        }
+        let calling_station = &results.players["calling_station"];
+        let tight_aggressive = &results.players["tight_aggressive"];
+        assert!(tight_aggressive.vpip_hands < calling_station.vpip_hands);
+        assert!(tight_aggressive.pfr_hands > 0);
+    }
+
+    #[test]
+    fn seeded_random_agents_are_reproducible() {
+        let run = || {
+            let mut sim = Simulation::new(GameSettings::default().with_seed(1));
+            sim.add_agent("0", RandomAgent::with_seed(0)).unwrap();
+            sim.add_agent("1", RandomAgent::with_seed(1)).unwrap();
+            sim.run(100)
+        };
+        assert_eq!(run(), run());
+    }
+}
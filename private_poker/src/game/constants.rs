@@ -1,4 +1,19 @@
+use std::time::Duration;
+
 // Don't want too many people waiting to play the game.
 pub const MAX_PLAYERS: usize = 10;
 pub const DEFAULT_MAX_USERS: usize = MAX_PLAYERS + 6;
+/// Most users a table can have. Tables make room for all of them up
+/// front, and users can create tables with their own settings.
+pub const MAX_USERS: usize = 64;
+/// Everyone at a table can spectate by default since that's where new
+/// users start.
+pub const DEFAULT_MAX_SPECTATORS: usize = DEFAULT_MAX_USERS;
+/// Times a user can buy more money at a table.
+pub const DEFAULT_MAX_REBUYS: usize = 3;
+/// Time players have to act before they're forced to fold.
+pub const DEFAULT_TURN_TIMEOUT: Duration = Duration::from_secs(30);
 pub const MAX_USER_INPUT_LENGTH: usize = 16;
+pub const MAX_CHAT_MESSAGE_LENGTH: usize = 256;
+/// Tournament blind multiples for each level relative to the minimum blinds.
+pub const TOURNAMENT_BLIND_MULTIPLES: [u32; 10] = [1, 2, 3, 4, 6, 8, 10, 15, 20, 30];
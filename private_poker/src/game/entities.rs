@@ -1,12 +1,17 @@
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{HashMap, VecDeque},
+    borrow::Borrow,
+    cmp::min,
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
     fmt,
     hash::{Hash, Hasher},
     mem::discriminant,
+    ops::Deref,
+    str::FromStr,
+    time::Duration,
 };
 
-use super::constants;
+use super::{constants, functional};
 
 #[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub enum Suit {
@@ -54,7 +59,71 @@ impl fmt::Display for Card {
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+/// Why cards couldn't be parsed.
+#[derive(Clone, Debug, Eq, thiserror::Error, PartialEq)]
+pub enum ParseCardError {
+    #[error("{0:?} isn't a card")]
+    Invalid(String),
+}
+
+/// Parse the card at the start of the string, returning it along with
+/// the rest of the string.
+fn parse_card_prefix(s: &str) -> Option<(Card, &str)> {
+    let (value, rest) = match s.get(..2) {
+        Some("10") => (10, &s[2..]),
+        _ => {
+            let mut chars = s.chars();
+            let value = match chars.next()? {
+                'A' | 'a' => 1,
+                'K' | 'k' => 13,
+                'Q' | 'q' => 12,
+                'J' | 'j' => 11,
+                'T' | 't' => 10,
+                c @ '2'..='9' => c as Value - b'0',
+                _ => return None,
+            };
+            (value, chars.as_str())
+        }
+    };
+    let rest = rest.strip_prefix('/').unwrap_or(rest);
+    let mut chars = rest.chars();
+    let suit = match chars.next()? {
+        'c' => Suit::Club,
+        'd' => Suit::Diamond,
+        'h' => Suit::Heart,
+        's' => Suit::Spade,
+        _ => return None,
+    };
+    Some((Card(value, suit), chars.as_str()))
+}
+
+impl FromStr for Card {
+    type Err = ParseCardError;
+
+    /// Parse a card written as its value and suit, like `As`, `Td`, or
+    /// `10/h` the way cards are displayed. Aces are parsed as 1s.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_card_prefix(s.trim()) {
+            Some((card, "")) => Ok(card),
+            _ => Err(ParseCardError::Invalid(s.to_string())),
+        }
+    }
+}
+
+/// Parse cards written one after another, like `AsKh` or `As Kh, 7c`.
+pub fn parse_cards(s: &str) -> Result<Vec<Card>, ParseCardError> {
+    let mut cards = vec![];
+    let mut rest = s.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+    while !rest.is_empty() {
+        let (card, next) =
+            parse_card_prefix(rest).ok_or_else(|| ParseCardError::Invalid(s.to_string()))?;
+        cards.push(card);
+        rest = next.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+    }
+    Ok(cards)
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub enum Rank {
     HighCard,
     OnePair,
@@ -108,35 +177,372 @@ impl fmt::Display for SubHand {
     }
 }
 
-/// Type alias for whole dollars. All bets and player stacks are represented
-/// as whole dollars (there's no point arguing over pennies).
+/// The strength of a hand, made of the subhands that make up its best 5
+/// cards. Stronger hands compare greater than weaker ones, and hands of
+/// equal strength (split pots) compare equal.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct HandRank(pub(crate) Vec<SubHand>);
+
+impl HandRank {
+    /// The hand's category, like a flush or two pair.
+    pub fn rank(&self) -> Rank {
+        self.0
+            .first()
+            .map_or(Rank::HighCard, |subhand| subhand.rank)
+    }
+
+    /// The subhands that make up the hand, strongest first.
+    pub fn subhands(&self) -> &[SubHand] {
+        &self.0
+    }
+}
+
+impl HandRank {
+    /// Describe the hand the way players would say it, like "two pair,
+    /// kings and nines".
+    pub fn description(&self) -> String {
+        let Some(best) = self.0.first() else {
+            return "nothing".to_string();
+        };
+        let value = |idx: usize| best.values.get(idx).copied().unwrap_or_default();
+        match best.rank {
+            Rank::HighCard => format!("{} high", value_name(value(0))),
+            Rank::OnePair => format!("a pair of {}", value_plural(value(0))),
+            Rank::TwoPair => format!(
+                "two pair, {} and {}",
+                value_plural(value(0)),
+                value_plural(value(2))
+            ),
+            Rank::ThreeOfAKind => format!("three {}", value_plural(value(0))),
+            Rank::Straight => format!("a straight, {} high", value_name(value(0))),
+            Rank::Flush => format!("a flush, {} high", value_name(value(0))),
+            Rank::FullHouse => format!(
+                "a full house, {} full of {}",
+                value_plural(value(0)),
+                value_plural(value(3))
+            ),
+            Rank::FourOfAKind => format!("four {}", value_plural(value(0))),
+            Rank::StraightFlush if value(0) == 14 => "a royal flush".to_string(),
+            Rank::StraightFlush => format!("a straight flush, {} high", value_name(value(0))),
+        }
+    }
+}
+
+fn value_name(value: Value) -> &'static str {
+    match value {
+        1 | 14 => "ace",
+        2 => "two",
+        3 => "three",
+        4 => "four",
+        5 => "five",
+        6 => "six",
+        7 => "seven",
+        8 => "eight",
+        9 => "nine",
+        10 => "ten",
+        11 => "jack",
+        12 => "queen",
+        13 => "king",
+        _ => "joker",
+    }
+}
+
+fn value_plural(value: Value) -> String {
+    match value {
+        6 => "sixes".to_string(),
+        value => format!("{}s", value_name(value)),
+    }
+}
+
+impl fmt::Display for HandRank {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let repr = self
+            .0
+            .iter()
+            .map(|subhand| subhand.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(f, "{repr}")
+    }
+}
+
+/// Type alias for money in cents. All bets and player stacks are fixed-point
+/// amounts with two decimal places so micro stakes like $0.05/$0.10 blinds
+/// can be played. Use [`Dollars`] to show or parse amounts.
 ///
-/// If the total money in a game ever surpasses ~4.2 billion, then we may
+/// If the total money in a game ever surpasses ~$42 million, then we may
 /// have a problem.
 pub type Usd = u32;
-/// Type alias for decimal dollars. Only used to represent the remainder of
-/// whole dollars in the cases where whole dollars can't be distributed evenly
-/// amongst users.
+/// Type alias for decimal cents. Only used to represent the remainder of
+/// cents in the cases where cents can't be distributed evenly amongst users.
 pub type Usdf = f32;
 
-/// Type alias for poker user usernames.
-pub type Username = String;
+pub const CENTS_PER_USD: Usd = 100;
+
+/// Convert an amount saved back when money was counted in whole dollars.
+pub(crate) fn dollars_to_cents(dollars: Usd) -> Usd {
+    dollars.saturating_mul(CENTS_PER_USD)
+}
+
+/// An amount of money shown as dollars, like `$0.05` or `$200`. Cents are
+/// only shown for amounts that aren't whole dollars.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Dollars(pub Usd);
+
+impl fmt::Display for Dollars {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        ChipDisplay::Usd.chips(self.0).fmt(f)
+    }
+}
+
+/// How a table shows amounts of money. Amounts are counted the same way
+/// no matter how they're shown, so `200` is a $200 buy-in at a cash game
+/// and a 200 chip starting stack at a home game.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum ChipDisplay {
+    /// Plain chip counts, like `1500`, for tournaments and home games
+    /// that aren't played for money.
+    Chips,
+    /// Play money, like `P$200`.
+    PlayMoney,
+    /// Dollars, like `$0.05` or `$200`.
+    #[default]
+    Usd,
+}
+
+impl fmt::Display for ChipDisplay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let repr = match self {
+            ChipDisplay::Chips => "chips",
+            ChipDisplay::PlayMoney => "play money",
+            ChipDisplay::Usd => "USD",
+        };
+        write!(f, "{repr}")
+    }
+}
+
+impl ChipDisplay {
+    /// Pair an amount with this display so it can be shown.
+    pub fn chips(self, amount: Usd) -> Chips {
+        Chips {
+            amount,
+            display: self,
+        }
+    }
+}
+
+/// An amount of money along with how its table shows it. The fraction is
+/// only shown for amounts that aren't whole.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Chips {
+    pub amount: Usd,
+    pub display: ChipDisplay,
+}
+
+impl fmt::Display for Chips {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (whole, cents) = (self.amount / CENTS_PER_USD, self.amount % CENTS_PER_USD);
+        let amount = if cents == 0 {
+            whole.to_string()
+        } else {
+            format!("{whole}.{cents:02}")
+        };
+        let repr = match self.display {
+            ChipDisplay::Chips => amount,
+            ChipDisplay::PlayMoney => format!("P${amount}"),
+            ChipDisplay::Usd => format!("${amount}"),
+        };
+        // Padding so amounts line up in tables.
+        f.pad(&repr)
+    }
+}
+
+/// Why an amount of money couldn't be parsed.
+#[derive(Clone, Debug, Deserialize, Eq, thiserror::Error, PartialEq, Serialize)]
+pub enum ParseUsdError {
+    #[error("{0:?} isn't an amount of money")]
+    Invalid(String),
+    #[error("{0:?} has more than 2 decimal places")]
+    TooPrecise(String),
+    #[error("{0:?} is too much money")]
+    TooLarge(String),
+}
+
+impl FromStr for Dollars {
+    type Err = ParseUsdError;
+
+    /// Parse dollars with up to 2 decimal places, like `5`, `0.05`, or
+    /// `$1.5`, into cents.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let amount = s.trim();
+        let amount = amount.strip_prefix('$').unwrap_or(amount);
+        let (dollars, cents) = amount.split_once('.').unwrap_or((amount, ""));
+        let is_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+        if (dollars.is_empty() && cents.is_empty()) || !is_digits(dollars) || !is_digits(cents) {
+            return Err(ParseUsdError::Invalid(s.to_string()));
+        } else if cents.len() > 2 {
+            return Err(ParseUsdError::TooPrecise(s.to_string()));
+        }
+        let too_large = || ParseUsdError::TooLarge(s.to_string());
+        let dollars: Usd = match dollars {
+            "" => 0,
+            dollars => dollars.parse().map_err(|_| too_large())?,
+        };
+        let cents: Usd = format!("{cents:0<2}").parse().expect("cents are digits");
+        dollars
+            .checked_mul(CENTS_PER_USD)
+            .and_then(|amount| amount.checked_add(cents))
+            .map(Dollars)
+            .ok_or_else(too_large)
+    }
+}
+
+/// Why a username can't be used.
+#[derive(Clone, Debug, Deserialize, Eq, thiserror::Error, PartialEq, Serialize)]
+pub enum UsernameError {
+    #[error("usernames can't be empty")]
+    Empty,
+    #[error("usernames can't contain {0:?}")]
+    InvalidChar(char),
+    #[error("usernames can't be longer than {max} characters")]
+    TooLong { max: usize },
+}
+
+/// A poker user's name.
+///
+/// Names are checked with [`Username::validate`] when users connect.
+/// Valid names never contain whitespace or control characters, so they
+/// never need quoting in commands that are split on spaces, and they
+/// can't mess up displays. Names aren't checked anywhere else so names
+/// saved before these rules existed can still be loaded.
+#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(transparent)]
+pub struct Username(String);
+
+impl Username {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Check a name before using it.
+    pub fn new(name: impl Into<String>) -> Result<Self, UsernameError> {
+        let username = Self(name.into());
+        username.validate()?;
+        Ok(username)
+    }
+
+    pub fn validate(&self) -> Result<(), UsernameError> {
+        if self.0.is_empty() {
+            return Err(UsernameError::Empty);
+        } else if self.0.chars().count() > constants::MAX_USER_INPUT_LENGTH {
+            return Err(UsernameError::TooLong {
+                max: constants::MAX_USER_INPUT_LENGTH,
+            });
+        }
+        match self.0.chars().find(|c| c.is_whitespace() || c.is_control()) {
+            Some(c) => Err(UsernameError::InvalidChar(c)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl AsRef<str> for Username {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Username {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for Username {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl fmt::Display for Username {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Unchecked, for names that are looked up rather than connected with.
+impl From<&str> for Username {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+/// Unchecked, for names that are looked up rather than connected with.
+impl From<String> for Username {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Username> for String {
+    fn from(value: Username) -> Self {
+        value.0
+    }
+}
+
+impl PartialEq<str> for Username {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Username {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialEq<String> for Username {
+    fn eq(&self, other: &String) -> bool {
+        &self.0 == other
+    }
+}
+
+impl PartialEq<Username> for str {
+    fn eq(&self, other: &Username) -> bool {
+        self == other.0
+    }
+}
+
+impl PartialEq<Username> for &str {
+    fn eq(&self, other: &Username) -> bool {
+        *self == other.0
+    }
+}
 
 // By default, a player will be cleaned if they fold 20 rounds with the big
 // blind.
-pub const DEFAULT_BUY_IN: Usd = 200;
+pub const DEFAULT_BUY_IN: Usd = 200 * CENTS_PER_USD;
 pub const DEFAULT_MIN_BIG_BLIND: Usd = DEFAULT_BUY_IN / 20;
 pub const DEFAULT_MIN_SMALL_BLIND: Usd = DEFAULT_MIN_BIG_BLIND / 2;
 
 #[derive(Clone, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct User {
-    pub name: String,
+    pub name: Username,
     pub money: Usd,
 }
 
+impl User {
+    pub(crate) fn dollars_to_cents(&mut self) {
+        self.money = dollars_to_cents(self.money);
+    }
+}
+
 impl fmt::Display for User {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let money = format!("${}", self.money);
+        let money = Dollars(self.money).to_string();
         write!(f, "{:16} {:>5}", self.name, money)
     }
 }
@@ -152,14 +558,7 @@ pub enum Action {
 
 impl fmt::Display for Action {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let repr = match self {
-            Action::AllIn => "all-in",
-            Action::Call(amount) => &format!("call ${amount}"),
-            Action::Check => "check",
-            Action::Fold => "fold",
-            Action::Raise(amount) => &format!("raise ${amount}"),
-        };
-        write!(f, "{repr}")
+        write!(f, "{}", self.to_chips_string(ChipDisplay::Usd))
     }
 }
 
@@ -174,20 +573,38 @@ impl From<Bet> for Action {
 }
 
 impl Action {
-    pub fn to_action_string(&self) -> String {
+    pub(crate) fn dollars_to_cents(&mut self) {
+        if let Action::Call(amount) | Action::Raise(amount) = self {
+            *amount = dollars_to_cents(*amount);
+        }
+    }
+
+    /// Same as the action's display, but with amounts shown the way the
+    /// table shows them.
+    pub fn to_chips_string(&self, chip_display: ChipDisplay) -> String {
+        match self {
+            Action::AllIn => "all-in".to_string(),
+            Action::Call(amount) => format!("call {}", chip_display.chips(*amount)),
+            Action::Check => "check".to_string(),
+            Action::Fold => "fold".to_string(),
+            Action::Raise(amount) => format!("raise {}", chip_display.chips(*amount)),
+        }
+    }
+
+    pub fn to_action_string(&self, chip_display: ChipDisplay) -> String {
         match self {
             Action::AllIn => format!("{self}s (unhinged)"),
             Action::Check | Action::Fold => format!("{self}s"),
-            Action::Call(amount) => format!("calls ${amount}"),
-            Action::Raise(amount) => format!("raises ${amount}"),
+            Action::Call(amount) => format!("calls {}", chip_display.chips(*amount)),
+            Action::Raise(amount) => format!("raises {}", chip_display.chips(*amount)),
         }
     }
 
-    pub fn to_option_string(&self) -> String {
+    pub fn to_option_string(&self, chip_display: ChipDisplay) -> String {
         match self {
             Action::AllIn | Action::Check | Action::Fold => self.to_string(),
-            Action::Call(amount) => format!("call (== ${})", amount),
-            Action::Raise(amount) => format!("raise (>= ${})", amount),
+            Action::Call(amount) => format!("call (== {})", chip_display.chips(*amount)),
+            Action::Raise(amount) => format!("raise (>= {})", chip_display.chips(*amount)),
         }
     }
 }
@@ -226,11 +643,11 @@ pub struct Bet {
 
 impl fmt::Display for Bet {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let amount = self.amount;
+        let amount = Dollars(self.amount);
         let repr = match self.action {
-            BetAction::AllIn => format!("all-in of ${amount}"),
-            BetAction::Call => format!("call of ${amount}"),
-            BetAction::Raise => format!("raise of ${amount}"),
+            BetAction::AllIn => format!("all-in of {amount}"),
+            BetAction::Call => format!("call of {amount}"),
+            BetAction::Raise => format!("raise of {amount}"),
         };
         write!(f, "{repr}")
     }
@@ -247,6 +664,8 @@ pub enum PlayerState {
     Fold,
     // Player shows their cards at the end of the game.
     Show,
+    // Player keeps their seat but isn't dealt in.
+    SitOut,
 }
 
 impl fmt::Display for PlayerState {
@@ -256,12 +675,13 @@ impl fmt::Display for PlayerState {
             PlayerState::AllIn => "all-in",
             PlayerState::Fold => "folded",
             PlayerState::Show => "showing",
+            PlayerState::SitOut => "away",
         };
         write!(f, "{repr:7}")
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Player {
     pub user: User,
     pub state: PlayerState,
@@ -283,12 +703,20 @@ impl Player {
         self.state = PlayerState::Wait;
         self.cards.clear();
     }
+
+    pub(crate) fn dollars_to_cents(&mut self) {
+        self.user.dollars_to_cents();
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Pot {
     // Map seat indices (players) to their investment in the pot.
     pub investments: HashMap<usize, Usd>,
+    /// Money in the main pot that isn't part of anyone's bet, like a big
+    /// blind ante, so it doesn't count toward the call.
+    #[serde(default)]
+    pub dead: Usd,
 }
 
 impl Default for Pot {
@@ -298,6 +726,13 @@ impl Default for Pot {
 }
 
 impl Pot {
+    pub(crate) fn dollars_to_cents(&mut self) {
+        for investment in self.investments.values_mut() {
+            *investment = dollars_to_cents(*investment);
+        }
+        self.dead = dollars_to_cents(self.dead);
+    }
+
     pub fn bet(&mut self, player_idx: usize, bet: &Bet) {
         let investment = self.investments.entry(player_idx).or_default();
         *investment += bet.amount;
@@ -326,7 +761,7 @@ impl Pot {
     }
 
     pub fn get_size(&self) -> Usd {
-        self.investments.values().sum()
+        self.investments.values().sum::<Usd>() + self.dead
     }
 
     pub fn is_empty(&self) -> bool {
@@ -336,7 +771,117 @@ impl Pot {
     pub fn new(max_players: usize) -> Pot {
         Pot {
             investments: HashMap::with_capacity(max_players),
+            dead: 0,
+        }
+    }
+
+    /// Split the pot into the main pot followed by side pots. Players can
+    /// only win as much from each other player as they put in themselves,
+    /// so every player all-in for less than the others caps a pot.
+    /// `contesting` holds the seats of players that haven't folded, and
+    /// `all_in` the seats of players that can't put in any more. Players
+    /// that can still bet are eligible for every pot. Dead money goes to
+    /// the main pot.
+    pub fn side_pots(
+        &self,
+        contesting: &BTreeSet<usize>,
+        all_in: &BTreeSet<usize>,
+    ) -> Vec<SidePot> {
+        if self.is_empty() {
+            return vec![];
+        }
+        let top = contesting
+            .iter()
+            .map(|player_idx| self.get_investment_by_player_idx(*player_idx))
+            .max()
+            .unwrap_or_default();
+        let mut levels: Vec<Usd> = all_in
+            .intersection(contesting)
+            .map(|player_idx| self.get_investment_by_player_idx(*player_idx))
+            .filter(|level| *level > 0 && *level < top)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        levels.push(top);
+
+        let mut side_pots = Vec::with_capacity(levels.len());
+        let mut prev_level = 0;
+        for (level_idx, level) in levels.iter().enumerate() {
+            // Anything past the last level, like money from players that
+            // folded after betting more than everyone else, goes to the
+            // last pot.
+            let is_last = level_idx == levels.len() - 1;
+            let size = self
+                .investments
+                .values()
+                .map(|investment| {
+                    let upper = if is_last {
+                        *investment
+                    } else {
+                        min(*investment, *level)
+                    };
+                    upper - min(*investment, prev_level)
+                })
+                .sum();
+            let seats = contesting
+                .iter()
+                .filter(|player_idx| {
+                    !all_in.contains(player_idx)
+                        || self.get_investment_by_player_idx(**player_idx) >= *level
+                })
+                .copied()
+                .collect();
+            side_pots.push(SidePot {
+                call: *level,
+                size,
+                seats,
+            });
+            prev_level = *level;
         }
+        side_pots[0].size += self.dead;
+        side_pots
+    }
+}
+
+/// A main pot or side pot and the players that can win it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SidePot {
+    /// The investment players need to have made to be in the pot.
+    pub call: Usd,
+    pub size: Usd,
+    /// Seats of the players eligible for the pot.
+    pub seats: Vec<usize>,
+}
+
+/// Where a player sits relative to the button, which decides when they
+/// act.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Position {
+    Button,
+    SmallBlind,
+    BigBlind,
+    /// First to act before the flop, followed by how many seats after
+    /// it, so `UnderTheGun(1)` is UTG+1.
+    UnderTheGun(usize),
+    Lojack,
+    Hijack,
+    Cutoff,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let repr = match self {
+            Position::Button => "BTN",
+            Position::SmallBlind => "SB",
+            Position::BigBlind => "BB",
+            Position::UnderTheGun(0) => "UTG",
+            Position::UnderTheGun(offset) => &format!("UTG+{offset}"),
+            Position::Lojack => "LJ",
+            Position::Hijack => "HJ",
+            Position::Cutoff => "CO",
+        };
+        // Padding so positions line up in tables.
+        f.pad(repr)
     }
 }
 
@@ -345,16 +890,118 @@ pub struct PlayerView {
     pub user: User,
     pub state: PlayerState,
     pub cards: Vec<Card>,
+    /// Money the player has put in the pot this hand.
+    pub investment: Usd,
+    #[serde(default)]
+    pub seat_idx: usize,
+    /// Nothing if the player isn't dealt in.
+    #[serde(default)]
+    pub position: Option<Position>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct PotView {
     pub size: Usd,
+    /// The largest investment in the pot, which every player has to
+    /// match to stay in the hand.
+    pub call: Usd,
+    /// The main pot followed by any side pots. See [`Pot::side_pots`].
+    pub side_pots: Vec<SidePotView>,
+    #[serde(default)]
+    pub chip_display: ChipDisplay,
 }
 
 impl fmt::Display for PotView {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "${}", self.size)
+        write!(f, "{}", self.chip_display.chips(self.size))?;
+        if self.side_pots.len() > 1 {
+            let side_pots = self
+                .side_pots
+                .iter()
+                .map(|side_pot| self.chip_display.chips(side_pot.size).to_string())
+                .collect::<Vec<_>>()
+                .join(" + ");
+            write!(f, " ({side_pots})")?;
+        }
+        Ok(())
+    }
+}
+
+/// A main pot or side pot as seen by users.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SidePotView {
+    pub size: Usd,
+    /// Players that can win the pot.
+    pub eligible: Vec<Username>,
+}
+
+/// Tournament progress as seen by users.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TournamentView {
+    /// Current blind level, starting from 1.
+    pub level: usize,
+    /// Time remaining until the blinds increase. Nothing if the tournament
+    /// hasn't started yet.
+    pub time_to_next_level: Option<Duration>,
+    /// Username of the last tournament's winner.
+    pub winner: Option<Username>,
+    /// Bounty on each player's head in a knockout tournament, paid to
+    /// whoever knocks them out. Zero if there are no bounties.
+    #[serde(default)]
+    pub bounty: Usd,
+    /// Bounties each player has collected this tournament, or in the last
+    /// one if it's over. The winner collects their own bounty too.
+    #[serde(default)]
+    pub bounties_won: BTreeMap<Username, Usd>,
+    /// Whether players can still register late or re-enter.
+    #[serde(default)]
+    pub is_registration_open: bool,
+    /// Number of buy-ins, counting re-entries.
+    #[serde(default)]
+    pub entries: usize,
+    /// Money paid to enter the tournament, counting re-entries.
+    #[serde(default)]
+    pub prize_pool: Usd,
+    /// Whether the tournament's tables are playing hand-for-hand on the
+    /// money bubble.
+    #[serde(default)]
+    pub is_hand_for_hand: bool,
+}
+
+impl TournamentView {
+    /// Who collected how much in bounties, most first.
+    pub fn bounties_to_string(&self) -> String {
+        let mut bounties_won: Vec<_> = self.bounties_won.iter().collect();
+        bounties_won.sort_by(|(_, a), (_, b)| b.cmp(a));
+        bounties_won
+            .into_iter()
+            .map(|(username, bounty)| format!("{username} collected {}", Dollars(*bounty)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl fmt::Display for TournamentView {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.time_to_next_level {
+            Some(remaining) => {
+                let level = self.level;
+                let secs = remaining.as_secs();
+                let (mins, secs) = (secs / 60, secs % 60);
+                write!(f, "level {level} ({mins}:{secs:02} left)")
+            }
+            None => write!(f, "level {} (not started)", self.level),
+        }?;
+        if self.is_registration_open {
+            write!(f, ", late registration open")?;
+        }
+        if self.is_hand_for_hand {
+            write!(f, ", hand-for-hand")?;
+        }
+        if self.bounty > 0 {
+            write!(f, ", {} bounties", Dollars(self.bounty))?;
+        }
+        Ok(())
     }
 }
 
@@ -363,15 +1010,582 @@ pub struct GameView {
     pub donations: Usdf,
     pub small_blind: Usd,
     pub big_blind: Usd,
-    pub spectators: HashMap<String, User>,
+    pub ante: Usd,
+    /// Whether the big blind posts the ante for the whole table instead
+    /// of every player posting their own.
+    #[serde(default)]
+    pub is_big_blind_ante: bool,
+    /// The ante every player posted if the hand is a bomb pot.
+    #[serde(default)]
+    pub bomb_pot: Option<Usd>,
+    pub spectators: HashMap<Username, User>,
     pub waitlist: VecDeque<User>,
     pub open_seats: VecDeque<usize>,
     pub players: Vec<PlayerView>,
     pub board: Vec<Card>,
+    /// Alternate board when the players in an all-in pot agreed to run
+    /// it twice. Empty otherwise.
+    pub second_board: Vec<Card>,
     pub pot: PotView,
     pub small_blind_idx: usize,
     pub big_blind_idx: usize,
+    /// Whether no one posts the small blind this hand. The small blind
+    /// index is the button's then.
+    pub is_small_blind_dead: bool,
     pub next_action_idx: Option<usize>,
+    /// The player whose turn it is, so spectators can tell who the table
+    /// is waiting on.
+    #[serde(default)]
+    pub action_on: Option<Username>,
+    /// Index of the player with the button. Nothing if there aren't
+    /// enough players dealt in for a hand.
+    #[serde(default)]
+    pub button_idx: Option<usize>,
+    /// Actions taken so far this hand, in order.
+    #[serde(default)]
+    pub actions: Vec<HandAction>,
+    pub tournament: Option<TournamentView>,
+    /// Seats that waitlisters asked to play in. Waitlisters are seated in
+    /// the order they joined the waitlist, waiting for their seat if they
+    /// asked for one.
+    pub seat_requests: HashMap<Username, usize>,
+    /// The user's place in the waitlist, starting from 1. Nothing if the
+    /// user isn't waitlisted.
+    pub waitlist_position: Option<usize>,
+    /// Money in the bad beat jackpot. Nothing if the table doesn't have
+    /// a jackpot.
+    pub jackpot: Option<Usd>,
+    /// How long the player whose turn it is has to act. Nothing if no
+    /// one's turn is being timed.
+    pub turn_timer: Option<TurnTimerView>,
+    /// How the table shows amounts.
+    #[serde(default)]
+    pub chip_display: ChipDisplay,
+}
+
+/// A turn's time limit as seen by users. Games don't time turns
+/// themselves, so whatever runs the game fills this in.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TurnTimerView {
+    /// How long turns last.
+    pub timeout: Duration,
+    /// Time left in the turn when the view was made.
+    pub remaining: Duration,
+}
+
+impl GameView {
+    /// The user's latest action on the street being bet on, if they've
+    /// acted on it yet.
+    pub fn last_action_this_street(&self, username: &str) -> Option<&Action> {
+        let street = Street::from_num_community_cards(self.board.len());
+        self.actions
+            .iter()
+            .rev()
+            .take_while(|action| action.street == street)
+            .find(|action| action.username == username)
+            .map(|action| &action.action)
+    }
+
+    /// Amount the player has to bet to stay in the hand.
+    pub fn call_amount(&self, player_idx: usize) -> Usd {
+        self.pot
+            .call
+            .saturating_sub(self.players[player_idx].investment)
+    }
+
+    /// Smallest bet that counts as a raise for the player, which doubles
+    /// the largest investment in the pot.
+    pub fn min_raise_amount(&self, player_idx: usize) -> Usd {
+        (2 * self.pot.call).saturating_sub(self.players[player_idx].investment)
+    }
+
+    /// Amount the player has to bet to call and then raise by the
+    /// fraction of the pot after calling, like 1.0 for a pot-sized raise.
+    /// Raises are never smaller than the minimum raise.
+    pub fn pot_raise_amount(&self, player_idx: usize, fraction: Usdf) -> Usd {
+        let call = self.call_amount(player_idx);
+        let raise = (fraction * (self.pot.size + call) as Usdf).round() as Usd;
+        (call + raise).max(self.min_raise_amount(player_idx))
+    }
+
+    /// Amount the player has to bet to raise the largest investment in
+    /// the pot to the multiple of itself, like 3.0 to triple it. Raises
+    /// are never smaller than the minimum raise.
+    pub fn multiple_raise_amount(&self, player_idx: usize, multiple: Usdf) -> Usd {
+        let investment = (multiple * self.pot.call as Usdf).round() as Usd;
+        investment
+            .saturating_sub(self.players[player_idx].investment)
+            .max(self.min_raise_amount(player_idx))
+    }
+
+    /// Equity a call needs to break even, as a fraction of the pot after
+    /// calling. Calling with more equity than this wins money in the long
+    /// run.
+    pub fn minimum_defense_equity(&self, call_amount: Usd) -> Usdf {
+        if call_amount == 0 {
+            return 0.0;
+        }
+        call_amount as Usdf / (self.pot.size as Usdf + call_amount as Usdf)
+    }
+
+    /// Ratio of the pot to the cost of calling, like 3.0 for 3-to-1 odds.
+    /// Nothing if calling is free.
+    pub fn pot_odds(&self, call_amount: Usd) -> Option<Usdf> {
+        if call_amount == 0 {
+            None
+        } else {
+            Some(self.pot.size as Usdf / call_amount as Usdf)
+        }
+    }
+}
+
+pub type GameViews = HashMap<Username, GameView>;
+
+/// The users at a game and their money. Snapshots are only taken in the
+/// lobby when no money is tied up in the pot, and are used to restore
+/// games after server restarts.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct GameSnapshot {
+    pub donations: Usdf,
+    pub spectators: Vec<User>,
+    pub waitlist: Vec<User>,
+    /// Players and the seats they were sitting in.
+    pub players: Vec<(usize, User)>,
+    /// Money in the bad beat jackpot.
+    #[serde(default)]
+    pub jackpot: Usd,
+}
+
+impl GameSnapshot {
+    #[cfg(feature = "net")]
+    pub(crate) fn dollars_to_cents(&mut self) {
+        self.donations *= CENTS_PER_USD as Usdf;
+        for user in self
+            .spectators
+            .iter_mut()
+            .chain(self.waitlist.iter_mut())
+            .chain(self.players.iter_mut().map(|(_, user)| user))
+        {
+            user.dollars_to_cents();
+        }
+        self.jackpot = dollars_to_cents(self.jackpot);
+    }
+}
+
+/// A round of betting within a hand.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Street {
+    Preflop,
+    Flop,
+    Turn,
+    River,
+}
+
+impl fmt::Display for Street {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let repr = match self {
+            Street::Preflop => "preflop",
+            Street::Flop => "flop",
+            Street::Turn => "turn",
+            Street::River => "river",
+        };
+        write!(f, "{repr}")
+    }
+}
+
+impl Street {
+    /// The street being bet on given the number of community cards.
+    pub fn from_num_community_cards(num_community_cards: usize) -> Self {
+        match num_community_cards {
+            0..=2 => Street::Preflop,
+            3 => Street::Flop,
+            4 => Street::Turn,
+            _ => Street::River,
+        }
+    }
+
+    /// The number of community cards out while the street is bet on.
+    pub fn num_community_cards(&self) -> usize {
+        match self {
+            Street::Preflop => 0,
+            Street::Flop => 3,
+            Street::Turn => 4,
+            Street::River => 5,
+        }
+    }
+}
+
+/// An action a player took during a hand.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct HandAction {
+    pub street: Street,
+    pub username: Username,
+    pub action: Action,
+}
+
+/// A pot (or half a pot when running it twice) and who won it.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PotResult {
+    /// Index of the board the pot was played on. Always 0 unless the
+    /// board was run twice.
+    pub board_idx: usize,
+    pub size: Usd,
+    pub winners: Vec<Username>,
+}
+
+/// A hand revealed at showdown and the best hand it made on each board.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ShownHand {
+    pub username: Username,
+    pub cards: Vec<Card>,
+    /// Descriptions of the best hand made on each board, like "a flush,
+    /// ace high". There are two when the board was run twice.
+    pub descriptions: Vec<String>,
+}
+
+/// The hands revealed at the end of a hand and who won which pot.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Showdown {
+    pub hands: Vec<ShownHand>,
+    /// Players that lost at showdown and didn't show their hand.
+    #[serde(default)]
+    pub mucked: Vec<Username>,
+    pub pots: Vec<PotResult>,
+    #[serde(default)]
+    pub chip_display: ChipDisplay,
+}
+
+impl fmt::Display for Showdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let run_twice = self.pots.iter().any(|pot| pot.board_idx > 0);
+        write!(f, "showdown")?;
+        for hand in self.hands.iter() {
+            let cards = hand
+                .cards
+                .iter()
+                .map(|card| card.to_string().trim_start().to_string())
+                .collect::<Vec<_>>()
+                .join(" ");
+            write!(
+                f,
+                "\n{} shows {cards} for {}",
+                hand.username,
+                hand.descriptions.join(" and ")
+            )?;
+        }
+        for username in self.mucked.iter() {
+            write!(f, "\n{username} mucks")?;
+        }
+        for pot in self.pots.iter() {
+            match pot.winners.as_slice() {
+                [winner] => write!(f, "\n{winner} wins {}", self.chip_display.chips(pot.size))?,
+                winners => write!(
+                    f,
+                    "\n{} split {}",
+                    winners.join(", "),
+                    self.chip_display.chips(pot.size)
+                )?,
+            }
+            if run_twice {
+                write!(f, " on board {}", pot.board_idx + 1)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A bad beat jackpot that was hit. Half of it goes to the player whose
+/// hand lost, and the rest is split amongst the players that beat them.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct JackpotPayout {
+    pub size: Usd,
+    pub loser: Username,
+    pub winners: Vec<Username>,
+    #[serde(default)]
+    pub chip_display: ChipDisplay,
+}
+
+impl fmt::Display for JackpotPayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} hit the {} bad beat jackpot against {}",
+            self.loser,
+            self.chip_display.chips(self.size),
+            self.winners.join(", ")
+        )
+    }
+}
+
+/// A player knocked out of a bounty tournament. Their bounty is split
+/// between the players that won the last of their money.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Knockout {
+    pub username: Username,
+    pub eliminators: Vec<Username>,
+    pub bounty: Usd,
+}
+
+impl fmt::Display for Knockout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} knocked out {}, collecting a {} bounty",
+            self.eliminators.join(" and "),
+            self.username,
+            Dollars(self.bounty)
+        )
+    }
+}
+
+/// Where the money went in a single hand. Money put into the pots has to
+/// come back out of them, and stacks have to change by what players won
+/// less what they put in. Anything else means money was made or lost by
+/// mistake, like when a pot isn't split right.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct HandAudit {
+    /// How much each player's stack changed over the hand, not counting
+    /// buy-ins.
+    pub stack_deltas: BTreeMap<Username, i64>,
+    /// Money players put into the pots.
+    pub chips_in: Usd,
+    /// Money players won from the pots.
+    pub awarded: Usd,
+    /// Money from the pots that went to the donations. Odd chips from
+    /// split pots go to players now, but older hand histories have them
+    /// here.
+    pub donated: Usd,
+    /// Money players won from the bad beat jackpot.
+    pub jackpot_won: Usd,
 }
 
-pub type GameViews = HashMap<String, GameView>;
+/// A record of everything that happened during a single hand.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct HandHistory {
+    /// Players dealt into the hand, their seats, and the money they had
+    /// before antes and blinds.
+    pub players: Vec<(usize, User)>,
+    pub small_blind: Usd,
+    pub big_blind: Usd,
+    /// The ante each player posted, or the big blind posted for the
+    /// table with `is_big_blind_ante`. In a bomb pot, it's what everyone
+    /// anted instead of posting blinds.
+    pub ante: Usd,
+    /// Whether the big blind posted the ante for the whole table. They
+    /// post their blind first, so a short stack posts only what's left
+    /// of it.
+    #[serde(default)]
+    pub is_big_blind_ante: bool,
+    /// Whether the hand was a bomb pot, which started on the flop
+    /// without blinds or betting before it.
+    #[serde(default)]
+    pub is_bomb_pot: bool,
+    pub small_blind_username: Username,
+    pub big_blind_username: Username,
+    /// Whether no one posted the small blind because the last big blind
+    /// left. The small blind's username is the button's then.
+    #[serde(default)]
+    pub is_small_blind_dead: bool,
+    /// Actions in the order they were taken.
+    pub actions: Vec<HandAction>,
+    pub board: Vec<Card>,
+    pub second_board: Vec<Card>,
+    /// Hole cards of players that showed their hand.
+    pub shown_hands: Vec<(Username, Vec<Card>)>,
+    /// Players that went to showdown and mucked their hand instead.
+    #[serde(default)]
+    pub mucked_hands: Vec<Username>,
+    pub pots: Vec<PotResult>,
+    /// Money taken from the pots before they were awarded.
+    #[serde(default)]
+    pub rake: Usd,
+    /// Money taken from the pots to fund the bad beat jackpot.
+    #[serde(default)]
+    pub jackpot_drop: Usd,
+    #[serde(default)]
+    pub jackpot_payout: Option<JackpotPayout>,
+    /// Players knocked out of a bounty tournament this hand.
+    #[serde(default)]
+    pub knockouts: Vec<Knockout>,
+    #[serde(default)]
+    pub audit: HandAudit,
+    /// How the table showed amounts when the hand was played.
+    #[serde(default)]
+    pub chip_display: ChipDisplay,
+}
+
+impl HandHistory {
+    pub(crate) fn dollars_to_cents(&mut self) {
+        for (_, user) in self.players.iter_mut() {
+            user.dollars_to_cents();
+        }
+        self.small_blind = dollars_to_cents(self.small_blind);
+        self.big_blind = dollars_to_cents(self.big_blind);
+        self.ante = dollars_to_cents(self.ante);
+        for action in self.actions.iter_mut() {
+            action.action.dollars_to_cents();
+        }
+        for pot in self.pots.iter_mut() {
+            pot.size = dollars_to_cents(pot.size);
+        }
+        self.rake = dollars_to_cents(self.rake);
+        self.jackpot_drop = dollars_to_cents(self.jackpot_drop);
+        if let Some(ref mut payout) = self.jackpot_payout {
+            payout.size = dollars_to_cents(payout.size);
+        }
+        for knockout in self.knockouts.iter_mut() {
+            knockout.bounty = dollars_to_cents(knockout.bounty);
+        }
+        let audit = &mut self.audit;
+        for delta in audit.stack_deltas.values_mut() {
+            *delta = delta.saturating_mul(CENTS_PER_USD.into());
+        }
+        audit.chips_in = dollars_to_cents(audit.chips_in);
+        audit.awarded = dollars_to_cents(audit.awarded);
+        audit.donated = dollars_to_cents(audit.donated);
+        audit.jackpot_won = dollars_to_cents(audit.jackpot_won);
+    }
+
+    /// The hands shown at the end of the hand and who won which pot, if
+    /// the hand went to showdown.
+    pub fn showdown(&self) -> Option<Showdown> {
+        if self.shown_hands.is_empty() {
+            return None;
+        }
+        let boards: Vec<_> = [&self.board, &self.second_board]
+            .into_iter()
+            .filter(|board| !board.is_empty())
+            .collect();
+        let hands = self
+            .shown_hands
+            .iter()
+            .map(|(username, cards)| {
+                let descriptions = boards
+                    .iter()
+                    .map(|board| {
+                        let mut cards = cards.clone();
+                        cards.extend(board.iter());
+                        functional::evaluate(&cards).description()
+                    })
+                    .collect();
+                ShownHand {
+                    username: username.clone(),
+                    cards: cards.clone(),
+                    descriptions,
+                }
+            })
+            .collect();
+        Some(Showdown {
+            hands,
+            mucked: self.mucked_hands.clone(),
+            pots: self.pots.clone(),
+            chip_display: self.chip_display,
+        })
+    }
+
+    /// Return whether all the money in the hand is accounted for. See
+    /// [`HandAudit`].
+    pub fn is_balanced(&self) -> bool {
+        let audit = &self.audit;
+        let chips_out = audit.awarded as i64
+            + audit.donated as i64
+            + self.rake as i64
+            + self.jackpot_drop as i64;
+        let net_change: i64 = audit.stack_deltas.values().sum();
+        audit.chips_in as i64 == chips_out
+            && net_change == audit.awarded as i64 + audit.jackpot_won as i64 - audit.chips_in as i64
+    }
+}
+
+impl fmt::Display for HandHistory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cards_to_string = |cards: &[Card]| {
+            cards
+                .iter()
+                .map(|card| card.to_string().trim_start().to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        let chips = |amount| self.chip_display.chips(amount);
+        if self.is_bomb_pot {
+            write!(f, "bomb pot, ante: {}", chips(self.ante))?;
+        } else {
+            write!(
+                f,
+                "blinds: {}/{}",
+                chips(self.big_blind),
+                chips(self.small_blind)
+            )?;
+            if self.ante > 0 && self.is_big_blind_ante {
+                write!(f, ", big blind ante: {}", chips(self.ante))?;
+            } else if self.ante > 0 {
+                write!(f, ", ante: {}", chips(self.ante))?;
+            }
+        }
+        writeln!(f)?;
+        for (seat_idx, user) in self.players.iter() {
+            let money = chips(user.money).to_string();
+            writeln!(f, "  seat {seat_idx}: {:16} {money:>5}", user.name)?;
+        }
+        if self.is_bomb_pot {
+            writeln!(f, "  everyone anted {}", chips(self.ante))?;
+        } else if self.is_small_blind_dead {
+            writeln!(
+                f,
+                "  the small blind was dead, {} posted the big blind",
+                self.big_blind_username
+            )?;
+        } else {
+            writeln!(
+                f,
+                "  {} posted the small blind, {} posted the big blind",
+                self.small_blind_username, self.big_blind_username
+            )?;
+        }
+        let mut street = None;
+        for action in self.actions.iter() {
+            if street != Some(action.street) {
+                street = Some(action.street);
+                writeln!(f, "  {}:", action.street)?;
+            }
+            writeln!(
+                f,
+                "    {} {}",
+                action.username,
+                action.action.to_action_string(self.chip_display)
+            )?;
+        }
+        if !self.board.is_empty() {
+            writeln!(f, "  board: {}", cards_to_string(&self.board))?;
+        }
+        if !self.second_board.is_empty() {
+            writeln!(f, "  second board: {}", cards_to_string(&self.second_board))?;
+        }
+        for (username, cards) in self.shown_hands.iter() {
+            writeln!(f, "  {username} showed {}", cards_to_string(cards))?;
+        }
+        for username in self.mucked_hands.iter() {
+            writeln!(f, "  {username} mucked")?;
+        }
+        for pot in self.pots.iter() {
+            write!(f, "  {} won by {}", chips(pot.size), pot.winners.join(", "))?;
+            if !self.second_board.is_empty() {
+                write!(f, " on board {}", pot.board_idx + 1)?;
+            }
+            writeln!(f)?;
+        }
+        if self.rake > 0 {
+            writeln!(f, "  rake: {}", chips(self.rake))?;
+        }
+        if self.jackpot_drop > 0 {
+            writeln!(f, "  jackpot drop: {}", chips(self.jackpot_drop))?;
+        }
+        if let Some(ref payout) = self.jackpot_payout {
+            writeln!(f, "  {payout}")?;
+        }
+        for knockout in self.knockouts.iter() {
+            writeln!(f, "  {knockout}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,297 @@
+//! Hand equity calculations.
+//!
+//! Equities are the chances each hand has of winning or tying once the
+//! rest of the board is dealt. They're found either by dealing every
+//! possible runout ([`exhaustive`]) or by dealing random runouts
+//! ([`monte_carlo`]). Runouts are ranked with the lookup evaluator in
+//! [`super::lookup`], which is what makes dealing millions of them
+//! practical.
+
+use rand::{seq::SliceRandom, Rng};
+use thiserror::Error;
+
+use super::{
+    entities::Card,
+    functional::new_deck,
+    lookup::{CardSet, Strength},
+};
+
+/// Number of community cards in a complete board.
+pub const BOARD_SIZE: usize = 5;
+
+#[derive(Debug, Eq, Error, PartialEq)]
+pub enum EquityError {
+    #[error("card {} is used more than once", .0.to_string().trim())]
+    DuplicateCard(Card),
+    #[error("card {} isn't in a standard deck", .0.to_string().trim())]
+    InvalidCard(Card),
+    #[error("not enough cards left in the deck to finish the board")]
+    NotEnoughCards,
+    #[error("need at least 2 hands to compare")]
+    NotEnoughHands,
+    #[error("board can't have more than {BOARD_SIZE} cards")]
+    TooManyBoardCards,
+}
+
+/// How often a hand wins or ties, as fractions of runouts.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Equity {
+    pub win: f64,
+    pub tie: f64,
+    /// Expected share of the pot, counting ties as split pots.
+    pub share: f64,
+}
+
+/// Running totals of showdown results across runouts.
+struct Tally {
+    hands: Vec<CardSet>,
+    wins: Vec<usize>,
+    ties: Vec<usize>,
+    shares: Vec<f64>,
+    runouts: usize,
+    /// Each hand's strength on the latest runout, kept so showdowns
+    /// don't allocate.
+    strengths: Vec<Strength>,
+}
+
+impl Tally {
+    fn new(hands: &[Vec<Card>]) -> Self {
+        let num_hands = hands.len();
+        Self {
+            hands: hands.iter().map(|hand| CardSet::new(hand)).collect(),
+            wins: vec![0; num_hands],
+            ties: vec![0; num_hands],
+            shares: vec![0.0; num_hands],
+            runouts: 0,
+            strengths: vec![0; num_hands],
+        }
+    }
+
+    /// Compare the hands on a complete board.
+    fn showdown(&mut self, board: CardSet) {
+        for (strength, hand) in self.strengths.iter_mut().zip(self.hands.iter()) {
+            *strength = (*hand | board).strength();
+        }
+        let best = self.strengths.iter().max().copied().unwrap_or_default();
+        let num_winners = self
+            .strengths
+            .iter()
+            .filter(|strength| **strength == best)
+            .count();
+        for (hand_idx, strength) in self.strengths.iter().enumerate() {
+            if *strength != best {
+                continue;
+            } else if num_winners == 1 {
+                self.wins[hand_idx] += 1;
+            } else {
+                self.ties[hand_idx] += 1;
+            }
+            self.shares[hand_idx] += 1.0 / num_winners as f64;
+        }
+        self.runouts += 1;
+    }
+
+    fn into_equities(self) -> Vec<Equity> {
+        let runouts = self.runouts.max(1) as f64;
+        (0..self.wins.len())
+            .map(|hand_idx| Equity {
+                win: self.wins[hand_idx] as f64 / runouts,
+                tie: self.ties[hand_idx] as f64 / runouts,
+                share: self.shares[hand_idx] / runouts,
+            })
+            .collect()
+    }
+}
+
+/// Check the hands and board, returning the cards left in the deck.
+fn remaining_deck(hands: &[Vec<Card>], board: &[Card]) -> Result<Vec<Card>, EquityError> {
+    if hands.len() < 2 {
+        return Err(EquityError::NotEnoughHands);
+    } else if board.len() > BOARD_SIZE {
+        return Err(EquityError::TooManyBoardCards);
+    }
+    let mut deck = Vec::from(new_deck());
+    for card in hands.iter().flatten().chain(board.iter()) {
+        // Aces can be given as high aces, but the deck only has low ones.
+        let card = match card {
+            Card(14, suit) => Card(1, *suit),
+            card => *card,
+        };
+        match deck.iter().position(|c| *c == card) {
+            Some(card_idx) => {
+                deck.swap_remove(card_idx);
+            }
+            None if new_deck().contains(&card) => return Err(EquityError::DuplicateCard(card)),
+            None => return Err(EquityError::InvalidCard(card)),
+        }
+    }
+    if deck.len() < BOARD_SIZE - board.len() {
+        return Err(EquityError::NotEnoughCards);
+    }
+    Ok(deck)
+}
+
+/// Get the equity of each hand by dealing every possible runout of the
+/// board. This is exact, but dealing a whole board takes 1.7 million
+/// runouts for two hands, so [`monte_carlo`] is quicker preflop.
+///
+/// # Examples
+///
+/// ```
+/// use private_poker::{entities::{Card, Suit}, equity::exhaustive};
+///
+/// let hands = [
+///     vec![Card(1, Suit::Spade), Card(1, Suit::Heart)],
+///     vec![Card(13, Suit::Spade), Card(13, Suit::Heart)],
+/// ];
+/// let board = [Card(2, Suit::Club), Card(7, Suit::Diamond), Card(9, Suit::Heart), Card(4, Suit::Spade)];
+/// let equities = exhaustive(&hands, &board).unwrap();
+/// // Only the two kings left in the deck save the second hand.
+/// assert_eq!(equities[1].win, 2.0 / 44.0);
+/// ```
+pub fn exhaustive(hands: &[Vec<Card>], board: &[Card]) -> Result<Vec<Equity>, EquityError> {
+    let deck = remaining_deck(hands, board)?;
+    let num_cards_to_deal = BOARD_SIZE - board.len();
+    let mut tally = Tally::new(hands);
+    let board = CardSet::new(board);
+
+    // Indices into the deck of the cards being dealt, always increasing
+    // so each runout is only dealt once.
+    let mut deck_indices: Vec<usize> = (0..num_cards_to_deal).collect();
+    loop {
+        let mut runout = board;
+        for deck_idx in deck_indices.iter() {
+            runout.insert(deck[*deck_idx]);
+        }
+        tally.showdown(runout);
+
+        // Move to the next combination of deck indices.
+        let Some(idx) = (0..num_cards_to_deal)
+            .rev()
+            .find(|idx| deck_indices[*idx] < deck.len() - num_cards_to_deal + idx)
+        else {
+            break;
+        };
+        deck_indices[idx] += 1;
+        for next_idx in idx + 1..num_cards_to_deal {
+            deck_indices[next_idx] = deck_indices[next_idx - 1] + 1;
+        }
+    }
+    Ok(tally.into_equities())
+}
+
+/// Estimate the equity of each hand by dealing random runouts of the
+/// board.
+///
+/// # Examples
+///
+/// ```
+/// use private_poker::{entities::{Card, Suit}, equity::monte_carlo};
+///
+/// let hands = [
+///     vec![Card(1, Suit::Spade), Card(1, Suit::Heart)],
+///     vec![Card(13, Suit::Spade), Card(13, Suit::Heart)],
+/// ];
+/// let equities = monte_carlo(&hands, &[], 1000, &mut rand::thread_rng()).unwrap();
+/// assert!(equities[0].win > equities[1].win);
+/// ```
+pub fn monte_carlo<R: Rng + ?Sized>(
+    hands: &[Vec<Card>],
+    board: &[Card],
+    num_runouts: usize,
+    rng: &mut R,
+) -> Result<Vec<Equity>, EquityError> {
+    let mut deck = remaining_deck(hands, board)?;
+    let num_cards_to_deal = BOARD_SIZE - board.len();
+    let mut tally = Tally::new(hands);
+    let board = CardSet::new(board);
+    for _ in 0..num_runouts {
+        let (dealt, _) = deck.partial_shuffle(rng, num_cards_to_deal);
+        tally.showdown(board | CardSet::new(dealt));
+    }
+    Ok(tally.into_equities())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::{exhaustive, monte_carlo, EquityError};
+    use crate::game::entities::{Card, Suit};
+
+    fn aces_and_kings() -> [Vec<Card>; 2] {
+        [
+            vec![Card(1, Suit::Spade), Card(1, Suit::Heart)],
+            vec![Card(13, Suit::Spade), Card(13, Suit::Heart)],
+        ]
+    }
+
+    #[test]
+    fn complete_board_has_one_runout() {
+        let hands = aces_and_kings();
+        let board = [
+            Card(2, Suit::Club),
+            Card(7, Suit::Diamond),
+            Card(9, Suit::Heart),
+            Card(13, Suit::Club),
+            Card(4, Suit::Spade),
+        ];
+        let equities = exhaustive(&hands, &board).unwrap();
+        assert_eq!(equities[0].win, 0.0);
+        assert_eq!(equities[1].win, 1.0);
+        assert_eq!(equities[1].share, 1.0);
+    }
+
+    #[test]
+    fn exhaustive_counts_ties() {
+        let hands = [
+            vec![Card(1, Suit::Spade), Card(13, Suit::Heart)],
+            vec![Card(1, Suit::Heart), Card(13, Suit::Spade)],
+        ];
+        let board = [
+            Card(2, Suit::Club),
+            Card(7, Suit::Diamond),
+            Card(9, Suit::Club),
+            Card(10, Suit::Club),
+        ];
+        let equities = exhaustive(&hands, &board).unwrap();
+        // Neither hand can make a flush with the board, so they always tie.
+        assert_eq!(equities[0].tie, 1.0);
+        assert_eq!(equities[0].share, 0.5);
+        assert_eq!(equities[1].share, 0.5);
+    }
+
+    #[test]
+    fn invalid_inputs() {
+        let [aces, _] = aces_and_kings();
+        assert_eq!(
+            exhaustive(std::slice::from_ref(&aces), &[]),
+            Err(EquityError::NotEnoughHands)
+        );
+        assert_eq!(
+            exhaustive(&[aces.clone(), aces.clone()], &[]),
+            Err(EquityError::DuplicateCard(Card(1, Suit::Spade)))
+        );
+        let kings = vec![Card(13, Suit::Spade), Card(13, Suit::Wild)];
+        assert_eq!(
+            exhaustive(&[aces, kings], &[]),
+            Err(EquityError::InvalidCard(Card(13, Suit::Wild)))
+        );
+    }
+
+    #[test]
+    fn monte_carlo_approximates_exhaustive() {
+        let hands = aces_and_kings();
+        let board = [
+            Card(2, Suit::Club),
+            Card(7, Suit::Diamond),
+            Card(9, Suit::Heart),
+        ];
+        let exact = exhaustive(&hands, &board).unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+        let estimate = monte_carlo(&hands, &board, 10_000, &mut rng).unwrap();
+        for (exact, estimate) in exact.iter().zip(estimate.iter()) {
+            assert!((exact.share - estimate.share).abs() < 0.02);
+        }
+    }
+}
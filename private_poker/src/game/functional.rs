@@ -1,9 +1,9 @@
-use std::{
-    cmp::Ordering,
-    collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet},
-};
+use std::cmp::Ordering;
 
-use super::entities::{Card, Rank, SubHand, Suit, Value};
+use super::{
+    entities::{Card, HandRank, Rank, SubHand, Suit},
+    lookup,
+};
 
 /// Get the indices corresponding to the winning hands from an array
 /// of hands that were each created from `eval`.
@@ -41,11 +41,11 @@ pub fn argmax(hands: &[Vec<SubHand>]) -> Vec<usize> {
 
 /// Evaluate any number of cards, returning the best (up to) 5-card hand.
 ///
-/// This function assumes the cards are already sorted in increasing order.
-/// Cards are grouped into hand rankings and insorted into a heap.
-/// The top subhands are created from the heap and compose a hand.
-/// Multiple hands can then be compared, and the winning hand(s)
-/// can be retrieved with the `argmax` function.
+/// Hands are ranked with the lookup tables in [`super::lookup`] and
+/// broken into the subhands that make them up. Multiple hands can then be
+/// compared, and the winning hand(s) can be retrieved with the `argmax`
+/// function. Cards are usually prepared with `prepare_hand` first, but
+/// they don't have to be.
 ///
 /// # Examples
 ///
@@ -57,292 +57,31 @@ pub fn argmax(hands: &[Vec<SubHand>]) -> Vec<usize> {
 /// assert_eq!(subhands[0].rank, Rank::OnePair)
 /// ```
 pub fn eval(cards: &[Card]) -> Vec<SubHand> {
-    // Mapping of suit to (sorted) cards within that suit.
-    // Used for tracking whether there's a flush or straight flush.
-    let mut values_per_suit: HashMap<Suit, Vec<Value>> = HashMap::new();
-
-    // Used for tracking whether there's a straight.
-    let mut straight_count: usize = 0;
-    let mut straight_prev_value: Value = 0;
-
-    // Mapping of rank to each subhand for that rank. Helps track
-    // the highest subhand in each rank.
-    let mut subhands_per_rank: BTreeMap<Rank, BTreeSet<SubHand>> = BTreeMap::new();
-    // Count number of times a card value appears. Helps track one pair,
-    // two pair, etc.
-    let mut value_counts: HashMap<Value, usize> = HashMap::new();
-
-    // Loop through cards in hand assuming the hand is sorted
-    // and that each ace appears in the hand twice (at the low
-    // end with a value of 1 and at the high end with a value
-    // of 14). We push hands into a binary heap so we can
-    // easily get the best hand at the end.
-    let mut hands: BinaryHeap<SubHand> = BinaryHeap::new();
-    for Card(value, suit) in cards.iter() {
-        // Keep a count of cards for each suit. If the suit count
-        // reaches a flush, it's also checked for a straight
-        // for the straight flush potential.
-        let values_in_suit = values_per_suit.entry(*suit).or_default();
-        values_in_suit.push(*value);
-
-        // Since aces appear in the cards twice, we need to make sure
-        // they aren't counted twice for the flush. To get around this,
-        // we just subtract one from the flush count in the case of the
-        // high valued ace.
-        let mut flush_count = values_in_suit.len();
-        if *value == 14 {
-            flush_count -= 1;
-        }
-
-        // A flush was found.
-        if flush_count >= 5 {
-            let maybe_straight_flush_start_idx = values_in_suit.len() - 5;
-            let maybe_straight_flush_cards = &values_in_suit[maybe_straight_flush_start_idx..];
-            let mut is_straight_flush = true;
-            for flush_idx in 0..4 {
-                if (maybe_straight_flush_cards[flush_idx] + 1)
-                    != maybe_straight_flush_cards[flush_idx + 1]
-                {
-                    is_straight_flush = false;
-                    break;
-                }
-            }
-
-            let values = maybe_straight_flush_cards.iter().rev().copied().collect();
-            if is_straight_flush {
-                hands.push(SubHand {
-                    rank: Rank::StraightFlush,
-                    values,
-                })
-            } else {
-                hands.push(SubHand {
-                    rank: Rank::Flush,
-                    values,
-                })
-            }
-        }
-
-        // Keep a count of cards that're in sequential order to check for
-        // a straight. If the same value appears again, we can keep the
-        // straight count the same and don't have to reset.
-        if (straight_prev_value + 1) == *value {
-            straight_count += 1;
-        } else if straight_prev_value == *value {
-        } else {
-            straight_count = 1;
-        }
-
-        // A straight was found.
-        straight_prev_value = *value;
-        if straight_count >= 5 {
-            let straight_subhand = SubHand {
-                rank: Rank::Straight,
-                values: (*value - 4..=*value).rev().collect(),
-            };
-            // We don't need to push the straight into the heap if something
-            // better was already found.
-            let best_subhand = hands.peek();
-            match best_subhand {
-                None => hands.push(straight_subhand),
-                Some(subhand) => {
-                    if *subhand < straight_subhand {
-                        hands.push(straight_subhand);
-                    }
-                }
-            }
-        }
-
-        // Now start checking for hands besides straights and flushes.
-        let value_count = value_counts.entry(*value).or_insert(0);
-        *value_count += 1;
-
-        match *value_count {
-            1 => {
-                let high_card_subhand = SubHand {
-                    rank: Rank::HighCard,
-                    values: vec![*value],
-                };
-                subhands_per_rank
-                    .entry(Rank::HighCard)
-                    .or_default()
-                    .insert(high_card_subhand);
-            }
-
-            2 => {
-                let one_pair_subhand = SubHand {
-                    rank: Rank::OnePair,
-                    values: vec![*value; 2],
-                };
-                let one_pairs = subhands_per_rank.entry(Rank::OnePair).or_default();
-                one_pairs.insert(one_pair_subhand);
-
-                // Check if a pair also occurs, then both pairs
-                // make a two pair.
-                if let Some(next_best_one_pair) = one_pairs.iter().nth_back(1) {
-                    // Ignore the case where a high ace and low ace get counted together
-                    // as a two pair.
-                    if *value != 14 || next_best_one_pair.values != vec![1, 1] {
-                        let mut two_pair_cards = vec![*value; 2];
-                        two_pair_cards.extend(next_best_one_pair.values.clone());
-                        let two_pair_subhand = SubHand {
-                            rank: Rank::TwoPair,
-                            values: two_pair_cards,
-                        };
-                        subhands_per_rank
-                            .entry(Rank::TwoPair)
-                            .or_default()
-                            .insert(two_pair_subhand);
-                    }
-                }
-
-                // Check if a three of a kind also occurs, then the pair
-                // and three of a kind make a full house.
-                if let Some(three_of_a_kinds) = subhands_per_rank.get(&Rank::ThreeOfAKind) {
-                    if let Some(best_three_of_a_kind) = three_of_a_kinds.iter().next() {
-                        // Ignore the case where a high ace and low ace get counted together
-                        // as a full house.
-                        if *value != 14 || best_three_of_a_kind.values != vec![1, 1, 1] {
-                            let mut full_house_cards = best_three_of_a_kind.values.clone();
-                            full_house_cards.extend(vec![*value; 2]);
-                            let full_house_subhand = SubHand {
-                                rank: Rank::FullHouse,
-                                values: full_house_cards,
-                            };
-
-                            subhands_per_rank
-                                .entry(Rank::FullHouse)
-                                .or_default()
-                                .insert(full_house_subhand);
-                        }
-                    }
-                }
-            }
-
-            3 => {
-                let one_pair_subhand = SubHand {
-                    rank: Rank::OnePair,
-                    values: vec![*value; 2],
-                };
-                let three_of_a_kind_subhand = SubHand {
-                    rank: Rank::ThreeOfAKind,
-                    values: vec![*value; 3],
-                };
-                subhands_per_rank
-                    .get_mut(&Rank::OnePair)
-                    .map(|one_pairs| one_pairs.remove(&one_pair_subhand));
-                subhands_per_rank
-                    .entry(Rank::ThreeOfAKind)
-                    .or_default()
-                    .insert(three_of_a_kind_subhand);
-
-                // Check if a pair also occurs, then the three of a kind
-                // and the pair make a full house.
-                if let Some(one_pairs) = subhands_per_rank.get(&Rank::OnePair) {
-                    if let Some(best_one_pair) = one_pairs.iter().next_back() {
-                        // Ignore the case where a high ace and low ace get counted together
-                        // as a full house.
-                        if *value != 14 || best_one_pair.values != vec![1, 1] {
-                            let mut full_house_cards = vec![*value; 3];
-                            full_house_cards.extend(best_one_pair.values.clone());
-                            let full_house_subhand = SubHand {
-                                rank: Rank::FullHouse,
-                                values: full_house_cards,
-                            };
-                            subhands_per_rank
-                                .entry(Rank::FullHouse)
-                                .or_default()
-                                .insert(full_house_subhand);
-                        }
-                    }
-                }
-
-                // Check if another three of a kind occurs, then both three
-                // of a kinds make a full house.
-                if let Some(three_of_a_kinds) = subhands_per_rank.get(&Rank::ThreeOfAKind) {
-                    if let Some(next_best_three_of_a_kind) = three_of_a_kinds.iter().nth_back(1) {
-                        // Ignore the case where a high ace and low ace get counted together
-                        // as a full house.
-                        if *value != 14 || next_best_three_of_a_kind.values != vec![1, 1, 1] {
-                            let mut full_house_cards = vec![*value; 3];
-                            full_house_cards.extend(vec![next_best_three_of_a_kind.values[0]; 2]);
-                            let full_house_subhand = SubHand {
-                                rank: Rank::FullHouse,
-                                values: full_house_cards,
-                            };
-                            subhands_per_rank
-                                .entry(Rank::FullHouse)
-                                .or_default()
-                                .insert(full_house_subhand);
-                        }
-                    }
-                }
-            }
-
-            4 => {
-                let three_of_a_kind_subhand = SubHand {
-                    rank: Rank::ThreeOfAKind,
-                    values: vec![*value; 3],
-                };
-                let four_of_a_kind_subhand = SubHand {
-                    rank: Rank::FourOfAKind,
-                    values: vec![*value; 4],
-                };
-                subhands_per_rank
-                    .get_mut(&Rank::ThreeOfAKind)
-                    .map(|three_of_a_kinds| three_of_a_kinds.remove(&three_of_a_kind_subhand));
-                subhands_per_rank
-                    .entry(Rank::FourOfAKind)
-                    .or_default()
-                    .insert(four_of_a_kind_subhand);
-
-                // You can't get a four of a kind and a straight flush
-                // in the same round for any individual player.
-                break;
-            }
-
-            _ => unreachable!("cheater"),
-        }
-    }
-
-    // Move subhands according to rank to the temporary hands heap.
-    // Can only keep the best subhand for each except for high cards.
-    // There can be up to 5 high cards in the final hand.
-    while let Some((rank, mut subhands)) = subhands_per_rank.pop_last() {
-        if let Some(best_subhand) = hands.peek() {
-            if best_subhand.rank >= Rank::Straight {
-                break;
-            }
-        }
-        if rank == Rank::HighCard {
-            while let Some(best_subhand) = subhands.pop_last() {
-                hands.push(best_subhand);
-            }
-        } else if let Some(best_subhand) = subhands.pop_last() {
-            hands.push(best_subhand);
-        }
-    }
+    lookup::subhands(lookup::strength(cards))
+}
 
-    // Now convert the binary heap to a vector containing the best
-    // hand. Do this by popping from the binary heap until we get
-    // the 5 best cards in our hand to construct the best hand.
-    let mut cards_in_hand: HashSet<Value> = HashSet::with_capacity(5);
-    let mut num_cards: usize = 0;
-    let mut hand: Vec<SubHand> = Vec::with_capacity(5);
-    while let Some(subhand) = hands.pop() {
-        if hand.is_empty()
-            || (subhand.rank == Rank::HighCard && !cards_in_hand.contains(&subhand.values[0]))
-        {
-            num_cards += subhand.values.len();
-            cards_in_hand.extend(subhand.values.clone());
-            hand.push(subhand);
-        }
-        if let Some(best_subhand) = hand.first() {
-            if best_subhand.rank >= Rank::Straight || num_cards >= 5 {
-                break;
-            }
-        }
-    }
-    hand
+/// Rank any number of cards by their best (up to) 5-card hand, as a
+/// `HandRank` that can describe itself. Aces can be given as 1s or 14s.
+///
+/// # Examples
+///
+/// ```
+/// use private_poker::{entities::{Card, Rank, Suit}, evaluate};
+///
+/// let flush = evaluate(&[
+///     Card(2, Suit::Heart),
+///     Card(7, Suit::Heart),
+///     Card(9, Suit::Heart),
+///     Card(12, Suit::Heart),
+///     Card(14, Suit::Heart),
+///     Card(12, Suit::Club),
+/// ]);
+/// let pair = evaluate(&[Card(12, Suit::Spade), Card(12, Suit::Diamond)]);
+/// assert_eq!(flush.rank(), Rank::Flush);
+/// assert!(flush > pair);
+/// ```
+pub fn evaluate(cards: &[Card]) -> HandRank {
+    HandRank(eval(cards))
 }
 
 /// Create a new, unshuffled deck of cards.
@@ -395,7 +134,7 @@ pub fn prepare_hand(cards: &mut Vec<Card>) {
 
 #[cfg(test)]
 mod tests {
-    use super::{argmax, eval};
+    use super::{argmax, eval, evaluate};
     use crate::game::entities::{Card, Rank, SubHand, Suit};
 
     struct TestHand {
@@ -497,7 +236,7 @@ mod tests {
                     values: vec![10]
                 },
                 cards: vec![
-                    Card(1, Suit::Diamond),
+                    Card(2, Suit::Diamond),
                     Card(5, Suit::Heart),
                     Card(6, Suit::Heart),
                     Card(7, Suit::Heart),
@@ -970,4 +709,40 @@ mod tests {
             }, vec![0, 1]
         ),
     }
+
+    #[test]
+    fn evaluate_unprepared_cards() {
+        // Aces given as 14s still make a wheel.
+        let wheel = evaluate(&[
+            Card(14, Suit::Club),
+            Card(2, Suit::Heart),
+            Card(3, Suit::Spade),
+            Card(4, Suit::Diamond),
+            Card(5, Suit::Club),
+            Card(13, Suit::Heart),
+            Card(13, Suit::Club),
+        ]);
+        assert_eq!(wheel.rank(), Rank::Straight);
+        assert_eq!(wheel.subhands()[0].values, vec![5, 4, 3, 2, 1]);
+
+        let trips = evaluate(&[
+            Card(13, Suit::Spade),
+            Card(13, Suit::Heart),
+            Card(13, Suit::Club),
+            Card(2, Suit::Heart),
+            Card(9, Suit::Spade),
+        ]);
+        assert_eq!(trips.rank(), Rank::ThreeOfAKind);
+        assert!(wheel > trips);
+
+        // The same hand in a different order and suits is a split pot.
+        let wheel2 = evaluate(&[
+            Card(5, Suit::Spade),
+            Card(4, Suit::Club),
+            Card(3, Suit::Club),
+            Card(2, Suit::Diamond),
+            Card(1, Suit::Heart),
+        ]);
+        assert_eq!(wheel, wheel2);
+    }
 }
@@ -0,0 +1,441 @@
+//! Checks that a game is in a state the rules allow.
+//!
+//! The engine keeps money, pots, and turns consistent as the game moves
+//! from state to state, and [`check`] confirms it did for any state,
+//! whether it came from the server, a simulation, or a checkpoint.
+//! Applications that embed the engine can run it after every change in
+//! their own tests, the same way the engine's tests do.
+
+use thiserror::Error;
+
+use super::{
+    entities::{GameView, GameViews, PlayerState, Street, Usd, Username},
+    replay::hand_views,
+    GameData, PokerState,
+};
+
+#[derive(Debug, Eq, Error, PartialEq)]
+pub enum InvariantError {
+    #[error("{username} acted after they folded or went all-in")]
+    ActionByInactivePlayer { username: Username },
+    #[error("{username} acted without being dealt in")]
+    ActionByPlayerNotInHand { username: Username },
+    #[error("{username} acted on the {street} after the {last_street}")]
+    ActionOnPastStreet {
+        username: Username,
+        street: Street,
+        last_street: Street,
+    },
+    #[error("players started the hand with {expected} but have {actual}")]
+    ChipsNotConserved { expected: u64, actual: u64 },
+    #[error("{username} has {actual} in the pot, but their actions put in {expected}")]
+    InvestmentMismatch {
+        username: Username,
+        expected: Usd,
+        actual: Usd,
+    },
+    #[error("{viewer} can see {username}'s hole cards")]
+    HoleCardsLeaked {
+        viewer: Username,
+        username: Username,
+    },
+    #[error("player {player_idx} has money in the pot without being dealt in")]
+    InvestmentWithoutPlayer { player_idx: usize },
+    #[error("{size} is in the pot between hands")]
+    PotOutsideHand { size: Usd },
+    #[error("it's {username}'s turn, but they're done acting")]
+    TurnOnInactivePlayer { username: Username },
+    #[error("the last hand's money isn't accounted for")]
+    UnbalancedHand,
+}
+
+/// Check that the game's money, pot, and turns are consistent:
+///
+/// - Chips are conserved. During a hand, the money players have plus
+///   what they've put in the pot, dead money included, less rake and
+///   jackpot drops, is the
+///   money they started the hand with. After the hand, its audit
+///   balances (see [`HandHistory::is_balanced`]).
+/// - The pot only has money in it during a hand, and only from players
+///   dealt into the hand. While players are betting, each player's share
+///   of the pot is what their blinds and actions put in.
+/// - Actions are taken in order. Players only act if they were dealt in
+///   and haven't folded or gone all-in, streets don't go backwards, and
+///   the turn is only ever on players that can act.
+/// - Users' views only have the hole cards they're entitled to: their
+///   own, hands that were shown, and hands turned face up for a
+///   run-out.
+///
+/// [`HandHistory::is_balanced`]: super::entities::HandHistory::is_balanced
+///
+/// # Examples
+///
+/// ```
+/// use private_poker::{invariants, GameSettings, PokerState};
+///
+/// let mut state: PokerState = GameSettings::default().with_seed(0).into();
+/// for username in ["0", "1", "2"] {
+///     state.new_user(username).unwrap();
+///     state.waitlist_user(username).unwrap();
+/// }
+/// // Play into the first betting round.
+/// while state.get_next_action_username().is_none() {
+///     if let PokerState::Lobby(ref mut game) = state {
+///         game.init_start().unwrap();
+///     }
+///     state = state.step();
+///     assert_eq!(invariants::check(&state), Ok(()));
+/// }
+/// ```
+pub fn check(state: &PokerState) -> Result<(), InvariantError> {
+    let data = state.get_data();
+    let views = hand_views(&data.hand_history);
+    check_actions(data, &views)?;
+    check_hole_cards(data, &state.get_views())?;
+    match state {
+        PokerState::Deal(_)
+        | PokerState::TakeAction(_)
+        | PokerState::Flop(_)
+        | PokerState::Turn(_)
+        | PokerState::River(_) => {
+            check_investments(data)?;
+            check_chips(data)?;
+            check_betting(data, &views)?;
+        }
+        PokerState::ShowHands(_) | PokerState::DistributePot(_) => {
+            check_investments(data)?;
+            check_chips(data)?;
+        }
+        PokerState::RemovePlayers(_)
+        | PokerState::DivideDonations(_)
+        | PokerState::UpdateBlinds(_)
+        | PokerState::BootPlayers(_) => {
+            check_empty_pot(data)?;
+            if !data.hand_history.is_balanced() {
+                return Err(InvariantError::UnbalancedHand);
+            }
+        }
+        PokerState::Lobby(_)
+        | PokerState::SeatPlayers(_)
+        | PokerState::MoveButton(_)
+        | PokerState::CollectBlinds(_) => check_empty_pot(data)?,
+    }
+    if let PokerState::TakeAction(ref game) = state {
+        if let Some(player) = game
+            .data
+            .next_action_idx
+            .filter(|_| !game.is_ready_for_next_phase())
+            .and_then(|player_idx| game.data.players.get(player_idx))
+        {
+            if player.state != PlayerState::Wait {
+                return Err(InvariantError::TurnOnInactivePlayer {
+                    username: player.user.name.clone(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Actions in the hand's history are by players that could act, on
+/// streets in order. The views are the hand's views before each action.
+fn check_actions(data: &GameData, views: &[GameView]) -> Result<(), InvariantError> {
+    let hand = &data.hand_history;
+    let mut last_street = Street::Preflop;
+    for (view, hand_action) in views.iter().zip(hand.actions.iter()) {
+        let username = &hand_action.username;
+        let Some(player) = view.players.iter().find(|p| p.user.name == *username) else {
+            return Err(InvariantError::ActionByPlayerNotInHand {
+                username: username.clone(),
+            });
+        };
+        if player.state != PlayerState::Wait {
+            return Err(InvariantError::ActionByInactivePlayer {
+                username: username.clone(),
+            });
+        }
+        if hand_action.street.num_community_cards() < last_street.num_community_cards() {
+            return Err(InvariantError::ActionOnPastStreet {
+                username: username.clone(),
+                street: hand_action.street,
+                last_street,
+            });
+        }
+        last_street = hand_action.street;
+    }
+    Ok(())
+}
+
+/// While players are betting, their share of the pot is what the
+/// hand's blinds and actions say they put in.
+fn check_betting(data: &GameData, views: &[GameView]) -> Result<(), InvariantError> {
+    let Some(view) = views.last() else {
+        return Ok(());
+    };
+    for player_view in view.players.iter() {
+        let actual = data
+            .players
+            .iter()
+            .position(|p| p.user.name == player_view.user.name)
+            .map_or(0, |player_idx| {
+                data.pot.get_investment_by_player_idx(player_idx)
+            });
+        if actual != player_view.investment {
+            return Err(InvariantError::InvestmentMismatch {
+                username: player_view.user.name.clone(),
+                expected: player_view.investment,
+                actual,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Money taken out of the hand's pots went back to players, to the
+/// rake, or to the jackpot, and nothing else changed players' stacks
+/// except jackpot payouts.
+fn check_chips(data: &GameData) -> Result<(), InvariantError> {
+    let hand = &data.hand_history;
+    let expected = hand
+        .players
+        .iter()
+        .map(|(_, user)| user.money as u64)
+        .sum::<u64>()
+        + hand.audit.jackpot_won as u64;
+    let actual = hand
+        .players
+        .iter()
+        .map(|(_, user)| {
+            data.players
+                .iter()
+                .position(|p| p.user.name == user.name)
+                .map_or(0, |player_idx| {
+                    data.players[player_idx].user.money as u64
+                        + data.pot.get_investment_by_player_idx(player_idx) as u64
+                })
+        })
+        .sum::<u64>()
+        + data.pot.dead as u64
+        + hand.rake as u64
+        + hand.jackpot_drop as u64
+        + hand.audit.donated as u64;
+    if expected != actual {
+        return Err(InvariantError::ChipsNotConserved { expected, actual });
+    }
+    Ok(())
+}
+
+/// Users only see their own hole cards and hands that are face up.
+/// Hands are face up once they're shown, or when they're still in the
+/// pot and betting is over for a run-out.
+fn check_hole_cards(data: &GameData, views: &GameViews) -> Result<(), InvariantError> {
+    let is_running_out = data.runout_idx.is_some();
+    for (viewer, view) in views.iter() {
+        for player_view in view.players.iter() {
+            let username = &player_view.user.name;
+            let Some(player) = data.players.iter().find(|p| p.user.name == *username) else {
+                continue;
+            };
+            let is_face_up = player.state == PlayerState::Show
+                || (is_running_out
+                    && !matches!(player.state, PlayerState::Fold | PlayerState::SitOut));
+            if !player_view.cards.is_empty() && username != viewer && !is_face_up {
+                return Err(InvariantError::HoleCardsLeaked {
+                    viewer: viewer.clone(),
+                    username: username.clone(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+fn check_empty_pot(data: &GameData) -> Result<(), InvariantError> {
+    match data.pot.get_size() {
+        0 => Ok(()),
+        size => Err(InvariantError::PotOutsideHand { size }),
+    }
+}
+
+/// Only players dealt into the hand have money in the pot.
+fn check_investments(data: &GameData) -> Result<(), InvariantError> {
+    for (player_idx, investment) in data.pot.investments.iter() {
+        let is_dealt_in = data
+            .players
+            .get(*player_idx)
+            .is_some_and(|player| player.state != PlayerState::SitOut);
+        if *investment > 0 && !is_dealt_in {
+            return Err(InvariantError::InvestmentWithoutPlayer {
+                player_idx: *player_idx,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+
+    use proptest::{collection, prelude::*};
+
+    use super::{check, check_hole_cards, InvariantError};
+    use crate::game::{
+        entities::{Action, Rank},
+        BombPotSettings, GameSettings, JackpotSettings, PokerState, RakeSettings,
+    };
+
+    /// Actions in the order they're picked from by index.
+    const ACTIONS: [Action; 5] = [
+        Action::Fold,
+        Action::Check,
+        Action::Call(0),
+        Action::Raise(0),
+        Action::AllIn,
+    ];
+
+    /// Start a game with the given number of players, an ante, a rake,
+    /// and a jackpot so money leaves and enters stacks in every way it
+    /// can. Odd seeds have the big blind post the ante for the table.
+    fn init_state(seed: u64, num_players: usize) -> PokerState {
+        let settings = GameSettings::default().with_seed(seed);
+        let settings = if seed.is_multiple_of(2) {
+            settings.with_ante(1)
+        } else {
+            let ante = settings.min_big_blind;
+            settings.with_big_blind_ante(ante)
+        };
+        let settings = settings
+            .with_rake(RakeSettings::new(5, Some(10), true))
+            .with_jackpot(JackpotSettings::new(1, Rank::OnePair))
+            .with_bomb_pot(BombPotSettings::new(10, Some(3)));
+        let mut state: PokerState = settings.into();
+        for i in 0..num_players {
+            let username = i.to_string();
+            state.new_user(&username).unwrap();
+            state.waitlist_user(&username).unwrap();
+        }
+        state
+    }
+
+    /// Choices for each turn of a game: which of the available actions
+    /// to take, and how much more than the minimum to raise. Failing
+    /// games shrink to fewer turns with smaller choices.
+    fn choices() -> impl Strategy<Value = Vec<(u8, u16)>> {
+        collection::vec(any::<(u8, u16)>(), 0..256)
+    }
+
+    /// Play the game, taking the chosen actions whenever it's someone's
+    /// turn, and check the game after every change. Some choices have a
+    /// player spectate or leave instead, which takes effect after the
+    /// hand.
+    fn play(seed: u64, num_players: usize, choices: Vec<(u8, u16)>) -> Result<(), TestCaseError> {
+        let mut state = init_state(seed, num_players);
+        let mut choices = choices.into_iter();
+        for _ in 0..1000 {
+            if let PokerState::Lobby(ref mut game) = state {
+                if game.init_start().is_err() {
+                    break;
+                }
+            }
+            match (state.get_next_action_username(), state.get_action_options()) {
+                (Some(username), Some(action_options)) => {
+                    let Some((choice, amount)) = choices.next() else {
+                        break;
+                    };
+                    match choice {
+                        250 => {
+                            let _ = state.spectate_user(&username);
+                        }
+                        251 => {
+                            let _ = state.remove_user(&username);
+                        }
+                        _ => {}
+                    }
+                    let options: Vec<_> = ACTIONS
+                        .iter()
+                        .filter_map(|action| action_options.get(action))
+                        .collect();
+                    let action = match options[choice as usize % options.len()] {
+                        Action::Raise(min_raise) => Action::Raise(min_raise + amount as u32),
+                        action => action.clone(),
+                    };
+                    state.take_action(&username, action).unwrap();
+                }
+                _ => state = mem::take(&mut state).step(),
+            }
+            if let Err(error) = check(&state) {
+                return Err(TestCaseError::fail(format!("{error} while {state}")));
+            }
+        }
+        Ok(())
+    }
+
+    proptest! {
+        #[test]
+        fn hold_for_any_actions(seed: u64, num_players in 2..7usize, choices in choices()) {
+            play(seed, num_players, choices)?;
+        }
+    }
+
+    #[test]
+    fn catch_broken_games() {
+        // Start a hand and get to the first turn.
+        let mut state = init_state(0, 3);
+        while state.get_next_action_username().is_none() {
+            if let PokerState::Lobby(ref mut game) = state {
+                game.init_start().unwrap();
+            }
+            state = state.step();
+        }
+        assert_eq!(check(&state), Ok(()));
+
+        // Money can't appear out of nowhere.
+        let mut broken = state.clone();
+        if let PokerState::TakeAction(ref mut game) = broken {
+            game.data.players[0].user.money += 1;
+        }
+        assert!(matches!(
+            check(&broken),
+            Err(InvariantError::ChipsNotConserved { .. })
+        ));
+
+        // Or move from a player's stack into the pot without an action.
+        let mut broken = state.clone();
+        if let PokerState::TakeAction(ref mut game) = broken {
+            game.data.players[0].user.money -= 1;
+            *game.data.pot.investments.entry(0).or_default() += 1;
+        }
+        assert!(matches!(
+            check(&broken),
+            Err(InvariantError::InvestmentMismatch { .. })
+        ));
+
+        // Or let users see their opponents' hands.
+        let mut views = state.get_views();
+        assert_eq!(check_hole_cards(state.get_data(), &views), Ok(()));
+        let cards = state.get_data().players[1].cards.clone();
+        views.get_mut("0").unwrap().players[1].cards = cards;
+        assert_eq!(
+            check_hole_cards(state.get_data(), &views),
+            Err(InvariantError::HoleCardsLeaked {
+                viewer: "0".into(),
+                username: "1".into(),
+            })
+        );
+
+        // Players that folded are done acting.
+        let username = state.get_next_action_username().unwrap();
+        state.take_action(&username, Action::Fold).unwrap();
+        assert_eq!(check(&state), Ok(()));
+        if let PokerState::TakeAction(ref mut game) = state {
+            let mut hand_action = game.data.hand_history.actions[0].clone();
+            hand_action.action = Action::Check;
+            game.data.hand_history.actions.push(hand_action);
+        }
+        assert_eq!(
+            check(&state),
+            Err(InvariantError::ActionByInactivePlayer { username })
+        );
+    }
+}
@@ -0,0 +1,423 @@
+//! A lookup-table hand evaluator.
+//!
+//! Cards are kept as bits, 16 to a suit, so a hand is a single `u64`.
+//! Hands are ranked with a few bitwise operations on the values in each
+//! suit and lookups into tables indexed by sets of values, which are
+//! perfect hashes of them. Ranking a hand doesn't sort, allocate, or look
+//! at cards one at a time, so it takes the same few nanoseconds however
+//! many cards there are. The tables are built at compile time.
+//!
+//! Hands are ranked by their [`Strength`], which packs the hand's
+//! category and the values of its best 5 cards into a number.
+//! [`subhands`] unpacks a strength into the subhands the rest of the
+//! engine works with.
+
+use std::ops::BitOr;
+
+use super::entities::{Card, Rank, SubHand, Suit, Value};
+
+/// The strength of a hand: its category in the bits above
+/// [`RANK_SHIFT`], followed by the values of its best 5 cards, 4 bits
+/// each, strongest first. Stronger hands have greater strengths, and
+/// hands that would split a pot have equal strengths. Aces are 14s except
+/// in wheels, where they're 1s, and missing cards are 0s.
+pub type Strength = u32;
+
+/// Where a strength's category starts.
+pub const RANK_SHIFT: u32 = 20;
+
+/// Number of distinct card values, deuces through aces.
+const NUM_VALUES: usize = 13;
+
+/// Number of sets of values, which is the size of each table.
+const NUM_VALUE_SETS: usize = 1 << NUM_VALUES;
+
+/// Bits of a suit's values within a hand.
+const SUIT_MASK: u64 = NUM_VALUE_SETS as u64 - 1;
+
+/// Pack up to 5 values into a strength without a category.
+const fn pack(values: [u32; 5]) -> u32 {
+    values[0] << 16 | values[1] << 12 | values[2] << 8 | values[3] << 4 | values[4]
+}
+
+/// The values of the highest (up to) 5 cards of each set of values, as
+/// packed by [`pack`].
+static TOP_FIVE: [u32; NUM_VALUE_SETS] = {
+    let mut table = [0; NUM_VALUE_SETS];
+    let mut set = 0;
+    while set < NUM_VALUE_SETS {
+        let mut values = [0; 5];
+        let mut num_values = 0;
+        let mut bit = NUM_VALUES;
+        while bit > 0 && num_values < 5 {
+            bit -= 1;
+            if set & (1 << bit) != 0 {
+                values[num_values] = bit as u32 + 2;
+                num_values += 1;
+            }
+        }
+        table[set] = pack(values);
+        set += 1;
+    }
+    table
+};
+
+/// The values of the highest straight in each set of values, as packed
+/// by [`pack`], or 0 if there isn't one.
+static STRAIGHTS: [u32; NUM_VALUE_SETS] = {
+    const WHEEL: usize = 1 << 12 | 0b1111;
+    let mut table = [0; NUM_VALUE_SETS];
+    let mut set = 0;
+    while set < NUM_VALUE_SETS {
+        let mut low_bit = NUM_VALUES - 5 + 1;
+        while low_bit > 0 {
+            low_bit -= 1;
+            let straight = 0b11111 << low_bit;
+            if set & straight == straight {
+                let high = low_bit as u32 + 6;
+                table[set] = pack([high, high - 1, high - 2, high - 3, high - 4]);
+                break;
+            }
+        }
+        if table[set] == 0 && set & WHEEL == WHEEL {
+            table[set] = pack([5, 4, 3, 2, 1]);
+        }
+        set += 1;
+    }
+    table
+};
+
+/// The value of the highest card in a non-empty set of values.
+fn highest(set: u32) -> u32 {
+    31 - set.leading_zeros() + 2
+}
+
+/// The bit for a value within a set of values.
+fn value_bit(value: u32) -> u32 {
+    1 << (value - 2)
+}
+
+fn with_rank(rank: Rank, values: u32) -> Strength {
+    (rank as u32) << RANK_SHIFT | values
+}
+
+/// A set of cards from a standard deck.
+///
+/// # Examples
+///
+/// ```
+/// use private_poker::{entities::{parse_cards, Rank}, lookup::{self, CardSet}};
+///
+/// let hole_cards = CardSet::new(&parse_cards("AsKs").unwrap());
+/// let board = CardSet::new(&parse_cards("QsJsTs2h2d").unwrap());
+/// assert_eq!(lookup::rank((hole_cards | board).strength()), Rank::StraightFlush);
+/// ```
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct CardSet(u64);
+
+impl CardSet {
+    /// Collect cards into a set. Aces can be given as 1s or 14s, and
+    /// cards outside a standard deck are left out.
+    pub fn new(cards: &[Card]) -> Self {
+        let mut set = Self::default();
+        for card in cards {
+            set.insert(*card);
+        }
+        set
+    }
+
+    pub fn insert(&mut self, card: Card) {
+        let suit_idx = match card.1 {
+            Suit::Club => 0,
+            Suit::Spade => 1,
+            Suit::Diamond => 2,
+            Suit::Heart => 3,
+            Suit::Wild => return,
+        };
+        let value = match card.0 {
+            1 => 14,
+            2..=14 => card.0,
+            _ => return,
+        };
+        self.0 |= 1 << (16 * suit_idx + value as u64 - 2);
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// The strength of the best (up to) 5-card hand in the set.
+    pub fn strength(self) -> Strength {
+        let suits = [0, 16, 32, 48].map(|shift| ((self.0 >> shift) & SUIT_MASK) as u32);
+        let [clubs, spades, diamonds, hearts] = suits;
+        let values = clubs | spades | diamonds | hearts;
+
+        // Sets of values that appear at least 2, 3, or 4 times.
+        let pairs = (clubs & spades)
+            | (clubs & diamonds)
+            | (clubs & hearts)
+            | (spades & diamonds)
+            | (spades & hearts)
+            | (diamonds & hearts);
+        let trips = (clubs & spades & diamonds)
+            | (clubs & spades & hearts)
+            | (clubs & diamonds & hearts)
+            | (spades & diamonds & hearts);
+        let quads = clubs & spades & diamonds & hearts;
+
+        // More than 7 cards can make flushes in more than one suit.
+        let mut flush = 0;
+        for suit in suits {
+            if suit.count_ones() >= 5 {
+                let strength = match STRAIGHTS[suit as usize] {
+                    0 => with_rank(Rank::Flush, TOP_FIVE[suit as usize]),
+                    straight => with_rank(Rank::StraightFlush, straight),
+                };
+                flush = flush.max(strength);
+            }
+        }
+        if flush >> RANK_SHIFT == Rank::StraightFlush as u32 {
+            return flush;
+        }
+
+        if quads != 0 {
+            let quad = highest(quads);
+            let kickers = values & !value_bit(quad);
+            return with_rank(
+                Rank::FourOfAKind,
+                pack([quad, quad, quad, quad, 0]) | TOP_FIVE[kickers as usize] >> 16,
+            );
+        }
+
+        if trips != 0 {
+            let trip = highest(trips);
+            let fills = pairs & !value_bit(trip);
+            if fills != 0 {
+                let pair = highest(fills);
+                return with_rank(Rank::FullHouse, pack([trip, trip, trip, pair, pair]));
+            }
+        }
+
+        if flush != 0 {
+            return flush;
+        }
+
+        let straight = STRAIGHTS[values as usize];
+        if straight != 0 {
+            return with_rank(Rank::Straight, straight);
+        }
+
+        if trips != 0 {
+            let trip = highest(trips);
+            let kickers = values & !value_bit(trip);
+            return with_rank(
+                Rank::ThreeOfAKind,
+                pack([trip, trip, trip, 0, 0]) | TOP_FIVE[kickers as usize] >> 12,
+            );
+        }
+
+        if pairs != 0 {
+            let high_pair = highest(pairs);
+            let low_pairs = pairs & !value_bit(high_pair);
+            if low_pairs != 0 {
+                let low_pair = highest(low_pairs);
+                let kickers = values & !value_bit(high_pair) & !value_bit(low_pair);
+                return with_rank(
+                    Rank::TwoPair,
+                    pack([high_pair, high_pair, low_pair, low_pair, 0])
+                        | TOP_FIVE[kickers as usize] >> 16,
+                );
+            }
+            let kickers = values & !value_bit(high_pair);
+            return with_rank(
+                Rank::OnePair,
+                pack([high_pair, high_pair, 0, 0, 0]) | TOP_FIVE[kickers as usize] >> 8,
+            );
+        }
+
+        with_rank(Rank::HighCard, TOP_FIVE[values as usize])
+    }
+}
+
+impl BitOr for CardSet {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// The strength of the best (up to) 5-card hand that can be made from
+/// the cards. The cards don't need to be prepared, and aces can be given
+/// as 1s or 14s.
+///
+/// # Examples
+///
+/// ```
+/// use private_poker::{entities::parse_cards, lookup::strength};
+///
+/// let flush = strength(&parse_cards("2h7h9hQhAhQc").unwrap());
+/// let pair = strength(&parse_cards("QsQd").unwrap());
+/// assert!(flush > pair);
+/// ```
+pub fn strength(cards: &[Card]) -> Strength {
+    CardSet::new(cards).strength()
+}
+
+/// A strength's category.
+pub fn rank(strength: Strength) -> Rank {
+    match strength >> RANK_SHIFT {
+        0 => Rank::HighCard,
+        1 => Rank::OnePair,
+        2 => Rank::TwoPair,
+        3 => Rank::ThreeOfAKind,
+        4 => Rank::Straight,
+        5 => Rank::Flush,
+        6 => Rank::FullHouse,
+        7 => Rank::FourOfAKind,
+        _ => Rank::StraightFlush,
+    }
+}
+
+/// Unpack a strength into the subhands that make it up, strongest first.
+pub fn subhands(strength: Strength) -> Vec<SubHand> {
+    let rank = rank(strength);
+    let values: Vec<Value> = (0..5)
+        .rev()
+        .map(|idx| (strength >> (4 * idx) & 0xf) as Value)
+        .take_while(|value| *value != 0)
+        .collect();
+    let num_made = match rank {
+        Rank::HighCard => 0,
+        Rank::OnePair => 2,
+        Rank::ThreeOfAKind => 3,
+        Rank::TwoPair | Rank::FourOfAKind => 4,
+        Rank::Straight | Rank::Flush | Rank::FullHouse | Rank::StraightFlush => 5,
+    };
+    let num_made = num_made.min(values.len());
+    let mut subhands = Vec::with_capacity(1 + values.len() - num_made);
+    if num_made > 0 {
+        subhands.push(SubHand {
+            rank,
+            values: values[..num_made].to_vec(),
+        });
+    }
+    subhands.extend(values[num_made..].iter().map(|value| SubHand {
+        rank: Rank::HighCard,
+        values: vec![*value],
+    }));
+    subhands
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+    use super::{rank, strength, subhands, CardSet, Strength, RANK_SHIFT};
+    use crate::game::{
+        entities::{parse_cards, Card, Rank, Suit},
+        functional::new_deck,
+    };
+
+    fn strength_of(cards: &str) -> Strength {
+        strength(&parse_cards(cards).unwrap())
+    }
+
+    #[test]
+    fn every_five_card_hand() {
+        // Counts of each category and of distinct strengths out of all
+        // 2,598,960 five-card hands, which are well known.
+        let deck = new_deck();
+        let mut counts: BTreeMap<Rank, usize> = BTreeMap::new();
+        let mut is_seen = vec![false; 9 << RANK_SHIFT];
+        for a in 0..52 {
+            for b in a + 1..52 {
+                for c in b + 1..52 {
+                    for d in c + 1..52 {
+                        let hand = CardSet::new(&[deck[a], deck[b], deck[c], deck[d]]);
+                        for card in deck[d + 1..].iter() {
+                            let mut hand = hand;
+                            hand.insert(*card);
+                            let strength = hand.strength();
+                            *counts.entry(rank(strength)).or_default() += 1;
+                            is_seen[strength as usize] = true;
+                        }
+                    }
+                }
+            }
+        }
+        assert_eq!(
+            counts.into_values().collect::<Vec<_>>(),
+            [1302540, 1098240, 123552, 54912, 10200, 5108, 3744, 624, 40]
+        );
+        assert_eq!(is_seen.into_iter().filter(|is_seen| *is_seen).count(), 7462);
+    }
+
+    #[test]
+    fn seven_cards_play_their_best_five() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut deck = new_deck();
+        for _ in 0..2000 {
+            deck.shuffle(&mut rng);
+            let cards = &deck[..7];
+            let mut best = 0;
+            for skipped1 in 0..7 {
+                for skipped2 in skipped1 + 1..7 {
+                    let five: Vec<_> = (0..7)
+                        .filter(|idx| *idx != skipped1 && *idx != skipped2)
+                        .map(|idx| cards[idx])
+                        .collect();
+                    best = best.max(strength(&five));
+                }
+            }
+            assert_eq!(strength(cards), best, "{cards:?}");
+        }
+    }
+
+    #[test]
+    fn quads_use_aces_and_kickers() {
+        assert!(strength_of("AsAhAdAcKh") > strength_of("KsKhKdKcQh"));
+        // Quads on the board are split unless someone has a better kicker.
+        assert!(strength_of("2s2h2d2c3h4sKd") > strength_of("2s2h2d2c3h4sQd"));
+        assert_eq!(strength_of("2s2h2d2c3h4sKd"), strength_of("2s2h2d2c3h4sKc"));
+    }
+
+    #[test]
+    fn aces_play_high_or_low() {
+        assert_eq!(rank(strength_of("As2h3d4c5s")), Rank::Straight);
+        assert!(strength_of("As2h3d4c5s") < strength_of("2h3d4c5s6s"));
+        assert!(strength_of("TsJhQdKcAs") > strength_of("9sTsJhQdKc"));
+        // Aces given as 1s or 14s are the same card.
+        assert_eq!(
+            CardSet::new(&[Card(1, Suit::Spade)]),
+            CardSet::new(&[Card(1, Suit::Spade), Card(14, Suit::Spade)])
+        );
+    }
+
+    #[test]
+    fn unpack_subhands() {
+        let full_house = subhands(strength_of("KsKhKd2c2hAs"));
+        assert_eq!(full_house.len(), 1);
+        assert_eq!(full_house[0].rank, Rank::FullHouse);
+        assert_eq!(full_house[0].values, vec![13, 13, 13, 2, 2]);
+
+        let two_pair = subhands(strength_of("KsKh2d2c9h3s"));
+        assert_eq!(two_pair.len(), 2);
+        assert_eq!(two_pair[0].values, vec![13, 13, 2, 2]);
+        assert_eq!(two_pair[1].rank, Rank::HighCard);
+        assert_eq!(two_pair[1].values, vec![9]);
+
+        // Hands with fewer than 5 cards only have the cards they have.
+        let short = subhands(strength_of("AsAh5d"));
+        assert_eq!(short.len(), 2);
+        assert_eq!(short[0].values, vec![14, 14]);
+        assert_eq!(short[1].values, vec![5]);
+        assert!(subhands(strength(&[])).is_empty());
+    }
+}
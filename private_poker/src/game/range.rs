@@ -0,0 +1,328 @@
+//! Starting hand ranges.
+//!
+//! Ranges are written in the usual shorthand, a comma-separated list of
+//! hands like `"22+, ATs+, KQo, A2s-A5s, AsKh"`:
+//!
+//! - `TT` is every pair of tens, and `TT+` adds every higher pair.
+//! - `AK` is every ace-king, `AKs` only the suited ones, and `AKo` only
+//!   the offsuit ones. `ATs+` raises the kicker up to `AKs`.
+//! - `22-55` and `A2s-A5s` are every hand between the two ends.
+//! - `AsKh` is a single combination of cards.
+
+use std::{collections::BTreeSet, str::FromStr};
+use thiserror::Error;
+
+use super::entities::{Card, Suit, Value};
+
+const SUITS: [Suit; 4] = [Suit::Club, Suit::Spade, Suit::Diamond, Suit::Heart];
+
+#[derive(Debug, Eq, Error, PartialEq)]
+pub enum RangeError {
+    #[error("invalid hand in range: {0}")]
+    InvalidHand(String),
+}
+
+/// Whether a hand's cards share a suit.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Suitedness {
+    Any,
+    Offsuit,
+    Suited,
+}
+
+/// Card values with aces high so they can be compared.
+fn parse_value(c: char) -> Option<Value> {
+    match c {
+        '2'..='9' => c.to_digit(10).map(|v| v as Value),
+        'T' => Some(10),
+        'J' => Some(11),
+        'Q' => Some(12),
+        'K' => Some(13),
+        'A' => Some(14),
+        _ => None,
+    }
+}
+
+fn parse_suit(c: char) -> Option<Suit> {
+    match c {
+        'c' => Some(Suit::Club),
+        'd' => Some(Suit::Diamond),
+        'h' => Some(Suit::Heart),
+        's' => Some(Suit::Spade),
+        _ => None,
+    }
+}
+
+/// Aces are low in the deck, but high everywhere in a range.
+fn to_deck_card(value: Value, suit: Suit) -> Card {
+    Card(if value == 14 { 1 } else { value }, suit)
+}
+
+/// Put a combination's cards in a consistent order so the same cards
+/// always make the same combination.
+fn normalize(card1: Card, card2: Card) -> [Card; 2] {
+    let card1 = to_deck_card(card1.0, card1.1);
+    let card2 = to_deck_card(card2.0, card2.1);
+    if card1 > card2 {
+        [card1, card2]
+    } else {
+        [card2, card1]
+    }
+}
+
+/// A hand like `AKs`, with its values ordered high then low.
+#[derive(Clone, Copy)]
+struct HandClass {
+    high: Value,
+    low: Value,
+    suitedness: Suitedness,
+}
+
+impl HandClass {
+    fn parse(s: &str) -> Option<Self> {
+        let mut chars = s.chars();
+        let high = parse_value(chars.next()?)?;
+        let low = parse_value(chars.next()?)?;
+        let suitedness = match chars.next() {
+            None => Suitedness::Any,
+            Some('o') => Suitedness::Offsuit,
+            Some('s') => Suitedness::Suited,
+            Some(_) => return None,
+        };
+        let is_pair = high == low;
+        if chars.next().is_some() || (is_pair && suitedness != Suitedness::Any) {
+            return None;
+        }
+        Some(Self {
+            high: high.max(low),
+            low: high.min(low),
+            suitedness,
+        })
+    }
+
+    fn combos(&self) -> impl Iterator<Item = [Card; 2]> + '_ {
+        SUITS
+            .iter()
+            .enumerate()
+            .flat_map(move |(suit_idx1, suit1)| {
+                SUITS
+                    .iter()
+                    .enumerate()
+                    .filter(move |(suit_idx2, suit2)| {
+                        if self.high == self.low {
+                            suit_idx1 < *suit_idx2
+                        } else {
+                            match self.suitedness {
+                                Suitedness::Any => true,
+                                Suitedness::Offsuit => suit1 != *suit2,
+                                Suitedness::Suited => suit1 == *suit2,
+                            }
+                        }
+                    })
+                    .map(move |(_, suit2)| {
+                        normalize(
+                            to_deck_card(self.high, *suit1),
+                            to_deck_card(self.low, *suit2),
+                        )
+                    })
+            })
+    }
+}
+
+/// A set of two-card starting hands.
+///
+/// # Examples
+///
+/// ```
+/// use private_poker::{entities::{Card, Suit}, range::Range};
+///
+/// let range: Range = "22+, ATs+, KQo".parse().unwrap();
+/// assert_eq!(range.len(), 78 + 16 + 12);
+/// assert!(range.contains(&[Card(1, Suit::Heart), Card(11, Suit::Heart)]));
+/// assert!(!range.contains(&[Card(1, Suit::Heart), Card(11, Suit::Spade)]));
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Range {
+    combos: BTreeSet<[Card; 2]>,
+}
+
+impl Range {
+    /// Whether the range has a hand. Aces can be given as 1s or 14s.
+    pub fn contains(&self, cards: &[Card; 2]) -> bool {
+        self.combos.contains(&normalize(cards[0], cards[1]))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.combos.is_empty()
+    }
+
+    /// Iterate over every combination of cards in the range.
+    pub fn iter(&self) -> impl Iterator<Item = &[Card; 2]> {
+        self.combos.iter()
+    }
+
+    /// Number of card combinations in the range.
+    pub fn len(&self) -> usize {
+        self.combos.len()
+    }
+
+    fn insert_class(&mut self, class: &HandClass) {
+        self.combos.extend(class.combos());
+    }
+
+    fn insert_hand(&mut self, hand: &str) -> Result<(), RangeError> {
+        let invalid = || RangeError::InvalidHand(hand.to_string());
+        let chars: Vec<char> = hand.chars().collect();
+
+        // A single combination like `AsKh`.
+        if let [value1, suit1, value2, suit2] = chars[..] {
+            if let (Some(value1), Some(suit1), Some(value2), Some(suit2)) = (
+                parse_value(value1),
+                parse_suit(suit1),
+                parse_value(value2),
+                parse_suit(suit2),
+            ) {
+                let card1 = to_deck_card(value1, suit1);
+                let card2 = to_deck_card(value2, suit2);
+                if card1 == card2 {
+                    return Err(invalid());
+                }
+                self.combos.insert(normalize(card1, card2));
+                return Ok(());
+            }
+        }
+
+        // Hands between two ends, like `22-55` or `A2s-A5s`.
+        if let Some((start, end)) = hand.split_once('-') {
+            let start = HandClass::parse(start).ok_or_else(invalid)?;
+            let end = HandClass::parse(end).ok_or_else(invalid)?;
+            if start.suitedness != end.suitedness {
+                return Err(invalid());
+            }
+            let is_pair_range = start.high == start.low && end.high == end.low;
+            if is_pair_range {
+                for value in start.high.min(end.high)..=start.high.max(end.high) {
+                    self.insert_class(&HandClass {
+                        high: value,
+                        low: value,
+                        suitedness: start.suitedness,
+                    });
+                }
+            } else if start.high == end.high && start.high != start.low && end.high != end.low {
+                for low in start.low.min(end.low)..=start.low.max(end.low) {
+                    self.insert_class(&HandClass {
+                        high: start.high,
+                        low,
+                        suitedness: start.suitedness,
+                    });
+                }
+            } else {
+                return Err(invalid());
+            }
+            return Ok(());
+        }
+
+        // A hand and everything above it, like `TT+` or `ATs+`.
+        if let Some(hand) = hand.strip_suffix('+') {
+            let class = HandClass::parse(hand).ok_or_else(invalid)?;
+            if class.high == class.low {
+                for value in class.high..=14 {
+                    self.insert_class(&HandClass {
+                        high: value,
+                        low: value,
+                        suitedness: class.suitedness,
+                    });
+                }
+            } else {
+                for low in class.low..class.high {
+                    self.insert_class(&HandClass { low, ..class });
+                }
+            }
+            return Ok(());
+        }
+
+        let class = HandClass::parse(hand).ok_or_else(invalid)?;
+        self.insert_class(&class);
+        Ok(())
+    }
+}
+
+impl FromStr for Range {
+    type Err = RangeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut range = Range::default();
+        for hand in s.split(',').map(str::trim).filter(|hand| !hand.is_empty()) {
+            range.insert_hand(hand)?;
+        }
+        Ok(range)
+    }
+}
+
+impl<'a> IntoIterator for &'a Range {
+    type Item = &'a [Card; 2];
+    type IntoIter = std::collections::btree_set::Iter<'a, [Card; 2]>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.combos.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Range, RangeError};
+    use crate::game::entities::{Card, Suit};
+
+    fn len(range: &str) -> usize {
+        range.parse::<Range>().unwrap().len()
+    }
+
+    #[test]
+    fn count_combos() {
+        assert_eq!(len("TT"), 6);
+        assert_eq!(len("22+"), 78);
+        assert_eq!(len("AK"), 16);
+        assert_eq!(len("AKs"), 4);
+        assert_eq!(len("AKo"), 12);
+        assert_eq!(len("ATs+"), 16);
+        assert_eq!(len("K9o+"), 48);
+        assert_eq!(len("22-44"), 18);
+        assert_eq!(len("A5s-A2s"), 16);
+        assert_eq!(len("AsKh"), 1);
+        assert_eq!(len("22+, ATs+, KQo"), 106);
+        // Overlapping hands are only counted once.
+        assert_eq!(len("AK, AKs, AsKs"), 16);
+        assert_eq!(len(""), 0);
+    }
+
+    #[test]
+    fn contains_hands() {
+        let range: Range = "QQ+, AsKh".parse().unwrap();
+        assert!(range.contains(&[Card(12, Suit::Club), Card(12, Suit::Heart)]));
+        assert!(range.contains(&[Card(14, Suit::Club), Card(1, Suit::Heart)]));
+        assert!(range.contains(&[Card(13, Suit::Heart), Card(1, Suit::Spade)]));
+        assert!(!range.contains(&[Card(13, Suit::Spade), Card(1, Suit::Heart)]));
+        assert!(!range.contains(&[Card(11, Suit::Club), Card(11, Suit::Heart)]));
+    }
+
+    #[test]
+    fn iterate_combos() {
+        let range: Range = "AA".parse().unwrap();
+        assert!(range
+            .iter()
+            .all(|[card1, card2]| card1.0 == 1 && card2.0 == 1));
+        assert_eq!(range.iter().count(), 6);
+    }
+
+    #[test]
+    fn invalid_hands() {
+        for hand in [
+            "AAs", "AKx", "A", "AK+s", "22-AK", "A2s-K2s", "A2s-A5o", "AsAs", "1K",
+        ] {
+            assert_eq!(
+                hand.parse::<Range>(),
+                Err(RangeError::InvalidHand(hand.to_string()))
+            );
+        }
+    }
+}
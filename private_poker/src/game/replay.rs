@@ -0,0 +1,453 @@
+//! Replaying games from their actions.
+//!
+//! A seeded game deals the same cards every time, so a game can be
+//! played again from its settings, the users that started it, and the
+//! actions its players took. A [`Replay`] does that one step at a time,
+//! producing a view of the game after each state change or action with
+//! every player's cards shown. Replays are only faithful for games where
+//! no one joined or left after the start, like simulations and tests.
+//!
+//! Recorded hands don't keep the seed they were dealt with, so they're
+//! stepped through with [`hand_views`] instead, which rebuilds views from
+//! the hand's history alone.
+
+use std::{collections::HashMap, iter::Peekable, mem, vec};
+
+use thiserror::Error;
+
+use super::{
+    entities::{
+        Action, GameView, HandAction, HandHistory, PlayerState, PlayerView, PotView, Usd, User,
+        Username,
+    },
+    label_positions, GameSettings, PokerState, UserError,
+};
+
+#[derive(Debug, Eq, Error, PartialEq)]
+pub enum ReplayError {
+    #[error("{action_username} acted when it was {turn_username}'s turn")]
+    OutOfTurn {
+        action_username: Username,
+        turn_username: Username,
+    },
+    #[error(transparent)]
+    User(#[from] UserError),
+}
+
+/// Steps through a game, yielding a view after each state change or
+/// action.
+///
+/// # Examples
+///
+/// ```
+/// use private_poker::{
+///     entities::{Action, HandAction, Street, User},
+///     replay::Replay,
+///     GameSettings,
+/// };
+///
+/// let settings = GameSettings::default().with_seed(0);
+/// let users: Vec<_> = ["0", "1"]
+///     .into_iter()
+///     .map(|name| User {
+///         name: name.into(),
+///         money: settings.buy_in,
+///     })
+///     .collect();
+/// // Heads-up, the small blind acts first and folds.
+/// let actions = vec![HandAction {
+///     street: Street::Preflop,
+///     username: "1".into(),
+///     action: Action::Fold,
+/// }];
+/// let views: Vec<_> = Replay::new(settings, &users, actions)
+///     .unwrap()
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// // Every player's cards are shown.
+/// assert!(views.iter().any(|view| view.players.iter().all(|p| p.cards.len() == 2)));
+/// ```
+pub struct Replay {
+    state: PokerState,
+    actions: Peekable<vec::IntoIter<HandAction>>,
+    is_done: bool,
+}
+
+impl Replay {
+    /// Start a game with the settings, waitlisting the users in order with
+    /// the money they started with. The settings need the seed the game
+    /// was played with to deal the same cards.
+    pub fn new(
+        settings: GameSettings,
+        users: &[User],
+        actions: Vec<HandAction>,
+    ) -> Result<Self, UserError> {
+        let mut state: PokerState = settings.into();
+        for user in users {
+            state.new_user(&user.name)?;
+            if let PokerState::Lobby(ref mut game) = state {
+                if let Some(spectator) = game.data.spectators.get_mut(&user.name) {
+                    spectator.money = user.money;
+                }
+            }
+            state.waitlist_user(&user.name)?;
+        }
+        Ok(Self {
+            state,
+            actions: actions.into_iter().peekable(),
+            is_done: false,
+        })
+    }
+
+    /// Take the next action from the log for the player whose turn it is.
+    fn act(&mut self, turn_username: Username) -> Option<Result<GameView, ReplayError>> {
+        let hand_action = self.actions.next()?;
+        if hand_action.username != turn_username {
+            return Some(Err(ReplayError::OutOfTurn {
+                action_username: hand_action.username,
+                turn_username,
+            }));
+        }
+        match self
+            .state
+            .take_action(&hand_action.username, hand_action.action)
+        {
+            Ok(_) => Some(Ok(self.state.get_revealed_view())),
+            Err(error) => Some(Err(error.into())),
+        }
+    }
+
+    fn step(&mut self) -> Option<Result<GameView, ReplayError>> {
+        if let PokerState::Lobby(ref mut game) = self.state {
+            // The replay ends once every action has been taken and the
+            // last hand is over.
+            self.actions.peek()?;
+            if !game.is_ready_to_start() {
+                if let Err(error) = game.init_start() {
+                    return Some(Err(error.into()));
+                }
+            }
+        }
+        let turn_username = match self.state {
+            PokerState::TakeAction(ref game) if !game.is_ready_for_next_phase() => {
+                game.get_next_action_username()
+            }
+            _ => None,
+        };
+        match turn_username {
+            Some(turn_username) => self.act(turn_username),
+            None => {
+                self.state = mem::take(&mut self.state).step();
+                Some(Ok(self.state.get_revealed_view()))
+            }
+        }
+    }
+}
+
+impl Iterator for Replay {
+    type Item = Result<GameView, ReplayError>;
+
+    /// Stops after the last hand with actions ends, if the log runs out
+    /// mid-hand, or after the first error.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_done {
+            return None;
+        }
+        let item = self.step();
+        if !matches!(item, Some(Ok(_))) {
+            self.is_done = true;
+        }
+        item
+    }
+}
+
+/// Put money from the player's stack into the pot, going all-in if it's
+/// more than they have.
+fn post(player: &mut PlayerView, amount: Usd) {
+    let amount = amount.min(player.user.money);
+    player.user.money -= amount;
+    player.investment += amount;
+    if player.user.money == 0 {
+        player.state = PlayerState::AllIn;
+    }
+}
+
+/// Views of a recorded hand, one from before each action with the turn on
+/// the player that took it, and one once betting was over with the whole
+/// board out. Only cards that were shown at the end of the hand are
+/// known, so they're the only ones in the views. A history without any
+/// players, like the one kept between hands, doesn't have any views.
+pub fn hand_views(hand: &HandHistory) -> Vec<GameView> {
+    let num_players = hand.players.len();
+    if num_players == 0 {
+        return vec![];
+    }
+    let player_idx = |username: &Username| {
+        hand.players
+            .iter()
+            .position(|(_, user)| user.name == *username)
+    };
+    let small_blind_idx = player_idx(&hand.small_blind_username).unwrap_or(0);
+    let big_blind_idx = player_idx(&hand.big_blind_username).unwrap_or(0);
+    // The history only has players that were dealt in, so the button is
+    // right before the small blind.
+    let button_idx = if num_players == 2 || hand.is_small_blind_dead {
+        small_blind_idx
+    } else {
+        (small_blind_idx + num_players - 1) % num_players
+    };
+    let positions = if num_players >= 2 {
+        label_positions(
+            &vec![true; num_players],
+            button_idx,
+            small_blind_idx,
+            big_blind_idx,
+            hand.is_small_blind_dead,
+        )
+    } else {
+        vec![None; num_players]
+    };
+    let shown_hands: HashMap<_, _> = hand.shown_hands.iter().cloned().collect();
+    let mut players: Vec<_> = hand
+        .players
+        .iter()
+        .zip(positions)
+        .map(|((seat_idx, user), position)| PlayerView {
+            user: user.clone(),
+            state: PlayerState::Wait,
+            cards: shown_hands.get(&user.name).cloned().unwrap_or_default(),
+            investment: 0,
+            seat_idx: *seat_idx,
+            position,
+        })
+        .collect();
+    // Antes are dead money, so they're in the pot without being part of
+    // anyone's bet, unless they put a player all-in. Bomb pot antes are
+    // everyone's bet since there's no betting before the flop anyways.
+    let mut dead = 0;
+    if hand.ante > 0 && !hand.is_big_blind_ante {
+        for player in players.iter_mut() {
+            if hand.is_bomb_pot || player.user.money <= hand.ante {
+                post(player, hand.ante);
+            } else {
+                player.user.money -= hand.ante;
+                dead += hand.ante;
+            }
+        }
+    }
+    // Bomb pots don't have blinds, just antes.
+    if !hand.is_small_blind_dead && !hand.is_bomb_pot {
+        if let Some(player) = players.get_mut(small_blind_idx) {
+            post(player, hand.small_blind);
+        }
+    }
+    // So is a big blind ante.
+    if let Some(player) = players.get_mut(big_blind_idx).filter(|_| !hand.is_bomb_pot) {
+        post(player, hand.big_blind);
+        if hand.is_big_blind_ante && player.state != PlayerState::AllIn {
+            let ante = hand.ante.min(player.user.money);
+            player.user.money -= ante;
+            dead += ante;
+            if player.user.money == 0 {
+                player.state = PlayerState::AllIn;
+            }
+        }
+    }
+
+    let view = |players: &[PlayerView], num_actions: usize| {
+        let (action_on, board, second_board) = match hand.actions.get(num_actions) {
+            Some(hand_action) => {
+                let num_community_cards = hand_action.street.num_community_cards();
+                let board = hand.board.iter().take(num_community_cards).copied();
+                (Some(&hand_action.username), board.collect(), vec![])
+            }
+            None => (None, hand.board.clone(), hand.second_board.clone()),
+        };
+        let players: Vec<_> = players
+            .iter()
+            .map(|player| PlayerView {
+                user: player.user.clone(),
+                state: player.state.clone(),
+                cards: player.cards.clone(),
+                investment: player.investment,
+                seat_idx: player.seat_idx,
+                position: player.position,
+            })
+            .collect();
+        GameView {
+            donations: 0.0,
+            small_blind: hand.small_blind,
+            big_blind: hand.big_blind,
+            ante: hand.ante,
+            is_big_blind_ante: hand.is_big_blind_ante,
+            bomb_pot: hand.is_bomb_pot.then_some(hand.ante),
+            spectators: HashMap::new(),
+            waitlist: Default::default(),
+            open_seats: Default::default(),
+            board,
+            second_board,
+            pot: PotView {
+                size: players.iter().map(|player| player.investment).sum::<Usd>() + dead,
+                call: players
+                    .iter()
+                    .map(|player| player.investment)
+                    .max()
+                    .unwrap_or(0),
+                side_pots: vec![],
+                chip_display: hand.chip_display,
+            },
+            small_blind_idx,
+            big_blind_idx,
+            is_small_blind_dead: hand.is_small_blind_dead,
+            next_action_idx: action_on.and_then(player_idx),
+            action_on: action_on.cloned(),
+            button_idx: (num_players >= 2).then_some(button_idx),
+            actions: hand.actions[..num_actions].to_vec(),
+            tournament: None,
+            seat_requests: HashMap::new(),
+            waitlist_position: None,
+            jackpot: None,
+            turn_timer: None,
+            chip_display: hand.chip_display,
+            players,
+        }
+    };
+
+    let mut views = Vec::with_capacity(hand.actions.len() + 1);
+    for (num_actions, hand_action) in hand.actions.iter().enumerate() {
+        views.push(view(&players, num_actions));
+        let Some(player) = player_idx(&hand_action.username).map(|idx| &mut players[idx]) else {
+            continue;
+        };
+        match hand_action.action {
+            Action::AllIn => post(player, player.user.money),
+            Action::Call(amount) | Action::Raise(amount) => post(player, amount),
+            Action::Check => {}
+            Action::Fold => player.state = PlayerState::Fold,
+        }
+    }
+    for player in players.iter_mut() {
+        if shown_hands.contains_key(&player.user.name) {
+            player.state = PlayerState::Show;
+        }
+    }
+    views.push(view(&players, hand.actions.len()));
+    views
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+
+    use super::{hand_views, Replay, ReplayError};
+    use crate::game::{
+        entities::{Action, HandHistory, PlayerState, User},
+        GameSettings, PokerState,
+    };
+
+    fn users(settings: &GameSettings) -> Vec<User> {
+        (0..3)
+            .map(|i| User {
+                name: i.to_string().into(),
+                money: settings.buy_in,
+            })
+            .collect()
+    }
+
+    /// Play hands where everyone calls, returning each hand's history.
+    fn play(settings: &GameSettings, num_hands: usize) -> Vec<HandHistory> {
+        let mut state: PokerState = settings.clone().into();
+        for user in users(settings) {
+            state.new_user(&user.name).unwrap();
+            state.waitlist_user(&user.name).unwrap();
+        }
+        let mut hands = Vec::new();
+        while hands.len() < num_hands {
+            if let PokerState::Lobby(ref mut game) = state {
+                game.init_start().unwrap();
+            }
+            match (state.get_next_action_username(), state.get_action_options()) {
+                (Some(username), Some(action_options)) => {
+                    let action = action_options
+                        .get(&Action::Call(0))
+                        .or(action_options.get(&Action::Check))
+                        .cloned()
+                        .unwrap_or(Action::AllIn);
+                    state.take_action(&username, action).unwrap();
+                }
+                _ => {
+                    state = mem::take(&mut state).step();
+                    if let Some(hand) = state.get_hand_history() {
+                        hands.push(hand);
+                    }
+                }
+            }
+        }
+        hands
+    }
+
+    #[test]
+    fn replay_deals_the_same_cards() {
+        let settings = GameSettings::default().with_seed(0);
+        let hands = play(&settings, 2);
+        let actions = hands.iter().flat_map(|hand| hand.actions.clone()).collect();
+        let views: Vec<_> = Replay::new(settings.clone(), &users(&settings), actions)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let boards: Vec<_> = views
+            .iter()
+            .filter(|view| view.board.len() == 5)
+            .map(|view| &view.board)
+            .collect();
+        for hand in hands.iter() {
+            assert!(boards.contains(&&hand.board));
+        }
+        // The replay stops in the lobby after the last hand.
+        let last_view = views.last().unwrap();
+        assert!(last_view.board.is_empty());
+        assert_eq!(last_view.pot.size, 0);
+    }
+
+    #[test]
+    fn replay_stops_at_out_of_turn_action() {
+        let settings = GameSettings::default().with_seed(0);
+        let mut actions = play(&settings, 1).remove(0).actions;
+        actions.swap(0, 1);
+        let mut replay = Replay::new(settings.clone(), &users(&settings), actions).unwrap();
+        let error = replay.find_map(Result::err).unwrap();
+        assert!(matches!(error, ReplayError::OutOfTurn { .. }));
+        assert!(replay.next().is_none());
+    }
+
+    #[test]
+    fn step_through_hand_history() {
+        let settings = GameSettings::default().with_seed(0);
+        let hand = play(&settings, 1).remove(0);
+        let views = hand_views(&hand);
+        assert_eq!(views.len(), hand.actions.len() + 1);
+        // Blinds are in before anyone acts.
+        let first_view = &views[0];
+        assert_eq!(first_view.pot.size, hand.small_blind + hand.big_blind);
+        assert!(first_view.board.is_empty());
+        for (view, hand_action) in views.iter().zip(hand.actions.iter()) {
+            assert_eq!(view.action_on.as_ref(), Some(&hand_action.username));
+            assert_eq!(view.board.len(), hand_action.street.num_community_cards());
+        }
+        // Everything put in the pot is accounted for by the end, and
+        // players that showed their hand have their cards shown.
+        let last_view = views.last().unwrap();
+        assert_eq!(last_view.action_on, None);
+        assert_eq!(last_view.board, hand.board);
+        assert_eq!(last_view.pot.size, hand.audit.chips_in);
+        for (username, cards) in hand.shown_hands.iter() {
+            let player = last_view
+                .players
+                .iter()
+                .find(|player| player.user.name == *username)
+                .unwrap();
+            assert_eq!(player.state, PlayerState::Show);
+            assert_eq!(&player.cards, cards);
+        }
+    }
+}
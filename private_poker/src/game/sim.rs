@@ -0,0 +1,336 @@
+//! Simulations of many hands between programmatic players.
+//!
+//! A [`Simulation`] seats agents at a game and plays complete hands
+//! with the same engine the server uses, asking each agent for an action
+//! whenever it's their turn. Every stack is reset to the buy-in before
+//! each hand so players that bust keep playing and profits from
+//! different hands can be compared, which makes simulations useful for
+//! checking rule changes and measuring how strategies do against each
+//! other.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    mem,
+};
+
+use super::{
+    agent::Agent,
+    entities::{Action, HandHistory, Street, Usd, Username},
+    GameSettings, PokerState, UserError,
+};
+
+/// A player's totals across every hand of a simulation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PlayerResults {
+    /// Hands the player was dealt into.
+    pub hands: usize,
+    /// Hands where the player won at least part of a pot.
+    pub hands_won: usize,
+    /// Hands where the player went to showdown, whether they showed
+    /// their cards or mucked them.
+    pub showdowns: usize,
+    /// Hands where the player voluntarily put money in preflop.
+    pub vpip_hands: usize,
+    /// Hands where the player raised preflop.
+    pub pfr_hands: usize,
+    /// Actions the game rejected, which were replaced with folds.
+    pub invalid_actions: usize,
+    /// Money won minus money lost.
+    pub profit: i64,
+}
+
+impl PlayerResults {
+    /// Average profit per hand dealt.
+    pub fn profit_per_hand(&self) -> f64 {
+        if self.hands == 0 {
+            0.0
+        } else {
+            self.profit as f64 / self.hands as f64
+        }
+    }
+}
+
+/// Results of all the hands played by a simulation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SimResults {
+    /// Number of hands played.
+    pub hands: usize,
+    pub players: BTreeMap<Username, PlayerResults>,
+}
+
+impl SimResults {
+    /// Tally a finished hand given every player's money after the hand.
+    fn record_hand(&mut self, history: &HandHistory, money: &HashMap<Username, Usd>) {
+        self.hands += 1;
+        for (_, user) in history.players.iter() {
+            let results = self.players.entry(user.name.clone()).or_default();
+            results.hands += 1;
+            let final_money = money.get(&user.name).copied().unwrap_or_default();
+            results.profit += i64::from(final_money) - i64::from(user.money);
+        }
+        let winners: HashSet<_> = history.pots.iter().flat_map(|pot| &pot.winners).collect();
+        for username in winners {
+            self.players.entry(username.clone()).or_default().hands_won += 1;
+        }
+        let showdown_usernames = history
+            .shown_hands
+            .iter()
+            .map(|(username, _)| username)
+            .chain(history.mucked_hands.iter());
+        for username in showdown_usernames {
+            self.players.entry(username.clone()).or_default().showdowns += 1;
+        }
+        let mut vpip_players = HashSet::new();
+        let mut pfr_players = HashSet::new();
+        for hand_action in history
+            .actions
+            .iter()
+            .filter(|hand_action| hand_action.street == Street::Preflop)
+        {
+            let username = &hand_action.username;
+            match hand_action.action {
+                Action::AllIn | Action::Raise(_) => {
+                    vpip_players.insert(username);
+                    pfr_players.insert(username);
+                }
+                Action::Call(_) => {
+                    vpip_players.insert(username);
+                }
+                Action::Check | Action::Fold => {}
+            }
+        }
+        for username in vpip_players {
+            self.players.entry(username.clone()).or_default().vpip_hands += 1;
+        }
+        for username in pfr_players {
+            self.players.entry(username.clone()).or_default().pfr_hands += 1;
+        }
+    }
+}
+
+/// Plays hands between agents with the real game engine.
+///
+/// # Examples
+///
+/// ```
+/// use std::collections::HashSet;
+///
+/// use private_poker::{
+///     entities::{Action, GameView},
+///     sim::Simulation,
+///     GameSettings,
+/// };
+///
+/// fn call(_: &GameView, options: &HashSet<Action>) -> Action {
+///     options
+///         .iter()
+///         .find(|action| matches!(action, Action::Call(_) | Action::Check))
+///         .cloned()
+///         .unwrap_or(Action::Fold)
+/// }
+///
+/// let mut sim = Simulation::new(GameSettings::default().with_seed(0));
+/// sim.add_agent("caller", call).unwrap();
+/// sim.add_agent("folder", |_: &GameView, _: &HashSet<Action>| Action::Fold)
+///     .unwrap();
+/// let results = sim.run(100);
+/// assert_eq!(results.hands, 100);
+/// assert!(results.players["caller"].profit > 0);
+/// ```
+pub struct Simulation {
+    agents: HashMap<Username, Box<dyn Agent>>,
+    buy_in: Usd,
+    state: PokerState,
+}
+
+impl Simulation {
+    /// Tournament settings are ignored since stacks are reset every
+    /// hand. Seed the settings to make the deals reproducible.
+    pub fn new(mut settings: GameSettings) -> Self {
+        settings.tournament = None;
+        Self {
+            agents: HashMap::new(),
+            buy_in: settings.buy_in,
+            state: settings.into(),
+        }
+    }
+
+    /// Add a player to the game that's controlled by the agent. Players
+    /// take seats in the order they're added, and players added once the
+    /// table is full wait for a seat.
+    pub fn add_agent(
+        &mut self,
+        username: &str,
+        agent: impl Agent + 'static,
+    ) -> Result<(), UserError> {
+        self.state.new_user(username)?;
+        self.state.waitlist_user(username)?;
+        self.agents.insert(username.into(), Box::new(agent));
+        Ok(())
+    }
+
+    /// Play hands until the given number of hands have been played or
+    /// there aren't enough players to start another hand.
+    pub fn run(&mut self, num_hands: usize) -> SimResults {
+        let mut results = SimResults::default();
+        for _ in 0..num_hands {
+            if !self.play_hand(&mut results) {
+                break;
+            }
+        }
+        results
+    }
+
+    /// Play a hand from the lobby back to the lobby, returning whether
+    /// a hand could be started.
+    fn play_hand(&mut self, results: &mut SimResults) -> bool {
+        let PokerState::Lobby(ref mut game) = self.state else {
+            unreachable!("hands always end in the lobby");
+        };
+        for user in game
+            .data
+            .spectators
+            .values_mut()
+            .chain(game.data.waitlist.iter_mut())
+            .chain(game.data.players.iter_mut().map(|p| &mut p.user))
+        {
+            user.money = self.buy_in;
+        }
+        // Spectators are waitlisted in order so seeded simulations seat
+        // players the same way every time.
+        let mut spectators: Vec<_> = game.data.spectators.keys().cloned().collect();
+        spectators.sort();
+        for username in spectators {
+            game.waitlist_user(&username)
+                .expect("spectators can afford the big blind after a reset");
+        }
+        if game.init_start().is_err() {
+            return false;
+        }
+
+        let mut played = false;
+        loop {
+            let turn = match self.state {
+                PokerState::TakeAction(ref game) if !game.is_ready_for_next_phase() => {
+                    game.get_next_action_username()
+                }
+                _ => None,
+            };
+            if let Some(username) = turn {
+                let action_options = self.state.get_action_options().unwrap_or_default();
+                let view = self
+                    .state
+                    .get_views()
+                    .remove(&username)
+                    .expect("players have views");
+                let agent = self
+                    .agents
+                    .get_mut(&username)
+                    .expect("every player has an agent");
+                let action = agent.act(&view, &action_options);
+                if self.state.take_action(&username, action).is_err() {
+                    results
+                        .players
+                        .entry(username.clone())
+                        .or_default()
+                        .invalid_actions += 1;
+                    self.state
+                        .take_action(&username, Action::Fold)
+                        .expect("folding is always an option");
+                }
+                continue;
+            }
+
+            self.state = mem::take(&mut self.state).step();
+            match self.state {
+                PokerState::RemovePlayers(ref game) => {
+                    let money = game
+                        .data
+                        .players
+                        .iter()
+                        .map(|p| (p.user.name.clone(), p.user.money))
+                        .collect();
+                    results.record_hand(&game.get_hand_history(), &money);
+                    played = true;
+                }
+                // The game goes back to the lobby without playing a hand
+                // if not enough players could be seated.
+                PokerState::Lobby(_) => return played,
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::Simulation;
+    use crate::game::{
+        entities::{Action, GameView},
+        GameSettings,
+    };
+
+    /// Call any bet, going all-in if calling takes every chip.
+    fn call_or_check(_: &GameView, action_options: &HashSet<Action>) -> Action {
+        action_options
+            .iter()
+            .find(|action| matches!(action, Action::Call(_) | Action::Check))
+            .cloned()
+            .unwrap_or(Action::AllIn)
+    }
+
+    fn init_sim(seed: u64) -> Simulation {
+        let mut sim = Simulation::new(GameSettings::default().with_seed(seed));
+        sim.add_agent("0", call_or_check).unwrap();
+        sim.add_agent("1", |view: &GameView, options: &HashSet<Action>| {
+            if options.contains(&Action::AllIn) {
+                Action::AllIn
+            } else {
+                call_or_check(view, options)
+            }
+        })
+        .unwrap();
+        sim.add_agent("2", call_or_check).unwrap();
+        sim
+    }
+
+    #[test]
+    fn money_is_conserved() {
+        let results = init_sim(0).run(200);
+        assert_eq!(results.hands, 200);
+        let total_profit: i64 = results.players.values().map(|r| r.profit).sum();
+        assert_eq!(total_profit, 0);
+        for player_results in results.players.values() {
+            assert_eq!(player_results.hands, 200);
+            assert!(player_results.hands_won > 0);
+        }
+        // The all-in player shoves preflop every hand.
+        assert_eq!(results.players["1"].pfr_hands, 200);
+    }
+
+    #[test]
+    fn seeded_sims_are_reproducible() {
+        assert_eq!(init_sim(1).run(100), init_sim(1).run(100));
+    }
+
+    #[test]
+    fn invalid_actions_fold() {
+        let mut sim = Simulation::new(GameSettings::default().with_seed(0));
+        sim.add_agent("0", |_: &GameView, _: &HashSet<Action>| Action::Raise(1))
+            .unwrap();
+        sim.add_agent("1", call_or_check).unwrap();
+        let results = sim.run(10);
+        assert_eq!(results.players["0"].invalid_actions, 10);
+        assert_eq!(results.players["0"].hands_won, 0);
+        assert!(results.players["0"].profit < 0);
+    }
+
+    #[test]
+    fn not_enough_players() {
+        let mut sim = Simulation::new(GameSettings::default());
+        sim.add_agent("0", call_or_check).unwrap();
+        assert_eq!(sim.run(10).hands, 0);
+    }
+}
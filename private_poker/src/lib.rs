@@ -1,9 +1,24 @@
+#[cfg(feature = "net")]
 pub mod net;
-pub use net::{client::Client, messages, server, utils};
+#[cfg(feature = "client")]
+pub use net::client::Client;
+#[cfg(feature = "quic")]
+pub use net::quic;
+#[cfg(feature = "server")]
+pub use net::{auth, control, proxy, server, status};
+#[cfg(feature = "net")]
+pub use net::{messages, persistence, socket, tls, transport, utils};
 
 pub mod game;
 pub use game::{
-    constants::{self, DEFAULT_MAX_USERS, MAX_PLAYERS},
-    entities::{self, DEFAULT_BUY_IN, DEFAULT_MIN_BIG_BLIND, DEFAULT_MIN_SMALL_BLIND},
-    functional, GameSettings, PokerState, UserError,
+    agent::{self, Agent},
+    constants::{
+        self, DEFAULT_MAX_REBUYS, DEFAULT_MAX_SPECTATORS, DEFAULT_MAX_USERS, DEFAULT_TURN_TIMEOUT,
+        MAX_PLAYERS, MAX_USERS,
+    },
+    entities::{self, HandRank, DEFAULT_BUY_IN, DEFAULT_MIN_BIG_BLIND, DEFAULT_MIN_SMALL_BLIND},
+    equity,
+    functional::{self, evaluate},
+    invariants, lookup, range, replay, sim, BombPotSettings, GameCheckpoint, GameSettings,
+    GameSettingsError, JackpotSettings, PokerState, RakeSettings, TournamentSettings, UserError,
 };
@@ -1,4 +1,20 @@
+#[cfg(feature = "server")]
+pub mod auth;
+#[cfg(feature = "client")]
 pub mod client;
+#[cfg(feature = "server")]
+pub mod control;
 pub mod messages;
+pub mod persistence;
+#[cfg(feature = "server")]
+pub mod proxy;
+#[cfg(feature = "quic")]
+pub mod quic;
+#[cfg(feature = "server")]
 pub mod server;
+pub mod socket;
+#[cfg(feature = "server")]
+pub mod status;
+pub mod tls;
+pub mod transport;
 pub mod utils;
@@ -0,0 +1,120 @@
+//! Username and password authentication.
+
+use anyhow::Error;
+use argon2::{
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::PathBuf,
+};
+use tracing::error;
+
+use crate::game::entities::Username;
+
+use super::messages::ClientError;
+
+/// Password hashes for every registered user. Users are registered the
+/// first time they authenticate, and they must use the same password
+/// from then on.
+#[derive(Debug, Default)]
+pub struct Credentials {
+    path: Option<PathBuf>,
+    password_hashes: HashMap<Username, String>,
+}
+
+impl Credentials {
+    /// Check the user's password, registering the user with the password
+    /// if they haven't authenticated before.
+    pub fn authenticate(&mut self, username: &str, password: &str) -> Result<(), ClientError> {
+        let argon2 = Argon2::default();
+        match self.password_hashes.get(username) {
+            Some(password_hash) => {
+                let password_hash = PasswordHash::new(password_hash)
+                    .map_err(|_| ClientError::AuthenticationFailed)?;
+                argon2
+                    .verify_password(password.as_bytes(), &password_hash)
+                    .map_err(|_| ClientError::AuthenticationFailed)
+            }
+            None => {
+                let salt = SaltString::generate(&mut rand::thread_rng());
+                let password_hash = argon2
+                    .hash_password(password.as_bytes(), &salt)
+                    .map_err(|_| ClientError::AuthenticationFailed)?;
+                self.password_hashes
+                    .insert(username.into(), password_hash.to_string());
+                // Failing to save only means the user has to register again
+                // after a restart, so it shouldn't stop them from playing.
+                if let Err(error) = self.save() {
+                    error!("couldn't save credentials: {error}");
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Load credentials from a file, creating the file when the first
+    /// user registers. New registrations are saved to the same file.
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        let password_hashes = match File::open(&path) {
+            Ok(file) => bincode::deserialize_from(BufReader::new(file))?,
+            // No one has registered yet.
+            Err(error) if error.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(error) => return Err(error.into()),
+        };
+        Ok(Self {
+            path: Some(path),
+            password_hashes,
+        })
+    }
+
+    /// Credentials that're only kept in memory and are forgotten when the
+    /// server stops.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        if let Some(path) = &self.path {
+            let tmp_path = path.with_extension("tmp");
+            let file = File::create(&tmp_path)?;
+            bincode::serialize_into(BufWriter::new(file), &self.password_hashes)?;
+            fs::rename(tmp_path, path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use crate::net::messages::ClientError;
+
+    use super::Credentials;
+
+    #[test]
+    fn register_then_authenticate() {
+        let path = env::temp_dir().join(format!("pp_credentials_{}.bin", process::id()));
+        let mut credentials = Credentials::load(&path).unwrap();
+        assert_eq!(credentials.authenticate("0", "hunter2"), Ok(()));
+        assert_eq!(credentials.authenticate("0", "hunter2"), Ok(()));
+        assert_eq!(
+            credentials.authenticate("0", "hunter3"),
+            Err(ClientError::AuthenticationFailed)
+        );
+
+        // Registrations survive reloading.
+        let mut credentials = Credentials::load(&path).unwrap();
+        assert_eq!(
+            credentials.authenticate("0", "hunter3"),
+            Err(ClientError::AuthenticationFailed)
+        );
+        assert_eq!(credentials.authenticate("0", "hunter2"), Ok(()));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -4,25 +4,89 @@
 //! rather than an actual poker client.
 
 use anyhow::{bail, Error};
-use std::{net::TcpStream, thread, time::Duration};
+use std::{
+    io::{self, Write},
+    thread,
+    time::Duration,
+};
 
-use crate::game::{entities::Action, UserError};
+use crate::game::{
+    agent::Agent,
+    entities::{Action, HandHistory, Usd, Username},
+    GameSettings, UserError,
+};
 
 use super::{
-    messages::{ClientError, ClientMessage, GameView, ServerMessage, UserCommand, UserState},
+    messages::{
+        AdminOp, ClientError, ClientMessage, GameView, ServerMessage, SessionToken, Standing,
+        TableId, TableView, UserCommand, UserState,
+    },
+    socket::{Addr, Socket},
+    tls::{ClientTls, Stream},
     utils,
 };
 
+#[cfg(feature = "quic")]
+use super::quic;
+
 pub const READ_TIMEOUT: Duration = Duration::from_secs(10);
 pub const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
 
+/// Optional ways to secure a connection.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectOptions {
+    /// Password for servers that require authentication.
+    pub password: Option<String>,
+    /// Session from the last time the user connected, so they can take
+    /// back their spot if their connection dropped, even from a new
+    /// address. The password is used instead if there is one.
+    pub session: Option<SessionToken>,
+    /// Encrypts the connection with TLS if set.
+    pub tls: Option<ClientTls>,
+    /// Asks the server to compress big messages, which saves bandwidth
+    /// at busy tables.
+    pub compress: bool,
+    /// How to reach the server.
+    pub transport: Transport,
+}
+
+/// Protocols the client can connect over.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Transport {
+    #[default]
+    Tcp,
+    /// QUIC, which always uses TLS, so it needs TLS options to connect.
+    #[cfg(feature = "quic")]
+    Quic,
+}
+
 pub struct Client {
-    pub username: String,
+    pub username: Username,
     pub addr: String,
-    pub stream: TcpStream,
+    /// Session to reconnect with if the connection drops.
+    pub session: SessionToken,
+    pub stream: Stream<Socket>,
 }
 
 impl Client {
+    pub fn admin(&mut self, op: AdminOp) -> Result<(), Error> {
+        let msg = ClientMessage {
+            username: self.username.clone(),
+            command: UserCommand::Admin(op),
+        };
+        utils::write_prefixed(&mut self.stream, &msg)?;
+        Ok(())
+    }
+
+    pub fn buy_in(&mut self, amount: Usd) -> Result<(), Error> {
+        let msg = ClientMessage {
+            username: self.username.clone(),
+            command: UserCommand::BuyIn(amount),
+        };
+        utils::write_prefixed(&mut self.stream, &msg)?;
+        Ok(())
+    }
+
     pub fn change_state(&mut self, state: UserState) -> Result<(), Error> {
         let msg = ClientMessage {
             username: self.username.clone(),
@@ -32,31 +96,99 @@ impl Client {
         Ok(())
     }
 
+    pub fn chat(&mut self, message: &str) -> Result<(), Error> {
+        let msg = ClientMessage {
+            username: self.username.clone(),
+            command: UserCommand::Chat(message.to_string()),
+        };
+        utils::write_prefixed(&mut self.stream, &msg)?;
+        Ok(())
+    }
+
+    /// Connect to the server at the address, which can be a `unix:` path
+    /// for servers on the same machine.
     pub fn connect(username: &str, addr: &str) -> Result<(Self, GameView), Error> {
-        let addr = addr.parse()?;
+        Client::connect_with_options(username, addr, &ConnectOptions::default())
+    }
+
+    /// Connect to a server that requires a password. Users are registered
+    /// with the password the first time they connect.
+    pub fn connect_with_password(
+        username: &str,
+        password: &str,
+        addr: &str,
+    ) -> Result<(Self, GameView), Error> {
+        let options = ConnectOptions {
+            password: Some(password.to_string()),
+            ..Default::default()
+        };
+        Client::connect_with_options(username, addr, &options)
+    }
+
+    pub fn connect_with_options(
+        username: &str,
+        addr: &str,
+        options: &ConnectOptions,
+    ) -> Result<(Self, GameView), Error> {
+        // Names the server would turn away aren't worth connecting with.
+        let username = Username::new(username)?;
+        let addr: Addr = addr.parse()?;
+        let command = match (&options.password, &options.session) {
+            (Some(password), _) => UserCommand::Authenticate(password.clone()),
+            (None, Some(session)) => UserCommand::Reconnect(session.clone()),
+            (None, None) => UserCommand::Connect,
+        };
         let mut connect_timeouts = vec![
             Duration::from_secs(1),
             Duration::from_millis(500),
             Duration::from_millis(100),
         ];
         while let Some(connect_timeout) = connect_timeouts.pop() {
-            match TcpStream::connect_timeout(&addr, connect_timeout) {
-                Ok(mut stream) => {
+            let connected = match options.transport {
+                Transport::Tcp => {
+                    Socket::connect_timeout(&addr, connect_timeout).map_err(Error::from)
+                }
+                #[cfg(feature = "quic")]
+                Transport::Quic => match (&options.tls, addr.as_tcp()) {
+                    (Some(tls), Some(tcp_addr)) => {
+                        quic::connect(tcp_addr, tls, connect_timeout).map(Socket::Tcp)
+                    }
+                    (None, _) => bail!("QUIC connections need TLS options"),
+                    (_, None) => bail!("QUIC connections need an IP address"),
+                },
+            };
+            match connected {
+                Ok(stream) => {
                     stream.set_read_timeout(Some(READ_TIMEOUT))?;
                     stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+                    // QUIC connections are already encrypted.
+                    let mut stream = match (&options.tls, options.transport, addr.as_tcp()) {
+                        (Some(tls), Transport::Tcp, Some(tcp_addr)) => {
+                            tls.connect(tcp_addr, stream)?
+                        }
+                        (Some(_), Transport::Tcp, None) => {
+                            bail!("TLS isn't supported over Unix sockets")
+                        }
+                        _ => Stream::Plain(stream),
+                    };
+                    if options.compress {
+                        stream.write_all(&utils::COMPRESSION_HANDSHAKE)?;
+                    }
                     let msg = ClientMessage {
-                        username: username.to_string(),
-                        command: UserCommand::Connect,
+                        username: username.clone(),
+                        command: command.clone(),
                     };
                     utils::write_prefixed(&mut stream, &msg)?;
+                    let session = Client::recv_session(&mut stream)?;
                     Client::recv_ack(&mut stream)?;
                     // Then receive the game view.
                     match Client::recv_view(&mut stream) {
                         Ok(view) => {
                             return Ok((
                                 Self {
-                                    username: username.to_string(),
+                                    username,
                                     addr: addr.to_string(),
+                                    session,
                                     stream,
                                 },
                                 view,
@@ -71,18 +203,176 @@ impl Client {
         bail!("couldn't connect to {addr} as {username}")
     }
 
+    pub fn create_table(&mut self, settings: GameSettings) -> Result<(), Error> {
+        let msg = ClientMessage {
+            username: self.username.clone(),
+            command: UserCommand::CreateTable(settings),
+        };
+        utils::write_prefixed(&mut self.stream, &msg)?;
+        Ok(())
+    }
+
+    pub fn join_table(&mut self, table_id: TableId) -> Result<(), Error> {
+        let msg = ClientMessage {
+            username: self.username.clone(),
+            command: UserCommand::JoinTable(table_id),
+        };
+        utils::write_prefixed(&mut self.stream, &msg)?;
+        Ok(())
+    }
+
+    /// Leave the game so the server removes the user right away instead
+    /// of holding their spot for them to reconnect.
+    pub fn leave(&mut self) -> Result<(), Error> {
+        let msg = ClientMessage {
+            username: self.username.clone(),
+            command: UserCommand::Leave,
+        };
+        utils::write_prefixed(&mut self.stream, &msg)?;
+        Ok(())
+    }
+
+    /// Ask for the users that have won the most at the server's cash
+    /// games, up to the given number of users.
+    pub fn leaderboard(&mut self, num_users: usize) -> Result<(), Error> {
+        let msg = ClientMessage {
+            username: self.username.clone(),
+            command: UserCommand::Leaderboard(num_users),
+        };
+        utils::write_prefixed(&mut self.stream, &msg)?;
+        Ok(())
+    }
+
+    pub fn leave_table(&mut self) -> Result<(), Error> {
+        let msg = ClientMessage {
+            username: self.username.clone(),
+            command: UserCommand::LeaveTable,
+        };
+        utils::write_prefixed(&mut self.stream, &msg)?;
+        Ok(())
+    }
+
+    pub fn list_hands(&mut self) -> Result<(), Error> {
+        let msg = ClientMessage {
+            username: self.username.clone(),
+            command: UserCommand::ListHands,
+        };
+        utils::write_prefixed(&mut self.stream, &msg)?;
+        Ok(())
+    }
+
+    pub fn list_tables(&mut self) -> Result<(), Error> {
+        let msg = ClientMessage {
+            username: self.username.clone(),
+            command: UserCommand::ListTables,
+        };
+        utils::write_prefixed(&mut self.stream, &msg)?;
+        Ok(())
+    }
+
+    pub fn muck_hand(&mut self) -> Result<(), Error> {
+        let msg = ClientMessage {
+            username: self.username.clone(),
+            command: UserCommand::MuckHand,
+        };
+        utils::write_prefixed(&mut self.stream, &msg)?;
+        Ok(())
+    }
+
+    /// Let an agent play for the user until the server closes the
+    /// connection, starting from the last view the user received. The
+    /// user should already be waitlisted at a table. Other messages from
+    /// the server are ignored, and actions the server rejects are replaced
+    /// with folds so the agent doesn't hold up the game.
+    pub fn play(
+        &mut self,
+        mut view: GameView,
+        agent: &mut (impl Agent + ?Sized),
+    ) -> Result<(), Error> {
+        // Agents can wait a long time between turns.
+        self.stream.get_ref().set_read_timeout(None)?;
+        let mut is_acting = false;
+        loop {
+            let msg = match utils::read_prefixed::<ServerMessage, Stream<Socket>>(&mut self.stream)
+            {
+                Ok(msg) => msg,
+                Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(error) => bail!(error),
+            };
+            match msg {
+                ServerMessage::Ping => self.pong()?,
+                ServerMessage::Ack(ClientMessage {
+                    username,
+                    command: UserCommand::TakeAction(_),
+                }) if username == self.username => is_acting = false,
+                ServerMessage::ClientError(error) => bail!(error),
+                ServerMessage::GameView(new_view) => view = *new_view,
+                // Agents don't need to show losing hands.
+                ServerMessage::ShowOrMuck => self.muck_hand()?,
+                ServerMessage::TurnSignal(action_options) => {
+                    let action = agent.act(&view, &action_options);
+                    self.take_action(action)?;
+                    is_acting = true;
+                }
+                ServerMessage::UserError(_) if is_acting => {
+                    self.take_action(Action::Fold)?;
+                    is_acting = false;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Answer a ping so the server knows the connection is still alive.
+    pub fn pong(&mut self) -> Result<(), Error> {
+        let msg = ClientMessage {
+            username: self.username.clone(),
+            command: UserCommand::Pong,
+        };
+        utils::write_prefixed(&mut self.stream, &msg)?;
+        Ok(())
+    }
+
+    /// Read the next message, answering any pings that come first.
     pub fn recv(&mut self) -> Result<ServerMessage, Error> {
-        match utils::read_prefixed::<ServerMessage, TcpStream>(&mut self.stream) {
+        loop {
+            match utils::read_prefixed::<ServerMessage, Stream<Socket>>(&mut self.stream) {
+                Ok(ServerMessage::ClientError(error)) => bail!(error),
+                Ok(ServerMessage::Ping) => self.pong()?,
+                Ok(ServerMessage::UserError(error)) => bail!(error),
+                Ok(msg) => return Ok(msg),
+                Err(error) => bail!(error),
+            }
+        }
+    }
+
+    /// Read the next message that isn't a ping. The `recv_*` helpers
+    /// don't know who to answer pings as, so clients that only use them
+    /// should call [`Client::pong`] themselves if they go quiet for long.
+    fn read_message(stream: &mut Stream<Socket>) -> io::Result<ServerMessage> {
+        loop {
+            match utils::read_prefixed(stream)? {
+                ServerMessage::Ping => {}
+                msg => return Ok(msg),
+            }
+        }
+    }
+
+    pub fn recv_ack(stream: &mut Stream<Socket>) -> Result<(), Error> {
+        match Client::read_message(stream) {
+            Ok(ServerMessage::Ack(_)) => Ok(()),
             Ok(ServerMessage::ClientError(error)) => bail!(error),
             Ok(ServerMessage::UserError(error)) => bail!(error),
-            Ok(msg) => Ok(msg),
+            Ok(response) => {
+                bail!("invalid server response: {response}")
+            }
             Err(error) => bail!(error),
         }
     }
 
-    pub fn recv_ack(stream: &mut TcpStream) -> Result<(), Error> {
-        match utils::read_prefixed::<ServerMessage, TcpStream>(stream) {
-            Ok(ServerMessage::Ack(_)) => Ok(()),
+    pub fn recv_chat(stream: &mut Stream<Socket>) -> Result<(Username, String), Error> {
+        match Client::read_message(stream) {
+            Ok(ServerMessage::Chat { username, message }) => Ok((username, message)),
             Ok(ServerMessage::ClientError(error)) => bail!(error),
             Ok(ServerMessage::UserError(error)) => bail!(error),
             Ok(response) => {
@@ -92,8 +382,8 @@ impl Client {
         }
     }
 
-    pub fn recv_client_error(stream: &mut TcpStream) -> Result<ClientError, Error> {
-        match utils::read_prefixed::<ServerMessage, TcpStream>(stream) {
+    pub fn recv_client_error(stream: &mut Stream<Socket>) -> Result<ClientError, Error> {
+        match Client::read_message(stream) {
             Ok(ServerMessage::ClientError(error)) => Ok(error),
             Ok(response) => {
                 bail!("invalid server response: {response}")
@@ -102,8 +392,56 @@ impl Client {
         }
     }
 
-    pub fn recv_user_error(stream: &mut TcpStream) -> Result<UserError, Error> {
-        match utils::read_prefixed::<ServerMessage, TcpStream>(stream) {
+    pub fn recv_hands(stream: &mut Stream<Socket>) -> Result<Vec<HandHistory>, Error> {
+        match Client::read_message(stream) {
+            Ok(ServerMessage::ClientError(error)) => bail!(error),
+            Ok(ServerMessage::Hands(hands)) => Ok(hands),
+            Ok(ServerMessage::UserError(error)) => bail!(error),
+            Ok(response) => {
+                bail!("invalid server response: {response}")
+            }
+            Err(error) => bail!(error),
+        }
+    }
+
+    pub fn recv_leaderboard(stream: &mut Stream<Socket>) -> Result<Vec<Standing>, Error> {
+        match Client::read_message(stream) {
+            Ok(ServerMessage::ClientError(error)) => bail!(error),
+            Ok(ServerMessage::Leaderboard(standings)) => Ok(standings),
+            Ok(ServerMessage::UserError(error)) => bail!(error),
+            Ok(response) => {
+                bail!("invalid server response: {response}")
+            }
+            Err(error) => bail!(error),
+        }
+    }
+
+    pub fn recv_session(stream: &mut Stream<Socket>) -> Result<SessionToken, Error> {
+        match Client::read_message(stream) {
+            Ok(ServerMessage::ClientError(error)) => bail!(error),
+            Ok(ServerMessage::Session(session)) => Ok(session),
+            Ok(ServerMessage::UserError(error)) => bail!(error),
+            Ok(response) => {
+                bail!("invalid server response: {response}")
+            }
+            Err(error) => bail!(error),
+        }
+    }
+
+    pub fn recv_tables(stream: &mut Stream<Socket>) -> Result<Vec<TableView>, Error> {
+        match Client::read_message(stream) {
+            Ok(ServerMessage::ClientError(error)) => bail!(error),
+            Ok(ServerMessage::Tables(tables)) => Ok(tables),
+            Ok(ServerMessage::UserError(error)) => bail!(error),
+            Ok(response) => {
+                bail!("invalid server response: {response}")
+            }
+            Err(error) => bail!(error),
+        }
+    }
+
+    pub fn recv_user_error(stream: &mut Stream<Socket>) -> Result<UserError, Error> {
+        match Client::read_message(stream) {
             Ok(ServerMessage::UserError(error)) => Ok(error),
             Ok(response) => {
                 bail!("invalid server response: {response}")
@@ -112,10 +450,10 @@ impl Client {
         }
     }
 
-    pub fn recv_view(stream: &mut TcpStream) -> Result<GameView, Error> {
-        match utils::read_prefixed::<ServerMessage, TcpStream>(stream) {
+    pub fn recv_view(stream: &mut Stream<Socket>) -> Result<GameView, Error> {
+        match Client::read_message(stream) {
             Ok(ServerMessage::ClientError(error)) => bail!(error),
-            Ok(ServerMessage::GameView(view)) => Ok(view),
+            Ok(ServerMessage::GameView(view)) => Ok(*view),
             Ok(ServerMessage::UserError(error)) => bail!(error),
             Ok(response) => {
                 bail!("invalid server response: {response}")
@@ -124,9 +462,18 @@ impl Client {
         }
     }
 
+    pub fn run_it_twice(&mut self, consent: bool) -> Result<(), Error> {
+        let msg = ClientMessage {
+            username: self.username.clone(),
+            command: UserCommand::RunItTwice(consent),
+        };
+        utils::write_prefixed(&mut self.stream, &msg)?;
+        Ok(())
+    }
+
     pub fn show_hand(&mut self) -> Result<(), Error> {
         let msg = ClientMessage {
-            username: self.username.to_string(),
+            username: self.username.clone(),
             command: UserCommand::ShowHand,
         };
         utils::write_prefixed(&mut self.stream, &msg)?;
@@ -135,7 +482,7 @@ impl Client {
 
     pub fn start_game(&mut self) -> Result<(), Error> {
         let msg = ClientMessage {
-            username: self.username.to_string(),
+            username: self.username.clone(),
             command: UserCommand::StartGame,
         };
         utils::write_prefixed(&mut self.stream, &msg)?;
@@ -144,10 +491,28 @@ impl Client {
 
     pub fn take_action(&mut self, action: Action) -> Result<(), Error> {
         let msg = ClientMessage {
-            username: self.username.to_string(),
+            username: self.username.clone(),
             command: UserCommand::TakeAction(action),
         };
         utils::write_prefixed(&mut self.stream, &msg)?;
         Ok(())
     }
+
+    pub fn take_seat(&mut self, seat_idx: usize) -> Result<(), Error> {
+        let msg = ClientMessage {
+            username: self.username.clone(),
+            command: UserCommand::TakeSeat(seat_idx),
+        };
+        utils::write_prefixed(&mut self.stream, &msg)?;
+        Ok(())
+    }
+
+    pub fn vote_bomb_pot(&mut self, vote: bool) -> Result<(), Error> {
+        let msg = ClientMessage {
+            username: self.username.clone(),
+            command: UserCommand::BombPot(vote),
+        };
+        utils::write_prefixed(&mut self.stream, &msg)?;
+        Ok(())
+    }
 }
@@ -0,0 +1,190 @@
+//! A console for running a server without joining it.
+//!
+//! Servers can listen for console connections on a Unix socket or a
+//! loopback address. Each connection sends one [`ControlRequest`] and
+//! gets one [`ControlResponse`] back, framed like the rest of the wire
+//! protocol. Anyone that can reach the console can run the server, so
+//! it's kept off the network, and Unix sockets are permissioned with
+//! filesystem modes like any other file.
+
+use anyhow::{bail, Error};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    io::{self, Write},
+    net::TcpListener,
+    thread,
+    time::Duration,
+};
+#[cfg(unix)]
+use std::{fs, os::unix::net::UnixListener, path::PathBuf};
+
+use crate::game::entities::Username;
+
+use super::{
+    messages::{AdminOp, RakeLedger, TableId},
+    persistence::TableSnapshot,
+    socket::{Addr, Socket},
+    utils,
+};
+
+/// Name the console goes by when it tells users what it did. Users
+/// can't connect with it.
+pub const CONSOLE_USERNAME: &str = "console";
+/// How long either side of a console connection waits on the other.
+pub const CONSOLE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Something for the server to do.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ControlRequest {
+    /// Run an admin operation as if an admin sent it.
+    Admin(AdminOp),
+    /// Get every table's settings and users.
+    Dump,
+    /// List the users at each table.
+    Users,
+}
+
+/// What the server did about a request.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ControlResponse {
+    Done,
+    Dump(BTreeMap<TableId, TableSnapshot>),
+    Error(String),
+    Rake(RakeLedger),
+    Users(BTreeMap<TableId, BTreeSet<Username>>),
+}
+
+impl fmt::Display for ControlResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControlResponse::Done => write!(f, "done"),
+            ControlResponse::Dump(tables) => {
+                let dump = serde_json::to_string_pretty(tables).map_err(|_| fmt::Error)?;
+                write!(f, "{dump}")
+            }
+            ControlResponse::Error(error) => write!(f, "error: {error}"),
+            ControlResponse::Rake(ledger) => write!(f, "{ledger}"),
+            ControlResponse::Users(tables) => {
+                let lines: Vec<_> = tables
+                    .iter()
+                    .map(|(table_id, usernames)| {
+                        let usernames: Vec<_> = usernames.iter().map(Username::as_str).collect();
+                        format!("table {table_id}: {}", usernames.join(", "))
+                    })
+                    .collect();
+                write!(f, "{}", lines.join("\n"))
+            }
+        }
+    }
+}
+
+/// Keeps the console listening. A Unix socket's file is removed when
+/// this is dropped.
+#[derive(Debug)]
+pub struct Console {
+    #[cfg(unix)]
+    path: Option<PathBuf>,
+}
+
+#[cfg(unix)]
+impl Drop for Console {
+    fn drop(&mut self) {
+        if let Some(ref path) = self.path {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Start answering console requests at the address with the handler.
+/// Requests are answered one at a time on a thread of their own.
+pub fn spawn(
+    addr: &Addr,
+    handle: impl Fn(ControlRequest) -> ControlResponse + Send + 'static,
+) -> Result<Console, Error> {
+    match addr {
+        Addr::Tcp(addr) => {
+            if !addr.ip().is_loopback() {
+                bail!("the console can only listen on loopback addresses");
+            }
+            let listener = TcpListener::bind(addr)?;
+            thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    // Clients that go away mid-request don't need a
+                    // response.
+                    let _ = respond(Socket::Tcp(stream), &handle);
+                }
+            });
+            Ok(Console {
+                #[cfg(unix)]
+                path: None,
+            })
+        }
+        #[cfg(unix)]
+        Addr::Unix(path) => {
+            let listener = UnixListener::bind(path)?;
+            thread::spawn(move || {
+                for stream in listener.incoming().flatten() {
+                    let _ = respond(Socket::Unix(stream), &handle);
+                }
+            });
+            Ok(Console {
+                path: Some(path.clone()),
+            })
+        }
+        #[cfg(unix)]
+        Addr::Fd(_) => bail!("the console can't listen on inherited sockets"),
+    }
+}
+
+fn respond(
+    mut socket: Socket,
+    handle: &impl Fn(ControlRequest) -> ControlResponse,
+) -> io::Result<()> {
+    socket.set_read_timeout(Some(CONSOLE_TIMEOUT))?;
+    socket.set_write_timeout(Some(CONSOLE_TIMEOUT))?;
+    let request = utils::read_prefixed(&mut socket)?;
+    utils::write_prefixed(&mut socket, &handle(request))?;
+    socket.flush()
+}
+
+/// Send a request to a server's console and wait for its response.
+pub fn request(addr: &Addr, request: &ControlRequest) -> Result<ControlResponse, Error> {
+    let mut socket = Socket::connect_timeout(addr, CONSOLE_TIMEOUT)?;
+    socket.set_read_timeout(Some(CONSOLE_TIMEOUT))?;
+    utils::write_prefixed(&mut socket, request)?;
+    Ok(utils::read_prefixed(&mut socket)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::{request, spawn, ControlRequest, ControlResponse};
+    use crate::net::{messages::AdminOp, socket::Addr};
+
+    #[test]
+    fn answer_requests() {
+        // Find a free port to listen on.
+        let addr: Addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .into();
+        let _console = spawn(&addr, |request| match request {
+            ControlRequest::Admin(AdminOp::Pause(0)) => ControlResponse::Done,
+            _ => ControlResponse::Error("no".to_string()),
+        })
+        .unwrap();
+
+        let response = request(&addr, &ControlRequest::Admin(AdminOp::Pause(0))).unwrap();
+        assert_eq!(response, ControlResponse::Done);
+        let response = request(&addr, &ControlRequest::Users).unwrap();
+        assert_eq!(response, ControlResponse::Error("no".to_string()));
+
+        // Only loopback addresses are allowed.
+        let addr: Addr = "0.0.0.0:0".parse().unwrap();
+        assert!(spawn(&addr, |_| ControlResponse::Done).is_err());
+    }
+}
@@ -1,22 +1,181 @@
 use serde::{Deserialize, Serialize};
-use std::{collections::HashSet, fmt};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+};
 
 pub use crate::game::entities::GameView;
 use crate::game::{
-    entities::{Action, Username},
-    Game, TakeAction, UserError,
+    entities::{Action, ChipDisplay, Dollars, HandHistory, Showdown, Usd, Username, UsernameError},
+    Game, GameSettings, TakeAction, UserError,
 };
 
+/// Identifies a table hosted by the server.
+pub type TableId = usize;
+
+/// Proof that a user is whoever connected with their username, given to
+/// them by the server each time they connect. Users whose connection
+/// drops send it with [`UserCommand::Reconnect`] to take back their spot,
+/// wherever they're connecting from.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct SessionToken(String);
+
+impl SessionToken {
+    /// Make a token nobody else can guess.
+    #[cfg(feature = "server")]
+    pub(crate) fn generate() -> Self {
+        Self(format!("{:032x}", rand::random::<u128>()))
+    }
+}
+
+/// A summary of a table so users can decide which table to join.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TableView {
+    pub id: TableId,
+    pub settings: GameSettings,
+    pub num_users: usize,
+}
+
+impl fmt::Display for TableView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Self {
+            id,
+            settings,
+            num_users,
+        } = self;
+        write!(
+            f,
+            "table {id} ({} buy-in, {}/{} blinds, {num_users}/{} users",
+            settings.chips(settings.buy_in),
+            settings.chips(settings.min_big_blind),
+            settings.chips(settings.min_small_blind),
+            settings.max_users,
+        )?;
+        if let Some(ref rake) = settings.rake {
+            write!(f, ", {}% rake", rake.percent)?;
+        }
+        if settings.jackpot.is_some() {
+            write!(f, ", bad beat jackpot")?;
+        }
+        if settings.tournament.is_some() {
+            write!(f, ", tournament")?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Rake the server has collected since it started, in total and by
+/// table.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RakeLedger {
+    pub total: Usd,
+    pub tables: BTreeMap<TableId, Usd>,
+}
+
+impl fmt::Display for RakeLedger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} raked", Dollars(self.total))?;
+        for (table_id, rake) in self.tables.iter() {
+            write!(f, ", {} at table {table_id}", Dollars(*rake))?;
+        }
+        Ok(())
+    }
+}
+
+/// A user's results across every cash game hand they've played at the
+/// server, including hands played before the server last restarted.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Standing {
+    pub username: Username,
+    /// Money won minus money put into pots, not counting buy-ins.
+    pub profit: i64,
+    pub hands: usize,
+    /// Size of the biggest pot the user won or split.
+    pub biggest_pot: Usd,
+}
+
+impl fmt::Display for Standing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.profit < 0 { "-" } else { "+" };
+        let profit = Usd::try_from(self.profit.unsigned_abs()).unwrap_or(Usd::MAX);
+        write!(
+            f,
+            "{}: {sign}{} over {} hand(s), biggest pot {}",
+            self.username,
+            Dollars(profit),
+            self.hands,
+            Dollars(self.biggest_pot)
+        )
+    }
+}
+
+/// Moderation operations that only admins can perform.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum AdminOp {
+    /// Ban the user's username and IP address, disconnecting them.
+    Ban(Username),
+    /// Fold for the user if it's their turn, so a stalled hand can
+    /// progress without waiting for the action timeout.
+    ForceFold(Username),
+    /// Disconnect the user. They're free to reconnect.
+    Kick(Username),
+    /// Stop dealing hands at the table once the hand being played
+    /// finishes.
+    Pause(TableId),
+    /// Show how much rake the server has collected.
+    Rake,
+    /// Start dealing hands at a paused table again.
+    Resume(TableId),
+    /// Change the table's minimum blinds, starting next hand.
+    SetBlinds {
+        table_id: TableId,
+        small_blind: Usd,
+        big_blind: Usd,
+    },
+}
+
+impl fmt::Display for AdminOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdminOp::Ban(username) => write!(f, "banned {username}"),
+            AdminOp::ForceFold(username) => write!(f, "forced {username} to fold"),
+            AdminOp::Kick(username) => write!(f, "kicked {username}"),
+            AdminOp::Pause(table_id) => write!(f, "paused table {table_id}"),
+            AdminOp::Rake => write!(f, "checked the rake"),
+            AdminOp::Resume(table_id) => write!(f, "resumed table {table_id}"),
+            AdminOp::SetBlinds {
+                table_id,
+                small_blind,
+                big_blind,
+            } => write!(
+                f,
+                "set the blinds at table {table_id} to {}/{}",
+                Dollars(*small_blind),
+                Dollars(*big_blind)
+            ),
+        }
+    }
+}
+
 /// Errors due to the poker client's interaction with the poker server
 /// and not from the user's particular action.
 #[derive(Debug, Deserialize, Eq, thiserror::Error, PartialEq, Serialize)]
 pub enum ClientError {
     #[error("already associated")]
     AlreadyAssociated,
+    #[error("authentication failed")]
+    AuthenticationFailed,
+    #[error("banned")]
+    Banned,
     #[error("does not exist")]
     DoesNotExist,
     #[error("expired")]
     Expired,
+    #[error(transparent)]
+    InvalidUsername(#[from] UsernameError),
+    #[error("spectators full")]
+    SpectatorsFull,
     #[error("unassociated")]
     Unassociated,
 }
@@ -25,6 +184,9 @@ pub enum ClientError {
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum UserState {
     Play,
+    /// Keep a seat without being dealt in. Players come back by asking
+    /// to play again.
+    SitOut,
     Spectate,
 }
 
@@ -32,6 +194,7 @@ impl fmt::Display for UserState {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let repr = match self {
             UserState::Play => "waitlister",
+            UserState::SitOut => "player sitting out",
             UserState::Spectate => "spectator",
         };
         write!(f, "{repr}")
@@ -41,14 +204,65 @@ impl fmt::Display for UserState {
 /// A user command.
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum UserCommand {
+    /// An admin wants to moderate another user. The server ignores
+    /// these from users that aren't admins.
+    Admin(AdminOp),
+    /// A new user wants to connect to a server that requires a password.
+    /// The server checks the password and then treats this as a
+    /// [`UserCommand::Connect`], so the password is never relayed to
+    /// other users.
+    Authenticate(String),
+    /// The user votes for (or against) the next hand being a bomb pot.
+    /// The next hand is a bomb pot once every player dealt into it has
+    /// voted for one.
+    BombPot(bool),
+    /// The user wants to buy more money, either to rebuy after going
+    /// broke or to top up a short stack. Players in the middle of a hand
+    /// get the money once the hand is over.
+    BuyIn(Usd),
     /// The user wants to change their state (play or spectate).
     ChangeState(UserState),
-    /// A new user wants to connect to the game.
+    /// The user wants to send a chat message to everyone at their
+    /// table, players and spectators alike.
+    Chat(String),
+    /// A new user wants to connect to the server. New users join the
+    /// server's first table.
     Connect,
+    /// User wants to create a new table with the given settings.
+    CreateTable(GameSettings),
+    /// User wants to join a specific table, leaving the table they're
+    /// currently at.
+    JoinTable(TableId),
+    /// User wants to see the users that have won the most at the
+    /// server's cash games, up to the given number of users.
+    Leaderboard(usize),
     /// User wants to leave the game. This is really just a
     /// friendly courtesy and doesn't need to be sent by
     /// clients.
     Leave,
+    /// User wants to leave their table without disconnecting from
+    /// the server.
+    LeaveTable,
+    /// User wants to see the most recent hands played at their table.
+    ListHands,
+    /// User wants to list all the tables hosted by the server.
+    ListTables,
+    /// User lost at showdown and wants to muck their hand rather than
+    /// show it. Hands are mucked by default, so this just settles the
+    /// choice before the [`ServerMessage::ShowOrMuck`] window ends.
+    MuckHand,
+    /// Reply to a [`ServerMessage::Ping`] showing the client's connection
+    /// is still alive.
+    Pong,
+    /// A user whose connection dropped wants their spot back. The server
+    /// checks the token against the one it gave them and then treats this
+    /// as a [`UserCommand::Connect`], so the token is never relayed to
+    /// other users.
+    Reconnect(SessionToken),
+    /// User agrees (or declines) to run the rest of the board twice.
+    /// Can only occur if they're still in an all-in hand and the rest
+    /// of the board hasn't been dealt yet.
+    RunItTwice(bool),
     /// User wants to show their hand. Can only occur if they're
     /// a player and the game is in a state that allows hands to
     /// be shown.
@@ -59,17 +273,40 @@ pub enum UserCommand {
     /// User wants to make a bet. Can only occur if they're a
     /// player and it's their turn.
     TakeAction(Action),
+    /// The user wants to play in a particular seat. They're waitlisted
+    /// like with [`UserState::Play`], keeping their place in line until
+    /// the seat opens up.
+    TakeSeat(usize),
 }
 
 impl fmt::Display for UserCommand {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let repr = match &self {
+            UserCommand::Admin(op) => &op.to_string(),
+            UserCommand::Authenticate(_) => "authenticated",
+            UserCommand::BombPot(true) => "voted for a bomb pot",
+            UserCommand::BombPot(false) => "voted against a bomb pot",
+            UserCommand::BuyIn(amount) => &format!("bought in for {}", Dollars(*amount)),
+            UserCommand::ChangeState(UserState::SitOut) => "sat out",
             UserCommand::ChangeState(state) => &format!("joined the {state}s"),
+            UserCommand::Chat(message) => &format!("said {message:?}"),
             UserCommand::Connect => "connected",
+            UserCommand::CreateTable(_) => "created a table",
+            UserCommand::JoinTable(id) => &format!("joined table {id}"),
+            UserCommand::Leaderboard(_) => "checked the leaderboard",
             UserCommand::Leave => "left the game",
+            UserCommand::LeaveTable => "left the table",
+            UserCommand::ListHands => "listed recent hands",
+            UserCommand::ListTables => "listed the tables",
+            UserCommand::MuckHand => "mucked their hand",
+            UserCommand::Pong => "is still connected",
+            UserCommand::Reconnect(_) => "reconnected",
+            UserCommand::RunItTwice(true) => "agreed to run it twice",
+            UserCommand::RunItTwice(false) => "declined to run it twice",
             UserCommand::ShowHand => "showed their hand",
             UserCommand::StartGame => "started the game",
-            UserCommand::TakeAction(action) => &action.to_action_string(),
+            UserCommand::TakeAction(action) => &action.to_action_string(ChipDisplay::default()),
+            UserCommand::TakeSeat(seat_idx) => &format!("asked for seat {seat_idx}"),
         };
         write!(f, "{repr}")
     }
@@ -91,19 +328,59 @@ impl fmt::Display for ClientMessage {
     }
 }
 
+impl ClientMessage {
+    /// Same as the message's display, but with amounts shown the way the
+    /// table shows them.
+    pub fn to_chips_string(&self, chip_display: ChipDisplay) -> String {
+        let command = match self.command {
+            UserCommand::BuyIn(amount) => format!("bought in for {}", chip_display.chips(amount)),
+            UserCommand::TakeAction(ref action) => action.to_action_string(chip_display),
+            ref command => command.to_string(),
+        };
+        format!("{} {command}", self.username)
+    }
+}
+
 /// A message from the poker server to a poker client.
 #[derive(Debug, Deserialize, Serialize)]
 pub enum ServerMessage {
     /// An acknowledgement of a client message, signaling that the client's
     /// command was successfully processed by the game thread.
     Ack(ClientMessage),
+    /// A chat message from a user at the same table.
+    Chat { username: Username, message: String },
     /// An indication that the poker client caused an error, resulting in
     /// the client's message not being processed correctly.
     ClientError(ClientError),
     /// The game state as viewed from the client's perspective.
-    GameView(GameView),
+    GameView(Box<GameView>),
+    /// The most recent hands played at the user's table, oldest first.
+    Hands(Vec<HandHistory>),
+    /// The users that have won the most at the server's cash games, best
+    /// first.
+    Leaderboard(Vec<Standing>),
+    /// Sent every heartbeat to check the connection is still alive.
+    /// Clients that don't reply with [`UserCommand::Pong`] (or any other
+    /// message) for a few heartbeats are treated as disconnected.
+    Ping,
+    /// Rake the server has collected, sent to admins that ask for it.
+    Rake(RakeLedger),
+    /// The token the user can reconnect with if their connection drops,
+    /// sent right before the acknowledgement of their
+    /// [`UserCommand::Connect`].
+    Session(SessionToken),
+    /// The hands revealed at the end of a hand and who won which pot,
+    /// sent to everyone at the table when a hand goes to showdown.
+    Showdown(Showdown),
+    /// A signal that the user lost at showdown and can show their hand
+    /// with [`UserCommand::ShowHand`] or muck it with
+    /// [`UserCommand::MuckHand`] before the next hand. Hands that aren't
+    /// shown are mucked.
+    ShowOrMuck,
     /// The game state represented as a string.
     Status(String),
+    /// Summaries of all the tables hosted by the server.
+    Tables(Vec<TableView>),
     /// A sginal indicating that it's the user's turn.
     TurnSignal(HashSet<Action>),
     /// An indication that the poker client sent a message that was read
@@ -116,11 +393,30 @@ impl fmt::Display for ServerMessage {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let repr = match &self {
             ServerMessage::Ack(msg) => msg.to_string(),
+            ServerMessage::Chat { username, message } => format!("{username}: {message}"),
             ServerMessage::ClientError(error) => error.to_string(),
             ServerMessage::GameView(_) => "game view".to_string(),
+            ServerMessage::Hands(hands) => format!("{} recent hand(s)", hands.len()),
+            ServerMessage::Leaderboard(standings) => standings
+                .iter()
+                .map(|standing| standing.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            ServerMessage::Ping => "ping".to_string(),
+            ServerMessage::Rake(ledger) => ledger.to_string(),
+            ServerMessage::Session(_) => "session".to_string(),
+            ServerMessage::Showdown(showdown) => showdown.to_string(),
+            ServerMessage::ShowOrMuck => {
+                "you lost at showdown; show or muck your hand (mucked by default)".to_string()
+            }
             ServerMessage::Status(status) => status.to_string(),
+            ServerMessage::Tables(tables) => tables
+                .iter()
+                .map(|table| table.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
             ServerMessage::TurnSignal(action_options) => {
-                Game::<TakeAction>::action_options_to_string(action_options)
+                Game::<TakeAction>::action_options_to_string(action_options, ChipDisplay::default())
             }
             ServerMessage::UserError(error) => error.to_string(),
         };
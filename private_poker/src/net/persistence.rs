@@ -0,0 +1,604 @@
+//! Persisting tables across server restarts and recording finished
+//! hands.
+
+use anyhow::{bail, Error};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    convert,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::game::{
+    entities::{ChipDisplay, GameSnapshot, HandHistory, Rank, Usd, Usdf, User, Username},
+    GameSettings, JackpotSettings, RakeSettings, TournamentSettings,
+};
+
+use super::messages::{Standing, TableId};
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+/// A table's settings and users, saved so the table can be restored
+/// when the server restarts.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TableSnapshot {
+    pub settings: GameSettings,
+    pub game: GameSnapshot,
+}
+
+/// [`TableSnapshot`] as it was saved before files had versions, when
+/// money was counted in whole dollars. These types are frozen so old
+/// files keep loading no matter how the current ones change, since
+/// bincode only knows a value's layout from the type it's read into.
+#[derive(Deserialize)]
+struct TableSnapshotV1 {
+    settings: GameSettingsV1,
+    game: GameSnapshotV1,
+}
+
+#[derive(Deserialize)]
+struct GameSettingsV1 {
+    buy_in: Usd,
+    min_buy_in: Usd,
+    max_buy_in: Usd,
+    min_big_blind: Usd,
+    min_small_blind: Usd,
+    min_ante: Usd,
+    max_players: usize,
+    max_users: usize,
+    max_spectators: usize,
+    max_rebuys: usize,
+    rake: Option<RakeSettingsV1>,
+    jackpot: Option<JackpotSettingsV1>,
+    tournament: Option<TournamentSettingsV1>,
+    turn_timeout: Duration,
+    seed: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct RakeSettingsV1 {
+    percent: u8,
+    cap: Option<Usd>,
+    no_flop_no_drop: bool,
+}
+
+#[derive(Deserialize)]
+struct JackpotSettingsV1 {
+    drop: Usd,
+    min_rank: Rank,
+}
+
+#[derive(Deserialize)]
+struct TournamentSettingsV1 {
+    level_duration: Duration,
+}
+
+#[derive(Deserialize)]
+struct GameSnapshotV1 {
+    donations: Usdf,
+    spectators: Vec<User>,
+    waitlist: Vec<User>,
+    players: Vec<(usize, User)>,
+    jackpot: Usd,
+}
+
+impl From<TableSnapshotV1> for TableSnapshot {
+    fn from(value: TableSnapshotV1) -> Self {
+        let TableSnapshotV1 { settings, game } = value;
+        let mut settings = GameSettings {
+            buy_in: settings.buy_in,
+            min_buy_in: settings.min_buy_in,
+            max_buy_in: settings.max_buy_in,
+            min_big_blind: settings.min_big_blind,
+            min_small_blind: settings.min_small_blind,
+            min_ante: settings.min_ante,
+            is_big_blind_ante: false,
+            max_players: settings.max_players,
+            max_users: settings.max_users,
+            max_spectators: settings.max_spectators,
+            max_rebuys: settings.max_rebuys,
+            rake: settings.rake.map(|rake| RakeSettings {
+                percent: rake.percent,
+                cap: rake.cap,
+                no_flop_no_drop: rake.no_flop_no_drop,
+            }),
+            jackpot: settings
+                .jackpot
+                .map(|jackpot| JackpotSettings::new(jackpot.drop, jackpot.min_rank)),
+            bomb_pot: None,
+            tournament: settings
+                .tournament
+                .map(|tournament| TournamentSettings::new(tournament.level_duration)),
+            turn_timeout: settings.turn_timeout,
+            seed: settings.seed,
+            chip_display: ChipDisplay::default(),
+        };
+        let mut game = GameSnapshot {
+            donations: game.donations,
+            spectators: game.spectators,
+            waitlist: game.waitlist,
+            players: game.players,
+            jackpot: game.jackpot,
+        };
+        settings.dollars_to_cents();
+        game.dollars_to_cents();
+        Self { settings, game }
+    }
+}
+
+/// Every user's results across all the cash game hands played at the
+/// server.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Leaderboard {
+    standings: BTreeMap<Username, Standing>,
+}
+
+impl Leaderboard {
+    /// Tally a finished hand for everyone dealt into it.
+    pub fn record_hand(&mut self, hand: &HandHistory) {
+        for (_, user) in hand.players.iter() {
+            let standing = self
+                .standings
+                .entry(user.name.clone())
+                .or_insert_with(|| Standing {
+                    username: user.name.clone(),
+                    ..Default::default()
+                });
+            standing.hands += 1;
+            standing.profit += hand
+                .audit
+                .stack_deltas
+                .get(&user.name)
+                .copied()
+                .unwrap_or_default();
+        }
+        for pot in hand.pots.iter() {
+            for username in pot.winners.iter() {
+                if let Some(standing) = self.standings.get_mut(username) {
+                    standing.biggest_pot = standing.biggest_pot.max(pot.size);
+                }
+            }
+        }
+    }
+
+    /// Return the users that have won the most, best first. Users that
+    /// have won the same amount are ranked by who's played more hands.
+    pub fn top(&self, num_users: usize) -> Vec<Standing> {
+        let mut standings: Vec<_> = self.standings.values().collect();
+        standings.sort_by(|a, b| b.profit.cmp(&a.profit).then(b.hands.cmp(&a.hands)));
+        standings.into_iter().take(num_users).cloned().collect()
+    }
+}
+
+/// Storage backend for persisting tables and the leaderboard.
+pub trait Store: Send {
+    /// Load all the saved tables.
+    fn load(&mut self) -> Result<BTreeMap<TableId, TableSnapshot>, Error>;
+
+    /// Save a table, replacing its previous snapshot.
+    fn save(&mut self, table_id: TableId, snapshot: TableSnapshot) -> Result<(), Error>;
+
+    /// Load the saved leaderboard, or an empty one if there isn't one.
+    fn load_leaderboard(&mut self) -> Result<Leaderboard, Error>;
+
+    /// Save the leaderboard, replacing the previous one.
+    fn save_leaderboard(&mut self, leaderboard: &Leaderboard) -> Result<(), Error>;
+}
+
+/// Stores all tables in a single file, and the leaderboard next to it
+/// with a `.leaderboard` extension. Files are rewritten on every save, so
+/// a crash mid-write never corrupts what was previously saved.
+pub struct FileStore {
+    path: PathBuf,
+    tables: BTreeMap<TableId, TableSnapshot>,
+}
+
+impl FileStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            tables: BTreeMap::new(),
+        }
+    }
+
+    fn leaderboard_path(&self) -> PathBuf {
+        self.path.with_extension("leaderboard")
+    }
+}
+
+/// Saved files start with this, followed by the version they were
+/// saved with. Files saved before there were versions start right with
+/// the saved value.
+const SAVE_HEADER: &[u8; 4] = b"PPSV";
+
+/// Version of the saved files' format, bumped whenever what's saved
+/// changes. Version 1 is the headerless files, where tables count money
+/// in whole dollars, and version 2 counts money in cents. Leaderboards
+/// came after cents, so version 1 leaderboards are already in cents.
+const SAVE_VERSION: u32 = 2;
+
+/// Read a value saved with [`write_atomically`], or return nothing if
+/// it hasn't been saved yet. Files saved before there were versions are
+/// read with their old layout and migrated.
+fn read_saved<T, V1>(path: &Path, migrate: impl FnOnce(V1) -> T) -> Result<Option<T>, Error>
+where
+    T: for<'de> Deserialize<'de>,
+    V1: for<'de> Deserialize<'de>,
+{
+    let saved = match fs::read(path) {
+        Ok(saved) => saved,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error.into()),
+    };
+    let Some(saved) = saved.strip_prefix(SAVE_HEADER) else {
+        return Ok(Some(migrate(bincode::deserialize(&saved)?)));
+    };
+    let (version, value): (u32, T) = bincode::deserialize(saved)?;
+    if version > SAVE_VERSION {
+        bail!(
+            "{} was saved by a newer version (format {version})",
+            path.display()
+        );
+    }
+    Ok(Some(value))
+}
+
+/// Write the value to a temporary file first and then move it into
+/// place, so readers only ever see complete saves.
+fn write_atomically(path: &Path, tmp_path: &Path, value: &impl Serialize) -> Result<(), Error> {
+    let mut file = BufWriter::new(File::create(tmp_path)?);
+    file.write_all(SAVE_HEADER)?;
+    bincode::serialize_into(&mut file, &(SAVE_VERSION, value))?;
+    file.flush()?;
+    fs::rename(tmp_path, path)?;
+    Ok(())
+}
+
+impl Store for FileStore {
+    fn load(&mut self) -> Result<BTreeMap<TableId, TableSnapshot>, Error> {
+        let migrate = |tables: BTreeMap<TableId, TableSnapshotV1>| {
+            tables
+                .into_iter()
+                .map(|(table_id, snapshot)| (table_id, snapshot.into()))
+                .collect()
+        };
+        self.tables = read_saved(&self.path, migrate)?.unwrap_or_default();
+        Ok(self.tables.clone())
+    }
+
+    fn save(&mut self, table_id: TableId, snapshot: TableSnapshot) -> Result<(), Error> {
+        self.tables.insert(table_id, snapshot);
+        write_atomically(&self.path, &self.path.with_extension("tmp"), &self.tables)
+    }
+
+    fn load_leaderboard(&mut self) -> Result<Leaderboard, Error> {
+        let leaderboard = read_saved(&self.leaderboard_path(), convert::identity)?;
+        Ok(leaderboard.unwrap_or_default())
+    }
+
+    fn save_leaderboard(&mut self, leaderboard: &Leaderboard) -> Result<(), Error> {
+        let path = self.leaderboard_path();
+        write_atomically(&path, &path.with_extension("leaderboard.tmp"), leaderboard)
+    }
+}
+
+/// A line of a history file. Each variant is a version of the hand
+/// history, like [`crate::game::GameCheckpoint`]. Lines written before
+/// there were versions are a bare hand history that counts money in
+/// whole dollars.
+#[derive(Deserialize, Serialize)]
+enum HandRecord<H = HandHistory> {
+    /// Money counted in cents.
+    V2(H),
+}
+
+/// Append a finished hand to a history file, creating the file if it
+/// doesn't exist. Each hand is written as a single line of JSON so
+/// tables sharing a file never interleave their records.
+pub fn append_hand_history(path: impl AsRef<Path>, hand: &HandHistory) -> Result<(), Error> {
+    let mut line = serde_json::to_string(&HandRecord::V2(hand))?;
+    line.push('\n');
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// Read all the hands recorded in a history file, oldest first.
+pub fn read_hand_history(path: impl AsRef<Path>) -> Result<Vec<HandHistory>, Error> {
+    let file = File::open(path)?;
+    let mut hands = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if line.is_empty() {
+            continue;
+        }
+        let hand = match serde_json::from_str::<HandRecord>(&line) {
+            Ok(HandRecord::V2(hand)) => hand,
+            Err(_) => {
+                let mut hand: HandHistory = serde_json::from_str(&line)?;
+                hand.dollars_to_cents();
+                hand
+            }
+        };
+        hands.push(hand);
+    }
+    Ok(hands)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process, time::Duration};
+
+    use crate::game::{
+        entities::{
+            Action, GameSnapshot, HandAction, HandAudit, HandHistory, PotResult, Rank, Street, Usd,
+            User,
+        },
+        GameSettings, JackpotSettings, RakeSettings,
+    };
+
+    use super::{
+        append_hand_history, read_hand_history, FileStore, Leaderboard, Store, TableSnapshot,
+    };
+
+    /// A heads-up hand where the second player wins the first player's
+    /// money.
+    pub(super) fn heads_up_hand(loser: &str, winner: &str, amount: Usd) -> HandHistory {
+        HandHistory {
+            players: [loser, winner]
+                .into_iter()
+                .enumerate()
+                .map(|(seat_idx, username)| {
+                    let user = User {
+                        name: username.into(),
+                        money: 200,
+                    };
+                    (seat_idx, user)
+                })
+                .collect(),
+            pots: vec![PotResult {
+                board_idx: 0,
+                size: 2 * amount,
+                winners: vec![winner.into()],
+            }],
+            audit: HandAudit {
+                stack_deltas: [
+                    (loser.into(), -i64::from(amount)),
+                    (winner.into(), i64::from(amount)),
+                ]
+                .into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rank_leaderboard() {
+        let mut leaderboard = Leaderboard::default();
+        leaderboard.record_hand(&heads_up_hand("0", "1", 50));
+        leaderboard.record_hand(&heads_up_hand("1", "0", 10));
+        leaderboard.record_hand(&heads_up_hand("2", "0", 5));
+
+        let standings = leaderboard.top(2);
+        assert_eq!(standings.len(), 2);
+        assert_eq!(standings[0].username, "1");
+        assert_eq!((standings[0].profit, standings[0].hands), (40, 2));
+        assert_eq!(standings[0].biggest_pot, 100);
+        assert_eq!(standings[1].username, "2");
+        assert_eq!((standings[1].profit, standings[1].hands), (-5, 1));
+        assert_eq!(standings[1].biggest_pot, 0);
+
+        // Users that won the same are ranked by hands played.
+        leaderboard.record_hand(&heads_up_hand("3", "0", 75));
+        let standings = leaderboard.top(10);
+        assert_eq!(standings.len(), 4);
+        assert_eq!(standings[0].username, "0");
+        assert_eq!((standings[0].profit, standings[0].hands), (40, 4));
+        assert_eq!(standings[0].biggest_pot, 150);
+        assert_eq!(standings[1].username, "1");
+        assert_eq!(standings[3].username, "3");
+    }
+
+    #[test]
+    fn save_and_load_leaderboard() {
+        let path = env::temp_dir().join(format!("pp_leaderboard_{}.bin", process::id()));
+        let mut store = FileStore::new(&path);
+        assert_eq!(store.load_leaderboard().unwrap(), Leaderboard::default());
+
+        let mut leaderboard = Leaderboard::default();
+        leaderboard.record_hand(&heads_up_hand("0", "1", 50));
+        store.save_leaderboard(&leaderboard).unwrap();
+
+        // The leaderboard doesn't get in the way of saving tables.
+        assert!(store.load().unwrap().is_empty());
+        let mut store = FileStore::new(&path);
+        assert_eq!(store.load_leaderboard().unwrap(), leaderboard);
+        fs::remove_file(path.with_extension("leaderboard")).unwrap();
+    }
+
+    #[test]
+    fn load_unversioned_leaderboard() {
+        // Saved before files had versions. Leaderboards came after money
+        // was counted in cents, so nothing's migrated.
+        let path = env::temp_dir().join(format!("pp_leaderboard_v1_{}.bin", process::id()));
+        fs::write(
+            path.with_extension("leaderboard"),
+            include_bytes!("../../tests/fixtures/leaderboard_v1.bin"),
+        )
+        .unwrap();
+
+        let mut store = FileStore::new(&path);
+        let standings = store.load_leaderboard().unwrap().top(3);
+        assert_eq!(standings[0].username, "1");
+        assert_eq!((standings[0].profit, standings[0].hands), (5250, 2));
+        assert_eq!(standings[0].biggest_pot, 10000);
+        assert_eq!(standings[1].username, "2");
+        assert_eq!(standings[1].profit, -250);
+        assert_eq!(standings[2].profit, -5000);
+        fs::remove_file(path.with_extension("leaderboard")).unwrap();
+    }
+
+    #[test]
+    fn load_unversioned_tables() {
+        // Saved before files had versions, back when money was counted
+        // in whole dollars.
+        let path = env::temp_dir().join(format!("pp_store_v1_{}.bin", process::id()));
+        fs::write(&path, include_bytes!("../../tests/fixtures/tables_v1.bin")).unwrap();
+
+        let mut store = FileStore::new(&path);
+        let tables = store.load().unwrap();
+        assert_eq!(tables.keys().copied().collect::<Vec<_>>(), vec![0, 3]);
+        let snapshot = &tables[&0];
+        let settings = &snapshot.settings;
+        assert_eq!(
+            (settings.min_buy_in, settings.buy_in, settings.max_buy_in),
+            (10000, 20000, 40000)
+        );
+        assert_eq!(
+            (settings.min_small_blind, settings.min_big_blind),
+            (100, 200)
+        );
+        assert_eq!(
+            (settings.max_players, settings.max_users, settings.seed),
+            (6, 8, Some(7))
+        );
+        assert_eq!(settings.rake, Some(RakeSettings::new(5, Some(300), true)));
+        assert_eq!(
+            settings.jackpot,
+            Some(JackpotSettings::new(100, Rank::FourOfAKind))
+        );
+        assert_eq!(snapshot.game.donations, 150.0);
+        assert_eq!(snapshot.game.spectators[0].money, 5000);
+        assert_eq!(snapshot.game.waitlist[0].money, 20000);
+        assert_eq!(snapshot.game.players[0].0, 4);
+        assert_eq!(snapshot.game.players[0].1.money, 15000);
+        assert_eq!(snapshot.game.jackpot, 300);
+        let tournament = tables[&3].settings.tournament.as_ref().unwrap();
+        assert_eq!(tournament.level_duration, Duration::from_secs(600));
+        assert_eq!(tables[&3].settings.buy_in, 100000);
+
+        // Saving writes the current version, which isn't migrated again.
+        store.save(1, snapshot.clone()).unwrap();
+        let mut store = FileStore::new(&path);
+        assert_eq!(store.load().unwrap()[&1], tables[&0]);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn save_and_load() {
+        let path = env::temp_dir().join(format!("pp_store_{}.bin", process::id()));
+        let mut store = FileStore::new(&path);
+        assert!(store.load().unwrap().is_empty());
+
+        let snapshot = TableSnapshot {
+            settings: GameSettings::default(),
+            game: GameSnapshot {
+                donations: 1.0,
+                spectators: vec![User {
+                    name: "0".into(),
+                    money: 100,
+                }],
+                waitlist: vec![],
+                players: vec![(
+                    3,
+                    User {
+                        name: "1".into(),
+                        money: 300,
+                    },
+                )],
+                jackpot: 0,
+            },
+        };
+        store.save(0, snapshot.clone()).unwrap();
+
+        // A new store reads what the old one saved.
+        let mut store = FileStore::new(&path);
+        let tables = store.load().unwrap();
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[&0], snapshot);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn append_and_read_hand_history() {
+        let path = env::temp_dir().join(format!("pp_history_{}.jsonl", process::id()));
+        let hand = HandHistory {
+            players: vec![
+                (
+                    0,
+                    User {
+                        name: "0".into(),
+                        money: 200,
+                    },
+                ),
+                (
+                    1,
+                    User {
+                        name: "1".into(),
+                        money: 200,
+                    },
+                ),
+            ],
+            small_blind: 5,
+            big_blind: 10,
+            ante: 0,
+            small_blind_username: "0".into(),
+            big_blind_username: "1".into(),
+            actions: vec![HandAction {
+                street: Street::Preflop,
+                username: "0".into(),
+                action: Action::Fold,
+            }],
+            pots: vec![PotResult {
+                board_idx: 0,
+                size: 15,
+                winners: vec!["1".into()],
+            }],
+            ..Default::default()
+        };
+        append_hand_history(&path, &hand).unwrap();
+        append_hand_history(&path, &hand).unwrap();
+        assert_eq!(read_hand_history(&path).unwrap(), vec![hand.clone(), hand]);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn read_unversioned_hand_history() {
+        // Hands used to be recorded without a version, back when money
+        // was counted in whole dollars.
+        let path = env::temp_dir().join(format!("pp_history_v1_{}.jsonl", process::id()));
+        fs::write(
+            &path,
+            include_str!("../../tests/fixtures/hand_history_v1.jsonl"),
+        )
+        .unwrap();
+
+        let hands = read_hand_history(&path).unwrap();
+        let hand = &hands[0];
+        assert_eq!(hand.players[0].1.money, 20000);
+        assert_eq!((hand.small_blind, hand.big_blind), (500, 1000));
+        assert_eq!(hand.actions[0].action, Action::Raise(1500));
+        assert_eq!(hand.pots[0].size, 3000);
+        assert_eq!(hand.audit.stack_deltas["0"], -1000);
+        assert_eq!(hand.audit.chips_in, 3000);
+        assert!(hand.is_balanced());
+
+        // New hands are versioned and read back as they were written.
+        append_hand_history(&path, hand).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.lines().nth(1).unwrap().starts_with(r#"{"V2":"#));
+        assert_eq!(
+            read_hand_history(&path).unwrap(),
+            vec![hand.clone(), hand.clone()]
+        );
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,196 @@
+use anyhow::{bail, Error};
+use rusqlite::{params, Connection};
+use std::{collections::BTreeMap, path::Path};
+
+use crate::game::entities::Username;
+
+use super::{
+    super::messages::{Standing, TableId},
+    Leaderboard, Store, TableSnapshot,
+};
+
+/// Version of the database's schema, kept in its `user_version`. Tables
+/// are saved as JSON, so fields added to them with defaults don't need a
+/// new version.
+const SCHEMA_VERSION: i64 = 1;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS tables (
+        table_id INTEGER PRIMARY KEY,
+        snapshot TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS standings (
+        username TEXT PRIMARY KEY,
+        profit INTEGER NOT NULL,
+        hands INTEGER NOT NULL,
+        biggest_pot INTEGER NOT NULL
+    );
+";
+
+/// Stores tables and the leaderboard in a SQLite database. Each save is
+/// a transaction, so a crash mid-save never corrupts what was previously
+/// saved, and other tools can query the leaderboard while the server's
+/// running.
+pub struct SqliteStore {
+    conn: Connection,
+}
+
+impl SqliteStore {
+    /// Open the database, creating it if it doesn't exist.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let conn = Connection::open(path)?;
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version > SCHEMA_VERSION {
+            bail!("database was saved by a newer version (schema {version})");
+        }
+        conn.execute_batch(SCHEMA)?;
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        Ok(Self { conn })
+    }
+}
+
+impl Store for SqliteStore {
+    fn load(&mut self) -> Result<BTreeMap<TableId, TableSnapshot>, Error> {
+        let mut stmt = self.conn.prepare("SELECT table_id, snapshot FROM tables")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut tables = BTreeMap::new();
+        for row in rows {
+            let (table_id, snapshot) = row?;
+            tables.insert(table_id.try_into()?, serde_json::from_str(&snapshot)?);
+        }
+        Ok(tables)
+    }
+
+    fn save(&mut self, table_id: TableId, snapshot: TableSnapshot) -> Result<(), Error> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO tables (table_id, snapshot) VALUES (?1, ?2)",
+            params![i64::try_from(table_id)?, serde_json::to_string(&snapshot)?],
+        )?;
+        Ok(())
+    }
+
+    fn load_leaderboard(&mut self) -> Result<Leaderboard, Error> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT username, profit, hands, biggest_pot FROM standings")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get(3)?,
+            ))
+        })?;
+        let mut standings = BTreeMap::new();
+        for row in rows {
+            let (username, profit, hands, biggest_pot) = row?;
+            let username = Username::from(username);
+            let standing = Standing {
+                username: username.clone(),
+                profit,
+                hands: hands.try_into()?,
+                biggest_pot,
+            };
+            standings.insert(username, standing);
+        }
+        Ok(Leaderboard { standings })
+    }
+
+    fn save_leaderboard(&mut self, leaderboard: &Leaderboard) -> Result<(), Error> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM standings", [])?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO standings (username, profit, hands, biggest_pot)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for standing in leaderboard.standings.values() {
+                stmt.execute(params![
+                    standing.username.as_str(),
+                    standing.profit,
+                    i64::try_from(standing.hands)?,
+                    standing.biggest_pot,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use crate::game::{
+        entities::{GameSnapshot, User},
+        GameSettings,
+    };
+
+    use super::{
+        super::{tests::heads_up_hand, Leaderboard, Store, TableSnapshot},
+        SqliteStore,
+    };
+
+    #[test]
+    fn save_and_load() {
+        let path = env::temp_dir().join(format!("pp_store_{}.sqlite", process::id()));
+        let mut store = SqliteStore::open(&path).unwrap();
+        assert!(store.load().unwrap().is_empty());
+        assert_eq!(store.load_leaderboard().unwrap(), Leaderboard::default());
+
+        let snapshot = TableSnapshot {
+            settings: GameSettings::default().with_blinds(1, 2),
+            game: GameSnapshot {
+                donations: 1.0,
+                spectators: vec![User {
+                    name: "0".into(),
+                    money: 100,
+                }],
+                waitlist: vec![],
+                players: vec![(
+                    3,
+                    User {
+                        name: "1".into(),
+                        money: 300,
+                    },
+                )],
+                jackpot: 5,
+            },
+        };
+        store.save(0, snapshot.clone()).unwrap();
+        store.save(2, snapshot.clone()).unwrap();
+        let mut leaderboard = Leaderboard::default();
+        leaderboard.record_hand(&heads_up_hand("0", "1", 50));
+        store.save_leaderboard(&leaderboard).unwrap();
+
+        // Saving again replaces what was saved before.
+        let mut updated = snapshot.clone();
+        updated.game.jackpot = 10;
+        store.save(2, updated.clone()).unwrap();
+        leaderboard.record_hand(&heads_up_hand("1", "2", 10));
+        store.save_leaderboard(&leaderboard).unwrap();
+
+        // A new store reads what the old one saved.
+        drop(store);
+        let mut store = SqliteStore::open(&path).unwrap();
+        let tables = store.load().unwrap();
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[&0], snapshot);
+        assert_eq!(tables[&2], updated);
+        assert_eq!(store.load_leaderboard().unwrap(), leaderboard);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reject_newer_schema() {
+        let path = env::temp_dir().join(format!("pp_store_newer_{}.sqlite", process::id()));
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        conn.pragma_update(None, "user_version", 2).unwrap();
+        drop(conn);
+        assert!(SqliteStore::open(&path).is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,183 @@
+//! HAProxy's PROXY protocol.
+//!
+//! Load balancers that speak the PROXY protocol start every connection
+//! they forward with a header saying where the connection really came
+//! from, so servers behind them can see clients' addresses instead of
+//! the load balancer's. Both the text (v1) and binary (v2) versions of
+//! the header are understood.
+
+use std::{
+    io::{self, Read},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    str,
+};
+
+const V1_PREFIX: [u8; 5] = *b"PROXY";
+
+/// The longest a v1 header can be, line ending included.
+const V1_MAX_LEN: usize = 107;
+
+const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+fn invalid_data() -> io::Error {
+    io::ErrorKind::InvalidData.into()
+}
+
+/// Read a header from the start of a connection, returning the client's
+/// address if the proxy passed one along. Proxies leave it out for their
+/// own health checks and for clients that aren't on TCP.
+///
+/// Like the rest of the wire protocol, the header is expected to arrive
+/// all at once. A would block error before any of it arrives can be
+/// retried, but headers cut short are invalid.
+pub fn read_header<R: Read>(reader: &mut R) -> io::Result<Option<SocketAddr>> {
+    let mut prefix = [0; 5];
+    reader.read_exact(&mut prefix)?;
+    let result = if prefix == V1_PREFIX {
+        read_v1(reader)
+    } else if prefix == V2_SIGNATURE[..5] {
+        read_v2(reader)
+    } else {
+        Err(invalid_data())
+    };
+    result.map_err(|error| match error.kind() {
+        io::ErrorKind::WouldBlock => invalid_data(),
+        _ => error,
+    })
+}
+
+/// Read the rest of a text header, like
+/// `PROXY TCP4 192.0.2.1 198.51.100.1 56324 6969\r\n`.
+fn read_v1<R: Read>(reader: &mut R) -> io::Result<Option<SocketAddr>> {
+    let mut line = Vec::from(V1_PREFIX);
+    while !line.ends_with(b"\r\n") {
+        if line.len() >= V1_MAX_LEN {
+            return Err(invalid_data());
+        }
+        let mut byte = [0];
+        reader.read_exact(&mut byte)?;
+        line.push(byte[0]);
+    }
+    let line = str::from_utf8(&line[..line.len() - 2]).map_err(|_| invalid_data())?;
+    let fields: Vec<_> = line.split(' ').collect();
+    match fields[..] {
+        ["PROXY", "UNKNOWN", ..] => Ok(None),
+        ["PROXY", protocol @ ("TCP4" | "TCP6"), src_ip, _, src_port, _] => {
+            let ip: IpAddr = src_ip.parse().map_err(|_| invalid_data())?;
+            let port = src_port.parse().map_err(|_| invalid_data())?;
+            if (protocol == "TCP4") != ip.is_ipv4() {
+                return Err(invalid_data());
+            }
+            Ok(Some(SocketAddr::new(ip, port)))
+        }
+        _ => Err(invalid_data()),
+    }
+}
+
+/// Read the rest of a binary header after the start of its signature.
+fn read_v2<R: Read>(reader: &mut R) -> io::Result<Option<SocketAddr>> {
+    let mut rest = [0; 11];
+    reader.read_exact(&mut rest)?;
+    let (signature, header) = rest.split_at(7);
+    if signature != &V2_SIGNATURE[5..] {
+        return Err(invalid_data());
+    }
+    let [version_command, family, len @ ..] = header else {
+        unreachable!("the header is four bytes");
+    };
+    if version_command >> 4 != 2 {
+        return Err(invalid_data());
+    }
+    // The addresses are followed by optional extensions that can be
+    // skipped.
+    let mut data = vec![0; u16::from_be_bytes([len[0], len[1]]) as usize];
+    reader.read_exact(&mut data)?;
+    match (version_command & 0xf, family >> 4) {
+        // Local connections come from the proxy itself.
+        (0, _) => Ok(None),
+        (1, 1) if data.len() >= 12 => {
+            let ip: [u8; 4] = data[..4].try_into().expect("4 bytes");
+            let port = u16::from_be_bytes([data[8], data[9]]);
+            Ok(Some(SocketAddr::new(Ipv4Addr::from(ip).into(), port)))
+        }
+        (1, 2) if data.len() >= 36 => {
+            let ip: [u8; 16] = data[..16].try_into().expect("16 bytes");
+            let port = u16::from_be_bytes([data[32], data[33]]);
+            Ok(Some(SocketAddr::new(Ipv6Addr::from(ip).into(), port)))
+        }
+        // Unix sockets and unspecified families.
+        (1, 0 | 3) => Ok(None),
+        _ => Err(invalid_data()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, net::SocketAddr};
+
+    use super::{read_header, V2_SIGNATURE};
+
+    fn v2_header(command: u8, family: u8, data: &[u8]) -> Vec<u8> {
+        let mut header = Vec::from(V2_SIGNATURE);
+        header.extend([0x20 | command, family]);
+        header.extend((data.len() as u16).to_be_bytes());
+        header.extend(data);
+        header
+    }
+
+    #[test]
+    fn read_v1_header() {
+        let mut header = &b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 6969\r\nrest"[..];
+        let addr: SocketAddr = "192.0.2.1:56324".parse().unwrap();
+        assert_eq!(read_header(&mut header).unwrap(), Some(addr));
+        // Nothing past the header is read.
+        assert_eq!(header, b"rest");
+
+        let mut header = &b"PROXY TCP6 2001:db8::1 2001:db8::2 56324 6969\r\n"[..];
+        let addr: SocketAddr = "[2001:db8::1]:56324".parse().unwrap();
+        assert_eq!(read_header(&mut header).unwrap(), Some(addr));
+
+        let mut header = &b"PROXY UNKNOWN\r\n"[..];
+        assert_eq!(read_header(&mut header).unwrap(), None);
+    }
+
+    #[test]
+    fn read_v2_header() {
+        let mut data = vec![192, 0, 2, 1, 198, 51, 100, 1];
+        data.extend(56324u16.to_be_bytes());
+        data.extend(6969u16.to_be_bytes());
+        // An extension that should be skipped.
+        data.extend([0x04, 0, 1, 0]);
+        let mut header = v2_header(1, 0x11, &data);
+        header.extend(b"rest");
+        let mut header = &header[..];
+        let addr: SocketAddr = "192.0.2.1:56324".parse().unwrap();
+        assert_eq!(read_header(&mut header).unwrap(), Some(addr));
+        assert_eq!(header, b"rest");
+
+        let header = v2_header(0, 0, &[]);
+        assert_eq!(read_header(&mut &header[..]).unwrap(), None);
+    }
+
+    #[test]
+    fn reject_bad_headers() {
+        let too_long = [&b"PROXY "[..], &[b'x'; 200]].concat();
+        for header in [
+            &b"JSON"[..],
+            b"PROXY TCP4 2001:db8::1 192.0.2.1 56324 6969\r\n",
+            b"PROXY TCP4 192.0.2.1 198.51.100.1 port 6969\r\n",
+            b"PROXY TCP4 192.0.2.1",
+            &too_long,
+        ] {
+            let error = read_header(&mut &header[..]).unwrap_err();
+            assert!(matches!(
+                error.kind(),
+                io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof
+            ));
+        }
+        // Version 1 isn't a valid binary header.
+        let mut header = Vec::from(V2_SIGNATURE);
+        header.extend([0x11, 0x11, 0, 0]);
+        assert!(read_header(&mut &header[..]).is_err());
+    }
+}
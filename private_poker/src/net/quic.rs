@@ -0,0 +1,102 @@
+//! Optional QUIC transport for the wire protocol.
+//!
+//! QUIC recovers from packet loss faster than TCP and is always
+//! encrypted, which helps clients on lossy networks. Each QUIC connection
+//! carries the usual length-prefixed protocol from [`super::utils`] over
+//! a single bidirectional stream, so the server treats it like any other
+//! connection once it's open. Only the tokio server accepts QUIC
+//! connections, and it needs a TLS certificate to do so.
+//!
+//! The client is blocking while QUIC isn't, so [`connect`] runs the QUIC
+//! connection on a thread of its own and hands back a loopback socket
+//! that's relayed to it. Everything built on plain sockets works
+//! unchanged on top of that socket.
+
+use anyhow::{bail, Error};
+use quinn::{
+    crypto::rustls::{QuicClientConfig, QuicServerConfig},
+    ClientConfig, Endpoint, ServerConfig, VarInt,
+};
+use std::{
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream},
+    sync::{mpsc, Arc},
+    thread,
+    time::Duration,
+};
+use tokio::{io, runtime, time};
+
+use super::tls::ClientTls;
+
+/// Protocol name that clients and servers agree on during the TLS
+/// handshake.
+pub const ALPN: &[u8] = b"private_poker";
+
+/// QUIC settings for a server with the given TLS settings.
+pub fn server_config(tls: Arc<rustls::ServerConfig>) -> Result<ServerConfig, Error> {
+    let mut tls = (*tls).clone();
+    tls.alpn_protocols = vec![ALPN.to_vec()];
+    let crypto = QuicServerConfig::try_from(tls)?;
+    Ok(ServerConfig::with_crypto(Arc::new(crypto)))
+}
+
+/// Open a QUIC connection to the server, giving up after the timeout.
+/// Returns a blocking loopback socket whose reads and writes are relayed
+/// over the connection until either side closes it.
+pub fn connect(addr: SocketAddr, tls: &ClientTls, timeout: Duration) -> Result<TcpStream, Error> {
+    let mut crypto = tls.client_config(addr)?;
+    crypto.alpn_protocols = vec![ALPN.to_vec()];
+    let config = ClientConfig::new(Arc::new(QuicClientConfig::try_from(crypto)?));
+
+    // Make sure nothing else snuck onto the loopback listener before the
+    // client did.
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?;
+    let stream = TcpStream::connect(listener.local_addr()?)?;
+    let (relay, relay_addr) = listener.accept()?;
+    if relay_addr != stream.local_addr()? {
+        bail!("something else connected to the QUIC relay");
+    }
+    relay.set_nonblocking(true)?;
+
+    let (tx_result, rx_result) = mpsc::channel();
+    thread::spawn(move || -> Result<(), Error> {
+        let runtime = runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        runtime.block_on(async move {
+            let bind_addr: SocketAddr = match addr {
+                SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+                SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+            };
+            let open = async {
+                let endpoint = Endpoint::client(bind_addr)?;
+                let connection = endpoint
+                    .connect_with(config, addr, &addr.ip().to_string())?
+                    .await?;
+                let (send, recv) = connection.open_bi().await?;
+                Ok::<_, Error>((endpoint, connection, send, recv))
+            };
+            let (endpoint, connection, send, recv) = match time::timeout(timeout, open).await {
+                Ok(Ok(opened)) => opened,
+                Ok(Err(error)) => {
+                    let _ = tx_result.send(Err(error));
+                    return Ok(());
+                }
+                Err(_) => {
+                    let _ = tx_result.send(Err(Error::msg("timed out opening a QUIC connection")));
+                    return Ok(());
+                }
+            };
+            let _ = tx_result.send(Ok(()));
+            let mut relay = tokio::net::TcpStream::from_std(relay)?;
+            let mut quic = io::join(recv, send);
+            // The relay stops with an error when either side goes away
+            // without closing cleanly, which is just as final.
+            let _ = io::copy_bidirectional(&mut relay, &mut quic).await;
+            connection.close(VarInt::from_u32(0), b"");
+            endpoint.wait_idle().await;
+            Ok(())
+        })
+    });
+    rx_result.recv()??;
+    Ok(stream)
+}
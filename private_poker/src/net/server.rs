@@ -1,39 +1,88 @@
-use anyhow::{bail, Error};
-use log::{debug, error, info, warn};
-use mio::{
-    net::{TcpListener, TcpStream},
-    Events, Interest, Poll, Token, Waker,
-};
+use anyhow::{anyhow, bail, Error};
+use mio::{Events, Interest, Poll, Token, Waker};
+use rustls::{ServerConfig, ServerConnection, StreamOwned};
 use serde::{Deserialize, Serialize};
 use std::{
     cmp::max,
     collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
-    io,
-    sync::mpsc::{channel, Receiver, Sender},
+    fs, io,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, sync_channel, Receiver, Sender, SyncSender, TrySendError},
+        Arc, Mutex,
+    },
     thread,
     time::{Duration, Instant},
 };
+use tracing::{debug, error, field, info, info_span, warn, Span};
 
 use crate::{
-    constants::MAX_USER_INPUT_LENGTH,
+    constants::MAX_CHAT_MESSAGE_LENGTH,
     game::{
-        entities::{Action, GameView, Username},
-        GameSettings, PokerState,
+        entities::{
+            Action, ChipDisplay, GameSnapshot, GameView, GameViews, HandHistory, TournamentView,
+            TurnTimerView, Usd, User, Username,
+        },
+        Game, GameSettings, PokerState, RakeSettings, TakeAction, UserError,
     },
 };
 
 use super::{
-    messages::{ClientError, ClientMessage, ServerMessage, UserCommand, UserState},
-    utils::{read_prefixed, write_prefixed},
+    auth::Credentials,
+    control::{self, Console, ControlRequest, ControlResponse, CONSOLE_USERNAME},
+    messages::{
+        AdminOp, ClientError, ClientMessage, RakeLedger, ServerMessage, SessionToken, TableId,
+        TableView, UserCommand, UserState,
+    },
+    persistence::{self, Leaderboard, Store, TableSnapshot},
+    socket::{Addr, Listener, UNIX_PREFIX},
+    status::{self, StatusBoard, TableStatus},
+    tls::Stream,
+    transport::{Connection, WebSocketConnection},
 };
 
-pub const DEFAULT_ACTION_TIMEOUT: Duration = Duration::from_secs(30);
+#[cfg(feature = "tokio")]
+mod async_io;
+mod io_workers;
+mod multi_table;
+
+#[cfg(feature = "tokio")]
+pub use async_io::run_async;
+use io_workers::{ConnectionId, IoWorkers, WorkerEvent};
+use multi_table::{MultiTableTournaments, Order, TableReport};
+
 pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+pub const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+/// Idle users are left alone unless a server opts in.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::ZERO;
+/// Worker threads that connection I/O is spread over.
+pub const DEFAULT_IO_WORKERS: usize = 4;
+/// Messages that can be waiting for a client that isn't reading before
+/// its stale views are dropped, and then before it's disconnected.
+pub const DEFAULT_MAX_QUEUED_MESSAGES: usize = 256;
+pub const DEFAULT_MAX_TABLES: usize = 8;
 pub const DEFAULT_POLL_TIMEOUT: Duration = Duration::from_secs(1);
+pub const DEFAULT_RECONNECT_TIMEOUT: Duration = Duration::from_secs(60);
+pub const DEFAULT_RUNOUT_TIMEOUT: Duration = Duration::from_secs(2);
 pub const DEFAULT_STEP_TIMEOUT: Duration = Duration::from_secs(5);
 pub const MAX_NETWORK_EVENTS_PER_USER: usize = 6;
+/// Messages from the tables that can be waiting on the networking side
+/// before tables have to wait for room.
+pub const MAX_QUEUED_SERVER_DATA: usize = 1024;
+/// How often the networking side checks whether a backed up queue has
+/// room again.
+const BACKLOG_RETRY_INTERVAL: Duration = Duration::from_millis(1);
+/// Number of heartbeats a client can go without sending anything before
+/// its connection is considered dead.
+pub const MAX_MISSED_HEARTBEATS: u32 = 3;
+pub const DEFAULT_TABLE_ID: TableId = 0;
+/// Number of finished hands each table keeps for users to look back on.
+pub const MAX_RECENT_HANDS: usize = 10;
 pub const SERVER: Token = Token(0);
 pub const WAKER: Token = Token(1);
+pub const WEBSOCKET_SERVER: Token = Token(2);
 
 /// A server message for communication between poker server threads. This
 /// message is never sent directly to poker clients, but fields within the
@@ -41,46 +90,375 @@ pub const WAKER: Token = Token(1);
 #[derive(Debug, Deserialize, Serialize)]
 enum ServerData {
     /// An acknowledgement of a client message, signaling that the client's
-    /// command was successfully processed by the game thread.
-    Ack(ClientMessage),
+    /// command was successfully processed by a table's thread. Acks are
+    /// relayed to the given users.
+    Ack {
+        msg: ClientMessage,
+        usernames: HashSet<Username>,
+    },
+    /// A chat message relayed to the given users.
+    Chat {
+        username: Username,
+        message: String,
+        usernames: HashSet<Username>,
+    },
     /// A server message sent to a specific client.
     Response {
         username: Username,
         data: Box<ServerMessage>,
     },
-    /// Game state represented as a string.
-    Status(String),
+    /// Game state represented as a string, relayed to the given users.
+    Status {
+        status: String,
+        usernames: HashSet<Username>,
+    },
+    /// A kick or ban from the server console. Kicks and bans are about
+    /// connections, so they're handled like any admin's by the
+    /// networking side.
+    Kick(ClientMessage),
+    /// Every table finished its last hand and the server is exiting.
+    /// Queued messages are sent before connections are closed.
+    Shutdown,
     /// Mapping of usernames to their game views.
     Views(HashMap<Username, GameView>),
 }
 
+/// A message to the table manager for communication between poker server
+/// threads. Client messages are routed to the tables users are at, and
+/// tables report users joining or leaving them so the manager knows where
+/// to route future client messages.
+#[derive(Debug)]
+enum ManagerData {
+    Client(ClientMessage),
+    Joined {
+        username: Username,
+        table_id: TableId,
+    },
+    Left {
+        username: Username,
+        table_id: TableId,
+    },
+    /// A table's users or their money changed and the table should be
+    /// persisted.
+    Snapshot {
+        table_id: TableId,
+        snapshot: GameSnapshot,
+    },
+    /// A table raked a hand's pots.
+    Raked {
+        table_id: TableId,
+        amount: Usd,
+    },
+    /// A cash game table finished a hand, which counts toward the
+    /// leaderboard.
+    Played(Box<HandHistory>),
+    /// A multi-table tournament's table changed in a way that might mean
+    /// moving players between the tournament's tables.
+    Tournament {
+        table_id: TableId,
+        report: TableReport,
+    },
+    /// A table took a player out of a multi-table tournament to move
+    /// them to another of its tables. There's no one to move if the
+    /// player already left.
+    MovedOut {
+        username: Username,
+        to: TableId,
+        user: Option<User>,
+    },
+    /// A table finished its last hand because the server is shutting
+    /// down.
+    Stopped(TableId),
+    /// A request from the server console, answered over the sender.
+    Control {
+        request: ControlRequest,
+        tx_response: Sender<ControlResponse>,
+    },
+}
+
+/// A message to a table's thread. Besides user commands, tables take
+/// orders from the table manager for balancing multi-table tournaments.
+#[derive(Debug)]
+enum TableData {
+    Client(ClientMessage),
+    /// Move a player to another table once the table is between hands.
+    MoveOut {
+        username: Username,
+        to: TableId,
+    },
+    /// Seat a player moved from another table.
+    MoveIn(User),
+    /// End the tournament at the table.
+    FinishTournament,
+    HandForHand(bool),
+    /// Deal the next hand if the table is waiting on other tables to
+    /// finish theirs.
+    DealNextHand,
+}
+
+/// Asks a running server to shut down. The server stops accepting new
+/// connections right away, but lets tables finish the hands they're
+/// playing and saves them before exiting.
+#[derive(Clone, Debug, Default)]
+pub struct ShutdownSignal(Arc<AtomicBool>);
+
+impl ShutdownSignal {
+    pub fn is_triggered(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn trigger(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Cut chat messages down to size before anything else sees them.
+/// Usernames that are too long are turned away when users connect.
+fn truncate_user_input(msg: &mut ClientMessage) {
+    if let UserCommand::Chat(ref mut message) = msg.command {
+        if let Some((idx, _)) = message.char_indices().nth(MAX_CHAT_MESSAGE_LENGTH) {
+            message.truncate(idx);
+        }
+    }
+}
+
+/// Wakes the networking side of the server so it sends out the server
+/// data queued for it.
+#[derive(Clone)]
+enum IoWaker {
+    Mio(Arc<Waker>),
+    #[cfg(feature = "tokio")]
+    Tokio(Arc<tokio::sync::Notify>),
+}
+
+impl IoWaker {
+    fn wake(&self) -> io::Result<()> {
+        match self {
+            Self::Mio(waker) => waker.wake(),
+            #[cfg(feature = "tokio")]
+            Self::Tokio(notify) => {
+                notify.notify_one();
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Hands clients' messages from the networking side to the table
+/// manager without ever waiting on it. The manager's queue is bounded,
+/// and messages that don't fit wait here, in order, while the networking
+/// side stops taking new messages from connections until the manager
+/// catches up. Tables and the console can wait for room instead, since
+/// nothing the manager does waits on them.
+struct ManagerSender {
+    tx_manager: SyncSender<ManagerData>,
+    backlog: VecDeque<ManagerData>,
+}
+
+impl ManagerSender {
+    fn new(tx_manager: SyncSender<ManagerData>) -> Self {
+        Self {
+            tx_manager,
+            backlog: VecDeque::new(),
+        }
+    }
+
+    fn send(&mut self, msg: ClientMessage) -> Result<(), Error> {
+        self.backlog.push_back(ManagerData::Client(msg));
+        self.flush()
+    }
+
+    /// Send waiting messages for as long as the manager has room for
+    /// them.
+    fn flush(&mut self) -> Result<(), Error> {
+        while let Some(msg) = self.backlog.pop_front() {
+            match self.tx_manager.try_send(msg) {
+                Ok(()) => {}
+                Err(TrySendError::Full(msg)) => {
+                    self.backlog.push_front(msg);
+                    break;
+                }
+                Err(TrySendError::Disconnected(_)) => bail!("the table manager stopped"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether messages are waiting on the manager, in which case no more
+    /// should be taken from connections.
+    fn is_backlogged(&self) -> bool {
+        !self.backlog.is_empty()
+    }
+}
+
 fn token_to_string(token: &Token) -> String {
     let id = token.0;
     format!("token({id})")
 }
 
+/// Keeps a supervisor like systemd posted on how a running server is
+/// doing.
+pub trait Supervisor: Send {
+    /// The server is listening for connections.
+    fn ready(&mut self);
+
+    /// The server's networking is still responsive. This is called at
+    /// least once per poll timeout.
+    fn alive(&mut self);
+}
+
+/// Settings that can be changed while the server is running without
+/// dropping anyone's connection. Settings that aren't set fall back to
+/// each table's own.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LiveSettings {
+    /// Seconds players have to act.
+    pub turn_timeout: Option<u64>,
+    pub max_spectators: Option<usize>,
+}
+
+impl LiveSettings {
+    /// Load settings from a JSON file, like `{"turn_timeout": 30}`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let contents = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+}
+
+/// Changes a running server's [`LiveSettings`]. Tables pick up changes
+/// between game steps.
+#[derive(Clone, Debug, Default)]
+pub struct LiveConfig(Arc<Mutex<LiveSettings>>);
+
+impl LiveConfig {
+    pub fn get(&self) -> LiveSettings {
+        self.0.lock().expect("live settings lock").clone()
+    }
+
+    pub fn set(&self, settings: LiveSettings) {
+        *self.0.lock().expect("live settings lock") = settings;
+    }
+}
+
+#[derive(Clone, Copy)]
 pub struct ServerTimeouts {
-    pub action: Duration,
     pub connect: Duration,
+    /// How often clients are pinged to check their connections are still
+    /// alive. Connections that go quiet for a few heartbeats are dropped,
+    /// catching half-open connections that reads and writes wouldn't
+    /// notice for a long time. Zero disables heartbeats.
+    pub heartbeat: Duration,
+    /// How long users can go without sending a command before they're
+    /// moved to the spectators, freeing their seat. Users that stay idle
+    /// for as long again are disconnected. Pongs don't count as commands.
+    /// Zero disables idle tracking.
+    pub idle: Duration,
     pub poll: Duration,
+    /// How long a user whose connection dropped keeps their spot at
+    /// their table, letting them reconnect with the same username and
+    /// pick up where they left off.
+    pub reconnect: Duration,
+    /// How long each street is shown when the board is run out with no
+    /// more betting, before the next one is dealt.
+    pub runout: Duration,
     pub step: Duration,
 }
 
 impl Default for ServerTimeouts {
     fn default() -> Self {
         Self {
-            action: DEFAULT_ACTION_TIMEOUT,
             connect: DEFAULT_CONNECT_TIMEOUT,
+            heartbeat: DEFAULT_HEARTBEAT_INTERVAL,
+            idle: DEFAULT_IDLE_TIMEOUT,
             poll: DEFAULT_POLL_TIMEOUT,
+            reconnect: DEFAULT_RECONNECT_TIMEOUT,
+            runout: DEFAULT_RUNOUT_TIMEOUT,
             step: DEFAULT_STEP_TIMEOUT,
         }
     }
 }
 
-#[derive(Default)]
 pub struct PokerConfig {
+    /// Settings for the server's first table.
     pub game_settings: GameSettings,
     pub server_timeouts: ServerTimeouts,
+    /// Maximum number of tables the server can host at once.
+    pub max_tables: usize,
+    /// Number of threads reading from and writing to connections. Each
+    /// connection is served by one of them, so a slow connection only
+    /// holds up the others on its thread. Only the mio server uses these;
+    /// the tokio server spreads connections over the runtime's threads.
+    pub io_workers: usize,
+    /// Most messages that can be queued for a client. Clients that fall
+    /// this far behind only get the latest view of their table, and are
+    /// disconnected if that isn't enough to catch up.
+    pub max_queued_messages: usize,
+    /// Where tables are persisted so users keep their money across
+    /// server restarts. Nothing is persisted if this isn't set.
+    pub store: Option<Box<dyn Store>>,
+    /// Registered users' passwords. Users must authenticate with a
+    /// password to connect if this is set.
+    pub credentials: Option<Credentials>,
+    /// Encrypts connections with TLS if set.
+    pub tls: Option<Arc<ServerConfig>>,
+    /// Also accepts WebSocket connections at this address if set, so
+    /// browser clients can connect.
+    pub websocket_addr: Option<String>,
+    /// Serves the server's status as JSON over HTTP at this address if
+    /// set. See [`status`].
+    pub status_addr: Option<String>,
+    /// Listens for the server console at this Unix socket or loopback
+    /// address if set. See [`control`].
+    pub console_addr: Option<String>,
+    /// Expects every connection to start with a PROXY protocol header
+    /// so servers behind load balancers like HAProxy see clients' real
+    /// addresses. Connections without one are dropped.
+    pub proxy_protocol: bool,
+    /// Also accepts QUIC connections at this address if set. Only the
+    /// tokio server built with the `quic` feature can, and it needs TLS.
+    pub quic_addr: Option<String>,
+    /// Users that can kick, ban, force other users to fold, and check the
+    /// rake. Admins should authenticate so others can't connect as them.
+    pub admins: HashSet<Username>,
+    /// Every finished hand is appended to this file if set.
+    pub hand_history: Option<PathBuf>,
+    /// Rakes pots at every table if set. Tables' own rake settings are
+    /// ignored so only the server decides on a rake. Admins can see how
+    /// much has been raked.
+    pub rake: Option<RakeSettings>,
+    /// Settings that can be changed while the server is running.
+    pub live_settings: LiveConfig,
+    /// Told when the server is ready and that it's still alive if set.
+    pub supervisor: Option<Box<dyn Supervisor>>,
+    /// Triggering this shuts the server down gracefully.
+    pub shutdown: ShutdownSignal,
+}
+
+impl Default for PokerConfig {
+    fn default() -> Self {
+        Self {
+            game_settings: GameSettings::default(),
+            server_timeouts: ServerTimeouts::default(),
+            max_tables: DEFAULT_MAX_TABLES,
+            io_workers: DEFAULT_IO_WORKERS,
+            max_queued_messages: DEFAULT_MAX_QUEUED_MESSAGES,
+            store: None,
+            credentials: None,
+            tls: None,
+            websocket_addr: None,
+            status_addr: None,
+            console_addr: None,
+            proxy_protocol: false,
+            quic_addr: None,
+            admins: HashSet::new(),
+            hand_history: None,
+            rake: None,
+            live_settings: LiveConfig::default(),
+            supervisor: None,
+            shutdown: ShutdownSignal::default(),
+        }
+    }
 }
 
 impl From<GameSettings> for PokerConfig {
@@ -89,6 +467,23 @@ impl From<GameSettings> for PokerConfig {
         Self {
             game_settings: value,
             server_timeouts,
+            max_tables: DEFAULT_MAX_TABLES,
+            io_workers: DEFAULT_IO_WORKERS,
+            max_queued_messages: DEFAULT_MAX_QUEUED_MESSAGES,
+            store: None,
+            credentials: None,
+            tls: None,
+            websocket_addr: None,
+            status_addr: None,
+            console_addr: None,
+            proxy_protocol: false,
+            quic_addr: None,
+            admins: HashSet::new(),
+            hand_history: None,
+            rake: None,
+            live_settings: LiveConfig::default(),
+            supervisor: None,
+            shutdown: ShutdownSignal::default(),
         }
     }
 }
@@ -99,20 +494,352 @@ impl From<ServerTimeouts> for PokerConfig {
         Self {
             game_settings: game_config,
             server_timeouts: value,
+            max_tables: DEFAULT_MAX_TABLES,
+            io_workers: DEFAULT_IO_WORKERS,
+            max_queued_messages: DEFAULT_MAX_QUEUED_MESSAGES,
+            store: None,
+            credentials: None,
+            tls: None,
+            websocket_addr: None,
+            status_addr: None,
+            console_addr: None,
+            proxy_protocol: false,
+            quic_addr: None,
+            admins: HashSet::new(),
+            hand_history: None,
+            rake: None,
+            live_settings: LiveConfig::default(),
+            supervisor: None,
+            shutdown: ShutdownSignal::default(),
+        }
+    }
+}
+
+/// A table hosted by the server. Each table runs its own poker game in
+/// its own thread and receives user commands over its channel.
+struct Table {
+    settings: GameSettings,
+    tx_client: Sender<TableData>,
+    num_users: usize,
+}
+
+/// Tracks the tables hosted by the server and which table each user is
+/// at so user commands can be routed to the correct table.
+///
+/// Tables are the source of truth for their users. Users only move
+/// between tables once their current table reports that they've left,
+/// which may not happen until the end of the current game if they're
+/// playing.
+struct TableManager {
+    max_tables: usize,
+    tables: BTreeMap<TableId, Table>,
+    usernames_to_table_ids: HashMap<Username, TableId>,
+    /// Users waiting to leave their current table to join another table.
+    pending_joins: HashMap<Username, TableId>,
+    /// Tables that are part of multi-table tournaments.
+    tournaments: MultiTableTournaments,
+}
+
+impl TableManager {
+    /// Confirm a table's report that a user joined it.
+    pub fn confirm_join(&mut self, username: Username, table_id: TableId) {
+        if let Some(table) = self.tables.get_mut(&table_id) {
+            table.num_users += 1;
+            self.usernames_to_table_ids.insert(username, table_id);
+        }
+    }
+
+    /// Confirm a table's report that a user left it, returning the table
+    /// the user is waiting to join next, if any.
+    pub fn confirm_leave(&mut self, username: &str, table_id: TableId) -> Option<TableId> {
+        if let Some(table) = self.tables.get_mut(&table_id) {
+            table.num_users = table.num_users.saturating_sub(1);
+        }
+        if self.usernames_to_table_ids.get(username) == Some(&table_id) {
+            self.usernames_to_table_ids.remove(username);
+        }
+        self.pending_joins.remove(username)
+    }
+
+    pub fn get_settings(&self, table_id: TableId) -> Option<&GameSettings> {
+        self.tables.get(&table_id).map(|table| &table.settings)
+    }
+
+    /// Return the users at a table.
+    pub fn get_usernames_at_table(&self, table_id: TableId) -> HashSet<Username> {
+        self.usernames_to_table_ids
+            .iter()
+            .filter(|(_, id)| **id == table_id)
+            .map(|(username, _)| username.clone())
+            .collect()
+    }
+
+    /// Return the users at every table, including tables nobody is at.
+    pub fn get_usernames_by_table(&self) -> BTreeMap<TableId, BTreeSet<Username>> {
+        let mut tables: BTreeMap<_, BTreeSet<_>> = self
+            .tables
+            .keys()
+            .map(|table_id| (*table_id, BTreeSet::new()))
+            .collect();
+        for (username, table_id) in &self.usernames_to_table_ids {
+            if let Some(usernames) = tables.get_mut(table_id) {
+                usernames.insert(username.clone());
+            }
+        }
+        tables
+    }
+
+    pub fn get_table_id_with_username(&self, username: &str) -> Result<TableId, UserError> {
+        self.usernames_to_table_ids
+            .get(username)
+            .copied()
+            .ok_or(UserError::NotAtTable)
+    }
+
+    /// Return summaries of all the tables so users can pick one to join.
+    pub fn get_views(&self) -> Vec<TableView> {
+        self.tables
+            .iter()
+            .map(|(id, table)| TableView {
+                id: *id,
+                // Seeds stay secret since they'd reveal every deal.
+                settings: GameSettings {
+                    seed: None,
+                    ..table.settings.clone()
+                },
+                num_users: table.num_users,
+            })
+            .collect()
+    }
+
+    /// Queue a user to join a table. If the user is already at another
+    /// table, that table is returned and the user will join the new table
+    /// once they've left it. Otherwise, the user can join the new table
+    /// right away.
+    pub fn join_table(
+        &mut self,
+        username: &str,
+        table_id: TableId,
+    ) -> Result<Option<TableId>, UserError> {
+        let table = self
+            .tables
+            .get(&table_id)
+            .ok_or(UserError::TableDoesNotExist)?;
+        match self.usernames_to_table_ids.get(username) {
+            // Let the table decide what to do with a user that's already
+            // there.
+            Some(current_table_id) if *current_table_id == table_id => {
+                self.pending_joins.remove(username);
+                Ok(None)
+            }
+            _ if table.num_users >= table.settings.max_users => Err(UserError::CapacityReached),
+            Some(current_table_id) => {
+                let current_table_id = *current_table_id;
+                self.pending_joins.insert(username.into(), table_id);
+                Ok(Some(current_table_id))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Cancel any table the user was waiting to join, returning the table
+    /// the user is currently at. The user is forgotten right away rather
+    /// than when the table confirms they left so their next commands
+    /// aren't sent to the table they're leaving.
+    pub fn leave_table(&mut self, username: &str) -> Option<TableId> {
+        self.pending_joins.remove(username);
+        self.usernames_to_table_ids.remove(username)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    pub fn new(max_tables: usize) -> Self {
+        Self {
+            max_tables,
+            tables: BTreeMap::new(),
+            usernames_to_table_ids: HashMap::new(),
+            pending_joins: HashMap::new(),
+            tournaments: MultiTableTournaments::default(),
+        }
+    }
+
+    /// Create a new table, returning its ID and the channel its user
+    /// commands will be received on.
+    pub fn new_table(
+        &mut self,
+        settings: GameSettings,
+    ) -> Result<(TableId, Receiver<TableData>), UserError> {
+        settings
+            .validate()
+            .map_err(UserError::InvalidGameSettings)?;
+        if self.tables.len() >= self.max_tables {
+            return Err(UserError::TableCapacityReached);
+        }
+        let table_id = self
+            .tables
+            .last_key_value()
+            .map_or(DEFAULT_TABLE_ID, |(id, _)| id + 1);
+        // Tables wait for room in the manager's queue, so the manager
+        // never waits on a table's. What a table gets is already limited
+        // by the manager's bounded queue.
+        let (tx_client, rx_client) = channel();
+        self.tournaments.add_table(table_id, &settings);
+        let table = Table {
+            settings,
+            tx_client,
+            num_users: 0,
+        };
+        self.tables.insert(table_id, table);
+        Ok((table_id, rx_client))
+    }
+
+    /// Remove a table that stopped running, along with any record of
+    /// users being at or waiting to join it.
+    pub fn remove_table(&mut self, table_id: TableId) {
+        self.tables.remove(&table_id);
+        self.tournaments.remove_table(table_id);
+        self.usernames_to_table_ids.retain(|_, id| *id != table_id);
+        self.pending_joins.retain(|_, id| *id != table_id);
+    }
+
+    /// Route an admin's command for a table to it. Blinds are checked
+    /// here so admins hear about bad blinds right away, and so the
+    /// table's settings stay up to date.
+    pub fn send_admin_op(&mut self, msg: ClientMessage) -> Result<(), Error> {
+        match msg.command {
+            UserCommand::Admin(AdminOp::ForceFold(ref username)) => {
+                let table_id = self
+                    .get_table_id_with_username(username)
+                    .map_err(|_| UserError::UserDoesNotExist)?;
+                self.send(table_id, msg)
+            }
+            UserCommand::Admin(AdminOp::Pause(table_id) | AdminOp::Resume(table_id)) => {
+                self.send(table_id, msg)
+            }
+            UserCommand::Admin(AdminOp::SetBlinds {
+                table_id,
+                small_blind,
+                big_blind,
+            }) => {
+                let table = self
+                    .tables
+                    .get_mut(&table_id)
+                    .ok_or(UserError::TableDoesNotExist)?;
+                let settings = table.settings.clone().with_blinds(small_blind, big_blind);
+                settings
+                    .validate()
+                    .map_err(UserError::InvalidGameSettings)?;
+                table.settings = settings;
+                self.send(table_id, msg)
+            }
+            _ => bail!("{} isn't for a table", msg.command),
+        }
+    }
+
+    /// Route a user command to a table.
+    pub fn send(&self, table_id: TableId, msg: ClientMessage) -> Result<(), Error> {
+        self.send_data(table_id, TableData::Client(msg))
+    }
+
+    pub fn send_data(&self, table_id: TableId, data: TableData) -> Result<(), Error> {
+        match self.tables.get(&table_id) {
+            Some(table) => table.tx_client.send(data)?,
+            None => bail!(UserError::TableDoesNotExist),
+        }
+        Ok(())
+    }
+
+    /// Send an order to a multi-table tournament's table. Tables stop
+    /// while the server is shutting down, so orders for tables that
+    /// stopped are dropped.
+    fn send_order(&self, table_id: TableId, data: TableData) {
+        if let Err(error) = self.send_data(table_id, data) {
+            debug!("couldn't send an order to table {table_id}: {error}");
+        }
+    }
+
+    /// Carry out what a multi-table tournament needs done, letting the
+    /// users at its tables know when it starts or stops playing
+    /// hand-for-hand and when it's won.
+    pub fn carry_out_orders(
+        &self,
+        orders: Vec<Order>,
+        tx_server: &SyncSender<ServerData>,
+        waker: &IoWaker,
+    ) -> Result<(), Error> {
+        let send_status = |status: String, table_ids: &[TableId]| -> Result<(), Error> {
+            info!("{status}");
+            let usernames = table_ids
+                .iter()
+                .flat_map(|table_id| self.get_usernames_at_table(*table_id))
+                .collect();
+            tx_server.send(ServerData::Status { status, usernames })?;
+            waker.wake()?;
+            Ok(())
+        };
+        for order in orders {
+            match order {
+                Order::Move { username, from, to } => {
+                    self.send_order(from, TableData::MoveOut { username, to });
+                }
+                Order::FinishTournament(table_id) => {
+                    self.send_order(table_id, TableData::FinishTournament);
+                }
+                Order::HandForHand {
+                    name,
+                    table_ids,
+                    is_hand_for_hand,
+                } => {
+                    let status = if is_hand_for_hand {
+                        format!("the {name} tournament is on the bubble and playing hand-for-hand")
+                    } else {
+                        format!("the {name} tournament is done playing hand-for-hand")
+                    };
+                    send_status(status, &table_ids)?;
+                    for table_id in table_ids {
+                        self.send_order(table_id, TableData::HandForHand(is_hand_for_hand));
+                    }
+                }
+                Order::DealNextHand(table_ids) => {
+                    for table_id in table_ids {
+                        self.send_order(table_id, TableData::DealNextHand);
+                    }
+                }
+                Order::Won {
+                    name,
+                    winner,
+                    prize_pool,
+                    table_ids,
+                } => {
+                    let chip_display = table_ids
+                        .first()
+                        .and_then(|table_id| self.get_settings(*table_id))
+                        .map(|settings| settings.chip_display)
+                        .unwrap_or_default();
+                    let status = format!(
+                        "{winner} won the {name} tournament and its {} prize pool",
+                        chip_display.chips(prize_pool)
+                    );
+                    send_status(status, &table_ids)?;
+                }
+            }
         }
+        Ok(())
     }
 }
 
 struct UnconfirmedClient {
-    stream: TcpStream,
+    id: ConnectionId,
     t: Instant,
     timeout: Duration,
 }
 
 impl UnconfirmedClient {
-    pub fn new(stream: TcpStream) -> Self {
+    pub fn new(id: ConnectionId) -> Self {
         UnconfirmedClient {
-            stream,
+            id,
             t: Instant::now(),
             timeout: Duration::ZERO,
         }
@@ -131,7 +858,7 @@ impl UnconfirmedClient {
 ///   clients that have sent their usernames and those usernames have
 ///   been confirmed by the poker game.
 struct TokenManager {
-    pub confirmed_tokens: BTreeMap<Token, TcpStream>,
+    pub confirmed_tokens: BTreeMap<Token, ConnectionId>,
     confirmed_usernames_to_tokens: HashMap<Username, Token>,
     recycled_tokens: BTreeSet<Token>,
     token_association_timeout: Duration,
@@ -141,17 +868,16 @@ struct TokenManager {
 }
 
 impl TokenManager {
-    /// Associate a token with a TCP stream. Since tokens are usually registered
-    /// with a poll, the typical workflow is:
+    /// Associate a token with a connection. Since connections are served by
+    /// the I/O workers, the typical workflow is:
     ///
     /// 1. Create a new token.
-    /// 2. Register the token and stream with the poll.
-    /// 3. Associate the token and stream with the token manager.
+    /// 2. Associate the token and connection with the token manager.
+    /// 3. Hand the connection to the worker for its token.
     ///
-    /// This transfers ownership of the stream to the token manager, allowing
-    /// deallocation of the stream wheenver the token is recycled.
-    pub fn associate_token_and_stream(&mut self, token: Token, stream: TcpStream) {
-        let unconfirmed_client = UnconfirmedClient::new(stream);
+    /// The connection is closed by its worker whenever the token is recycled.
+    pub fn associate_token_and_connection(&mut self, token: Token, id: ConnectionId) {
+        let unconfirmed_client = UnconfirmedClient::new(id);
         self.unconfirmed_tokens.insert(token, unconfirmed_client);
     }
 
@@ -186,8 +912,7 @@ impl TokenManager {
             Some(username) => match self.unconfirmed_usernames_to_tokens.remove_entry(username) {
                 Some((username, token)) => match self.unconfirmed_tokens.remove(&token) {
                     Some(unconfirmed_client) => {
-                        self.confirmed_tokens
-                            .insert(token, unconfirmed_client.stream);
+                        self.confirmed_tokens.insert(token, unconfirmed_client.id);
                         self.confirmed_usernames_to_tokens.insert(username, token);
                         Ok(())
                     }
@@ -214,16 +939,13 @@ impl TokenManager {
         }
     }
 
-    pub fn get_mut_stream_with_token(
-        &mut self,
-        token: &Token,
-    ) -> Result<&mut TcpStream, ClientError> {
+    pub fn get_connection_id_with_token(&self, token: &Token) -> Result<ConnectionId, ClientError> {
         match (
-            self.unconfirmed_tokens.get_mut(token),
-            self.confirmed_tokens.get_mut(token),
+            self.unconfirmed_tokens.get(token),
+            self.confirmed_tokens.get(token),
         ) {
-            (Some(unconfirmed_client), None) => Ok(&mut unconfirmed_client.stream),
-            (None, Some(stream)) => Ok(stream),
+            (Some(unconfirmed_client), None) => Ok(unconfirmed_client.id),
+            (None, Some(id)) => Ok(*id),
             (None, None) => Err(ClientError::DoesNotExist),
             _ => unreachable!("a token must be either unconfirmed or confirmed"),
         }
@@ -264,7 +986,7 @@ impl TokenManager {
                     (Some((unconfirmed, _)), Some((confirmed, _))) => max(unconfirmed, confirmed),
                     (Some((unconfirmed, _)), None) => unconfirmed,
                     (None, Some((verified, _))) => verified,
-                    (None, None) => &WAKER,
+                    (None, None) => &WEBSOCKET_SERVER,
                 };
                 Token(newest.0 + 1)
             }
@@ -274,7 +996,7 @@ impl TokenManager {
 
     /// Recycle tokens that've gone stale because the client has yet
     /// to associate a username with itself before the association timeout.
-    pub fn recycle_expired_tokens(&mut self) -> VecDeque<(Token, TcpStream)> {
+    pub fn recycle_expired_tokens(&mut self) -> VecDeque<(Token, ConnectionId)> {
         let mut tokens_to_recycle = VecDeque::new();
         for (token, unknown_client) in self
             .unconfirmed_tokens
@@ -292,9 +1014,7 @@ impl TokenManager {
         let mut recyclables = VecDeque::new();
         for token in tokens_to_recycle {
             match self.unconfirmed_tokens.remove(&token) {
-                Some(unconfirmed_client) => {
-                    recyclables.push_back((token, unconfirmed_client.stream))
-                }
+                Some(unconfirmed_client) => recyclables.push_back((token, unconfirmed_client.id)),
                 None => unreachable!("an unassociated token is always unconfirmed"),
             }
             self.recycled_tokens.insert(token);
@@ -304,68 +1024,306 @@ impl TokenManager {
 
     /// Manually recycle an individual token. Should be used when a client is dropped,
     /// unfaithful, or when a user leaves the game.
-    pub fn recycle_token(&mut self, token: Token) -> Result<TcpStream, ClientError> {
+    pub fn recycle_token(&mut self, token: Token) -> Result<ConnectionId, ClientError> {
         if let Some(username) = self.tokens_to_usernames.remove(&token) {
             self.unconfirmed_usernames_to_tokens.remove(&username);
             self.confirmed_usernames_to_tokens.remove(&username);
         }
-        let stream = match (
+        let id = match (
             self.unconfirmed_tokens.remove(&token),
             self.confirmed_tokens.remove(&token),
         ) {
-            (Some(unconfirmed), None) => unconfirmed.stream,
-            (None, Some(stream)) => stream,
+            (Some(unconfirmed), None) => unconfirmed.id,
+            (None, Some(id)) => id,
             (None, None) => return Err(ClientError::DoesNotExist),
             _ => unreachable!("a token must be either unconfirmed or confirmed"),
         };
         self.recycled_tokens.insert(token);
-        Ok(stream)
+        Ok(id)
     }
 }
 
-/// Run the poker server in two separate threads. The parent thread manages
-/// the poker game state while the child thread manages non-blocking networking
-/// IO.
+/// Drop every view queued for a client but the latest, since each view
+/// replaces the one before it. Returns how many views were dropped.
+fn drop_stale_views(messages: &mut VecDeque<ServerMessage>) -> usize {
+    let Some(latest_idx) = messages
+        .iter()
+        .rposition(|msg| matches!(msg, ServerMessage::GameView(_)))
+    else {
+        return 0;
+    };
+    let num_messages = messages.len();
+    let mut idx = 0;
+    messages.retain(|msg| {
+        let is_stale = idx < latest_idx && matches!(msg, ServerMessage::GameView(_));
+        idx += 1;
+        !is_stale
+    });
+    num_messages - messages.len()
+}
+
+/// Start the server console if the server has one, passing its requests
+/// on to the table manager.
+fn spawn_console(
+    addr: Option<&String>,
+    tx_manager: SyncSender<ManagerData>,
+) -> Result<Option<Console>, Error> {
+    let Some(addr) = addr else {
+        return Ok(None);
+    };
+    let console = control::spawn(&addr.parse()?, move |request| {
+        let (tx_response, rx_response) = channel();
+        let msg = ManagerData::Control {
+            request,
+            tx_response,
+        };
+        if tx_manager.send(msg).is_err() {
+            return ControlResponse::Error("the server is shutting down".to_string());
+        }
+        rx_response
+            .recv_timeout(control::CONSOLE_TIMEOUT)
+            .unwrap_or_else(|_| ControlResponse::Error("the server didn't respond".to_string()))
+    })?;
+    Ok(Some(console))
+}
+
+/// Run the poker server at one address. See [`run_on`].
 pub fn run(addr: &str, config: PokerConfig) -> Result<(), Error> {
-    let addr = addr.parse()?;
-    let max_network_events = MAX_NETWORK_EVENTS_PER_USER * config.game_settings.max_users;
+    run_on(&[addr], config)
+}
 
-    let (tx_client, rx_client): (Sender<ClientMessage>, Receiver<ClientMessage>) = channel();
-    let (tx_server, rx_server): (Sender<ServerData>, Receiver<ServerData>) = channel();
+/// Run the poker server. The parent thread manages the server's tables,
+/// routing user commands to the table each user is at. Each table manages
+/// its own poker game state in its own thread, while another child thread
+/// accepts connections and checks clients' messages, and a few more read
+/// from and write to the connections with non-blocking IO. Connections to
+/// any of the addresses end up at the same tables, so a server can listen
+/// on IPv4 and IPv6 at once.
+pub fn run_on(addrs: &[impl AsRef<str>], config: PokerConfig) -> Result<(), Error> {
+    config.game_settings.validate()?;
+    if config.quic_addr.is_some() {
+        bail!("only the tokio server accepts QUIC connections");
+    }
+    let addrs = addrs
+        .iter()
+        .map(|addr| addr.as_ref().parse())
+        .collect::<Result<Vec<Addr>, _>>()?;
+    if addrs.is_empty() {
+        bail!("the server needs an address to bind to");
+    }
+    if config.io_workers == 0 {
+        bail!("the server needs at least one I/O worker");
+    }
+    let websocket_addr: Option<Addr> = match &config.websocket_addr {
+        Some(websocket_addr) => Some(websocket_addr.parse()?),
+        None => None,
+    };
+    let status_board = StatusBoard::default();
+    if let Some(ref status_addr) = config.status_addr {
+        status::spawn(status_addr, status_board.clone())?;
+    }
+    // Clients check certificates against the server's IP address.
+    // Inherited sockets are up to whoever set them up.
+    if config.tls.is_some()
+        && addrs
+            .iter()
+            .any(|addr| addr.to_string().starts_with(UNIX_PREFIX))
+    {
+        bail!("TLS isn't supported over Unix sockets");
+    }
+    let max_network_events =
+        MAX_NETWORK_EVENTS_PER_USER * config.game_settings.max_users * config.max_tables;
+
+    // Queues between threads are bounded so a flood of clients' messages
+    // slows those clients down instead of growing without limit.
+    let (tx_manager, rx_manager) = sync_channel(max_network_events);
+    let (tx_server, rx_server) = sync_channel(MAX_QUEUED_SERVER_DATA);
+    let _console = spawn_console(config.console_addr.as_ref(), tx_manager.clone())?;
 
     let mut poll = Poll::new()?;
-    let waker = Waker::new(poll.registry(), WAKER)?;
+    let mio_waker = Arc::new(Waker::new(poll.registry(), WAKER)?);
+    let waker = IoWaker::Mio(mio_waker.clone());
 
-    // This thread is where the actual networking happens for non-blocking IO.
-    // A server is bound to the address and manages connections to clients.
-    // Messages from the main thread are queued for each client/user
-    // connection.
-    thread::spawn(move || -> Result<(), Error> {
-        let mut events = Events::with_capacity(max_network_events);
+    // Sockets are read from and written to by the workers, which tell the
+    // networking thread what happens on their connections.
+    let (tx_events, rx_events) = sync_channel(max_network_events);
+    let io_workers = IoWorkers::spawn(
+        config.io_workers,
+        max_network_events,
+        config.max_queued_messages,
+        tx_events,
+        mio_waker,
+    )?;
+
+    // This thread is where connections are accepted and clients' messages
+    // are checked before they go to the tables. Messages from the main
+    // thread are queued for each client/user connection and handed to the
+    // workers to write.
+    let mut tx_client = ManagerSender::new(tx_manager.clone());
+    let shutdown = config.shutdown.clone();
+    let io_thread = thread::spawn(move || -> Result<(), Error> {
+        // Only the listeners and the waker are polled here.
+        let mut events = Events::with_capacity(addrs.len() + 2);
+        let mut next_connection_id: ConnectionId = 0;
         let mut messages_to_process: HashMap<Token, VecDeque<ClientMessage>> = HashMap::new();
         let mut messages_to_write: HashMap<Token, VecDeque<ServerMessage>> = HashMap::new();
-        let mut server = TcpListener::bind(addr)?;
         let mut token_manager = TokenManager::new(config.server_timeouts.connect);
+        let mut credentials = config.credentials;
+        let admins = config.admins;
+        let mut supervisor = config.supervisor;
+        let mut banned_ips: HashSet<IpAddr> = HashSet::new();
+        let mut banned_usernames: HashSet<Username> = HashSet::new();
+        let mut peer_ips: HashMap<Token, IpAddr> = HashMap::new();
+        // When each client last sent anything, for detecting dead
+        // connections between heartbeats.
+        let mut last_heard: HashMap<Token, Instant> = HashMap::new();
+        let mut last_heartbeat = Instant::now();
+        // When each client last sent a command other than a pong, and the
+        // idle users that have already been moved to the spectators.
+        let idle = config.server_timeouts.idle;
+        let mut last_active: HashMap<Token, Instant> = HashMap::new();
+        let mut idle_tokens: HashSet<Token> = HashSet::new();
+        // Wake up at least once a heartbeat so pings go out on time.
+        let heartbeat = config.server_timeouts.heartbeat;
+        let poll_timeout = if heartbeat.is_zero() {
+            config.server_timeouts.poll
+        } else {
+            config.server_timeouts.poll.min(heartbeat)
+        };
+        // The session each connected user was given, so they can prove
+        // who they are if their connection drops.
+        let mut sessions: HashMap<Token, SessionToken> = HashMap::new();
+        // Users whose connections dropped, when they dropped, and the
+        // session they were given. They stay at their table until the
+        // reconnect timeout expires, holding their spot for whoever
+        // reconnects with that session.
+        let mut disconnected_usernames: HashMap<Username, (Instant, SessionToken)> = HashMap::new();
+        let mut dropped_tokens: HashSet<Token> = HashSet::new();
         let mut tokens_to_remove: HashSet<Token> = HashSet::new();
-        let mut tokens_to_reregister: HashSet<Token> = HashSet::new();
-        poll.registry()
-            .register(&mut server, SERVER, Interest::READABLE)?;
+        let mut is_accepting = true;
+        let mut exit_deadline: Option<Instant> = None;
+        // Listeners after the first get tokens from the top of the range
+        // so they never collide with clients' tokens.
+        let mut listeners: HashMap<Token, Listener> = HashMap::new();
+        for (i, addr) in addrs.iter().enumerate() {
+            let token = match i {
+                0 => SERVER,
+                i => Token(usize::MAX - i),
+            };
+            let mut listener = Listener::bind(addr)?;
+            poll.registry()
+                .register(&mut listener, token, Interest::READABLE)?;
+            listeners.insert(token, listener);
+        }
+        if let Some(websocket_addr) = websocket_addr {
+            let mut websocket_server = Listener::bind(&websocket_addr)?;
+            poll.registry().register(
+                &mut websocket_server,
+                WEBSOCKET_SERVER,
+                Interest::READABLE,
+            )?;
+            listeners.insert(WEBSOCKET_SERVER, websocket_server);
+        }
+        if let Some(ref mut supervisor) = supervisor {
+            supervisor.ready();
+        }
 
         loop {
-            if let Err(error) = poll.poll(&mut events, Some(config.server_timeouts.poll)) {
+            // Exit once the workers have sent everything queued, or once
+            // clients have had a fair chance to receive it.
+            if let Some(deadline) = exit_deadline {
+                return io_workers.stop(deadline);
+            }
+
+            let timeout = match tx_client.is_backlogged() {
+                true => BACKLOG_RETRY_INTERVAL,
+                false => poll_timeout,
+            };
+            if let Err(error) = poll.poll(&mut events, Some(timeout)) {
                 match error.kind() {
                     io::ErrorKind::Interrupted => continue,
                     _ => bail!(error),
                 }
             }
+            if let Some(ref mut supervisor) = supervisor {
+                supervisor.alive();
+            }
+            // Stop accepting connections as soon as a shutdown is requested,
+            // and let everyone know the server is going away. This is checked
+            // after polling so the notice is queued before the final message
+            // from tables that saw the shutdown while the poll was blocked.
+            if is_accepting && shutdown.is_triggered() {
+                is_accepting = false;
+                info!("shutting down after the current hands finish");
+                for listener in listeners.values_mut() {
+                    poll.registry().deregister(listener)?;
+                }
+                for &token in token_manager.confirmed_tokens.keys() {
+                    let msg = ServerMessage::Status(
+                        "the server is shutting down after the current hand".to_string(),
+                    );
+                    messages_to_write.entry(token).or_default().push_back(msg);
+                }
+            }
+
+            // Handle what happened on connections since the workers
+            // last checked in. While the table manager is behind, events
+            // are left queued, which stops the workers from reading more.
+            tx_client.flush()?;
+            while !tx_client.is_backlogged() {
+                let Ok(event) = rx_events.try_recv() else {
+                    break;
+                };
+                // Connections can close before their events are
+                // handled, and their tokens can go to new
+                // connections.
+                let (token, id) = event.get_connection();
+                if tokens_to_remove.contains(&token)
+                    || token_manager.get_connection_id_with_token(&token) != Ok(id)
+                {
+                    continue;
+                }
+                match event {
+                    WorkerEvent::Message { mut msg, .. } => {
+                        last_heard.insert(token, Instant::now());
+                        truncate_user_input(&mut msg);
+                        messages_to_process
+                            .entry(token)
+                            .or_default()
+                            .push_back(*msg);
+                    }
+                    WorkerEvent::Proxied { ip, .. } => {
+                        let repr = token_to_string(&token);
+                        match ip {
+                            Some(ip) if banned_ips.contains(&ip) => {
+                                debug!("rejected connection from banned address {ip}");
+                                tokens_to_remove.insert(token);
+                            }
+                            Some(ip) => {
+                                debug!("{repr} is proxied for {ip}");
+                                peer_ips.insert(token, ip);
+                            }
+                            None => debug!("{repr} is proxied for an unknown address"),
+                        }
+                    }
+                    WorkerEvent::Dropped { .. } => {
+                        dropped_tokens.insert(token);
+                        tokens_to_remove.insert(token);
+                    }
+                    WorkerEvent::Closed { .. } => {
+                        tokens_to_remove.insert(token);
+                    }
+                }
+            }
 
             for event in events.iter() {
                 match event.token() {
-                    SERVER => loop {
-                        // Received an event for the TCP server socket, which
+                    token if listeners.contains_key(&token) => loop {
+                        // Received an event for one of the server sockets, which
                         // indicates we can accept a connection.
-                        let mut stream = match server.accept() {
-                            Ok((stream, _)) => stream,
+                        let listener = &listeners[&token];
+                        let (stream, peer_ip) = match listener.accept() {
+                            Ok((stream, peer_ip)) => (stream, peer_ip),
                             Err(error) => {
                                 match error.kind() {
                                     // If we get a `WouldBlock` error we know our
@@ -380,10 +1338,35 @@ pub fn run(addr: &str, config: PokerConfig) -> Result<(), Error> {
                             }
                         };
 
+                        // Unix socket peers don't have an address to ban.
+                        if let Some(peer_ip) = peer_ip.filter(|ip| banned_ips.contains(ip)) {
+                            debug!("rejected connection from banned address {peer_ip}");
+                            continue;
+                        }
+                        let stream = match &config.tls {
+                            Some(tls) => {
+                                let conn = ServerConnection::new(tls.clone())?;
+                                Stream::Server(Box::new(StreamOwned::new(conn, stream)))
+                            }
+                            None => Stream::Plain(stream),
+                        };
+                        let stream = match event.token() {
+                            WEBSOCKET_SERVER => {
+                                Connection::WebSocket(Box::new(WebSocketConnection::accept(stream)))
+                            }
+                            _ => stream.into(),
+                        };
                         let token = token_manager.new_token();
-                        poll.registry()
-                            .register(&mut stream, token, Interest::READABLE)?;
-                        token_manager.associate_token_and_stream(token, stream);
+                        let id = next_connection_id;
+                        next_connection_id += 1;
+                        token_manager.associate_token_and_connection(token, id);
+                        io_workers.add(token, id, stream, config.proxy_protocol)?;
+                        // The peer of a proxied connection is the proxy, so the
+                        // client's address comes from the proxy's header instead.
+                        if let Some(peer_ip) = peer_ip.filter(|_| !config.proxy_protocol) {
+                            peer_ips.insert(token, peer_ip);
+                        }
+                        last_heard.insert(token, Instant::now());
                         let repr = token_to_string(&token);
                         debug!("accepted new connection with {repr}");
                     },
@@ -393,8 +1376,8 @@ pub fn run(addr: &str, config: PokerConfig) -> Result<(), Error> {
                         while let Ok(msg) = rx_server.try_recv() {
                             match msg {
                                 // Acks are effectively successful responses to client
-                                // messages and are relayed to all clients.
-                                ServerData::Ack(msg) => {
+                                // messages and are relayed to all users at the table.
+                                ServerData::Ack { msg, usernames } => {
                                     // We only need to check this connect edge case because all other
                                     // client commands can only go through to the parent thread if the
                                     // client's username has already been confirmed by the parent
@@ -418,10 +1401,36 @@ pub fn run(addr: &str, config: PokerConfig) -> Result<(), Error> {
                                             tx_client.send(msg)?;
                                         }
                                     }
-                                    for token in token_manager.confirmed_tokens.keys() {
-                                        let msg = ServerMessage::Ack(msg.clone());
-                                        messages_to_write.entry(*token).or_default().push_back(msg);
-                                        tokens_to_reregister.insert(*token);
+                                    for username in usernames {
+                                        if let Ok(token) =
+                                            token_manager.get_token_with_username(&username)
+                                        {
+                                            let msg = ServerMessage::Ack(msg.clone());
+                                            messages_to_write
+                                                .entry(token)
+                                                .or_default()
+                                                .push_back(msg);
+                                        }
+                                    }
+                                }
+                                ServerData::Chat {
+                                    username,
+                                    message,
+                                    usernames,
+                                } => {
+                                    for recipient in usernames {
+                                        if let Ok(token) =
+                                            token_manager.get_token_with_username(&recipient)
+                                        {
+                                            let msg = ServerMessage::Chat {
+                                                username: username.clone(),
+                                                message: message.clone(),
+                                            };
+                                            messages_to_write
+                                                .entry(token)
+                                                .or_default()
+                                                .push_back(msg);
+                                        }
                                     }
                                 }
                                 // A response goes to a single client. We can safely ignore cases where a
@@ -435,16 +1444,62 @@ pub fn run(addr: &str, config: PokerConfig) -> Result<(), Error> {
                                             .entry(token)
                                             .or_default()
                                             .push_back(*data);
-                                        tokens_to_reregister.insert(token);
                                     }
                                 }
-                                // Server status is a game status update to all clients.
-                                ServerData::Status(msg) => {
-                                    for token in token_manager.confirmed_tokens.keys() {
-                                        let msg = ServerMessage::Status(msg.clone());
-                                        messages_to_write.entry(*token).or_default().push_back(msg);
-                                        tokens_to_reregister.insert(*token);
+                                // Server status is a game status update to all users
+                                // at the table.
+                                ServerData::Status { status, usernames } => {
+                                    for username in usernames {
+                                        if let Ok(token) =
+                                            token_manager.get_token_with_username(&username)
+                                        {
+                                            let msg = ServerMessage::Status(status.clone());
+                                            messages_to_write
+                                                .entry(token)
+                                                .or_default()
+                                                .push_back(msg);
+                                        }
+                                    }
+                                }
+                                // Kicks and bans from the console work like
+                                // any admin's.
+                                ServerData::Kick(msg) => {
+                                    if let UserCommand::Admin(
+                                        AdminOp::Ban(ref target) | AdminOp::Kick(ref target),
+                                    ) = msg.command
+                                    {
+                                        let target_token =
+                                            token_manager.get_token_with_username(target).ok();
+                                        if let UserCommand::Admin(AdminOp::Ban(_)) = msg.command {
+                                            banned_usernames.insert(target.clone());
+                                            if let Some(ip) =
+                                                target_token.and_then(|t| peer_ips.get(&t))
+                                            {
+                                                banned_ips.insert(*ip);
+                                            }
+                                        }
+                                        if let Some(target_token) = target_token {
+                                            tokens_to_remove.insert(target_token);
+                                        }
+                                        info!(username = %msg.username, "{}", msg.command);
+                                        for &token in token_manager.confirmed_tokens.keys() {
+                                            let msg = ServerMessage::Ack(msg.clone());
+                                            messages_to_write
+                                                .entry(token)
+                                                .or_default()
+                                                .push_back(msg);
+                                        }
+                                    }
+                                }
+                                ServerData::Shutdown => {
+                                    for &token in token_manager.confirmed_tokens.keys() {
+                                        let msg = ServerMessage::Status(
+                                            "the server shut down".to_string(),
+                                        );
+                                        messages_to_write.entry(token).or_default().push_back(msg);
                                     }
+                                    exit_deadline =
+                                        Some(Instant::now() + config.server_timeouts.connect);
                                 }
                                 // Views go to all clients. We can safely ignore cases where a client
                                 // no longer exists to receive a view because the view is specific
@@ -454,166 +1509,106 @@ pub fn run(addr: &str, config: PokerConfig) -> Result<(), Error> {
                                         if let Ok(token) =
                                             token_manager.get_token_with_username(&username)
                                         {
-                                            let msg = ServerMessage::GameView(view);
+                                            let msg = ServerMessage::GameView(Box::new(view));
                                             messages_to_write
                                                 .entry(token)
                                                 .or_default()
                                                 .push_back(msg);
-                                            tokens_to_reregister.insert(token);
                                         }
                                     }
                                 }
                             }
                         }
-                        for token in tokens_to_reregister.drain() {
-                            if let Ok(stream) = token_manager.get_mut_stream_with_token(&token) {
-                                poll.registry().reregister(
-                                    stream,
-                                    token,
-                                    Interest::READABLE | Interest::WRITABLE,
-                                )?;
-                            }
-                        }
                     }
-                    // Only care about events associated with clients that are
-                    // still valid.
-                    token if !tokens_to_remove.contains(&token) => {
-                        // Maybe received an event for a TCP connection.
-                        if let Ok(stream) = token_manager.get_mut_stream_with_token(&token) {
-                            if event.is_writable() {
-                                if let Some(messages) = messages_to_write.get_mut(&token) {
-                                    // Need to handle the case where there's an unresponsive or
-                                    // misbehaving client that doesn't let us write messages to
-                                    // them. If their message queue reaches a certain size, queue
-                                    // them for removal.
-                                    if messages.len() >= max_network_events {
-                                        let repr = token_to_string(&token);
-                                        error!(
-                                            "{repr} has not been receiving and will be removed."
-                                        );
-                                        tokens_to_remove.insert(token);
-                                        continue;
-                                    }
-                                    while let Some(msg) = messages.pop_front() {
-                                        match write_prefixed::<ServerMessage, TcpStream>(
-                                            stream, &msg,
-                                        ) {
-                                            Ok(_) => {
-                                                // Client errors are strict and result in the removal of a connection.
-                                                if let ServerMessage::ClientError(_) = msg {
-                                                    let repr = token_to_string(&token);
-                                                    debug!("{repr}: {msg}");
-                                                    tokens_to_remove.insert(token);
-                                                    break;
-                                                }
-                                            }
-                                            Err(error) => {
-                                                match error.kind() {
-                                                    // `write_prefixed` uses `write_all` under the hood, so we know
-                                                    // that if any of these occur, then the connection was probably
-                                                    // dropped at some point.
-                                                    io::ErrorKind::BrokenPipe
-                                                    | io::ErrorKind::ConnectionAborted
-                                                    | io::ErrorKind::ConnectionReset
-                                                    | io::ErrorKind::TimedOut
-                                                    | io::ErrorKind::UnexpectedEof => {
-                                                        let repr = token_to_string(&token);
-                                                        debug!("{repr} connection dropped");
-                                                        tokens_to_remove.insert(token);
-                                                    }
-                                                    // Would block "errors" are the OS's way of saying that the
-                                                    // connection is not actually ready to perform this I/O operation.
-                                                    io::ErrorKind::WouldBlock => {
-                                                        // The message couldn't be sent, so we need to push it back
-                                                        // onto the queue so we don't accidentally forget about it.
-                                                        messages.push_front(msg);
-                                                    }
-                                                    // Retry writing in the case that the full message couldn't
-                                                    // be written. This should be infrequent.
-                                                    io::ErrorKind::WriteZero => {
-                                                        let repr = token_to_string(&token);
-                                                        debug!("{repr} got a zero write, but will retry");
-                                                        messages.push_front(msg);
-                                                        continue;
-                                                    }
-                                                    // Other errors we'll consider fatal.
-                                                    _ => bail!(error),
-                                                }
-                                                poll.registry().reregister(
-                                                    stream,
-                                                    token,
-                                                    Interest::READABLE,
-                                                )?;
-                                                break;
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-
-                            if event.is_readable() {
-                                // We can (maybe) read from the connection.
-                                loop {
-                                    match read_prefixed::<ClientMessage, TcpStream>(stream) {
-                                        Ok(mut msg) => {
-                                            msg.username.truncate(MAX_USER_INPUT_LENGTH);
-                                            let messages =
-                                                messages_to_process.entry(token).or_default();
-                                            messages.push_back(msg);
-                                            if messages.len() >= MAX_NETWORK_EVENTS_PER_USER {
-                                                let repr = token_to_string(&token);
-                                                error!(
-                                                    "{repr} has been spamming and will be removed."
-                                                );
-                                                tokens_to_remove.insert(token);
-                                                break;
-                                            }
-                                        }
-                                        Err(error) => {
-                                            match error.kind() {
-                                                // `read_prefixed` uses `read_exact` under the hood, so we know
-                                                // that an Eof error means the connection was dropped.
-                                                io::ErrorKind::BrokenPipe
-                                                | io::ErrorKind::ConnectionAborted
-                                                | io::ErrorKind::ConnectionReset
-                                                | io::ErrorKind::InvalidData
-                                                | io::ErrorKind::TimedOut
-                                                | io::ErrorKind::UnexpectedEof => {
-                                                    let repr = token_to_string(&token);
-                                                    debug!("{repr}'s connection dropped");
-                                                    tokens_to_remove.insert(token);
-                                                }
-                                                // Would block "errors" are the OS's way of saying that the
-                                                // connection is not actually ready to perform this I/O operation.
-                                                io::ErrorKind::WouldBlock => {}
-                                                // Other errors we'll consider fatal.
-                                                _ => {
-                                                    bail!(error)
-                                                }
-                                            }
-                                            break;
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    // The client is already queued for removal and so this event
-                    // will be ignored.
                     _ => {}
                 }
             }
 
             // Process all the messages received from the clients.
-            for (token, msgs) in messages_to_process
-                .drain()
-                .filter(|(t, _)| !tokens_to_remove.contains(t))
-            {
-                for msg in msgs {
+            let messages: Vec<_> = messages_to_process.drain().collect();
+            for (token, msgs) in messages {
+                for mut msg in msgs {
+                    // Clients can be removed while their messages are being
+                    // processed (e.g., if they're kicked).
+                    if tokens_to_remove.contains(&token) {
+                        break;
+                    }
                     let result = match msg.command {
+                        // Names that would break commands or displays are
+                        // turned away.
+                        UserCommand::Authenticate(_)
+                        | UserCommand::Connect
+                        | UserCommand::Reconnect(_)
+                            if msg.username.validate().is_err() =>
+                        {
+                            msg.username
+                                .validate()
+                                .map_err(ClientError::InvalidUsername)
+                        }
+                        // Banned users can't connect again.
+                        UserCommand::Authenticate(_)
+                        | UserCommand::Connect
+                        | UserCommand::Reconnect(_)
+                            if banned_usernames.contains(&msg.username) =>
+                        {
+                            Err(ClientError::Banned)
+                        }
+                        // Nobody can pass themselves off as the server
+                        // console.
+                        UserCommand::Authenticate(_)
+                        | UserCommand::Connect
+                        | UserCommand::Reconnect(_)
+                            if msg.username == CONSOLE_USERNAME =>
+                        {
+                            Err(ClientError::AlreadyAssociated)
+                        }
+                        // Only the user that lost their connection can take
+                        // their spot back. Users that authenticate have
+                        // already proven who they are, but anyone can claim
+                        // a username, so they have to come back with the
+                        // session they were given, or with their password
+                        // on servers that have them.
+                        UserCommand::Connect | UserCommand::Reconnect(_)
+                            if disconnected_usernames
+                                .get(&msg.username)
+                                .is_some_and(|(_, session)| {
+                                    !matches!(msg.command, UserCommand::Reconnect(ref s) if s == session)
+                                }) =>
+                        {
+                            Err(if credentials.is_some() {
+                                ClientError::AuthenticationFailed
+                            } else {
+                                ClientError::AlreadyAssociated
+                            })
+                        }
+                        // Check the user's password before connecting them. The
+                        // password is dropped so it's never sent to the table.
+                        UserCommand::Authenticate(ref password) => {
+                            let result = match credentials.as_mut() {
+                                Some(credentials) => {
+                                    credentials.authenticate(&msg.username, password)
+                                }
+                                None => Ok(()),
+                            };
+                            msg.command = UserCommand::Connect;
+                            result.and_then(|_| {
+                                token_manager
+                                    .associate_token_and_username(token, msg.username.clone())
+                            })
+                        }
+                        // Users can't connect without a password if the server
+                        // requires authentication. Sessions only stand in for
+                        // a password while the user's spot is held.
+                        UserCommand::Connect | UserCommand::Reconnect(_)
+                            if credentials.is_some()
+                                && !disconnected_usernames.contains_key(&msg.username) =>
+                        {
+                            Err(ClientError::AuthenticationFailed)
+                        }
                         // Check if the client wasn't able to associate its token with a username
                         // in time, or if that username is already taken.
-                        UserCommand::Connect => {
+                        UserCommand::Connect | UserCommand::Reconnect(_) => {
+                            msg.command = UserCommand::Connect;
                             token_manager.associate_token_and_username(token, msg.username.clone())
                         }
                         // Check if the client is being faithful and sending messages with
@@ -630,28 +1625,123 @@ pub fn run(addr: &str, config: PokerConfig) -> Result<(), Error> {
                         },
                     };
                     let repr = token_to_string(&token);
-                    match result {
-                        Ok(_) => {
-                            debug!("{repr}: {msg}");
-                            tx_client.send(msg)?
+                    // Pongs are sent without the user doing anything, so
+                    // they don't keep users from going idle.
+                    if result.is_ok() && msg.command != UserCommand::Pong {
+                        last_active.insert(token, Instant::now());
+                        idle_tokens.remove(&token);
+                    }
+                    if result.is_ok() && msg.command == UserCommand::Connect {
+                        // The user is back before their spot was given up.
+                        if disconnected_usernames.remove(&msg.username).is_some() {
+                            info!(username = %msg.username, "reconnected");
                         }
+                        let session = SessionToken::generate();
+                        sessions.insert(token, session.clone());
+                        let msg = ServerMessage::Session(session);
+                        messages_to_write.entry(token).or_default().push_back(msg);
+                    }
+                    match result {
+                        Ok(_) => match msg.command {
+                            // Pongs only keep the connection alive, which
+                            // reading them already did.
+                            UserCommand::Pong => {}
+                            UserCommand::Admin(_) if !admins.contains(&msg.username) => {
+                                debug!(username = %msg.username, "{repr}: {}: {}", msg.command, UserError::NotAnAdmin);
+                                let msg = ServerMessage::UserError(UserError::NotAnAdmin);
+                                messages_to_write.entry(token).or_default().push_back(msg);
+                            }
+                            // Kicks and bans are about connections rather than games,
+                            // so they're handled here. Everyone on the server is told
+                            // about them.
+                            UserCommand::Admin(
+                                AdminOp::Ban(ref target) | AdminOp::Kick(ref target),
+                            ) => {
+                                let target_token =
+                                    token_manager.get_token_with_username(target).ok();
+                                if let UserCommand::Admin(AdminOp::Ban(_)) = msg.command {
+                                    banned_usernames.insert(target.clone());
+                                    if let Some(ip) = target_token.and_then(|t| peer_ips.get(&t)) {
+                                        banned_ips.insert(*ip);
+                                    }
+                                } else if target_token.is_none() {
+                                    debug!(username = %msg.username, "{repr}: {}: {}", msg.command, UserError::UserDoesNotExist);
+                                    let msg = ServerMessage::UserError(UserError::UserDoesNotExist);
+                                    messages_to_write.entry(token).or_default().push_back(msg);
+                                    continue;
+                                }
+                                if let Some(target_token) = target_token {
+                                    tokens_to_remove.insert(target_token);
+                                }
+                                info!(username = %msg.username, "{}", msg.command);
+                                for &other_token in token_manager.confirmed_tokens.keys() {
+                                    let msg = ServerMessage::Ack(msg.clone());
+                                    messages_to_write
+                                        .entry(other_token)
+                                        .or_default()
+                                        .push_back(msg);
+                                }
+                            }
+                            _ => {
+                                debug!(username = %msg.username, "{repr}: {}", msg.command);
+                                tx_client.send(msg)?
+                            }
+                        },
                         Err(error) => {
                             debug!("{repr}: {error}");
                             let msg = ServerMessage::ClientError(error);
                             messages_to_write.entry(token).or_default().push_back(msg);
-                            tokens_to_reregister.insert(token);
                         }
                     }
                 }
             }
-            // Make sure we allow writing errors back to the client.
-            for token in tokens_to_reregister.drain() {
-                if let Ok(stream) = token_manager.get_mut_stream_with_token(&token) {
-                    poll.registry().reregister(
-                        stream,
-                        token,
-                        Interest::READABLE | Interest::WRITABLE,
-                    )?;
+            // Ping everyone each heartbeat, and treat connections that have
+            // been quiet for too long like any other dropped connection so
+            // their users can still reconnect.
+            if !heartbeat.is_zero() {
+                if last_heartbeat.elapsed() >= heartbeat {
+                    last_heartbeat = Instant::now();
+                    for &token in token_manager.confirmed_tokens.keys() {
+                        let msg = ServerMessage::Ping;
+                        messages_to_write.entry(token).or_default().push_back(msg);
+                    }
+                }
+                for &token in token_manager.confirmed_tokens.keys() {
+                    if last_heard
+                        .get(&token)
+                        .is_some_and(|t| t.elapsed() >= heartbeat * MAX_MISSED_HEARTBEATS)
+                        && tokens_to_remove.insert(token)
+                    {
+                        let repr = token_to_string(&token);
+                        debug!("{repr} missed too many heartbeats");
+                        dropped_tokens.insert(token);
+                    }
+                }
+            }
+
+            // Move idle users to the spectators so their seats go to
+            // someone else, and disconnect them if they stay idle.
+            if !idle.is_zero() {
+                for &token in token_manager.confirmed_tokens.keys() {
+                    let Some(idle_for) = last_active.get(&token).map(Instant::elapsed) else {
+                        continue;
+                    };
+                    let Ok(username) = token_manager.get_confirmed_username_with_token(&token)
+                    else {
+                        continue;
+                    };
+                    if idle_for >= idle * 2 {
+                        if tokens_to_remove.insert(token) {
+                            info!(%username, "was idle for too long");
+                        }
+                    } else if idle_for >= idle && idle_tokens.insert(token) {
+                        info!(%username, "is idle and will spectate");
+                        let msg = ClientMessage {
+                            username,
+                            command: UserCommand::ChangeState(UserState::Spectate),
+                        };
+                        tx_client.send(msg)?;
+                    }
                 }
             }
 
@@ -660,29 +1750,762 @@ pub fn run(addr: &str, config: PokerConfig) -> Result<(), Error> {
             for token in tokens_to_remove.drain() {
                 let repr = token_to_string(&token);
                 debug!("{repr} is being removed");
+                let dropped = dropped_tokens.remove(&token);
                 if let Ok(username) = token_manager.get_confirmed_username_with_token(&token) {
-                    let msg = ClientMessage {
-                        username,
-                        command: UserCommand::Leave,
-                    };
-                    tx_client.send(msg)?;
+                    // Users that lost their connection get a chance to
+                    // reconnect before they're removed from their table.
+                    let session = sessions.remove(&token);
+                    if let Some(session) =
+                        session.filter(|_| dropped && !config.server_timeouts.reconnect.is_zero())
+                    {
+                        info!(
+                            "{username} disconnected and has {:?} to reconnect",
+                            config.server_timeouts.reconnect
+                        );
+                        disconnected_usernames.insert(username, (Instant::now(), session));
+                    } else {
+                        let msg = ClientMessage {
+                            username,
+                            command: UserCommand::Leave,
+                        };
+                        tx_client.send(msg)?;
+                    }
                 }
                 messages_to_write.remove(&token);
-                if let Ok(mut stream) = token_manager.recycle_token(token) {
-                    poll.registry().deregister(&mut stream)?;
+                peer_ips.remove(&token);
+                sessions.remove(&token);
+                last_heard.remove(&token);
+                last_active.remove(&token);
+                idle_tokens.remove(&token);
+                if token_manager.recycle_token(token).is_ok() {
+                    io_workers.remove(token)?;
                 }
             }
-            for (token, mut stream) in token_manager.recycle_expired_tokens() {
+            for (token, _) in token_manager.recycle_expired_tokens() {
                 let repr = token_to_string(&token);
                 debug!("{repr} expired");
                 messages_to_write.remove(&token);
-                poll.registry().deregister(&mut stream)?;
+                peer_ips.remove(&token);
+                sessions.remove(&token);
+                last_heard.remove(&token);
+                last_active.remove(&token);
+                io_workers.remove(token)?;
+            }
+            // Give up the spots of users that didn't reconnect in time.
+            let expired_usernames: Vec<_> = disconnected_usernames
+                .iter()
+                .filter(|(_, (t, _))| t.elapsed() >= config.server_timeouts.reconnect)
+                .map(|(username, _)| username.clone())
+                .collect();
+            for username in expired_usernames {
+                disconnected_usernames.remove(&username);
+                debug!(%username, "didn't reconnect in time");
+                let msg = ClientMessage {
+                    username,
+                    command: UserCommand::Leave,
+                };
+                tx_client.send(msg)?;
             }
+
+            // Hand everything queued for clients to the workers serving
+            // them.
+            io_workers.write(&mut messages_to_write)?;
         }
     });
 
-    let mut state: PokerState = config.game_settings.into();
+    run_manager(ManagerContext {
+        game_settings: config.game_settings,
+        server_timeouts: config.server_timeouts,
+        max_tables: config.max_tables,
+        store: config.store,
+        hand_history: config.hand_history,
+        rake: config.rake,
+        status_board,
+        live_settings: config.live_settings,
+        shutdown: config.shutdown,
+        rx_manager,
+        tx_manager,
+        tx_server,
+        // Wakes are lost if the waker is dropped before the networking
+        // thread sees them, so this one has to outlive the manager.
+        waker: waker.clone(),
+    })?;
+    io_thread
+        .join()
+        .map_err(|_| anyhow!("networking thread panicked"))??;
+    info!("shut down");
+    Ok(())
+}
+
+/// Everything the table manager needs to run the server's tables.
+struct ManagerContext {
+    game_settings: GameSettings,
+    server_timeouts: ServerTimeouts,
+    max_tables: usize,
+    store: Option<Box<dyn Store>>,
+    hand_history: Option<PathBuf>,
+    rake: Option<RakeSettings>,
+    status_board: StatusBoard,
+    live_settings: LiveConfig,
+    shutdown: ShutdownSignal,
+    rx_manager: Receiver<ManagerData>,
+    tx_manager: SyncSender<ManagerData>,
+    tx_server: SyncSender<ServerData>,
+    waker: IoWaker,
+}
+
+/// Run the server's tables, routing user commands from the networking
+/// thread to the table each user is at. Returns once every table stopped
+/// because the server is shutting down.
+fn run_manager(context: ManagerContext) -> Result<(), Error> {
+    let ManagerContext {
+        game_settings,
+        server_timeouts,
+        max_tables,
+        mut store,
+        hand_history,
+        rake,
+        status_board,
+        live_settings,
+        shutdown,
+        rx_manager,
+        tx_manager,
+        tx_server,
+        waker,
+    } = context;
+    // Restore tables from the last time the server ran. The first table
+    // always uses the configured settings, but keeps its users.
+    let mut snapshots = match store {
+        Some(ref mut store) => store.load()?,
+        None => BTreeMap::new(),
+    };
+    let mut tables = vec![(
+        game_settings,
+        snapshots.remove(&DEFAULT_TABLE_ID).map(|s| s.game),
+    )];
+    tables.extend(snapshots.into_values().map(|s| (s.settings, Some(s.game))));
+
+    // The server always hosts at least one table. New users join this
+    // table when they first connect.
+    let mut table_manager = TableManager::new(max_tables);
+    // Rake collected since the server started.
+    let mut rake_ledger = RakeLedger::default();
+    // Users' results from every run of the server that had a store.
+    let mut leaderboard = match store {
+        Some(ref mut store) => store.load_leaderboard()?,
+        None => Leaderboard::default(),
+    };
+    // Each table's latest users and their money, for dumping from the
+    // console.
+    let mut table_snapshots: BTreeMap<TableId, GameSnapshot> = BTreeMap::new();
+    for (mut game_settings, snapshot) in tables {
+        game_settings.rake.clone_from(&rake);
+        let (table_id, rx_client) = match table_manager.new_table(game_settings.clone()) {
+            Ok(table) => table,
+            Err(error) if table_manager.get_settings(DEFAULT_TABLE_ID).is_some() => {
+                warn!("couldn't restore a table: {error}");
+                continue;
+            }
+            Err(error) => bail!(error),
+        };
+        spawn_table(TableContext {
+            table_id,
+            game_settings,
+            server_timeouts,
+            snapshot,
+            hand_history: hand_history.clone(),
+            status_board: status_board.clone(),
+            live_settings: live_settings.clone(),
+            shutdown: shutdown.clone(),
+            rx_client,
+            tx_manager: tx_manager.clone(),
+            tx_server: tx_server.clone(),
+            waker: waker.clone(),
+        });
+    }
+
+    loop {
+        match rx_manager.recv()? {
+            ManagerData::Client(msg) => {
+                let result = match msg.command {
+                    // Users restored from a previous run reconnect to the table
+                    // they were at.
+                    UserCommand::Connect => {
+                        let table_id = table_manager
+                            .get_table_id_with_username(&msg.username)
+                            .unwrap_or(DEFAULT_TABLE_ID);
+                        table_manager
+                            .join_table(&msg.username, table_id)
+                            .map(|_| table_manager.send(table_id, msg.clone()))
+                    }
+                    // Chat doesn't affect the game, so it's relayed to everyone
+                    // at the user's table without bothering the table.
+                    UserCommand::Chat(ref message) => table_manager
+                        .get_table_id_with_username(&msg.username)
+                        .map(|table_id| {
+                            let msg = ServerData::Chat {
+                                username: msg.username.clone(),
+                                message: message.clone(),
+                                usernames: table_manager.get_usernames_at_table(table_id),
+                            };
+                            tx_server.send(msg)?;
+                            waker.wake()?;
+                            Ok(())
+                        }),
+                    // Admins can force users at any table to fold, and pause
+                    // or change the blinds at any table.
+                    UserCommand::Admin(
+                        AdminOp::ForceFold(_)
+                        | AdminOp::Pause(_)
+                        | AdminOp::Resume(_)
+                        | AdminOp::SetBlinds { .. },
+                    ) => match table_manager.send_admin_op(msg.clone()) {
+                        Ok(()) => Ok(Ok(())),
+                        Err(error) => match error.downcast::<UserError>() {
+                            Ok(error) => Err(error),
+                            Err(error) => Ok(Err(error)),
+                        },
+                    },
+                    UserCommand::Admin(AdminOp::Rake) => {
+                        let msg = ServerData::Response {
+                            username: msg.username.clone(),
+                            data: Box::new(ServerMessage::Rake(rake_ledger.clone())),
+                        };
+                        tx_server.send(msg)?;
+                        waker.wake()?;
+                        Ok(Ok(()))
+                    }
+                    UserCommand::CreateTable(ref settings) => {
+                        // Users can't pick seeds since they'd know every deal,
+                        // and the server decides on the rake.
+                        let settings = GameSettings {
+                            seed: None,
+                            rake: rake.clone(),
+                            ..settings.clone()
+                        };
+                        match table_manager.new_table(settings.clone()) {
+                            Ok((table_id, rx_client)) => {
+                                spawn_table(TableContext {
+                                    table_id,
+                                    game_settings: settings.clone(),
+                                    server_timeouts,
+                                    snapshot: None,
+                                    hand_history: hand_history.clone(),
+                                    status_board: status_board.clone(),
+                                    live_settings: live_settings.clone(),
+                                    shutdown: shutdown.clone(),
+                                    rx_client,
+                                    tx_manager: tx_manager.clone(),
+                                    tx_server: tx_server.clone(),
+                                    waker: waker.clone(),
+                                });
+                                info!(username = %msg.username, "{}", msg.command);
+                                let tables = ServerMessage::Tables(table_manager.get_views());
+                                let msg = ServerData::Response {
+                                    username: msg.username.clone(),
+                                    data: Box::new(tables),
+                                };
+                                tx_server.send(msg)?;
+                                waker.wake()?;
+                                Ok(Ok(()))
+                            }
+                            Err(error) => Err(error),
+                        }
+                    }
+                    UserCommand::JoinTable(table_id) => {
+                        match table_manager.join_table(&msg.username, table_id) {
+                            // The user has to leave their current table before
+                            // they can join the new one.
+                            Ok(Some(current_table_id)) => {
+                                let msg = ClientMessage {
+                                    username: msg.username.clone(),
+                                    command: UserCommand::LeaveTable,
+                                };
+                                Ok(table_manager.send(current_table_id, msg))
+                            }
+                            Ok(None) => Ok(table_manager.send(table_id, msg.clone())),
+                            Err(error) => Err(error),
+                        }
+                    }
+                    UserCommand::Leaderboard(num_users) => {
+                        let standings = ServerMessage::Leaderboard(leaderboard.top(num_users));
+                        let msg = ServerData::Response {
+                            username: msg.username.clone(),
+                            data: Box::new(standings),
+                        };
+                        tx_server.send(msg)?;
+                        waker.wake()?;
+                        Ok(Ok(()))
+                    }
+                    UserCommand::Leave => match table_manager.leave_table(&msg.username) {
+                        Some(table_id) => Ok(table_manager.send(table_id, msg.clone())),
+                        None => Ok(Ok(())),
+                    },
+                    UserCommand::ListTables => {
+                        let tables = ServerMessage::Tables(table_manager.get_views());
+                        let msg = ServerData::Response {
+                            username: msg.username.clone(),
+                            data: Box::new(tables),
+                        };
+                        tx_server.send(msg)?;
+                        waker.wake()?;
+                        Ok(Ok(()))
+                    }
+                    UserCommand::LeaveTable => match table_manager.leave_table(&msg.username) {
+                        Some(table_id) => Ok(table_manager.send(table_id, msg.clone())),
+                        None => Err(UserError::NotAtTable),
+                    },
+                    _ => table_manager
+                        .get_table_id_with_username(&msg.username)
+                        .map(|table_id| table_manager.send(table_id, msg.clone())),
+                };
+                match result {
+                    // Tables stop while shutting down, so commands can race
+                    // with a table's last hand ending.
+                    Ok(Err(error)) if shutdown.is_triggered() => {
+                        debug!(username = %msg.username, "couldn't route {} while shutting down: {error}", msg.command);
+                    }
+                    Ok(result) => result?,
+                    Err(error) => {
+                        error!(username = %msg.username, "{error}: {}", msg.command);
+                        let msg = ServerData::Response {
+                            username: msg.username,
+                            data: Box::new(ServerMessage::UserError(error)),
+                        };
+                        tx_server.send(msg)?;
+                        waker.wake()?;
+                    }
+                }
+            }
+            ManagerData::Control {
+                request,
+                tx_response,
+            } => {
+                let response = match request {
+                    ControlRequest::Admin(AdminOp::Rake) => {
+                        ControlResponse::Rake(rake_ledger.clone())
+                    }
+                    // Connections are managed by the IO thread.
+                    ControlRequest::Admin(AdminOp::Kick(ref username))
+                        if table_manager.get_table_id_with_username(username).is_err() =>
+                    {
+                        ControlResponse::Error(UserError::UserDoesNotExist.to_string())
+                    }
+                    ControlRequest::Admin(op @ (AdminOp::Ban(_) | AdminOp::Kick(_))) => {
+                        let msg = ClientMessage {
+                            username: CONSOLE_USERNAME.into(),
+                            command: UserCommand::Admin(op),
+                        };
+                        tx_server.send(ServerData::Kick(msg))?;
+                        waker.wake()?;
+                        ControlResponse::Done
+                    }
+                    ControlRequest::Admin(op) => {
+                        let msg = ClientMessage {
+                            username: CONSOLE_USERNAME.into(),
+                            command: UserCommand::Admin(op),
+                        };
+                        match table_manager.send_admin_op(msg) {
+                            Ok(()) => ControlResponse::Done,
+                            Err(error) => ControlResponse::Error(error.to_string()),
+                        }
+                    }
+                    ControlRequest::Dump => ControlResponse::Dump(
+                        table_snapshots
+                            .iter()
+                            .filter_map(|(table_id, game)| {
+                                let settings = table_manager.get_settings(*table_id)?;
+                                let snapshot = TableSnapshot {
+                                    settings: settings.clone(),
+                                    game: game.clone(),
+                                };
+                                Some((*table_id, snapshot))
+                            })
+                            .collect(),
+                    ),
+                    ControlRequest::Users => {
+                        ControlResponse::Users(table_manager.get_usernames_by_table())
+                    }
+                };
+                // The console may have given up waiting.
+                let _ = tx_response.send(response);
+            }
+            ManagerData::Joined { username, table_id } => {
+                table_manager.confirm_join(username, table_id);
+            }
+            // Users waiting to join another table can finally join it
+            // once they've left their previous table.
+            ManagerData::Left { username, table_id } => {
+                if let Some(next_table_id) = table_manager.confirm_leave(&username, table_id) {
+                    let msg = ClientMessage {
+                        username,
+                        command: UserCommand::JoinTable(next_table_id),
+                    };
+                    table_manager.send(next_table_id, msg)?;
+                }
+            }
+            ManagerData::Snapshot { table_id, snapshot } => {
+                if let (Some(store), Some(settings)) =
+                    (store.as_mut(), table_manager.get_settings(table_id))
+                {
+                    let snapshot = TableSnapshot {
+                        settings: settings.clone(),
+                        game: snapshot.clone(),
+                    };
+                    if let Err(error) = store.save(table_id, snapshot) {
+                        error!("couldn't save table {table_id}: {error}");
+                    }
+                }
+                table_snapshots.insert(table_id, snapshot);
+            }
+            ManagerData::Raked { table_id, amount } => {
+                rake_ledger.total = rake_ledger.total.saturating_add(amount);
+                let table_rake = rake_ledger.tables.entry(table_id).or_default();
+                *table_rake = table_rake.saturating_add(amount);
+            }
+            ManagerData::Played(hand) => {
+                leaderboard.record_hand(&hand);
+                if let Some(ref mut store) = store {
+                    if let Err(error) = store.save_leaderboard(&leaderboard) {
+                        error!("couldn't save the leaderboard: {error}");
+                    }
+                }
+            }
+            ManagerData::Tournament { table_id, report } => {
+                let orders = table_manager.tournaments.report(table_id, report);
+                table_manager.carry_out_orders(orders, &tx_server, &waker)?;
+            }
+            // Players moved to another table keep their stack, and the
+            // manager hears they joined the new table once it seats them.
+            ManagerData::MovedOut {
+                username,
+                to,
+                user: Some(user),
+            } => {
+                let name = table_manager
+                    .get_settings(to)
+                    .and_then(|settings| settings.tournament.as_ref())
+                    .and_then(|tournament| tournament.name.clone())
+                    .unwrap_or_default();
+                let status =
+                    format!("{username} is moving to table {to} to balance the {name} tournament");
+                info!("{status}");
+                let mut usernames = table_manager.get_usernames_at_table(to);
+                usernames.insert(username);
+                tx_server.send(ServerData::Status { status, usernames })?;
+                waker.wake()?;
+                table_manager.send_order(to, TableData::MoveIn(user));
+            }
+            ManagerData::MovedOut {
+                username,
+                to,
+                user: None,
+            } => {
+                let orders = table_manager.tournaments.cancel_move(&username, to);
+                table_manager.carry_out_orders(orders, &tx_server, &waker)?;
+            }
+            // Tables are saved before they stop, so the server can exit
+            // once the last one stops.
+            ManagerData::Stopped(table_id) => {
+                info!("table {table_id} stopped");
+                table_manager.remove_table(table_id);
+                table_snapshots.remove(&table_id);
+                if table_manager.is_empty() {
+                    tx_server.send(ServerData::Shutdown)?;
+                    waker.wake()?;
+                    break;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Everything a table's thread needs to run the table's poker game.
+struct TableContext {
+    table_id: TableId,
+    game_settings: GameSettings,
+    server_timeouts: ServerTimeouts,
+    /// Users to restore the table with.
+    snapshot: Option<GameSnapshot>,
+    /// Where to append finished hands.
+    hand_history: Option<PathBuf>,
+    /// Where the table posts what it's doing.
+    status_board: StatusBoard,
+    live_settings: LiveConfig,
+    shutdown: ShutdownSignal,
+    rx_client: Receiver<TableData>,
+    tx_manager: SyncSender<ManagerData>,
+    tx_server: SyncSender<ServerData>,
+    waker: IoWaker,
+}
+
+/// Spawn a thread that manages a table's poker game state.
+fn spawn_table(context: TableContext) {
+    thread::spawn(move || -> Result<(), Error> {
+        // Everything the table logs carries the table's ID, along with
+        // the number of the hand being played once hands start.
+        let span = info_span!("table", table_id = context.table_id, hand = field::Empty);
+        let _entered = span.enter();
+        let table_id = context.table_id;
+        let status_board = context.status_board.clone();
+        let result = run_table(context);
+        status_board.remove(table_id);
+        if let Err(ref error) = result {
+            error!("table stopped: {error}");
+        }
+        result
+    });
+}
+
+/// Let users know how long the player whose turn it is has left to act.
+fn time_turn(views: &mut GameViews, turn_timer: Option<TurnTimerView>) {
+    for view in views.values_mut() {
+        view.turn_timer = turn_timer;
+    }
+}
+
+/// Let the table manager know which users joined or left a table since
+/// the table's views were last updated.
+fn update_table_usernames(
+    table_id: TableId,
+    usernames: &mut HashSet<Username>,
+    views: &GameViews,
+    tx_manager: &SyncSender<ManagerData>,
+) -> Result<(), Error> {
+    update_table_joins(table_id, usernames, views, tx_manager)?;
+    update_table_leaves(table_id, usernames, views, tx_manager)
+}
+
+/// Let the table manager know which users joined a table since the
+/// table's views were last updated.
+fn update_table_joins(
+    table_id: TableId,
+    usernames: &mut HashSet<Username>,
+    views: &GameViews,
+    tx_manager: &SyncSender<ManagerData>,
+) -> Result<(), Error> {
+    for username in views.keys() {
+        if usernames.insert(username.clone()) {
+            let msg = ManagerData::Joined {
+                username: username.clone(),
+                table_id,
+            };
+            tx_manager.send(msg)?;
+        }
+    }
+    Ok(())
+}
+
+/// Let the table manager know which users left a table since the table's
+/// views were last updated. Users moving to another table join it as soon
+/// as the manager hears they left.
+fn update_table_leaves(
+    table_id: TableId,
+    usernames: &mut HashSet<Username>,
+    views: &GameViews,
+    tx_manager: &SyncSender<ManagerData>,
+) -> Result<(), Error> {
+    for username in usernames.iter().filter(|u| !views.contains_key(*u)) {
+        let msg = ManagerData::Left {
+            username: username.clone(),
+            table_id,
+        };
+        tx_manager.send(msg)?;
+    }
+    usernames.retain(|username| views.contains_key(username));
+    Ok(())
+}
+
+/// Let the table manager know when a table's users or their money changed
+/// so the table can be persisted.
+fn update_table_snapshot(
+    table_id: TableId,
+    snapshot: &mut Option<GameSnapshot>,
+    state: &PokerState,
+    tx_manager: &SyncSender<ManagerData>,
+) -> Result<(), Error> {
+    if let Some(new_snapshot) = state.get_snapshot() {
+        if snapshot.as_ref() != Some(&new_snapshot) {
+            *snapshot = Some(new_snapshot.clone());
+            let msg = ManagerData::Snapshot {
+                table_id,
+                snapshot: new_snapshot,
+            };
+            tx_manager.send(msg)?;
+        }
+    }
+    Ok(())
+}
+
+/// Let the table manager know when a multi-table tournament's table
+/// changed in a way that matters for balancing the tournament's tables.
+/// Other tables don't have a report to send.
+fn update_table_report(
+    table_id: TableId,
+    report: &mut Option<TableReport>,
+    state: &PokerState,
+    tx_manager: &SyncSender<ManagerData>,
+) -> Result<(), Error> {
+    if let Some(report) = report {
+        let new_report = TableReport::new(state);
+        if *report != new_report {
+            *report = new_report.clone();
+            let msg = ManagerData::Tournament {
+                table_id,
+                report: new_report,
+            };
+            tx_manager.send(msg)?;
+        }
+    }
+    Ok(())
+}
+
+/// Take players the table manager is moving to other tables out of a
+/// multi-table tournament's table once it's between hands. The manager
+/// hears what the table looks like without them before it hears where
+/// they're going.
+fn move_out_players(
+    table_id: TableId,
+    pending_moves: &mut Vec<(Username, TableId)>,
+    report: &mut Option<TableReport>,
+    state: &mut PokerState,
+    tx_manager: &SyncSender<ManagerData>,
+) -> Result<(), Error> {
+    if pending_moves.is_empty() || !state.is_in_lobby() {
+        return Ok(());
+    }
+    let moves: Vec<_> = pending_moves
+        .drain(..)
+        .map(|(username, to)| {
+            let user = state.move_out_player(&username).ok();
+            ManagerData::MovedOut { username, to, user }
+        })
+        .collect();
+    update_table_report(table_id, report, state, tx_manager)?;
+    for msg in moves {
+        tx_manager.send(msg)?;
+    }
+    Ok(())
+}
+
+/// Describe what changed between two views of a table's tournament.
+/// Multi-table tournaments are won across tables, so their winners are
+/// announced by the table manager instead.
+fn get_tournament_statuses(
+    old: &TournamentView,
+    new: &TournamentView,
+    is_multi_table: bool,
+    chip_display: ChipDisplay,
+) -> Vec<String> {
+    let mut statuses = Vec::with_capacity(2);
+    if new.time_to_next_level.is_some() {
+        // A tournament that starts right after the last one goes back
+        // to the first level without ever stopping in between.
+        if old.time_to_next_level.is_none() || new.level < old.level {
+            statuses.push(format!(
+                "the tournament started with blinds at level {}",
+                new.level
+            ));
+        } else if new.level > old.level {
+            let level = new.level;
+            statuses.push(format!("tournament blinds increased to level {level}"));
+            if old.is_registration_open && !new.is_registration_open {
+                statuses.push(format!(
+                    "late registration closed with {} entries",
+                    new.entries
+                ));
+            }
+        }
+    }
+    if old.winner != new.winner && !is_multi_table {
+        if let Some(ref winner) = new.winner {
+            statuses.push(format!(
+                "{winner} won the tournament and its {} prize pool",
+                chip_display.chips(new.prize_pool)
+            ));
+            if !new.bounties_won.is_empty() {
+                statuses.push(format!("bounties: {}", new.bounties_to_string()));
+            }
+        }
+    }
+    statuses
+}
+
+/// Post what the table is doing for the status endpoint.
+fn update_table_status(table_id: TableId, state: &PokerState, status_board: &StatusBoard) {
+    let view = state.get_public_view();
+    let status = TableStatus {
+        phase: state.to_string(),
+        players: view.players.into_iter().map(|p| p.user.name).collect(),
+        waitlist_size: view.waitlist.len(),
+    };
+    status_board.update(table_id, status);
+}
+
+/// Run a table's poker game, stepping the game state at fixed intervals
+/// and in response to user commands routed to the table.
+fn run_table(context: TableContext) -> Result<(), Error> {
+    let TableContext {
+        table_id,
+        game_settings,
+        server_timeouts,
+        mut snapshot,
+        hand_history,
+        status_board,
+        live_settings,
+        shutdown,
+        rx_client,
+        tx_manager,
+        tx_server,
+        waker,
+    } = context;
+    // Settings changed while the server is running override these.
+    let default_turn_timeout = game_settings.turn_timeout;
+    let default_max_spectators = game_settings.max_spectators;
+    let chip_display = game_settings.chip_display;
+    // Tournament chips aren't money, so only cash games count toward the
+    // leaderboard.
+    let is_cash_game = game_settings.tournament.is_none();
+    // Multi-table tournaments are won across all their tables, so the
+    // table manager announces their winners instead.
+    let is_multi_table = game_settings
+        .tournament
+        .as_ref()
+        .is_some_and(|tournament| tournament.name.is_some());
+    let mut report = is_multi_table.then(TableReport::default);
+    // Players the table manager is moving to other tables once the hand
+    // being played is over.
+    let mut pending_moves = Vec::new();
+    // Tables playing hand-for-hand wait between hands until the table
+    // manager says every table finished its hand.
+    let mut can_deal = false;
+    // Restored users aren't connected yet, so they can connect without
+    // being added to the game again.
+    let mut restored_usernames = HashSet::new();
+    let mut state: PokerState = match snapshot.clone() {
+        Some(snapshot) => {
+            restored_usernames.extend(
+                snapshot
+                    .spectators
+                    .iter()
+                    .chain(snapshot.waitlist.iter())
+                    .chain(snapshot.players.iter().map(|(_, user)| user))
+                    .map(|user| user.name.clone()),
+            );
+            PokerState::restore(game_settings, snapshot)
+        }
+        None => game_settings.into(),
+    };
+    let mut usernames = HashSet::new();
+    let mut recent_hands: VecDeque<HandHistory> = VecDeque::with_capacity(MAX_RECENT_HANDS);
+    // Hands played since the table started, for telling them apart in
+    // the logs.
+    let mut hand_num: usize = 0;
     let mut status = state.to_string();
+    let mut tournament = state.get_tournament_view();
+    // Paused tables finish the hand being played but don't start another.
+    let mut is_paused = false;
     loop {
         // Order is kind of key here. We get the status string before
         // we step so we can inform users what's happening rather than
@@ -693,19 +2516,159 @@ pub fn run(addr: &str, config: PokerConfig) -> Result<(), Error> {
         if status != repr {
             info!("{repr}");
             status = repr;
-            let msg = ServerData::Status(status.clone());
+            let msg = ServerData::Status {
+                status: status.clone(),
+                usernames: usernames.clone(),
+            };
             tx_server.send(msg)?;
             waker.wake()?;
         }
-        state = state.step();
+        let is_holding = (is_paused
+            && matches!(state, PokerState::Lobby(_) | PokerState::SeatPlayers(_)))
+            || (state.is_in_lobby()
+                && !can_deal
+                && state
+                    .get_tournament_view()
+                    .is_some_and(|tournament| tournament.is_hand_for_hand));
+        if !is_holding {
+            state = state.step();
+        }
+        if !state.is_in_lobby() {
+            can_deal = false;
+        }
+        move_out_players(
+            table_id,
+            &mut pending_moves,
+            &mut report,
+            &mut state,
+            &tx_manager,
+        )?;
+        if matches!(state, PokerState::MoveButton(_)) {
+            hand_num += 1;
+            Span::current().record("hand", hand_num);
+        }
+        if let Some(hand) = state.get_hand_history() {
+            // Every hand is audited so mistakes that make or lose money
+            // show up in the logs right away.
+            let audit = serde_json::to_string(&hand.audit)?;
+            if hand.is_balanced() {
+                info!(%audit, "hand audit");
+            } else {
+                error!(
+                    %audit,
+                    rake = hand.rake,
+                    jackpot_drop = hand.jackpot_drop,
+                    "money in the hand doesn't add up"
+                );
+            }
+            if let Some(showdown) = hand.showdown() {
+                for username in usernames.iter() {
+                    let msg = ServerData::Response {
+                        username: username.clone(),
+                        data: Box::new(ServerMessage::Showdown(showdown.clone())),
+                    };
+                    tx_server.send(msg)?;
+                }
+                waker.wake()?;
+            }
+            if hand.rake > 0 {
+                let amount = hand.rake;
+                tx_manager.send(ManagerData::Raked { table_id, amount })?;
+            }
+            let statuses = hand
+                .jackpot_payout
+                .iter()
+                .map(|payout| payout.to_string())
+                .chain(hand.knockouts.iter().map(|knockout| knockout.to_string()));
+            for status in statuses {
+                info!("{status}");
+                let msg = ServerData::Status {
+                    status,
+                    usernames: usernames.clone(),
+                };
+                tx_server.send(msg)?;
+                waker.wake()?;
+            }
+            if let Some(ref path) = hand_history {
+                if let Err(error) = persistence::append_hand_history(path, &hand) {
+                    error!("couldn't record hand history: {error}");
+                }
+            }
+            if is_cash_game {
+                tx_manager.send(ManagerData::Played(Box::new(hand.clone())))?;
+            }
+            if recent_hands.len() == MAX_RECENT_HANDS {
+                recent_hands.pop_front();
+            }
+            recent_hands.push_back(hand);
+        }
 
-        let views = state.get_views();
+        // Let users know when a tournament starts, when its blinds
+        // increase, or when it's been won.
+        let new_tournament = state.get_tournament_view();
+        if let (Some(old), Some(new)) = (&tournament, &new_tournament) {
+            let statuses = get_tournament_statuses(old, new, is_multi_table, chip_display);
+            for status in statuses {
+                info!("{status}");
+                let msg = ServerData::Status {
+                    status,
+                    usernames: usernames.clone(),
+                };
+                tx_server.send(msg)?;
+                waker.wake()?;
+            }
+        }
+        tournament = new_tournament;
+
+        // Pick up settings changed while the server is running.
+        let live = live_settings.get();
+        let turn_timeout = live
+            .turn_timeout
+            .map_or(default_turn_timeout, Duration::from_secs);
+
+        // A turn starts with the full timeout right after these views
+        // are sent if anyone has a turn to take.
+        let mut views = state.get_views();
+        let turn_timer = state.get_action_options().map(|_| TurnTimerView {
+            timeout: turn_timeout,
+            remaining: turn_timeout,
+        });
+        time_turn(&mut views, turn_timer);
+        update_table_usernames(table_id, &mut usernames, &views, &tx_manager)?;
+        update_table_status(table_id, &state, &status_board);
+        update_table_snapshot(table_id, &mut snapshot, &state, &tx_manager)?;
+        update_table_report(table_id, &mut report, &state, &tx_manager)?;
         let msg = ServerData::Views(views);
         tx_server.send(msg)?;
         waker.wake()?;
 
+        // Players that lost at showdown get until the next step to show
+        // their hands before they're mucked.
+        for username in state.get_showdown_losers() {
+            let msg = ServerData::Response {
+                username,
+                data: Box::new(ServerMessage::ShowOrMuck),
+            };
+            tx_server.send(msg)?;
+            waker.wake()?;
+        }
+
+        state.set_max_spectators(live.max_spectators.unwrap_or(default_max_spectators));
+
+        // Don't start another hand if the server is shutting down. The
+        // table was just saved, so it can stop right away.
+        if shutdown.is_triggered() && state.is_in_lobby() {
+            tx_manager.send(ManagerData::Stopped(table_id))?;
+            return Ok(());
+        }
+
         let mut next_action_username = state.get_next_action_username();
-        let mut timeout = config.server_timeouts.step;
+        // Streets dealt during a run-out go by quicker than other steps.
+        let mut timeout = if state.is_running_out() {
+            server_timeouts.runout
+        } else {
+            server_timeouts.step
+        };
         'command: loop {
             // Check if it's a user's turn. If so, send them a turn signal
             // and increase the timeout to give them time to make their
@@ -722,26 +2685,35 @@ pub fn run(addr: &str, config: PokerConfig) -> Result<(), Error> {
                         if timeout.as_secs() == 0 && &username == last_username {
                             // Ack that they will fold (the poker state will
                             // fold for them).
-                            warn!("{username} ran out of time and will be forced to fold");
+                            warn!(%username, "ran out of time and will be forced to fold");
                             let command = UserCommand::TakeAction(Action::Fold);
-                            let msg = ServerData::Ack(ClientMessage {
-                                username: username.clone(),
-                                command,
-                            });
+                            let msg = ServerData::Ack {
+                                msg: ClientMessage {
+                                    username: username.clone(),
+                                    command,
+                                },
+                                usernames: usernames.clone(),
+                            };
                             tx_server.send(msg)?;
                             waker.wake()?;
 
                             // Force remove them so they don't disrupt future games.
-                            warn!("{username} will be removed at the end of the game");
+                            warn!(%username, "will be removed at the end of the game");
                             state.remove_user(&username)?;
 
                             break 'command;
                         } else {
                             // Let all users know whose turn it is.
+                            let options = Game::<TakeAction>::action_options_to_string(
+                                &action_options,
+                                chip_display,
+                            );
+                            let status = format!("it's {username}'s turn and they can {options}");
                             let turn_signal = ServerMessage::TurnSignal(action_options);
-                            let status =
-                                format!("it's {username}'s turn and they can {turn_signal}");
-                            let msg = ServerData::Status(status.clone());
+                            let msg = ServerData::Status {
+                                status: status.clone(),
+                                usernames: usernames.clone(),
+                            };
                             tx_server.send(msg)?;
                             waker.wake()?;
 
@@ -755,7 +2727,7 @@ pub fn run(addr: &str, config: PokerConfig) -> Result<(), Error> {
                             waker.wake()?;
 
                             next_action_username = Some(username);
-                            timeout = config.server_timeouts.action;
+                            timeout = turn_timeout;
                         }
                     }
                 }
@@ -772,14 +2744,98 @@ pub fn run(addr: &str, config: PokerConfig) -> Result<(), Error> {
             // IO thread.
             while timeout.as_secs() > 0 {
                 let start = Instant::now();
-                if let Ok(mut msg) = rx_client.recv_timeout(timeout) {
+                if let Ok(data) = rx_client.recv_timeout(timeout) {
+                    let mut msg = match data {
+                        TableData::Client(msg) => msg,
+                        // Orders from the table manager for balancing a
+                        // multi-table tournament come without a user to
+                        // ack, but users still see what changed.
+                        order => {
+                            match order {
+                                TableData::MoveOut { username, to } => {
+                                    pending_moves.push((username, to));
+                                    move_out_players(
+                                        table_id,
+                                        &mut pending_moves,
+                                        &mut report,
+                                        &mut state,
+                                        &tx_manager,
+                                    )?;
+                                }
+                                TableData::MoveIn(user) => state.move_in_player(user),
+                                TableData::FinishTournament => state.finish_tournament(),
+                                TableData::HandForHand(is_hand_for_hand) => {
+                                    state.set_hand_for_hand(is_hand_for_hand);
+                                }
+                                TableData::DealNextHand => {
+                                    if state.is_in_lobby() {
+                                        can_deal = true;
+                                        timeout = Duration::ZERO;
+                                    }
+                                }
+                                TableData::Client(_) => unreachable!("user commands are matched"),
+                            }
+                            let mut views = state.get_views();
+                            let remaining = timeout.saturating_sub(start.elapsed());
+                            let turn_timer = state
+                                .get_action_options()
+                                .filter(|_| !remaining.is_zero())
+                                .map(|_| TurnTimerView {
+                                    timeout: turn_timeout,
+                                    remaining,
+                                });
+                            time_turn(&mut views, turn_timer);
+                            update_table_usernames(table_id, &mut usernames, &views, &tx_manager)?;
+                            update_table_snapshot(table_id, &mut snapshot, &state, &tx_manager)?;
+                            update_table_report(table_id, &mut report, &state, &tx_manager)?;
+                            update_table_status(table_id, &state, &status_board);
+                            let msg = ServerData::Views(views);
+                            tx_server.send(msg)?;
+                            waker.wake()?;
+                            timeout = timeout.saturating_sub(Instant::now() - start);
+                            continue;
+                        }
+                    };
                     let result = match msg.command {
                         UserCommand::ChangeState(ref new_user_state) => match new_user_state {
                             UserState::Play => state.waitlist_user(&msg.username),
+                            UserState::SitOut => state.sit_out_user(&msg.username),
                             UserState::Spectate => state.spectate_user(&msg.username),
                         },
-                        UserCommand::Connect => state.new_user(&msg.username),
-                        UserCommand::Leave => state.remove_user(&msg.username),
+                        UserCommand::BuyIn(amount) => state.buy_in_user(&msg.username, amount),
+                        UserCommand::TakeSeat(seat_idx) => {
+                            state.waitlist_user_at_seat(&msg.username, seat_idx)
+                        }
+                        UserCommand::Connect | UserCommand::JoinTable(_) => {
+                            // Users restored from a previous run and users
+                            // reconnecting after their connection dropped are
+                            // already at the table. The IO thread only lets
+                            // users connect with a username that isn't in use,
+                            // and only lets users whose connection dropped back
+                            // in with the session they were given.
+                            let returning = restored_usernames.remove(&msg.username)
+                                || msg.command == UserCommand::Connect;
+                            if returning && usernames.contains(&msg.username) {
+                                Ok(())
+                            } else {
+                                state.new_user(&msg.username)
+                            }
+                        }
+                        UserCommand::Leave | UserCommand::LeaveTable => {
+                            state.remove_user(&msg.username)
+                        }
+                        UserCommand::ListHands => Ok(()),
+                        // There's no need to wait once every loser has
+                        // mucked.
+                        UserCommand::MuckHand => state.muck_hand(&msg.username).map(|_| {
+                            if state.get_showdown_losers().is_empty() {
+                                timeout = Duration::ZERO;
+                            }
+                        }),
+                        UserCommand::RunItTwice(consent) => {
+                            state.run_it_twice(&msg.username, consent)
+                        }
+                        UserCommand::BombPot(vote) => state.vote_bomb_pot(&msg.username, vote),
                         UserCommand::ShowHand => state.show_hand(&msg.username),
                         UserCommand::StartGame => state.init_start(&msg.username),
                         UserCommand::TakeAction(ref mut action) => state
@@ -788,6 +2844,34 @@ pub fn run(addr: &str, config: PokerConfig) -> Result<(), Error> {
                                 timeout = Duration::ZERO;
                                 *action = new_action;
                             }),
+                        UserCommand::Admin(AdminOp::ForceFold(ref username)) => state
+                            .take_action(username, Action::Fold)
+                            .map(|_| timeout = Duration::ZERO),
+                        UserCommand::Admin(AdminOp::Pause(_)) => {
+                            is_paused = true;
+                            Ok(())
+                        }
+                        UserCommand::Admin(AdminOp::Resume(_)) => {
+                            is_paused = false;
+                            Ok(())
+                        }
+                        UserCommand::Admin(AdminOp::SetBlinds {
+                            small_blind,
+                            big_blind,
+                            ..
+                        }) => state.set_blinds(small_blind, big_blind),
+                        UserCommand::Admin(_)
+                        | UserCommand::Authenticate(_)
+                        | UserCommand::Pong
+                        | UserCommand::Reconnect(_) => {
+                            unreachable!("connections are managed by the IO thread")
+                        }
+                        UserCommand::Chat(_)
+                        | UserCommand::CreateTable(_)
+                        | UserCommand::Leaderboard(_)
+                        | UserCommand::ListTables => {
+                            unreachable!("table commands are handled by the table manager")
+                        }
                     };
 
                     // Get the result from a client's command. If their command
@@ -795,21 +2879,69 @@ pub fn run(addr: &str, config: PokerConfig) -> Result<(), Error> {
                     // happened. If their command is bad, send an error back to
                     // the commanding client.
                     match result {
+                        // Looking back at hands doesn't change the game, so the
+                        // hands go straight to the user instead of acking
+                        // everyone.
+                        Ok(()) if msg.command == UserCommand::ListHands => {
+                            let hands =
+                                ServerMessage::Hands(recent_hands.iter().cloned().collect());
+                            let msg = ServerData::Response {
+                                username: msg.username,
+                                data: Box::new(hands),
+                            };
+                            tx_server.send(msg)?;
+                            waker.wake()?;
+                        }
                         Ok(()) => {
-                            info!("{msg}");
-                            let msg = ServerData::Ack(msg);
+                            info!(username = %msg.username, "{}", msg.command);
+                            // Users leaving the table still get the ack so they
+                            // know they left. The ack is sent before the table
+                            // manager hears about users leaving so users moving
+                            // to another table get it before the new table's.
+                            // Turns keep counting down through other
+                            // users' commands, and end with actions.
+                            let mut views = state.get_views();
+                            let remaining = timeout.saturating_sub(start.elapsed());
+                            let turn_timer = state
+                                .get_action_options()
+                                .filter(|_| !remaining.is_zero())
+                                .map(|_| TurnTimerView {
+                                    timeout: turn_timeout,
+                                    remaining,
+                                });
+                            time_turn(&mut views, turn_timer);
+                            let mut ack_usernames = usernames.clone();
+                            ack_usernames.insert(msg.username.clone());
+                            update_table_joins(table_id, &mut usernames, &views, &tx_manager)?;
+                            let msg = ServerData::Ack {
+                                msg,
+                                usernames: ack_usernames.union(&usernames).cloned().collect(),
+                            };
                             tx_server.send(msg)?;
                             waker.wake()?;
+                            update_table_leaves(table_id, &mut usernames, &views, &tx_manager)?;
+                            update_table_snapshot(table_id, &mut snapshot, &state, &tx_manager)?;
+                            update_table_status(table_id, &state, &status_board);
 
-                            let msg = ServerData::Views(state.get_views());
+                            let msg = ServerData::Views(views);
                             tx_server.send(msg)?;
                             waker.wake()?;
                         }
                         Err(error) => {
-                            error!("{error}: {msg}");
+                            error!(username = %msg.username, "{error}: {}", msg.command);
+                            // Users that can't even watch have no reason to
+                            // stay connected.
+                            let data = match error {
+                                UserError::SpectatorsFull
+                                    if msg.command == UserCommand::Connect =>
+                                {
+                                    ServerMessage::ClientError(ClientError::SpectatorsFull)
+                                }
+                                _ => ServerMessage::UserError(error),
+                            };
                             let msg = ServerData::Response {
                                 username: msg.username,
-                                data: Box::new(ServerMessage::UserError(error)),
+                                data: Box::new(data),
                             };
                             tx_server.send(msg)?;
                             waker.wake()?;
@@ -824,49 +2956,31 @@ pub fn run(addr: &str, config: PokerConfig) -> Result<(), Error> {
 
 #[cfg(test)]
 mod tests {
-    use std::time::Duration;
-
-    use mio::{
-        net::{TcpListener, TcpStream},
-        Token,
-    };
-
-    use crate::net::messages::ClientError;
+    use std::{collections::VecDeque, sync::mpsc::sync_channel, time::Duration};
 
-    use super::TokenManager;
+    use mio::Token;
 
-    fn get_random_open_port() -> u16 {
-        let addr = "127.0.0.1:0".parse().unwrap();
-        // Bind to port 0, which tells the OS to assign an available port
-        let listener = TcpListener::bind(addr).unwrap();
-        // Get the assigned port
-        listener.local_addr().unwrap().port()
-    }
-
-    fn get_server() -> TcpListener {
-        let port = get_random_open_port();
-        let addr = format!("127.0.0.1:{port}").parse().unwrap();
-        TcpListener::bind(addr).unwrap()
-    }
+    use crate::{
+        game::{
+            entities::{ChipDisplay, TournamentView},
+            PokerState,
+        },
+        net::messages::{ClientError, ClientMessage, ServerMessage, UserCommand},
+    };
 
-    fn get_stream(listener: &TcpListener) -> TcpStream {
-        let port = listener.local_addr().unwrap().port();
-        let addr = format!("127.0.0.1:{port}").parse().unwrap();
-        TcpStream::connect(addr).unwrap();
-        let (stream, _) = listener.accept().unwrap();
-        stream
-    }
+    use super::{
+        drop_stale_views, get_tournament_statuses, ManagerData, ManagerSender, TokenManager,
+        Username,
+    };
 
     #[test]
     fn confirm_username() {
-        let server = get_server();
-        let stream = get_stream(&server);
         let mut token_manager = TokenManager::new(Duration::ZERO);
 
         let token = token_manager.new_token();
-        token_manager.associate_token_and_stream(token, stream);
+        token_manager.associate_token_and_connection(token, 0);
 
-        let username = "ognf".to_string();
+        let username = Username::from("ognf");
         assert_eq!(
             token_manager.get_token_with_username(&username),
             Err(ClientError::Unassociated)
@@ -887,15 +3001,13 @@ mod tests {
 
     #[test]
     fn confirm_username_recycled_token() {
-        let server = get_server();
-        let stream = get_stream(&server);
         let mut token_manager = TokenManager::new(Duration::ZERO);
 
         let token = token_manager.new_token();
-        token_manager.associate_token_and_stream(token, stream);
+        token_manager.associate_token_and_connection(token, 0);
         token_manager.recycle_expired_tokens();
 
-        let username = "ognf".to_string();
+        let username = Username::from("ognf");
         assert_eq!(
             token_manager.get_token_with_username(&username),
             Err(ClientError::Unassociated)
@@ -908,44 +3020,36 @@ mod tests {
 
     #[test]
     fn recycle_expired_tokens() {
-        let server = get_server();
-        let stream1 = get_stream(&server);
-        let stream2 = get_stream(&server);
-        let stream3 = get_stream(&server);
-        let stream4 = get_stream(&server);
         let mut token_manager = TokenManager::new(Duration::ZERO);
 
         // Create a couple of tokens and immediately recycle them.
         let token1 = token_manager.new_token();
-        token_manager.associate_token_and_stream(token1, stream1);
+        token_manager.associate_token_and_connection(token1, 0);
         let token2 = token_manager.new_token();
-        token_manager.associate_token_and_stream(token2, stream2);
+        token_manager.associate_token_and_connection(token2, 1);
         token_manager.recycle_expired_tokens();
 
         // Tokens are immediately resused.
         let token3 = token_manager.new_token();
-        token_manager.associate_token_and_stream(token1, stream3);
+        token_manager.associate_token_and_connection(token1, 2);
         let token4 = token_manager.new_token();
-        token_manager.associate_token_and_stream(token2, stream4);
-        assert_eq!(token1, Token(2));
+        token_manager.associate_token_and_connection(token2, 3);
+        assert_eq!(token1, Token(3));
         assert_eq!(token1, token3);
-        assert_eq!(token2, Token(3));
+        assert_eq!(token2, Token(4));
         assert_eq!(token2, token4);
     }
 
     #[test]
     fn recycle_token() {
-        let server = get_server();
-        let stream1 = get_stream(&server);
-        let stream2 = get_stream(&server);
         let mut token_manager = TokenManager::new(Duration::ZERO);
 
         let token1 = token_manager.new_token();
-        token_manager.associate_token_and_stream(token1, stream1);
+        token_manager.associate_token_and_connection(token1, 0);
         let token2 = token_manager.new_token();
-        token_manager.associate_token_and_stream(token2, stream2);
+        token_manager.associate_token_and_connection(token2, 1);
 
-        let username = "ognf".to_string();
+        let username = Username::from("ognf");
         assert_eq!(
             token_manager.associate_token_and_username(token1, username.clone()),
             Ok(())
@@ -954,11 +3058,89 @@ mod tests {
             token_manager.associate_token_and_username(token2, username.clone()),
             Err(ClientError::AlreadyAssociated)
         );
-        assert!(token_manager.recycle_token(token1).is_ok());
+        assert_eq!(token_manager.recycle_token(token1), Ok(0));
         assert_eq!(
             token_manager.associate_token_and_username(token2, username),
             Ok(())
         );
-        assert_eq!(token1, token_manager.new_token());
+        let token3 = token_manager.new_token();
+        assert_eq!(token1, token3);
+
+        // Events from the old connection aren't mistaken for the new one's.
+        token_manager.associate_token_and_connection(token3, 2);
+        assert_eq!(token_manager.get_connection_id_with_token(&token1), Ok(2));
+    }
+
+    #[test]
+    fn announce_tournament_levels() {
+        let view = |level, is_running: bool| TournamentView {
+            level,
+            time_to_next_level: is_running.then_some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        let statuses = |old, new| get_tournament_statuses(&old, &new, false, ChipDisplay::Chips);
+
+        assert_eq!(
+            statuses(view(1, false), view(1, true)),
+            vec!["the tournament started with blinds at level 1"]
+        );
+        assert_eq!(
+            statuses(view(1, true), view(2, true)),
+            vec!["tournament blinds increased to level 2"]
+        );
+        assert!(statuses(view(2, true), view(2, true)).is_empty());
+        // The next tournament starts back at the first level, which isn't
+        // an increase.
+        assert_eq!(
+            statuses(view(7, true), view(1, true)),
+            vec!["the tournament started with blinds at level 1"]
+        );
+        assert!(statuses(view(7, true), view(7, false)).is_empty());
+    }
+
+    #[test]
+    fn drop_only_stale_views() {
+        let view = || ServerMessage::GameView(Box::new(PokerState::new().get_revealed_view()));
+        let status = |status: &str| ServerMessage::Status(status.to_string());
+        let mut messages = VecDeque::from([view(), status("0"), view(), status("1"), view()]);
+        assert_eq!(drop_stale_views(&mut messages), 2);
+        assert_eq!(messages.len(), 3);
+        assert!(matches!(messages[0], ServerMessage::Status(ref status) if status == "0"));
+        assert!(matches!(messages[1], ServerMessage::Status(ref status) if status == "1"));
+        assert!(matches!(messages[2], ServerMessage::GameView(_)));
+
+        // Nothing's dropped without any views.
+        let mut messages = VecDeque::from([status("0")]);
+        assert_eq!(drop_stale_views(&mut messages), 0);
+        assert_eq!(messages.len(), 1);
+    }
+
+    #[test]
+    fn hold_client_messages_until_the_manager_has_room() {
+        let (tx_manager, rx_manager) = sync_channel(2);
+        let mut tx_client = ManagerSender::new(tx_manager);
+        let msg = |username: &str| ClientMessage {
+            username: Username::from(username),
+            command: UserCommand::Leave,
+        };
+        for username in ["0", "1", "2", "3"] {
+            tx_client.send(msg(username)).unwrap();
+        }
+        assert!(tx_client.is_backlogged());
+
+        // Messages reach the manager in the order they were sent.
+        let mut received = Vec::new();
+        while received.len() < 4 {
+            while let Ok(ManagerData::Client(msg)) = rx_manager.try_recv() {
+                received.push(msg.username.to_string());
+            }
+            tx_client.flush().unwrap();
+        }
+        assert_eq!(received, ["0", "1", "2", "3"]);
+        assert!(!tx_client.is_backlogged());
+
+        // The manager going away is an error rather than a backlog.
+        drop(rx_manager);
+        assert!(tx_client.send(msg("4")).is_err());
     }
 }
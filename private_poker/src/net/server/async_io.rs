@@ -0,0 +1,882 @@
+//! The server's networking on tokio.
+//!
+//! [`run_async`] hosts the same tables as [`super::run`] and speaks the
+//! same length-prefixed protocol, so it can stand in for it in
+//! applications that already run an async runtime. Only the networking
+//! is async; the table manager and tables still block on their own
+//! threads. Every connection gets a task that writes the frames queued
+//! for it and another that reads its messages, while a hub keeps track
+//! of who's connected like the networking thread of the mio server does.
+//! WebSocket connections aren't supported yet, but QUIC connections are
+//! with the `quic` feature.
+
+use anyhow::{anyhow, bail, Error};
+use std::{
+    collections::{HashMap, HashSet},
+    net::{IpAddr, SocketAddr},
+    sync::{mpsc::sync_channel, Arc},
+    time::{Duration, Instant},
+};
+use tokio::{
+    io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::{mpsc, Notify},
+    task::{self, JoinSet},
+    time::{self, Interval, MissedTickBehavior},
+};
+use tokio_rustls::TlsAcceptor;
+use tracing::{debug, error, info, warn};
+
+#[cfg(feature = "quic")]
+use quinn::Endpoint;
+
+#[cfg(feature = "quic")]
+use super::super::quic;
+
+use crate::game::{entities::Username, UserError};
+
+use super::{
+    super::{
+        auth::Credentials,
+        control::CONSOLE_USERNAME,
+        messages::{
+            AdminOp, ClientError, ClientMessage, ServerMessage, SessionToken, UserCommand,
+            UserState,
+        },
+        socket::{FD_PREFIX, UNIX_PREFIX},
+        status::{self, StatusBoard},
+        utils::{self, Framing},
+    },
+    run_manager, spawn_console, truncate_user_input, IoWaker, ManagerContext, ManagerSender,
+    PokerConfig, ServerData, ServerTimeouts, BACKLOG_RETRY_INTERVAL, MAX_MISSED_HEARTBEATS,
+    MAX_NETWORK_EVENTS_PER_USER, MAX_QUEUED_SERVER_DATA,
+};
+
+/// How long writing a frame can take before the connection is considered
+/// dead.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+type ClientId = u64;
+
+/// A connection that's waiting to be served.
+enum NewConnection {
+    Tcp(TcpStream),
+    #[cfg(feature = "quic")]
+    Quic(Box<quinn::Incoming>),
+}
+
+/// Something that happened on a client's connection.
+enum ConnectionEvent {
+    Message {
+        id: ClientId,
+        /// How the client's messages are framed as of this message.
+        framing: Framing,
+        msg: Box<ClientMessage>,
+    },
+    /// The connection closed or stopped following the protocol.
+    Dropped(ClientId),
+}
+
+struct Client {
+    /// Frames waiting to be written to the client.
+    tx: mpsc::Sender<Vec<u8>>,
+    /// The latest view that didn't fit in the client's queue. Each view
+    /// replaces the one before it, so only this one is sent once the
+    /// client catches up.
+    pending_view: Option<Vec<u8>>,
+    framing: Framing,
+    ip: IpAddr,
+    /// The username the client connected with, which the table manager
+    /// has to confirm before the client is treated as a user.
+    username: Option<Username>,
+    /// The session the user was given when they connected.
+    session: Option<SessionToken>,
+    is_confirmed: bool,
+    connected_at: Instant,
+    last_heard: Instant,
+    /// When the client last sent a command other than a pong.
+    last_active: Instant,
+    /// Whether the user was moved to the spectators for being idle.
+    is_idle: bool,
+}
+
+/// Tracks connected clients and relays messages between them and the
+/// table manager.
+struct Hub {
+    admins: HashSet<Username>,
+    banned_ips: HashSet<IpAddr>,
+    banned_usernames: HashSet<Username>,
+    clients: HashMap<ClientId, Client>,
+    credentials: Option<Credentials>,
+    /// Users whose connections dropped, when they dropped, and the
+    /// session they were given. They stay at their table until the
+    /// reconnect timeout expires, holding their spot for whoever
+    /// reconnects with that session.
+    disconnected_usernames: HashMap<Username, (Instant, SessionToken)>,
+    /// Clients that fall this many frames behind only get their latest
+    /// view, and are removed if they fall behind on anything else.
+    max_queued_frames: usize,
+    next_id: ClientId,
+    server_timeouts: ServerTimeouts,
+    tls: Option<TlsAcceptor>,
+    tx_events: mpsc::Sender<ConnectionEvent>,
+    tx_manager: ManagerSender,
+    usernames_to_ids: HashMap<Username, ClientId>,
+}
+
+impl Hub {
+    /// Start serving a new connection.
+    fn accept(
+        &mut self,
+        connection: NewConnection,
+        peer_addr: SocketAddr,
+        connections: &mut JoinSet<()>,
+    ) {
+        if self.banned_ips.contains(&peer_addr.ip()) {
+            debug!("rejected connection from banned address {peer_addr}");
+            return;
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        let (tx, rx) = mpsc::channel(self.max_queued_frames);
+        let now = Instant::now();
+        let client = Client {
+            tx,
+            pending_view: None,
+            framing: Framing::default(),
+            ip: peer_addr.ip(),
+            username: None,
+            session: None,
+            is_confirmed: false,
+            connected_at: now,
+            last_heard: now,
+            last_active: now,
+            is_idle: false,
+        };
+        self.clients.insert(id, client);
+        let tls = self.tls.clone();
+        let tx_events = self.tx_events.clone();
+        connections.spawn(async move {
+            match (connection, tls) {
+                (NewConnection::Tcp(stream), Some(tls)) => match tls.accept(stream).await {
+                    Ok(stream) => serve(id, stream, rx, tx_events).await,
+                    Err(error) => {
+                        debug!("client {id} failed the TLS handshake: {error}");
+                        let _ = tx_events.send(ConnectionEvent::Dropped(id)).await;
+                    }
+                },
+                (NewConnection::Tcp(stream), None) => serve(id, stream, rx, tx_events).await,
+                #[cfg(feature = "quic")]
+                (NewConnection::Quic(incoming), _) => {
+                    let open = async {
+                        let connection = (*incoming).await?;
+                        let (send, recv) = connection.accept_bi().await?;
+                        Ok::<_, quinn::ConnectionError>((connection, send, recv))
+                    };
+                    match open.await {
+                        Ok((connection, send, recv)) => {
+                            serve(id, io::join(recv, send), rx, tx_events).await;
+                            // Closing the connection right away could lose
+                            // the last frames, so the client gets a chance
+                            // to close it first.
+                            let _ = time::timeout(WRITE_TIMEOUT, connection.closed()).await;
+                        }
+                        Err(error) => {
+                            debug!("client {id} failed to open a QUIC stream: {error}");
+                            let _ = tx_events.send(ConnectionEvent::Dropped(id)).await;
+                        }
+                    }
+                }
+            }
+        });
+        debug!("accepted new connection from {peer_addr} as client {id}");
+    }
+
+    fn associate(&mut self, id: ClientId, username: Username) -> Result<(), ClientError> {
+        let client = self.clients.get_mut(&id).ok_or(ClientError::DoesNotExist)?;
+        if client.username.is_some() || self.usernames_to_ids.contains_key(&username) {
+            return Err(ClientError::AlreadyAssociated);
+        }
+        client.username = Some(username.clone());
+        self.usernames_to_ids.insert(username, id);
+        Ok(())
+    }
+
+    /// Queue a message for everyone that's connected.
+    fn broadcast(&mut self, msg: &ServerMessage) -> Result<(), Error> {
+        let ids: Vec<_> = self
+            .clients
+            .iter()
+            .filter(|(_, client)| client.is_confirmed)
+            .map(|(id, _)| *id)
+            .collect();
+        for id in ids {
+            self.send(id, msg)?;
+        }
+        Ok(())
+    }
+
+    /// Remove clients that took too long to say who they are, move idle
+    /// users to the spectators and then disconnect them, and give up the
+    /// spots of users that didn't reconnect in time.
+    fn expire(&mut self) -> Result<(), Error> {
+        let expired_ids: Vec<_> = self
+            .clients
+            .iter()
+            .filter(|(_, client)| {
+                client.username.is_none()
+                    && client.connected_at.elapsed() >= self.server_timeouts.connect
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        for id in expired_ids {
+            debug!("client {id} expired");
+            self.remove(id, false)?;
+        }
+        let idle = self.server_timeouts.idle;
+        if !idle.is_zero() {
+            let idle_ids: Vec<_> = self
+                .clients
+                .iter()
+                .filter(|(_, client)| client.is_confirmed)
+                .map(|(id, client)| (*id, client.last_active.elapsed()))
+                .filter(|(_, idle_for)| *idle_for >= idle)
+                .collect();
+            for (id, idle_for) in idle_ids {
+                let client = self.clients.get_mut(&id).expect("client exists");
+                let username = client
+                    .username
+                    .clone()
+                    .expect("confirmed clients have usernames");
+                if idle_for >= idle * 2 {
+                    info!(%username, "was idle for too long");
+                    self.remove(id, false)?;
+                } else if !client.is_idle {
+                    client.is_idle = true;
+                    info!(%username, "is idle and will spectate");
+                    let msg = ClientMessage {
+                        username,
+                        command: UserCommand::ChangeState(UserState::Spectate),
+                    };
+                    self.tx_manager.send(msg)?;
+                }
+            }
+        }
+        let expired_usernames: Vec<_> = self
+            .disconnected_usernames
+            .iter()
+            .filter(|(_, (t, _))| t.elapsed() >= self.server_timeouts.reconnect)
+            .map(|(username, _)| username.clone())
+            .collect();
+        for username in expired_usernames {
+            self.disconnected_usernames.remove(&username);
+            debug!(%username, "didn't reconnect in time");
+            let msg = ClientMessage {
+                username,
+                command: UserCommand::Leave,
+            };
+            self.tx_manager.send(msg)?;
+        }
+        Ok(())
+    }
+
+    fn handle_event(&mut self, event: ConnectionEvent) -> Result<(), Error> {
+        match event {
+            ConnectionEvent::Message { id, framing, msg } => {
+                // Clients can be removed before their last messages are
+                // handled (e.g., if they're kicked).
+                if let Some(client) = self.clients.get_mut(&id) {
+                    client.framing = framing;
+                    client.last_heard = Instant::now();
+                    self.handle_message(id, *msg)?;
+                }
+                Ok(())
+            }
+            ConnectionEvent::Dropped(id) => self.remove(id, true),
+        }
+    }
+
+    fn handle_message(&mut self, id: ClientId, mut msg: ClientMessage) -> Result<(), Error> {
+        let result = match msg.command {
+            // Names that would break commands or displays are turned away.
+            UserCommand::Authenticate(_) | UserCommand::Connect | UserCommand::Reconnect(_)
+                if msg.username.validate().is_err() =>
+            {
+                msg.username
+                    .validate()
+                    .map_err(ClientError::InvalidUsername)
+            }
+            // Banned users can't connect again.
+            UserCommand::Authenticate(_) | UserCommand::Connect | UserCommand::Reconnect(_)
+                if self.banned_usernames.contains(&msg.username) =>
+            {
+                Err(ClientError::Banned)
+            }
+            // Nobody can pass themselves off as the server console.
+            UserCommand::Authenticate(_) | UserCommand::Connect | UserCommand::Reconnect(_)
+                if msg.username == CONSOLE_USERNAME =>
+            {
+                Err(ClientError::AlreadyAssociated)
+            }
+            // Only the user that lost their connection can take their spot
+            // back. Users that authenticate have already proven who they
+            // are, but anyone can claim a username, so they have to come
+            // back with the session they were given, or with their
+            // password on servers that have them.
+            UserCommand::Connect | UserCommand::Reconnect(_)
+                if self
+                    .disconnected_usernames
+                    .get(&msg.username)
+                    .is_some_and(|(_, session)| {
+                        !matches!(msg.command, UserCommand::Reconnect(ref s) if s == session)
+                    }) =>
+            {
+                Err(if self.credentials.is_some() {
+                    ClientError::AuthenticationFailed
+                } else {
+                    ClientError::AlreadyAssociated
+                })
+            }
+            // Check the user's password before connecting them. The
+            // password is dropped so it's never sent to the table.
+            UserCommand::Authenticate(ref password) => {
+                let result = match self.credentials.as_mut() {
+                    Some(credentials) => credentials.authenticate(&msg.username, password),
+                    None => Ok(()),
+                };
+                msg.command = UserCommand::Connect;
+                result.and_then(|_| self.associate(id, msg.username.clone()))
+            }
+            // Users can't connect without a password if the server
+            // requires authentication. Sessions only stand in for a
+            // password while the user's spot is held.
+            UserCommand::Connect | UserCommand::Reconnect(_)
+                if self.credentials.is_some()
+                    && !self.disconnected_usernames.contains_key(&msg.username) =>
+            {
+                Err(ClientError::AuthenticationFailed)
+            }
+            UserCommand::Connect | UserCommand::Reconnect(_) => {
+                msg.command = UserCommand::Connect;
+                self.associate(id, msg.username.clone())
+            }
+            // Check if the client is being faithful and sending messages
+            // with the correct username.
+            _ => match self.usernames_to_ids.get(&msg.username) {
+                Some(associated_id) if *associated_id == id => Ok(()),
+                _ => Err(ClientError::Unassociated),
+            },
+        };
+        if result.is_ok() && msg.command == UserCommand::Connect {
+            if self.disconnected_usernames.remove(&msg.username).is_some() {
+                info!(username = %msg.username, "reconnected");
+            }
+            let session = SessionToken::generate();
+            if let Some(client) = self.clients.get_mut(&id) {
+                client.session = Some(session.clone());
+            }
+            self.send(id, &ServerMessage::Session(session))?;
+        }
+        // Pongs are sent without the user doing anything, so they don't
+        // keep users from going idle.
+        if result.is_ok() && msg.command != UserCommand::Pong {
+            if let Some(client) = self.clients.get_mut(&id) {
+                client.last_active = Instant::now();
+                client.is_idle = false;
+            }
+        }
+        match result {
+            Ok(_) => match msg.command {
+                // Pongs only keep the connection alive, which receiving
+                // them already did.
+                UserCommand::Pong => {}
+                UserCommand::Admin(_) if !self.admins.contains(&msg.username) => {
+                    debug!(username = %msg.username, "client {id}: {}: {}", msg.command, UserError::NotAnAdmin);
+                    self.send(id, &ServerMessage::UserError(UserError::NotAnAdmin))?;
+                }
+                // Kicks and bans are about connections rather than games,
+                // so they're handled here. Everyone on the server is told
+                // about them.
+                UserCommand::Admin(AdminOp::Ban(_) | AdminOp::Kick(_)) => {
+                    if !self.kick(&msg)? {
+                        debug!(username = %msg.username, "client {id}: {}: {}", msg.command, UserError::UserDoesNotExist);
+                        let error = ServerMessage::UserError(UserError::UserDoesNotExist);
+                        return self.send(id, &error);
+                    }
+                }
+                _ => {
+                    debug!(username = %msg.username, "client {id}: {}", msg.command);
+                    self.tx_manager.send(msg)?;
+                }
+            },
+            // Client errors are strict and result in the removal of a
+            // connection.
+            Err(error) => {
+                debug!("client {id}: {error}");
+                self.send(id, &ServerMessage::ClientError(error))?;
+                self.remove(id, false)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Disconnect the target of a kick or ban, banning them too if it's
+    /// a ban, and let everyone know. Returns whether there was anyone to
+    /// kick.
+    fn kick(&mut self, msg: &ClientMessage) -> Result<bool, Error> {
+        let UserCommand::Admin(AdminOp::Ban(ref target) | AdminOp::Kick(ref target)) = msg.command
+        else {
+            return Ok(false);
+        };
+        let target_id = self.usernames_to_ids.get(target).copied();
+        if let UserCommand::Admin(AdminOp::Ban(_)) = msg.command {
+            self.banned_usernames.insert(target.clone());
+            if let Some(client) = target_id.and_then(|id| self.clients.get(&id)) {
+                self.banned_ips.insert(client.ip);
+            }
+        } else if target_id.is_none() {
+            return Ok(false);
+        }
+        if let Some(target_id) = target_id {
+            self.remove(target_id, false)?;
+        }
+        info!(username = %msg.username, "{}", msg.command);
+        self.broadcast(&ServerMessage::Ack(msg.clone()))?;
+        Ok(true)
+    }
+
+    /// Relay data from the tables to the users it's meant for, returning
+    /// whether the server is done.
+    fn handle_server_data(&mut self, data: ServerData) -> Result<bool, Error> {
+        match data {
+            ServerData::Ack { msg, usernames } => {
+                // Users are confirmed once their table has them. Users that
+                // disconnected before then have to leave the table again.
+                if msg.command == UserCommand::Connect {
+                    let client = self
+                        .usernames_to_ids
+                        .get(&msg.username)
+                        .and_then(|id| self.clients.get_mut(id));
+                    match client {
+                        Some(client) => client.is_confirmed = true,
+                        None => {
+                            let msg = ClientMessage {
+                                username: msg.username.clone(),
+                                command: UserCommand::Leave,
+                            };
+                            self.tx_manager.send(msg)?;
+                        }
+                    }
+                }
+                let ack = ServerMessage::Ack(msg);
+                for username in usernames {
+                    self.send_to_user(&username, &ack)?;
+                }
+            }
+            ServerData::Chat {
+                username,
+                message,
+                usernames,
+            } => {
+                let chat = ServerMessage::Chat { username, message };
+                for recipient in usernames {
+                    self.send_to_user(&recipient, &chat)?;
+                }
+            }
+            ServerData::Response { username, data } => {
+                self.send_to_user(&username, &data)?;
+                // Client errors are just as strict coming from the tables.
+                if let ServerMessage::ClientError(_) = *data {
+                    if let Some(id) = self.usernames_to_ids.get(&username) {
+                        self.remove(*id, false)?;
+                    }
+                }
+            }
+            ServerData::Status { status, usernames } => {
+                let status = ServerMessage::Status(status);
+                for username in usernames {
+                    self.send_to_user(&username, &status)?;
+                }
+            }
+            ServerData::Kick(msg) => {
+                self.kick(&msg)?;
+            }
+            ServerData::Shutdown => {
+                self.broadcast(&ServerMessage::Status("the server shut down".to_string()))?;
+                return Ok(true);
+            }
+            ServerData::Views(views) => {
+                for (username, view) in views {
+                    self.send_to_user(&username, &ServerMessage::GameView(Box::new(view)))?;
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Ping everyone, and treat connections that have been quiet for too
+    /// long like any other dropped connection so their users can still
+    /// reconnect.
+    fn heartbeat(&mut self) -> Result<(), Error> {
+        let max_quiet = self.server_timeouts.heartbeat * MAX_MISSED_HEARTBEATS;
+        let ids: Vec<_> = self
+            .clients
+            .iter()
+            .filter(|(_, client)| client.is_confirmed)
+            .map(|(id, client)| (*id, client.last_heard.elapsed() >= max_quiet))
+            .collect();
+        for (id, is_quiet) in ids {
+            if is_quiet {
+                debug!("client {id} missed too many heartbeats");
+                self.remove(id, true)?;
+            } else {
+                self.send(id, &ServerMessage::Ping)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Forget a client. Its connection closes once everything queued for
+    /// it has been written. Users that lost their connection get a chance
+    /// to reconnect before they're removed from their table.
+    fn remove(&mut self, id: ClientId, dropped: bool) -> Result<(), Error> {
+        let Some(client) = self.clients.remove(&id) else {
+            return Ok(());
+        };
+        debug!("client {id} is being removed");
+        let Some(username) = client.username else {
+            return Ok(());
+        };
+        self.usernames_to_ids.remove(&username);
+        if !client.is_confirmed {
+            return Ok(());
+        }
+        if let Some(session) = client
+            .session
+            .filter(|_| dropped && !self.server_timeouts.reconnect.is_zero())
+        {
+            info!(
+                "{username} disconnected and has {:?} to reconnect",
+                self.server_timeouts.reconnect
+            );
+            self.disconnected_usernames
+                .insert(username, (Instant::now(), session));
+        } else {
+            let msg = ClientMessage {
+                username,
+                command: UserCommand::Leave,
+            };
+            self.tx_manager.send(msg)?;
+        }
+        Ok(())
+    }
+
+    /// Queue a message for a client. Unresponsive or misbehaving clients
+    /// that don't keep up with their messages are removed.
+    fn send(&mut self, id: ClientId, msg: &ServerMessage) -> Result<(), Error> {
+        let Some(client) = self.clients.get_mut(&id) else {
+            return Ok(());
+        };
+        // A view that didn't fit has to go out before anything newer.
+        if let Some(view) = client.pending_view.take() {
+            if let Err(mpsc::error::TrySendError::Full(view)) = client.tx.try_send(view) {
+                client.pending_view = Some(view);
+            }
+        }
+        let frame = utils::encode_frame(msg, client.framing)?;
+        let result = match client.pending_view {
+            Some(_) => Err(mpsc::error::TrySendError::Full(frame)),
+            None => client.tx.try_send(frame),
+        };
+        match result {
+            Err(mpsc::error::TrySendError::Full(frame))
+                if matches!(msg, ServerMessage::GameView(_)) =>
+            {
+                if client.pending_view.replace(frame).is_none() {
+                    warn!("client {id} is falling behind, so stale views will be dropped");
+                }
+                Ok(())
+            }
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                error!("client {id} has not been receiving and will be removed.");
+                self.remove(id, false)
+            }
+            // The connection is closing and the hub will hear about it
+            // soon.
+            Ok(_) | Err(mpsc::error::TrySendError::Closed(_)) => Ok(()),
+        }
+    }
+
+    fn send_to_user(&mut self, username: &str, msg: &ServerMessage) -> Result<(), Error> {
+        match self.usernames_to_ids.get(username) {
+            Some(id) => self.send(*id, msg),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Intervals can't be empty, so zero periods tick as often as tokio's
+/// timer allows.
+fn interval(period: Duration) -> Interval {
+    let mut interval = time::interval(period.max(Duration::from_millis(1)));
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    interval
+}
+
+async fn read_message<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    framing: &mut Framing,
+) -> io::Result<ClientMessage> {
+    let mut len_bytes = [0; 4];
+    reader.read_exact(&mut len_bytes).await?;
+    while framing.negotiate(len_bytes) {
+        reader.read_exact(&mut len_bytes).await?;
+    }
+    let mut buf = vec![0; utils::frame_len(len_bytes)?];
+    reader.read_exact(&mut buf).await?;
+    utils::decode_frame(len_bytes, buf, framing.codec)
+}
+
+/// Forward a client's messages to the hub until the connection drops or
+/// the client stops following the protocol.
+async fn read_messages<R: AsyncRead + Unpin>(
+    id: ClientId,
+    mut reader: R,
+    tx_events: mpsc::Sender<ConnectionEvent>,
+) {
+    let mut framing = Framing::default();
+    loop {
+        match read_message(&mut reader, &mut framing).await {
+            Ok(mut msg) => {
+                truncate_user_input(&mut msg);
+                let event = ConnectionEvent::Message {
+                    id,
+                    framing,
+                    msg: Box::new(msg),
+                };
+                if tx_events.send(event).await.is_err() {
+                    return;
+                }
+            }
+            Err(error) => {
+                debug!("client {id}'s connection dropped: {error}");
+                let _ = tx_events.send(ConnectionEvent::Dropped(id)).await;
+                return;
+            }
+        }
+    }
+}
+
+/// Write the frames queued for a client until the hub forgets about it,
+/// reading the client's messages in the meantime.
+async fn serve<S>(
+    id: ClientId,
+    stream: S,
+    mut rx: mpsc::Receiver<Vec<u8>>,
+    tx_events: mpsc::Sender<ConnectionEvent>,
+) where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (reader, mut writer) = io::split(stream);
+    let reading = task::spawn(read_messages(id, reader, tx_events.clone()));
+    while let Some(frame) = rx.recv().await {
+        let write = async {
+            writer.write_all(&frame).await?;
+            writer.flush().await
+        };
+        if !matches!(time::timeout(WRITE_TIMEOUT, write).await, Ok(Ok(_))) {
+            debug!("client {id}'s connection dropped");
+            let _ = tx_events.send(ConnectionEvent::Dropped(id)).await;
+            break;
+        }
+    }
+    reading.abort();
+    let _ = time::timeout(WRITE_TIMEOUT, writer.shutdown()).await;
+}
+
+/// Start accepting QUIC connections if the server has an address for
+/// them, passing them along to the hub.
+#[cfg(feature = "quic")]
+fn listen_quic(
+    config: &PokerConfig,
+    tx_accepted: mpsc::Sender<(NewConnection, SocketAddr)>,
+) -> Result<Option<task::JoinHandle<()>>, Error> {
+    let Some(quic_addr) = &config.quic_addr else {
+        return Ok(None);
+    };
+    let Some(tls) = &config.tls else {
+        bail!("QUIC connections need a TLS certificate");
+    };
+    let endpoint = Endpoint::server(quic::server_config(tls.clone())?, quic_addr.parse()?)?;
+    let listening = task::spawn(async move {
+        while let Some(incoming) = endpoint.accept().await {
+            let peer_addr = incoming.remote_address();
+            let connection = NewConnection::Quic(Box::new(incoming));
+            if tx_accepted.send((connection, peer_addr)).await.is_err() {
+                break;
+            }
+        }
+    });
+    Ok(Some(listening))
+}
+
+#[cfg(not(feature = "quic"))]
+fn listen_quic(
+    config: &PokerConfig,
+    _tx_accepted: mpsc::Sender<(NewConnection, SocketAddr)>,
+) -> Result<Option<task::JoinHandle<()>>, Error> {
+    if config.quic_addr.is_some() {
+        bail!("the server was built without the quic feature");
+    }
+    Ok(None)
+}
+
+/// Run the poker server on the current tokio runtime. This takes the same
+/// config as [`super::run`] and behaves the same way, but the networking
+/// runs as tasks instead of on its own thread. Servers with a WebSocket
+/// address, bound to a Unix socket, or behind a PROXY protocol proxy
+/// can't be run this way yet, while servers with a QUIC address can only
+/// be run this way.
+pub async fn run_async(addr: &str, config: PokerConfig) -> Result<(), Error> {
+    config.game_settings.validate()?;
+    if config.websocket_addr.is_some() {
+        bail!("the async server doesn't support WebSocket connections yet");
+    }
+    if config.proxy_protocol {
+        bail!("the async server doesn't support the PROXY protocol yet");
+    }
+    if addr.starts_with(UNIX_PREFIX) {
+        bail!("the async server doesn't support Unix sockets yet");
+    }
+    if addr.starts_with(FD_PREFIX) {
+        bail!("the async server doesn't support inherited sockets yet");
+    }
+    let listener = TcpListener::bind(addr).await?;
+    let status_board = StatusBoard::default();
+    if let Some(ref status_addr) = config.status_addr {
+        status::spawn(status_addr, status_board.clone())?;
+    }
+    let (tx_accepted, mut rx_accepted) = mpsc::channel(1);
+    let quic_listening = listen_quic(&config, tx_accepted)?;
+    let max_queued_frames = config.max_queued_messages;
+    let max_queued_events =
+        MAX_NETWORK_EVENTS_PER_USER * config.game_settings.max_users * config.max_tables;
+
+    let (tx_manager, rx_manager) = sync_channel(max_queued_events);
+    let (tx_server, rx_server) = sync_channel(MAX_QUEUED_SERVER_DATA);
+    let _console = spawn_console(config.console_addr.as_ref(), tx_manager.clone())?;
+    // Readers wait for the hub when it falls behind, which also keeps
+    // spammers in check. The hub stops taking events while the manager is
+    // behind, so readers end up waiting on the manager too.
+    let (tx_events, mut rx_events) = mpsc::channel(max_queued_events);
+    let notify = Arc::new(Notify::new());
+
+    let server_timeouts = config.server_timeouts;
+    let shutdown = config.shutdown.clone();
+    let mut hub = Hub {
+        admins: config.admins,
+        banned_ips: HashSet::new(),
+        banned_usernames: HashSet::new(),
+        clients: HashMap::new(),
+        credentials: config.credentials,
+        disconnected_usernames: HashMap::new(),
+        max_queued_frames,
+        next_id: 0,
+        server_timeouts,
+        tls: config.tls.map(TlsAcceptor::from),
+        tx_events,
+        tx_manager: ManagerSender::new(tx_manager.clone()),
+        usernames_to_ids: HashMap::new(),
+    };
+    // The manager blocks on its channel, so it gets a thread of its own.
+    let context = ManagerContext {
+        game_settings: config.game_settings,
+        server_timeouts,
+        max_tables: config.max_tables,
+        store: config.store,
+        hand_history: config.hand_history,
+        rake: config.rake,
+        status_board,
+        live_settings: config.live_settings,
+        shutdown: config.shutdown,
+        rx_manager,
+        tx_manager,
+        tx_server,
+        waker: IoWaker::Tokio(notify.clone()),
+    };
+    let mut manager = task::spawn_blocking(move || run_manager(context));
+    let mut is_manager_running = true;
+
+    let mut connections = JoinSet::new();
+    let mut heartbeats = interval(server_timeouts.heartbeat);
+    let mut housekeeping = interval(server_timeouts.poll);
+    let mut is_accepting = true;
+    let mut is_done = false;
+    let mut supervisor = config.supervisor;
+    if let Some(ref mut supervisor) = supervisor {
+        supervisor.ready();
+    }
+    while !is_done {
+        tokio::select! {
+            result = listener.accept(), if is_accepting => {
+                let (stream, peer_addr) = result?;
+                hub.accept(NewConnection::Tcp(stream), peer_addr, &mut connections);
+            }
+            Some((connection, peer_addr)) = rx_accepted.recv(), if is_accepting => {
+                hub.accept(connection, peer_addr, &mut connections);
+            }
+            Some(event) = rx_events.recv(), if !hub.tx_manager.is_backlogged() => {
+                hub.handle_event(event)?;
+            }
+            () = time::sleep(BACKLOG_RETRY_INTERVAL), if hub.tx_manager.is_backlogged() => {
+                hub.tx_manager.flush()?;
+            }
+            () = notify.notified() => {
+                while let Ok(data) = rx_server.try_recv() {
+                    is_done |= hub.handle_server_data(data)?;
+                }
+            }
+            _ = heartbeats.tick(), if !server_timeouts.heartbeat.is_zero() => hub.heartbeat()?,
+            _ = housekeeping.tick() => {
+                hub.expire()?;
+                if let Some(ref mut supervisor) = supervisor {
+                    supervisor.alive();
+                }
+            }
+            Some(_) = connections.join_next() => {}
+            // The manager only stops on its own after telling the hub the
+            // server is done, unless something went wrong.
+            result = &mut manager, if is_manager_running => {
+                is_manager_running = false;
+                result.map_err(|_| anyhow!("table manager panicked"))??;
+            }
+        }
+        // Stop accepting connections as soon as a shutdown is requested,
+        // and let everyone know the server is going away.
+        if is_accepting && shutdown.is_triggered() {
+            is_accepting = false;
+            if let Some(quic_listening) = &quic_listening {
+                quic_listening.abort();
+            }
+            info!("shutting down after the current hands finish");
+            let status = "the server is shutting down after the current hand".to_string();
+            hub.broadcast(&ServerMessage::Status(status))?;
+        }
+    }
+
+    // Closing every client's queue lets their connections close once
+    // they've been sent everything, but clients only get so long to
+    // receive it.
+    hub.clients.clear();
+    let drain = async { while connections.join_next().await.is_some() {} };
+    if time::timeout(server_timeouts.connect, drain).await.is_err() {
+        connections.shutdown().await;
+    }
+    if is_manager_running {
+        manager
+            .await
+            .map_err(|_| anyhow!("table manager panicked"))??;
+    }
+    info!("shut down");
+    Ok(())
+}
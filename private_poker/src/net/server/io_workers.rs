@@ -0,0 +1,837 @@
+//! Connection I/O for the mio server.
+//!
+//! Connections are spread over a small pool of worker threads that each
+//! poll their own share of sockets. Workers do everything that touches a
+//! socket: handshakes, PROXY headers, reading and writing messages, and
+//! dealing with clients that stop reading. The networking thread accepts
+//! connections, decides what clients' messages mean, and hands workers
+//! the messages to write, so a slow or misbehaving socket only holds up
+//! the connections on its worker rather than everyone's.
+//!
+//! Workers hand events to the networking thread over a bounded queue.
+//! When it's full, events wait on the worker and the worker stops reading
+//! from its sockets until the networking thread catches up, so clients
+//! sending more than the server can handle are slowed down by TCP rather
+//! than having their messages pile up in memory.
+
+use anyhow::{anyhow, bail, Error};
+use mio::{Events, Interest, Poll, Token, Waker};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io, mem,
+    net::IpAddr,
+    sync::{
+        mpsc::{channel, Receiver, Sender, SyncSender, TrySendError},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+use tracing::{debug, error, warn};
+
+use super::{
+    super::{
+        messages::{ClientMessage, ServerMessage},
+        proxy,
+        socket::NonblockingSocket,
+        transport::Connection,
+    },
+    drop_stale_views, token_to_string, BACKLOG_RETRY_INTERVAL, MAX_NETWORK_EVENTS_PER_USER, WAKER,
+};
+
+/// Identifies a connection for as long as the server runs. Tokens are
+/// reused once connections close, so events are tagged with this too
+/// and events from connections that already closed are ignored.
+pub type ConnectionId = u64;
+
+/// Something the networking thread needs a worker to do.
+enum WorkerCommand {
+    /// Start serving a newly accepted connection.
+    Add {
+        token: Token,
+        id: ConnectionId,
+        stream: Connection<NonblockingSocket>,
+        /// Whether the connection starts with a PROXY protocol header.
+        is_proxied: bool,
+    },
+    /// Queue messages to be written to connections.
+    Write(Vec<(Token, VecDeque<ServerMessage>)>),
+    /// Close a connection.
+    Remove(Token),
+    /// Write what's queued, giving up at the deadline, and exit.
+    Stop(Instant),
+}
+
+/// Something that happened on one of a worker's connections.
+#[derive(Debug)]
+pub enum WorkerEvent {
+    Message {
+        token: Token,
+        id: ConnectionId,
+        msg: Box<ClientMessage>,
+    },
+    /// The connection's PROXY header named the client's address, if the
+    /// proxy knew it.
+    Proxied {
+        token: Token,
+        id: ConnectionId,
+        ip: Option<IpAddr>,
+    },
+    /// The connection dropped, so its user can still reconnect.
+    Dropped { token: Token, id: ConnectionId },
+    /// The connection has to be closed, like when the client is spamming,
+    /// isn't reading, or was sent a client error.
+    Closed { token: Token, id: ConnectionId },
+}
+
+impl WorkerEvent {
+    /// The connection the event happened on.
+    pub fn get_connection(&self) -> (Token, ConnectionId) {
+        match self {
+            WorkerEvent::Message { token, id, .. }
+            | WorkerEvent::Proxied { token, id, .. }
+            | WorkerEvent::Dropped { token, id }
+            | WorkerEvent::Closed { token, id } => (*token, *id),
+        }
+    }
+}
+
+/// A connection being served by a worker.
+struct WorkerConnection {
+    id: ConnectionId,
+    stream: Connection<NonblockingSocket>,
+    messages: VecDeque<ServerMessage>,
+    is_awaiting_proxy_header: bool,
+    /// Set once the networking thread has been told the connection has
+    /// to go, after which nothing else is read from or written to it.
+    is_closing: bool,
+}
+
+/// Read the PROXY protocol header from the start of a connection,
+/// returning the client's IP address if the proxy knew it.
+fn read_proxy_header(stream: &mut Connection<NonblockingSocket>) -> io::Result<Option<IpAddr>> {
+    let socket = stream
+        .get_socket_mut()
+        .ok_or(io::Error::from(io::ErrorKind::NotConnected))?;
+    let addr = proxy::read_header(socket)?;
+    Ok(addr.map(|addr| addr.ip()))
+}
+
+/// A worker thread and its share of the server's connections.
+struct Worker {
+    poll: Poll,
+    connections: HashMap<Token, WorkerConnection>,
+    max_events: usize,
+    max_queued_messages: usize,
+    rx_commands: Receiver<WorkerCommand>,
+    tx_events: SyncSender<WorkerEvent>,
+    /// Events waiting for room in the networking thread's queue. Nothing
+    /// is read from connections while there are any.
+    undelivered: VecDeque<WorkerEvent>,
+    /// Connections that may have data left to read from when reading was
+    /// paused.
+    unread: HashSet<Token>,
+    /// Wakes the networking thread so it handles the worker's events.
+    waker: Arc<Waker>,
+    /// Whether events were sent since the networking thread was last
+    /// woken.
+    has_events: bool,
+}
+
+impl Worker {
+    fn send(&mut self, event: WorkerEvent) -> Result<(), Error> {
+        self.undelivered.push_back(event);
+        self.deliver()
+    }
+
+    /// Send waiting events, in order, for as long as the networking
+    /// thread has room for them.
+    fn deliver(&mut self) -> Result<(), Error> {
+        while let Some(event) = self.undelivered.pop_front() {
+            match self.tx_events.try_send(event) {
+                Ok(()) => self.has_events = true,
+                Err(TrySendError::Full(event)) => {
+                    self.undelivered.push_front(event);
+                    break;
+                }
+                Err(TrySendError::Disconnected(_)) => bail!("the networking thread stopped"),
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether reading is paused until the networking thread catches up.
+    fn is_backlogged(&self) -> bool {
+        !self.undelivered.is_empty()
+    }
+
+    /// Tell the networking thread the connection has to go, and stop
+    /// serving it until it's removed.
+    fn close(&mut self, token: Token, is_dropped: bool) -> Result<(), Error> {
+        let Some(connection) = self.connections.get_mut(&token) else {
+            return Ok(());
+        };
+        if connection.is_closing {
+            return Ok(());
+        }
+        connection.is_closing = true;
+        connection.messages.clear();
+        let id = connection.id;
+        match is_dropped {
+            true => self.send(WorkerEvent::Dropped { token, id }),
+            false => self.send(WorkerEvent::Closed { token, id }),
+        }
+    }
+
+    fn handle_command(&mut self, command: WorkerCommand) -> Result<(), Error> {
+        match command {
+            WorkerCommand::Add {
+                token,
+                id,
+                mut stream,
+                is_proxied,
+            } => {
+                self.poll
+                    .registry()
+                    .register(&mut stream, token, Interest::READABLE)?;
+                let connection = WorkerConnection {
+                    id,
+                    stream,
+                    messages: VecDeque::new(),
+                    is_awaiting_proxy_header: is_proxied,
+                    is_closing: false,
+                };
+                self.connections.insert(token, connection);
+            }
+            WorkerCommand::Write(batch) => {
+                for (token, mut messages) in batch {
+                    let Some(connection) = self
+                        .connections
+                        .get_mut(&token)
+                        .filter(|connection| !connection.is_closing)
+                    else {
+                        continue;
+                    };
+                    connection.messages.append(&mut messages);
+                    self.poll.registry().reregister(
+                        &mut connection.stream,
+                        token,
+                        Interest::READABLE | Interest::WRITABLE,
+                    )?;
+                    // Clients that stop reading would otherwise have their
+                    // messages pile up forever. Only their latest view
+                    // matters, and clients that are still too far behind
+                    // after that are removed.
+                    if connection.messages.len() <= self.max_queued_messages {
+                        continue;
+                    }
+                    let repr = token_to_string(&token);
+                    let num_dropped = drop_stale_views(&mut connection.messages);
+                    if connection.messages.len() > self.max_queued_messages {
+                        error!("{repr} has not been receiving and will be removed.");
+                        self.close(token, false)?;
+                    } else {
+                        warn!(
+                            "{repr} is falling behind, so {num_dropped} stale views were dropped"
+                        );
+                    }
+                }
+            }
+            WorkerCommand::Remove(token) => {
+                if let Some(mut connection) = self.connections.remove(&token) {
+                    self.poll.registry().deregister(&mut connection.stream)?;
+                }
+            }
+            WorkerCommand::Stop(_) => unreachable!("stops are handled by the worker's loop"),
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, token: Token) -> Result<(), Error> {
+        let Some(connection) = self.connections.get_mut(&token) else {
+            return Ok(());
+        };
+        let stream = &mut connection.stream;
+        while let Some(msg) = connection.messages.pop_front() {
+            match stream.write_message(&msg) {
+                Ok(_) => {
+                    // Client errors are strict and result in the removal of a connection.
+                    if let ServerMessage::ClientError(_) = msg {
+                        let repr = token_to_string(&token);
+                        debug!("{repr}: {msg}");
+                        return self.close(token, false);
+                    }
+                }
+                Err(error) => {
+                    match error.kind() {
+                        // If any of these occur, then the connection was probably
+                        // dropped at some point.
+                        io::ErrorKind::BrokenPipe
+                        | io::ErrorKind::ConnectionAborted
+                        | io::ErrorKind::ConnectionReset
+                        | io::ErrorKind::TimedOut
+                        | io::ErrorKind::UnexpectedEof => {
+                            let repr = token_to_string(&token);
+                            debug!("{repr} connection dropped");
+                            return self.close(token, true);
+                        }
+                        // Would block "errors" are the OS's way of saying that the
+                        // connection is not actually ready to perform this I/O operation.
+                        // The connection is still registered as writable, so the rest
+                        // is written once the socket says it can take more.
+                        io::ErrorKind::WouldBlock => {
+                            // The message couldn't be sent, so we need to push it back
+                            // onto the queue so we don't accidentally forget about it.
+                            connection.messages.push_front(msg);
+                        }
+                        // Retry writing in the case that the previous message couldn't
+                        // be finished. This should be infrequent.
+                        io::ErrorKind::WriteZero => {
+                            let repr = token_to_string(&token);
+                            debug!("{repr} got a zero write, but will retry");
+                            connection.messages.push_front(msg);
+                            continue;
+                        }
+                        // Other errors we'll consider fatal.
+                        _ => bail!(error),
+                    }
+                    break;
+                }
+            }
+        }
+        // Encrypted and WebSocket data can be left over when the socket
+        // can't take all of it at once, so keep sending it even if there
+        // aren't any new messages. Errors will show up on the next read or
+        // write.
+        if stream.wants_write() {
+            let _ = stream.flush();
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, token: Token) -> Result<(), Error> {
+        let Some(connection) = self.connections.get_mut(&token) else {
+            return Ok(());
+        };
+        let id = connection.id;
+        if connection.is_awaiting_proxy_header {
+            let repr = token_to_string(&token);
+            match read_proxy_header(&mut connection.stream) {
+                Ok(ip) => {
+                    connection.is_awaiting_proxy_header = false;
+                    self.send(WorkerEvent::Proxied { token, id, ip })?;
+                }
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(error) => {
+                    debug!("{repr} sent an invalid proxy header: {error}");
+                    return self.close(token, false);
+                }
+            }
+        }
+        // We can (maybe) read from the connection.
+        let mut num_messages = 0;
+        loop {
+            if self.is_backlogged() {
+                self.unread.insert(token);
+                return Ok(());
+            }
+            let Some(connection) = self.connections.get_mut(&token) else {
+                return Ok(());
+            };
+            match connection.stream.read_message::<ClientMessage>() {
+                Ok(msg) => {
+                    self.send(WorkerEvent::Message {
+                        token,
+                        id,
+                        msg: Box::new(msg),
+                    })?;
+                    num_messages += 1;
+                    if num_messages >= MAX_NETWORK_EVENTS_PER_USER {
+                        let repr = token_to_string(&token);
+                        error!("{repr} has been spamming and will be removed.");
+                        return self.close(token, false);
+                    }
+                }
+                Err(error) => {
+                    return match error.kind() {
+                        // Messages that only partly arrived are kept until the rest
+                        // does, so an Eof error means the connection was dropped.
+                        io::ErrorKind::BrokenPipe
+                        | io::ErrorKind::ConnectionAborted
+                        | io::ErrorKind::ConnectionReset
+                        | io::ErrorKind::InvalidData
+                        | io::ErrorKind::TimedOut
+                        | io::ErrorKind::UnexpectedEof => {
+                            let repr = token_to_string(&token);
+                            debug!("{repr}'s connection dropped");
+                            self.close(token, true)
+                        }
+                        // Would block "errors" are the OS's way of saying that the
+                        // connection is not actually ready to perform this I/O operation.
+                        io::ErrorKind::WouldBlock => Ok(()),
+                        // Other errors we'll consider fatal.
+                        _ => bail!(error),
+                    };
+                }
+            }
+        }
+    }
+
+    fn run(mut self) -> Result<(), Error> {
+        let mut events = Events::with_capacity(self.max_events);
+        let mut stop_deadline = None;
+        loop {
+            // Exit once everything queued has been sent, or once clients
+            // have had a fair chance to receive it.
+            if let Some(deadline) = stop_deadline {
+                if self
+                    .connections
+                    .values()
+                    .all(|connection| connection.messages.is_empty())
+                    || Instant::now() >= deadline
+                {
+                    return Ok(());
+                }
+            }
+
+            let mut timeout = stop_deadline
+                .map(|deadline: Instant| deadline.saturating_duration_since(Instant::now()));
+            // Readiness is only reported once, so connections left unread
+            // are retried on a timer rather than waited on.
+            if self.is_backlogged() || !self.unread.is_empty() {
+                timeout = Some(timeout.map_or(BACKLOG_RETRY_INTERVAL, |timeout: Duration| {
+                    timeout.min(BACKLOG_RETRY_INTERVAL)
+                }));
+            }
+            if let Err(error) = self.poll.poll(&mut events, timeout) {
+                match error.kind() {
+                    io::ErrorKind::Interrupted => continue,
+                    _ => bail!(error),
+                }
+            }
+
+            for event in events.iter() {
+                let token = event.token();
+                // Commands are checked every time the worker wakes up.
+                if token == WAKER {
+                    continue;
+                }
+                let is_closing = self
+                    .connections
+                    .get(&token)
+                    .is_none_or(|connection| connection.is_closing);
+                if is_closing {
+                    continue;
+                }
+                if event.is_writable() {
+                    self.write(token)?;
+                }
+                let is_closing = self
+                    .connections
+                    .get(&token)
+                    .is_none_or(|connection| connection.is_closing);
+                if event.is_readable() && !is_closing {
+                    self.read(token)?;
+                }
+            }
+
+            while let Ok(command) = self.rx_commands.try_recv() {
+                match command {
+                    WorkerCommand::Stop(deadline) => stop_deadline = Some(deadline),
+                    command => self.handle_command(command)?,
+                }
+            }
+
+            self.deliver()?;
+            if !self.is_backlogged() {
+                for token in mem::take(&mut self.unread) {
+                    let is_closing = self
+                        .connections
+                        .get(&token)
+                        .is_none_or(|connection| connection.is_closing);
+                    if !is_closing {
+                        self.read(token)?;
+                    }
+                }
+            }
+
+            if self.has_events {
+                self.has_events = false;
+                self.waker.wake()?;
+            }
+        }
+    }
+}
+
+/// A worker thread, along with how to reach it.
+struct WorkerHandle {
+    tx_commands: Sender<WorkerCommand>,
+    waker: Waker,
+    thread: JoinHandle<Result<(), Error>>,
+}
+
+/// The worker threads serving the server's connections. A connection
+/// stays on the worker its token maps to for as long as it's open, so
+/// commands for it are always handled in order.
+pub struct IoWorkers {
+    workers: Vec<WorkerHandle>,
+}
+
+impl IoWorkers {
+    /// Start the workers. Their events are sent over the sender, and the
+    /// waker is woken whenever there are new ones. Workers stop reading
+    /// from connections while the sender's queue is full.
+    pub fn spawn(
+        num_workers: usize,
+        max_events: usize,
+        max_queued_messages: usize,
+        tx_events: SyncSender<WorkerEvent>,
+        waker: Arc<Waker>,
+    ) -> Result<Self, Error> {
+        let mut workers = Vec::with_capacity(num_workers);
+        for worker_idx in 0..num_workers {
+            let poll = Poll::new()?;
+            let worker_waker = Waker::new(poll.registry(), WAKER)?;
+            let (tx_commands, rx_commands) = channel();
+            let worker = Worker {
+                poll,
+                connections: HashMap::new(),
+                max_events: max_events.div_ceil(num_workers).max(1),
+                max_queued_messages,
+                rx_commands,
+                tx_events: tx_events.clone(),
+                undelivered: VecDeque::new(),
+                unread: HashSet::new(),
+                waker: waker.clone(),
+                has_events: false,
+            };
+            let thread = thread::spawn(move || {
+                let result = worker.run();
+                if let Err(ref error) = result {
+                    error!("connection worker {worker_idx} failed: {error}");
+                }
+                result
+            });
+            workers.push(WorkerHandle {
+                tx_commands,
+                waker: worker_waker,
+                thread,
+            });
+        }
+        Ok(Self { workers })
+    }
+
+    fn get_worker_idx(&self, token: Token) -> usize {
+        token.0 % self.workers.len()
+    }
+
+    fn send(&self, worker_idx: usize, command: WorkerCommand) -> Result<(), Error> {
+        let worker = &self.workers[worker_idx];
+        worker
+            .tx_commands
+            .send(command)
+            .map_err(|_| anyhow!("connection worker {worker_idx} stopped"))?;
+        worker.waker.wake()?;
+        Ok(())
+    }
+
+    /// Hand a newly accepted connection to its worker.
+    pub fn add(
+        &self,
+        token: Token,
+        id: ConnectionId,
+        stream: Connection<NonblockingSocket>,
+        is_proxied: bool,
+    ) -> Result<(), Error> {
+        let command = WorkerCommand::Add {
+            token,
+            id,
+            stream,
+            is_proxied,
+        };
+        self.send(self.get_worker_idx(token), command)
+    }
+
+    /// Close a connection.
+    pub fn remove(&self, token: Token) -> Result<(), Error> {
+        self.send(self.get_worker_idx(token), WorkerCommand::Remove(token))
+    }
+
+    /// Hand every queued message to the worker serving its connection,
+    /// leaving the queues empty.
+    pub fn write(
+        &self,
+        messages: &mut HashMap<Token, VecDeque<ServerMessage>>,
+    ) -> Result<(), Error> {
+        let mut batches: Vec<Vec<_>> = (0..self.workers.len()).map(|_| Vec::new()).collect();
+        for (token, messages) in messages.iter_mut() {
+            if !messages.is_empty() {
+                batches[self.get_worker_idx(*token)].push((*token, mem::take(messages)));
+            }
+        }
+        for (worker_idx, batch) in batches.into_iter().enumerate() {
+            if !batch.is_empty() {
+                self.send(worker_idx, WorkerCommand::Write(batch))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Have every worker finish writing what's queued, giving up at the
+    /// deadline, and wait for them to exit.
+    pub fn stop(self, deadline: Instant) -> Result<(), Error> {
+        for worker_idx in 0..self.workers.len() {
+            self.send(worker_idx, WorkerCommand::Stop(deadline))?;
+        }
+        for (worker_idx, worker) in self.workers.into_iter().enumerate() {
+            worker
+                .thread
+                .join()
+                .map_err(|_| anyhow!("connection worker {worker_idx} panicked"))??;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mio::{Poll, Token, Waker};
+    use std::{
+        collections::{HashMap, HashSet, VecDeque},
+        io::Write,
+        mem,
+        net::{TcpListener, TcpStream},
+        sync::{
+            mpsc::{channel, sync_channel, SyncSender},
+            Arc,
+        },
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use crate::net::{
+        messages::{ClientMessage, ServerMessage, UserCommand},
+        socket::NonblockingSocket,
+        transport::Connection,
+        utils::{read_prefixed, write_prefixed},
+    };
+
+    use super::{IoWorkers, Worker, WorkerCommand, WorkerEvent};
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Connect a client to a new server socket, returning the client's
+    /// end and the server's end.
+    fn connect() -> (TcpStream, Connection<NonblockingSocket>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client.set_read_timeout(Some(TIMEOUT)).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        stream.set_nonblocking(true).unwrap();
+        let stream = NonblockingSocket::Tcp(mio::net::TcpStream::from_std(stream));
+        (client, stream.into())
+    }
+
+    fn chat(username: &str, message: &str) -> ClientMessage {
+        ClientMessage {
+            username: username.into(),
+            command: UserCommand::Chat(message.to_string()),
+        }
+    }
+
+    /// The connection, username, and chat message of a message event.
+    fn unpack(event: WorkerEvent) -> (Token, String, String) {
+        let WorkerEvent::Message { token, msg, .. } = event else {
+            panic!("expected a message but got {event:?}");
+        };
+        let UserCommand::Chat(message) = msg.command else {
+            panic!("expected a chat but got {msg:?}");
+        };
+        (token, msg.username.to_string(), message)
+    }
+
+    /// A worker that isn't running on its own thread, so tests can step
+    /// it and look at its connections.
+    fn new_worker(tx_events: SyncSender<WorkerEvent>) -> Worker {
+        let poll = Poll::new().unwrap();
+        let waker = Arc::new(Waker::new(poll.registry(), Token(0)).unwrap());
+        let (_, rx_commands) = channel();
+        Worker {
+            poll,
+            connections: HashMap::new(),
+            max_events: 8,
+            max_queued_messages: 8,
+            rx_commands,
+            tx_events,
+            undelivered: VecDeque::new(),
+            unread: HashSet::new(),
+            waker,
+            has_events: false,
+        }
+    }
+
+    /// Read from the worker's connection until it has sent an event.
+    fn read_until_sent(worker: &mut Worker, token: Token) {
+        let start = Instant::now();
+        while !worker.has_events {
+            assert!(start.elapsed() < TIMEOUT, "the worker never sent an event");
+            thread::sleep(Duration::from_millis(1));
+            worker.read(token).unwrap();
+        }
+    }
+
+    #[test]
+    fn connections_are_spread_over_workers() {
+        let poll = Poll::new().unwrap();
+        let waker = Arc::new(Waker::new(poll.registry(), Token(0)).unwrap());
+        let (tx_events, rx_events) = sync_channel(16);
+        let io_workers = IoWorkers::spawn(2, 16, 8, tx_events, waker).unwrap();
+
+        let tokens: Vec<_> = (2..6).map(Token).collect();
+        let worker_idxs: HashSet<_> = tokens
+            .iter()
+            .map(|token| io_workers.get_worker_idx(*token))
+            .collect();
+        assert_eq!(worker_idxs, HashSet::from([0, 1]));
+
+        // Every worker reads from and writes to its own connections.
+        let mut clients = Vec::new();
+        for (id, token) in tokens.iter().enumerate() {
+            let (client, stream) = connect();
+            io_workers.add(*token, id as u64, stream, false).unwrap();
+            clients.push(client);
+        }
+        for (idx, client) in clients.iter_mut().enumerate() {
+            write_prefixed(client, &chat(&idx.to_string(), "hi")).unwrap();
+        }
+        let mut senders = HashSet::new();
+        for _ in 0..tokens.len() {
+            let (token, username, _) = unpack(rx_events.recv_timeout(TIMEOUT).unwrap());
+            assert_eq!(username, (token.0 - 2).to_string());
+            senders.insert(token);
+        }
+        assert_eq!(senders.len(), tokens.len());
+
+        let mut messages: HashMap<_, _> = tokens
+            .iter()
+            .map(|token| {
+                let msg = ServerMessage::Status(format!("to {}", token.0));
+                (*token, VecDeque::from([msg]))
+            })
+            .collect();
+        io_workers.write(&mut messages).unwrap();
+        assert!(messages.values().all(VecDeque::is_empty));
+        for (token, client) in tokens.iter().zip(clients.iter_mut()) {
+            let msg: ServerMessage = read_prefixed(client).unwrap();
+            assert!(
+                matches!(msg, ServerMessage::Status(status) if status == format!("to {}", token.0))
+            );
+        }
+        io_workers.stop(Instant::now() + TIMEOUT).unwrap();
+    }
+
+    #[test]
+    fn backlog_drains_once_there_is_room() {
+        // Room for one event, so the networking thread falls behind as
+        // soon as a client sends two messages.
+        let (tx_events, rx_events) = sync_channel(1);
+        let mut worker = new_worker(tx_events);
+        let token = Token(2);
+        let (mut client, stream) = connect();
+        worker
+            .handle_command(WorkerCommand::Add {
+                token,
+                id: 0,
+                stream,
+                is_proxied: false,
+            })
+            .unwrap();
+
+        let mut frames = Vec::new();
+        for message in ["0", "1", "2"] {
+            write_prefixed(&mut frames, &chat("0", message)).unwrap();
+        }
+        client.write_all(&frames).unwrap();
+        // Give all the messages time to arrive so they're read together.
+        thread::sleep(Duration::from_millis(50));
+        read_until_sent(&mut worker, token);
+
+        // The second message waits on the worker, which stops reading
+        // the third until it's been delivered.
+        assert_eq!(worker.undelivered.len(), 1);
+        assert!(worker.is_backlogged());
+        assert!(worker.unread.contains(&token));
+        worker.read(token).unwrap();
+        assert_eq!(worker.undelivered.len(), 1);
+
+        // Messages are delivered in order as the networking thread
+        // catches up, and reading picks up where it left off.
+        for message in ["0", "1", "2"] {
+            let (_, _, received) = unpack(rx_events.try_recv().unwrap());
+            assert_eq!(received, message);
+            worker.deliver().unwrap();
+            if !worker.is_backlogged() {
+                for token in mem::take(&mut worker.unread) {
+                    worker.read(token).unwrap();
+                }
+            }
+        }
+        assert!(!worker.is_backlogged());
+        assert!(rx_events.try_recv().is_err());
+    }
+
+    #[test]
+    fn dropped_connections_are_cleaned_up() {
+        let (tx_events, rx_events) = sync_channel(4);
+        let mut worker = new_worker(tx_events);
+        let token = Token(2);
+        let (client, stream) = connect();
+        worker
+            .handle_command(WorkerCommand::Add {
+                token,
+                id: 7,
+                stream,
+                is_proxied: false,
+            })
+            .unwrap();
+
+        drop(client);
+        read_until_sent(&mut worker, token);
+        assert!(matches!(
+            rx_events.try_recv().unwrap(),
+            WorkerEvent::Dropped {
+                token: Token(2),
+                id: 7
+            }
+        ));
+
+        // Nothing more is written to or reported for the connection while
+        // the networking thread gets around to removing it.
+        let msg = ServerMessage::Status("hi".into());
+        worker
+            .handle_command(WorkerCommand::Write(vec![(token, VecDeque::from([msg]))]))
+            .unwrap();
+        assert!(worker.connections[&token].messages.is_empty());
+        worker.close(token, true).unwrap();
+        assert!(rx_events.try_recv().is_err());
+
+        worker.handle_command(WorkerCommand::Remove(token)).unwrap();
+        assert!(worker.connections.is_empty());
+
+        // The token can be handed to a new connection.
+        let (mut client, stream) = connect();
+        worker
+            .handle_command(WorkerCommand::Add {
+                token,
+                id: 8,
+                stream,
+                is_proxied: false,
+            })
+            .unwrap();
+        write_prefixed(&mut client, &chat("1", "hi")).unwrap();
+        worker.has_events = false;
+        read_until_sent(&mut worker, token);
+        let event = rx_events.try_recv().unwrap();
+        assert_eq!(event.get_connection(), (token, 8));
+    }
+}
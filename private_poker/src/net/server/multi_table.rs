@@ -0,0 +1,488 @@
+//! Balancing the tables of multi-table tournaments.
+//!
+//! Tables with the same tournament name are one multi-table tournament.
+//! Each table plays its own hands and reports its players whenever they
+//! change, and the table manager uses those reports to move players from
+//! the fullest table to the shortest, break tables once the players left
+//! fit at fewer of them, and play hand-for-hand on the money bubble so
+//! every table finishes its hand before any table deals the next.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    mem,
+};
+
+use crate::game::{
+    entities::{Usd, Username},
+    GameSettings, PokerState,
+};
+
+use super::super::messages::TableId;
+
+/// What a multi-table tournament's table looks like, as far as balancing
+/// goes.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TableReport {
+    /// Players still in the tournament, starting from the fairest one to
+    /// move.
+    pub players: Vec<Username>,
+    /// Number of buy-ins the table took, counting re-entries.
+    pub entries: usize,
+    pub winner: Option<Username>,
+    pub is_running: bool,
+    /// Whether the table finished its hand and hasn't dealt the next.
+    pub is_between_hands: bool,
+}
+
+impl TableReport {
+    pub fn new(state: &PokerState) -> Self {
+        let (entries, winner, is_running) = state
+            .get_tournament_view()
+            .map(|view| (view.entries, view.winner, view.time_to_next_level.is_some()))
+            .unwrap_or_default();
+        Self {
+            players: state.get_tournament_players(),
+            entries,
+            winner,
+            is_running,
+            is_between_hands: state.is_in_lobby(),
+        }
+    }
+}
+
+/// Something the table manager has to do for a multi-table tournament.
+#[derive(Debug, Eq, PartialEq)]
+pub enum Order {
+    /// Move a player to another table once their table is between hands.
+    Move {
+        username: Username,
+        from: TableId,
+        to: TableId,
+    },
+    /// End the tournament at the table, crowning its last player if it
+    /// has one. Tables that were broken finish without a winner.
+    FinishTournament(TableId),
+    /// Start or stop playing hand-for-hand at the tables.
+    HandForHand {
+        name: String,
+        table_ids: Vec<TableId>,
+        is_hand_for_hand: bool,
+    },
+    /// Deal the next hand at tables that are playing hand-for-hand.
+    /// Tables that already dealt it ignore this.
+    DealNextHand(Vec<TableId>),
+    /// Let the tournament's tables know who won it.
+    Won {
+        name: String,
+        winner: Username,
+        prize_pool: Usd,
+        table_ids: Vec<TableId>,
+    },
+}
+
+/// A table of a multi-table tournament.
+#[derive(Debug)]
+struct Table {
+    buy_in: Usd,
+    max_players: usize,
+    report: TableReport,
+}
+
+/// A multi-table tournament and where its players are.
+#[derive(Debug, Default)]
+struct Tournament {
+    paid_places: usize,
+    tables: BTreeMap<TableId, Table>,
+    /// Players on their way to another table, and the table they're
+    /// moving to. Tables aren't balanced again until they've arrived,
+    /// since reports can't be trusted to count them until then.
+    moving: HashMap<Username, TableId>,
+    is_hand_for_hand: bool,
+}
+
+impl Tournament {
+    /// Return the tables that still have players in the tournament, and
+    /// how many players each has.
+    fn get_tables_in_play(&self) -> BTreeMap<TableId, Vec<Username>> {
+        self.tables
+            .iter()
+            .filter(|(_, table)| table.report.is_running && !table.report.players.is_empty())
+            .map(|(table_id, table)| (*table_id, table.report.players.clone()))
+            .collect()
+    }
+
+    /// Decide how to move players so no table has more than one player
+    /// more than any other, breaking the shortest table if everyone fits
+    /// at the other tables.
+    fn balance(&self, tables: &mut BTreeMap<TableId, Vec<Username>>) -> Vec<Order> {
+        let mut orders = Vec::new();
+        let num_players: usize = tables.values().map(Vec::len).sum();
+        let max_players = tables
+            .keys()
+            .map(|table_id| self.tables[table_id].max_players)
+            .min()
+            .unwrap_or_default();
+        let shortest = |tables: &BTreeMap<TableId, Vec<Username>>, except: Option<TableId>| {
+            tables
+                .iter()
+                .filter(|(table_id, _)| Some(**table_id) != except)
+                .min_by_key(|(table_id, players)| (players.len(), **table_id))
+                .map(|(table_id, _)| *table_id)
+                .expect("tables are in play")
+        };
+        if num_players <= (tables.len() - 1) * max_players {
+            // Later tables are broken first when they're just as short.
+            let (broken_table_id, players) = tables
+                .iter()
+                .min_by_key(|(table_id, players)| (players.len(), usize::MAX - **table_id))
+                .map(|(table_id, players)| (*table_id, players.clone()))
+                .expect("tables are in play");
+            for username in players {
+                let to = shortest(tables, Some(broken_table_id));
+                tables
+                    .get_mut(&to)
+                    .expect("table exists")
+                    .push(username.clone());
+                orders.push(Order::Move {
+                    username,
+                    from: broken_table_id,
+                    to,
+                });
+            }
+            tables.remove(&broken_table_id);
+            return orders;
+        }
+        loop {
+            let (from, num_from) = tables
+                .iter()
+                .max_by_key(|(table_id, players)| (players.len(), usize::MAX - **table_id))
+                .map(|(table_id, players)| (*table_id, players.len()))
+                .expect("tables are in play");
+            let to = shortest(tables, None);
+            if num_from <= tables[&to].len() + 1 {
+                return orders;
+            }
+            let username = tables.get_mut(&from).expect("table exists").remove(0);
+            tables
+                .get_mut(&to)
+                .expect("table exists")
+                .push(username.clone());
+            orders.push(Order::Move { username, from, to });
+        }
+    }
+
+    fn plan(&mut self, name: &str) -> Vec<Order> {
+        if !self.moving.is_empty() {
+            return Vec::new();
+        }
+        let mut tables = self.get_tables_in_play();
+        if tables.len() == 1 {
+            // The last table left plays down to its last player, who wins.
+            let (table_id, players) = tables.pop_first().expect("a table is in play");
+            let mut orders = Vec::from_iter(self.set_hand_for_hand(name, false));
+            if players.len() == 1 {
+                orders.push(Order::FinishTournament(table_id));
+            }
+            return orders;
+        } else if tables.is_empty() {
+            return Vec::new();
+        }
+
+        let orders = self.balance(&mut tables);
+        if !orders.is_empty() {
+            for order in orders.iter() {
+                if let Order::Move { username, to, .. } = order {
+                    self.moving.insert(username.clone(), *to);
+                }
+            }
+            return orders;
+        }
+
+        // The bubble is when the next player out is the last one to miss
+        // the money.
+        let num_players: usize = tables.values().map(Vec::len).sum();
+        let is_on_bubble = self.paid_places > 0 && num_players == self.paid_places + 1;
+        let mut orders = Vec::from_iter(self.set_hand_for_hand(name, is_on_bubble));
+        let are_tables_waiting = tables
+            .keys()
+            .all(|table_id| self.tables[table_id].report.is_between_hands);
+        if self.is_hand_for_hand && are_tables_waiting {
+            orders.push(Order::DealNextHand(tables.into_keys().collect()));
+        }
+        orders
+    }
+
+    fn set_hand_for_hand(&mut self, name: &str, is_hand_for_hand: bool) -> Option<Order> {
+        if self.is_hand_for_hand == is_hand_for_hand {
+            return None;
+        }
+        self.is_hand_for_hand = is_hand_for_hand;
+        Some(Order::HandForHand {
+            name: name.to_string(),
+            table_ids: self.tables.keys().copied().collect(),
+            is_hand_for_hand,
+        })
+    }
+}
+
+/// The server's multi-table tournaments.
+#[derive(Debug, Default)]
+pub struct MultiTableTournaments {
+    tournaments: HashMap<String, Tournament>,
+    table_names: HashMap<TableId, String>,
+}
+
+impl MultiTableTournaments {
+    /// Start keeping track of a table if it's one of a multi-table
+    /// tournament's tables.
+    pub fn add_table(&mut self, table_id: TableId, settings: &GameSettings) {
+        let Some(tournament_settings) = settings.tournament.as_ref() else {
+            return;
+        };
+        let Some(ref name) = tournament_settings.name else {
+            return;
+        };
+        let tournament = self.tournaments.entry(name.clone()).or_default();
+        tournament.paid_places = tournament_settings.paid_places;
+        let table = Table {
+            buy_in: settings.buy_in,
+            max_players: settings.max_players,
+            report: TableReport::default(),
+        };
+        tournament.tables.insert(table_id, table);
+        self.table_names.insert(table_id, name.clone());
+    }
+
+    /// Stop keeping track of a table that stopped running.
+    pub fn remove_table(&mut self, table_id: TableId) {
+        let Some(name) = self.table_names.remove(&table_id) else {
+            return;
+        };
+        let tournament = self.tournaments.get_mut(&name).expect("tournament exists");
+        tournament.tables.remove(&table_id);
+        tournament.moving.retain(|_, to| *to != table_id);
+        if tournament.tables.is_empty() {
+            self.tournaments.remove(&name);
+        }
+    }
+
+    /// Take in a table's latest report, returning what has to be done to
+    /// keep the tournament's tables balanced.
+    pub fn report(&mut self, table_id: TableId, report: TableReport) -> Vec<Order> {
+        let Some(name) = self.table_names.get(&table_id) else {
+            return Vec::new();
+        };
+        let tournament = self.tournaments.get_mut(name).expect("tournament exists");
+        let mut orders = Vec::new();
+        let table = tournament.tables.get_mut(&table_id).expect("table exists");
+        let old_report = mem::replace(&mut table.report, report.clone());
+        tournament
+            .moving
+            .retain(|username, to| *to != table_id || !report.players.contains(username));
+
+        // The final table crowns the winner, and the tables that were
+        // broken along the way are done too.
+        if let Some(ref winner) = report.winner {
+            if !report.is_running && old_report.winner.as_ref() != Some(winner) {
+                let prize_pool = tournament
+                    .tables
+                    .values()
+                    .map(|table| (table.report.entries as Usd).saturating_mul(table.buy_in))
+                    .fold(0, Usd::saturating_add);
+                orders.push(Order::Won {
+                    name: name.clone(),
+                    winner: winner.clone(),
+                    prize_pool,
+                    table_ids: tournament.tables.keys().copied().collect(),
+                });
+                for (other_table_id, table) in tournament.tables.iter() {
+                    if *other_table_id != table_id && table.report.is_running {
+                        orders.push(Order::FinishTournament(*other_table_id));
+                    }
+                }
+                tournament.moving.clear();
+                tournament.is_hand_for_hand = false;
+                return orders;
+            }
+        }
+        orders.extend(tournament.plan(name));
+        orders
+    }
+
+    /// A player couldn't be moved, like when they left before their
+    /// table finished its hand.
+    pub fn cancel_move(&mut self, username: &str, table_id: TableId) -> Vec<Order> {
+        let Some(name) = self.table_names.get(&table_id) else {
+            return Vec::new();
+        };
+        let tournament = self.tournaments.get_mut(name).expect("tournament exists");
+        tournament.moving.remove(username);
+        tournament.plan(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::game::{entities::Username, GameSettings, TournamentSettings};
+
+    use super::{MultiTableTournaments, Order, TableReport};
+
+    fn tournament(num_tables: usize, max_players: usize) -> MultiTableTournaments {
+        let settings = GameSettings::new(max_players, max_players, 1000).with_tournament(
+            TournamentSettings::new(Duration::from_secs(60)).with_multi_table("sunday", 3),
+        );
+        let mut tournaments = MultiTableTournaments::default();
+        for table_id in 0..num_tables {
+            tournaments.add_table(table_id, &settings);
+        }
+        tournaments
+    }
+
+    fn report(players: &[&str], is_between_hands: bool) -> TableReport {
+        TableReport {
+            players: players
+                .iter()
+                .map(|username| Username::from(*username))
+                .collect(),
+            entries: players.len(),
+            winner: None,
+            is_running: true,
+            is_between_hands,
+        }
+    }
+
+    fn moved(username: &str, from: usize, to: usize) -> Order {
+        Order::Move {
+            username: username.into(),
+            from,
+            to,
+        }
+    }
+
+    #[test]
+    fn move_players_from_the_fullest_table_to_the_shortest() {
+        let mut tournaments = tournament(2, 6);
+        assert_eq!(
+            tournaments.report(0, report(&["0", "1", "2", "3", "4", "5"], true)),
+            Vec::new()
+        );
+        // The first players listed are the fairest to move.
+        assert_eq!(
+            tournaments.report(1, report(&["6", "7", "8"], true)),
+            vec![moved("0", 0, 1)]
+        );
+
+        // Nothing else moves until the player arrives.
+        assert_eq!(
+            tournaments.report(0, report(&["1", "2", "3", "4", "5"], true)),
+            Vec::new()
+        );
+        assert_eq!(
+            tournaments.report(1, report(&["6", "7", "8", "0"], true)),
+            Vec::new()
+        );
+        // Tables that are one player apart are balanced.
+        assert_eq!(
+            tournaments.report(0, report(&["1", "2", "3", "4"], true)),
+            Vec::new()
+        );
+    }
+
+    #[test]
+    fn break_tables_once_players_fit_at_fewer() {
+        let mut tournaments = tournament(3, 4);
+        tournaments.report(0, report(&["0", "1", "2"], true));
+        tournaments.report(1, report(&["3", "4", "5"], true));
+        assert_eq!(
+            tournaments.report(2, report(&["6", "7"], true)),
+            vec![moved("6", 2, 0), moved("7", 2, 1)]
+        );
+
+        // A failed move doesn't hold up the tournament.
+        tournaments.report(0, report(&["0", "1", "2", "6"], true));
+        tournaments.report(2, report(&[], true));
+        assert_eq!(tournaments.cancel_move("7", 1), Vec::new());
+
+        // Down to one table, which plays the tournament down to a winner.
+        tournaments.report(0, report(&["0", "6"], true));
+        assert_eq!(
+            tournaments.report(1, report(&["3"], true)),
+            vec![moved("3", 1, 0)]
+        );
+        tournaments.report(1, report(&[], true));
+        assert_eq!(
+            tournaments.report(0, report(&["0", "6", "3"], true)),
+            Vec::new()
+        );
+        assert_eq!(
+            tournaments.report(0, report(&["6"], true)),
+            vec![Order::FinishTournament(0)]
+        );
+    }
+
+    #[test]
+    fn play_hand_for_hand_on_the_bubble() {
+        let mut tournaments = tournament(2, 3);
+        tournaments.report(0, report(&["0", "1", "2"], false));
+        tournaments.report(1, report(&["3", "4", "5"], false));
+
+        // Three places are paid, so the bubble is at four players.
+        let hand_for_hand = |is_hand_for_hand| Order::HandForHand {
+            name: "sunday".to_string(),
+            table_ids: vec![0, 1],
+            is_hand_for_hand,
+        };
+        assert_eq!(tournaments.report(0, report(&["0", "1"], true)), Vec::new());
+        assert_eq!(
+            tournaments.report(1, report(&["3", "4"], false)),
+            vec![hand_for_hand(true)]
+        );
+        // The next hand is dealt once every table finished theirs.
+        assert_eq!(
+            tournaments.report(1, report(&["3", "4"], true)),
+            vec![Order::DealNextHand(vec![0, 1])]
+        );
+        tournaments.report(1, report(&["3", "4"], false));
+        tournaments.report(0, report(&["0", "1"], false));
+        assert_eq!(tournaments.report(0, report(&["0", "1"], true)), Vec::new());
+
+        // Hand-for-hand is over once the tournament's down to one table.
+        assert_eq!(
+            tournaments.report(1, report(&["3"], true)),
+            vec![moved("3", 1, 0)]
+        );
+        tournaments.report(1, report(&[], true));
+        assert_eq!(
+            tournaments.report(0, report(&["0", "1", "3"], true)),
+            vec![hand_for_hand(false)]
+        );
+    }
+
+    #[test]
+    fn crown_the_winner_at_the_final_table() {
+        let mut tournaments = tournament(2, 9);
+        tournaments.report(0, report(&["0", "1"], true));
+        tournaments.report(1, report(&[], true));
+        let won = TableReport {
+            players: vec!["0".into()],
+            entries: 2,
+            winner: Some("0".into()),
+            is_running: false,
+            is_between_hands: true,
+        };
+        assert_eq!(
+            tournaments.report(0, won),
+            vec![
+                Order::Won {
+                    name: "sunday".to_string(),
+                    winner: "0".into(),
+                    prize_pool: 2000,
+                    table_ids: vec![0, 1],
+                },
+                Order::FinishTournament(1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,409 @@
+//! Sockets that clients and the server can talk over.
+//!
+//! Besides TCP, local games can use Unix domain sockets, which skip the
+//! network stack entirely and are permissioned with filesystem modes
+//! like any other file. Addresses starting with `unix:` name a socket
+//! file, like `unix:/tmp/poker.sock`, while anything else is parsed as
+//! an `IP:PORT` address. Servers can also listen on sockets inherited
+//! from the process that started them, like systemd does with socket
+//! activation, with addresses like `fd:3`.
+
+use mio::{event::Source, Interest, Registry, Token};
+use socket2::{Domain, Protocol, Type};
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    net::{AddrParseError, IpAddr, SocketAddr, TcpStream},
+    str::FromStr,
+    time::Duration,
+};
+#[cfg(unix)]
+use std::{
+    fs,
+    os::{
+        fd::{FromRawFd, OwnedFd, RawFd},
+        unix::net::UnixStream,
+    },
+    path::PathBuf,
+};
+
+/// Prefix for addresses of Unix domain sockets.
+pub const UNIX_PREFIX: &str = "unix:";
+/// Prefix for addresses of inherited listening sockets.
+pub const FD_PREFIX: &str = "fd:";
+/// Connections that can wait to be accepted, the same as mio uses.
+const LISTEN_BACKLOG: i32 = 1024;
+
+/// Where a server listens or a client connects.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Addr {
+    Tcp(SocketAddr),
+    /// Path to a Unix domain socket file.
+    #[cfg(unix)]
+    Unix(PathBuf),
+    /// File descriptor of a listening TCP or Unix socket inherited from
+    /// the parent process. Only servers can use these.
+    #[cfg(unix)]
+    Fd(RawFd),
+}
+
+impl Addr {
+    /// The TCP address, if it is one.
+    pub fn as_tcp(&self) -> Option<SocketAddr> {
+        match self {
+            Addr::Tcp(addr) => Some(*addr),
+            #[cfg(unix)]
+            Addr::Unix(_) | Addr::Fd(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Addr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Addr::Tcp(addr) => write!(f, "{addr}"),
+            #[cfg(unix)]
+            Addr::Unix(path) => write!(f, "{UNIX_PREFIX}{}", path.display()),
+            #[cfg(unix)]
+            Addr::Fd(fd) => write!(f, "{FD_PREFIX}{fd}"),
+        }
+    }
+}
+
+impl From<SocketAddr> for Addr {
+    fn from(value: SocketAddr) -> Self {
+        Addr::Tcp(value)
+    }
+}
+
+impl FromStr for Addr {
+    type Err = AddrParseError;
+
+    /// Unix socket addresses are only recognized on Unix, so they fail
+    /// to parse as TCP addresses everywhere else.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        #[cfg(unix)]
+        if let Some(path) = s.strip_prefix(UNIX_PREFIX) {
+            return Ok(Addr::Unix(path.into()));
+        }
+        // Bad file descriptors fail to parse as TCP addresses too.
+        #[cfg(unix)]
+        if let Some(Ok(fd)) = s.strip_prefix(FD_PREFIX).map(str::parse) {
+            return Ok(Addr::Fd(fd));
+        }
+        Ok(Addr::Tcp(s.parse()?))
+    }
+}
+
+/// A blocking connection over TCP or a Unix socket.
+#[derive(Debug)]
+pub enum Socket {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Socket {
+    /// Connect to the address, giving up after the timeout. Unix sockets
+    /// connect right away or not at all, so they ignore the timeout.
+    pub fn connect_timeout(addr: &Addr, timeout: Duration) -> io::Result<Self> {
+        match addr {
+            Addr::Tcp(addr) => TcpStream::connect_timeout(addr, timeout).map(Socket::Tcp),
+            #[cfg(unix)]
+            Addr::Unix(path) => UnixStream::connect(path).map(Socket::Unix),
+            #[cfg(unix)]
+            Addr::Fd(_) => Err(io::ErrorKind::InvalidInput.into()),
+        }
+    }
+
+    /// Convert the socket into a nonblocking socket that can be
+    /// registered with a poll.
+    pub fn into_nonblocking(self) -> io::Result<NonblockingSocket> {
+        match self {
+            Socket::Tcp(sock) => {
+                sock.set_nonblocking(true)?;
+                Ok(NonblockingSocket::Tcp(mio::net::TcpStream::from_std(sock)))
+            }
+            #[cfg(unix)]
+            Socket::Unix(sock) => {
+                sock.set_nonblocking(true)?;
+                Ok(NonblockingSocket::Unix(mio::net::UnixStream::from_std(
+                    sock,
+                )))
+            }
+        }
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Socket::Tcp(sock) => sock.set_read_timeout(timeout),
+            #[cfg(unix)]
+            Socket::Unix(sock) => sock.set_read_timeout(timeout),
+        }
+    }
+
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Socket::Tcp(sock) => sock.set_write_timeout(timeout),
+            #[cfg(unix)]
+            Socket::Unix(sock) => sock.set_write_timeout(timeout),
+        }
+    }
+}
+
+impl From<TcpStream> for Socket {
+    fn from(value: TcpStream) -> Self {
+        Socket::Tcp(value)
+    }
+}
+
+impl Read for Socket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Socket::Tcp(sock) => sock.read(buf),
+            #[cfg(unix)]
+            Socket::Unix(sock) => sock.read(buf),
+        }
+    }
+}
+
+impl Write for Socket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Socket::Tcp(sock) => sock.write(buf),
+            #[cfg(unix)]
+            Socket::Unix(sock) => sock.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Socket::Tcp(sock) => sock.flush(),
+            #[cfg(unix)]
+            Socket::Unix(sock) => sock.flush(),
+        }
+    }
+}
+
+/// A nonblocking connection over TCP or a Unix socket.
+#[derive(Debug)]
+pub enum NonblockingSocket {
+    Tcp(mio::net::TcpStream),
+    #[cfg(unix)]
+    Unix(mio::net::UnixStream),
+}
+
+impl From<mio::net::TcpStream> for NonblockingSocket {
+    fn from(value: mio::net::TcpStream) -> Self {
+        NonblockingSocket::Tcp(value)
+    }
+}
+
+impl Read for NonblockingSocket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            NonblockingSocket::Tcp(sock) => sock.read(buf),
+            #[cfg(unix)]
+            NonblockingSocket::Unix(sock) => sock.read(buf),
+        }
+    }
+}
+
+impl Write for NonblockingSocket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            NonblockingSocket::Tcp(sock) => sock.write(buf),
+            #[cfg(unix)]
+            NonblockingSocket::Unix(sock) => sock.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            NonblockingSocket::Tcp(sock) => sock.flush(),
+            #[cfg(unix)]
+            NonblockingSocket::Unix(sock) => sock.flush(),
+        }
+    }
+}
+
+impl Source for NonblockingSocket {
+    fn register(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        match self {
+            NonblockingSocket::Tcp(sock) => sock.register(registry, token, interests),
+            #[cfg(unix)]
+            NonblockingSocket::Unix(sock) => sock.register(registry, token, interests),
+        }
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        match self {
+            NonblockingSocket::Tcp(sock) => sock.reregister(registry, token, interests),
+            #[cfg(unix)]
+            NonblockingSocket::Unix(sock) => sock.reregister(registry, token, interests),
+        }
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        match self {
+            NonblockingSocket::Tcp(sock) => sock.deregister(registry),
+            #[cfg(unix)]
+            NonblockingSocket::Unix(sock) => sock.deregister(registry),
+        }
+    }
+}
+
+/// A nonblocking listener for TCP or Unix socket connections. Unix
+/// socket files are removed when the listener that bound them is
+/// dropped. Inherited sockets' files belong to whoever bound them.
+#[derive(Debug)]
+pub enum Listener {
+    Tcp(mio::net::TcpListener),
+    #[cfg(unix)]
+    Unix(mio::net::UnixListener, Option<PathBuf>),
+}
+
+impl Listener {
+    /// Start listening at the address. Binding to a Unix socket fails if
+    /// its file already exists. IPv6 listeners only accept IPv6
+    /// connections, so an IPv4 listener can share their port for
+    /// dual-stack hosting.
+    pub fn bind(addr: &Addr) -> io::Result<Self> {
+        match addr {
+            Addr::Tcp(addr) => {
+                let socket = socket2::Socket::new(
+                    Domain::for_address(*addr),
+                    Type::STREAM,
+                    Some(Protocol::TCP),
+                )?;
+                if addr.is_ipv6() {
+                    socket.set_only_v6(true)?;
+                }
+                // Restarted servers shouldn't have to wait for old
+                // connections to time out before binding again.
+                #[cfg(unix)]
+                socket.set_reuse_address(true)?;
+                socket.bind(&(*addr).into())?;
+                socket.listen(LISTEN_BACKLOG)?;
+                socket.set_nonblocking(true)?;
+                Ok(Listener::Tcp(mio::net::TcpListener::from_std(
+                    socket.into(),
+                )))
+            }
+            #[cfg(unix)]
+            Addr::Unix(path) => {
+                let listener = mio::net::UnixListener::bind(path)?;
+                Ok(Listener::Unix(listener, Some(path.clone())))
+            }
+            #[cfg(unix)]
+            Addr::Fd(fd) => {
+                // SAFETY: the parent process handed the socket over, and
+                // nothing else in this process uses it.
+                let socket = unsafe { socket2::Socket::from_raw_fd(*fd) };
+                socket.set_nonblocking(true)?;
+                if socket.local_addr()?.is_unix() {
+                    let listener = std::os::unix::net::UnixListener::from(OwnedFd::from(socket));
+                    let listener = mio::net::UnixListener::from_std(listener);
+                    Ok(Listener::Unix(listener, None))
+                } else {
+                    Ok(Listener::Tcp(mio::net::TcpListener::from_std(
+                        socket.into(),
+                    )))
+                }
+            }
+        }
+    }
+
+    /// Accept a connection, along with the peer's IP address for TCP
+    /// connections.
+    pub fn accept(&self) -> io::Result<(NonblockingSocket, Option<IpAddr>)> {
+        match self {
+            Listener::Tcp(listener) => {
+                let (sock, peer_addr) = listener.accept()?;
+                Ok((NonblockingSocket::Tcp(sock), Some(peer_addr.ip())))
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener, _) => {
+                let (sock, _) = listener.accept()?;
+                Ok((NonblockingSocket::Unix(sock), None))
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Listener {
+    fn drop(&mut self) {
+        if let Listener::Unix(_, Some(path)) = self {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+impl Source for Listener {
+    fn register(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        match self {
+            Listener::Tcp(listener) => listener.register(registry, token, interests),
+            #[cfg(unix)]
+            Listener::Unix(listener, _) => listener.register(registry, token, interests),
+        }
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        match self {
+            Listener::Tcp(listener) => listener.reregister(registry, token, interests),
+            #[cfg(unix)]
+            Listener::Unix(listener, _) => listener.reregister(registry, token, interests),
+        }
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        match self {
+            Listener::Tcp(listener) => listener.deregister(registry),
+            #[cfg(unix)]
+            Listener::Unix(listener, _) => listener.deregister(registry),
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{env, process};
+
+    use super::{Addr, UNIX_PREFIX};
+
+    #[test]
+    fn parse_addrs() {
+        assert_eq!(
+            "127.0.0.1:6969".parse::<Addr>().unwrap(),
+            Addr::Tcp("127.0.0.1:6969".parse().unwrap())
+        );
+        let path = env::temp_dir().join(format!("pp_{}.sock", process::id()));
+        let addr: Addr = format!("{UNIX_PREFIX}{}", path.display()).parse().unwrap();
+        assert_eq!(addr, Addr::Unix(path));
+        assert_eq!(addr.to_string().parse::<Addr>().unwrap(), addr);
+        assert_eq!("fd:3".parse::<Addr>().unwrap(), Addr::Fd(3));
+        assert!("fd:three".parse::<Addr>().is_err());
+        assert!("localhost".parse::<Addr>().is_err());
+    }
+}
@@ -0,0 +1,159 @@
+//! A tiny HTTP endpoint for checking on a server without joining it.
+//!
+//! `GET /status` returns JSON with how long the server has been up and
+//! what each of its tables is doing, so dashboards and server browsers
+//! can poll a server's health. Anything else is a 404. Requests are
+//! served one at a time on a thread of their own, which is plenty for
+//! occasional polling.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::game::entities::Username;
+
+use super::messages::TableId;
+
+/// Longest request that's read. Status requests are a single line and a
+/// few headers.
+const MAX_REQUEST_LEN: usize = 8192;
+/// How long clients have to send their request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// What a table is doing.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TableStatus {
+    /// The table's game state, like "in lobby" or "the flop".
+    pub phase: String,
+    /// Players seated at the table, in seat order.
+    pub players: Vec<Username>,
+    pub waitlist_size: usize,
+}
+
+/// The body of a status response.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ServerStatus {
+    /// Seconds since the server started.
+    pub uptime: u64,
+    pub tables: BTreeMap<TableId, TableStatus>,
+}
+
+/// Latest status of every table, kept up to date by the tables
+/// themselves.
+#[derive(Clone, Debug, Default)]
+pub struct StatusBoard(Arc<Mutex<BTreeMap<TableId, TableStatus>>>);
+
+impl StatusBoard {
+    pub fn update(&self, table_id: TableId, status: TableStatus) {
+        self.0
+            .lock()
+            .expect("status board lock")
+            .insert(table_id, status);
+    }
+
+    pub fn remove(&self, table_id: TableId) {
+        self.0.lock().expect("status board lock").remove(&table_id);
+    }
+
+    fn get_tables(&self) -> BTreeMap<TableId, TableStatus> {
+        self.0.lock().expect("status board lock").clone()
+    }
+}
+
+/// Start serving status requests at the address. The address is bound
+/// right away so mistakes surface before the server starts.
+pub fn spawn(addr: &str, board: StatusBoard) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    let started = Instant::now();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // Clients that go away mid-request don't need a response.
+            let _ = respond(stream, &board, started);
+        }
+    });
+    Ok(())
+}
+
+fn respond(mut stream: TcpStream, board: &StatusBoard, started: Instant) -> io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let n = stream.read(&mut buf)?;
+        if n == 0 || request.len() + n > MAX_REQUEST_LEN {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let request_line: Vec<_> = request.lines().next().unwrap_or("").split(' ').collect();
+    let (status_line, body) = match request_line[..] {
+        ["GET", "/status", _] => {
+            let status = ServerStatus {
+                uptime: started.elapsed().as_secs(),
+                tables: board.get_tables(),
+            };
+            let body = serde_json::to_string(&status).map_err(io::Error::other)?;
+            ("200 OK", body)
+        }
+        [_, "/status", _] => ("405 Method Not Allowed", String::new()),
+        _ => ("404 Not Found", String::new()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status_line}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::{spawn, ServerStatus, StatusBoard, TableStatus};
+
+    fn get(addr: &str, path: &str) -> String {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: {addr}\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn serve_status() {
+        // Find a free port to serve on.
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+        let board = StatusBoard::default();
+        let table = TableStatus {
+            phase: "in lobby".to_string(),
+            players: vec!["0".into()],
+            waitlist_size: 1,
+        };
+        board.update(0, table.clone());
+        spawn(&addr, board).unwrap();
+
+        let response = get(&addr, "/status");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        let status: ServerStatus = serde_json::from_str(body).unwrap();
+        assert_eq!(status.tables.get(&0), Some(&table));
+
+        let response = get(&addr, "/");
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+}
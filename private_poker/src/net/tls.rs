@@ -0,0 +1,329 @@
+//! Optional TLS encryption for the wire protocol.
+
+use anyhow::Error;
+use mio::{event::Source, Interest, Registry, Token};
+use rustls::{
+    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+    crypto::{self, ring, CryptoProvider},
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer, ServerName, UnixTime},
+    CertificateError, ClientConfig, ClientConnection, DigitallySignedStruct, RootCertStore,
+    ServerConfig, ServerConnection, SignatureScheme, StreamOwned,
+};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    net::SocketAddr,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use super::socket::{NonblockingSocket, Socket};
+
+/// A connection that may or may not be encrypted. Reads and writes
+/// go through TLS for encrypted connections.
+pub enum Stream<S: Read + Write> {
+    Plain(S),
+    Client(Box<StreamOwned<ClientConnection, S>>),
+    Server(Box<StreamOwned<ServerConnection, S>>),
+}
+
+impl<S: Read + Write> Stream<S> {
+    /// The underlying socket.
+    pub fn get_ref(&self) -> &S {
+        match self {
+            Stream::Plain(sock) => sock,
+            Stream::Client(stream) => &stream.sock,
+            Stream::Server(stream) => &stream.sock,
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        match self {
+            Stream::Plain(sock) => sock,
+            Stream::Client(stream) => &mut stream.sock,
+            Stream::Server(stream) => &mut stream.sock,
+        }
+    }
+
+    /// Whether encrypted data is still waiting to be written to the
+    /// socket. Nonblocking sockets may not accept everything at once.
+    pub fn wants_write(&self) -> bool {
+        match self {
+            Stream::Plain(_) => false,
+            Stream::Client(stream) => stream.conn.wants_write(),
+            Stream::Server(stream) => stream.conn.wants_write(),
+        }
+    }
+}
+
+impl Stream<Socket> {
+    /// Convert a blocking stream into a nonblocking stream that can be
+    /// registered with a poll.
+    pub fn into_nonblocking(self) -> io::Result<Stream<NonblockingSocket>> {
+        let stream = match self {
+            Stream::Plain(sock) => Stream::Plain(sock.into_nonblocking()?),
+            Stream::Client(stream) => {
+                let (conn, sock) = stream.into_parts();
+                Stream::Client(Box::new(StreamOwned::new(conn, sock.into_nonblocking()?)))
+            }
+            Stream::Server(stream) => {
+                let (conn, sock) = stream.into_parts();
+                Stream::Server(Box::new(StreamOwned::new(conn, sock.into_nonblocking()?)))
+            }
+        };
+        Ok(stream)
+    }
+}
+
+impl<S: Read + Write> From<S> for Stream<S> {
+    fn from(value: S) -> Self {
+        Stream::Plain(value)
+    }
+}
+
+impl<S: Read + Write> Read for Stream<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Plain(sock) => sock.read(buf),
+            Stream::Client(stream) => stream.read(buf),
+            Stream::Server(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl<S: Read + Write> Write for Stream<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Plain(sock) => sock.write(buf),
+            Stream::Client(stream) => stream.write(buf),
+            Stream::Server(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Plain(sock) => sock.flush(),
+            Stream::Client(stream) => stream.flush(),
+            Stream::Server(stream) => stream.flush(),
+        }
+    }
+}
+
+impl Source for Stream<NonblockingSocket> {
+    fn register(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        self.get_mut().register(registry, token, interests)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        self.get_mut().reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        self.get_mut().deregister(registry)
+    }
+}
+
+/// How a client decides whether to trust a server's certificate.
+#[derive(Clone, Debug)]
+pub enum ClientTls {
+    /// Only trust servers with certificates signed by one of the
+    /// certificate authorities in the given PEM file.
+    Verify(PathBuf),
+    /// Trust the certificate a server presents the first time the client
+    /// connects to it, remembering it in the given file. Connecting fails
+    /// if the server presents a different certificate later.
+    TrustOnFirstUse(PathBuf),
+}
+
+impl ClientTls {
+    /// TLS settings for connecting to the server at the given address.
+    pub fn client_config(&self, addr: SocketAddr) -> Result<ClientConfig, Error> {
+        let provider = Arc::new(ring::default_provider());
+        let builder = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()?;
+        let config = match self {
+            ClientTls::Verify(path) => {
+                let mut roots = RootCertStore::empty();
+                for cert in CertificateDer::pem_file_iter(path)? {
+                    roots.add(cert?)?;
+                }
+                builder.with_root_certificates(roots)
+            }
+            ClientTls::TrustOnFirstUse(path) => {
+                let verifier = TrustOnFirstUse {
+                    addr: addr.to_string(),
+                    path: path.clone(),
+                    provider,
+                };
+                builder
+                    .dangerous()
+                    .with_custom_certificate_verifier(Arc::new(verifier))
+            }
+        }
+        .with_no_client_auth();
+        Ok(config)
+    }
+
+    /// Start a TLS session with the server over the given socket. The
+    /// handshake finishes on the first read or write.
+    pub fn connect<S: Read + Write>(&self, addr: SocketAddr, sock: S) -> Result<Stream<S>, Error> {
+        let config = self.client_config(addr)?;
+        let conn = ClientConnection::new(Arc::new(config), ServerName::from(addr.ip()))?;
+        Ok(Stream::Client(Box::new(StreamOwned::new(conn, sock))))
+    }
+}
+
+/// Load the server's certificate chain and private key from PEM files.
+pub fn server_config(
+    cert_path: impl Into<PathBuf>,
+    key_path: impl Into<PathBuf>,
+) -> Result<Arc<ServerConfig>, Error> {
+    let certs = CertificateDer::pem_file_iter(cert_path.into())?.collect::<Result<_, _>>()?;
+    let key = PrivateKeyDer::from_pem_file(key_path.into())?;
+    let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    Ok(Arc::new(config))
+}
+
+/// Trusts the first certificate a server presents and only that certificate
+/// from then on. Certificates are saved by server address.
+#[derive(Debug)]
+struct TrustOnFirstUse {
+    addr: String,
+    path: PathBuf,
+    provider: Arc<CryptoProvider>,
+}
+
+impl TrustOnFirstUse {
+    fn load(&self) -> Result<HashMap<String, Vec<u8>>, Error> {
+        match File::open(&self.path) {
+            Ok(file) => Ok(bincode::deserialize_from(BufReader::new(file))?),
+            // No servers have been trusted yet.
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn save(&self, known_certs: &HashMap<String, Vec<u8>>) -> Result<(), Error> {
+        let tmp_path = self.path.with_extension("tmp");
+        let file = File::create(&tmp_path)?;
+        bincode::serialize_into(BufWriter::new(file), known_certs)?;
+        fs::rename(tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+impl ServerCertVerifier for TrustOnFirstUse {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let mut known_certs = self
+            .load()
+            .map_err(|error| rustls::Error::General(error.to_string()))?;
+        match known_certs.get(&self.addr) {
+            Some(cert) if cert.as_slice() == end_entity.as_ref() => {
+                Ok(ServerCertVerified::assertion())
+            }
+            Some(_) => Err(rustls::Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure,
+            )),
+            None => {
+                known_certs.insert(self.addr.clone(), end_entity.to_vec());
+                self.save(&known_certs)
+                    .map_err(|error| rustls::Error::General(error.to_string()))?;
+                Ok(ServerCertVerified::assertion())
+            }
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rcgen::generate_simple_self_signed;
+    use rustls::{
+        client::danger::ServerCertVerifier,
+        crypto::ring,
+        pki_types::{ServerName, UnixTime},
+    };
+    use std::{env, fs, process, sync::Arc};
+
+    use super::TrustOnFirstUse;
+
+    #[test]
+    fn trust_on_first_use() {
+        let path = env::temp_dir().join(format!("pp_known_certs_{}.bin", process::id()));
+        let verifier = TrustOnFirstUse {
+            addr: "127.0.0.1:6969".to_string(),
+            path: path.clone(),
+            provider: Arc::new(ring::default_provider()),
+        };
+        let server_name = ServerName::try_from("127.0.0.1").unwrap();
+        let cert = generate_simple_self_signed(["127.0.0.1".to_string()])
+            .unwrap()
+            .cert;
+        let other_cert = generate_simple_self_signed(["127.0.0.1".to_string()])
+            .unwrap()
+            .cert;
+        let verify = |cert: &rcgen::Certificate| {
+            verifier.verify_server_cert(cert.der(), &[], &server_name, &[], UnixTime::now())
+        };
+
+        // The first certificate is trusted, and then only that certificate.
+        assert!(verify(&cert).is_ok());
+        assert!(verify(&cert).is_ok());
+        assert!(verify(&other_cert).is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
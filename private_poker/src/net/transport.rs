@@ -0,0 +1,279 @@
+//! Transports that carry messages between clients and the server.
+//!
+//! Native clients use the length-prefixed protocol from [`super::utils`],
+//! with bincode or JSON messages that may be compressed and frames that
+//! may take several reads or writes to get through, while browser
+//! clients can speak WebSockets, sending messages as JSON text frames or
+//! bincode binary frames.
+
+use mio::{event::Source, Interest, Registry, Token};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    io::{self, Read, Write},
+    mem,
+};
+use tungstenite::{
+    handshake::{server::NoCallback, HandshakeError, MidHandshake},
+    protocol::WebSocketConfig,
+    Message, ServerHandshake, WebSocket,
+};
+
+use super::{socket::NonblockingSocket, tls::Stream, utils::FrameBuffer};
+
+/// Most bytes buffered for a WebSocket client that isn't reading.
+/// Messages that don't fit stay queued on the server, where slow clients
+/// are dealt with.
+pub const MAX_WEBSOCKET_WRITE_BUFFER: usize = 1 << 20;
+
+/// How messages are encoded in WebSocket frames.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FrameFormat {
+    /// Bincode in binary frames, the same encoding as the TCP protocol.
+    Binary,
+    /// JSON in text frames, which is easier to handle in browsers.
+    Text,
+}
+
+enum WebSocketState<S: Read + Write> {
+    /// Nothing has been read from the client yet.
+    Accepting(Stream<S>),
+    Handshaking(MidHandshake<ServerHandshake<Stream<S>, NoCallback>>),
+    Open(WebSocket<Stream<S>>),
+    Failed,
+}
+
+/// A server-side WebSocket connection. Replies use the same frame format
+/// as the last message received from the client.
+pub struct WebSocketConnection<S: Read + Write> {
+    state: WebSocketState<S>,
+    format: FrameFormat,
+}
+
+impl<S: Read + Write> WebSocketConnection<S> {
+    /// Start accepting a WebSocket connection. The handshake happens over
+    /// the next few reads.
+    pub fn accept(stream: Stream<S>) -> Self {
+        Self {
+            state: WebSocketState::Accepting(stream),
+            format: FrameFormat::Binary,
+        }
+    }
+
+    fn get_mut(&mut self) -> Option<&mut Stream<S>> {
+        match &mut self.state {
+            WebSocketState::Accepting(stream) => Some(stream),
+            WebSocketState::Handshaking(handshake) => Some(handshake.get_mut().get_mut()),
+            WebSocketState::Open(socket) => Some(socket.get_mut()),
+            WebSocketState::Failed => None,
+        }
+    }
+
+    fn read_message<T: DeserializeOwned>(&mut self) -> io::Result<T> {
+        if let WebSocketState::Accepting(_) = self.state {
+            let WebSocketState::Accepting(stream) =
+                mem::replace(&mut self.state, WebSocketState::Failed)
+            else {
+                unreachable!("the connection is still accepting");
+            };
+            let config =
+                WebSocketConfig::default().max_write_buffer_size(MAX_WEBSOCKET_WRITE_BUFFER);
+            match tungstenite::accept_with_config(stream, Some(config)) {
+                Ok(socket) => self.state = WebSocketState::Open(socket),
+                Err(HandshakeError::Interrupted(handshake)) => {
+                    self.state = WebSocketState::Handshaking(handshake);
+                    return Err(io::ErrorKind::WouldBlock.into());
+                }
+                Err(HandshakeError::Failure(error)) => return Err(to_io_error(error)),
+            }
+        }
+        if let WebSocketState::Handshaking(_) = self.state {
+            let WebSocketState::Handshaking(handshake) =
+                mem::replace(&mut self.state, WebSocketState::Failed)
+            else {
+                unreachable!("the connection is still handshaking");
+            };
+            match handshake.handshake() {
+                Ok(socket) => self.state = WebSocketState::Open(socket),
+                Err(HandshakeError::Interrupted(handshake)) => {
+                    self.state = WebSocketState::Handshaking(handshake);
+                    return Err(io::ErrorKind::WouldBlock.into());
+                }
+                Err(HandshakeError::Failure(error)) => return Err(to_io_error(error)),
+            }
+        }
+        let WebSocketState::Open(socket) = &mut self.state else {
+            return Err(io::ErrorKind::ConnectionAborted.into());
+        };
+        loop {
+            match socket.read().map_err(to_io_error)? {
+                Message::Binary(data) => {
+                    self.format = FrameFormat::Binary;
+                    return bincode::deserialize(&data)
+                        .map_err(|_| io::ErrorKind::InvalidData.into());
+                }
+                Message::Text(data) => {
+                    self.format = FrameFormat::Text;
+                    return serde_json::from_str(&data)
+                        .map_err(|_| io::ErrorKind::InvalidData.into());
+                }
+                Message::Close(_) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                // Pings are answered automatically on the next write.
+                Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => {}
+            }
+        }
+    }
+
+    fn write_message<T: Serialize>(&mut self, value: &T) -> io::Result<()> {
+        let WebSocketState::Open(socket) = &mut self.state else {
+            // Nothing can be sent until the handshake is done, but clients
+            // don't get any messages until they've sent one anyways.
+            return Err(io::ErrorKind::WouldBlock.into());
+        };
+        let msg = match self.format {
+            FrameFormat::Binary => Message::Binary(
+                bincode::serialize(value)
+                    .map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?
+                    .into(),
+            ),
+            FrameFormat::Text => Message::Text(
+                serde_json::to_string(value)
+                    .map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?
+                    .into(),
+            ),
+        };
+        // The message is buffered unless the buffer is full, so it's only
+        // retried in that case. Buffered messages are sent on later flushes.
+        match socket.write(msg).and_then(|_| socket.flush()) {
+            Ok(_) => Ok(()),
+            Err(tungstenite::Error::WriteBufferFull(_)) => Err(io::ErrorKind::WouldBlock.into()),
+            Err(error) => match to_io_error(error) {
+                error if error.kind() == io::ErrorKind::WouldBlock => Ok(()),
+                error => Err(error),
+            },
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.state {
+            WebSocketState::Open(socket) => socket.flush().map_err(to_io_error),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn to_io_error(error: tungstenite::Error) -> io::Error {
+    match error {
+        tungstenite::Error::Io(error) => error,
+        tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed => {
+            io::ErrorKind::ConnectionAborted.into()
+        }
+        _ => io::ErrorKind::InvalidData.into(),
+    }
+}
+
+/// A client connection using one of the supported transports.
+pub enum Connection<S: Read + Write> {
+    /// Length-prefixed messages over a (possibly encrypted) stream,
+    /// framed the way the client asked for.
+    Prefixed(Stream<S>, FrameBuffer),
+    WebSocket(Box<WebSocketConnection<S>>),
+}
+
+impl<S: Read + Write> Connection<S> {
+    /// The underlying socket. Nothing has been read from it until the
+    /// first message is read.
+    pub fn get_socket_mut(&mut self) -> Option<&mut S> {
+        match self {
+            Connection::Prefixed(stream, _) => Some(stream.get_mut()),
+            Connection::WebSocket(socket) => socket.get_mut().map(Stream::get_mut),
+        }
+    }
+
+    /// Send any data that couldn't be written earlier.
+    pub fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Connection::Prefixed(stream, buf) => {
+                buf.flush(stream)?;
+                stream.flush()
+            }
+            Connection::WebSocket(socket) => socket.flush(),
+        }
+    }
+
+    pub fn read_message<T: DeserializeOwned>(&mut self) -> io::Result<T> {
+        match self {
+            Connection::Prefixed(stream, buf) => buf.read_message(stream),
+            Connection::WebSocket(socket) => socket.read_message(),
+        }
+    }
+
+    /// Whether data is still waiting to be written to the socket.
+    /// WebSockets always flush just in case.
+    pub fn wants_write(&self) -> bool {
+        match self {
+            Connection::Prefixed(stream, buf) => buf.wants_write() || stream.wants_write(),
+            Connection::WebSocket(_) => true,
+        }
+    }
+
+    pub fn write_message<T: Serialize>(&mut self, value: &T) -> io::Result<()> {
+        match self {
+            Connection::Prefixed(stream, buf) => buf.write_message(stream, value),
+            Connection::WebSocket(socket) => socket.write_message(value),
+        }
+    }
+}
+
+impl<S: Read + Write> From<Stream<S>> for Connection<S> {
+    fn from(value: Stream<S>) -> Self {
+        Connection::Prefixed(value, FrameBuffer::default())
+    }
+}
+
+impl<S: Read + Write> From<S> for Connection<S> {
+    fn from(value: S) -> Self {
+        Connection::Prefixed(Stream::Plain(value), FrameBuffer::default())
+    }
+}
+
+impl Source for Connection<NonblockingSocket> {
+    fn register(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        match self {
+            Connection::Prefixed(stream, _) => stream.register(registry, token, interests),
+            Connection::WebSocket(socket) => match socket.get_mut() {
+                Some(stream) => stream.register(registry, token, interests),
+                None => Err(io::ErrorKind::NotConnected.into()),
+            },
+        }
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        match self {
+            Connection::Prefixed(stream, _) => stream.reregister(registry, token, interests),
+            Connection::WebSocket(socket) => match socket.get_mut() {
+                Some(stream) => stream.reregister(registry, token, interests),
+                None => Err(io::ErrorKind::NotConnected.into()),
+            },
+        }
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        match self {
+            Connection::Prefixed(stream, _) => stream.deregister(registry),
+            Connection::WebSocket(socket) => match socket.get_mut() {
+                Some(stream) => stream.deregister(registry),
+                None => Ok(()),
+            },
+        }
+    }
+}
@@ -1,20 +1,225 @@
-use bincode::{deserialize, serialize, ErrorKind};
+//! Length-prefixed framing for messages over streams.
+//!
+//! Each message is sent as its size in bytes (a little-endian `u32`)
+//! followed by the encoded message. Clients can change how messages are
+//! framed by sending handshakes in place of a size before their first
+//! message:
+//!
+//! - [`JSON_HANDSHAKE`] switches both sides to JSON for the rest of the
+//!   connection. JSON is slower and bigger, but makes it easy to write
+//!   clients in languages without a bincode implementation.
+//! - [`COMPRESSION_HANDSHAKE`] has the server compress messages of at
+//!   least [`COMPRESSION_THRESHOLD`] bytes with LZ4, which mostly shrinks
+//!   the game views sent to busy tables. Compressed frames have the
+//!   highest bit of their size set, and readers always accept them.
+//!
+//! Sizes come from whoever's on the other end, so frames bigger than
+//! [`MAX_FRAME_LEN`] are rejected before anything is allocated for them.
+//!
+//! The functions here read and write whole frames with blocking I/O. The
+//! server instead keeps a [`FrameBuffer`] for each connection, which
+//! reuses its buffers from message to message and picks up frames that
+//! only partly arrived or left where they stopped.
+
 use serde::{de::DeserializeOwned, Serialize};
 use std::io::{self, Read, Write};
 
-pub fn read_prefixed<T: DeserializeOwned, R: Read>(reader: &mut R) -> io::Result<T> {
-    // Read the size as a u32
+/// Sent by clients before their first message to use JSON instead of
+/// bincode. No bincode message is big enough for these bytes to be
+/// mistaken for a size.
+pub const JSON_HANDSHAKE: [u8; 4] = *b"JSON";
+
+/// Sent by clients before their first message to have big messages
+/// compressed.
+pub const COMPRESSION_HANDSHAKE: [u8; 4] = *b"LZ4!";
+
+/// Messages at least this many bytes are compressed when compression is
+/// on. Smaller messages don't shrink enough to be worth it.
+pub const COMPRESSION_THRESHOLD: usize = 512;
+
+/// Frames can't be bigger than this, compressed or not. Game views at
+/// full tables are a few kilobytes, so anything near this is a sender
+/// trying to make readers allocate more than they should.
+pub const MAX_FRAME_LEN: usize = 1 << 20;
+
+/// Marks a frame's size as the size of compressed data.
+const COMPRESSED_FLAG: u32 = 1 << 31;
+
+/// Most bytes asked of a reader at a time. Buffers only grow with the
+/// data that actually arrives, so a size promising a big frame doesn't
+/// allocate anything by itself.
+const READ_CHUNK_LEN: usize = 8 * 1024;
+
+/// Buffers that grew past this for an unusually big message are shrunk
+/// back once they're empty, so they don't hold onto the memory forever.
+const MAX_RETAINED_BUFFER_LEN: usize = 64 * 1024;
+
+/// How messages are encoded within frames.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Codec {
+    #[default]
+    Bincode,
+    Json,
+}
+
+impl Codec {
+    fn decode<T: DeserializeOwned>(&self, buf: &[u8]) -> io::Result<T> {
+        match self {
+            Codec::Bincode => bincode::deserialize(buf).map_err(|error| match *error {
+                bincode::ErrorKind::Io(error) => error,
+                _ => io::ErrorKind::InvalidData.into(),
+            }),
+            Codec::Json => {
+                serde_json::from_slice(buf).map_err(|error| match error.io_error_kind() {
+                    Some(kind) => kind.into(),
+                    None => io::ErrorKind::InvalidData.into(),
+                })
+            }
+        }
+    }
+
+    /// Encode a value at the end of the buffer.
+    fn encode_into<T: Serialize>(&self, value: &T, buf: &mut Vec<u8>) -> io::Result<()> {
+        match self {
+            Codec::Bincode => bincode::serialize_into(buf, value).map_err(|error| match *error {
+                bincode::ErrorKind::Io(error) => error,
+                _ => io::ErrorKind::InvalidData.into(),
+            }),
+            Codec::Json => {
+                serde_json::to_writer(buf, value).map_err(|error| match error.io_error_kind() {
+                    Some(kind) => kind.into(),
+                    None => io::ErrorKind::InvalidData.into(),
+                })
+            }
+        }
+    }
+}
+
+/// How a connection's messages are framed, as negotiated by the client.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Framing {
+    pub codec: Codec,
+    /// Whether big messages are compressed.
+    pub compress: bool,
+}
+
+impl Framing {
+    /// Update the framing if the bytes are a handshake, returning whether
+    /// they were.
+    pub(crate) fn negotiate(&mut self, handshake: [u8; 4]) -> bool {
+        match handshake {
+            JSON_HANDSHAKE => self.codec = Codec::Json,
+            COMPRESSION_HANDSHAKE => self.compress = true,
+            _ => return false,
+        }
+        true
+    }
+}
+
+fn read_size<R: Read>(reader: &mut R) -> io::Result<[u8; 4]> {
     let mut len_bytes = [0; 4];
     reader.read_exact(&mut len_bytes)?;
-    let len = u32::from_le_bytes(len_bytes) as usize;
+    Ok(len_bytes)
+}
+
+/// Number of bytes of data that follow a frame's size. Sizes over
+/// [`MAX_FRAME_LEN`] are invalid.
+pub(crate) fn frame_len(len_bytes: [u8; 4]) -> io::Result<usize> {
+    let len = (u32::from_le_bytes(len_bytes) & !COMPRESSED_FLAG) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(io::ErrorKind::InvalidData.into());
+    }
+    Ok(len)
+}
+
+/// Decode the data that followed a frame's size, decompressing it into
+/// the scratch buffer if it's compressed.
+fn decode_data<T: DeserializeOwned>(
+    len_bytes: [u8; 4],
+    data: &[u8],
+    codec: Codec,
+    scratch: &mut Vec<u8>,
+) -> io::Result<T> {
+    if u32::from_le_bytes(len_bytes) & COMPRESSED_FLAG == 0 {
+        return codec.decode(data);
+    }
+    // The decompressed size is allocated up front, so it's checked like
+    // any other frame size first.
+    let (len, compressed) = lz4_flex::block::uncompressed_size(data)
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?;
+    if len > MAX_FRAME_LEN {
+        return Err(io::ErrorKind::InvalidData.into());
+    }
+    scratch.clear();
+    scratch.resize(len, 0);
+    let len = lz4_flex::block::decompress_into(compressed, scratch)
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?;
+    codec.decode(&scratch[..len])
+}
+
+/// Decode the data that followed a frame's size.
+pub(crate) fn decode_frame<T: DeserializeOwned>(
+    len_bytes: [u8; 4],
+    buf: Vec<u8>,
+    codec: Codec,
+) -> io::Result<T> {
+    decode_data(len_bytes, &buf, codec, &mut Vec::new())
+}
+
+/// Encode a message as a whole frame, size included, at the end of the
+/// buffer. Messages that get compressed are encoded in the scratch
+/// buffer first.
+fn encode_frame_into<T: Serialize>(
+    value: &T,
+    framing: Framing,
+    buf: &mut Vec<u8>,
+    scratch: &mut Vec<u8>,
+) -> io::Result<()> {
+    let size_idx = buf.len();
+    let data_idx = size_idx + 4;
+    buf.extend_from_slice(&[0; 4]);
+    framing.codec.encode_into(value, buf)?;
+    let mut size = (buf.len() - data_idx) as u32;
+    if framing.compress && buf.len() - data_idx >= COMPRESSION_THRESHOLD {
+        // Compressed data starts with its decompressed size, the same as
+        // `lz4_flex::compress_prepend_size`.
+        scratch.clear();
+        scratch.extend_from_slice(&buf[data_idx..]);
+        buf.truncate(data_idx);
+        buf.extend_from_slice(&(scratch.len() as u32).to_le_bytes());
+        let compressed_idx = buf.len();
+        buf.resize(
+            compressed_idx + lz4_flex::block::get_maximum_output_size(scratch.len()),
+            0,
+        );
+        let len = lz4_flex::block::compress_into(scratch, &mut buf[compressed_idx..])
+            .map_err(|_| io::Error::from(io::ErrorKind::InvalidData))?;
+        buf.truncate(compressed_idx + len);
+        size = (buf.len() - data_idx) as u32 | COMPRESSED_FLAG;
+    }
+    buf[size_idx..data_idx].copy_from_slice(&size.to_le_bytes());
+    Ok(())
+}
 
+/// Encode a message as a whole frame, size included.
+pub(crate) fn encode_frame<T: Serialize>(value: &T, framing: Framing) -> io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    encode_frame_into(value, framing, &mut buf, &mut Vec::new())?;
+    Ok(buf)
+}
+
+fn read_frame<T: DeserializeOwned, R: Read>(
+    reader: &mut R,
+    len_bytes: [u8; 4],
+    codec: Codec,
+) -> io::Result<T> {
     // Read the remaining data. If we get a would block error,
     // then it's very likely that the sender doesn't follow the
     // prefix protocol. Return an invalid data error to let
     // the readers determine how to handle such senders. It is
     // possible for the would block error to be something that
     // isn't as sketchy, but that should be pretty rare.
-    let mut buf = vec![0; len];
+    let mut buf = vec![0; frame_len(len_bytes)?];
     if let Err(error) = reader.read_exact(&mut buf) {
         let kind = match error.kind() {
             io::ErrorKind::WouldBlock => io::ErrorKind::InvalidData,
@@ -22,31 +227,176 @@ pub fn read_prefixed<T: DeserializeOwned, R: Read>(reader: &mut R) -> io::Result
         };
         return Err(kind.into());
     }
+    decode_frame(len_bytes, buf, codec)
+}
 
-    match deserialize(&buf) {
-        Ok(value) => Ok(value),
-        Err(error) => match *error {
-            ErrorKind::Io(error) => Err(error),
-            _ => Err(io::ErrorKind::InvalidData.into()),
-        },
+/// Read a bincode message.
+pub fn read_prefixed<T: DeserializeOwned, R: Read>(reader: &mut R) -> io::Result<T> {
+    let len_bytes = read_size(reader)?;
+    read_frame(reader, len_bytes, Codec::Bincode)
+}
+
+/// Read a message from a connection that may negotiate its framing. The
+/// framing is updated by any handshakes that come before the message.
+pub fn read_prefixed_with<T: DeserializeOwned, R: Read>(
+    reader: &mut R,
+    framing: &mut Framing,
+) -> io::Result<T> {
+    let mut len_bytes = read_size(reader)?;
+    while framing.negotiate(len_bytes) {
+        len_bytes = read_size(reader)?;
     }
+    read_frame(reader, len_bytes, framing.codec)
 }
 
+/// Write a bincode message.
 pub fn write_prefixed<T: Serialize, W: Write>(writer: &mut W, value: &T) -> io::Result<()> {
-    match serialize(&value) {
-        Ok(serialized) => {
-            // Write the size of the serialized data and the serialized data
-            // all in one chunk to prevent read-side EOF race conditions.
-            let size = serialized.len() as u32;
-            let mut buf = Vec::from(size.to_le_bytes());
-            buf.extend(serialized);
-            writer.write_all(&buf)?;
-            Ok(())
+    write_prefixed_with(writer, value, Framing::default())
+}
+
+pub fn write_prefixed_with<T: Serialize, W: Write>(
+    writer: &mut W,
+    value: &T,
+    framing: Framing,
+) -> io::Result<()> {
+    // Write the size of the serialized data and the serialized data
+    // all in one chunk to prevent read-side EOF race conditions.
+    let buf = encode_frame(value, framing)?;
+    writer.write_all(&buf)?;
+    Ok(())
+}
+
+fn shrink_if_empty(buf: &mut Vec<u8>) {
+    if buf.is_empty() && buf.capacity() > MAX_RETAINED_BUFFER_LEN {
+        buf.shrink_to(MAX_RETAINED_BUFFER_LEN);
+    }
+}
+
+/// Framing for one end of a nonblocking connection that reuses its
+/// buffers from message to message. Frames can arrive over any number of
+/// reads and leave over any number of writes, with whatever's unfinished
+/// kept until the socket is ready again.
+#[derive(Debug, Default)]
+pub struct FrameBuffer {
+    framing: Framing,
+    /// Bytes read that haven't been decoded yet.
+    read_buf: Vec<u8>,
+    /// The frame being written, of which the bytes before `write_idx`
+    /// have been written.
+    write_buf: Vec<u8>,
+    write_idx: usize,
+    /// Decompressed messages, and messages about to be compressed.
+    scratch: Vec<u8>,
+}
+
+impl FrameBuffer {
+    pub fn new(framing: Framing) -> Self {
+        Self {
+            framing,
+            ..Default::default()
+        }
+    }
+
+    /// The framing as negotiated so far.
+    pub fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    /// Whether some of the last frame still has to be written.
+    pub fn wants_write(&self) -> bool {
+        self.write_idx < self.write_buf.len()
+    }
+
+    /// Decode the first whole frame that's been read, applying any
+    /// handshakes that come before it.
+    fn decode_next<T: DeserializeOwned>(&mut self) -> io::Result<Option<T>> {
+        let mut idx = 0;
+        let result = loop {
+            let Some(len_bytes) = self.read_buf.get(idx..idx + 4) else {
+                break Ok(None);
+            };
+            let len_bytes: [u8; 4] = len_bytes.try_into().expect("sizes are 4 bytes");
+            if self.framing.negotiate(len_bytes) {
+                idx += 4;
+                continue;
+            }
+            let data_idx = idx + 4;
+            let end_idx = data_idx + frame_len(len_bytes)?;
+            let Some(data) = self.read_buf.get(data_idx..end_idx) else {
+                break Ok(None);
+            };
+            idx = end_idx;
+            break decode_data(len_bytes, data, self.framing.codec, &mut self.scratch).map(Some);
+        };
+        self.read_buf.drain(..idx);
+        self.scratch.clear();
+        shrink_if_empty(&mut self.read_buf);
+        shrink_if_empty(&mut self.scratch);
+        result
+    }
+
+    /// Read a message. Returns a would block error if the reader runs out
+    /// of data before the message is whole, keeping what was read for the
+    /// next call.
+    pub fn read_message<T: DeserializeOwned, R: Read>(&mut self, reader: &mut R) -> io::Result<T> {
+        loop {
+            if let Some(msg) = self.decode_next()? {
+                return Ok(msg);
+            }
+            let read_idx = self.read_buf.len();
+            self.read_buf.resize(read_idx + READ_CHUNK_LEN, 0);
+            let result = reader.read(&mut self.read_buf[read_idx..]);
+            let num_read = *result.as_ref().unwrap_or(&0);
+            self.read_buf.truncate(read_idx + num_read);
+            match result {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(_) => {}
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    /// Write what's left of the last frame.
+    pub fn flush<W: Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        while self.wants_write() {
+            match writer.write(&self.write_buf[self.write_idx..]) {
+                Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+                Ok(num_written) => self.write_idx += num_written,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+        self.write_buf.clear();
+        self.write_idx = 0;
+        shrink_if_empty(&mut self.write_buf);
+        Ok(())
+    }
+
+    /// Write a message. The rest of the last frame is written first, and
+    /// the message isn't taken if that can't be finished. Once the
+    /// message is taken, whatever the writer can't take of it right away
+    /// is written by later flushes.
+    pub fn write_message<T: Serialize, W: Write>(
+        &mut self,
+        writer: &mut W,
+        value: &T,
+    ) -> io::Result<()> {
+        self.flush(writer)?;
+        encode_frame_into(value, self.framing, &mut self.write_buf, &mut self.scratch)?;
+        self.scratch.clear();
+        shrink_if_empty(&mut self.scratch);
+        match self.flush(writer) {
+            Err(error)
+                if matches!(
+                    error.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::WriteZero
+                ) =>
+            {
+                Ok(())
+            }
+            result => result,
         }
-        Err(error) => match *error {
-            ErrorKind::Io(error) => Err(error),
-            _ => Err(io::ErrorKind::InvalidData.into()),
-        },
     }
 }
 
@@ -56,7 +406,50 @@ mod tests {
 
     use mio::net::{TcpListener, TcpStream};
 
-    use super::{read_prefixed, write_prefixed};
+    use super::{
+        encode_frame, read_prefixed, read_prefixed_with, write_prefixed, write_prefixed_with,
+        Codec, FrameBuffer, Framing, COMPRESSED_FLAG, COMPRESSION_HANDSHAKE, COMPRESSION_THRESHOLD,
+        JSON_HANDSHAKE, MAX_FRAME_LEN,
+    };
+
+    /// A nonblocking socket that only has a few bytes ready at a time.
+    /// Reads and writes of each chunk are followed by a would block
+    /// error.
+    #[derive(Default)]
+    struct Trickle {
+        data: Vec<u8>,
+        chunk_len: usize,
+        is_ready: bool,
+    }
+
+    impl io::Read for Trickle {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.is_ready = !self.is_ready;
+            if !self.is_ready {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            let len = self.chunk_len.min(buf.len()).min(self.data.len());
+            buf[..len].copy_from_slice(&self.data[..len]);
+            self.data.drain(..len);
+            Ok(len)
+        }
+    }
+
+    impl io::Write for Trickle {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.is_ready = !self.is_ready;
+            if !self.is_ready {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            let len = self.chunk_len.min(buf.len());
+            self.data.extend_from_slice(&buf[..len]);
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
 
     fn get_random_open_port() -> u16 {
         let addr = "127.0.0.1:0".parse().unwrap();
@@ -109,4 +502,151 @@ mod tests {
             Err(io::ErrorKind::UnexpectedEof)
         );
     }
+
+    #[test]
+    fn json_handshake_switches_codec() {
+        let (mut client, mut stream) = setup();
+        let value = "Hello, World!".to_string();
+        assert!(stream.write_all(&JSON_HANDSHAKE).is_ok());
+        let json = Framing {
+            codec: Codec::Json,
+            compress: false,
+        };
+        assert!(write_prefixed_with(&mut stream, &value, json).is_ok());
+        assert!(write_prefixed_with(&mut stream, &value, json).is_ok());
+        let mut framing = Framing::default();
+        for _ in 0..2 {
+            assert!(
+                read_prefixed_with::<String, TcpStream>(&mut client, &mut framing)
+                    .is_ok_and(|v| v == value)
+            );
+            assert_eq!(framing, json);
+        }
+    }
+
+    #[test]
+    fn compression_handshake_compresses_big_frames() {
+        let (mut client, mut stream) = setup();
+        let value = "a".repeat(4 * COMPRESSION_THRESHOLD);
+        assert!(stream.write_all(&COMPRESSION_HANDSHAKE).is_ok());
+        assert!(write_prefixed(&mut stream, &value).is_ok());
+        let mut framing = Framing::default();
+        assert!(
+            read_prefixed_with::<String, TcpStream>(&mut client, &mut framing)
+                .is_ok_and(|v| v == value)
+        );
+        assert!(framing.compress);
+
+        // Plain readers understand compressed frames too.
+        let mut buf = Vec::new();
+        assert!(write_prefixed_with(&mut buf, &value, framing).is_ok());
+        assert!(buf.len() < COMPRESSION_THRESHOLD);
+        assert!(read_prefixed::<String, _>(&mut buf.as_slice()).is_ok_and(|v| v == value));
+    }
+
+    #[test]
+    fn reject_oversized_frames() {
+        // A size bigger than any message is rejected without waiting for
+        // the data it promises.
+        let size = (MAX_FRAME_LEN as u32 + 1).to_le_bytes();
+        assert_eq!(
+            read_prefixed::<String, _>(&mut size.as_slice()).map_err(|e| e.kind()),
+            Err(io::ErrorKind::InvalidData)
+        );
+
+        // So is a small compressed frame that claims to decompress to
+        // something huge.
+        let mut buf = Vec::from((8 | COMPRESSED_FLAG).to_le_bytes());
+        buf.extend(u32::MAX.to_le_bytes());
+        buf.extend([0; 4]);
+        assert_eq!(
+            read_prefixed::<String, _>(&mut buf.as_slice()).map_err(|e| e.kind()),
+            Err(io::ErrorKind::InvalidData)
+        );
+    }
+
+    #[test]
+    fn frame_buffer_reads_frames_across_reads() {
+        let value = "a".repeat(4 * COMPRESSION_THRESHOLD);
+        let compressed = Framing {
+            codec: Codec::Json,
+            compress: true,
+        };
+        let mut data = Vec::from(JSON_HANDSHAKE);
+        data.extend(COMPRESSION_HANDSHAKE);
+        data.extend(encode_frame(&value, compressed).unwrap());
+        data.extend(encode_frame(&value, compressed).unwrap());
+        let mut reader = Trickle {
+            data,
+            chunk_len: 7,
+            ..Default::default()
+        };
+
+        // The reader runs dry mid-frame over and over, but nothing's lost.
+        let mut buf = FrameBuffer::default();
+        for _ in 0..2 {
+            let msg = loop {
+                match buf.read_message::<String, _>(&mut reader) {
+                    Ok(msg) => break msg,
+                    Err(error) => assert_eq!(error.kind(), io::ErrorKind::WouldBlock),
+                }
+            };
+            assert_eq!(msg, value);
+            assert_eq!(buf.framing(), compressed);
+        }
+        assert_eq!(
+            buf.read_message::<String, _>(&mut reader)
+                .map_err(|e| e.kind()),
+            Err(io::ErrorKind::WouldBlock)
+        );
+        assert_eq!(
+            buf.read_message::<String, _>(&mut reader)
+                .map_err(|e| e.kind()),
+            Err(io::ErrorKind::UnexpectedEof)
+        );
+    }
+
+    #[test]
+    fn frame_buffer_finishes_frames_across_writes() {
+        let value = "Hello, World!".to_string();
+        let mut writer = Trickle {
+            chunk_len: 5,
+            ..Default::default()
+        };
+
+        // Messages are taken once the frame before them is finished, and
+        // the rest of them waits for the next flush.
+        let mut buf = FrameBuffer::default();
+        let mut num_taken = 0;
+        while num_taken < 2 {
+            match buf.write_message(&mut writer, &value) {
+                Ok(_) => num_taken += 1,
+                Err(error) => {
+                    assert_eq!(error.kind(), io::ErrorKind::WouldBlock);
+                    assert!(buf.wants_write());
+                }
+            }
+        }
+        while buf.wants_write() {
+            let _ = buf.flush(&mut writer);
+        }
+        let mut data = writer.data.as_slice();
+        for _ in 0..2 {
+            assert!(read_prefixed::<String, _>(&mut data).is_ok_and(|v| v == value));
+        }
+        assert!(data.is_empty());
+    }
+
+    #[test]
+    fn frame_buffer_rejects_oversized_frames() {
+        // The size is rejected as soon as it's read, without waiting on
+        // data that'll never fit.
+        let mut buf = FrameBuffer::default();
+        let size = (MAX_FRAME_LEN as u32 + 1).to_le_bytes();
+        assert_eq!(
+            buf.read_message::<String, _>(&mut size.as_slice())
+                .map_err(|e| e.kind()),
+            Err(io::ErrorKind::InvalidData)
+        );
+    }
 }
@@ -1,11 +1,36 @@
 use mio::net::TcpListener;
 
-use std::{thread, time::Duration};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    env, fs,
+    io::{Read, Write},
+    net::TcpStream,
+    process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
 use private_poker::{
-    messages,
-    server::{self, PokerConfig, ServerTimeouts},
-    Client, UserError,
+    auth::Credentials,
+    control::{self, ControlRequest, ControlResponse, CONSOLE_USERNAME},
+    entities::{Action, GameView, UsernameError},
+    messages::{
+        self, AdminOp, ClientError, ClientMessage, RakeLedger, ServerMessage, UserCommand,
+        UserState,
+    },
+    net::client::ConnectOptions,
+    persistence::FileStore,
+    server::{self, LiveSettings, PokerConfig, ServerTimeouts},
+    socket::{Addr, Socket},
+    status::ServerStatus,
+    tls::{self, ClientTls, Stream},
+    utils::{self, Codec, Framing},
+    Client, GameSettings, GameSettingsError, RakeSettings, TournamentSettings, UserError,
+    DEFAULT_TURN_TIMEOUT,
 };
 
 fn get_random_open_port() -> u16 {
@@ -36,6 +61,335 @@ fn already_associated_err() {
     assert!(Client::connect(username, &addr).is_err());
 }
 
+#[test]
+fn invalid_username_err() {
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    thread::spawn(move || server::run(&addr, server::PokerConfig::default()));
+
+    // Clients check names before connecting.
+    let addr = format!("127.0.0.1:{port}");
+    assert!(Client::connect("o g", &addr).is_err());
+
+    // And the server checks them for clients that don't.
+    let stream = loop {
+        if let Ok(stream) = TcpStream::connect(&addr) {
+            break stream;
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    stream
+        .set_read_timeout(Some(Duration::from_secs(1)))
+        .unwrap();
+    let mut stream = Stream::Plain(Socket::Tcp(stream));
+    let msg = ClientMessage {
+        username: "o\u{1b}g".into(),
+        command: UserCommand::Connect,
+    };
+    utils::write_prefixed(&mut stream, &msg).unwrap();
+    assert!(matches!(
+        utils::read_prefixed::<ServerMessage, _>(&mut stream).unwrap(),
+        ServerMessage::ClientError(ClientError::InvalidUsername(UsernameError::InvalidChar(
+            '\u{1b}'
+        )))
+    ));
+}
+
+#[test]
+fn admin_bans_user() {
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    let config = PokerConfig {
+        admins: HashSet::from(["admin".into()]),
+        ..Default::default()
+    };
+    thread::spawn(move || server::run(&addr, config));
+
+    let addr = format!("127.0.0.1:{port}");
+    let (mut admin, _) = Client::connect("admin", &addr).unwrap();
+    let (mut client, _) = Client::connect("ognf", &addr).unwrap();
+    Client::recv_ack(&mut admin.stream).unwrap();
+    Client::recv_view(&mut admin.stream).unwrap();
+
+    // Only admins can moderate.
+    client.admin(AdminOp::Kick("admin".into())).unwrap();
+    assert_eq!(
+        Client::recv_user_error(&mut client.stream).unwrap(),
+        UserError::NotAnAdmin
+    );
+    admin.admin(AdminOp::Kick("nobody".into())).unwrap();
+    assert_eq!(
+        Client::recv_user_error(&mut admin.stream).unwrap(),
+        UserError::UserDoesNotExist
+    );
+
+    // Banning disconnects the user, and they can't connect again from
+    // the same address.
+    admin.admin(AdminOp::Ban("ognf".into())).unwrap();
+    Client::recv_ack(&mut admin.stream).unwrap();
+    Client::recv_ack(&mut admin.stream).unwrap();
+    let view = Client::recv_view(&mut admin.stream).unwrap();
+    assert!(!view.spectators.contains_key("ognf"));
+    assert!(Client::connect("ognf", &addr).is_err());
+    assert!(Client::connect("ognf2", &addr).is_err());
+}
+
+#[test]
+fn admin_checks_rake() {
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    let config = PokerConfig {
+        admins: HashSet::from(["admin".into()]),
+        rake: Some(RakeSettings::new(5, Some(10), true)),
+        ..Default::default()
+    };
+    thread::spawn(move || server::run(&addr, config));
+
+    let addr = format!("127.0.0.1:{port}");
+    let (mut admin, _) = Client::connect("admin", &addr).unwrap();
+    let (mut client, _) = Client::connect("ognf", &addr).unwrap();
+    Client::recv_ack(&mut admin.stream).unwrap();
+    Client::recv_view(&mut admin.stream).unwrap();
+
+    // Tables use the server's rake, and only admins can see how much
+    // has been raked.
+    client.list_tables().unwrap();
+    let tables = Client::recv_tables(&mut client.stream).unwrap();
+    assert_eq!(
+        tables[0].settings.rake,
+        Some(RakeSettings::new(5, Some(10), true))
+    );
+    client.admin(AdminOp::Rake).unwrap();
+    assert_eq!(
+        Client::recv_user_error(&mut client.stream).unwrap(),
+        UserError::NotAnAdmin
+    );
+    admin.admin(AdminOp::Rake).unwrap();
+    match utils::read_prefixed::<ServerMessage, _>(&mut admin.stream).unwrap() {
+        ServerMessage::Rake(ledger) => assert_eq!(ledger, RakeLedger::default()),
+        msg => panic!("expected the rake, got {msg}"),
+    }
+}
+
+#[test]
+fn console_runs_server() {
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    let console_addr: Addr = format!("127.0.0.1:{}", get_random_open_port())
+        .parse()
+        .unwrap();
+    let config = PokerConfig {
+        console_addr: Some(console_addr.to_string()),
+        ..Default::default()
+    };
+    thread::spawn(move || server::run(&addr, config));
+
+    // Nobody can pretend to be the console.
+    let addr = format!("127.0.0.1:{port}");
+    let (mut client, _) = Client::connect("ognf", &addr).unwrap();
+    assert!(Client::connect(CONSOLE_USERNAME, &addr).is_err());
+    let response = control::request(&console_addr, &ControlRequest::Users).unwrap();
+    assert_eq!(
+        response,
+        ControlResponse::Users(BTreeMap::from([(0, BTreeSet::from(["ognf".into()]))]))
+    );
+
+    // Table operations are checked before they reach the table.
+    let pause = ControlRequest::Admin(AdminOp::Pause(0));
+    assert_eq!(
+        control::request(&console_addr, &pause).unwrap(),
+        ControlResponse::Done
+    );
+    Client::recv_ack(&mut client.stream).unwrap();
+    Client::recv_view(&mut client.stream).unwrap();
+    let pause = ControlRequest::Admin(AdminOp::Pause(1));
+    assert!(matches!(
+        control::request(&console_addr, &pause).unwrap(),
+        ControlResponse::Error(_)
+    ));
+    let blinds = AdminOp::SetBlinds {
+        table_id: 0,
+        small_blind: 10,
+        big_blind: 5,
+    };
+    assert_eq!(
+        blinds.to_string(),
+        "set the blinds at table 0 to $0.10/$0.05"
+    );
+    let blinds = ControlRequest::Admin(blinds);
+    assert!(matches!(
+        control::request(&console_addr, &blinds).unwrap(),
+        ControlResponse::Error(_)
+    ));
+
+    // Kicking a user disconnects them.
+    let kick = ControlRequest::Admin(AdminOp::Kick("nobody".into()));
+    assert!(matches!(
+        control::request(&console_addr, &kick).unwrap(),
+        ControlResponse::Error(_)
+    ));
+    let kick = ControlRequest::Admin(AdminOp::Kick("ognf".into()));
+    assert_eq!(
+        control::request(&console_addr, &kick).unwrap(),
+        ControlResponse::Done
+    );
+    assert!(Client::recv_view(&mut client.stream).is_err());
+}
+
+/// Connect as if through a proxy that speaks the PROXY protocol.
+fn connect_proxied(
+    username: &str,
+    addr: &str,
+    client_ip: &str,
+) -> Result<(Client, GameView), anyhow::Error> {
+    let mut stream = TcpStream::connect(addr)?;
+    stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+    // Proxies send the header all at once, while `write!` can split it
+    // up.
+    let header = format!("PROXY TCP4 {client_ip} 127.0.0.1 56324 6969\r\n");
+    stream.write_all(header.as_bytes())?;
+    let mut stream = Stream::Plain(Socket::Tcp(stream));
+    let msg = ClientMessage {
+        username: username.into(),
+        command: UserCommand::Connect,
+    };
+    utils::write_prefixed(&mut stream, &msg)?;
+    let session = Client::recv_session(&mut stream)?;
+    Client::recv_ack(&mut stream)?;
+    let view = Client::recv_view(&mut stream)?;
+    let client = Client {
+        username: username.into(),
+        addr: addr.to_string(),
+        session,
+        stream,
+    };
+    Ok((client, view))
+}
+
+#[test]
+fn admin_bans_proxied_user() {
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    let config = PokerConfig {
+        admins: HashSet::from(["admin".into()]),
+        proxy_protocol: true,
+        ..Default::default()
+    };
+    thread::spawn(move || server::run(&addr, config));
+    thread::sleep(Duration::from_millis(100));
+
+    // Connections have to say who they're proxying.
+    let addr = format!("127.0.0.1:{port}");
+    assert!(Client::connect("ognf", &addr).is_err());
+    let (mut admin, _) = connect_proxied("admin", &addr, "192.0.2.1").unwrap();
+    let (_client, _) = connect_proxied("ognf", &addr, "192.0.2.2").unwrap();
+    Client::recv_ack(&mut admin.stream).unwrap();
+    Client::recv_view(&mut admin.stream).unwrap();
+
+    // Bans apply to the client's address rather than the proxy's.
+    admin.admin(AdminOp::Ban("ognf".into())).unwrap();
+    Client::recv_ack(&mut admin.stream).unwrap();
+    assert!(connect_proxied("ognf2", &addr, "192.0.2.2").is_err());
+    assert!(connect_proxied("ognf2", &addr, "192.0.2.3").is_ok());
+}
+
+#[test]
+fn users_chat_at_table() {
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    thread::spawn(move || server::run(&addr, server::PokerConfig::default()));
+
+    let addr = format!("127.0.0.1:{port}");
+    let (mut client1, _) = Client::connect("ognf", &addr).unwrap();
+    let (mut client2, _) = Client::connect("ognf2", &addr).unwrap();
+    Client::recv_ack(&mut client1.stream).unwrap();
+    Client::recv_view(&mut client1.stream).unwrap();
+
+    // Everyone at the table gets the message, including the sender.
+    client2.chat("nice hand").unwrap();
+    for client in [&mut client1, &mut client2] {
+        let (username, message) = Client::recv_chat(&mut client.stream).unwrap();
+        assert_eq!(username, "ognf2");
+        assert_eq!(message, "nice hand");
+    }
+}
+
+#[test]
+fn agents_play_over_the_network() {
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    let config: PokerConfig = ServerTimeouts {
+        step: Duration::from_secs(1),
+        ..Default::default()
+    }
+    .into();
+    thread::spawn(move || server::run(&addr, config));
+
+    let addr = format!("127.0.0.1:{port}");
+    let (mut client1, view1) = Client::connect("ognf", &addr).unwrap();
+    let (mut client2, view2) = Client::connect("ognf2", &addr).unwrap();
+    let (mut spectator, _) = Client::connect("spectator", &addr).unwrap();
+
+    // Wait for the other user to see the first player join before
+    // starting so both players are dealt in.
+    client1.change_state(messages::UserState::Play).unwrap();
+    while !matches!(
+        utils::read_prefixed::<ServerMessage, _>(&mut client2.stream).unwrap(),
+        ServerMessage::Ack(ClientMessage {
+            command: UserCommand::ChangeState(_),
+            ..
+        })
+    ) {}
+    client2.change_state(messages::UserState::Play).unwrap();
+    client2.start_game().unwrap();
+
+    // Agents always make an invalid raise, which is replaced with a fold.
+    // Only turns that players can see the timer for are counted.
+    let turns = Arc::new(AtomicUsize::new(0));
+    for (mut client, view) in [(client1, view1), (client2, view2)] {
+        let turns = turns.clone();
+        thread::spawn(move || {
+            let mut agent = move |view: &GameView, _: &HashSet<Action>| {
+                if view
+                    .turn_timer
+                    .is_some_and(|timer| timer.timeout == DEFAULT_TURN_TIMEOUT)
+                {
+                    turns.fetch_add(1, Ordering::SeqCst);
+                }
+                Action::Raise(1)
+            };
+            client.play(view, &mut agent)
+        });
+    }
+
+    // The first player to act folds, ending the hand.
+    let hands = loop {
+        spectator.list_hands().unwrap();
+        match utils::read_prefixed::<ServerMessage, _>(&mut spectator.stream).unwrap() {
+            ServerMessage::Hands(hands) if !hands.is_empty() => break hands,
+            _ => thread::sleep(Duration::from_millis(100)),
+        }
+    };
+    assert_eq!(turns.load(Ordering::SeqCst), 1);
+    assert_eq!(hands[0].actions.len(), 1);
+    assert_eq!(hands[0].actions[0].action, Action::Fold);
+
+    // The hand counts toward the leaderboard, and the player that won
+    // the blinds is on top.
+    spectator.leaderboard(10).unwrap();
+    let standings = loop {
+        if let ServerMessage::Leaderboard(standings) =
+            utils::read_prefixed::<ServerMessage, _>(&mut spectator.stream).unwrap()
+        {
+            break standings;
+        }
+    };
+    assert_eq!(standings.len(), 2);
+    assert_eq!(standings[0].username, hands[0].pots[0].winners[0]);
+    assert!(standings.iter().all(|standing| standing.hands == 1));
+    assert_eq!(standings[0].profit + standings[1].profit, 0);
+}
+
 #[test]
 fn one_user_connects_to_lobby() {
     let port = get_random_open_port();
@@ -82,18 +436,895 @@ fn one_user_connects_to_lobby() {
 }
 
 #[test]
-fn one_user_fails_to_connect_to_lobby() {
+fn one_user_lists_hands() {
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    thread::spawn(move || server::run(&addr, server::PokerConfig::default()));
+
+    // No hands have been played yet.
+    let addr = format!("127.0.0.1:{port}");
+    let (mut client, _) = Client::connect("ognf", &addr).unwrap();
+    client.list_hands().unwrap();
+    assert!(Client::recv_hands(&mut client.stream).unwrap().is_empty());
+}
+
+#[test]
+fn one_user_moves_between_tables() {
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    thread::spawn(move || server::run(&addr, server::PokerConfig::default()));
+
+    // Connect to the default table.
+    let addr = format!("127.0.0.1:{port}");
+    let username = "ognf";
+    let (mut client, _) = Client::connect(username, &addr).unwrap();
+    client.list_tables().unwrap();
+    let tables = Client::recv_tables(&mut client.stream).unwrap();
+    assert_eq!(tables.len(), 1);
+    assert_eq!(tables[0].num_users, 1);
+
+    // Create a new table.
+    let settings = GameSettings::default().with_blinds(1, 2);
+    client.create_table(settings.clone()).unwrap();
+    let tables = Client::recv_tables(&mut client.stream).unwrap();
+    assert_eq!(tables.len(), 2);
+    assert_eq!(tables[1].settings, settings);
+
+    // Can't join a table that doesn't exist.
+    client.join_table(2).unwrap();
+    assert_eq!(
+        Client::recv_user_error(&mut client.stream).unwrap(),
+        UserError::TableDoesNotExist
+    );
+
+    // Move to the new table, leaving the old one first.
+    client.join_table(tables[1].id).unwrap();
+    Client::recv_ack(&mut client.stream).unwrap();
+    Client::recv_ack(&mut client.stream).unwrap();
+    let view = Client::recv_view(&mut client.stream).unwrap();
+    assert_eq!(view.big_blind, 2);
+    assert!(view.spectators.contains_key(&client.username));
+
+    // Leave the table without leaving the server.
+    client.leave_table().unwrap();
+    Client::recv_ack(&mut client.stream).unwrap();
+    client.start_game().unwrap();
+    assert_eq!(
+        Client::recv_user_error(&mut client.stream).unwrap(),
+        UserError::NotAtTable
+    );
+}
+
+#[test]
+fn one_user_cant_create_an_oversized_table() {
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    thread::spawn(move || server::run(&addr, server::PokerConfig::default()));
+
+    let addr = format!("127.0.0.1:{port}");
+    let (mut client, _) = Client::connect("ognf", &addr).unwrap();
+
+    // The server would run out of memory making room for this many
+    // players and users if it didn't turn the table down.
+    let settings = GameSettings::new(usize::MAX, usize::MAX, 200);
+    client.create_table(settings).unwrap();
+    assert_eq!(
+        Client::recv_user_error(&mut client.stream).unwrap(),
+        UserError::InvalidGameSettings(GameSettingsError::PlayersOutOfBounds {
+            max_players: usize::MAX
+        })
+    );
+    let settings = GameSettings::default().with_max_spectators(usize::MAX);
+    client.create_table(settings).unwrap();
+    assert_eq!(
+        Client::recv_user_error(&mut client.stream).unwrap(),
+        UserError::InvalidGameSettings(GameSettingsError::TooManySpectators {
+            max_spectators: usize::MAX
+        })
+    );
+
+    // The server's still up and only has its default table.
+    client.list_tables().unwrap();
+    let tables = Client::recv_tables(&mut client.stream).unwrap();
+    assert_eq!(tables.len(), 1);
+}
+
+/// Read messages until one matches, returning it.
+fn recv_until(client: &mut Client, mut f: impl FnMut(&ServerMessage) -> bool) -> ServerMessage {
+    loop {
+        let msg = utils::read_prefixed::<ServerMessage, _>(&mut client.stream).unwrap();
+        if f(&msg) {
+            return msg;
+        }
+    }
+}
+
+#[test]
+fn players_move_to_balance_a_multi_table_tournament() {
     let port = get_random_open_port();
     let addr = format!("127.0.0.1:{port}");
     let config: PokerConfig = ServerTimeouts {
-        action: Duration::ZERO,
-        connect: Duration::ZERO,
-        poll: Duration::from_secs(5),
-        step: Duration::from_secs(5),
+        step: Duration::from_secs(1),
+        ..Default::default()
     }
     .into();
     thread::spawn(move || server::run(&addr, config));
 
+    // Two tables of the same tournament, with four seats each.
+    let addr = format!("127.0.0.1:{port}");
+    let (mut host, _) = Client::connect("host", &addr).unwrap();
+    let settings = GameSettings::new(4, 6, 2000).with_tournament(
+        TournamentSettings::new(Duration::from_secs(600)).with_multi_table("sunday", 0),
+    );
+    for _ in 0..2 {
+        host.create_table(settings.clone()).unwrap();
+        recv_until(&mut host, |msg| matches!(msg, ServerMessage::Tables(_)));
+    }
+
+    // The second table starts with two players, and the first with four.
+    let mut players = Vec::new();
+    for (table_id, num_players) in [(2, 2), (1, 4)] {
+        for i in 0..num_players {
+            let username = format!("{table_id}-{i}");
+            let (mut client, _) = Client::connect(&username, &addr).unwrap();
+            client.join_table(table_id).unwrap();
+            recv_until(&mut client, |msg| {
+                matches!(
+                    msg,
+                    ServerMessage::Ack(ClientMessage {
+                        command: UserCommand::JoinTable(_),
+                        ..
+                    })
+                )
+            });
+            client.change_state(UserState::Play).unwrap();
+            recv_until(&mut client, |msg| {
+                matches!(
+                    msg,
+                    ServerMessage::Ack(ClientMessage {
+                        command: UserCommand::ChangeState(_),
+                        ..
+                    })
+                )
+            });
+            if i + 1 == num_players {
+                client.start_game().unwrap();
+            }
+            let ServerMessage::GameView(view) =
+                recv_until(&mut client, |msg| matches!(msg, ServerMessage::GameView(_)))
+            else {
+                unreachable!("only views are matched");
+            };
+            players.push((client, *view));
+        }
+    }
+    for (mut client, view) in players {
+        thread::spawn(move || {
+            let mut agent = |_: &GameView, options: &HashSet<Action>| {
+                if options.contains(&Action::Check) {
+                    Action::Check
+                } else {
+                    Action::Fold
+                }
+            };
+            client.play(view, &mut agent)
+        });
+    }
+
+    // A player from the first table moves to the second once the first
+    // table's hand is over.
+    let deadline = Instant::now() + Duration::from_secs(60);
+    loop {
+        assert!(Instant::now() < deadline, "players weren't balanced");
+        host.list_tables().unwrap();
+        let ServerMessage::Tables(tables) =
+            recv_until(&mut host, |msg| matches!(msg, ServerMessage::Tables(_)))
+        else {
+            unreachable!("only tables are matched");
+        };
+        if tables[1].num_users == 3 && tables[2].num_users == 3 {
+            break;
+        }
+        thread::sleep(Duration::from_millis(500));
+    }
+}
+
+#[test]
+fn one_user_resumes_after_disconnect() {
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    thread::spawn(move || server::run(&addr, server::PokerConfig::default()));
+
+    // Join the waitlist, then lose the connection.
+    let addr = format!("127.0.0.1:{port}");
+    let username = "ognf";
+    let (mut client, _) = Client::connect(username, &addr).unwrap();
+    client.change_state(messages::UserState::Play).unwrap();
+    Client::recv_ack(&mut client.stream).unwrap();
+    Client::recv_view(&mut client.stream).unwrap();
+    let options = ConnectOptions {
+        session: Some(client.session.clone()),
+        ..Default::default()
+    };
+    drop(client);
+    thread::sleep(Duration::from_millis(100));
+
+    // Other users still see the disconnected user.
+    let (_, view) = Client::connect("ognf2", &addr).unwrap();
+    assert_eq!(view.waitlist.len(), 1);
+    assert_eq!(view.waitlist[0].name, username);
+
+    // Reconnecting picks up where the user left off.
+    let (_, view) = Client::connect_with_options(username, &addr, &options).unwrap();
+    assert_eq!(view.waitlist.len(), 1);
+    assert_eq!(view.waitlist[0].name, username);
+    assert!(!view.spectators.contains_key(username));
+}
+
+#[test]
+fn other_user_cant_take_a_disconnected_seat() {
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    thread::spawn(move || server::run(&addr, server::PokerConfig::default()));
+    thread::sleep(Duration::from_millis(100));
+
+    // Join the waitlist, then lose the connection.
+    let addr = format!("127.0.0.1:{port}");
+    let username = "ognf";
+    let (mut client, _) = Client::connect(username, &addr).unwrap();
+    client.change_state(messages::UserState::Play).unwrap();
+    Client::recv_ack(&mut client.stream).unwrap();
+    Client::recv_view(&mut client.stream).unwrap();
+    let session = client.session.clone();
+    drop(client);
+    thread::sleep(Duration::from_millis(100));
+
+    // Someone else can't take the spot by claiming the username, even
+    // from the same address or with another user's session.
+    let error = Client::connect(username, &addr).err().unwrap();
+    assert_eq!(
+        error.downcast_ref::<ClientError>(),
+        Some(&ClientError::AlreadyAssociated)
+    );
+    let (other, _) = Client::connect("ognf2", &addr).unwrap();
+    let options = ConnectOptions {
+        session: Some(other.session.clone()),
+        ..Default::default()
+    };
+    assert!(Client::connect_with_options(username, &addr, &options).is_err());
+
+    // The user can come back with their session.
+    let options = ConnectOptions {
+        session: Some(session),
+        ..Default::default()
+    };
+    let (_, view) = Client::connect_with_options(username, &addr, &options).unwrap();
+    assert_eq!(view.waitlist.len(), 1);
+    assert_eq!(view.waitlist[0].name, username);
+}
+
+#[test]
+fn quiet_user_misses_heartbeats() {
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    let config: PokerConfig = ServerTimeouts {
+        heartbeat: Duration::from_millis(100),
+        reconnect: Duration::ZERO,
+        ..Default::default()
+    }
+    .into();
+    thread::spawn(move || server::run(&addr, config));
+
+    // The second user never answers pings, so the server drops them while
+    // the first user stays connected by answering.
+    let addr = format!("127.0.0.1:{port}");
+    let (mut client, _) = Client::connect("ognf", &addr).unwrap();
+    let (_quiet_client, _) = Client::connect("ognf2", &addr).unwrap();
+    loop {
+        if let ServerMessage::Ack(ClientMessage {
+            username,
+            command: UserCommand::Leave,
+        }) = client.recv().unwrap()
+        {
+            assert_eq!(username, "ognf2");
+            break;
+        }
+    }
+    client.chat("still here").unwrap();
+    loop {
+        if let ServerMessage::Chat { username, .. } = client.recv().unwrap() {
+            assert_eq!(username, "ognf");
+            break;
+        }
+    }
+}
+
+#[test]
+fn idle_user_spectates_then_disconnects() {
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    let config: PokerConfig = ServerTimeouts {
+        heartbeat: Duration::ZERO,
+        idle: Duration::from_millis(200),
+        poll: Duration::from_millis(50),
+        ..Default::default()
+    }
+    .into();
+    thread::spawn(move || server::run(&addr, config));
+
+    // The user joins the waitlist and then stops sending commands, so they
+    // lose their spot and then their connection.
+    let addr = format!("127.0.0.1:{port}");
+    let (mut client, _) = Client::connect("ognf", &addr).unwrap();
+    client.change_state(UserState::Play).unwrap();
+    loop {
+        if let ServerMessage::Ack(ClientMessage {
+            username,
+            command: UserCommand::ChangeState(UserState::Spectate),
+        }) = client.recv().unwrap()
+        {
+            assert_eq!(username, "ognf");
+            break;
+        }
+    }
+    while client.recv().is_ok() {}
+}
+
+#[test]
+fn one_user_reconnects_after_restart() {
+    let path = env::temp_dir().join(format!("pp_restart_{}.bin", process::id()));
+    let run_server = |path| {
+        let port = get_random_open_port();
+        let addr = format!("127.0.0.1:{port}");
+        let config = PokerConfig {
+            store: Some(Box::new(FileStore::new(path))),
+            ..Default::default()
+        };
+        thread::spawn(move || server::run(&addr, config));
+        format!("127.0.0.1:{port}")
+    };
+
+    // Join the waitlist so there's something to restore.
+    let addr = run_server(path.clone());
+    let username = "ognf";
+    let (mut client, _) = Client::connect(username, &addr).unwrap();
+    client.change_state(messages::UserState::Play).unwrap();
+    Client::recv_ack(&mut client.stream).unwrap();
+    Client::recv_view(&mut client.stream).unwrap();
+    thread::sleep(Duration::from_millis(100));
+
+    // A new server restores the user in the waitlist.
+    let addr = run_server(path.clone());
+    let (client, view) = Client::connect(username, &addr).unwrap();
+    assert_eq!(view.waitlist.len(), 1);
+    assert_eq!(view.waitlist[0].name, client.username);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn users_connect_over_ipv4_and_ipv6() {
+    let port = get_random_open_port();
+    let addrs = [format!("127.0.0.1:{port}"), format!("[::1]:{port}")];
+    thread::spawn(move || server::run_on(&addrs, PokerConfig::default()));
+
+    // Both addresses lead to the same table.
+    let (_client1, _) = Client::connect("ognf", &format!("127.0.0.1:{port}")).unwrap();
+    let (_client2, view) = Client::connect("ognf2", &format!("[::1]:{port}")).unwrap();
+    assert_eq!(view.spectators.len(), 2);
+}
+
+#[test]
+fn one_user_connects_over_tls() {
+    let dir = env::temp_dir();
+    let cert_path = dir.join(format!("pp_cert_{}.pem", process::id()));
+    let key_path = dir.join(format!("pp_key_{}.pem", process::id()));
+    let cert = rcgen::generate_simple_self_signed(["127.0.0.1".to_string()]).unwrap();
+    fs::write(&cert_path, cert.cert.pem()).unwrap();
+    fs::write(&key_path, cert.key_pair.serialize_pem()).unwrap();
+
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    let config = PokerConfig {
+        tls: Some(tls::server_config(&cert_path, &key_path).unwrap()),
+        ..Default::default()
+    };
+    thread::spawn(move || server::run(&addr, config));
+
+    // The server's certificate is trusted, so the client can play.
+    let addr = format!("127.0.0.1:{port}");
+    let options = ConnectOptions {
+        tls: Some(ClientTls::Verify(cert_path.clone())),
+        ..Default::default()
+    };
+    let (mut client, view) = Client::connect_with_options("ognf", &addr, &options).unwrap();
+    assert!(view.spectators.contains_key(&client.username));
+    client.change_state(messages::UserState::Play).unwrap();
+    Client::recv_ack(&mut client.stream).unwrap();
+    let view = Client::recv_view(&mut client.stream).unwrap();
+    assert!(view
+        .waitlist
+        .iter()
+        .any(|user| user.name == client.username));
+
+    // Plain connections can't talk to a TLS server.
+    assert!(Client::connect("ognf2", &addr).is_err());
+    fs::remove_file(cert_path).unwrap();
+    fs::remove_file(key_path).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn one_user_connects_over_unix_socket() {
+    let path = env::temp_dir().join(format!("pp_{}.sock", process::id()));
+    let addr = format!("unix:{}", path.display());
+    let config = PokerConfig::default();
+    let shutdown = config.shutdown.clone();
+    let server = thread::spawn(move || server::run(&addr, config));
+
+    let addr = format!("unix:{}", path.display());
+    let (mut client, view) = Client::connect("ognf", &addr).unwrap();
+    assert!(view.spectators.contains_key(&client.username));
+    client.change_state(messages::UserState::Play).unwrap();
+    Client::recv_ack(&mut client.stream).unwrap();
+    let view = Client::recv_view(&mut client.stream).unwrap();
+    assert!(view
+        .waitlist
+        .iter()
+        .any(|user| user.name == client.username));
+
+    // The socket file is cleaned up once the server stops.
+    shutdown.trigger();
+    while utils::read_prefixed::<ServerMessage, _>(&mut client.stream).is_ok() {}
+    assert!(server.join().unwrap().is_ok());
+    assert!(!path.exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn one_user_connects_over_inherited_socket() {
+    use std::{net::TcpListener, os::fd::IntoRawFd, sync::mpsc};
+
+    struct ReadySupervisor(mpsc::Sender<()>);
+
+    impl server::Supervisor for ReadySupervisor {
+        fn ready(&mut self) {
+            self.0.send(()).unwrap();
+        }
+
+        fn alive(&mut self) {}
+    }
+
+    // The listening socket is set up ahead of time, like systemd does
+    // with socket activation.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let fd = listener.into_raw_fd();
+    let (tx_ready, rx_ready) = mpsc::channel();
+    let config = PokerConfig {
+        supervisor: Some(Box::new(ReadySupervisor(tx_ready))),
+        ..Default::default()
+    };
+    thread::spawn(move || server::run(&format!("fd:{fd}"), config));
+
+    rx_ready.recv_timeout(Duration::from_secs(5)).unwrap();
+    let (client, view) = Client::connect("ognf", &addr).unwrap();
+    assert!(view.spectators.contains_key(&client.username));
+}
+
+#[test]
+fn one_user_connects_over_websocket() {
+    let port = get_random_open_port();
+    let websocket_port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    let config = PokerConfig {
+        websocket_addr: Some(format!("127.0.0.1:{websocket_port}")),
+        ..Default::default()
+    };
+    thread::spawn(move || server::run(&addr, config));
+    thread::sleep(Duration::from_millis(100));
+
+    // Browsers send JSON text frames and get JSON back.
+    let url = format!("ws://127.0.0.1:{websocket_port}");
+    let (mut socket, _) = tungstenite::connect(url).unwrap();
+    let msg = ClientMessage {
+        username: "ognf".into(),
+        command: UserCommand::Connect,
+    };
+    let msg = serde_json::to_string(&msg).unwrap();
+    socket.send(tungstenite::Message::text(msg)).unwrap();
+    let mut recv = || -> ServerMessage {
+        let msg = socket.read().unwrap();
+        serde_json::from_str(msg.to_text().unwrap()).unwrap()
+    };
+    assert!(matches!(recv(), ServerMessage::Session(_)));
+    assert!(matches!(recv(), ServerMessage::Ack(_)));
+    let ServerMessage::GameView(view) = recv() else {
+        panic!("expected a game view");
+    };
+    assert!(view.spectators.contains_key("ognf"));
+
+    // TCP clients share the table with WebSocket clients.
+    let addr = format!("127.0.0.1:{port}");
+    let (_, view) = Client::connect("ognf2", &addr).unwrap();
+    assert_eq!(view.spectators.len(), 2);
+}
+
+#[test]
+fn server_reports_status_over_http() {
+    let port = get_random_open_port();
+    let status_port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    let status_addr = format!("127.0.0.1:{status_port}");
+    let config = PokerConfig {
+        status_addr: Some(status_addr.clone()),
+        ..Default::default()
+    };
+    thread::spawn(move || server::run(&addr, config));
+    thread::sleep(Duration::from_millis(100));
+
+    let addr = format!("127.0.0.1:{port}");
+    let (mut client, _) = Client::connect("ognf", &addr).unwrap();
+    client.change_state(UserState::Play).unwrap();
+    client.recv().unwrap();
+    client.recv().unwrap();
+
+    // The table posts its status after handling the command, so it may
+    // take a moment to show up.
+    let get_status = || -> ServerStatus {
+        let mut stream = TcpStream::connect(&status_addr).unwrap();
+        write!(stream, "GET /status HTTP/1.1\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        serde_json::from_str(body).unwrap()
+    };
+    let mut status = get_status();
+    for _ in 0..10 {
+        if status.tables.get(&0).is_some_and(|t| t.waitlist_size == 1) {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+        status = get_status();
+    }
+    let table = &status.tables[&0];
+    assert_eq!(table.phase, "in lobby");
+    assert_eq!(table.waitlist_size, 1);
+    assert!(table.players.is_empty());
+}
+
+#[test]
+fn one_user_connects_with_json() {
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    thread::spawn(move || server::run(&addr, server::PokerConfig::default()));
+    thread::sleep(Duration::from_millis(100));
+
+    // Clients ask for JSON before their first message and get JSON back.
+    let addr = format!("127.0.0.1:{port}");
+    let mut stream = TcpStream::connect(&addr).unwrap();
+    stream.write_all(&utils::JSON_HANDSHAKE).unwrap();
+    let msg = r#"{"username": "ognf", "command": "Connect"}"#;
+    stream.write_all(&(msg.len() as u32).to_le_bytes()).unwrap();
+    stream.write_all(msg.as_bytes()).unwrap();
+    let mut framing = Framing {
+        codec: Codec::Json,
+        compress: false,
+    };
+    let mut recv =
+        || -> ServerMessage { utils::read_prefixed_with(&mut stream, &mut framing).unwrap() };
+    assert!(matches!(recv(), ServerMessage::Session(_)));
+    assert!(matches!(recv(), ServerMessage::Ack(_)));
+    let ServerMessage::GameView(view) = recv() else {
+        panic!("expected a game view");
+    };
+    assert!(view.spectators.contains_key("ognf"));
+
+    // Bincode clients share the table with JSON clients.
+    let (_, view) = Client::connect("ognf2", &addr).unwrap();
+    assert_eq!(view.spectators.len(), 2);
+}
+
+#[test]
+fn users_connect_with_compression() {
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    thread::spawn(move || server::run(&addr, server::PokerConfig::default()));
+
+    // Compressed and uncompressed clients share the table, and big views
+    // are decoded the same way either way.
+    let addr = format!("127.0.0.1:{port}");
+    let options = ConnectOptions {
+        compress: true,
+        ..Default::default()
+    };
+    let mut clients: Vec<_> = (0..8)
+        .map(|i| {
+            let username = format!("ognf{i}");
+            let options = if i % 2 == 0 {
+                options.clone()
+            } else {
+                ConnectOptions::default()
+            };
+            Client::connect_with_options(&username, &addr, &options)
+                .unwrap()
+                .0
+        })
+        .collect();
+    let client = clients.last_mut().unwrap();
+    client.change_state(messages::UserState::Play).unwrap();
+    Client::recv_ack(&mut client.stream).unwrap();
+    let view = Client::recv_view(&mut client.stream).unwrap();
+    assert_eq!(view.spectators.len(), 7);
+    assert_eq!(view.waitlist.len(), 1);
+    let client = clients.first_mut().unwrap();
+    let view = loop {
+        if let ServerMessage::GameView(view) = client.recv().unwrap() {
+            if view.waitlist.len() == 1 {
+                break view;
+            }
+        }
+    };
+    assert_eq!(view.spectators.len(), 7);
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn users_play_on_the_async_server() {
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    let config: PokerConfig = ServerTimeouts {
+        step: Duration::from_secs(1),
+        ..Default::default()
+    }
+    .into();
+    let shutdown = config.shutdown.clone();
+    let server = thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(server::run_async(&addr, config))
+    });
+
+    // The async server speaks the same protocol, so clients can't tell
+    // the difference.
+    let addr = format!("127.0.0.1:{port}");
+    let (mut client1, _) = Client::connect("ognf", &addr).unwrap();
+    assert!(Client::connect("ognf", &addr).is_err());
+    let (mut client2, view) = Client::connect("ognf2", &addr).unwrap();
+    assert_eq!(view.spectators.len(), 2);
+    client1.chat("gl").unwrap();
+    loop {
+        if let ServerMessage::Chat { username, message } = client2.recv().unwrap() {
+            assert_eq!(username, "ognf");
+            assert_eq!(message, "gl");
+            break;
+        }
+    }
+
+    // It shuts down the same way too.
+    shutdown.trigger();
+    let mut statuses = Vec::new();
+    while let Ok(msg) = utils::read_prefixed::<ServerMessage, _>(&mut client2.stream) {
+        if let ServerMessage::Status(status) = msg {
+            statuses.push(status);
+        }
+    }
+    assert_eq!(statuses.last().unwrap(), "the server shut down");
+    assert!(server.join().unwrap().is_ok());
+}
+
+#[cfg(feature = "quic")]
+#[test]
+fn one_user_connects_over_quic() {
+    use private_poker::net::client::Transport;
+    use std::net::UdpSocket;
+
+    let dir = env::temp_dir();
+    let cert_path = dir.join(format!("pp_quic_cert_{}.pem", process::id()));
+    let key_path = dir.join(format!("pp_quic_key_{}.pem", process::id()));
+    let cert = rcgen::generate_simple_self_signed(["127.0.0.1".to_string()]).unwrap();
+    fs::write(&cert_path, cert.cert.pem()).unwrap();
+    fs::write(&key_path, cert.key_pair.serialize_pem()).unwrap();
+
+    let port = get_random_open_port();
+    let quic_port = UdpSocket::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let addr = format!("127.0.0.1:{port}");
+    let config = PokerConfig {
+        tls: Some(tls::server_config(&cert_path, &key_path).unwrap()),
+        quic_addr: Some(format!("127.0.0.1:{quic_port}")),
+        ..Default::default()
+    };
+    let shutdown = config.shutdown.clone();
+    let server = thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(server::run_async(&addr, config))
+    });
+
+    // QUIC clients play alongside TCP clients.
+    let quic_addr = format!("127.0.0.1:{quic_port}");
+    let options = ConnectOptions {
+        tls: Some(ClientTls::Verify(cert_path.clone())),
+        transport: Transport::Quic,
+        ..Default::default()
+    };
+    let (mut client1, view) = Client::connect_with_options("ognf", &quic_addr, &options).unwrap();
+    assert!(view.spectators.contains_key(&client1.username));
+    let addr = format!("127.0.0.1:{port}");
+    let options = ConnectOptions {
+        tls: Some(ClientTls::Verify(cert_path.clone())),
+        ..Default::default()
+    };
+    let (mut client2, _) = Client::connect_with_options("ognf2", &addr, &options).unwrap();
+    client2.chat("gl").unwrap();
+    loop {
+        if let ServerMessage::Chat { username, message } = client1.recv().unwrap() {
+            assert_eq!(username, "ognf2");
+            assert_eq!(message, "gl");
+            break;
+        }
+    }
+
+    // QUIC clients still hear everything before the server shuts down.
+    shutdown.trigger();
+    let mut statuses = Vec::new();
+    while let Ok(msg) = utils::read_prefixed::<ServerMessage, _>(&mut client1.stream) {
+        if let ServerMessage::Status(status) = msg {
+            statuses.push(status);
+        }
+    }
+    assert_eq!(statuses.last().unwrap(), "the server shut down");
+    assert!(server.join().unwrap().is_ok());
+    fs::remove_file(cert_path).unwrap();
+    fs::remove_file(key_path).unwrap();
+}
+
+#[test]
+fn server_shuts_down_gracefully() {
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    let config: PokerConfig = ServerTimeouts {
+        step: Duration::from_secs(1),
+        ..Default::default()
+    }
+    .into();
+    let shutdown = config.shutdown.clone();
+    let server = thread::spawn(move || server::run(&addr, config));
+
+    let addr = format!("127.0.0.1:{port}");
+    let (mut client, _) = Client::connect("ognf", &addr).unwrap();
+
+    // Users are told the server is going away, and the server exits
+    // once the table is between hands.
+    shutdown.trigger();
+    let mut statuses = Vec::new();
+    while let Ok(msg) = utils::read_prefixed::<ServerMessage, _>(&mut client.stream) {
+        if let ServerMessage::Status(status) = msg {
+            statuses.push(status);
+        }
+    }
+    assert!(statuses.iter().any(|s| s.contains("shutting down")));
+    assert_eq!(statuses.last().unwrap(), "the server shut down");
+    assert!(server.join().unwrap().is_ok());
+
+    // New users can't connect anymore.
+    assert!(Client::connect("ognf2", &addr).is_err());
+}
+
+#[test]
+fn one_user_fails_to_authenticate() {
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    let config = PokerConfig {
+        credentials: Some(Credentials::new()),
+        ..Default::default()
+    };
+    thread::spawn(move || server::run(&addr, config));
+
+    // The first connection registers the password.
+    let addr = format!("127.0.0.1:{port}");
+    let username = "ognf";
+    let (client, _) = Client::connect_with_password(username, "hunter2", &addr).unwrap();
+    drop(client);
+    thread::sleep(Duration::from_millis(100));
+
+    // Connecting with the wrong password or without a password fails.
+    let error = Client::connect_with_password(username, "hunter3", &addr)
+        .err()
+        .unwrap();
+    assert_eq!(
+        error.downcast_ref::<ClientError>(),
+        Some(&ClientError::AuthenticationFailed)
+    );
+    let error = Client::connect(username, &addr).err().unwrap();
+    assert_eq!(
+        error.downcast_ref::<ClientError>(),
+        Some(&ClientError::AuthenticationFailed)
+    );
+
+    // The right password still works.
+    assert!(Client::connect_with_password(username, "hunter2", &addr).is_ok());
+}
+
+#[test]
+fn one_user_finds_spectators_full() {
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    let config: PokerConfig = GameSettings::default().with_max_spectators(1).into();
+    thread::spawn(move || server::run(&addr, config));
+
+    let addr = format!("127.0.0.1:{port}");
+    let (mut client, _) = Client::connect("ognf", &addr).unwrap();
+    let error = Client::connect("onjk", &addr).err().unwrap();
+    assert_eq!(
+        error.downcast_ref::<ClientError>(),
+        Some(&ClientError::SpectatorsFull)
+    );
+
+    // Once the first user takes a seat, there's room to watch again.
+    client.change_state(messages::UserState::Play).unwrap();
+    Client::recv_ack(&mut client.stream).unwrap();
+    assert!(Client::connect("onjk", &addr).is_ok());
+}
+
+#[test]
+fn server_reloads_settings() {
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    let config: PokerConfig = ServerTimeouts {
+        step: Duration::from_secs(1),
+        ..Default::default()
+    }
+    .into();
+    let live_settings = config.live_settings.clone();
+    thread::spawn(move || server::run(&addr, config));
+
+    // Spectators can be capped while the server is running.
+    let addr = format!("127.0.0.1:{port}");
+    let (_client, _) = Client::connect("ognf", &addr).unwrap();
+    live_settings.set(LiveSettings {
+        max_spectators: Some(1),
+        ..Default::default()
+    });
+    thread::sleep(Duration::from_millis(1500));
+    let error = Client::connect("onjk", &addr).err().unwrap();
+    assert_eq!(
+        error.downcast_ref::<ClientError>(),
+        Some(&ClientError::SpectatorsFull)
+    );
+
+    // Going back to the table's own settings lifts the cap.
+    live_settings.set(LiveSettings::default());
+    thread::sleep(Duration::from_millis(1500));
+    assert!(Client::connect("onjk", &addr).is_ok());
+}
+
+#[test]
+fn one_user_fails_to_connect_to_lobby() {
+    let port = get_random_open_port();
+    let addr = format!("127.0.0.1:{port}");
+    let config = PokerConfig {
+        game_settings: GameSettings::default().with_turn_timeout(Duration::ZERO),
+        server_timeouts: ServerTimeouts {
+            connect: Duration::ZERO,
+            heartbeat: Duration::ZERO,
+            idle: Duration::ZERO,
+            poll: Duration::from_secs(5),
+            reconnect: Duration::ZERO,
+            runout: Duration::ZERO,
+            step: Duration::from_secs(5),
+        },
+        ..Default::default()
+    };
+    thread::spawn(move || server::run(&addr, config));
+
     // Try to connect, but we won't be fast enough.
     let addr = format!("127.0.0.1:{port}");
     let username = "ognf";
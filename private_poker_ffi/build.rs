@@ -0,0 +1,14 @@
+use std::{env, path::Path};
+
+fn main() {
+    let crate_dir = env::var("CARGO_MANIFEST_DIR").expect("cargo sets the manifest dir");
+    let config = cbindgen::Config::from_root_or_default(&crate_dir);
+    cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("bindings generate")
+        .write_to_file(Path::new(&crate_dir).join("include/private_poker.h"));
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+}
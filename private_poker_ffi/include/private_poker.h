@@ -0,0 +1,146 @@
+#ifndef PRIVATE_POKER_H
+#define PRIVATE_POKER_H
+
+/* Generated by cbindgen from private_poker_ffi. Don't edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define PP_SUIT_CLUB 0
+
+#define PP_SUIT_SPADE 1
+
+#define PP_SUIT_DIAMOND 2
+
+#define PP_SUIT_HEART 3
+
+#define PP_RANK_HIGH_CARD 0
+
+#define PP_RANK_ONE_PAIR 1
+
+#define PP_RANK_TWO_PAIR 2
+
+#define PP_RANK_THREE_OF_A_KIND 3
+
+#define PP_RANK_STRAIGHT 4
+
+#define PP_RANK_FLUSH 5
+
+#define PP_RANK_FULL_HOUSE 6
+
+#define PP_RANK_FOUR_OF_A_KIND 7
+
+#define PP_RANK_STRAIGHT_FLUSH 8
+
+#define PP_ERROR_NULL_POINTER -1
+
+#define PP_ERROR_INVALID_CARD -2
+
+#define PP_ERROR_DUPLICATE_CARD -3
+
+#define PP_ERROR_BUFFER_TOO_SMALL -4
+
+#define PP_ERROR_NOT_ENOUGH_HANDS -5
+
+#define PP_ERROR_TOO_MANY_BOARD_CARDS -6
+
+#define PP_ERROR_NOT_ENOUGH_CARDS -7
+
+/**
+ * Number of cards in each hand given to [`pp_equity`].
+ */
+#define PP_HAND_SIZE 2
+
+typedef struct PpCard {
+  uint8_t value;
+  uint8_t suit;
+} PpCard;
+
+/**
+ * The strength of a hand.
+ */
+typedef struct PpHand {
+  /**
+   * The hand's category, one of the `PP_RANK_*` ranks.
+   */
+  uint8_t rank;
+  /**
+   * Stronger hands have greater strengths, and hands that would split
+   * a pot have equal strengths. This is the engine's own strength, so
+   * it's packed the way `private_poker::lookup::Strength` describes.
+   */
+  uint32_t strength;
+} PpHand;
+
+/**
+ * How often a hand wins or ties, as fractions of runouts.
+ */
+typedef struct PpEquity {
+  double win;
+  double tie;
+  /**
+   * Expected share of the pot, counting ties as split pots.
+   */
+  double share;
+} PpEquity;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Describe an error code returned by one of the functions. The message
+ * is static and must not be freed.
+ */
+const char *pp_error_message(int32_t code);
+
+/**
+ * Parse cards written one after another, like `"AsKh"` or
+ * `"As Kh, 7c"`, into `cards`. Returns the number of cards parsed.
+ *
+ * # Safety
+ *
+ * `s` must be a null-terminated string, and `cards` must point to room
+ * for `capacity` cards unless `capacity` is zero.
+ */
+int32_t pp_parse_cards(const char *s, struct PpCard *cards, size_t capacity);
+
+/**
+ * Evaluate the best hand that can be made from the cards into `hand`.
+ *
+ * # Safety
+ *
+ * `cards` must point to `num_cards` cards unless `num_cards` is zero,
+ * and `hand` must point to a hand.
+ */
+int32_t pp_evaluate(const struct PpCard *cards, size_t num_cards, struct PpHand *hand);
+
+/**
+ * Find the equity of each hand into `equities`. `hands` has
+ * `PP_HAND_SIZE` cards for each of the `num_hands` hands, one hand
+ * after another. Every runout of the board is dealt if `num_runouts` is
+ * zero, which is exact but slow before the flop, and otherwise that
+ * many random runouts are dealt, shuffled with `seed`.
+ *
+ * # Safety
+ *
+ * `hands` must point to `num_hands * PP_HAND_SIZE` cards, `board` must
+ * point to `board_len` cards unless `board_len` is zero, and `equities`
+ * must point to room for `num_hands` equities.
+ */
+int32_t pp_equity(const struct PpCard *hands,
+                  size_t num_hands,
+                  const struct PpCard *board,
+                  size_t board_len,
+                  uint32_t num_runouts,
+                  uint64_t seed,
+                  struct PpEquity *equities);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* PRIVATE_POKER_H */
@@ -0,0 +1,400 @@
+//! C bindings for hand evaluation and equity calculations.
+//!
+//! Cards are passed as [`PpCard`]s, with values from 1 for aces (14 also
+//! works) through 13 for kings, and one of the `PP_SUIT_*` suits.
+//! Functions return a non-negative number on success or one of the
+//! negative `PP_ERROR_*` codes, which [`pp_error_message`] describes.
+//! Building the crate generates `include/private_poker.h` to include
+//! from C or C++.
+
+use std::{
+    ffi::{c_char, CStr},
+    slice,
+};
+
+use private_poker::{
+    entities::{self, Card, Suit},
+    equity::{self, EquityError},
+    lookup,
+};
+use rand::{rngs::StdRng, SeedableRng};
+
+pub const PP_SUIT_CLUB: u8 = 0;
+pub const PP_SUIT_SPADE: u8 = 1;
+pub const PP_SUIT_DIAMOND: u8 = 2;
+pub const PP_SUIT_HEART: u8 = 3;
+
+pub const PP_RANK_HIGH_CARD: u8 = 0;
+pub const PP_RANK_ONE_PAIR: u8 = 1;
+pub const PP_RANK_TWO_PAIR: u8 = 2;
+pub const PP_RANK_THREE_OF_A_KIND: u8 = 3;
+pub const PP_RANK_STRAIGHT: u8 = 4;
+pub const PP_RANK_FLUSH: u8 = 5;
+pub const PP_RANK_FULL_HOUSE: u8 = 6;
+pub const PP_RANK_FOUR_OF_A_KIND: u8 = 7;
+pub const PP_RANK_STRAIGHT_FLUSH: u8 = 8;
+
+pub const PP_ERROR_NULL_POINTER: i32 = -1;
+pub const PP_ERROR_INVALID_CARD: i32 = -2;
+pub const PP_ERROR_DUPLICATE_CARD: i32 = -3;
+pub const PP_ERROR_BUFFER_TOO_SMALL: i32 = -4;
+pub const PP_ERROR_NOT_ENOUGH_HANDS: i32 = -5;
+pub const PP_ERROR_TOO_MANY_BOARD_CARDS: i32 = -6;
+pub const PP_ERROR_NOT_ENOUGH_CARDS: i32 = -7;
+
+/// Number of cards in each hand given to [`pp_equity`].
+pub const PP_HAND_SIZE: usize = 2;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PpCard {
+    pub value: u8,
+    pub suit: u8,
+}
+
+/// The strength of a hand.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PpHand {
+    /// The hand's category, one of the `PP_RANK_*` ranks.
+    pub rank: u8,
+    /// Stronger hands have greater strengths, and hands that would split
+    /// a pot have equal strengths. This is the engine's own strength, so
+    /// it's packed the way `private_poker::lookup::Strength` describes.
+    pub strength: u32,
+}
+
+/// How often a hand wins or ties, as fractions of runouts.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PpEquity {
+    pub win: f64,
+    pub tie: f64,
+    /// Expected share of the pot, counting ties as split pots.
+    pub share: f64,
+}
+
+impl TryFrom<PpCard> for Card {
+    type Error = i32;
+
+    fn try_from(value: PpCard) -> Result<Self, Self::Error> {
+        let suit = match value.suit {
+            PP_SUIT_CLUB => Suit::Club,
+            PP_SUIT_SPADE => Suit::Spade,
+            PP_SUIT_DIAMOND => Suit::Diamond,
+            PP_SUIT_HEART => Suit::Heart,
+            _ => return Err(PP_ERROR_INVALID_CARD),
+        };
+        match value.value {
+            14 => Ok(Card(1, suit)),
+            1..=13 => Ok(Card(value.value, suit)),
+            _ => Err(PP_ERROR_INVALID_CARD),
+        }
+    }
+}
+
+impl From<Card> for PpCard {
+    fn from(value: Card) -> Self {
+        let suit = match value.1 {
+            Suit::Club => PP_SUIT_CLUB,
+            Suit::Spade => PP_SUIT_SPADE,
+            Suit::Diamond => PP_SUIT_DIAMOND,
+            Suit::Heart | Suit::Wild => PP_SUIT_HEART,
+        };
+        Self {
+            value: value.0,
+            suit,
+        }
+    }
+}
+
+fn error_code(error: EquityError) -> i32 {
+    match error {
+        EquityError::DuplicateCard(_) => PP_ERROR_DUPLICATE_CARD,
+        EquityError::InvalidCard(_) => PP_ERROR_INVALID_CARD,
+        EquityError::NotEnoughCards => PP_ERROR_NOT_ENOUGH_CARDS,
+        EquityError::NotEnoughHands => PP_ERROR_NOT_ENOUGH_HANDS,
+        EquityError::TooManyBoardCards => PP_ERROR_TOO_MANY_BOARD_CARDS,
+    }
+}
+
+/// Read cards from C, checking that they're real and unique.
+///
+/// # Safety
+///
+/// `cards` must point to `num_cards` cards unless `num_cards` is zero.
+unsafe fn read_cards(cards: *const PpCard, num_cards: usize) -> Result<Vec<Card>, i32> {
+    if num_cards == 0 {
+        return Ok(vec![]);
+    } else if cards.is_null() {
+        return Err(PP_ERROR_NULL_POINTER);
+    }
+    let mut read: Vec<Card> = Vec::with_capacity(num_cards);
+    for card in slice::from_raw_parts(cards, num_cards) {
+        let card = Card::try_from(*card)?;
+        if read.contains(&card) {
+            return Err(PP_ERROR_DUPLICATE_CARD);
+        }
+        read.push(card);
+    }
+    Ok(read)
+}
+
+/// Describe an error code returned by one of the functions. The message
+/// is static and must not be freed.
+#[no_mangle]
+pub extern "C" fn pp_error_message(code: i32) -> *const c_char {
+    let message = match code {
+        PP_ERROR_NULL_POINTER => c"a required pointer is null",
+        PP_ERROR_INVALID_CARD => c"a card isn't in a standard deck",
+        PP_ERROR_DUPLICATE_CARD => c"a card is used more than once",
+        PP_ERROR_BUFFER_TOO_SMALL => c"the output buffer is too small",
+        PP_ERROR_NOT_ENOUGH_HANDS => c"need at least 2 hands to compare",
+        PP_ERROR_TOO_MANY_BOARD_CARDS => c"board can't have more than 5 cards",
+        PP_ERROR_NOT_ENOUGH_CARDS => c"not enough cards left in the deck to finish the board",
+        code if code >= 0 => c"success",
+        _ => c"unknown error",
+    };
+    message.as_ptr()
+}
+
+/// Parse cards written one after another, like `"AsKh"` or
+/// `"As Kh, 7c"`, into `cards`. Returns the number of cards parsed.
+///
+/// # Safety
+///
+/// `s` must be a null-terminated string, and `cards` must point to room
+/// for `capacity` cards unless `capacity` is zero.
+#[no_mangle]
+pub unsafe extern "C" fn pp_parse_cards(
+    s: *const c_char,
+    cards: *mut PpCard,
+    capacity: usize,
+) -> i32 {
+    if s.is_null() {
+        return PP_ERROR_NULL_POINTER;
+    }
+    let Ok(s) = CStr::from_ptr(s).to_str() else {
+        return PP_ERROR_INVALID_CARD;
+    };
+    let Ok(parsed) = entities::parse_cards(s) else {
+        return PP_ERROR_INVALID_CARD;
+    };
+    if parsed.len() > capacity {
+        return PP_ERROR_BUFFER_TOO_SMALL;
+    } else if parsed.is_empty() {
+        return 0;
+    } else if cards.is_null() {
+        return PP_ERROR_NULL_POINTER;
+    }
+    let cards = slice::from_raw_parts_mut(cards, parsed.len());
+    for (card, parsed) in cards.iter_mut().zip(parsed.iter()) {
+        *card = PpCard::from(*parsed);
+    }
+    parsed.len() as i32
+}
+
+/// Evaluate the best hand that can be made from the cards into `hand`.
+///
+/// # Safety
+///
+/// `cards` must point to `num_cards` cards unless `num_cards` is zero,
+/// and `hand` must point to a hand.
+#[no_mangle]
+pub unsafe extern "C" fn pp_evaluate(
+    cards: *const PpCard,
+    num_cards: usize,
+    hand: *mut PpHand,
+) -> i32 {
+    if hand.is_null() {
+        return PP_ERROR_NULL_POINTER;
+    }
+    let cards = match read_cards(cards, num_cards) {
+        Ok(cards) => cards,
+        Err(code) => return code,
+    };
+    let strength = lookup::strength(&cards);
+    *hand = PpHand {
+        rank: lookup::rank(strength) as u8,
+        strength,
+    };
+    0
+}
+
+/// Find the equity of each hand into `equities`. `hands` has
+/// `PP_HAND_SIZE` cards for each of the `num_hands` hands, one hand
+/// after another. Every runout of the board is dealt if `num_runouts` is
+/// zero, which is exact but slow before the flop, and otherwise that
+/// many random runouts are dealt, shuffled with `seed`.
+///
+/// # Safety
+///
+/// `hands` must point to `num_hands * PP_HAND_SIZE` cards, `board` must
+/// point to `board_len` cards unless `board_len` is zero, and `equities`
+/// must point to room for `num_hands` equities.
+#[no_mangle]
+pub unsafe extern "C" fn pp_equity(
+    hands: *const PpCard,
+    num_hands: usize,
+    board: *const PpCard,
+    board_len: usize,
+    num_runouts: u32,
+    seed: u64,
+    equities: *mut PpEquity,
+) -> i32 {
+    if equities.is_null() {
+        return PP_ERROR_NULL_POINTER;
+    }
+    let Some(num_cards) = num_hands.checked_mul(PP_HAND_SIZE) else {
+        return PP_ERROR_NOT_ENOUGH_CARDS;
+    };
+    let (hands, board) = match (read_cards(hands, num_cards), read_cards(board, board_len)) {
+        (Ok(hands), Ok(board)) => (hands, board),
+        (Err(code), _) | (_, Err(code)) => return code,
+    };
+    let hands: Vec<_> = hands
+        .chunks(PP_HAND_SIZE)
+        .map(|hand| hand.to_vec())
+        .collect();
+    let result = if num_runouts == 0 {
+        equity::exhaustive(&hands, &board)
+    } else {
+        let mut rng = StdRng::seed_from_u64(seed);
+        equity::monte_carlo(&hands, &board, num_runouts as usize, &mut rng)
+    };
+    match result {
+        Ok(results) => {
+            let equities = slice::from_raw_parts_mut(equities, num_hands);
+            for (equity, result) in equities.iter_mut().zip(results) {
+                *equity = PpEquity {
+                    win: result.win,
+                    tie: result.tie,
+                    share: result.share,
+                };
+            }
+            0
+        }
+        Err(error) => error_code(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::CStr, ptr};
+
+    use private_poker::{entities::Card, evaluate, functional::new_deck};
+    use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+    use super::{
+        pp_equity, pp_error_message, pp_evaluate, pp_parse_cards, PpCard, PpEquity, PpHand,
+        PP_ERROR_BUFFER_TOO_SMALL, PP_ERROR_DUPLICATE_CARD, PP_ERROR_INVALID_CARD,
+        PP_ERROR_NOT_ENOUGH_HANDS, PP_RANK_TWO_PAIR, PP_SUIT_HEART, PP_SUIT_SPADE,
+    };
+
+    fn parse(s: &CStr) -> Vec<PpCard> {
+        let mut cards = [PpCard::default(); 7];
+        let num_cards = unsafe { pp_parse_cards(s.as_ptr(), cards.as_mut_ptr(), cards.len()) };
+        assert!(num_cards >= 0, "{s:?} didn't parse");
+        cards[..num_cards as usize].to_vec()
+    }
+
+    #[test]
+    fn strength_orders_hands() {
+        let evaluate_ffi = |cards: &[Card]| {
+            let cards: Vec<_> = cards.iter().copied().map(PpCard::from).collect();
+            let mut hand = PpHand::default();
+            let code = unsafe { pp_evaluate(cards.as_ptr(), cards.len(), &mut hand) };
+            assert_eq!(code, 0);
+            hand
+        };
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut deck = new_deck();
+        for _ in 0..1000 {
+            deck.shuffle(&mut rng);
+            let (hand1, hand2) = (evaluate(&deck[..7]), evaluate(&deck[7..14]));
+            let (ffi_hand1, ffi_hand2) = (evaluate_ffi(&deck[..7]), evaluate_ffi(&deck[7..14]));
+            assert_eq!(ffi_hand1.rank, hand1.rank() as u8, "{hand1}");
+            assert_eq!(
+                ffi_hand1.strength.cmp(&ffi_hand2.strength),
+                hand1.cmp(&hand2),
+                "{hand1} and {hand2}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_and_evaluate() {
+        assert_eq!(
+            parse(c"AsKh"),
+            [
+                PpCard {
+                    value: 1,
+                    suit: PP_SUIT_SPADE
+                },
+                PpCard {
+                    value: 13,
+                    suit: PP_SUIT_HEART
+                }
+            ]
+        );
+        let mut cards = [PpCard::default(); 1];
+        unsafe {
+            assert_eq!(
+                pp_parse_cards(c"AsKh".as_ptr(), cards.as_mut_ptr(), cards.len()),
+                PP_ERROR_BUFFER_TOO_SMALL
+            );
+            assert_eq!(
+                pp_parse_cards(c"Ax".as_ptr(), cards.as_mut_ptr(), cards.len()),
+                PP_ERROR_INVALID_CARD
+            );
+        }
+
+        let evaluate = |cards: &[PpCard]| {
+            let mut hand = PpHand::default();
+            let code = unsafe { pp_evaluate(cards.as_ptr(), cards.len(), &mut hand) };
+            (code, hand)
+        };
+        let (code, two_pair) = evaluate(&parse(c"AsAhKdKc2s"));
+        assert_eq!(code, 0);
+        assert_eq!(two_pair.rank, PP_RANK_TWO_PAIR);
+        let (_, pair) = evaluate(&parse(c"AsAh3d4c7s"));
+        assert!(two_pair.strength > pair.strength);
+        assert_eq!(evaluate(&parse(c"AsAs")).0, PP_ERROR_DUPLICATE_CARD);
+    }
+
+    #[test]
+    fn equity_of_hands() {
+        let hands = parse(c"AsAhKsKh");
+        let board = parse(c"2c7d9h4s");
+        let mut equities = [PpEquity::default(); 2];
+        let code = unsafe {
+            pp_equity(
+                hands.as_ptr(),
+                2,
+                board.as_ptr(),
+                board.len(),
+                0,
+                0,
+                equities.as_mut_ptr(),
+            )
+        };
+        assert_eq!(code, 0);
+        // Only the two kings left in the deck save the second hand.
+        assert_eq!(equities[1].win, 2.0 / 44.0);
+
+        let code = unsafe {
+            pp_equity(
+                hands.as_ptr(),
+                1,
+                ptr::null(),
+                0,
+                1000,
+                0,
+                equities.as_mut_ptr(),
+            )
+        };
+        assert_eq!(code, PP_ERROR_NOT_ENOUGH_HANDS);
+        let message = unsafe { CStr::from_ptr(pp_error_message(code)) };
+        assert_eq!(message, c"need at least 2 hands to compare");
+    }
+}
@@ -0,0 +1,378 @@
+//! Python bindings for the poker engine.
+//!
+//! The `private_poker_py` module exposes hand evaluation, equity
+//! calculations, and simulations so strategies can be analyzed from
+//! Python with the same rules the server plays by. Networking isn't
+//! exposed.
+//!
+//! Cards are written as strings like `"AsKh"` or as lists like
+//! `["As", "Kh"]`, and amounts of money are in cents like everywhere in
+//! the engine. Agents written in Python are callables that take the game
+//! view and the actions they can take, as the same JSON the server sends
+//! loaded into Python objects, and return an action the same way, like
+//! `"Fold"` or `{"Raise": 400}`.
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashSet},
+    rc::Rc,
+};
+
+use private_poker::{
+    agent::{self, BASELINE_AGENTS},
+    entities::{self, Action, Card, GameView, HandRank, Usd, DEFAULT_BUY_IN},
+    equity, evaluate, sim, GameSettings, DEFAULT_MAX_USERS, MAX_PLAYERS,
+};
+use pyo3::{
+    exceptions::PyValueError,
+    prelude::*,
+    pyclass::CompareOp,
+    types::{PyModule, PyString},
+};
+use rand::{rngs::StdRng, SeedableRng};
+
+/// Actions in the order they're given to Python agents, so seeded
+/// agents choose the same way every run.
+const ACTIONS: [Action; 5] = [
+    Action::Fold,
+    Action::Check,
+    Action::Call(0),
+    Action::Raise(0),
+    Action::AllIn,
+];
+
+fn value_error(error: impl ToString) -> PyErr {
+    PyValueError::new_err(error.to_string())
+}
+
+/// Cards given as a string like `"AsKh"` or as a list like
+/// `["As", "Kh"]`.
+fn extract_cards(cards: &Bound<'_, PyAny>) -> PyResult<Vec<Card>> {
+    if let Ok(cards) = cards.downcast::<PyString>() {
+        return entities::parse_cards(&cards.to_cow()?).map_err(value_error);
+    }
+    cards
+        .try_iter()?
+        .map(|card| card?.extract::<String>()?.parse().map_err(value_error))
+        .collect()
+}
+
+fn extract_hands(hands: &[Bound<'_, PyAny>]) -> PyResult<Vec<Vec<Card>>> {
+    hands.iter().map(extract_cards).collect()
+}
+
+/// The strength of a hand. Stronger hands compare greater, and hands
+/// that would split a pot compare equal.
+#[pyclass(frozen, module = "private_poker_py")]
+struct Hand(HandRank);
+
+#[pymethods]
+impl Hand {
+    /// The hand's category, like `"TwoPair"`.
+    #[getter]
+    fn rank(&self) -> String {
+        format!("{:?}", self.0.rank())
+    }
+
+    /// The hand the way players would say it, like "two pair, kings and
+    /// nines".
+    #[getter]
+    fn description(&self) -> String {
+        self.0.description()
+    }
+
+    fn __richcmp__(&self, other: &Self, op: CompareOp) -> bool {
+        op.matches(self.0.cmp(&other.0))
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Hand({:?})", self.0.description())
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+/// How often a hand wins or ties, as fractions of runouts.
+#[pyclass(frozen, get_all, module = "private_poker_py")]
+struct Equity {
+    win: f64,
+    tie: f64,
+    /// Expected share of the pot, counting ties as split pots.
+    share: f64,
+}
+
+#[pymethods]
+impl Equity {
+    fn __repr__(&self) -> String {
+        format!(
+            "Equity(win={:?}, tie={:?}, share={:?})",
+            self.win, self.tie, self.share
+        )
+    }
+}
+
+impl From<equity::Equity> for Equity {
+    fn from(value: equity::Equity) -> Self {
+        Self {
+            win: value.win,
+            tie: value.tie,
+            share: value.share,
+        }
+    }
+}
+
+/// A player's totals across every hand of a simulation.
+#[derive(Clone)]
+#[pyclass(frozen, get_all, module = "private_poker_py")]
+struct PlayerResults {
+    hands: usize,
+    hands_won: usize,
+    showdowns: usize,
+    vpip_hands: usize,
+    pfr_hands: usize,
+    invalid_actions: usize,
+    profit: i64,
+}
+
+#[pymethods]
+impl PlayerResults {
+    /// Average profit per hand dealt.
+    fn profit_per_hand(&self) -> f64 {
+        if self.hands == 0 {
+            0.0
+        } else {
+            self.profit as f64 / self.hands as f64
+        }
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "PlayerResults(hands={}, hands_won={}, profit={})",
+            self.hands, self.hands_won, self.profit
+        )
+    }
+}
+
+impl From<sim::PlayerResults> for PlayerResults {
+    fn from(value: sim::PlayerResults) -> Self {
+        Self {
+            hands: value.hands,
+            hands_won: value.hands_won,
+            showdowns: value.showdowns,
+            vpip_hands: value.vpip_hands,
+            pfr_hands: value.pfr_hands,
+            invalid_actions: value.invalid_actions,
+            profit: value.profit,
+        }
+    }
+}
+
+/// Results of all the hands played by a simulation.
+#[pyclass(frozen, get_all, module = "private_poker_py")]
+struct SimResults {
+    hands: usize,
+    players: BTreeMap<String, PlayerResults>,
+}
+
+#[pymethods]
+impl SimResults {
+    fn __repr__(&self) -> String {
+        format!(
+            "SimResults(hands={}, players={:?})",
+            self.hands,
+            self.players.keys().collect::<Vec<_>>()
+        )
+    }
+}
+
+impl From<sim::SimResults> for SimResults {
+    fn from(value: sim::SimResults) -> Self {
+        Self {
+            hands: value.hands,
+            players: value
+                .players
+                .into_iter()
+                .map(|(username, results)| (username.to_string(), results.into()))
+                .collect(),
+        }
+    }
+}
+
+/// Ask a Python agent for an action, passing it the view and options as
+/// JSON loaded into Python objects.
+fn act(
+    py: Python<'_>,
+    agent: &Py<PyAny>,
+    view: &GameView,
+    action_options: &HashSet<Action>,
+) -> PyResult<Action> {
+    let json = py.import("json")?;
+    let loads = |value: String| json.call_method1("loads", (value,));
+    let view = loads(serde_json::to_string(view).map_err(value_error)?)?;
+    let action_options: Vec<_> = ACTIONS
+        .iter()
+        .filter_map(|action| action_options.get(action))
+        .collect();
+    let action_options = loads(serde_json::to_string(&action_options).map_err(value_error)?)?;
+    let action = agent.call1(py, (view, action_options))?;
+    let action: String = json.call_method1("dumps", (action,))?.extract()?;
+    serde_json::from_str(&action).map_err(|_| {
+        value_error(format!(
+            "{action} isn't an action like \"Fold\" or {{\"Raise\": 400}}"
+        ))
+    })
+}
+
+/// Plays hands between agents with the real game engine. Stacks are reset
+/// to the buy-in before every hand.
+#[pyclass(unsendable, module = "private_poker_py")]
+struct Simulation {
+    sim: sim::Simulation,
+    /// The first error raised by a Python agent, which stops the
+    /// simulation from being trusted, so it's raised once the run ends.
+    error: Rc<RefCell<Option<PyErr>>>,
+}
+
+#[pymethods]
+impl Simulation {
+    /// Seed the simulation to make the deals reproducible.
+    #[new]
+    #[pyo3(signature = (*, buy_in = DEFAULT_BUY_IN, small_blind = None, big_blind = None, ante = 0, max_players = MAX_PLAYERS, seed = None))]
+    fn new(
+        buy_in: Usd,
+        small_blind: Option<Usd>,
+        big_blind: Option<Usd>,
+        ante: Usd,
+        max_players: usize,
+        seed: Option<u64>,
+    ) -> PyResult<Self> {
+        let mut settings =
+            GameSettings::new(max_players, DEFAULT_MAX_USERS, buy_in).with_ante(ante);
+        if small_blind.is_some() || big_blind.is_some() {
+            let big_blind = big_blind.unwrap_or(settings.min_big_blind);
+            let small_blind = small_blind.unwrap_or(big_blind / 2);
+            settings = settings.with_blinds(small_blind, big_blind);
+        }
+        if let Some(seed) = seed {
+            settings = settings.with_seed(seed);
+        }
+        settings.validate().map_err(value_error)?;
+        Ok(Self {
+            sim: sim::Simulation::new(settings),
+            error: Rc::new(RefCell::new(None)),
+        })
+    }
+
+    /// Add a player controlled by the agent, either the name of a
+    /// baseline agent like `"tight_aggressive"` or a callable taking the
+    /// game view and action options and returning an action.
+    fn add_agent(&mut self, username: &str, agent: &Bound<'_, PyAny>) -> PyResult<()> {
+        if let Ok(name) = agent.extract::<String>() {
+            let Some(mut agent) = agent::from_name(&name) else {
+                return Err(value_error(format!(
+                    "{name} isn't a baseline agent; use one of {}",
+                    BASELINE_AGENTS.join(", ")
+                )));
+            };
+            return self
+                .sim
+                .add_agent(
+                    username,
+                    move |view: &GameView, action_options: &HashSet<Action>| {
+                        agent.act(view, action_options)
+                    },
+                )
+                .map_err(value_error);
+        }
+        if !agent.is_callable() {
+            return Err(value_error("agents are baseline agent names or callables"));
+        }
+        let agent = agent.clone().unbind();
+        let error = Rc::clone(&self.error);
+        self.sim
+            .add_agent(
+                username,
+                move |view: &GameView, action_options: &HashSet<Action>| {
+                    Python::with_gil(|py| {
+                        act(py, &agent, view, action_options).unwrap_or_else(|e| {
+                            error.borrow_mut().get_or_insert(e);
+                            Action::Fold
+                        })
+                    })
+                },
+            )
+            .map_err(value_error)
+    }
+
+    /// Play hands until the given number of hands have been played or
+    /// there aren't enough players to start another hand.
+    fn run(&mut self, num_hands: usize) -> PyResult<SimResults> {
+        let results = self.sim.run(num_hands);
+        match self.error.borrow_mut().take() {
+            Some(error) => Err(error),
+            None => Ok(results.into()),
+        }
+    }
+}
+
+/// Evaluate the best hand that can be made from the cards.
+#[pyfunction(name = "evaluate")]
+fn py_evaluate(cards: &Bound<'_, PyAny>) -> PyResult<Hand> {
+    Ok(Hand(evaluate(&extract_cards(cards)?)))
+}
+
+/// Find the equity of each hand by dealing every possible runout of the
+/// board.
+#[pyfunction]
+#[pyo3(signature = (hands, board = None))]
+fn exhaustive_equity(
+    py: Python<'_>,
+    hands: Vec<Bound<'_, PyAny>>,
+    board: Option<&Bound<'_, PyAny>>,
+) -> PyResult<Vec<Equity>> {
+    let hands = extract_hands(&hands)?;
+    let board = board.map(extract_cards).transpose()?.unwrap_or_default();
+    let equities = py
+        .allow_threads(|| equity::exhaustive(&hands, &board))
+        .map_err(value_error)?;
+    Ok(equities.into_iter().map(Equity::from).collect())
+}
+
+/// Estimate the equity of each hand by dealing random runouts of the
+/// board. Seed it to make the estimate reproducible.
+#[pyfunction]
+#[pyo3(signature = (hands, board = None, num_runouts = 10000, seed = None))]
+fn monte_carlo_equity(
+    py: Python<'_>,
+    hands: Vec<Bound<'_, PyAny>>,
+    board: Option<&Bound<'_, PyAny>>,
+    num_runouts: usize,
+    seed: Option<u64>,
+) -> PyResult<Vec<Equity>> {
+    let hands = extract_hands(&hands)?;
+    let board = board.map(extract_cards).transpose()?.unwrap_or_default();
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let equities = py
+        .allow_threads(|| equity::monte_carlo(&hands, &board, num_runouts, &mut rng))
+        .map_err(value_error)?;
+    Ok(equities.into_iter().map(Equity::from).collect())
+}
+
+#[pymodule]
+fn private_poker_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("BASELINE_AGENTS", BASELINE_AGENTS.to_vec())?;
+    m.add_class::<Equity>()?;
+    m.add_class::<Hand>()?;
+    m.add_class::<PlayerResults>()?;
+    m.add_class::<SimResults>()?;
+    m.add_class::<Simulation>()?;
+    m.add_function(wrap_pyfunction!(py_evaluate, m)?)?;
+    m.add_function(wrap_pyfunction!(exhaustive_equity, m)?)?;
+    m.add_function(wrap_pyfunction!(monte_carlo_equity, m)?)?;
+    Ok(())
+}